@@ -0,0 +1,5 @@
+-- 20261019090000_add_account_details.sql
+ALTER TABLE accounts
+    ADD COLUMN status        TEXT        NOT NULL DEFAULT 'pending',
+    ADD COLUMN roles         TEXT[]      NOT NULL DEFAULT '{player}',
+    ADD COLUMN last_login_at timestamptz NULL;
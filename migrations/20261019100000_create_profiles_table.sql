@@ -0,0 +1,14 @@
+-- 20261019100000_create_profiles_table.sql
+CREATE TABLE profiles
+(
+    user_id      uuid PRIMARY KEY REFERENCES accounts (user_id),
+    display_name TEXT        NOT NULL,
+    avatar       TEXT        NOT NULL,
+    bio          TEXT        NOT NULL DEFAULT '',
+    locale       TEXT        NOT NULL DEFAULT 'en',
+    created_at   timestamptz NOT NULL,
+    updated_at   timestamptz NOT NULL
+);
+
+-- Display names are unique regardless of case, `Bob` and `bob` are the same player
+CREATE UNIQUE INDEX profiles_display_name_lower_key ON profiles (lower(display_name));
@@ -0,0 +1,24 @@
+-- 20261019110000_case_insensitive_account_email.sql
+-- Accounts whose emails only differ by case were created as separate accounts. Keep the oldest one,
+-- the others are suspended and moved out of the way so the unique index below can be built.
+WITH ranked AS (
+    SELECT user_id,
+           row_number() OVER (PARTITION BY lower(email) ORDER BY created_at, user_id) AS position
+    FROM accounts
+)
+UPDATE accounts a
+SET email  = a.user_id || '@duplicate.invalid',
+    status = 'suspended'
+FROM ranked r
+WHERE a.user_id = r.user_id
+  AND r.position > 1;
+
+-- Domains are stored lower-cased from now on, bring the existing rows in line
+UPDATE accounts
+SET email = substring(email FROM '^(.*)@') || '@' || lower(substring(email FROM '@([^@]*)$'))
+WHERE email <> substring(email FROM '^(.*)@') || '@' || lower(substring(email FROM '@([^@]*)$'));
+
+ALTER TABLE accounts
+    DROP CONSTRAINT accounts_email_key;
+
+CREATE UNIQUE INDEX accounts_email_lower_key ON accounts (lower(email));
\ No newline at end of file
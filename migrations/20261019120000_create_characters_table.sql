@@ -0,0 +1,21 @@
+-- 20261019120000_create_characters_table.sql
+CREATE TABLE characters
+(
+    id           uuid PRIMARY KEY,
+    user_id      uuid        NOT NULL REFERENCES accounts (user_id),
+    name         TEXT        NOT NULL,
+    class        TEXT        NOT NULL,
+    level        INT         NOT NULL DEFAULT 1,
+    experience   BIGINT      NOT NULL DEFAULT 0,
+    strength     INT         NOT NULL,
+    dexterity    INT         NOT NULL,
+    intelligence INT         NOT NULL,
+    vitality     INT         NOT NULL,
+    created_at   timestamptz NOT NULL,
+    deleted_at   timestamptz NULL
+);
+
+CREATE INDEX characters_user_id_idx ON characters (user_id);
+
+-- Deleted characters give their name up, restoring one fails if somebody took it in the meantime
+CREATE UNIQUE INDEX characters_name_lower_key ON characters (lower(name)) WHERE deleted_at IS NULL;
\ No newline at end of file
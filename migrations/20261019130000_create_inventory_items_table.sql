@@ -0,0 +1,10 @@
+-- 20261019130000_create_inventory_items_table.sql
+-- One row per occupied slot, item ids point into the item catalog under data/items
+CREATE TABLE inventory_items
+(
+    character_id uuid NOT NULL REFERENCES characters (id),
+    slot         INT  NOT NULL CHECK (slot >= 0),
+    item_id      TEXT NOT NULL,
+    quantity     INT  NOT NULL CHECK (quantity > 0),
+    PRIMARY KEY (character_id, slot)
+);
\ No newline at end of file
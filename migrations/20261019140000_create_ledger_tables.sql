@@ -0,0 +1,62 @@
+-- 20261019140000_create_ledger_tables.sql
+-- Balances are a cache of the entries, the admin reconciliation report checks one against the other
+CREATE TABLE ledger_accounts
+(
+    id           uuid PRIMARY KEY,
+    character_id uuid        NULL REFERENCES characters (id),
+    system_name  TEXT        NULL,
+    currency     TEXT        NOT NULL,
+    balance      BIGINT      NOT NULL DEFAULT 0,
+    created_at   timestamptz NOT NULL DEFAULT now(),
+    CHECK ((character_id IS NULL) <> (system_name IS NULL)),
+    -- system accounts are where money comes from and goes to, only they can go negative
+    CHECK (system_name IS NOT NULL OR balance >= 0),
+    UNIQUE (character_id, currency),
+    UNIQUE (system_name, currency)
+);
+
+-- Idempotency keys are per debiting account, a key replayed with different details is a conflict
+CREATE TABLE ledger_transfers
+(
+    id              uuid PRIMARY KEY,
+    idempotency_key TEXT        NULL,
+    from_account    uuid        NOT NULL REFERENCES ledger_accounts (id),
+    to_account      uuid        NOT NULL REFERENCES ledger_accounts (id),
+    currency        TEXT        NOT NULL,
+    amount          BIGINT      NOT NULL CHECK (amount > 0),
+    reason          TEXT        NOT NULL,
+    created_at      timestamptz NOT NULL DEFAULT now(),
+    UNIQUE (from_account, idempotency_key)
+);
+
+-- Every transfer has exactly one debit and one credit entry that add up to zero
+CREATE TABLE ledger_entries
+(
+    id          BIGSERIAL PRIMARY KEY,
+    transfer_id uuid        NOT NULL REFERENCES ledger_transfers (id),
+    account_id  uuid        NOT NULL REFERENCES ledger_accounts (id),
+    amount      BIGINT      NOT NULL CHECK (amount <> 0),
+    created_at  timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX ledger_entries_account_id_idx ON ledger_entries (account_id);
+CREATE INDEX ledger_entries_transfer_id_idx ON ledger_entries (transfer_id);
+
+CREATE FUNCTION ledger_is_append_only() RETURNS trigger AS
+$$
+BEGIN
+    RAISE EXCEPTION 'ledger rows can not be changed or removed';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER ledger_transfers_append_only
+    BEFORE UPDATE OR DELETE
+    ON ledger_transfers
+    FOR EACH ROW
+EXECUTE FUNCTION ledger_is_append_only();
+
+CREATE TRIGGER ledger_entries_append_only
+    BEFORE UPDATE OR DELETE
+    ON ledger_entries
+    FOR EACH ROW
+EXECUTE FUNCTION ledger_is_append_only();
\ No newline at end of file
@@ -0,0 +1,27 @@
+-- 20261019150000_create_audit_log.sql
+-- Moderation and other sensitive actions, append only like the ledger
+CREATE TABLE audit_log
+(
+    id         uuid PRIMARY KEY,
+    actor_id   uuid        NULL REFERENCES accounts (user_id),
+    action     TEXT        NOT NULL,
+    subject_id uuid        NULL,
+    details    TEXT        NOT NULL DEFAULT '',
+    created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX audit_log_created_at_idx ON audit_log (created_at DESC);
+CREATE INDEX audit_log_subject_id_idx ON audit_log (subject_id);
+
+CREATE FUNCTION audit_log_is_append_only() RETURNS trigger AS
+$$
+BEGIN
+    RAISE EXCEPTION 'audit log rows can not be changed or removed';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only
+    BEFORE UPDATE OR DELETE
+    ON audit_log
+    FOR EACH ROW
+EXECUTE FUNCTION audit_log_is_append_only();
\ No newline at end of file
@@ -0,0 +1,40 @@
+-- 20261019150100_create_chat_tables.sql
+-- Names are copied when the message is sent so history shows what people were called at the time
+CREATE TABLE chat_messages
+(
+    id             uuid PRIMARY KEY,
+    channel        TEXT        NOT NULL,
+    sender_id      uuid        NOT NULL REFERENCES accounts (user_id),
+    sender_name    TEXT        NOT NULL,
+    recipient_id   uuid        NULL REFERENCES accounts (user_id),
+    recipient_name TEXT        NULL,
+    body           TEXT        NOT NULL,
+    filtered       BOOLEAN     NOT NULL DEFAULT false,
+    created_at     timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX chat_messages_channel_idx ON chat_messages (channel, created_at DESC, id DESC);
+CREATE INDEX chat_messages_whisper_idx ON chat_messages (sender_id, recipient_id, created_at DESC)
+    WHERE recipient_id IS NOT NULL;
+
+-- Personal block list, blocked players' messages are not delivered and their whispers refused
+CREATE TABLE chat_blocks
+(
+    user_id         uuid        NOT NULL REFERENCES accounts (user_id),
+    blocked_user_id uuid        NOT NULL REFERENCES accounts (user_id),
+    created_at      timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, blocked_user_id),
+    CHECK (user_id <> blocked_user_id)
+);
+
+CREATE INDEX chat_blocks_blocked_user_id_idx ON chat_blocks (blocked_user_id);
+
+-- Server wide mutes handed out by moderators
+CREATE TABLE chat_mutes
+(
+    user_id     uuid PRIMARY KEY REFERENCES accounts (user_id),
+    muted_until timestamptz NOT NULL,
+    reason      TEXT        NOT NULL,
+    muted_by    uuid        NULL REFERENCES accounts (user_id),
+    created_at  timestamptz NOT NULL DEFAULT now()
+);
\ No newline at end of file
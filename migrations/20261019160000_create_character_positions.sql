@@ -0,0 +1,10 @@
+-- 20261019160000_create_character_positions.sql
+-- Characters without a row haven't entered the world yet and start at the starting zone
+CREATE TABLE character_positions
+(
+    character_id uuid PRIMARY KEY REFERENCES characters (id),
+    zone_id      TEXT        NOT NULL,
+    x            INT         NOT NULL,
+    y            INT         NOT NULL,
+    updated_at   timestamptz NOT NULL DEFAULT now()
+);
\ No newline at end of file
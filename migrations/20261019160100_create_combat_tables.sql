@@ -0,0 +1,24 @@
+-- 20261019160100_create_combat_tables.sql
+-- Finished fights, `setup` and `actions` are JSON and together with the seed replay the whole fight
+CREATE TABLE combat_encounters
+(
+    id          uuid PRIMARY KEY,
+    seed        BIGINT      NOT NULL,
+    setup       TEXT        NOT NULL,
+    actions     TEXT        NOT NULL,
+    winner      INT         NULL,
+    rounds      INT         NOT NULL,
+    started_at  timestamptz NOT NULL,
+    finished_at timestamptz NOT NULL DEFAULT now()
+);
+
+-- Characters that took part, creatures only live in the setup
+CREATE TABLE combat_participants
+(
+    encounter_id uuid NOT NULL REFERENCES combat_encounters (id),
+    character_id uuid NOT NULL REFERENCES characters (id),
+    side         INT  NOT NULL,
+    PRIMARY KEY (encounter_id, character_id)
+);
+
+CREATE INDEX combat_participants_character_id_idx ON combat_participants (character_id);
\ No newline at end of file
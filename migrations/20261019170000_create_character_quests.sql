@@ -0,0 +1,17 @@
+-- 20261019170000_create_character_quests.sql
+-- Quest progress per character. `progress` has one counter per objective of the quest definition,
+-- collect objectives stay at 0 since they are counted from the inventory. Repeatable quests go back
+-- to active on accepting them again, `completions` keeps counting.
+CREATE TABLE character_quests
+(
+    character_id uuid        NOT NULL REFERENCES characters (id),
+    quest_id     TEXT        NOT NULL,
+    status       TEXT        NOT NULL,
+    progress     INT[]       NOT NULL,
+    completions  INT         NOT NULL DEFAULT 0,
+    accepted_at  timestamptz NOT NULL DEFAULT now(),
+    completed_at timestamptz NULL,
+    PRIMARY KEY (character_id, quest_id)
+);
+
+CREATE INDEX character_quests_active_idx ON character_quests (character_id) WHERE status = 'active';
\ No newline at end of file
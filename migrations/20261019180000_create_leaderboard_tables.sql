@@ -0,0 +1,33 @@
+-- 20261019180000_create_leaderboard_tables.sql
+-- PvP ratings, matches keep them up to date. Only characters that played a match are ranked.
+CREATE TABLE character_ratings
+(
+    character_id uuid PRIMARY KEY REFERENCES characters (id),
+    rating       INT         NOT NULL DEFAULT 1500,
+    games        INT         NOT NULL DEFAULT 0,
+    updated_at   timestamptz NOT NULL DEFAULT now()
+);
+
+-- The running season has no end yet
+CREATE TABLE leaderboard_seasons
+(
+    number     INT PRIMARY KEY,
+    started_at timestamptz NOT NULL DEFAULT now(),
+    ended_at   timestamptz NULL
+);
+
+CREATE UNIQUE INDEX leaderboard_seasons_running_idx ON leaderboard_seasons ((ended_at IS NULL)) WHERE ended_at IS NULL;
+
+INSERT INTO leaderboard_seasons (number) VALUES (1);
+
+-- The top of every board as it stood when the season ended, names are kept in case characters go away
+CREATE TABLE leaderboard_snapshots
+(
+    season         INT    NOT NULL REFERENCES leaderboard_seasons (number),
+    board          TEXT   NOT NULL,
+    rank           INT    NOT NULL,
+    character_id   uuid   NOT NULL,
+    character_name TEXT   NOT NULL,
+    score          BIGINT NOT NULL,
+    PRIMARY KEY (season, board, rank)
+);
\ No newline at end of file
@@ -0,0 +1,64 @@
+-- 20261019190000_create_guild_tables.sql
+-- Guilds belong to accounts like chat does, whichever character is played the guild stays the same
+CREATE TABLE guilds
+(
+    id         uuid PRIMARY KEY,
+    name       TEXT        NOT NULL,
+    tag        TEXT        NOT NULL,
+    motd       TEXT        NOT NULL DEFAULT '',
+    created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX guilds_name_lower_key ON guilds (lower(name));
+CREATE UNIQUE INDEX guilds_tag_key ON guilds (tag);
+
+-- Rank 0 leads the guild, higher numbers sit further down
+CREATE TABLE guild_ranks
+(
+    guild_id    uuid   NOT NULL REFERENCES guilds (id) ON DELETE CASCADE,
+    rank        INT    NOT NULL CHECK (rank >= 0),
+    name        TEXT   NOT NULL,
+    permissions TEXT[] NOT NULL DEFAULT '{}',
+    PRIMARY KEY (guild_id, rank)
+);
+
+-- An account is in one guild at most
+CREATE TABLE guild_members
+(
+    user_id   uuid PRIMARY KEY REFERENCES accounts (user_id),
+    guild_id  uuid        NOT NULL,
+    rank      INT         NOT NULL,
+    joined_at timestamptz NOT NULL DEFAULT now(),
+    FOREIGN KEY (guild_id, rank) REFERENCES guild_ranks (guild_id, rank) ON DELETE CASCADE
+);
+
+CREATE INDEX guild_members_guild_id_idx ON guild_members (guild_id);
+
+-- Invitations come from the guild, applications from the player, either way the other side decides
+CREATE TABLE guild_invitations
+(
+    guild_id   uuid        NOT NULL REFERENCES guilds (id) ON DELETE CASCADE,
+    user_id    uuid        NOT NULL REFERENCES accounts (user_id),
+    invited_by uuid        NOT NULL REFERENCES accounts (user_id),
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE TABLE guild_applications
+(
+    guild_id   uuid        NOT NULL REFERENCES guilds (id) ON DELETE CASCADE,
+    user_id    uuid        NOT NULL REFERENCES accounts (user_id),
+    message    TEXT        NOT NULL DEFAULT '',
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (guild_id, user_id)
+);
+
+-- Same shape as inventory_items, the inventory service fills both
+CREATE TABLE guild_bank_items
+(
+    guild_id uuid NOT NULL REFERENCES guilds (id) ON DELETE CASCADE,
+    slot     INT  NOT NULL CHECK (slot >= 0),
+    item_id  TEXT NOT NULL,
+    quantity INT  NOT NULL CHECK (quantity > 0),
+    PRIMARY KEY (guild_id, slot)
+);
\ No newline at end of file
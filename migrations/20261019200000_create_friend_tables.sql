@@ -0,0 +1,22 @@
+-- 20261019200000_create_friend_tables.sql
+-- Friends belong to accounts, a request waits until the addressee accepts or declines it
+CREATE TABLE friend_requests
+(
+    requester_id uuid        NOT NULL REFERENCES accounts (user_id),
+    addressee_id uuid        NOT NULL REFERENCES accounts (user_id),
+    created_at   timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (requester_id, addressee_id),
+    CHECK (requester_id <> addressee_id)
+);
+
+CREATE INDEX friend_requests_addressee_id_idx ON friend_requests (addressee_id);
+
+-- Stored once per direction so a friend list is a single lookup by user_id
+CREATE TABLE friendships
+(
+    user_id    uuid        NOT NULL REFERENCES accounts (user_id),
+    friend_id  uuid        NOT NULL REFERENCES accounts (user_id),
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, friend_id),
+    CHECK (user_id <> friend_id)
+);
\ No newline at end of file
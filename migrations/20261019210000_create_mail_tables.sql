@@ -0,0 +1,36 @@
+-- 20261019210000_create_mail_tables.sql
+-- In-game mail between characters, not to be confused with the outbound email_queue.
+-- Attached money sits in the escrow ledger account and attached items in mail_items until the
+-- recipient claims them, or the mail expires and goes back to the sender.
+CREATE TABLE mail
+(
+    id           uuid PRIMARY KEY,
+    recipient_id uuid        NOT NULL REFERENCES characters (id),
+    -- NULL for system mail
+    sender_id    uuid REFERENCES characters (id),
+    sender_name  TEXT        NOT NULL,
+    kind         TEXT        NOT NULL,
+    subject      TEXT        NOT NULL,
+    body         TEXT        NOT NULL,
+    currency     TEXT,
+    amount       BIGINT      NOT NULL DEFAULT 0 CHECK (amount >= 0),
+    -- gold the recipient pays the sender to take the attachments
+    cod_amount   BIGINT      NOT NULL DEFAULT 0 CHECK (cod_amount >= 0),
+    created_at   timestamptz NOT NULL DEFAULT now(),
+    expires_at   timestamptz NOT NULL,
+    read_at      timestamptz,
+    claimed_at   timestamptz,
+    CHECK (amount = 0 OR currency IS NOT NULL)
+);
+
+CREATE INDEX mail_recipient_id_idx ON mail (recipient_id, created_at DESC);
+CREATE INDEX mail_expires_at_idx ON mail (expires_at);
+
+CREATE TABLE mail_items
+(
+    mail_id  uuid NOT NULL REFERENCES mail (id) ON DELETE CASCADE,
+    position INT  NOT NULL,
+    item_id  TEXT NOT NULL,
+    quantity INT  NOT NULL CHECK (quantity > 0),
+    PRIMARY KEY (mail_id, position)
+);
\ No newline at end of file
@@ -0,0 +1,30 @@
+-- 20261019220000_create_auction_listings.sql
+-- The listed stack lives in the listing row until it is sold, cancelled or expires, then it
+-- goes out by mail. The highest bid sits in the escrow ledger account in the meantime.
+CREATE TABLE auction_listings
+(
+    id             uuid PRIMARY KEY,
+    seller_id      uuid        NOT NULL REFERENCES characters (id),
+    item_id        TEXT        NOT NULL,
+    quantity       INT         NOT NULL CHECK (quantity > 0),
+    kind           TEXT        NOT NULL,
+    -- NULL for fixed price listings
+    start_price    BIGINT CHECK (start_price > 0),
+    -- NULL for auctions that can only be won by bidding
+    buyout_price   BIGINT CHECK (buyout_price > 0),
+    listing_fee    BIGINT      NOT NULL CHECK (listing_fee >= 0),
+    high_bid       BIGINT,
+    high_bidder_id uuid REFERENCES characters (id),
+    status         TEXT        NOT NULL DEFAULT 'active',
+    buyer_id       uuid REFERENCES characters (id),
+    sold_price     BIGINT,
+    created_at     timestamptz NOT NULL DEFAULT now(),
+    expires_at     timestamptz NOT NULL,
+    closed_at      timestamptz,
+    CHECK (start_price IS NOT NULL OR buyout_price IS NOT NULL),
+    CHECK ((high_bid IS NULL) = (high_bidder_id IS NULL))
+);
+
+CREATE INDEX auction_listings_active_item_idx ON auction_listings (item_id) WHERE status = 'active';
+CREATE INDEX auction_listings_active_expires_at_idx ON auction_listings (expires_at) WHERE status = 'active';
+CREATE INDEX auction_listings_seller_id_idx ON auction_listings (seller_id, created_at DESC);
\ No newline at end of file
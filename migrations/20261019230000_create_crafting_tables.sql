@@ -0,0 +1,23 @@
+-- 20261019230000_create_crafting_tables.sql
+-- Inputs are taken from the inventory when a job is queued, outputs are handed out once
+-- finishes_at has passed. Jobs of one character run one after the other.
+CREATE TABLE crafting_jobs
+(
+    id           uuid PRIMARY KEY,
+    character_id uuid        NOT NULL REFERENCES characters (id),
+    recipe_id    TEXT        NOT NULL,
+    started_at   timestamptz NOT NULL,
+    finishes_at  timestamptz NOT NULL,
+    CHECK (finishes_at >= started_at)
+);
+
+CREATE INDEX crafting_jobs_character_id_idx ON crafting_jobs (character_id, finishes_at);
+CREATE INDEX crafting_jobs_finishes_at_idx ON crafting_jobs (finishes_at);
+
+CREATE TABLE character_crafting_skills
+(
+    character_id uuid   NOT NULL REFERENCES characters (id),
+    skill        TEXT   NOT NULL,
+    experience   BIGINT NOT NULL DEFAULT 0 CHECK (experience >= 0),
+    PRIMARY KEY (character_id, skill)
+);
\ No newline at end of file
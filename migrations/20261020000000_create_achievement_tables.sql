@@ -0,0 +1,27 @@
+-- 20261020000000_create_achievement_tables.sql
+-- Counters are kept per statistic as a total, with an empty subject, and per creature, quest or
+-- recipe where the statistic has one.
+CREATE TABLE character_statistics
+(
+    character_id uuid   NOT NULL REFERENCES characters (id),
+    statistic    TEXT   NOT NULL,
+    subject      TEXT   NOT NULL DEFAULT '',
+    value        BIGINT NOT NULL DEFAULT 0 CHECK (value >= 0),
+    PRIMARY KEY (character_id, statistic, subject)
+);
+
+CREATE TABLE character_achievements
+(
+    character_id   uuid        NOT NULL REFERENCES characters (id),
+    achievement_id TEXT        NOT NULL,
+    earned_at      timestamptz NOT NULL,
+    PRIMARY KEY (character_id, achievement_id)
+);
+
+-- The title a character shows, from one of its achievements
+CREATE TABLE character_titles
+(
+    character_id   uuid PRIMARY KEY REFERENCES characters (id),
+    achievement_id TEXT NOT NULL,
+    FOREIGN KEY (character_id, achievement_id) REFERENCES character_achievements (character_id, achievement_id)
+);
\ No newline at end of file
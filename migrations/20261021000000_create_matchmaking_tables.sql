@@ -0,0 +1,26 @@
+-- 20261021000000_create_matchmaking_tables.sql
+-- Rated matches, the id is the id of the fight. Settling a match is what sets finished_at, so
+-- ratings only ever change once per match.
+CREATE TABLE matchmaking_matches
+(
+    id          uuid PRIMARY KEY,
+    queue       TEXT        NOT NULL,
+    started_at  timestamptz NOT NULL,
+    finished_at timestamptz NULL,
+    -- the side that won, unset on a draw or while the fight is on
+    winner      INT         NULL,
+    -- the fight was lost without a record, by a restart or a failed store, and nobody was rated
+    voided      BOOLEAN     NOT NULL DEFAULT false
+);
+
+CREATE INDEX matchmaking_matches_unfinished_idx ON matchmaking_matches (started_at) WHERE finished_at IS NULL;
+
+CREATE TABLE matchmaking_match_players
+(
+    match_id      uuid NOT NULL REFERENCES matchmaking_matches (id),
+    character_id  uuid NOT NULL REFERENCES characters (id),
+    side          INT  NOT NULL,
+    rating_before INT  NOT NULL,
+    rating_after  INT  NULL,
+    PRIMARY KEY (match_id, character_id)
+);
\ No newline at end of file
@@ -0,0 +1,10 @@
+-- 20261022000000_create_loot_pity.sql
+-- Rolls of a loot table in a row without anything of its pity rarity, per character. Rows are
+-- only kept for tables that are counting, a lucky roll deletes the row.
+CREATE TABLE character_loot_pity
+(
+    character_id uuid    NOT NULL REFERENCES characters (id),
+    table_id     TEXT    NOT NULL,
+    misses       INTEGER NOT NULL CHECK (misses > 0),
+    PRIMARY KEY (character_id, table_id)
+);
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::achievements::{AchievementDefinition, Statistic};
+use crate::combat::CombatRules;
+use crate::crafting::RecipeBook;
+use crate::game_data::{data_directory, load_data_files, GameDataError};
+use crate::items::ItemCatalog;
+use crate::quests::QuestBook;
+
+#[derive(serde::Deserialize)]
+struct AchievementFile {
+    #[serde(default)]
+    achievements: Vec<AchievementDefinition>,
+}
+
+/// Every achievement from `data/achievements`. Creatures, quests, recipes and reward items are
+/// checked by `check_references` once the rest of the data is loaded.
+#[derive(Debug, Default)]
+pub struct AchievementBook {
+    achievements: HashMap<String, AchievementDefinition>,
+}
+
+impl AchievementBook {
+    pub fn new(definitions: Vec<AchievementDefinition>) -> Result<Self, GameDataError> {
+        let mut book = AchievementBook::default();
+        for definition in definitions {
+            definition.validate().map_err(GameDataError::Invalid)?;
+            if book.achievements.contains_key(&definition.id) {
+                return Err(GameDataError::Invalid(format!("Achievement {} is defined twice", definition.id)));
+            }
+            book.achievements.insert(definition.id.clone(), definition);
+        }
+        Ok(book)
+    }
+
+    pub fn load(directory: &Path) -> Result<Self, GameDataError> {
+        let mut definitions = Vec::new();
+        for (path, file) in load_data_files::<AchievementFile>(directory)? {
+            for definition in file.achievements {
+                definition.validate()
+                    .map_err(|e| GameDataError::Invalid(format!("{}: {}", path.display(), e)))?;
+                definitions.push(definition);
+            }
+        }
+        Self::new(definitions)
+    }
+
+    /// Subjects and reward items that don't exist, empty when all is well
+    pub fn check_references(
+        &self,
+        items: &ItemCatalog,
+        rules: &CombatRules,
+        quests: &QuestBook,
+        recipes: &RecipeBook,
+    ) -> Vec<String> {
+        let mut problems = Vec::new();
+        for achievement in self.sorted() {
+            for criterion in &achievement.criteria {
+                let Some(subject) = &criterion.subject else {
+                    continue;
+                };
+                let exists = match criterion.statistic {
+                    Statistic::CreaturesKilled => rules.creature(subject).is_some(),
+                    Statistic::QuestsCompleted => quests.get(subject).is_some(),
+                    Statistic::ItemsCrafted => recipes.get(subject).is_some(),
+                    Statistic::Deaths | Statistic::GoldEarned => false,
+                };
+                if !exists {
+                    problems.push(format!(
+                        "Achievement {} counts {} of unknown {}", achievement.id, criterion.statistic, subject
+                    ));
+                }
+            }
+            for reward in achievement.rewards.items.iter().filter(|r| !items.contains(&r.item)) {
+                problems.push(format!("Achievement {} rewards unknown item {}", achievement.id, reward.item));
+            }
+        }
+        problems
+    }
+
+    pub fn get(&self, id: &str) -> Option<&AchievementDefinition> {
+        self.achievements.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.achievements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.achievements.is_empty()
+    }
+
+    /// By name, the way the player page lists them
+    pub fn sorted(&self) -> Vec<&AchievementDefinition> {
+        let mut achievements: Vec<&AchievementDefinition> = self.achievements.values().collect();
+        achievements.sort_by_key(|a| a.name.as_str());
+        achievements
+    }
+}
+
+pub fn get_achievement_book() -> Result<AchievementBook, GameDataError> {
+    AchievementBook::load(&data_directory().join("achievements"))
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use uuid::Uuid;
+    use crate::achievements::{get_achievement_book, AchievementBook};
+    use crate::combat::get_combat_rules;
+    use crate::crafting::get_recipe_book;
+    use crate::items::get_item_catalog;
+    use crate::quests::get_quest_book;
+
+    fn load(content: &str) -> Result<AchievementBook, crate::game_data::GameDataError> {
+        let directory = std::env::temp_dir().join(format!("yaug-achievements-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("achievements.toml"), content).unwrap();
+        let book = AchievementBook::load(&directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+        book
+    }
+
+    fn achievement(id: &str, statistic: &str, subject: &str, reward: &str) -> String {
+        format!(
+            "[[achievements]]\nid = \"{}\"\nname = \"{}\"\n\
+             criteria = [{{ statistic = \"{}\", subject = \"{}\", count = 3 }}]\n\
+             rewards = {{ items = [{{ item = \"{}\" }}] }}\n",
+            id, id, statistic, subject, reward
+        )
+    }
+
+    fn problems(book: &AchievementBook) -> Vec<String> {
+        book.check_references(
+            &get_item_catalog().unwrap(),
+            &get_combat_rules().unwrap(),
+            &get_quest_book().unwrap(),
+            &get_recipe_book().unwrap(),
+        )
+    }
+
+    #[test]
+    fn shipped_achievements_reference_existing_data() {
+        let book = assert_ok!(get_achievement_book());
+        assert!(!book.is_empty());
+        assert_eq!(Vec::<String>::new(), problems(&book));
+    }
+
+    #[test]
+    fn achievements_are_read_with_defaults() {
+        let book = assert_ok!(load(&achievement("hunter", "creatures_killed", "wolf", "bread")));
+        let hunter = book.get("hunter").unwrap();
+        assert_eq!(None, hunter.title);
+        assert_eq!(0, hunter.rewards.gold);
+        assert_eq!(1, hunter.rewards.items[0].quantity);
+        assert_err!(load(&achievement("hunter", "dragons_tamed", "wolf", "bread")));
+    }
+
+    #[test]
+    fn achievements_are_defined_once() {
+        let hunter = achievement("hunter", "creatures_killed", "wolf", "bread");
+        assert_err!(load(&[hunter.clone(), hunter].join("\n")));
+    }
+
+    #[test]
+    fn unknown_references_are_reported() {
+        let book = assert_ok!(load(&[
+            achievement("dragon_slayer", "creatures_killed", "dragon", "bread"),
+            achievement("hero", "quests_completed", "save_the_world", "bread"),
+            achievement("baker", "items_crafted", "cake", "bread"),
+            achievement("hunter", "creatures_killed", "wolf", "golden_crown"),
+        ].join("\n")));
+
+        assert_eq!(4, problems(&book).len(), "{:?}", problems(&book));
+    }
+}
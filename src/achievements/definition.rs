@@ -0,0 +1,181 @@
+use crate::achievements::{Statistic, Statistics};
+use crate::game_data::is_valid_id;
+use crate::quests::ItemReward;
+
+/// A statistic that has to reach `count`, for one creature, quest or recipe when `subject` is set
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct Criterion {
+    pub statistic: Statistic,
+    #[serde(default)]
+    pub subject: Option<String>,
+    pub count: i64,
+    /// Written by hand in the data files, otherwise made up from the statistic and subject
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl Criterion {
+    pub fn label(&self) -> String {
+        match (&self.description, &self.subject) {
+            (Some(description), _) => description.clone(),
+            (None, Some(subject)) => format!("{} ({})", self.statistic.title(), subject.replace('_', " ")),
+            (None, None) => self.statistic.title().to_string(),
+        }
+    }
+
+    /// How far along the counter is, never past `count`
+    pub fn progress(&self, statistics: &Statistics) -> i64 {
+        statistics.get(self.statistic, self.subject.as_deref()).min(self.count)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.count <= 0 {
+            return Err(format!("{} is not a valid count", self.count));
+        }
+        match &self.subject {
+            Some(subject) if !self.statistic.has_subjects() => {
+                Err(format!("{} is not counted per {:?}", self.statistic, subject))
+            }
+            Some(subject) if !is_valid_id(subject) => Err(format!("{:?} is not a valid id", subject)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Sent by mail when the achievement is earned
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+pub struct AchievementRewards {
+    pub gold: i64,
+    pub items: Vec<ItemReward>,
+}
+
+impl AchievementRewards {
+    pub fn is_empty(&self) -> bool {
+        self.gold == 0 && self.items.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct AchievementDefinition {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Shown after the character's name once it is picked, like `the Wolfbane`
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Every one of them has to be met
+    pub criteria: Vec<Criterion>,
+    #[serde(default)]
+    pub rewards: AchievementRewards,
+}
+
+impl AchievementDefinition {
+    /// Checks the achievement on its own, references to other data are checked by the achievement
+    /// book
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_id(&self.id) {
+            return Err(format!("{:?} is not a valid achievement id", self.id));
+        }
+        if self.name.trim().is_empty() {
+            return Err(format!("Achievement {} has no name", self.id));
+        }
+        if self.title.as_ref().is_some_and(|t| t.trim().is_empty()) {
+            return Err(format!("Achievement {} has an empty title", self.id));
+        }
+        if self.criteria.is_empty() {
+            return Err(format!("Achievement {} has no criteria", self.id));
+        }
+        for (i, criterion) in self.criteria.iter().enumerate() {
+            criterion.validate().map_err(|e| format!("Achievement {} criterion {}: {}", self.id, i + 1, e))?;
+        }
+        if self.rewards.gold < 0 {
+            return Err(format!("Achievement {} rewards {} gold, rewards have to be positive", self.id, self.rewards.gold));
+        }
+        if let Some(reward) = self.rewards.items.iter().find(|r| r.quantity <= 0) {
+            return Err(format!("Achievement {} rewards {} {}, rewards have to be positive", self.id, reward.quantity, reward.item));
+        }
+        Ok(())
+    }
+
+    pub fn is_met(&self, statistics: &Statistics) -> bool {
+        self.criteria.iter().all(|c| c.progress(statistics) >= c.count)
+    }
+
+    /// Whether counting `statistic` could make a difference
+    pub fn counts(&self, statistic: Statistic) -> bool {
+        self.criteria.iter().any(|c| c.statistic == statistic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use crate::achievements::{AchievementDefinition, AchievementRewards, Criterion, Statistic, Statistics};
+
+    fn criterion(statistic: Statistic, subject: Option<&str>, count: i64) -> Criterion {
+        Criterion { statistic, subject: subject.map(str::to_string), count, description: None }
+    }
+
+    fn achievement() -> AchievementDefinition {
+        AchievementDefinition {
+            id: "wolfbane".to_string(),
+            name: "Wolfbane".to_string(),
+            description: String::new(),
+            title: Some("the Wolfbane".to_string()),
+            criteria: vec![criterion(Statistic::CreaturesKilled, Some("wolf"), 10)],
+            rewards: AchievementRewards::default(),
+        }
+    }
+
+    #[test]
+    fn a_simple_achievement_is_valid() {
+        assert_ok!(achievement().validate());
+    }
+
+    #[test]
+    fn achievements_need_criteria() {
+        let mut achievement = achievement();
+        achievement.criteria.clear();
+        assert_err!(achievement.validate());
+    }
+
+    #[test]
+    fn only_some_statistics_have_subjects() {
+        let mut achievement = achievement();
+        achievement.criteria = vec![criterion(Statistic::Deaths, Some("wolf"), 1)];
+        assert_err!(achievement.validate());
+    }
+
+    #[test]
+    fn counts_and_rewards_must_be_positive() {
+        let mut achievement = achievement();
+        achievement.criteria[0].count = 0;
+        assert_err!(achievement.validate());
+
+        let mut achievement = self::achievement();
+        achievement.rewards.gold = -1;
+        assert_err!(achievement.validate());
+    }
+
+    #[test]
+    fn every_criterion_has_to_be_met() {
+        let mut achievement = achievement();
+        achievement.criteria.push(criterion(Statistic::Deaths, None, 1));
+        let mut statistics = Statistics::default();
+        statistics.set(Statistic::CreaturesKilled, None, 20);
+        statistics.set(Statistic::CreaturesKilled, Some("wolf"), 12);
+        assert!(!achievement.is_met(&statistics));
+        assert_eq!(10, achievement.criteria[0].progress(&statistics));
+
+        statistics.set(Statistic::Deaths, None, 1);
+        assert!(achievement.is_met(&statistics));
+    }
+
+    #[test]
+    fn criteria_are_labelled_from_their_statistic() {
+        assert_eq!("Creatures killed (forest spider)", criterion(Statistic::CreaturesKilled, Some("forest_spider"), 1).label());
+        assert_eq!("Gold earned", criterion(Statistic::GoldEarned, None, 1).label());
+    }
+}
@@ -0,0 +1,12 @@
+mod book;
+mod definition;
+mod service;
+mod statistic;
+mod store;
+mod system;
+
+pub use book::{get_achievement_book, AchievementBook};
+pub use definition::{AchievementDefinition, AchievementRewards, Criterion};
+pub use service::{AchievementError, AchievementOverview, AchievementProgress, AchievementService, CriterionProgress, StatisticTotal};
+pub use statistic::{Statistic, Statistics, Tally};
+pub use system::AchievementSystem;
\ No newline at end of file
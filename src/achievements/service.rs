@@ -0,0 +1,239 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::achievements::{AchievementBook, AchievementDefinition, Statistic, Statistics, Tally};
+use crate::achievements::store::{add_statistic, get_earned_achievements, get_statistics, get_title, store_achievement, store_title};
+use crate::configuration::AchievementSettings;
+use crate::events::GameEvent;
+use crate::gateway::{ConnectionRegistry, ServerMessage};
+use crate::items::ItemStack;
+use crate::ledger::{transfer, Currency, LedgerAccount, LedgerError, SystemAccount, Transfer};
+use crate::mail::{MailError, MailKind, MailService, SystemMail};
+use crate::utils::error_chain_fmt;
+
+#[derive(thiserror::Error)]
+pub enum AchievementError {
+    #[error("There is no title called {0}")]
+    UnknownTitle(String),
+    #[error("You haven't earned that title yet")]
+    TitleNotEarned,
+    #[error(transparent)]
+    Mail(#[from] MailError),
+    #[error(transparent)]
+    Ledger(#[from] LedgerError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for AchievementError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StatisticTotal {
+    pub statistic: &'static str,
+    pub title: &'static str,
+    pub value: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CriterionProgress {
+    pub label: String,
+    pub current: i64,
+    pub required: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AchievementProgress {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub title: Option<String>,
+    pub earned_at: Option<DateTime<Utc>>,
+    pub criteria: Vec<CriterionProgress>,
+}
+
+/// Everything the player page shows about one character
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AchievementOverview {
+    /// The achievement whose title the character shows and the title itself
+    pub title_id: Option<String>,
+    pub title: Option<String>,
+    pub statistics: Vec<StatisticTotal>,
+    /// Every achievement in the book, earned or not
+    pub achievements: Vec<AchievementProgress>,
+}
+
+/// Counts game events into per character statistics and awards achievements as soon as their
+/// criteria are met. Rewards are sent by mail so a full backpack never holds an achievement up.
+/// Events are handled one at a time by `AchievementSystem`, storing the achievement is what makes
+/// sure it is only awarded once.
+pub struct AchievementService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    mail: Arc<MailService>,
+    book: Arc<AchievementBook>,
+    settings: AchievementSettings,
+}
+
+impl AchievementService {
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        mail: Arc<MailService>,
+        book: Arc<AchievementBook>,
+        settings: AchievementSettings,
+    ) -> Self {
+        AchievementService { pool, registry, mail, book, settings }
+    }
+
+    pub fn book(&self) -> &AchievementBook {
+        &self.book
+    }
+
+    pub async fn statistics(&self, character_id: Uuid) -> Result<Statistics, AchievementError> {
+        Ok(get_statistics(&self.pool, character_id).await?)
+    }
+
+    /// Counts the event and returns the ids of the achievements it earned
+    #[tracing::instrument(
+    name = "Handle event for achievements",
+    skip(self, event),
+    fields(character_id = % event.character_id())
+    )]
+    pub async fn handle(&self, event: &GameEvent) -> Result<Vec<String>, AchievementError> {
+        let Some(tally) = Tally::of(event) else {
+            return Ok(Vec::new());
+        };
+        let character_id = event.character_id();
+        let mut tx = self.begin().await?;
+        add_statistic(&mut tx, character_id, tally.statistic, "", tally.amount).await?;
+        if let Some(subject) = &tally.subject {
+            add_statistic(&mut tx, character_id, tally.statistic, subject, tally.amount).await?;
+        }
+
+        let statistics = get_statistics(&mut tx, character_id).await?;
+        let earned = get_earned_achievements(&mut tx, character_id).await?;
+        let now = Utc::now();
+        let mut awarded = Vec::new();
+        for achievement in self.book.sorted() {
+            if earned.contains_key(&achievement.id) || !achievement.counts(tally.statistic) || !achievement.is_met(&statistics) {
+                continue;
+            }
+            if store_achievement(&mut tx, character_id, &achievement.id, now).await? {
+                self.reward(&mut tx, character_id, achievement).await?;
+                awarded.push(achievement);
+            }
+        }
+        commit(tx).await?;
+
+        for achievement in &awarded {
+            let message = match &achievement.title {
+                Some(title) => format!("Achievement earned: {}, you can now go by {}", achievement.name, title),
+                None => format!("Achievement earned: {}", achievement.name),
+            };
+            self.registry.send_to_user(event.user_id(), &ServerMessage::Notice { message });
+        }
+        Ok(awarded.into_iter().map(|a| a.id.clone()).collect())
+    }
+
+    /// Statistic totals, the title and progress on every achievement
+    pub async fn overview(&self, character_id: Uuid) -> Result<AchievementOverview, AchievementError> {
+        let statistics = get_statistics(&self.pool, character_id).await?;
+        let earned = get_earned_achievements(&self.pool, character_id).await?;
+        let title_id = get_title(&self.pool, character_id).await?;
+        Ok(AchievementOverview {
+            title: title_id.as_ref().and_then(|id| self.book.get(id)).and_then(|a| a.title.clone()),
+            title_id,
+            statistics: Statistic::ALL
+                .into_iter()
+                .map(|statistic| StatisticTotal {
+                    statistic: statistic.as_str(),
+                    title: statistic.title(),
+                    value: statistics.get(statistic, None),
+                })
+                .collect(),
+            achievements: self.book
+                .sorted()
+                .into_iter()
+                .map(|achievement| AchievementProgress {
+                    id: achievement.id.clone(),
+                    name: achievement.name.clone(),
+                    description: achievement.description.clone(),
+                    title: achievement.title.clone(),
+                    earned_at: earned.get(&achievement.id).copied(),
+                    criteria: achievement.criteria
+                        .iter()
+                        .map(|c| CriterionProgress { label: c.label(), current: c.progress(&statistics), required: c.count })
+                        .collect(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Shows the title of an earned achievement after the character's name, none takes it off
+    #[tracing::instrument(
+    name = "Select title",
+    skip(self)
+    )]
+    pub async fn select_title(&self, character_id: Uuid, achievement_id: Option<&str>) -> Result<(), AchievementError> {
+        if let Some(id) = achievement_id {
+            if self.book.get(id).and_then(|a| a.title.as_ref()).is_none() {
+                return Err(AchievementError::UnknownTitle(id.to_string()));
+            }
+            if !get_earned_achievements(&self.pool, character_id).await?.contains_key(id) {
+                return Err(AchievementError::TitleNotEarned);
+            }
+        }
+        store_title(&self.pool, character_id, achievement_id).await?;
+        Ok(())
+    }
+
+    /// Gold comes out of the rewards account into escrow, where the mail holds it until claimed
+    async fn reward(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        achievement: &AchievementDefinition,
+    ) -> Result<(), AchievementError> {
+        if achievement.rewards.is_empty() {
+            return Ok(());
+        }
+        let money = (achievement.rewards.gold > 0).then_some((Currency::Gold, achievement.rewards.gold));
+        if let Some((currency, amount)) = money {
+            let funding = Transfer::new(
+                LedgerAccount::System(SystemAccount::Rewards),
+                LedgerAccount::System(SystemAccount::Escrow),
+                currency,
+                amount,
+                "achievement reward",
+            );
+            transfer(tx, &funding).await?;
+        }
+        let mail = SystemMail {
+            recipient_id: character_id,
+            subject: format!("Achievement: {}", achievement.name),
+            body: format!("Well done on earning {}, here is your reward.", achievement.name),
+            money,
+            items: achievement.rewards.items
+                .iter()
+                .map(|r| ItemStack { item_id: r.item.clone(), quantity: r.quantity })
+                .collect(),
+        };
+        self.mail.deliver(tx, MailKind::System, &self.settings.mail_sender, mail).await?;
+        Ok(())
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, AchievementError> {
+        Ok(self.pool.begin().await.context("Failed to begin achievement transaction")?)
+    }
+}
+
+async fn commit(tx: Transaction<'_, Postgres>) -> Result<(), AchievementError> {
+    Ok(tx.commit().await.context("Failed to commit achievement transaction")?)
+}
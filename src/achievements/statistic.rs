@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use crate::events::GameEvent;
+
+/// What gets counted for every character. Most statistics are also counted per creature, quest or
+/// recipe, the subject, next to the total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Statistic {
+    CreaturesKilled,
+    Deaths,
+    QuestsCompleted,
+    ItemsCrafted,
+    GoldEarned,
+}
+
+impl Statistic {
+    pub const ALL: [Statistic; 5] = [
+        Statistic::CreaturesKilled,
+        Statistic::Deaths,
+        Statistic::QuestsCompleted,
+        Statistic::ItemsCrafted,
+        Statistic::GoldEarned,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Statistic::CreaturesKilled => "creatures_killed",
+            Statistic::Deaths => "deaths",
+            Statistic::QuestsCompleted => "quests_completed",
+            Statistic::ItemsCrafted => "items_crafted",
+            Statistic::GoldEarned => "gold_earned",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Statistic::CreaturesKilled => "Creatures killed",
+            Statistic::Deaths => "Deaths",
+            Statistic::QuestsCompleted => "Quests completed",
+            Statistic::ItemsCrafted => "Items crafted",
+            Statistic::GoldEarned => "Gold earned",
+        }
+    }
+
+    /// Whether there is a count per creature, quest or recipe as well as the total
+    pub fn has_subjects(&self) -> bool {
+        matches!(self, Statistic::CreaturesKilled | Statistic::QuestsCompleted | Statistic::ItemsCrafted)
+    }
+}
+
+impl TryFrom<String> for Statistic {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "creatures_killed" => Ok(Statistic::CreaturesKilled),
+            "deaths" => Ok(Statistic::Deaths),
+            "quests_completed" => Ok(Statistic::QuestsCompleted),
+            "items_crafted" => Ok(Statistic::ItemsCrafted),
+            "gold_earned" => Ok(Statistic::GoldEarned),
+            other => Err(format!("{} is not a statistic", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for Statistic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What one game event adds to a character's statistics
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tally {
+    pub statistic: Statistic,
+    pub subject: Option<String>,
+    pub amount: i64,
+}
+
+impl Tally {
+    /// None for events that aren't counted
+    pub fn of(event: &GameEvent) -> Option<Tally> {
+        let (statistic, subject, amount) = match event {
+            GameEvent::CreatureDefeated { creature_id, .. } => (Statistic::CreaturesKilled, Some(creature_id), 1),
+            GameEvent::CharacterDied { .. } => (Statistic::Deaths, None, 1),
+            GameEvent::QuestCompleted { quest_id, .. } => (Statistic::QuestsCompleted, Some(quest_id), 1),
+            GameEvent::ItemCrafted { recipe_id, .. } => (Statistic::ItemsCrafted, Some(recipe_id), 1),
+            GameEvent::GoldEarned { amount, .. } if *amount > 0 => (Statistic::GoldEarned, None, *amount),
+            GameEvent::GoldEarned { .. }
+            | GameEvent::PartyKill { .. }
+            | GameEvent::PositionChanged { .. }
+            | GameEvent::TalkedTo { .. } => return None,
+        };
+        Some(Tally { statistic, subject: subject.cloned(), amount })
+    }
+}
+
+/// A character's counters, anything never counted is 0
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Statistics {
+    values: HashMap<(Statistic, String), i64>,
+}
+
+impl Statistics {
+    /// The total without a subject
+    pub fn get(&self, statistic: Statistic, subject: Option<&str>) -> i64 {
+        self.values
+            .get(&(statistic, subject.unwrap_or_default().to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set(&mut self, statistic: Statistic, subject: Option<&str>, value: i64) {
+        self.values.insert((statistic, subject.unwrap_or_default().to_string()), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::achievements::{Statistic, Statistics, Tally};
+    use crate::authentication::UserId;
+    use crate::events::GameEvent;
+
+    fn user_id() -> UserId {
+        UserId::from(Uuid::new_v4())
+    }
+
+    #[test]
+    fn kills_are_counted_per_creature() {
+        let event = GameEvent::CreatureDefeated {
+            user_id: user_id(),
+            character_id: Uuid::new_v4(),
+            creature_id: "wolf".to_string(),
+        };
+        let tally = Tally::of(&event).unwrap();
+        assert_eq!((Statistic::CreaturesKilled, Some("wolf"), 1), (tally.statistic, tally.subject.as_deref(), tally.amount));
+    }
+
+    #[test]
+    fn gold_is_counted_by_amount() {
+        let earned = |amount| GameEvent::GoldEarned { user_id: user_id(), character_id: Uuid::new_v4(), amount };
+        assert_eq!(Some(250), Tally::of(&earned(250)).map(|t| t.amount));
+        assert_eq!(None, Tally::of(&earned(0)));
+    }
+
+    #[test]
+    fn walking_around_is_not_counted() {
+        let event = GameEvent::TalkedTo {
+            user_id: user_id(),
+            character_id: Uuid::new_v4(),
+            npc_id: "elder".to_string(),
+            zone_id: "greenvale".to_string(),
+        };
+        assert_eq!(None, Tally::of(&event));
+    }
+
+    #[test]
+    fn totals_and_subjects_are_separate() {
+        let mut statistics = Statistics::default();
+        statistics.set(Statistic::CreaturesKilled, None, 5);
+        statistics.set(Statistic::CreaturesKilled, Some("wolf"), 3);
+
+        assert_eq!(5, statistics.get(Statistic::CreaturesKilled, None));
+        assert_eq!(3, statistics.get(Statistic::CreaturesKilled, Some("wolf")));
+        assert_eq!(0, statistics.get(Statistic::CreaturesKilled, Some("boar")));
+        assert_eq!(0, statistics.get(Statistic::Deaths, None));
+    }
+
+    #[test]
+    fn statistics_round_trip_through_their_names() {
+        for statistic in Statistic::ALL {
+            assert_eq!(Ok(statistic), Statistic::try_from(statistic.to_string()));
+        }
+    }
+}
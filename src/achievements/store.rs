@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::achievements::{Statistic, Statistics};
+
+/// Adds to a counter, `subject` is empty for the total
+#[tracing::instrument(
+name = "Add to statistic",
+skip(tx)
+)]
+pub async fn add_statistic(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    statistic: Statistic,
+    subject: &str,
+    amount: i64,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO character_statistics (character_id, statistic, subject, value)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (character_id, statistic, subject) DO UPDATE
+        SET value = character_statistics.value + EXCLUDED.value
+        "#,
+        character_id,
+        statistic.as_str(),
+        subject,
+        amount
+    )
+        .execute(tx)
+        .await
+        .context("Failed to add to statistic")?;
+    Ok(())
+}
+
+pub async fn get_statistics(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+) -> Result<Statistics, anyhow::Error> {
+    let rows = sqlx::query!(
+        "SELECT statistic, subject, value FROM character_statistics WHERE character_id = $1",
+        character_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch statistics")?;
+    let mut statistics = Statistics::default();
+    for row in rows {
+        let statistic = Statistic::try_from(row.statistic).map_err(|e| anyhow!(e))?;
+        statistics.set(statistic, Some(row.subject.as_str()).filter(|s| !s.is_empty()), row.value);
+    }
+    Ok(statistics)
+}
+
+/// When each earned achievement was earned
+pub async fn get_earned_achievements(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+) -> Result<HashMap<String, DateTime<Utc>>, anyhow::Error> {
+    let earned = sqlx::query!(
+        "SELECT achievement_id, earned_at FROM character_achievements WHERE character_id = $1",
+        character_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch achievements")?
+        .into_iter()
+        .map(|r| (r.achievement_id, r.earned_at))
+        .collect();
+    Ok(earned)
+}
+
+/// False when the character already had it, so an achievement is only ever awarded once
+#[tracing::instrument(
+name = "Store achievement",
+skip(tx)
+)]
+pub async fn store_achievement(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    achievement_id: &str,
+    earned_at: DateTime<Utc>,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO character_achievements (character_id, achievement_id, earned_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (character_id, achievement_id) DO NOTHING
+        "#,
+        character_id,
+        achievement_id,
+        earned_at
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store achievement")?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// The achievement whose title the character shows
+pub async fn get_title(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+) -> Result<Option<String>, anyhow::Error> {
+    let title = sqlx::query!(
+        "SELECT achievement_id FROM character_titles WHERE character_id = $1",
+        character_id
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch title")?
+        .map(|r| r.achievement_id);
+    Ok(title)
+}
+
+/// None takes the title off
+#[tracing::instrument(
+name = "Store title",
+skip(executor)
+)]
+pub async fn store_title(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+    achievement_id: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    match achievement_id {
+        Some(achievement_id) => sqlx::query!(
+            r#"
+            INSERT INTO character_titles (character_id, achievement_id)
+            VALUES ($1, $2)
+            ON CONFLICT (character_id) DO UPDATE SET achievement_id = EXCLUDED.achievement_id
+            "#,
+            character_id,
+            achievement_id
+        )
+            .execute(executor)
+            .await,
+        None => sqlx::query!("DELETE FROM character_titles WHERE character_id = $1", character_id)
+            .execute(executor)
+            .await,
+    }
+        .context("Failed to store title")?;
+    Ok(())
+}
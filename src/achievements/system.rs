@@ -0,0 +1,48 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::broadcast::error::TryRecvError;
+use tokio::sync::broadcast::Receiver;
+use crate::achievements::AchievementService;
+use crate::events::GameEvent;
+use crate::game_loop::{GameSystem, TickContext};
+
+/// Counts the game events of the last tick into statistics and awards what they earned
+pub struct AchievementSystem {
+    achievements: Arc<AchievementService>,
+    events: Receiver<GameEvent>,
+}
+
+impl AchievementSystem {
+    pub fn new(achievements: Arc<AchievementService>, events: Receiver<GameEvent>) -> Self {
+        AchievementSystem { achievements, events }
+    }
+}
+
+#[async_trait]
+impl GameSystem for AchievementSystem {
+    fn name(&self) -> &'static str {
+        "achievements"
+    }
+
+    async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error> {
+        let mut handled = 0;
+        loop {
+            let event = match self.events.try_recv() {
+                Ok(event) => event,
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+                Err(TryRecvError::Lagged(missed)) => {
+                    tracing::warn!(tick = ctx.tick, missed, "Statistics fell behind and missed game events");
+                    continue;
+                }
+            };
+            if let Err(e) = self.achievements.handle(&event).await {
+                tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to count game event");
+            }
+            handled += 1;
+        }
+        if handled > 0 {
+            tracing::debug!(tick = ctx.tick, handled, "Handled game events for achievements");
+        }
+        Ok(())
+    }
+}
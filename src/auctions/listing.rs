@@ -0,0 +1,199 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::items::ItemStack;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListingKind {
+    /// Sold to the first buyer who pays the buyout price
+    FixedPrice,
+    /// Goes to the highest bid when time runs out, or earlier to anyone paying the buyout
+    Auction,
+}
+
+impl ListingKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListingKind::FixedPrice => "fixed_price",
+            ListingKind::Auction => "auction",
+        }
+    }
+}
+
+impl TryFrom<String> for ListingKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "fixed_price" => Ok(ListingKind::FixedPrice),
+            "auction" => Ok(ListingKind::Auction),
+            other => Err(format!("{} is not a valid listing kind", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for ListingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListingStatus {
+    Active,
+    Sold,
+    /// Ran out of time without a single bid
+    Expired,
+    Cancelled,
+}
+
+impl ListingStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListingStatus::Active => "active",
+            ListingStatus::Sold => "sold",
+            ListingStatus::Expired => "expired",
+            ListingStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl TryFrom<String> for ListingStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "active" => Ok(ListingStatus::Active),
+            "sold" => Ok(ListingStatus::Sold),
+            "expired" => Ok(ListingStatus::Expired),
+            "cancelled" => Ok(ListingStatus::Cancelled),
+            other => Err(format!("{} is not a valid listing status", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for ListingStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Prices are in gold
+#[derive(Debug, Clone)]
+pub struct Listing {
+    pub id: Uuid,
+    pub seller_id: Uuid,
+    pub seller_name: String,
+    pub item: ItemStack,
+    pub kind: ListingKind,
+    /// Only auctions have one
+    pub start_price: Option<i64>,
+    pub buyout_price: Option<i64>,
+    pub listing_fee: i64,
+    pub high_bid: Option<i64>,
+    pub high_bidder_id: Option<Uuid>,
+    pub status: ListingStatus,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Listing {
+    /// Still up for sale at `now`, expired listings stay active until the sweep closes them
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        self.status == ListingStatus::Active && self.expires_at > now
+    }
+
+    /// The lowest bid that would be accepted, `None` when the listing can't be bid on
+    pub fn minimum_bid(&self, increment_percent: i64) -> Option<i64> {
+        if self.kind != ListingKind::Auction {
+            return None;
+        }
+        match self.high_bid {
+            Some(bid) => Some(bid + (bid * increment_percent / 100).max(1)),
+            None => self.start_price,
+        }
+    }
+}
+
+/// Paid up front on listing, a share of the buyout price or the starting bid without one
+pub fn listing_fee(start_price: Option<i64>, buyout_price: Option<i64>, percent: i64, minimum: i64) -> i64 {
+    let price = buyout_price.or(start_price).unwrap_or_default();
+    (price * percent / 100).max(minimum)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use claim::{assert_err, assert_none, assert_ok_eq, assert_some_eq};
+    use uuid::Uuid;
+    use crate::auctions::{listing_fee, Listing, ListingKind, ListingStatus};
+    use crate::items::ItemStack;
+
+    fn auction(start_price: i64) -> Listing {
+        let now = Utc::now();
+        Listing {
+            id: Uuid::new_v4(),
+            seller_id: Uuid::new_v4(),
+            seller_name: "Aldric".to_string(),
+            item: ItemStack { item_id: "potion".to_string(), quantity: 1 },
+            kind: ListingKind::Auction,
+            start_price: Some(start_price),
+            buyout_price: None,
+            listing_fee: 1,
+            high_bid: None,
+            high_bidder_id: None,
+            status: ListingStatus::Active,
+            created_at: now,
+            expires_at: now + Duration::hours(12),
+        }
+    }
+
+    #[test]
+    fn kinds_and_statuses_round_trip() {
+        for kind in [ListingKind::FixedPrice, ListingKind::Auction] {
+            assert_ok_eq!(ListingKind::try_from(kind.as_str().to_string()), kind);
+        }
+        for status in [ListingStatus::Active, ListingStatus::Sold, ListingStatus::Expired, ListingStatus::Cancelled] {
+            assert_ok_eq!(ListingStatus::try_from(status.as_str().to_string()), status);
+        }
+        assert_err!(ListingKind::try_from("barter".to_string()));
+    }
+
+    #[test]
+    fn the_first_bid_has_to_meet_the_start_price() {
+        assert_some_eq!(auction(40).minimum_bid(5), 40);
+    }
+
+    #[test]
+    fn later_bids_have_to_beat_the_high_bid_by_the_increment() {
+        let mut listing = auction(40);
+        listing.high_bid = Some(200);
+        assert_some_eq!(listing.minimum_bid(5), 210);
+        // never less than one gold more
+        listing.high_bid = Some(10);
+        assert_some_eq!(listing.minimum_bid(5), 11);
+    }
+
+    #[test]
+    fn fixed_price_listings_take_no_bids() {
+        let mut listing = auction(40);
+        listing.kind = ListingKind::FixedPrice;
+        assert_none!(listing.minimum_bid(5));
+    }
+
+    #[test]
+    fn listings_close_when_they_run_out_of_time() {
+        let listing = auction(40);
+        assert!(listing.is_open(Utc::now()));
+        assert!(!listing.is_open(listing.expires_at));
+        let mut sold = auction(40);
+        sold.status = ListingStatus::Sold;
+        assert!(!sold.is_open(Utc::now()));
+    }
+
+    #[test]
+    fn fees_follow_the_buyout_price_when_there_is_one() {
+        assert_eq!(5, listing_fee(Some(10), Some(100), 5, 1));
+        assert_eq!(2, listing_fee(Some(40), None, 5, 1));
+        assert_eq!(1, listing_fee(Some(3), None, 5, 1));
+    }
+}
@@ -0,0 +1,8 @@
+mod listing;
+mod service;
+mod store;
+mod system;
+
+pub use listing::{listing_fee, Listing, ListingKind, ListingStatus};
+pub use service::{AuctionError, AuctionService, BidOutcome, ListingDraft};
+pub use system::AuctionExpirySystem;
\ No newline at end of file
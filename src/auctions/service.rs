@@ -0,0 +1,494 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::auctions::{listing_fee, Listing, ListingKind, ListingStatus};
+use crate::auctions::store::{close_listing, count_active_listings, get_character_listings, get_finished_listing_ids, get_open_listings, lock_listing, lock_seller, store_bid, store_listing};
+use crate::authentication::UserId;
+use crate::characters::Character;
+use crate::configuration::AuctionSettings;
+use crate::gateway::{ClientError, ConnectionRegistry, ErrorCode, ServerMessage};
+use crate::items::{InventoryError, InventoryService, ItemStack};
+use crate::ledger::{transfer, transfer_once, Currency, LedgerAccount, LedgerError, SystemAccount, Transfer};
+use crate::mail::{get_character_owner, MailError, MailKind, MailService, SystemMail};
+use crate::utils::error_chain_fmt;
+
+/// Listings shown per search
+const SEARCH_LIMIT: i64 = 50;
+/// Finished listings settled per sweep, the rest waits for the next one
+const EXPIRY_BATCH_SIZE: i64 = 100;
+
+#[derive(thiserror::Error)]
+pub enum AuctionError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("That listing is no longer available")]
+    ListingNotFound,
+    #[error("You can't buy or bid on your own listing")]
+    OwnListing,
+    #[error("You already have the highest bid")]
+    AlreadyHighBidder,
+    #[error("Bids on this listing must be at least {0} gold")]
+    BidTooLow(i64),
+    #[error("That listing can only be bought outright")]
+    NotAnAuction,
+    #[error("That listing can't be bought outright")]
+    NoBuyout,
+    #[error("Listings with bids can't be cancelled")]
+    HasBids,
+    #[error(transparent)]
+    Inventory(#[from] InventoryError),
+    #[error(transparent)]
+    Ledger(#[from] LedgerError),
+    #[error(transparent)]
+    Mail(#[from] MailError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for AuctionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for AuctionError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            AuctionError::ValidationError(_) => ErrorCode::InvalidMessage,
+            AuctionError::ListingNotFound => ErrorCode::NotFound,
+            AuctionError::OwnListing => ErrorCode::Forbidden,
+            AuctionError::Inventory(e) => e.code(),
+            AuctionError::Ledger(e) => e.code(),
+            AuctionError::Mail(e) => e.code(),
+            AuctionError::UnexpectedError(_) => ErrorCode::Internal,
+            _ => ErrorCode::InvalidAction,
+        }
+    }
+}
+
+/// A stack to put up from a backpack slot. Without a starting bid it is a fixed price listing.
+#[derive(Debug, Clone)]
+pub struct ListingDraft {
+    pub slot: usize,
+    pub quantity: i32,
+    pub start_price: Option<i64>,
+    pub buyout_price: Option<i64>,
+    pub hours: i64,
+}
+
+#[derive(Debug)]
+pub enum BidOutcome {
+    /// The bid is the highest for now
+    Leading(Listing),
+    /// The bid reached the buyout price and won the listing right away
+    Bought(Listing),
+}
+
+/// The listed stack is held in the listing and the highest bid in the escrow ledger account until
+/// the listing closes. Every change locks the listing row first, so two buyers can't both win it.
+/// Whatever a listing leaves behind goes out as auction house mail, which works with full
+/// backpacks and characters that are offline.
+pub struct AuctionService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    inventory: Arc<InventoryService>,
+    mail: Arc<MailService>,
+    settings: AuctionSettings,
+}
+
+impl AuctionService {
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        inventory: Arc<InventoryService>,
+        mail: Arc<MailService>,
+        settings: AuctionSettings,
+    ) -> Self {
+        AuctionService { pool, registry, inventory, mail, settings }
+    }
+
+    pub fn settings(&self) -> &AuctionSettings {
+        &self.settings
+    }
+
+    /// Open listings for items whose name contains `query`, everything when it is empty
+    pub async fn search(&self, query: &str) -> Result<Vec<Listing>, AuctionError> {
+        let query = query.trim().to_lowercase();
+        let item_ids: Vec<String> = self.inventory
+            .catalog()
+            .iter()
+            .filter(|item| item.name.to_lowercase().contains(&query))
+            .map(|item| item.id.clone())
+            .collect();
+        Ok(get_open_listings(&self.pool, query.is_empty(), &item_ids, Utc::now(), SEARCH_LIMIT).await?)
+    }
+
+    /// What the character is selling and bidding on
+    pub async fn listings_of(&self, character_id: Uuid) -> Result<Vec<Listing>, AuctionError> {
+        Ok(get_character_listings(&self.pool, character_id).await?)
+    }
+
+    /// `Minor Healing Potion x2`
+    pub fn describe(&self, item: &ItemStack) -> String {
+        let name = self.inventory
+            .catalog()
+            .get(&item.item_id)
+            .map(|item| item.name.clone())
+            .unwrap_or_else(|| item.item_id.clone());
+        format!("{} x{}", name, item.quantity)
+    }
+
+    #[tracing::instrument(
+    name = "List item",
+    skip(self, seller),
+    fields(character_id = % seller.id)
+    )]
+    pub async fn list(&self, seller: &Character, draft: ListingDraft) -> Result<Listing, AuctionError> {
+        self.validate(&draft)?;
+        let fee = listing_fee(
+            draft.start_price,
+            draft.buyout_price,
+            self.settings.listing_fee_percent,
+            self.settings.min_listing_fee,
+        );
+        let mut tx = self.begin().await?;
+        if !lock_seller(&mut tx, seller.id).await? {
+            return Err(InventoryError::CharacterNotFound.into());
+        }
+        if count_active_listings(&mut tx, seller.id).await? >= self.settings.max_listings {
+            return Err(AuctionError::ValidationError(format!(
+                "You can have at most {} listings up at once", self.settings.max_listings
+            )));
+        }
+        let item = self.inventory
+            .take_stacks(&mut tx, seller.id, &[(draft.slot, draft.quantity)])
+            .await?
+            .remove(0);
+        if fee > 0 {
+            let payment = Transfer::new(
+                LedgerAccount::Character(seller.id),
+                LedgerAccount::System(SystemAccount::Fees),
+                Currency::Gold,
+                fee,
+                "auction listing fee",
+            );
+            transfer(&mut tx, &payment).await?;
+        }
+        let now = Utc::now();
+        let listing = Listing {
+            id: Uuid::new_v4(),
+            seller_id: seller.id,
+            seller_name: seller.name.to_string(),
+            item,
+            kind: if draft.start_price.is_some() { ListingKind::Auction } else { ListingKind::FixedPrice },
+            start_price: draft.start_price,
+            buyout_price: draft.buyout_price,
+            listing_fee: fee,
+            high_bid: None,
+            high_bidder_id: None,
+            status: ListingStatus::Active,
+            created_at: now,
+            expires_at: now + chrono::Duration::hours(draft.hours),
+        };
+        store_listing(&mut tx, &listing).await?;
+        commit(tx).await?;
+        Ok(listing)
+    }
+
+    /// The bid goes into escrow and the one it beats back to its bidder. A bid that reaches the
+    /// buyout price buys the listing instead.
+    #[tracing::instrument(
+    name = "Bid on listing",
+    skip(self, bidder),
+    fields(character_id = % bidder.id)
+    )]
+    pub async fn bid(
+        &self,
+        bidder: &Character,
+        listing_id: Uuid,
+        amount: i64,
+        request_key: Option<&str>,
+    ) -> Result<BidOutcome, AuctionError> {
+        if amount <= 0 {
+            return Err(AuctionError::ValidationError(format!("{} is not a valid amount", amount)));
+        }
+        let mut tx = self.begin().await?;
+        let mut listing = self.lock_open(&mut tx, listing_id, bidder.id).await?;
+        if let Some(buyout_price) = listing.buyout_price {
+            if amount >= buyout_price && listing.kind == ListingKind::Auction {
+                let notices = self.buy_locked(&mut tx, &listing, bidder, buyout_price, request_key).await?;
+                commit(tx).await?;
+                self.notify_all(notices).await?;
+                listing.status = ListingStatus::Sold;
+                return Ok(BidOutcome::Bought(listing));
+            }
+        }
+        let minimum = listing
+            .minimum_bid(self.settings.min_bid_increment_percent)
+            .ok_or(AuctionError::NotAnAuction)?;
+        if listing.high_bidder_id == Some(bidder.id) {
+            return Err(AuctionError::AlreadyHighBidder);
+        }
+        if amount < minimum {
+            return Err(AuctionError::BidTooLow(minimum));
+        }
+
+        let escrow = Transfer::new(
+            LedgerAccount::Character(bidder.id),
+            LedgerAccount::System(SystemAccount::Escrow),
+            Currency::Gold,
+            amount,
+            "auction bid",
+        ).request_key(&format!("bid:{}", listing.id), request_key);
+        transfer_once(&mut tx, &escrow).await?;
+        let mut notices = Vec::new();
+        if let Some(outbid) = self.refund_high_bid(&mut tx, &listing).await? {
+            notices.push((outbid, format!(
+                "You were outbid on {}, the bid is now {} gold",
+                self.describe(&listing.item), amount
+            )));
+        }
+        store_bid(&mut tx, listing.id, bidder.id, amount).await?;
+        commit(tx).await?;
+
+        self.notify_all(notices).await?;
+        listing.high_bid = Some(amount);
+        listing.high_bidder_id = Some(bidder.id);
+        Ok(BidOutcome::Leading(listing))
+    }
+
+    /// Pays the buyout price, works for fixed price listings and auctions that have one
+    #[tracing::instrument(
+    name = "Buy listing",
+    skip(self, buyer),
+    fields(character_id = % buyer.id)
+    )]
+    pub async fn buy(&self, buyer: &Character, listing_id: Uuid, request_key: Option<&str>) -> Result<Listing, AuctionError> {
+        let mut tx = self.begin().await?;
+        let mut listing = self.lock_open(&mut tx, listing_id, buyer.id).await?;
+        let price = listing.buyout_price.ok_or(AuctionError::NoBuyout)?;
+        let notices = self.buy_locked(&mut tx, &listing, buyer, price, request_key).await?;
+        commit(tx).await?;
+
+        self.notify_all(notices).await?;
+        listing.status = ListingStatus::Sold;
+        Ok(listing)
+    }
+
+    /// Only before anyone has bid, the listing fee is kept
+    #[tracing::instrument(
+    name = "Cancel listing",
+    skip(self)
+    )]
+    pub async fn cancel(&self, seller_id: Uuid, listing_id: Uuid) -> Result<Listing, AuctionError> {
+        let mut tx = self.begin().await?;
+        let mut listing = match lock_listing(&mut tx, listing_id).await? {
+            Some(listing) if listing.seller_id == seller_id && listing.status == ListingStatus::Active => listing,
+            _ => return Err(AuctionError::ListingNotFound),
+        };
+        if listing.high_bid.is_some() {
+            return Err(AuctionError::HasBids);
+        }
+        close_listing(&mut tx, listing.id, ListingStatus::Cancelled, None).await?;
+        self.send_back(&mut tx, &listing, "Auction cancelled").await?;
+        commit(tx).await?;
+
+        listing.status = ListingStatus::Cancelled;
+        Ok(listing)
+    }
+
+    /// Closes listings that ran out of time, the highest bid wins and the rest go back unsold
+    #[tracing::instrument(
+    name = "Expire listings",
+    skip(self)
+    )]
+    pub async fn expire(&self, now: DateTime<Utc>) -> Result<usize, AuctionError> {
+        let mut closed = 0;
+        for listing_id in get_finished_listing_ids(&self.pool, now, EXPIRY_BATCH_SIZE).await? {
+            let mut tx = self.begin().await?;
+            let listing = match lock_listing(&mut tx, listing_id).await? {
+                // bought or cancelled in the meantime
+                Some(listing) if listing.status == ListingStatus::Active && listing.expires_at <= now => listing,
+                _ => continue,
+            };
+            let notices = match (listing.high_bidder_id, listing.high_bid) {
+                (Some(winner_id), Some(price)) => {
+                    self.settle(&mut tx, &listing, winner_id, price).await?;
+                    vec![
+                        (winner_id, format!("You won {} for {} gold", self.describe(&listing.item), price)),
+                        (listing.seller_id, format!("Your {} sold for {} gold", self.describe(&listing.item), price)),
+                    ]
+                }
+                _ => {
+                    close_listing(&mut tx, listing.id, ListingStatus::Expired, None).await?;
+                    self.send_back(&mut tx, &listing, "Auction expired").await?;
+                    vec![(listing.seller_id, format!("Your {} did not sell", self.describe(&listing.item)))]
+                }
+            };
+            commit(tx).await?;
+            self.notify_all(notices).await?;
+            closed += 1;
+        }
+        Ok(closed)
+    }
+
+    async fn lock_open(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        listing_id: Uuid,
+        character_id: Uuid,
+    ) -> Result<Listing, AuctionError> {
+        let listing = match lock_listing(tx, listing_id).await? {
+            Some(listing) if listing.is_open(Utc::now()) => listing,
+            _ => return Err(AuctionError::ListingNotFound),
+        };
+        if listing.seller_id == character_id {
+            return Err(AuctionError::OwnListing);
+        }
+        Ok(listing)
+    }
+
+    /// The buyer pays into escrow, whoever held the high bid gets it back. Returns who to tell.
+    async fn buy_locked(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        listing: &Listing,
+        buyer: &Character,
+        price: i64,
+        request_key: Option<&str>,
+    ) -> Result<Vec<(Uuid, String)>, AuctionError> {
+        let escrow = Transfer::new(
+            LedgerAccount::Character(buyer.id),
+            LedgerAccount::System(SystemAccount::Escrow),
+            Currency::Gold,
+            price,
+            "auction buyout",
+        ).request_key(&format!("buyout:{}", listing.id), request_key);
+        transfer_once(tx, &escrow).await?;
+        let mut notices = Vec::new();
+        if let Some(outbid) = self.refund_high_bid(tx, listing).await? {
+            notices.push((outbid, format!(
+                "{} was bought out, your bid was refunded",
+                self.describe(&listing.item)
+            )));
+        }
+        self.settle(tx, listing, buyer.id, price).await?;
+        notices.push((listing.seller_id, format!(
+            "Your {} sold for {} gold",
+            self.describe(&listing.item), price
+        )));
+        Ok(notices)
+    }
+
+    /// Gives the current high bid back out of escrow, returns who it belonged to
+    async fn refund_high_bid(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        listing: &Listing,
+    ) -> Result<Option<Uuid>, AuctionError> {
+        let (bidder_id, bid) = match (listing.high_bidder_id, listing.high_bid) {
+            (Some(bidder_id), Some(bid)) => (bidder_id, bid),
+            _ => return Ok(None),
+        };
+        let refund = Transfer::new(
+            LedgerAccount::System(SystemAccount::Escrow),
+            LedgerAccount::Character(bidder_id),
+            Currency::Gold,
+            bid,
+            "auction bid refund",
+        );
+        transfer(tx, &refund).await?;
+        Ok(Some(bidder_id))
+    }
+
+    /// Marks the listing sold and mails the stack to the buyer and the price to the seller, the
+    /// price has to be in escrow already
+    async fn settle(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        listing: &Listing,
+        buyer_id: Uuid,
+        price: i64,
+    ) -> Result<(), AuctionError> {
+        close_listing(tx, listing.id, ListingStatus::Sold, Some((buyer_id, price))).await?;
+        let item = self.describe(&listing.item);
+        let purchase = SystemMail {
+            recipient_id: buyer_id,
+            subject: format!("Auction won: {}", item),
+            body: format!("Bought from {} for {} gold", listing.seller_name, price),
+            money: None,
+            items: vec![listing.item.clone()],
+        };
+        self.mail.deliver(tx, MailKind::Auction, &self.settings.mail_sender, purchase).await?;
+        let proceeds = SystemMail {
+            recipient_id: listing.seller_id,
+            subject: format!("Auction sold: {}", item),
+            body: format!("Sold for {} gold", price),
+            money: Some((Currency::Gold, price)),
+            items: vec![],
+        };
+        self.mail.deliver(tx, MailKind::Auction, &self.settings.mail_sender, proceeds).await?;
+        Ok(())
+    }
+
+    async fn send_back(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        listing: &Listing,
+        reason: &str,
+    ) -> Result<(), AuctionError> {
+        let unsold = SystemMail {
+            recipient_id: listing.seller_id,
+            subject: format!("{}: {}", reason, self.describe(&listing.item)),
+            body: String::new(),
+            money: None,
+            items: vec![listing.item.clone()],
+        };
+        self.mail.deliver(tx, MailKind::Auction, &self.settings.mail_sender, unsold).await?;
+        Ok(())
+    }
+
+    fn validate(&self, draft: &ListingDraft) -> Result<(), AuctionError> {
+        if !self.settings.durations_hours.contains(&draft.hours) {
+            let hours: Vec<String> = self.settings.durations_hours.iter().map(|h| h.to_string()).collect();
+            return Err(AuctionError::ValidationError(format!(
+                "Listings run for {} hours", hours.join(", ")
+            )));
+        }
+        for price in [draft.start_price, draft.buyout_price].into_iter().flatten() {
+            if price <= 0 {
+                return Err(AuctionError::ValidationError(format!("{} is not a valid price", price)));
+            }
+        }
+        match (draft.start_price, draft.buyout_price) {
+            (None, None) => Err(AuctionError::ValidationError(
+                "Set a starting bid, a buyout price or both".to_string()
+            )),
+            (Some(start_price), Some(buyout_price)) if buyout_price < start_price => Err(AuctionError::ValidationError(
+                "The buyout price can't be below the starting bid".to_string()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, AuctionError> {
+        Ok(self.pool.begin().await.context("Failed to begin auction transaction")?)
+    }
+
+    /// Only once the transaction behind the notices has committed
+    async fn notify_all(&self, notices: Vec<(Uuid, String)>) -> Result<(), AuctionError> {
+        for (character_id, message) in notices {
+            if let Some(user_id) = get_character_owner(&self.pool, character_id).await? {
+                self.registry.send_to_user(UserId::from(user_id), &ServerMessage::Notice { message });
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn commit(tx: Transaction<'_, Postgres>) -> Result<(), AuctionError> {
+    Ok(tx.commit().await.context("Failed to commit auction transaction")?)
+}
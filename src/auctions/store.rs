@@ -0,0 +1,287 @@
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::auctions::{Listing, ListingKind, ListingStatus};
+use crate::items::ItemStack;
+
+struct ListingRow {
+    id: Uuid,
+    seller_id: Uuid,
+    seller_name: String,
+    item_id: String,
+    quantity: i32,
+    kind: String,
+    start_price: Option<i64>,
+    buyout_price: Option<i64>,
+    listing_fee: i64,
+    high_bid: Option<i64>,
+    high_bidder_id: Option<Uuid>,
+    status: String,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl TryFrom<ListingRow> for Listing {
+    type Error = anyhow::Error;
+
+    fn try_from(row: ListingRow) -> Result<Self, Self::Error> {
+        Ok(Listing {
+            id: row.id,
+            seller_id: row.seller_id,
+            seller_name: row.seller_name,
+            item: ItemStack { item_id: row.item_id, quantity: row.quantity },
+            kind: ListingKind::try_from(row.kind).map_err(|e| anyhow!(e))?,
+            start_price: row.start_price,
+            buyout_price: row.buyout_price,
+            listing_fee: row.listing_fee,
+            high_bid: row.high_bid,
+            high_bidder_id: row.high_bidder_id,
+            status: ListingStatus::try_from(row.status).map_err(|e| anyhow!(e))?,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+        })
+    }
+}
+
+#[tracing::instrument(
+name = "Store listing",
+skip(tx, listing),
+fields(listing_id = % listing.id)
+)]
+pub async fn store_listing(
+    tx: &mut Transaction<'_, Postgres>,
+    listing: &Listing,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO auction_listings (id, seller_id, item_id, quantity, kind, start_price, buyout_price,
+                                      listing_fee, status, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        "#,
+        listing.id,
+        listing.seller_id,
+        listing.item.item_id,
+        listing.item.quantity,
+        listing.kind.as_str(),
+        listing.start_price,
+        listing.buyout_price,
+        listing.listing_fee,
+        listing.status.as_str(),
+        listing.created_at,
+        listing.expires_at
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store listing")?;
+    Ok(())
+}
+
+/// Everything that changes a listing goes through this lock, so a listing is only ever sold once
+#[tracing::instrument(
+name = "Lock listing",
+skip(tx)
+)]
+pub async fn lock_listing(
+    tx: &mut Transaction<'_, Postgres>,
+    listing_id: Uuid,
+) -> Result<Option<Listing>, anyhow::Error> {
+    sqlx::query_as!(
+        ListingRow,
+        r#"
+        SELECT l.id, l.seller_id, c.name AS seller_name, l.item_id, l.quantity, l.kind, l.start_price,
+               l.buyout_price, l.listing_fee, l.high_bid, l.high_bidder_id, l.status, l.created_at, l.expires_at
+        FROM auction_listings l
+        JOIN characters c ON c.id = l.seller_id
+        WHERE l.id = $1
+        FOR UPDATE OF l
+        "#,
+        listing_id
+    )
+        .fetch_optional(tx)
+        .await
+        .context("Failed to lock listing")?
+        .map(Listing::try_from)
+        .transpose()
+}
+
+#[tracing::instrument(
+name = "Store bid",
+skip(tx)
+)]
+pub async fn store_bid(
+    tx: &mut Transaction<'_, Postgres>,
+    listing_id: Uuid,
+    bidder_id: Uuid,
+    amount: i64,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "UPDATE auction_listings SET high_bid = $2, high_bidder_id = $3 WHERE id = $1",
+        listing_id,
+        amount,
+        bidder_id
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store bid")?;
+    Ok(())
+}
+
+/// `buyer` is whoever bought it and for how much, only for sold listings
+#[tracing::instrument(
+name = "Close listing",
+skip(tx)
+)]
+pub async fn close_listing(
+    tx: &mut Transaction<'_, Postgres>,
+    listing_id: Uuid,
+    status: ListingStatus,
+    buyer: Option<(Uuid, i64)>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE auction_listings SET status = $2, buyer_id = $3, sold_price = $4, closed_at = now()
+        WHERE id = $1
+        "#,
+        listing_id,
+        status.as_str(),
+        buyer.map(|(id, _)| id),
+        buyer.map(|(_, price)| price)
+    )
+        .execute(tx)
+        .await
+        .context("Failed to close listing")?;
+    Ok(())
+}
+
+/// Locks the seller's row so two listings of theirs are counted one after the other, false when the
+/// character is gone
+#[tracing::instrument(
+name = "Lock seller",
+skip(tx)
+)]
+pub async fn lock_seller(
+    tx: &mut Transaction<'_, Postgres>,
+    seller_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let locked = sqlx::query!(
+        r#"SELECT id FROM characters WHERE id = $1 AND deleted_at IS NULL FOR UPDATE"#,
+        seller_id
+    )
+        .fetch_optional(tx)
+        .await
+        .context("Failed to lock seller")?
+        .is_some();
+    Ok(locked)
+}
+
+pub async fn count_active_listings(
+    executor: impl PgExecutor<'_>,
+    seller_id: Uuid,
+) -> Result<i64, anyhow::Error> {
+    let count = sqlx::query!(
+        r#"SELECT count(*) AS "count!" FROM auction_listings WHERE seller_id = $1 AND status = 'active'"#,
+        seller_id
+    )
+        .fetch_one(executor)
+        .await
+        .context("Failed to count listings")?
+        .count;
+    Ok(count)
+}
+
+/// Open listings ending soonest first, limited to `item_ids` unless `all_items` is set
+#[tracing::instrument(
+name = "Get open listings",
+skip(executor, item_ids)
+)]
+pub async fn get_open_listings(
+    executor: impl PgExecutor<'_>,
+    all_items: bool,
+    item_ids: &[String],
+    now: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Listing>, anyhow::Error> {
+    sqlx::query_as!(
+        ListingRow,
+        r#"
+        SELECT l.id, l.seller_id, c.name AS seller_name, l.item_id, l.quantity, l.kind, l.start_price,
+               l.buyout_price, l.listing_fee, l.high_bid, l.high_bidder_id, l.status, l.created_at, l.expires_at
+        FROM auction_listings l
+        JOIN characters c ON c.id = l.seller_id
+        WHERE l.status = 'active' AND l.expires_at > $3 AND ($1 OR l.item_id = ANY($2))
+        ORDER BY l.expires_at
+        LIMIT $4
+        "#,
+        all_items,
+        item_ids,
+        now,
+        limit
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch listings")?
+        .into_iter()
+        .map(Listing::try_from)
+        .collect()
+}
+
+/// Active listings a character put up or holds the high bid on, ending soonest first
+#[tracing::instrument(
+name = "Get character listings",
+skip(executor)
+)]
+pub async fn get_character_listings(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+) -> Result<Vec<Listing>, anyhow::Error> {
+    sqlx::query_as!(
+        ListingRow,
+        r#"
+        SELECT l.id, l.seller_id, c.name AS seller_name, l.item_id, l.quantity, l.kind, l.start_price,
+               l.buyout_price, l.listing_fee, l.high_bid, l.high_bidder_id, l.status, l.created_at, l.expires_at
+        FROM auction_listings l
+        JOIN characters c ON c.id = l.seller_id
+        WHERE l.status = 'active' AND (l.seller_id = $1 OR l.high_bidder_id = $1)
+        ORDER BY l.expires_at
+        "#,
+        character_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch character listings")?
+        .into_iter()
+        .map(Listing::try_from)
+        .collect()
+}
+
+/// Oldest first, each one is locked again before anything happens to it
+#[tracing::instrument(
+name = "Get finished listings",
+skip(executor)
+)]
+pub async fn get_finished_listing_ids(
+    executor: impl PgExecutor<'_>,
+    now: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Uuid>, anyhow::Error> {
+    let ids = sqlx::query!(
+        r#"
+        SELECT id
+        FROM auction_listings
+        WHERE status = 'active' AND expires_at <= $1
+        ORDER BY expires_at
+        LIMIT $2
+        "#,
+        now,
+        limit
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch finished listings")?
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+    Ok(ids)
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use crate::auctions::AuctionService;
+use crate::game_loop::{GameSystem, TickContext};
+
+/// Settles listings that ran out of time on the first tick and every so often in game time after that
+pub struct AuctionExpirySystem {
+    auctions: Arc<AuctionService>,
+    interval: chrono::Duration,
+    last_sweep: Option<DateTime<Utc>>,
+}
+
+impl AuctionExpirySystem {
+    pub fn new(auctions: Arc<AuctionService>, interval: chrono::Duration) -> Self {
+        AuctionExpirySystem { auctions, interval, last_sweep: None }
+    }
+}
+
+#[async_trait]
+impl GameSystem for AuctionExpirySystem {
+    fn name(&self) -> &'static str {
+        "auction_expiry"
+    }
+
+    async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error> {
+        if let Some(last_sweep) = self.last_sweep {
+            if ctx.now - last_sweep < self.interval {
+                return Ok(());
+            }
+        }
+        self.last_sweep = Some(ctx.now);
+        let closed = self.auctions.expire(ctx.now).await?;
+        if closed > 0 {
+            tracing::info!(tick = ctx.tick, closed, "Closed finished listings");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    ChatMessageFiltered,
+    ChatMessageReported,
+    ChatUserMuted,
+    ChatUserUnmuted,
+    GuildCreated,
+    GuildMemberInvited,
+    GuildMemberJoined,
+    GuildMemberLeft,
+    GuildMemberKicked,
+    GuildRankChanged,
+    GuildPermissionsChanged,
+    GuildMotdChanged,
+    GuildBankDeposit,
+    GuildBankWithdrawal,
+    GuildDisbanded,
+    LeaderboardSeasonEnded,
+    MailSystemSent,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::ChatMessageFiltered => "chat.message_filtered",
+            AuditAction::ChatMessageReported => "chat.message_reported",
+            AuditAction::ChatUserMuted => "chat.user_muted",
+            AuditAction::ChatUserUnmuted => "chat.user_unmuted",
+            AuditAction::GuildCreated => "guild.created",
+            AuditAction::GuildMemberInvited => "guild.member_invited",
+            AuditAction::GuildMemberJoined => "guild.member_joined",
+            AuditAction::GuildMemberLeft => "guild.member_left",
+            AuditAction::GuildMemberKicked => "guild.member_kicked",
+            AuditAction::GuildRankChanged => "guild.rank_changed",
+            AuditAction::GuildPermissionsChanged => "guild.permissions_changed",
+            AuditAction::GuildMotdChanged => "guild.motd_changed",
+            AuditAction::GuildBankDeposit => "guild.bank_deposit",
+            AuditAction::GuildBankWithdrawal => "guild.bank_withdrawal",
+            AuditAction::GuildDisbanded => "guild.disbanded",
+            AuditAction::LeaderboardSeasonEnded => "leaderboards.season_ended",
+            AuditAction::MailSystemSent => "mail.system_sent",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    /// `None` when the game itself did it
+    pub actor_id: Option<Uuid>,
+    pub action: AuditAction,
+    /// Whatever the action was about, a message, an account, a guild
+    pub subject_id: Option<Uuid>,
+    pub details: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredAuditEntry {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub action: String,
+    pub subject_id: Option<Uuid>,
+    pub details: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(
+name = "Record audit entry",
+skip(executor, entry),
+fields(action = entry.action.as_str())
+)]
+pub async fn record_audit_entry(
+    executor: impl PgExecutor<'_>,
+    entry: &AuditEntry,
+) -> Result<Uuid, anyhow::Error> {
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_log (id, actor_id, action, subject_id, details)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        id,
+        entry.actor_id,
+        entry.action.as_str(),
+        entry.subject_id,
+        entry.details
+    )
+        .execute(executor)
+        .await
+        .context("Failed to record audit entry")?;
+    Ok(id)
+}
+
+#[tracing::instrument(
+name = "Get recent audit entries",
+skip(executor)
+)]
+pub async fn get_recent_audit_entries(
+    executor: impl PgExecutor<'_>,
+    limit: i64,
+) -> Result<Vec<StoredAuditEntry>, anyhow::Error> {
+    sqlx::query_as!(
+        StoredAuditEntry,
+        r#"
+        SELECT id, actor_id, action, subject_id, details, created_at
+        FROM audit_log
+        ORDER BY created_at DESC
+        LIMIT $1
+        "#,
+        limit
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch audit entries")
+}
+
+#[tracing::instrument(
+name = "Get audit entries for subject",
+skip(executor)
+)]
+pub async fn get_audit_entries_for_subject(
+    executor: impl PgExecutor<'_>,
+    subject_id: Uuid,
+    limit: i64,
+) -> Result<Vec<StoredAuditEntry>, anyhow::Error> {
+    sqlx::query_as!(
+        StoredAuditEntry,
+        r#"
+        SELECT id, actor_id, action, subject_id, details, created_at
+        FROM audit_log
+        WHERE subject_id = $1
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+        subject_id,
+        limit
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch audit entries")
+}
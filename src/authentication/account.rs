@@ -0,0 +1,95 @@
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use anyhow::{anyhow, Context};
+use crate::authentication::UserId;
+use crate::domain::{Account, AccountRole};
+use crate::store::Store;
+use crate::utils::{e403, e500, see_other};
+
+/// Only available on routes wrapped by `reject_anonymous_users`, which puts the id into the request extensions
+impl FromRequest for UserId {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<UserId, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let user_id = req.extensions()
+            .get::<UserId>()
+            .copied()
+            .ok_or_else(|| e500("User id is missing, is the route protected by `reject_anonymous_users`?"));
+        ready(user_id)
+    }
+}
+
+impl FromRequest for Account {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output=Result<Account, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user_id = UserId::from_request(req, payload).into_inner();
+        let store = req.app_data::<Data<dyn Store>>().cloned();
+
+        Box::pin(async move {
+            let user_id = user_id?;
+            let store = store.ok_or_else(|| e500("Store is not configured"))?;
+
+            let account = store.begin()
+                .await
+                .context("Failed to begin account lookup transaction")
+                .map_err(e500)?
+                .get_account_by_user_id(*user_id)
+                .await
+                .context("Failed to fetch account")
+                .map_err(e500)?;
+
+            // The session outlived the account, send them back to login
+            account.ok_or_else(|| {
+                let e = anyhow!("Account {} no longer exists", user_id);
+                InternalError::from_response(e, see_other("/login")).into()
+            })
+        })
+    }
+}
+
+/// Logged in account holding the admin role, everyone else gets a 403
+pub struct Admin(pub Account);
+
+impl FromRequest for Admin {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output=Result<Admin, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let account = Account::from_request(req, payload);
+
+        Box::pin(async move {
+            let account = account.await?;
+            if !account.has_role(AccountRole::Admin) {
+                return Err(e403(format!("Account {} is not an admin", account.id)));
+            }
+            Ok(Admin(account))
+        })
+    }
+}
+
+/// Logged in account allowed to moderate, admins included
+pub struct Moderator(pub Account);
+
+impl FromRequest for Moderator {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output=Result<Moderator, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let account = Account::from_request(req, payload);
+
+        Box::pin(async move {
+            let account = account.await?;
+            if !account.has_role(AccountRole::Moderator) && !account.has_role(AccountRole::Admin) {
+                return Err(e403(format!("Account {} is not a moderator", account.id)));
+            }
+            Ok(Moderator(account))
+        })
+    }
+}
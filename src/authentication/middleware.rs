@@ -33,9 +33,15 @@ pub async fn reject_anonymous_users(
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct UserId(Uuid);
 
+impl From<Uuid> for UserId {
+    fn from(user_id: Uuid) -> Self {
+        UserId(user_id)
+    }
+}
+
 impl std::fmt::Display for UserId {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         self.0.fmt(f)
@@ -1,7 +1,9 @@
+mod account;
 mod middleware;
 mod password;
 mod session_state;
 
-pub use middleware::reject_anonymous_users;
+pub use account::{Admin, Moderator};
+pub use middleware::{reject_anonymous_users, UserId};
 pub use session_state::YaugSession;
 pub use password::{Credentials, validate_login_credentials, AuthenticationError, verify_password_hash};
\ No newline at end of file
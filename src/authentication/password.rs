@@ -1,8 +1,9 @@
 use anyhow::{anyhow, Context};
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use secrecy::{ExposeSecret, Secret};
-use sqlx::PgPool;
 use uuid::Uuid;
+use crate::domain::AccountEmail;
+use crate::store::Store;
 use crate::telemetry::spawn_blocking_with_tracing;
 
 pub struct Credentials {
@@ -20,11 +21,11 @@ pub enum AuthenticationError {
 
 #[tracing::instrument(
 name = "Validate login credentials",
-skip(credentials, pool),
+skip(credentials, store),
 )]
 pub async fn validate_login_credentials(
     credentials: Credentials,
-    pool: &PgPool,
+    store: &dyn Store,
 ) -> Result<Uuid, AuthenticationError> {
     let mut user_id = None;
     // The reason we set some random password hash is so that we dont get a user enumeration attack
@@ -33,10 +34,19 @@ pub async fn validate_login_credentials(
         "$argon2id$v=19$m=15000,t=2,p=1$gZiV/M1gPc22ElAH/Jh1Hw$CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno".to_string(),
     );
 
-    if let Some((stored_user_id, stored_password_hash)) = get_stored_credentials(
-        pool, &credentials.email,
-    ).await.map_err(AuthenticationError::UnexpectedError)?
-    {
+    // Normalise the same way registration did, anything unparseable simply will not match
+    let email = AccountEmail::parse(credentials.email.clone())
+        .map(|e| e.as_ref().to_string())
+        .unwrap_or(credentials.email);
+
+    let stored_credentials = store.begin()
+        .await
+        .context("Failed to begin credentials lookup transaction")?
+        .get_stored_credentials(&email)
+        .await
+        .context("Failed to perform query to get stored credentials")?;
+
+    if let Some((stored_user_id, stored_password_hash)) = stored_credentials {
         user_id = Some(stored_user_id);
         expected_password_hash = stored_password_hash;
     }
@@ -52,29 +62,6 @@ pub async fn validate_login_credentials(
     user_id.ok_or_else(|| AuthenticationError::InvalidCredentials(anyhow!("Invalid username or password.")))
 }
 
-#[tracing::instrument(
-name = "get stored credentials",
-skip(pool, email)
-)]
-pub async fn get_stored_credentials(
-    pool: &PgPool,
-    email: &str,
-) -> Result<Option<(Uuid, Secret<String>)>, anyhow::Error> {
-    let row = sqlx::query!(
-       r#"
-       SELECT user_id, password_hash
-       FROM accounts
-       WHERE email = $1
-       "#,
-       email
-   ).fetch_optional(pool)
-        .await
-        .context("Failed to perform query to get stored credentials")?
-        .map(|r| (r.user_id, Secret::new(r.password_hash)));
-
-    Ok(row)
-}
-
 #[tracing::instrument(
 name = "Verify password hash",
 skip(expected, given)
@@ -1,13 +1,18 @@
 use actix_session::{Session, SessionExt};
 use actix_web::dev::Payload;
 use actix_web::{FromRequest, HttpRequest};
+use chrono::{DateTime, Utc};
 use std::future::{ready, Ready};
 use uuid::Uuid;
+use crate::characters::CharacterDraft;
 
 pub struct YaugSession(Session);
 
 impl YaugSession {
     const USER_ID_KEY: &'static str = "user_id";
+    const ACTIVE_CHARACTER_KEY: &'static str = "active_character";
+    const CHARACTER_DRAFT_KEY: &'static str = "character_draft";
+    const PREVIOUS_LOGIN_KEY: &'static str = "previous_login";
 
     pub fn renew(&self) { self.0.renew() }
 
@@ -19,6 +24,41 @@ impl YaugSession {
         self.0.get::<Uuid>(Self::USER_ID_KEY)
     }
 
+    pub fn insert_active_character(&self, character_id: Uuid) -> Result<(), serde_json::Error> {
+        self.0.insert(Self::ACTIVE_CHARACTER_KEY, character_id)
+    }
+
+    pub fn get_active_character(&self) -> Result<Option<Uuid>, serde_json::Error> {
+        self.0.get::<Uuid>(Self::ACTIVE_CHARACTER_KEY)
+    }
+
+    pub fn remove_active_character(&self) {
+        self.0.remove(Self::ACTIVE_CHARACTER_KEY);
+    }
+
+    /// First step of the character creation wizard, kept until the character is created
+    pub fn insert_character_draft(&self, draft: &CharacterDraft) -> Result<(), serde_json::Error> {
+        self.0.insert(Self::CHARACTER_DRAFT_KEY, draft)
+    }
+
+    pub fn get_character_draft(&self) -> Result<Option<CharacterDraft>, serde_json::Error> {
+        self.0.get::<CharacterDraft>(Self::CHARACTER_DRAFT_KEY)
+    }
+
+    pub fn remove_character_draft(&self) {
+        self.0.remove(Self::CHARACTER_DRAFT_KEY);
+    }
+
+    /// When the account logged in before this session, none for the very first login
+    pub fn insert_previous_login(&self, at: Option<DateTime<Utc>>) -> Result<(), serde_json::Error> {
+        self.0.insert(Self::PREVIOUS_LOGIN_KEY, at)
+    }
+
+    /// None when the session doesn't know, Some(None) when there was no earlier login
+    pub fn get_previous_login(&self) -> Result<Option<Option<DateTime<Utc>>>, serde_json::Error> {
+        self.0.get::<Option<DateTime<Utc>>>(Self::PREVIOUS_LOGIN_KEY)
+    }
+
     pub fn logout(&self) {
         self.0.purge()
     }
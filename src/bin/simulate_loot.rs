@@ -0,0 +1,73 @@
+//! Rolls a loot table from `data/loot` over and over with a fixed seed and prints how often each
+//! item and rarity dropped, so drop rates can be checked before they ship. Rolls are made by one
+//! player so pity kicks in, unless `--no-pity` is given.
+//!
+//! `cargo run --release --bin simulate-loot -- <table> [--rolls 1000000] [--seed 42] [--no-pity]`
+use std::process::ExitCode;
+use yaug::items::get_item_catalog;
+use yaug::loot::{get_loot_book, simulate, LootRoller};
+
+const DEFAULT_ROLLS: u64 = 1_000_000;
+const DEFAULT_SEED: u64 = 42;
+
+struct Arguments {
+    table_id: String,
+    rolls: u64,
+    seed: u64,
+    pity: bool,
+}
+
+fn parse(mut args: impl Iterator<Item=String>) -> Result<Arguments, String> {
+    let mut arguments = Arguments { table_id: String::new(), rolls: DEFAULT_ROLLS, seed: DEFAULT_SEED, pity: true };
+    while let Some(arg) = args.next() {
+        let mut number = |name: &str| {
+            args.next()
+                .and_then(|n| n.replace('_', "").parse().ok())
+                .ok_or_else(|| format!("{} needs a number", name))
+        };
+        match arg.as_str() {
+            "--rolls" => arguments.rolls = number("--rolls")?,
+            "--seed" => arguments.seed = number("--seed")?,
+            "--no-pity" => arguments.pity = false,
+            other if other.starts_with("--") => return Err(format!("Unknown option {}", other)),
+            _ if !arguments.table_id.is_empty() => return Err("Only one table can be simulated at a time".to_string()),
+            table_id => arguments.table_id = table_id.to_string(),
+        }
+    }
+    if arguments.table_id.is_empty() {
+        return Err("Usage: simulate-loot <table> [--rolls 1000000] [--seed 42] [--no-pity]".to_string());
+    }
+    Ok(arguments)
+}
+
+fn main() -> ExitCode {
+    let arguments = match parse(std::env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let (items, book) = match (get_item_catalog(), get_loot_book()) {
+        (Ok(items), Ok(book)) => (items, book),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if !book.contains(&arguments.table_id) {
+        eprintln!("There is no loot table called {}", arguments.table_id);
+        return ExitCode::FAILURE;
+    }
+    let problems = book.check_references(&items);
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("{}", problem);
+        }
+        return ExitCode::FAILURE;
+    }
+
+    let roller = LootRoller::new(&book, &items);
+    print!("{}", simulate(&roller, &arguments.table_id, arguments.rolls, arguments.seed, arguments.pity));
+    ExitCode::SUCCESS
+}
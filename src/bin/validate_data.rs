@@ -0,0 +1,59 @@
+//! Loads everything in `data/` the way the server does and reports what doesn't add up, including
+//! quests that reference items, zones or creatures that don't exist and recipes that reference
+//! items or stations that don't, achievements that count creatures, quests or recipes that
+//! don't, loot tables that drop missing items or promise pity they can't pay and npcs that fight
+//! as missing creatures, drop missing items or tables or spawn where they can't stand. Exits with 1
+//! on any problem.
+use std::process::ExitCode;
+use yaug::achievements::get_achievement_book;
+use yaug::combat::get_combat_rules;
+use yaug::crafting::get_recipe_book;
+use yaug::game_data::GameDataError;
+use yaug::items::get_item_catalog;
+use yaug::loot::get_loot_book;
+use yaug::npcs::get_npc_book;
+use yaug::quests::get_quest_book;
+use yaug::world::get_world_map;
+
+fn report<T>(what: &str, loaded: Result<T, GameDataError>, problems: &mut Vec<String>) -> Option<T> {
+    match loaded {
+        Ok(data) => Some(data),
+        Err(e) => {
+            problems.push(format!("{}: {:?}", what, e));
+            None
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut problems = Vec::new();
+    let items = report("Items", get_item_catalog(), &mut problems);
+    let map = report("Zones", get_world_map(), &mut problems);
+    let rules = report("Combat", get_combat_rules(), &mut problems);
+    let quests = report("Quests", get_quest_book(), &mut problems);
+    let recipes = report("Recipes", get_recipe_book(), &mut problems);
+    let achievements = report("Achievements", get_achievement_book(), &mut problems);
+    let loot = report("Loot", get_loot_book(), &mut problems);
+    let npcs = report("Npcs", get_npc_book(), &mut problems);
+
+    if let (Some(items), Some(map), Some(rules), Some(quests), Some(recipes), Some(achievements), Some(loot), Some(npcs)) =
+        (&items, &map, &rules, &quests, &recipes, &achievements, &loot, &npcs) {
+        problems.extend(quests.check_references(items, map, rules));
+        problems.extend(recipes.check_references(items, map));
+        problems.extend(achievements.check_references(items, rules, quests, recipes));
+        problems.extend(loot.check_references(items));
+        problems.extend(npcs.check_references(items, map, rules, loot));
+        if problems.is_empty() {
+            println!(
+                "{} items, {} zones, {} creatures, {} quests, {} recipes, {} achievements, {} loot tables and {} npcs are valid",
+                items.len(), map.len(), rules.creatures().count(), quests.len(), recipes.len(), achievements.len(), loot.len(), npcs.len()
+            );
+            return ExitCode::SUCCESS;
+        }
+    }
+
+    for problem in &problems {
+        eprintln!("{}", problem);
+    }
+    ExitCode::FAILURE
+}
@@ -0,0 +1,76 @@
+/// Points a new character can spend on top of the class base attributes
+pub const BONUS_POINTS: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub struct Attributes {
+    pub strength: i32,
+    pub dexterity: i32,
+    pub intelligence: i32,
+    pub vitality: i32,
+}
+
+impl Attributes {
+    pub fn total(&self) -> i32 {
+        self.strength + self.dexterity + self.intelligence + self.vitality
+    }
+
+    /// Adds the wizard allocation to `self`, all of the bonus points have to be spent
+    pub fn allocate(&self, bonus: Attributes) -> Result<Attributes, String> {
+        let points = [bonus.strength, bonus.dexterity, bonus.intelligence, bonus.vitality];
+        if points.iter().any(|p| *p < 0) {
+            return Err("Attribute points can't be negative".to_string());
+        }
+        if bonus.total() != BONUS_POINTS {
+            return Err(format!(
+                "You have to spend exactly {} attribute points, you spent {}",
+                BONUS_POINTS,
+                bonus.total()
+            ));
+        }
+
+        Ok(Attributes {
+            strength: self.strength + bonus.strength,
+            dexterity: self.dexterity + bonus.dexterity,
+            intelligence: self.intelligence + bonus.intelligence,
+            vitality: self.vitality + bonus.vitality,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok_eq};
+    use quickcheck_macros::quickcheck;
+    use crate::characters::{Attributes, BONUS_POINTS};
+
+    fn bonus(strength: i32, dexterity: i32, intelligence: i32, vitality: i32) -> Attributes {
+        Attributes { strength, dexterity, intelligence, vitality }
+    }
+
+    #[test]
+    fn bonus_points_are_added_to_the_base() {
+        let base = bonus(5, 5, 5, 5);
+        assert_ok_eq!(base.allocate(bonus(4, 3, 2, 1)), bonus(9, 8, 7, 6));
+    }
+
+    #[test]
+    fn all_points_have_to_be_spent() {
+        let base = bonus(5, 5, 5, 5);
+        assert_err!(base.allocate(bonus(1, 1, 1, 1)));
+        assert_err!(base.allocate(bonus(10, 1, 0, 0)));
+    }
+
+    #[test]
+    fn negative_points_are_rejected() {
+        let base = bonus(5, 5, 5, 5);
+        assert_err!(base.allocate(bonus(12, -2, 0, 0)));
+    }
+
+    #[quickcheck]
+    fn valid_allocation_adds_exactly_the_bonus(a: u8, b: u8, c: u8) -> bool {
+        let (a, b, c) = (a as i32 % 4, b as i32 % 4, c as i32 % 3);
+        let base = bonus(5, 5, 5, 5);
+        let allocated = base.allocate(bonus(a, b, c, BONUS_POINTS - a - b - c)).unwrap();
+        allocated.total() == base.total() + BONUS_POINTS
+    }
+}
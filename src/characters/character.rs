@@ -0,0 +1,69 @@
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+use crate::characters::{Attributes, CharacterClass, CharacterName};
+
+#[derive(Debug, Clone)]
+pub struct Character {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: CharacterName,
+    pub class: CharacterClass,
+    pub level: i32,
+    pub experience: i64,
+    pub attributes: Attributes,
+    pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl Character {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// Deleted characters can be brought back until the grace period runs out
+    pub fn is_restorable(&self, now: DateTime<Utc>, grace_period: Duration) -> bool {
+        self.deleted_at.is_some_and(|deleted_at| deleted_at + grace_period > now)
+    }
+
+    pub fn restorable_until(&self, grace_period: Duration) -> Option<DateTime<Utc>> {
+        self.deleted_at.map(|deleted_at| deleted_at + grace_period)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+    use crate::characters::{Character, CharacterClass, CharacterName};
+
+    fn character() -> Character {
+        let class = CharacterClass::Rogue;
+        Character {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: CharacterName::parse("Garrett".to_string()).unwrap(),
+            class,
+            level: 1,
+            experience: 0,
+            attributes: class.base_attributes(),
+            created_at: Utc::now(),
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn live_characters_are_not_restorable() {
+        assert!(!character().is_restorable(Utc::now(), Duration::hours(72)));
+    }
+
+    #[test]
+    fn deleted_characters_are_restorable_within_the_grace_period() {
+        let now = Utc::now();
+        let mut c = character();
+        c.deleted_at = Some(now - Duration::hours(71));
+        assert!(c.is_restorable(now, Duration::hours(72)));
+
+        c.deleted_at = Some(now - Duration::hours(73));
+        assert!(!c.is_restorable(now, Duration::hours(72)));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::characters::Attributes;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterClass {
+    Warrior,
+    Mage,
+    Rogue,
+    Ranger,
+    Cleric,
+}
+
+impl CharacterClass {
+    pub const ALL: [CharacterClass; 5] = [
+        CharacterClass::Warrior,
+        CharacterClass::Mage,
+        CharacterClass::Rogue,
+        CharacterClass::Ranger,
+        CharacterClass::Cleric,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CharacterClass::Warrior => "warrior",
+            CharacterClass::Mage => "mage",
+            CharacterClass::Rogue => "rogue",
+            CharacterClass::Ranger => "ranger",
+            CharacterClass::Cleric => "cleric",
+        }
+    }
+
+    /// Every class starts with the same total, bonus points from the wizard come on top
+    pub fn base_attributes(&self) -> Attributes {
+        let (strength, dexterity, intelligence, vitality) = match self {
+            CharacterClass::Warrior => (8, 5, 3, 8),
+            CharacterClass::Mage => (3, 5, 10, 6),
+            CharacterClass::Rogue => (5, 9, 5, 5),
+            CharacterClass::Ranger => (5, 8, 5, 6),
+            CharacterClass::Cleric => (5, 4, 8, 7),
+        };
+        Attributes { strength, dexterity, intelligence, vitality }
+    }
+}
+
+impl TryFrom<String> for CharacterClass {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "warrior" => Ok(CharacterClass::Warrior),
+            "mage" => Ok(CharacterClass::Mage),
+            "rogue" => Ok(CharacterClass::Rogue),
+            "ranger" => Ok(CharacterClass::Ranger),
+            "cleric" => Ok(CharacterClass::Cleric),
+            other => Err(format!("{} is not a valid character class", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for CharacterClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok_eq};
+    use crate::characters::CharacterClass;
+
+    #[test]
+    fn all_classes_round_trip() {
+        for class in CharacterClass::ALL {
+            assert_ok_eq!(CharacterClass::try_from(class.as_str().to_uppercase()), class);
+        }
+        assert_err!(CharacterClass::try_from("bard".to_string()));
+    }
+
+    #[test]
+    fn classes_start_with_the_same_total() {
+        let totals: Vec<i32> = CharacterClass::ALL
+            .iter()
+            .map(|c| c.base_attributes().total())
+            .collect();
+        assert!(totals.windows(2).all(|w| w[0] == w[1]), "{:?}", totals);
+    }
+}
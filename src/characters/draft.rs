@@ -0,0 +1,25 @@
+use crate::characters::{CharacterClass, CharacterName};
+
+/// Name and class picked in the first wizard step, stored in the session between steps
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CharacterDraft {
+    pub name: String,
+    pub class: String,
+}
+
+impl CharacterDraft {
+    pub fn new(name: &CharacterName, class: CharacterClass) -> Self {
+        CharacterDraft {
+            name: name.as_ref().to_string(),
+            class: class.as_str().to_string(),
+        }
+    }
+
+    /// Drafts come back from the session store, so they get validated again
+    pub fn parse(&self) -> Result<(CharacterName, CharacterClass), String> {
+        Ok((
+            CharacterName::parse(self.name.clone())?,
+            CharacterClass::try_from(self.class.clone())?,
+        ))
+    }
+}
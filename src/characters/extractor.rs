@@ -0,0 +1,68 @@
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpRequest};
+use anyhow::{anyhow, Context};
+use crate::authentication::{UserId, YaugSession};
+use crate::characters::Character;
+use crate::store::Store;
+use crate::utils::{e500, see_other};
+
+/// The character picked on `/characters`, requests without one are sent there to pick one
+pub struct ActiveCharacter(Character);
+
+impl ActiveCharacter {
+    pub fn into_inner(self) -> Character {
+        self.0
+    }
+}
+
+impl Deref for ActiveCharacter {
+    type Target = Character;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromRequest for ActiveCharacter {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output=Result<ActiveCharacter, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user_id = UserId::from_request(req, payload).into_inner();
+        let session = YaugSession::from_request(req, payload).into_inner();
+        let store = req.app_data::<Data<dyn Store>>().cloned();
+
+        Box::pin(async move {
+            let user_id = user_id?;
+            let session = session?;
+            let store = store.ok_or_else(|| e500("Store is not configured"))?;
+
+            let character = match session.get_active_character().map_err(e500)? {
+                Some(character_id) => store.begin()
+                    .await
+                    .context("Failed to begin character lookup transaction")
+                    .map_err(e500)?
+                    .get_character(*user_id, character_id)
+                    .await
+                    .context("Failed to fetch character")
+                    .map_err(e500)?
+                    .filter(|c| !c.is_deleted()),
+                None => None,
+            };
+
+            match character {
+                Some(character) => Ok(ActiveCharacter(character)),
+                None => {
+                    session.remove_active_character();
+                    let e = anyhow!("No active character selected");
+                    Err(InternalError::from_response(e, see_other("/characters")).into())
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,47 @@
+pub const MAX_LEVEL: i32 = 60;
+
+/// Total experience needed to reach `level`, every level takes 200 more than the one before
+pub fn experience_for_level(level: i32) -> i64 {
+    let level = level.clamp(1, MAX_LEVEL) as i64;
+    100 * (level - 1) * level
+}
+
+pub fn level_for_experience(experience: i64) -> i32 {
+    let mut level = 1;
+    while level < MAX_LEVEL && experience >= experience_for_level(level + 1) {
+        level += 1;
+    }
+    level
+}
+
+/// Total experience for every level from 2 up, the level is one more than the thresholds reached
+pub fn level_thresholds() -> Vec<i64> {
+    (2..=MAX_LEVEL).map(experience_for_level).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::characters::{experience_for_level, level_for_experience, level_thresholds, MAX_LEVEL};
+
+    #[test]
+    fn levels_follow_experience() {
+        assert_eq!(0, experience_for_level(1));
+        assert_eq!(200, experience_for_level(2));
+        assert_eq!(600, experience_for_level(3));
+        assert_eq!(1, level_for_experience(0));
+        assert_eq!(1, level_for_experience(199));
+        assert_eq!(2, level_for_experience(200));
+        assert_eq!(3, level_for_experience(600));
+        assert_eq!(MAX_LEVEL, level_for_experience(i64::MAX));
+    }
+
+    #[test]
+    fn thresholds_count_the_levels_reached() {
+        let thresholds = level_thresholds();
+        assert_eq!(MAX_LEVEL as usize - 1, thresholds.len());
+        for experience in [0, 199, 200, 599, 600, 12_345, i64::MAX] {
+            let reached = thresholds.iter().filter(|t| **t <= experience).count() as i32;
+            assert_eq!(level_for_experience(experience), 1 + reached);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+mod attributes;
+mod character;
+mod class;
+mod draft;
+mod extractor;
+mod level;
+mod name;
+mod service;
+mod store;
+
+pub use attributes::{Attributes, BONUS_POINTS};
+pub use character::Character;
+pub use class::CharacterClass;
+pub use draft::CharacterDraft;
+pub use extractor::ActiveCharacter;
+pub use level::{experience_for_level, level_for_experience, level_thresholds, MAX_LEVEL};
+pub use name::CharacterName;
+pub use service::{create_character, delete_character, restore_character, CharacterError, NewCharacter};
+pub use store::{add_experience, count_live_characters, get_character, get_characters_by_user_id, lock_account, soft_delete_character, store_character, undelete_character};
\ No newline at end of file
@@ -0,0 +1,79 @@
+use std::fmt::Formatter;
+use crate::domain::check_name_is_acceptable;
+
+const MIN_LENGTH: usize = 3;
+const MAX_LENGTH: usize = 16;
+
+/// Character names are letters only and always stored capitalised, `bOB` becomes `Bob`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharacterName(String);
+
+impl CharacterName {
+    pub fn parse(v: String) -> Result<Self, String> {
+        let name = v.trim();
+
+        let length = name.chars().count();
+        if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) {
+            return Err(format!(
+                "Character name must be between {} and {} letters long",
+                MIN_LENGTH, MAX_LENGTH
+            ));
+        }
+
+        if !name.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err("Character name can only contain letters".to_string());
+        }
+
+        check_name_is_acceptable(name)?;
+
+        let lowercase = name.to_ascii_lowercase();
+        let mut chars = lowercase.chars();
+        let capitalised = chars.next()
+            .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+            .unwrap_or_default();
+        Ok(CharacterName(capitalised))
+    }
+}
+
+impl AsRef<str> for CharacterName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for CharacterName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::assert_err;
+    use crate::characters::CharacterName;
+
+    #[test]
+    fn names_are_capitalised() {
+        let name = CharacterName::parse(" aRAGORN ".to_string()).unwrap();
+        assert_eq!("Aragorn", name.as_ref());
+    }
+
+    #[test]
+    fn names_must_be_letters_only() {
+        for name in ["Bob1", "Bob_", "Bo b", "Bøb"] {
+            assert_err!(CharacterName::parse(name.to_string()));
+        }
+    }
+
+    #[test]
+    fn names_must_have_a_sensible_length() {
+        assert_err!(CharacterName::parse("Bo".to_string()));
+        assert_err!(CharacterName::parse("B".repeat(17)));
+    }
+
+    #[test]
+    fn reserved_and_profane_names_are_rejected() {
+        assert_err!(CharacterName::parse("Admin".to_string()));
+        assert_err!(CharacterName::parse("Shitface".to_string()));
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt::{Debug, Formatter};
+use anyhow::Context;
+use chrono::Utc;
+use uuid::Uuid;
+use crate::characters::{Attributes, Character, CharacterClass, CharacterName};
+use crate::configuration::CharacterSettings;
+use crate::store::{Store, StoreError};
+use crate::utils::error_chain_fmt;
+
+#[derive(thiserror::Error)]
+pub enum CharacterError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("You can't have more than {0} characters")]
+    LimitReached(i64),
+    #[error("The name {0} is already taken")]
+    NameTaken(String),
+    #[error("Character not found")]
+    NotFound,
+    #[error("{0} can no longer be restored")]
+    GracePeriodExpired(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for CharacterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl From<StoreError> for CharacterError {
+    fn from(e: StoreError) -> Self {
+        CharacterError::UnexpectedError(e.into())
+    }
+}
+
+pub struct NewCharacter {
+    pub name: CharacterName,
+    pub class: CharacterClass,
+    pub bonus: Attributes,
+}
+
+#[tracing::instrument(
+name = "Create character",
+skip(store, settings, new_character),
+fields(character_name = % new_character.name)
+)]
+pub async fn create_character(
+    store: &dyn Store,
+    settings: &CharacterSettings,
+    user_id: Uuid,
+    new_character: NewCharacter,
+) -> Result<Character, CharacterError> {
+    let attributes = new_character.class
+        .base_attributes()
+        .allocate(new_character.bonus)
+        .map_err(CharacterError::ValidationError)?;
+
+    let mut tx = store.begin()
+        .await
+        .context("Failed to begin character transaction")?;
+
+    tx.lock_account(user_id).await?;
+    if tx.count_live_characters(user_id).await? >= settings.max_per_account {
+        return Err(CharacterError::LimitReached(settings.max_per_account));
+    }
+
+    let character = Character {
+        id: Uuid::new_v4(),
+        user_id,
+        name: new_character.name,
+        class: new_character.class,
+        level: 1,
+        experience: 0,
+        attributes,
+        created_at: Utc::now(),
+        deleted_at: None,
+    };
+    tx.store_character(&character)
+        .await
+        .map_err(|e| name_taken_or_unexpected(e, &character.name))?;
+    tx.commit()
+        .await
+        .map_err(|e| name_taken_or_unexpected(e, &character.name))?;
+    Ok(character)
+}
+
+#[tracing::instrument(
+name = "Delete character",
+skip(store)
+)]
+pub async fn delete_character(
+    store: &dyn Store,
+    user_id: Uuid,
+    character_id: Uuid,
+) -> Result<Character, CharacterError> {
+    let mut tx = store.begin()
+        .await
+        .context("Failed to begin character transaction")?;
+
+    let character = tx.get_character(user_id, character_id)
+        .await?
+        .filter(|c| !c.is_deleted())
+        .ok_or(CharacterError::NotFound)?;
+    tx.soft_delete_character(character.id).await?;
+
+    tx.commit()
+        .await
+        .context("Failed to commit character transaction")?;
+    Ok(character)
+}
+
+/// Restored characters count towards the limit again and need their name to still be free
+#[tracing::instrument(
+name = "Restore character",
+skip(store, settings)
+)]
+pub async fn restore_character(
+    store: &dyn Store,
+    settings: &CharacterSettings,
+    user_id: Uuid,
+    character_id: Uuid,
+) -> Result<Character, CharacterError> {
+    let mut tx = store.begin()
+        .await
+        .context("Failed to begin character transaction")?;
+
+    tx.lock_account(user_id).await?;
+    let character = tx.get_character(user_id, character_id)
+        .await?
+        .filter(|c| c.is_deleted())
+        .ok_or(CharacterError::NotFound)?;
+    if !character.is_restorable(Utc::now(), settings.grace_period()) {
+        return Err(CharacterError::GracePeriodExpired(character.name.to_string()));
+    }
+    if tx.count_live_characters(user_id).await? >= settings.max_per_account {
+        return Err(CharacterError::LimitReached(settings.max_per_account));
+    }
+
+    tx.undelete_character(character.id)
+        .await
+        .map_err(|e| name_taken_or_unexpected(e, &character.name))?;
+    tx.commit()
+        .await
+        .map_err(|e| name_taken_or_unexpected(e, &character.name))?;
+    Ok(character)
+}
+
+// the store reports a taken name as a conflict, at the write or at the commit
+fn name_taken_or_unexpected(e: StoreError, name: &CharacterName) -> CharacterError {
+    match e {
+        StoreError::Conflict(_) => CharacterError::NameTaken(name.to_string()),
+        e => CharacterError::UnexpectedError(anyhow::Error::from(e).context("Failed to store character")),
+    }
+}
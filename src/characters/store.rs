@@ -0,0 +1,240 @@
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::characters::{level_thresholds, Attributes, Character, CharacterClass, CharacterName};
+
+struct CharacterRow {
+    id: Uuid,
+    user_id: Uuid,
+    name: String,
+    class: String,
+    level: i32,
+    experience: i64,
+    strength: i32,
+    dexterity: i32,
+    intelligence: i32,
+    vitality: i32,
+    created_at: DateTime<Utc>,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<CharacterRow> for Character {
+    type Error = anyhow::Error;
+
+    fn try_from(row: CharacterRow) -> Result<Self, Self::Error> {
+        Ok(Character {
+            id: row.id,
+            user_id: row.user_id,
+            name: CharacterName::parse(row.name).map_err(|e| anyhow!(e))?,
+            class: CharacterClass::try_from(row.class).map_err(|e| anyhow!(e))?,
+            level: row.level,
+            experience: row.experience,
+            attributes: Attributes {
+                strength: row.strength,
+                dexterity: row.dexterity,
+                intelligence: row.intelligence,
+                vitality: row.vitality,
+            },
+            created_at: row.created_at,
+            deleted_at: row.deleted_at,
+        })
+    }
+}
+
+/// Takes a row lock on the account so concurrent creations are counted one after another
+#[tracing::instrument(
+name = "Lock account for character changes",
+skip(tx)
+)]
+pub async fn lock_account(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"SELECT user_id FROM accounts WHERE user_id = $1 FOR UPDATE"#,
+        user_id
+    )
+        .fetch_one(tx)
+        .await
+        .context("Failed to lock account")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Count live characters",
+skip(executor)
+)]
+pub async fn count_live_characters(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<i64, anyhow::Error> {
+    let count = sqlx::query!(
+        r#"
+        SELECT count(*) as "count!"
+        FROM characters
+        WHERE user_id = $1 AND deleted_at IS NULL
+        "#,
+        user_id
+    )
+        .fetch_one(executor)
+        .await
+        .context("Failed to count characters")?
+        .count;
+    Ok(count)
+}
+
+#[tracing::instrument(
+name = "Store character",
+skip(tx, character),
+fields(character_name = % character.name)
+)]
+pub async fn store_character(
+    tx: &mut Transaction<'_, Postgres>,
+    character: &Character,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO characters (id, user_id, name, class, level, experience,
+                                strength, dexterity, intelligence, vitality, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        "#,
+        character.id,
+        character.user_id,
+        character.name.as_ref(),
+        character.class.as_str(),
+        character.level,
+        character.experience,
+        character.attributes.strength,
+        character.attributes.dexterity,
+        character.attributes.intelligence,
+        character.attributes.vitality,
+        character.created_at,
+    )
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+/// Deleted characters are included so the list can offer to restore them
+#[tracing::instrument(
+name = "Get characters by user id",
+skip(executor)
+)]
+pub async fn get_characters_by_user_id(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<Vec<Character>, anyhow::Error> {
+    sqlx::query_as!(
+        CharacterRow,
+        r#"
+        SELECT id, user_id, name, class, level, experience,
+               strength, dexterity, intelligence, vitality, created_at, deleted_at
+        FROM characters
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+        user_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to execute character list query")?
+        .into_iter()
+        .map(Character::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to parse stored character")
+}
+
+/// Only finds characters owned by `user_id`, someone else's character id is as good as a missing one
+#[tracing::instrument(
+name = "Get character",
+skip(executor)
+)]
+pub async fn get_character(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    character_id: Uuid,
+) -> Result<Option<Character>, anyhow::Error> {
+    sqlx::query_as!(
+        CharacterRow,
+        r#"
+        SELECT id, user_id, name, class, level, experience,
+               strength, dexterity, intelligence, vitality, created_at, deleted_at
+        FROM characters
+        WHERE id = $1 AND user_id = $2
+        "#,
+        character_id,
+        user_id
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to execute character fetch query")?
+        .map(Character::try_from)
+        .transpose()
+        .context("Failed to parse stored character")
+}
+
+#[tracing::instrument(
+name = "Soft delete character",
+skip(tx)
+)]
+pub async fn soft_delete_character(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"UPDATE characters SET deleted_at = now() WHERE id = $1 AND deleted_at IS NULL"#,
+        character_id
+    )
+        .execute(tx)
+        .await
+        .context("Failed to delete character")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Undelete character",
+skip(tx)
+)]
+pub async fn undelete_character(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE characters SET deleted_at = NULL WHERE id = $1"#,
+        character_id
+    )
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+/// Levels the character up in the same update, returns the new level
+#[tracing::instrument(
+name = "Add character experience",
+skip(tx)
+)]
+pub async fn add_experience(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    experience: i64,
+) -> Result<i32, anyhow::Error> {
+    let level = sqlx::query!(
+        r#"
+        UPDATE characters
+        SET experience = experience + $2,
+            level      = 1 + (SELECT count(*) FROM unnest($3::BIGINT[]) AS t (threshold) WHERE threshold <= experience + $2)
+        WHERE id = $1
+        RETURNING level
+        "#,
+        character_id,
+        experience,
+        &level_thresholds()
+    )
+        .fetch_one(tx)
+        .await
+        .context("Failed to add experience")?
+        .level;
+    Ok(level)
+}
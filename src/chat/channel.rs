@@ -0,0 +1,92 @@
+use uuid::Uuid;
+
+/// What clients name in their messages, the zone, party or guild is always the one the player is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatChannelKind {
+    Global,
+    Zone,
+    Party,
+    Guild,
+    Whisper,
+}
+
+/// A channel messages are stored and delivered on
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChatChannel {
+    Global,
+    Zone(String),
+    Party(Uuid),
+    Guild(Uuid),
+    Whisper,
+}
+
+impl ChatChannel {
+    /// Stored with every message, history is looked up by it
+    pub fn key(&self) -> String {
+        match self {
+            ChatChannel::Global => "global".to_string(),
+            ChatChannel::Zone(zone_id) => format!("zone:{}", zone_id),
+            ChatChannel::Party(party_id) => format!("party:{}", party_id),
+            ChatChannel::Guild(guild_id) => format!("guild:{}", guild_id),
+            ChatChannel::Whisper => "whisper".to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> ChatChannelKind {
+        match self {
+            ChatChannel::Global => ChatChannelKind::Global,
+            ChatChannel::Zone(_) => ChatChannelKind::Zone,
+            ChatChannel::Party(_) => ChatChannelKind::Party,
+            ChatChannel::Guild(_) => ChatChannelKind::Guild,
+            ChatChannel::Whisper => ChatChannelKind::Whisper,
+        }
+    }
+}
+
+impl TryFrom<String> for ChatChannel {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.split_once(':') {
+            None if value == "global" => Ok(ChatChannel::Global),
+            None if value == "whisper" => Ok(ChatChannel::Whisper),
+            Some(("zone", zone_id)) if !zone_id.is_empty() => Ok(ChatChannel::Zone(zone_id.to_string())),
+            Some(("party", party_id)) => Uuid::parse_str(party_id)
+                .map(ChatChannel::Party)
+                .map_err(|_| format!("{} is not a valid party channel", value)),
+            Some(("guild", guild_id)) => Uuid::parse_str(guild_id)
+                .map(ChatChannel::Guild)
+                .map_err(|_| format!("{} is not a valid guild channel", value)),
+            _ => Err(format!("{} is not a valid chat channel", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok_eq};
+    use uuid::Uuid;
+    use crate::chat::ChatChannel;
+
+    #[test]
+    fn channel_keys_round_trip() {
+        let channels = vec![
+            ChatChannel::Global,
+            ChatChannel::Zone("old_forest".to_string()),
+            ChatChannel::Party(Uuid::new_v4()),
+            ChatChannel::Guild(Uuid::new_v4()),
+            ChatChannel::Whisper,
+        ];
+        for channel in channels {
+            assert_ok_eq!(ChatChannel::try_from(channel.key()), channel);
+        }
+    }
+
+    #[test]
+    fn malformed_channel_keys_are_rejected() {
+        assert_err!(ChatChannel::try_from("zone:".to_string()));
+        assert_err!(ChatChannel::try_from("party:not-a-uuid".to_string()));
+        assert_err!(ChatChannel::try_from("trade".to_string()));
+    }
+}
@@ -0,0 +1,104 @@
+const SUFFIXES: &[&str] = &["", "s", "es", "ed", "er", "ers", "ing", "y"];
+
+/// Masks filtered words in chat messages. Only whole words count, so `Scunthorpe` is left alone,
+/// but the usual tricks like `$h1t` or `s.h.i.t` and simple inflections are still caught.
+#[derive(Debug, Clone, Default)]
+pub struct WordFilter {
+    words: Vec<String>,
+}
+
+impl WordFilter {
+    pub fn new(words: &[String]) -> Self {
+        let words = words.iter()
+            .map(|w| fold(w))
+            .filter(|w| !w.is_empty())
+            .collect();
+        WordFilter { words }
+    }
+
+    /// Returns the message with every filtered word replaced by `*`, `None` when nothing matched
+    pub fn apply(&self, message: &str) -> Option<String> {
+        let mut filtered = String::with_capacity(message.len());
+        let mut matched = false;
+        let mut rest = message;
+
+        while !rest.is_empty() {
+            let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let (word, tail) = rest.split_at(word_end);
+            // trailing punctuation ends the sentence, it isn't standing in for a letter
+            let core = word.trim_end_matches(['!', '?', '.', ',', ';', ':']);
+            if self.is_filtered(core) {
+                matched = true;
+                filtered.extend(core.chars().map(|_| '*'));
+                filtered.push_str(&word[core.len()..]);
+            } else {
+                filtered.push_str(word);
+            }
+
+            let space_end = tail.find(|c: char| !c.is_whitespace()).unwrap_or(tail.len());
+            let (space, tail) = tail.split_at(space_end);
+            filtered.push_str(space);
+            rest = tail;
+        }
+
+        matched.then_some(filtered)
+    }
+
+    fn is_filtered(&self, word: &str) -> bool {
+        let folded = fold(word);
+        !folded.is_empty() && self.words.iter().any(|w| {
+            folded.strip_prefix(w.as_str()).is_some_and(|suffix| SUFFIXES.contains(&suffix))
+        })
+    }
+}
+
+fn fold(word: &str) -> String {
+    word.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | '!' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '7' => 't',
+            '8' => 'b',
+            other => other,
+        })
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_none, assert_some_eq};
+    use crate::chat::WordFilter;
+
+    fn filter() -> WordFilter {
+        WordFilter::new(&["darn".to_string(), "Heck".to_string()])
+    }
+
+    #[test]
+    fn clean_messages_pass_unchanged() {
+        assert_none!(filter().apply("well met, traveller"));
+        assert_none!(filter().apply(""));
+    }
+
+    #[test]
+    fn filtered_words_are_masked_in_place() {
+        assert_some_eq!(filter().apply("what the heck is that"), "what the **** is that".to_string());
+        assert_some_eq!(filter().apply("HECK!  darn"), "****!  ****".to_string());
+    }
+
+    #[test]
+    fn substitutions_and_inflections_are_caught() {
+        assert_some_eq!(filter().apply("d4rn"), "****".to_string());
+        assert_some_eq!(filter().apply("h.e.c.k."), "*******.".to_string());
+        assert_some_eq!(filter().apply("darned"), "******".to_string());
+    }
+
+    #[test]
+    fn words_merely_containing_a_filtered_word_are_left_alone() {
+        assert_none!(filter().apply("the darnell family of checkers"));
+    }
+}
@@ -0,0 +1,9 @@
+mod channel;
+mod filter;
+mod service;
+mod store;
+
+pub use channel::{ChatChannel, ChatChannelKind};
+pub use filter::WordFilter;
+pub use service::{ChatError, ChatService};
+pub use store::{get_active_mutes, is_blocked, ChatMuteRow};
\ No newline at end of file
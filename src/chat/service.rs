@@ -0,0 +1,475 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Mutex, RwLock};
+use std::time::Instant;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::audit::{record_audit_entry, AuditAction, AuditEntry};
+use crate::authentication::UserId;
+use crate::chat::{ChatChannel, ChatChannelKind, WordFilter};
+use crate::chat::store::{get_active_mute, get_blocked_display_names, get_channel_history, get_chat_message, get_users_blocking, get_whisper_history, remove_chat_block, remove_chat_mute, store_chat_block, store_chat_message, store_chat_mute, ChatMessageRow};
+use crate::configuration::ChatSettings;
+use crate::domain::Profile;
+use crate::gateway::{ChatLine, ClientError, ConnectionRegistry, ErrorCode, RateLimiter, ServerMessage};
+use crate::store::get_profile_by_display_name;
+use crate::store::get_profile_by_user_id;
+use crate::utils::error_chain_fmt;
+
+#[derive(thiserror::Error)]
+pub enum ChatError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("You are sending messages too fast")]
+    RateLimited,
+    #[error("You are muted until {0}")]
+    Muted(DateTime<Utc>),
+    #[error("Pick a display name on your profile before chatting")]
+    NoDisplayName,
+    #[error("You are not in a {0}")]
+    NotInChannel(&'static str),
+    #[error("There is no player called {0}")]
+    PlayerNotFound(String),
+    #[error("{0} is not online")]
+    PlayerOffline(String),
+    #[error("{0} is not accepting whispers from you")]
+    WhisperRefused(String),
+    #[error("That message doesn't exist")]
+    MessageNotFound,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for ChatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for ChatError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ChatError::ValidationError(_) => ErrorCode::InvalidMessage,
+            ChatError::RateLimited => ErrorCode::RateLimited,
+            ChatError::Muted(_) => ErrorCode::Muted,
+            ChatError::NoDisplayName | ChatError::NotInChannel(_) | ChatError::WhisperRefused(_) => ErrorCode::Forbidden,
+            ChatError::PlayerNotFound(_) | ChatError::PlayerOffline(_) | ChatError::MessageNotFound => ErrorCode::NotFound,
+            ChatError::UnexpectedError(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+/// Which zone, party and guild channels a player hears, kept up to date by the systems that own them
+#[derive(Debug, Clone, Default)]
+struct ChatMembership {
+    zone: Option<String>,
+    party: Option<Uuid>,
+    guild: Option<Uuid>,
+}
+
+/// A message that made it through every check, ready to be stored
+struct OutgoingMessage {
+    sender_id: Uuid,
+    sender_name: String,
+    body: String,
+    original: Option<String>,
+}
+
+pub struct ChatService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    settings: ChatSettings,
+    filter: WordFilter,
+    limiters: Mutex<HashMap<UserId, RateLimiter>>,
+    memberships: RwLock<HashMap<UserId, ChatMembership>>,
+}
+
+impl ChatService {
+    pub fn new(pool: PgPool, registry: ConnectionRegistry, settings: ChatSettings) -> Self {
+        let filter = WordFilter::new(&settings.filtered_words);
+        ChatService {
+            pool,
+            registry,
+            settings,
+            filter,
+            limiters: Mutex::new(HashMap::new()),
+            memberships: RwLock::new(HashMap::new()),
+        }
+    }
+
+    //region Membership
+    pub fn set_zone(&self, user_id: UserId, zone_id: Option<String>) {
+        let mut memberships = self.memberships.write().expect("Chat membership lock poisoned");
+        memberships.entry(user_id).or_default().zone = zone_id;
+    }
+
+    pub fn set_party(&self, user_id: UserId, party_id: Option<Uuid>) {
+        let mut memberships = self.memberships.write().expect("Chat membership lock poisoned");
+        memberships.entry(user_id).or_default().party = party_id;
+    }
+
+    pub fn set_guild(&self, user_id: UserId, guild_id: Option<Uuid>) {
+        let mut memberships = self.memberships.write().expect("Chat membership lock poisoned");
+        memberships.entry(user_id).or_default().guild = guild_id;
+    }
+
+    /// Drops the rate limiter of players that went offline, memberships stay with their owners
+    pub fn disconnected(&self, user_id: UserId) {
+        if !self.registry.is_online(user_id) {
+            self.limiters.lock().expect("Chat rate limiter lock poisoned").remove(&user_id);
+        }
+    }
+
+    fn membership(&self, user_id: UserId) -> ChatMembership {
+        self.memberships
+            .read()
+            .expect("Chat membership lock poisoned")
+            .get(&user_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn resolve_channel(&self, user_id: UserId, kind: ChatChannelKind) -> Result<ChatChannel, ChatError> {
+        let membership = self.membership(user_id);
+        match kind {
+            ChatChannelKind::Global => Ok(ChatChannel::Global),
+            ChatChannelKind::Zone => membership.zone
+                .map(ChatChannel::Zone)
+                .ok_or(ChatError::NotInChannel("zone")),
+            ChatChannelKind::Party => membership.party
+                .map(ChatChannel::Party)
+                .ok_or(ChatError::NotInChannel("party")),
+            ChatChannelKind::Guild => membership.guild
+                .map(ChatChannel::Guild)
+                .ok_or(ChatError::NotInChannel("guild")),
+            ChatChannelKind::Whisper => Err(ChatError::ValidationError("Whispers need a recipient".to_string())),
+        }
+    }
+
+    fn listeners(&self, channel: &ChatChannel) -> Vec<UserId> {
+        match channel {
+            ChatChannel::Global => self.registry.online_users(),
+            ChatChannel::Zone(zone_id) => self.members(|m| m.zone.as_ref() == Some(zone_id)),
+            ChatChannel::Party(party_id) => self.members(|m| m.party == Some(*party_id)),
+            ChatChannel::Guild(guild_id) => self.members(|m| m.guild == Some(*guild_id)),
+            ChatChannel::Whisper => vec![],
+        }
+    }
+
+    fn members(&self, is_member: impl Fn(&ChatMembership) -> bool) -> Vec<UserId> {
+        self.memberships
+            .read()
+            .expect("Chat membership lock poisoned")
+            .iter()
+            .filter(|(_, m)| is_member(m))
+            .map(|(user_id, _)| *user_id)
+            .collect()
+    }
+    //endregion
+
+    //region Messages
+    #[tracing::instrument(
+    name = "Send chat message",
+    skip(self, body),
+    fields(sender_id = % sender)
+    )]
+    pub async fn send(&self, sender: UserId, kind: ChatChannelKind, body: &str) -> Result<(), ChatError> {
+        let channel = self.resolve_channel(sender, kind)?;
+        let message = self.prepare(sender, body).await?;
+        let line = self.store(&channel, message, None).await?;
+
+        let blocking: HashSet<Uuid> = get_users_blocking(&self.pool, *sender).await?.into_iter().collect();
+        let chat_message = ServerMessage::ChatMessage(line);
+        for listener in self.listeners(&channel) {
+            if !blocking.contains(&listener) {
+                self.registry.send_to_user(listener, &chat_message);
+            }
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(
+    name = "Send whisper",
+    skip(self, body),
+    fields(sender_id = % sender)
+    )]
+    pub async fn whisper(&self, sender: UserId, to: &str, body: &str) -> Result<(), ChatError> {
+        let recipient = self.find_player(to).await?;
+        if recipient.user_id == *sender {
+            return Err(ChatError::ValidationError("You can't whisper to yourself".to_string()));
+        }
+        let recipient_id = UserId::from(recipient.user_id);
+        let recipient_name = recipient.display_name.to_string();
+        if get_users_blocking(&self.pool, *sender).await?.contains(&recipient.user_id) {
+            return Err(ChatError::WhisperRefused(recipient_name));
+        }
+        if !self.registry.is_online(recipient_id) {
+            return Err(ChatError::PlayerOffline(recipient_name));
+        }
+
+        let message = self.prepare(sender, body).await?;
+        let line = self.store(&ChatChannel::Whisper, message, Some((recipient.user_id, recipient_name))).await?;
+
+        let chat_message = ServerMessage::ChatMessage(line);
+        self.registry.send_to_user(recipient_id, &chat_message);
+        self.registry.send_to_user(sender, &chat_message);
+        Ok(())
+    }
+
+    /// Length, rate limit and mute checks, then the word filter
+    async fn prepare(&self, sender: UserId, body: &str) -> Result<OutgoingMessage, ChatError> {
+        let body = body.trim();
+        if body.is_empty() {
+            return Err(ChatError::ValidationError("Message can't be empty".to_string()));
+        }
+        if body.chars().count() > self.settings.max_message_length {
+            return Err(ChatError::ValidationError(format!(
+                "Messages can be at most {} characters long",
+                self.settings.max_message_length
+            )));
+        }
+
+        let allowed = self.limiters
+            .lock()
+            .expect("Chat rate limiter lock poisoned")
+            .entry(sender)
+            .or_insert_with(|| RateLimiter::new(self.settings.message_burst, self.settings.messages_per_second, Instant::now()))
+            .try_acquire(Instant::now());
+        if !allowed {
+            return Err(ChatError::RateLimited);
+        }
+
+        if let Some(muted_until) = get_active_mute(&self.pool, *sender).await? {
+            return Err(ChatError::Muted(muted_until));
+        }
+
+        let sender_name = get_profile_by_user_id(&self.pool, *sender)
+            .await?
+            .ok_or(ChatError::NoDisplayName)?
+            .display_name
+            .to_string();
+
+        Ok(match self.filter.apply(body) {
+            Some(filtered) => OutgoingMessage {
+                sender_id: *sender,
+                sender_name,
+                body: filtered,
+                original: Some(body.to_string()),
+            },
+            None => OutgoingMessage {
+                sender_id: *sender,
+                sender_name,
+                body: body.to_string(),
+                original: None,
+            },
+        })
+    }
+
+    /// Filtered messages are stored masked, the original only ends up in the audit log
+    async fn store(
+        &self,
+        channel: &ChatChannel,
+        message: OutgoingMessage,
+        recipient: Option<(Uuid, String)>,
+    ) -> Result<ChatLine, ChatError> {
+        let (recipient_id, recipient_name) = recipient.unzip();
+        let row = ChatMessageRow {
+            id: Uuid::new_v4(),
+            channel: channel.key(),
+            sender_id: message.sender_id,
+            sender_name: message.sender_name,
+            recipient_id,
+            recipient_name,
+            body: message.body,
+            filtered: message.original.is_some(),
+            created_at: Utc::now(),
+        };
+
+        let mut tx = self.pool.begin().await.context("Failed to begin chat transaction")?;
+        store_chat_message(&mut tx, &row).await?;
+        if let Some(original) = message.original {
+            let entry = AuditEntry {
+                actor_id: None,
+                action: AuditAction::ChatMessageFiltered,
+                subject_id: Some(row.id),
+                details: format!("{} on {}: {}", row.sender_name, row.channel, original),
+            };
+            record_audit_entry(&mut tx, &entry).await?;
+        }
+        tx.commit().await.context("Failed to commit chat transaction")?;
+
+        Ok(to_chat_line(row, channel.kind()))
+    }
+
+    /// One page of history, oldest message first
+    #[tracing::instrument(
+    name = "Get chat history",
+    skip(self),
+    fields(reader_id = % reader)
+    )]
+    pub async fn history(
+        &self,
+        reader: UserId,
+        kind: ChatChannelKind,
+        with: Option<&str>,
+        before: Option<Uuid>,
+    ) -> Result<(Vec<ChatLine>, bool), ChatError> {
+        let page_size = self.settings.history_page_size;
+        let mut rows = match kind {
+            ChatChannelKind::Whisper => {
+                let with = with.ok_or_else(|| ChatError::ValidationError("Whisper history needs a player".to_string()))?;
+                let other = self.find_player(with).await?;
+                get_whisper_history(&self.pool, *reader, other.user_id, before, page_size + 1).await?
+            }
+            kind => {
+                let channel = self.resolve_channel(reader, kind)?;
+                get_channel_history(&self.pool, &channel.key(), *reader, before, page_size + 1).await?
+            }
+        };
+
+        let more = rows.len() as i64 > page_size;
+        rows.truncate(page_size as usize);
+        let lines = rows.into_iter()
+            .rev()
+            .map(|row| to_chat_line(row, kind))
+            .collect();
+        Ok((lines, more))
+    }
+    //endregion
+
+    //region Moderation
+    #[tracing::instrument(
+    name = "Report chat message",
+    skip(self, reason),
+    fields(reporter_id = % reporter)
+    )]
+    pub async fn report(&self, reporter: UserId, message_id: Uuid, reason: &str) -> Result<(), ChatError> {
+        let message = get_chat_message(&self.pool, message_id)
+            .await?
+            .ok_or(ChatError::MessageNotFound)?;
+        if message.sender_id == *reporter {
+            return Err(ChatError::ValidationError("You can't report your own message".to_string()));
+        }
+        // whispers are only visible to the two players involved
+        if message.recipient_id.is_some_and(|recipient| recipient != *reporter) {
+            return Err(ChatError::MessageNotFound);
+        }
+
+        let entry = AuditEntry {
+            actor_id: Some(*reporter),
+            action: AuditAction::ChatMessageReported,
+            subject_id: Some(message.id),
+            details: format!("{} wrote \"{}\", reason: {}", message.sender_name, message.body, reason.trim()),
+        };
+        record_audit_entry(&self.pool, &entry).await?;
+        Ok(())
+    }
+
+    /// Works inside the caller's transaction, so whatever else blocking undoes goes with it
+    pub async fn block(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        user_id: UserId,
+        display_name: &str,
+    ) -> Result<String, ChatError> {
+        let blocked = self.find_player(display_name).await?;
+        if blocked.user_id == *user_id {
+            return Err(ChatError::ValidationError("You can't block yourself".to_string()));
+        }
+        store_chat_block(tx, *user_id, blocked.user_id).await?;
+        Ok(blocked.display_name.to_string())
+    }
+
+    pub async fn unblock(&self, user_id: UserId, display_name: &str) -> Result<String, ChatError> {
+        let blocked = self.find_player(display_name).await?;
+        remove_chat_block(&self.pool, *user_id, blocked.user_id).await?;
+        Ok(blocked.display_name.to_string())
+    }
+
+    pub async fn blocked(&self, user_id: UserId) -> Result<Vec<String>, ChatError> {
+        Ok(get_blocked_display_names(&self.pool, *user_id).await?)
+    }
+
+    /// Replaces any mute the player already had, they are told right away if online
+    #[tracing::instrument(
+    name = "Mute player",
+    skip(self, reason)
+    )]
+    pub async fn mute(
+        &self,
+        moderator_id: Uuid,
+        display_name: &str,
+        duration: chrono::Duration,
+        reason: &str,
+    ) -> Result<DateTime<Utc>, ChatError> {
+        if duration <= chrono::Duration::zero() {
+            return Err(ChatError::ValidationError("Mutes must last at least a minute".to_string()));
+        }
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(ChatError::ValidationError("A reason is required".to_string()));
+        }
+        let player = self.find_player(display_name).await?;
+        let muted_until = Utc::now() + duration;
+
+        let mut tx = self.pool.begin().await.context("Failed to begin mute transaction")?;
+        store_chat_mute(&mut tx, player.user_id, muted_until, reason, moderator_id).await?;
+        let entry = AuditEntry {
+            actor_id: Some(moderator_id),
+            action: AuditAction::ChatUserMuted,
+            subject_id: Some(player.user_id),
+            details: format!("{} muted until {}: {}", player.display_name, muted_until, reason),
+        };
+        record_audit_entry(&mut tx, &entry).await?;
+        tx.commit().await.context("Failed to commit mute transaction")?;
+
+        self.registry.send_to_user(
+            UserId::from(player.user_id),
+            &ServerMessage::Notice { message: format!("You have been muted until {}: {}", muted_until, reason) },
+        );
+        Ok(muted_until)
+    }
+
+    /// Returns whether the player was muted at all
+    #[tracing::instrument(
+    name = "Unmute player",
+    skip(self)
+    )]
+    pub async fn unmute(&self, moderator_id: Uuid, display_name: &str) -> Result<bool, ChatError> {
+        let player = self.find_player(display_name).await?;
+
+        let mut tx = self.pool.begin().await.context("Failed to begin unmute transaction")?;
+        if !remove_chat_mute(&mut tx, player.user_id).await? {
+            return Ok(false);
+        }
+        let entry = AuditEntry {
+            actor_id: Some(moderator_id),
+            action: AuditAction::ChatUserUnmuted,
+            subject_id: Some(player.user_id),
+            details: format!("{} unmuted", player.display_name),
+        };
+        record_audit_entry(&mut tx, &entry).await?;
+        tx.commit().await.context("Failed to commit unmute transaction")?;
+        Ok(true)
+    }
+    //endregion
+
+    async fn find_player(&self, display_name: &str) -> Result<Profile, ChatError> {
+        let display_name = display_name.trim();
+        get_profile_by_display_name(&self.pool, display_name)
+            .await?
+            .ok_or_else(|| ChatError::PlayerNotFound(display_name.to_string()))
+    }
+}
+
+fn to_chat_line(row: ChatMessageRow, channel: ChatChannelKind) -> ChatLine {
+    ChatLine {
+        id: row.id,
+        channel,
+        sender: row.sender_name,
+        recipient: row.recipient_name,
+        body: row.body,
+        sent_at: row.created_at,
+    }
+}
@@ -0,0 +1,368 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+
+pub struct ChatMessageRow {
+    pub id: Uuid,
+    pub channel: String,
+    pub sender_id: Uuid,
+    pub sender_name: String,
+    pub recipient_id: Option<Uuid>,
+    pub recipient_name: Option<String>,
+    pub body: String,
+    pub filtered: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(
+name = "Store chat message",
+skip(executor, message),
+fields(message_id = % message.id, channel = % message.channel)
+)]
+pub async fn store_chat_message(
+    executor: impl PgExecutor<'_>,
+    message: &ChatMessageRow,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO chat_messages (id, channel, sender_id, sender_name, recipient_id, recipient_name, body, filtered, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+        message.id,
+        message.channel,
+        message.sender_id,
+        message.sender_name,
+        message.recipient_id,
+        message.recipient_name,
+        message.body,
+        message.filtered,
+        message.created_at
+    )
+        .execute(executor)
+        .await
+        .context("Failed to store chat message")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Get chat message",
+skip(executor)
+)]
+pub async fn get_chat_message(
+    executor: impl PgExecutor<'_>,
+    message_id: Uuid,
+) -> Result<Option<ChatMessageRow>, anyhow::Error> {
+    sqlx::query_as!(
+        ChatMessageRow,
+        r#"
+        SELECT id, channel, sender_id, sender_name, recipient_id, recipient_name, body, filtered, created_at
+        FROM chat_messages
+        WHERE id = $1
+        "#,
+        message_id
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch chat message")
+}
+
+/// Newest first, messages from players `reader_id` blocked are left out.
+/// Pages are cut at `before`, a message id, so new messages don't shift them.
+#[tracing::instrument(
+name = "Get channel history",
+skip(executor)
+)]
+pub async fn get_channel_history(
+    executor: impl PgExecutor<'_>,
+    channel: &str,
+    reader_id: Uuid,
+    before: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<ChatMessageRow>, anyhow::Error> {
+    sqlx::query_as!(
+        ChatMessageRow,
+        r#"
+        SELECT id, channel, sender_id, sender_name, recipient_id, recipient_name, body, filtered, created_at
+        FROM chat_messages
+        WHERE channel = $1
+          AND sender_id NOT IN (SELECT blocked_user_id FROM chat_blocks WHERE user_id = $2)
+          AND ($3::uuid IS NULL OR (created_at, id) < (SELECT created_at, id FROM chat_messages WHERE id = $3))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4
+        "#,
+        channel,
+        reader_id,
+        before,
+        limit
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch channel history")
+}
+
+/// Both directions of the conversation between two players, newest first
+#[tracing::instrument(
+name = "Get whisper history",
+skip(executor)
+)]
+pub async fn get_whisper_history(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    other_id: Uuid,
+    before: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<ChatMessageRow>, anyhow::Error> {
+    sqlx::query_as!(
+        ChatMessageRow,
+        r#"
+        SELECT id, channel, sender_id, sender_name, recipient_id, recipient_name, body, filtered, created_at
+        FROM chat_messages
+        WHERE recipient_id IS NOT NULL
+          AND ((sender_id = $1 AND recipient_id = $2) OR (sender_id = $2 AND recipient_id = $1))
+          AND ($3::uuid IS NULL OR (created_at, id) < (SELECT created_at, id FROM chat_messages WHERE id = $3))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4
+        "#,
+        user_id,
+        other_id,
+        before,
+        limit
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch whisper history")
+}
+
+#[tracing::instrument(
+name = "Store chat block",
+skip(executor)
+)]
+pub async fn store_chat_block(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    blocked_user_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO chat_blocks (user_id, blocked_user_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        blocked_user_id
+    )
+        .execute(executor)
+        .await
+        .context("Failed to store chat block")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Remove chat block",
+skip(executor)
+)]
+pub async fn remove_chat_block(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    blocked_user_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM chat_blocks
+        WHERE user_id = $1 AND blocked_user_id = $2
+        "#,
+        user_id,
+        blocked_user_id
+    )
+        .execute(executor)
+        .await
+        .context("Failed to remove chat block")?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Display names of everyone `user_id` blocked
+#[tracing::instrument(
+name = "Get blocked display names",
+skip(executor)
+)]
+pub async fn get_blocked_display_names(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<Vec<String>, anyhow::Error> {
+    let names = sqlx::query!(
+        r#"
+        SELECT p.display_name
+        FROM chat_blocks b
+        JOIN profiles p ON p.user_id = b.blocked_user_id
+        WHERE b.user_id = $1
+        ORDER BY lower(p.display_name)
+        "#,
+        user_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch blocked players")?
+        .into_iter()
+        .map(|r| r.display_name)
+        .collect();
+    Ok(names)
+}
+
+/// Everyone who doesn't want to hear from `sender_id`
+#[tracing::instrument(
+name = "Get users blocking sender",
+skip(executor)
+)]
+pub async fn get_users_blocking(
+    executor: impl PgExecutor<'_>,
+    sender_id: Uuid,
+) -> Result<Vec<Uuid>, anyhow::Error> {
+    let users = sqlx::query!(
+        r#"
+        SELECT user_id
+        FROM chat_blocks
+        WHERE blocked_user_id = $1
+        "#,
+        sender_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch blocking users")?
+        .into_iter()
+        .map(|r| r.user_id)
+        .collect();
+    Ok(users)
+}
+
+/// Whether `user_id` blocked `blocked_user_id`, blocks only ever work in one direction
+#[tracing::instrument(
+name = "Check chat block",
+skip(executor)
+)]
+pub async fn is_blocked(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    blocked_user_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let blocked = sqlx::query!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM chat_blocks WHERE user_id = $1 AND blocked_user_id = $2
+        ) AS "blocked!"
+        "#,
+        user_id,
+        blocked_user_id
+    )
+        .fetch_one(executor)
+        .await
+        .context("Failed to check chat block")?
+        .blocked;
+    Ok(blocked)
+}
+
+pub struct ChatMuteRow {
+    pub user_id: Uuid,
+    pub display_name: Option<String>,
+    pub muted_until: DateTime<Utc>,
+    pub reason: String,
+}
+
+#[tracing::instrument(
+name = "Get active chat mute",
+skip(executor)
+)]
+pub async fn get_active_mute(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+    let muted_until = sqlx::query!(
+        r#"
+        SELECT muted_until
+        FROM chat_mutes
+        WHERE user_id = $1 AND muted_until > now()
+        "#,
+        user_id
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch chat mute")?
+        .map(|r| r.muted_until);
+    Ok(muted_until)
+}
+
+#[tracing::instrument(
+name = "Get active chat mutes",
+skip(executor)
+)]
+pub async fn get_active_mutes(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<ChatMuteRow>, anyhow::Error> {
+    sqlx::query_as!(
+        ChatMuteRow,
+        r#"
+        SELECT m.user_id, p.display_name as "display_name?", m.muted_until, m.reason
+        FROM chat_mutes m
+        LEFT JOIN profiles p ON p.user_id = m.user_id
+        WHERE m.muted_until > now()
+        ORDER BY m.muted_until
+        "#
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch chat mutes")
+}
+
+/// A new mute replaces whatever mute the player had before
+#[tracing::instrument(
+name = "Store chat mute",
+skip(executor, reason)
+)]
+pub async fn store_chat_mute(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    muted_until: DateTime<Utc>,
+    reason: &str,
+    muted_by: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO chat_mutes (user_id, muted_until, reason, muted_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO UPDATE
+        SET muted_until = EXCLUDED.muted_until,
+            reason = EXCLUDED.reason,
+            muted_by = EXCLUDED.muted_by,
+            created_at = now()
+        "#,
+        user_id,
+        muted_until,
+        reason,
+        muted_by
+    )
+        .execute(executor)
+        .await
+        .context("Failed to store chat mute")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Remove chat mute",
+skip(executor)
+)]
+pub async fn remove_chat_mute(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM chat_mutes
+        WHERE user_id = $1 AND muted_until > now()
+        "#,
+        user_id
+    )
+        .execute(executor)
+        .await
+        .context("Failed to remove chat mute")?;
+    Ok(result.rows_affected() > 0)
+}
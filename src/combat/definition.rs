@@ -0,0 +1,265 @@
+use crate::characters::Attributes;
+use crate::game_data::is_valid_id;
+
+/// `base + per_level * level + strength * STR + ...`, every term is optional.
+/// `variance` spreads the result evenly by up to that fraction either way, 0.1 is ±10%.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Formula {
+    pub base: f64,
+    pub per_level: f64,
+    pub strength: f64,
+    pub dexterity: f64,
+    pub intelligence: f64,
+    pub vitality: f64,
+    pub variance: f64,
+}
+
+impl Formula {
+    /// The value before variance
+    pub fn evaluate(&self, level: i32, attributes: &Attributes) -> f64 {
+        self.base
+            + self.per_level * level as f64
+            + self.strength * attributes.strength as f64
+            + self.dexterity * attributes.dexterity as f64
+            + self.intelligence * attributes.intelligence as f64
+            + self.vitality * attributes.vitality as f64
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(0.0..1.0).contains(&self.variance) {
+            return Err(format!("variance has to be at least 0 and below 1, got {}", self.variance));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DamageKind {
+    /// Reduced by the target's vitality
+    #[default]
+    Physical,
+    /// Reduced by the target's intelligence
+    Magical,
+    /// Never reduced
+    True,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AbilityTarget {
+    Enemy,
+    /// Anyone on the user's side, the user included
+    Ally,
+    #[serde(rename = "self")]
+    User,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct EffectApplication {
+    pub effect: String,
+    #[serde(default = "always")]
+    pub chance: f64,
+    /// Lands on the user of the ability instead of its target
+    #[serde(default)]
+    pub on_self: bool,
+}
+
+fn always() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AbilityDefinition {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub target: AbilityTarget,
+    /// Own turns to wait after using it, 0 can be used every turn
+    #[serde(default)]
+    pub cooldown: u32,
+    #[serde(default)]
+    pub damage: Option<Formula>,
+    #[serde(default)]
+    pub damage_kind: DamageKind,
+    #[serde(default)]
+    pub heal: Option<Formula>,
+    /// Added to the dexterity based critical chance of the user
+    #[serde(default)]
+    pub critical_chance: f64,
+    #[serde(default)]
+    pub effects: Vec<EffectApplication>,
+}
+
+impl AbilityDefinition {
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_id(&self.id) {
+            return Err(format!("{:?} is not a valid ability id", self.id));
+        }
+        if self.name.trim().is_empty() {
+            return Err(format!("Ability {} has no name", self.id));
+        }
+        if self.damage.is_none() && self.heal.is_none() && self.effects.is_empty() {
+            return Err(format!("Ability {} does nothing", self.id));
+        }
+        if self.damage.is_some() && self.target != AbilityTarget::Enemy {
+            return Err(format!("Ability {} damages but doesn't target an enemy", self.id));
+        }
+        for formula in self.damage.iter().chain(self.heal.iter()) {
+            formula.validate().map_err(|e| format!("Ability {}: {}", self.id, e))?;
+        }
+        if !(0.0..=1.0).contains(&self.critical_chance) {
+            return Err(format!("Ability {} has a critical chance outside of 0 to 1", self.id));
+        }
+        if let Some(application) = self.effects.iter().find(|a| !(0.0..=1.0).contains(&a.chance)) {
+            return Err(format!("Ability {} applies {} with a chance outside of 0 to 1", self.id, application.effect));
+        }
+        Ok(())
+    }
+}
+
+/// Lasts a number of the affected combatant's turns and ticks at the start of each of them
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StatusEffectDefinition {
+    pub id: String,
+    pub name: String,
+    pub duration: u32,
+    /// Per turn, from the stats of whoever applied it, never reduced
+    #[serde(default)]
+    pub damage: Option<Formula>,
+    #[serde(default)]
+    pub heal: Option<Formula>,
+    /// Skips the affected combatant's turns
+    #[serde(default)]
+    pub stun: bool,
+    #[serde(default = "unchanged")]
+    pub damage_dealt: f64,
+    #[serde(default = "unchanged")]
+    pub damage_taken: f64,
+}
+
+fn unchanged() -> f64 {
+    1.0
+}
+
+impl StatusEffectDefinition {
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_id(&self.id) {
+            return Err(format!("{:?} is not a valid status effect id", self.id));
+        }
+        if self.name.trim().is_empty() {
+            return Err(format!("Status effect {} has no name", self.id));
+        }
+        if self.duration == 0 {
+            return Err(format!("Status effect {} needs to last at least one turn", self.id));
+        }
+        if self.damage_dealt < 0.0 || self.damage_taken < 0.0 {
+            return Err(format!("Status effect {} can't scale damage below zero", self.id));
+        }
+        for formula in self.damage.iter().chain(self.heal.iter()) {
+            formula.validate().map_err(|e| format!("Status effect {}: {}", self.id, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Which abilities characters of a class fight with
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct ClassAbilities {
+    pub class: String,
+    pub abilities: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct CreatureDefinition {
+    pub id: String,
+    pub name: String,
+    pub level: i32,
+    pub attributes: Attributes,
+    /// Overrides the health characters get from vitality and level
+    #[serde(default)]
+    pub health: Option<i32>,
+    pub abilities: Vec<String>,
+    /// Zones where players can pick a fight with it
+    #[serde(default)]
+    pub zones: Vec<String>,
+}
+
+impl CreatureDefinition {
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_id(&self.id) {
+            return Err(format!("{:?} is not a valid creature id", self.id));
+        }
+        if self.name.trim().is_empty() {
+            return Err(format!("Creature {} has no name", self.id));
+        }
+        if self.level < 1 {
+            return Err(format!("Creature {} needs a level of at least 1", self.id));
+        }
+        if self.health.is_some_and(|h| h < 1) {
+            return Err(format!("Creature {} needs at least 1 health", self.id));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use crate::characters::Attributes;
+    use crate::combat::{AbilityDefinition, AbilityTarget, DamageKind, EffectApplication, Formula};
+
+    fn ability() -> AbilityDefinition {
+        AbilityDefinition {
+            id: "strike".to_string(),
+            name: "Strike".to_string(),
+            description: String::new(),
+            target: AbilityTarget::Enemy,
+            cooldown: 0,
+            damage: Some(Formula { base: 2.0, strength: 1.0, ..Formula::default() }),
+            damage_kind: DamageKind::Physical,
+            heal: None,
+            critical_chance: 0.0,
+            effects: vec![],
+        }
+    }
+
+    #[test]
+    fn formulas_add_up_every_term() {
+        let formula = Formula { base: 1.0, per_level: 2.0, strength: 0.5, intelligence: 1.5, ..Formula::default() };
+        let attributes = Attributes { strength: 10, dexterity: 7, intelligence: 4, vitality: 3 };
+        assert_eq!(1.0 + 6.0 + 5.0 + 6.0, formula.evaluate(3, &attributes));
+    }
+
+    #[test]
+    fn plain_attack_is_valid() {
+        assert_ok!(ability().validate());
+    }
+
+    #[test]
+    fn abilities_have_to_do_something() {
+        let mut definition = ability();
+        definition.damage = None;
+        assert_err!(definition.validate());
+    }
+
+    #[test]
+    fn damage_is_only_dealt_to_enemies() {
+        let mut definition = ability();
+        definition.target = AbilityTarget::Ally;
+        assert_err!(definition.validate());
+    }
+
+    #[test]
+    fn chances_are_between_zero_and_one() {
+        let mut definition = ability();
+        definition.effects.push(EffectApplication { effect: "bleeding".to_string(), chance: 1.5, on_self: false });
+        assert_err!(definition.validate());
+
+        let mut definition = ability();
+        definition.damage.as_mut().unwrap().variance = 1.0;
+        assert_err!(definition.validate());
+    }
+}
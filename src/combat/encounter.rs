@@ -0,0 +1,747 @@
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use uuid::Uuid;
+use crate::characters::Attributes;
+use crate::combat::{AbilityDefinition, AbilityTarget, DamageKind, Formula, StatusEffectDefinition};
+use crate::utils::error_chain_fmt;
+
+const CRITICAL_MULTIPLIER: f64 = 1.5;
+/// Critical chance every point of dexterity adds
+const CRITICAL_PER_DEXTERITY: f64 = 0.005;
+/// Defense is twice the defending attribute, `defense / (defense + MITIGATION_SCALE)` of the damage is absorbed
+const MITIGATION_SCALE: f64 = 40.0;
+
+/// Everyone taking part, as they were when the fight started
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Combatant {
+    pub name: String,
+    /// Combatants on the same side fight together, the last side standing wins
+    pub side: usize,
+    pub level: i32,
+    pub attributes: Attributes,
+    pub max_health: i32,
+    /// In the order the AI prefers them
+    pub abilities: Vec<String>,
+    #[serde(default)]
+    pub character_id: Option<Uuid>,
+    #[serde(default)]
+    pub creature_id: Option<String>,
+}
+
+/// A fight is fully described by its setup, seed and actions. The setup carries copies of every
+/// ability and effect used, so changing the data files later doesn't change how old fights replay.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CombatSetup {
+    pub combatants: Vec<Combatant>,
+    pub abilities: BTreeMap<String, AbilityDefinition>,
+    pub effects: BTreeMap<String, StatusEffectDefinition>,
+    /// Ends in a draw when nobody has won by then
+    pub max_rounds: u32,
+}
+
+impl CombatSetup {
+    fn validate(&self) -> Result<(), String> {
+        let mut sides: Vec<usize> = self.combatants.iter().map(|c| c.side).collect();
+        sides.sort_unstable();
+        sides.dedup();
+        if sides.len() < 2 {
+            return Err("A fight needs at least two sides".to_string());
+        }
+        if self.max_rounds == 0 {
+            return Err("A fight needs at least one round".to_string());
+        }
+        for combatant in &self.combatants {
+            if combatant.max_health < 1 {
+                return Err(format!("{} has no health", combatant.name));
+            }
+            if combatant.abilities.is_empty() {
+                return Err(format!("{} has no abilities", combatant.name));
+            }
+            if let Some(ability) = combatant.abilities.iter().find(|a| !self.abilities.contains_key(*a)) {
+                return Err(format!("{} has unknown ability {}", combatant.name, ability));
+            }
+        }
+        for ability in self.abilities.values() {
+            if let Some(application) = ability.effects.iter().find(|a| !self.effects.contains_key(&a.effect)) {
+                return Err(format!("Ability {} applies unknown effect {}", ability.id, application.effect));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `actor` and `target` are indexes into the setup's combatants
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CombatAction {
+    pub actor: usize,
+    pub ability: String,
+    pub target: usize,
+}
+
+/// What happened, in order. Combatants are referred to by index.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CombatEvent {
+    RoundStarted {
+        round: u32,
+    },
+    /// `actor` has to act next
+    TurnStarted {
+        actor: usize,
+    },
+    AbilityUsed {
+        actor: usize,
+        ability: String,
+        target: usize,
+    },
+    /// `effect` is set for damage over time
+    Damaged {
+        target: usize,
+        amount: i32,
+        critical: bool,
+        effect: Option<String>,
+        health: i32,
+    },
+    Healed {
+        target: usize,
+        amount: i32,
+        effect: Option<String>,
+        health: i32,
+    },
+    EffectApplied {
+        target: usize,
+        effect: String,
+        turns: u32,
+    },
+    EffectExpired {
+        target: usize,
+        effect: String,
+    },
+    /// The turn is skipped
+    Stunned {
+        actor: usize,
+    },
+    Defeated {
+        target: usize,
+    },
+    /// `winner` is the winning side, nobody wins when the rounds run out
+    Ended {
+        winner: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CombatOutcome {
+    pub winner: Option<usize>,
+    pub rounds: u32,
+}
+
+#[derive(thiserror::Error, PartialEq, Eq)]
+pub enum ActionError {
+    #[error("The fight is over")]
+    Finished,
+    #[error("It's not your turn")]
+    NotYourTurn,
+    #[error("You don't know {0}")]
+    UnknownAbility(String),
+    #[error("{0} is not ready yet")]
+    OnCooldown(String),
+    #[error("That is not a valid target")]
+    InvalidTarget,
+}
+
+impl Debug for ActionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ActiveEffect {
+    id: String,
+    remaining: u32,
+    /// Worked out from the stats of whoever applied it
+    damage: i32,
+    heal: i32,
+}
+
+#[derive(Debug, Clone)]
+struct CombatantState {
+    health: i32,
+    turns_taken: u32,
+    /// Ability id to the first own turn it can be used again
+    ready_at: BTreeMap<String, u32>,
+    effects: Vec<ActiveEffect>,
+}
+
+/// A turn based fight. Turn order is by dexterity, then by position in the setup. All randomness
+/// comes from one `StdRng` seeded up front, so the same setup, seed and actions always play out the
+/// same way on the same build, which is what `replay` relies on.
+#[derive(Debug)]
+pub struct Encounter {
+    setup: CombatSetup,
+    seed: u64,
+    rng: StdRng,
+    states: Vec<CombatantState>,
+    order: Vec<usize>,
+    round: u32,
+    turn: usize,
+    actions: Vec<CombatAction>,
+    log: Vec<CombatEvent>,
+    outcome: Option<CombatOutcome>,
+}
+
+impl Encounter {
+    pub fn new(setup: CombatSetup, seed: u64) -> Result<Self, String> {
+        setup.validate()?;
+        let states = setup.combatants.iter()
+            .map(|c| CombatantState { health: c.max_health, turns_taken: 0, ready_at: BTreeMap::new(), effects: vec![] })
+            .collect();
+        let mut order: Vec<usize> = (0..setup.combatants.len()).collect();
+        order.sort_by_key(|i| std::cmp::Reverse(setup.combatants[*i].attributes.dexterity));
+
+        let mut encounter = Encounter {
+            setup,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            states,
+            order,
+            round: 1,
+            turn: 0,
+            actions: vec![],
+            log: vec![],
+            outcome: None,
+        };
+        let mut events = vec![CombatEvent::RoundStarted { round: 1 }];
+        encounter.begin_turn(&mut events);
+        encounter.log.extend(events);
+        Ok(encounter)
+    }
+
+    /// Plays `actions` back on a fresh encounter, fails on the first one that isn't allowed
+    pub fn replay(setup: CombatSetup, seed: u64, actions: &[CombatAction]) -> Result<Self, String> {
+        let mut encounter = Encounter::new(setup, seed)?;
+        for (i, action) in actions.iter().enumerate() {
+            encounter.act(action.clone()).map_err(|e| format!("Action {} can't be replayed: {}", i + 1, e))?;
+        }
+        Ok(encounter)
+    }
+
+    pub fn setup(&self) -> &CombatSetup {
+        &self.setup
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn actions(&self) -> &[CombatAction] {
+        &self.actions
+    }
+
+    /// Every event since the start
+    pub fn log(&self) -> &[CombatEvent] {
+        &self.log
+    }
+
+    pub fn outcome(&self) -> Option<CombatOutcome> {
+        self.outcome
+    }
+
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    pub fn health(&self, combatant: usize) -> i32 {
+        self.states[combatant].health
+    }
+
+    /// Who has to act, `None` once the fight is over
+    pub fn current_actor(&self) -> Option<usize> {
+        match self.outcome {
+            Some(_) => None,
+            None => Some(self.order[self.turn]),
+        }
+    }
+
+    /// Whether `ability` could be used by `combatant` on its current turn
+    pub fn is_ready(&self, combatant: usize, ability: &str) -> bool {
+        let state = &self.states[combatant];
+        state.ready_at.get(ability).is_none_or(|ready_at| state.turns_taken >= *ready_at)
+    }
+
+    pub fn act(&mut self, action: CombatAction) -> Result<Vec<CombatEvent>, ActionError> {
+        let actor = self.current_actor().ok_or(ActionError::Finished)?;
+        if action.actor != actor {
+            return Err(ActionError::NotYourTurn);
+        }
+        if !self.setup.combatants[actor].abilities.contains(&action.ability) {
+            return Err(ActionError::UnknownAbility(action.ability));
+        }
+        let ability = self.setup.abilities[&action.ability].clone();
+        if !self.is_ready(actor, &ability.id) {
+            return Err(ActionError::OnCooldown(ability.name));
+        }
+        if !self.is_valid_target(actor, ability.target, action.target) {
+            return Err(ActionError::InvalidTarget);
+        }
+
+        let target = action.target;
+        let mut events = vec![CombatEvent::AbilityUsed { actor, ability: ability.id.clone(), target }];
+        let state = &mut self.states[actor];
+        state.ready_at.insert(ability.id.clone(), state.turns_taken + ability.cooldown + 1);
+
+        if let Some(formula) = &ability.damage {
+            let (amount, critical) = self.roll_damage(actor, target, formula, ability.damage_kind, ability.critical_chance);
+            self.damage(target, amount, critical, None, &mut events);
+        }
+        if let Some(formula) = &ability.heal {
+            let amount = self.roll(formula, actor).round() as i32;
+            self.heal(target, amount, None, &mut events);
+        }
+        for application in &ability.effects {
+            // rolled even when certain so adding a chance to an effect doesn't shift every later roll
+            let roll: f64 = self.rng.gen();
+            let receiver = if application.on_self { actor } else { target };
+            if roll < application.chance && self.states[receiver].health > 0 {
+                self.apply_effect(actor, receiver, &application.effect, &mut events);
+            }
+        }
+
+        self.actions.push(action);
+        self.check_outcome(&mut events);
+        self.next_turn(&mut events);
+        self.log.extend(events.iter().cloned());
+        Ok(events)
+    }
+
+    /// A sensible action for whoever's turn it is: heal a badly hurt ally if possible, otherwise hit
+    /// the weakest enemy with the first ready ability that has a cooldown, falling back to one without
+    pub fn auto_action(&self) -> Option<CombatAction> {
+        let actor = self.current_actor()?;
+        let ready: Vec<&AbilityDefinition> = self.setup.combatants[actor].abilities.iter()
+            .filter(|a| self.is_ready(actor, a))
+            .map(|a| &self.setup.abilities[a])
+            .collect();
+
+        let hurt_ally = self.living()
+            .filter(|i| self.is_valid_target(actor, AbilityTarget::Ally, *i))
+            .filter(|i| self.states[*i].health * 2 < self.setup.combatants[*i].max_health)
+            .min_by_key(|i| self.states[*i].health * 100 / self.setup.combatants[*i].max_health);
+        if let Some(ally) = hurt_ally {
+            let heal = ready.iter()
+                .filter(|a| a.heal.is_some() && a.damage.is_none())
+                .find(|a| self.is_valid_target(actor, a.target, ally));
+            if let Some(heal) = heal {
+                return Some(CombatAction { actor, ability: heal.id.clone(), target: ally });
+            }
+        }
+
+        let weakest_enemy = self.living()
+            .filter(|i| self.is_valid_target(actor, AbilityTarget::Enemy, *i))
+            .min_by_key(|i| self.states[*i].health)?;
+        let attack = ready.iter()
+            .filter(|a| a.target == AbilityTarget::Enemy)
+            .find(|a| a.cooldown > 0)
+            .or_else(|| ready.iter().find(|a| a.target == AbilityTarget::Enemy))?;
+        Some(CombatAction { actor, ability: attack.id.clone(), target: weakest_enemy })
+    }
+
+    fn living(&self) -> impl Iterator<Item=usize> + '_ {
+        (0..self.states.len()).filter(|i| self.states[*i].health > 0)
+    }
+
+    fn is_valid_target(&self, actor: usize, kind: AbilityTarget, target: usize) -> bool {
+        let Some(state) = self.states.get(target) else { return false };
+        if state.health <= 0 {
+            return false;
+        }
+        let same_side = self.setup.combatants[actor].side == self.setup.combatants[target].side;
+        match kind {
+            AbilityTarget::Enemy => !same_side,
+            AbilityTarget::Ally => same_side,
+            AbilityTarget::User => actor == target,
+        }
+    }
+
+    fn roll(&mut self, formula: &Formula, source: usize) -> f64 {
+        let combatant = &self.setup.combatants[source];
+        let value = formula.evaluate(combatant.level, &combatant.attributes);
+        if formula.variance > 0.0 {
+            value * (1.0 + self.rng.gen_range(-formula.variance..=formula.variance))
+        } else {
+            value
+        }
+    }
+
+    fn roll_damage(&mut self, actor: usize, target: usize, formula: &Formula, kind: DamageKind, critical_chance: f64) -> (i32, bool) {
+        let mut amount = self.roll(formula, actor);
+
+        let attacker = &self.setup.combatants[actor];
+        let chance = (critical_chance + attacker.attributes.dexterity as f64 * CRITICAL_PER_DEXTERITY).clamp(0.0, 1.0);
+        let critical = self.rng.gen_bool(chance);
+        if critical {
+            amount *= CRITICAL_MULTIPLIER;
+        }
+
+        let defender = &self.setup.combatants[target];
+        let defense = match kind {
+            DamageKind::Physical => defender.attributes.vitality as f64 * 2.0,
+            DamageKind::Magical => defender.attributes.intelligence as f64 * 2.0,
+            DamageKind::True => 0.0,
+        }.max(0.0);
+        amount *= 1.0 - defense / (defense + MITIGATION_SCALE);
+        amount *= self.effect_multiplier(actor, |e| e.damage_dealt);
+        amount *= self.effect_multiplier(target, |e| e.damage_taken);
+
+        // a hit always hurts a little, unless an effect took all of it away
+        let amount = if amount > 0.0 { (amount.round() as i32).max(1) } else { 0 };
+        (amount, critical)
+    }
+
+    fn effect_multiplier(&self, combatant: usize, multiplier: impl Fn(&StatusEffectDefinition) -> f64) -> f64 {
+        self.states[combatant].effects.iter()
+            .map(|e| multiplier(&self.setup.effects[&e.id]))
+            .product()
+    }
+
+    fn damage(&mut self, target: usize, amount: i32, critical: bool, effect: Option<String>, events: &mut Vec<CombatEvent>) {
+        let state = &mut self.states[target];
+        state.health = (state.health - amount).max(0);
+        events.push(CombatEvent::Damaged { target, amount, critical, effect, health: state.health });
+        if state.health == 0 {
+            state.effects.clear();
+            events.push(CombatEvent::Defeated { target });
+        }
+    }
+
+    fn heal(&mut self, target: usize, amount: i32, effect: Option<String>, events: &mut Vec<CombatEvent>) {
+        let max_health = self.setup.combatants[target].max_health;
+        let state = &mut self.states[target];
+        let amount = amount.clamp(0, max_health - state.health);
+        state.health += amount;
+        events.push(CombatEvent::Healed { target, amount, effect, health: state.health });
+    }
+
+    fn apply_effect(&mut self, source: usize, target: usize, effect_id: &str, events: &mut Vec<CombatEvent>) {
+        let definition = self.setup.effects[effect_id].clone();
+        let damage = definition.damage.as_ref().map_or(0, |f| self.roll(f, source).round().max(0.0) as i32);
+        let heal = definition.heal.as_ref().map_or(0, |f| self.roll(f, source).round().max(0.0) as i32);
+        let effects = &mut self.states[target].effects;
+        // applying an effect again refreshes it
+        effects.retain(|e| e.id != definition.id);
+        effects.push(ActiveEffect { id: definition.id.clone(), remaining: definition.duration, damage, heal });
+        events.push(CombatEvent::EffectApplied { target, effect: definition.id, turns: definition.duration });
+    }
+
+    fn check_outcome(&mut self, events: &mut Vec<CombatEvent>) {
+        if self.outcome.is_some() {
+            return;
+        }
+        let mut standing: Vec<usize> = self.living().map(|i| self.setup.combatants[i].side).collect();
+        standing.dedup();
+        if standing.len() <= 1 {
+            let winner = standing.first().copied();
+            self.outcome = Some(CombatOutcome { winner, rounds: self.round });
+            events.push(CombatEvent::Ended { winner });
+        }
+    }
+
+    fn next_turn(&mut self, events: &mut Vec<CombatEvent>) {
+        if self.outcome.is_some() {
+            return;
+        }
+        self.turn += 1;
+        if self.turn == self.order.len() {
+            self.turn = 0;
+            if self.round == self.setup.max_rounds {
+                self.outcome = Some(CombatOutcome { winner: None, rounds: self.round });
+                events.push(CombatEvent::Ended { winner: None });
+                return;
+            }
+            self.round += 1;
+            events.push(CombatEvent::RoundStarted { round: self.round });
+        }
+        self.begin_turn(events);
+    }
+
+    /// Ticks the effects of whoever is next and moves on when they can't act
+    fn begin_turn(&mut self, events: &mut Vec<CombatEvent>) {
+        let actor = self.order[self.turn];
+        if self.states[actor].health == 0 {
+            return self.next_turn(events);
+        }
+
+        let state = &mut self.states[actor];
+        state.turns_taken += 1;
+        let effects = state.effects.clone();
+        let mut stunned = false;
+        for effect in &effects {
+            if effect.damage > 0 && self.states[actor].health > 0 {
+                self.damage(actor, effect.damage, false, Some(effect.id.clone()), events);
+            }
+            if effect.heal > 0 && self.states[actor].health > 0 {
+                self.heal(actor, effect.heal, Some(effect.id.clone()), events);
+            }
+            stunned |= self.setup.effects[&effect.id].stun;
+        }
+
+        let state = &mut self.states[actor];
+        for effect in state.effects.iter_mut() {
+            effect.remaining -= 1;
+        }
+        let (expired, active) = std::mem::take(&mut state.effects).into_iter().partition(|e| e.remaining == 0);
+        state.effects = active;
+        let expired: Vec<ActiveEffect> = expired;
+        events.extend(expired.into_iter().map(|e| CombatEvent::EffectExpired { target: actor, effect: e.id }));
+
+        if self.states[actor].health == 0 {
+            self.check_outcome(events);
+            return self.next_turn(events);
+        }
+        if stunned {
+            events.push(CombatEvent::Stunned { actor });
+            return self.next_turn(events);
+        }
+        events.push(CombatEvent::TurnStarted { actor });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_none, assert_ok, assert_some};
+    use crate::characters::Attributes;
+    use crate::combat::{AbilityDefinition, AbilityTarget, ActionError, CombatAction, CombatEvent, CombatSetup, Combatant, DamageKind, EffectApplication, Encounter, Formula, StatusEffectDefinition};
+
+    fn ability(id: &str, target: AbilityTarget, cooldown: u32) -> AbilityDefinition {
+        AbilityDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            target,
+            cooldown,
+            damage: None,
+            damage_kind: DamageKind::Physical,
+            heal: None,
+            critical_chance: 0.0,
+            effects: vec![],
+        }
+    }
+
+    fn effect(id: &str, duration: u32) -> StatusEffectDefinition {
+        StatusEffectDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            duration,
+            damage: None,
+            heal: None,
+            stun: false,
+            damage_dealt: 1.0,
+            damage_taken: 1.0,
+        }
+    }
+
+    fn combatant(name: &str, side: usize, dexterity: i32, abilities: &[&str]) -> Combatant {
+        Combatant {
+            name: name.to_string(),
+            side,
+            level: 1,
+            attributes: Attributes { strength: 10, dexterity, intelligence: 5, vitality: 0 },
+            max_health: 50,
+            abilities: abilities.iter().map(|a| a.to_string()).collect(),
+            character_id: None,
+            creature_id: None,
+        }
+    }
+
+    fn setup() -> CombatSetup {
+        let mut strike = ability("strike", AbilityTarget::Enemy, 0);
+        strike.damage = Some(Formula { base: 2.0, strength: 0.5, variance: 0.2, ..Formula::default() });
+        strike.critical_chance = 0.1;
+        let mut bash = ability("bash", AbilityTarget::Enemy, 2);
+        bash.damage = Some(Formula { base: 1.0, ..Formula::default() });
+        bash.effects.push(EffectApplication { effect: "stunned".to_string(), chance: 1.0, on_self: false });
+        let mut poison = ability("poison", AbilityTarget::Enemy, 3);
+        poison.effects.push(EffectApplication { effect: "poisoned".to_string(), chance: 1.0, on_self: false });
+        let mut mend = ability("mend", AbilityTarget::Ally, 0);
+        mend.heal = Some(Formula { base: 10.0, ..Formula::default() });
+
+        let mut stunned = effect("stunned", 1);
+        stunned.stun = true;
+        let mut poisoned = effect("poisoned", 2);
+        poisoned.damage = Some(Formula { base: 3.0, ..Formula::default() });
+
+        CombatSetup {
+            combatants: vec![
+                combatant("Hero", 0, 8, &["strike", "bash", "mend"]),
+                combatant("Wolf", 1, 6, &["strike", "poison"]),
+            ],
+            abilities: [strike, bash, poison, mend].into_iter().map(|a| (a.id.clone(), a)).collect(),
+            effects: [stunned, poisoned].into_iter().map(|e| (e.id.clone(), e)).collect(),
+            max_rounds: 30,
+        }
+    }
+
+    fn action(actor: usize, ability: &str, target: usize) -> CombatAction {
+        CombatAction { actor, ability: ability.to_string(), target }
+    }
+
+    fn fight_to_the_end(encounter: &mut Encounter) {
+        while let Some(action) = encounter.auto_action() {
+            encounter.act(action).unwrap();
+        }
+    }
+
+    #[test]
+    fn faster_combatants_go_first() {
+        let encounter = Encounter::new(setup(), 1).unwrap();
+        assert_eq!(Some(0), encounter.current_actor());
+        assert_eq!(vec![CombatEvent::RoundStarted { round: 1 }, CombatEvent::TurnStarted { actor: 0 }], encounter.log());
+    }
+
+    #[test]
+    fn same_seed_and_actions_play_out_the_same() {
+        let mut first = Encounter::new(setup(), 42).unwrap();
+        fight_to_the_end(&mut first);
+
+        let replayed = Encounter::replay(setup(), 42, first.actions()).unwrap();
+
+        assert_some!(first.outcome());
+        assert_eq!(first.outcome(), replayed.outcome());
+        assert_eq!(first.log(), replayed.log());
+    }
+
+    #[test]
+    fn different_seeds_roll_differently() {
+        let damage = |seed| {
+            let mut encounter = Encounter::new(setup(), seed).unwrap();
+            encounter.act(action(0, "strike", 1)).unwrap();
+            encounter.health(1)
+        };
+        let distinct: std::collections::HashSet<i32> = (0..20).map(damage).collect();
+        assert!(distinct.len() > 1);
+    }
+
+    #[test]
+    fn only_the_current_actor_can_act() {
+        let mut encounter = Encounter::new(setup(), 1).unwrap();
+        assert_eq!(Err(ActionError::NotYourTurn), encounter.act(action(1, "strike", 0)));
+    }
+
+    #[test]
+    fn abilities_need_a_valid_target() {
+        let mut encounter = Encounter::new(setup(), 1).unwrap();
+        assert_eq!(Err(ActionError::InvalidTarget), encounter.act(action(0, "strike", 0)));
+        assert_eq!(Err(ActionError::InvalidTarget), encounter.act(action(0, "mend", 1)));
+        assert_eq!(Err(ActionError::InvalidTarget), encounter.act(action(0, "strike", 7)));
+        assert_eq!(Err(ActionError::UnknownAbility("poison".to_string())), encounter.act(action(0, "poison", 1)));
+    }
+
+    #[test]
+    fn cooldowns_count_own_turns() {
+        let mut encounter = Encounter::new(setup(), 1).unwrap();
+        assert_ok!(encounter.act(action(0, "bash", 1)));
+        // the wolf is stunned, so it's the hero's turn again
+        assert_eq!(Some(0), encounter.current_actor());
+        assert_eq!(Err(ActionError::OnCooldown("bash".to_string())), encounter.act(action(0, "bash", 1)));
+        assert_ok!(encounter.act(action(0, "strike", 1)));
+        assert_ok!(encounter.act(action(1, "strike", 0)));
+        assert_err!(encounter.act(action(0, "bash", 1)));
+        assert_ok!(encounter.act(action(0, "strike", 1)));
+        assert_ok!(encounter.act(action(1, "strike", 0)));
+        assert_ok!(encounter.act(action(0, "bash", 1)));
+    }
+
+    #[test]
+    fn stunned_combatants_lose_their_turn() {
+        let mut encounter = Encounter::new(setup(), 1).unwrap();
+        let events = encounter.act(action(0, "bash", 1)).unwrap();
+        assert!(events.contains(&CombatEvent::EffectApplied { target: 1, effect: "stunned".to_string(), turns: 1 }));
+        assert!(events.contains(&CombatEvent::Stunned { actor: 1 }));
+        assert!(events.contains(&CombatEvent::EffectExpired { target: 1, effect: "stunned".to_string() }));
+        assert_eq!(Some(&CombatEvent::TurnStarted { actor: 0 }), events.last());
+    }
+
+    #[test]
+    fn effects_tick_at_the_start_of_each_turn_until_they_expire() {
+        let mut encounter = Encounter::new(setup(), 1).unwrap();
+        encounter.act(action(0, "strike", 1)).unwrap();
+        encounter.act(action(1, "poison", 0)).unwrap();
+        let health = encounter.health(0);
+        assert_eq!(health + 3, encounter.setup().combatants[0].max_health);
+
+        encounter.act(action(0, "strike", 1)).unwrap();
+        let events = encounter.act(action(1, "strike", 0)).unwrap();
+        assert!(events.contains(&CombatEvent::EffectExpired { target: 0, effect: "poisoned".to_string() }));
+        let poison_ticks = encounter.log().iter()
+            .filter(|e| matches!(e, CombatEvent::Damaged { effect: Some(_), .. }))
+            .count();
+        assert_eq!(2, poison_ticks);
+    }
+
+    #[test]
+    fn healing_never_goes_past_max_health() {
+        let mut encounter = Encounter::new(setup(), 1).unwrap();
+        let events = encounter.act(action(0, "mend", 0)).unwrap();
+        assert!(events.contains(&CombatEvent::Healed { target: 0, amount: 0, effect: None, health: 50 }));
+    }
+
+    #[test]
+    fn last_side_standing_wins() {
+        let mut encounter = Encounter::new(setup(), 7).unwrap();
+        fight_to_the_end(&mut encounter);
+
+        let outcome = encounter.outcome().unwrap();
+        let winner = outcome.winner.unwrap();
+        assert_none!(encounter.current_actor());
+        assert_eq!(Some(&CombatEvent::Ended { winner: Some(winner) }), encounter.log().last());
+        let loser = 1 - winner;
+        assert_eq!(0, encounter.health(loser));
+        assert_eq!(Err(ActionError::Finished), encounter.act(action(winner, "strike", loser)));
+    }
+
+    #[test]
+    fn running_out_of_rounds_is_a_draw() {
+        let mut setup = setup();
+        setup.max_rounds = 2;
+        setup.combatants.iter_mut().for_each(|c| c.max_health = 1000);
+        let mut encounter = Encounter::new(setup, 3).unwrap();
+        fight_to_the_end(&mut encounter);
+
+        assert_eq!(None, encounter.outcome().unwrap().winner);
+        assert_eq!(2, encounter.outcome().unwrap().rounds);
+    }
+
+    #[test]
+    fn ai_heals_when_badly_hurt() {
+        let mut encounter = Encounter::new(setup(), 1).unwrap();
+        encounter.states[0].health = 10;
+        assert_eq!(Some(action(0, "mend", 0)), encounter.auto_action());
+    }
+
+    #[test]
+    fn setups_need_two_sides_and_known_abilities() {
+        let mut one_side = setup();
+        one_side.combatants[1].side = 0;
+        assert_err!(Encounter::new(one_side, 1));
+
+        let mut unknown = setup();
+        unknown.combatants[1].abilities.push("fireball".to_string());
+        assert_err!(Encounter::new(unknown, 1));
+    }
+
+    #[test]
+    fn replays_with_invalid_actions_fail() {
+        let actions = vec![action(0, "strike", 1), action(0, "strike", 1)];
+        assert_err!(Encounter::replay(setup(), 1, &actions));
+    }
+
+    #[test]
+    fn setups_survive_a_round_trip_through_json() {
+        let setup = setup();
+        let json = serde_json::to_string(&setup).unwrap();
+        assert_eq!(setup, serde_json::from_str::<CombatSetup>(&json).unwrap());
+    }
+}
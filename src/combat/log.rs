@@ -0,0 +1,49 @@
+use crate::combat::{CombatEvent, CombatSetup};
+
+/// One line of the combat log for `event`, `None` for bookkeeping events nobody needs to read
+pub fn describe_event(setup: &CombatSetup, event: &CombatEvent) -> Option<String> {
+    let name = |i: &usize| setup.combatants.get(*i).map_or("Someone", |c| c.name.as_str());
+    let ability = |id: &String| setup.abilities.get(id).map_or_else(|| id.clone(), |a| a.name.clone());
+    let effect = |id: &String| setup.effects.get(id).map_or_else(|| id.clone(), |e| e.name.clone());
+
+    let line = match event {
+        CombatEvent::RoundStarted { round } => format!("Round {}", round),
+        CombatEvent::TurnStarted { .. } => return None,
+        CombatEvent::AbilityUsed { actor, ability: id, target } if actor == target => {
+            format!("{} uses {}", name(actor), ability(id))
+        }
+        CombatEvent::AbilityUsed { actor, ability: id, target } => {
+            format!("{} uses {} on {}", name(actor), ability(id), name(target))
+        }
+        CombatEvent::Damaged { target, amount, effect: Some(id), health, .. } => {
+            format!("{} takes {} damage from {} ({} left)", name(target), amount, effect(id), health)
+        }
+        CombatEvent::Damaged { target, amount, critical, effect: None, health } => format!(
+            "{} takes {} damage{} ({} left)",
+            name(target), amount, if *critical { ", a critical hit" } else { "" }, health
+        ),
+        CombatEvent::Healed { target, amount, effect: Some(id), health } => {
+            format!("{} recovers {} health from {} ({} left)", name(target), amount, effect(id), health)
+        }
+        CombatEvent::Healed { target, amount, effect: None, health } => {
+            format!("{} recovers {} health ({} left)", name(target), amount, health)
+        }
+        CombatEvent::EffectApplied { target, effect: id, turns } => {
+            format!("{} is {} for {} turn{}", name(target), effect(id).to_lowercase(), turns, if *turns == 1 { "" } else { "s" })
+        }
+        CombatEvent::EffectExpired { target, effect: id } => {
+            format!("{} is no longer {}", name(target), effect(id).to_lowercase())
+        }
+        CombatEvent::Stunned { actor } => format!("{} is stunned and loses the turn", name(actor)),
+        CombatEvent::Defeated { target } => format!("{} is defeated", name(target)),
+        CombatEvent::Ended { winner: Some(side) } => {
+            let winners: Vec<&str> = setup.combatants.iter()
+                .filter(|c| c.side == *side)
+                .map(|c| c.name.as_str())
+                .collect();
+            format!("{} won", winners.join(", "))
+        }
+        CombatEvent::Ended { winner: None } => "Nobody won".to_string(),
+    };
+    Some(line)
+}
@@ -0,0 +1,15 @@
+mod definition;
+mod encounter;
+mod log;
+mod rules;
+mod service;
+mod store;
+mod system;
+
+pub use definition::{AbilityDefinition, AbilityTarget, ClassAbilities, CreatureDefinition, DamageKind, EffectApplication, Formula, StatusEffectDefinition};
+pub use encounter::{ActionError, CombatAction, CombatEvent, CombatOutcome, CombatSetup, Combatant, Encounter};
+pub use log::describe_event;
+pub use rules::{get_combat_rules, CombatRules};
+pub use service::{CombatError, CombatService, Participant};
+pub use store::{get_combat_record, get_recent_combats, is_combat_participant, CombatRecord, CombatSummary};
+pub use system::CombatTurnTimeoutSystem;
\ No newline at end of file
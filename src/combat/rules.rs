@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use crate::characters::{Character, CharacterClass};
+use crate::combat::{AbilityDefinition, AbilityTarget, ClassAbilities, CombatSetup, Combatant, CreatureDefinition, StatusEffectDefinition};
+use crate::game_data::{data_directory, load_data_files, GameDataError};
+
+#[derive(serde::Deserialize)]
+struct CombatFile {
+    #[serde(default)]
+    abilities: Vec<AbilityDefinition>,
+    #[serde(default)]
+    effects: Vec<StatusEffectDefinition>,
+    #[serde(default)]
+    classes: Vec<ClassAbilities>,
+    #[serde(default)]
+    creatures: Vec<CreatureDefinition>,
+}
+
+/// Health everyone gets before vitality and level
+const BASE_HEALTH: i32 = 30;
+const HEALTH_PER_VITALITY: i32 = 6;
+const HEALTH_PER_LEVEL: i32 = 4;
+
+/// Abilities, status effects, class loadouts and creatures from `data/combat`, checked to only
+/// reference each other. Every class and creature needs an attack without a cooldown so it can
+/// always do something on its turn.
+#[derive(Debug, Default)]
+pub struct CombatRules {
+    abilities: HashMap<String, AbilityDefinition>,
+    effects: HashMap<String, StatusEffectDefinition>,
+    classes: HashMap<String, Vec<String>>,
+    creatures: HashMap<String, CreatureDefinition>,
+}
+
+impl CombatRules {
+    pub fn new(
+        abilities: Vec<AbilityDefinition>,
+        effects: Vec<StatusEffectDefinition>,
+        classes: Vec<ClassAbilities>,
+        creatures: Vec<CreatureDefinition>,
+    ) -> Result<Self, GameDataError> {
+        let mut rules = CombatRules::default();
+        for effect in effects {
+            effect.validate().map_err(GameDataError::Invalid)?;
+            if rules.effects.contains_key(&effect.id) {
+                return Err(GameDataError::Invalid(format!("Status effect {} is defined twice", effect.id)));
+            }
+            rules.effects.insert(effect.id.clone(), effect);
+        }
+        for ability in abilities {
+            ability.validate().map_err(GameDataError::Invalid)?;
+            if rules.abilities.contains_key(&ability.id) {
+                return Err(GameDataError::Invalid(format!("Ability {} is defined twice", ability.id)));
+            }
+            if let Some(application) = ability.effects.iter().find(|a| !rules.effects.contains_key(&a.effect)) {
+                return Err(GameDataError::Invalid(format!(
+                    "Ability {} applies unknown status effect {}", ability.id, application.effect
+                )));
+            }
+            rules.abilities.insert(ability.id.clone(), ability);
+        }
+        for loadout in classes {
+            let class = CharacterClass::try_from(loadout.class.clone()).map_err(GameDataError::Invalid)?;
+            rules.check_loadout(&format!("Class {}", class), &loadout.abilities)?;
+            if rules.classes.insert(class.as_str().to_string(), loadout.abilities).is_some() {
+                return Err(GameDataError::Invalid(format!("Class {} has abilities defined twice", class)));
+            }
+        }
+        if let Some(class) = CharacterClass::ALL.iter().find(|c| !rules.classes.contains_key(c.as_str())) {
+            return Err(GameDataError::Invalid(format!("Class {} has no abilities", class)));
+        }
+        for creature in creatures {
+            creature.validate().map_err(GameDataError::Invalid)?;
+            rules.check_loadout(&format!("Creature {}", creature.id), &creature.abilities)?;
+            if rules.creatures.contains_key(&creature.id) {
+                return Err(GameDataError::Invalid(format!("Creature {} is defined twice", creature.id)));
+            }
+            rules.creatures.insert(creature.id.clone(), creature);
+        }
+        Ok(rules)
+    }
+
+    pub fn load(directory: &Path) -> Result<Self, GameDataError> {
+        let (mut abilities, mut effects, mut classes, mut creatures) = (vec![], vec![], vec![], vec![]);
+        for (_, file) in load_data_files::<CombatFile>(directory)? {
+            abilities.extend(file.abilities);
+            effects.extend(file.effects);
+            classes.extend(file.classes);
+            creatures.extend(file.creatures);
+        }
+        Self::new(abilities, effects, classes, creatures)
+    }
+
+    fn check_loadout(&self, owner: &str, abilities: &[String]) -> Result<(), GameDataError> {
+        if let Some(ability) = abilities.iter().find(|a| !self.abilities.contains_key(*a)) {
+            return Err(GameDataError::Invalid(format!("{} uses unknown ability {}", owner, ability)));
+        }
+        let has_basic_attack = abilities.iter()
+            .map(|a| &self.abilities[a])
+            .any(|a| a.cooldown == 0 && a.target == AbilityTarget::Enemy);
+        if !has_basic_attack {
+            return Err(GameDataError::Invalid(format!("{} needs an attack without a cooldown", owner)));
+        }
+        Ok(())
+    }
+
+    pub fn ability(&self, id: &str) -> Option<&AbilityDefinition> {
+        self.abilities.get(id)
+    }
+
+    pub fn creature(&self, id: &str) -> Option<&CreatureDefinition> {
+        self.creatures.get(id)
+    }
+
+    pub fn creatures(&self) -> impl Iterator<Item=&CreatureDefinition> {
+        self.creatures.values()
+    }
+
+    pub fn class_abilities(&self, class: CharacterClass) -> &[String] {
+        self.classes.get(class.as_str()).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn character_combatant(&self, character: &Character, side: usize) -> Combatant {
+        Combatant {
+            name: character.name.to_string(),
+            side,
+            level: character.level,
+            attributes: character.attributes,
+            max_health: BASE_HEALTH + HEALTH_PER_VITALITY * character.attributes.vitality + HEALTH_PER_LEVEL * character.level,
+            abilities: self.class_abilities(character.class).to_vec(),
+            character_id: Some(character.id),
+            creature_id: None,
+        }
+    }
+
+    pub fn creature_combatant(&self, creature: &CreatureDefinition, side: usize) -> Combatant {
+        Combatant {
+            name: creature.name.clone(),
+            side,
+            level: creature.level,
+            attributes: creature.attributes,
+            max_health: creature.health.unwrap_or(
+                BASE_HEALTH + HEALTH_PER_VITALITY * creature.attributes.vitality + HEALTH_PER_LEVEL * creature.level
+            ),
+            abilities: creature.abilities.clone(),
+            character_id: None,
+            creature_id: Some(creature.id.clone()),
+        }
+    }
+
+    /// Copies the definitions the combatants need into the setup
+    pub fn setup(&self, combatants: Vec<Combatant>, max_rounds: u32) -> CombatSetup {
+        let abilities: BTreeMap<String, AbilityDefinition> = combatants.iter()
+            .flat_map(|c| c.abilities.iter())
+            .filter_map(|id| self.abilities.get(id))
+            .map(|a| (a.id.clone(), a.clone()))
+            .collect();
+        let effects = abilities.values()
+            .flat_map(|a| a.effects.iter())
+            .filter_map(|application| self.effects.get(&application.effect))
+            .map(|e| (e.id.clone(), e.clone()))
+            .collect();
+        CombatSetup { combatants, abilities, effects, max_rounds }
+    }
+}
+
+pub fn get_combat_rules() -> Result<CombatRules, GameDataError> {
+    CombatRules::load(&data_directory().join("combat"))
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use uuid::Uuid;
+    use crate::characters::CharacterClass;
+    use crate::combat::{get_combat_rules, CombatRules};
+
+    const CLASSES: &str = r#"
+[[abilities]]
+id = "strike"
+name = "Strike"
+target = "enemy"
+[abilities.damage]
+base = 3
+strength = 0.5
+
+[[classes]]
+class = "warrior"
+abilities = ["strike"]
+[[classes]]
+class = "mage"
+abilities = ["strike"]
+[[classes]]
+class = "rogue"
+abilities = ["strike"]
+[[classes]]
+class = "ranger"
+abilities = ["strike"]
+[[classes]]
+class = "cleric"
+abilities = ["strike"]
+"#;
+
+    fn load(files: &[(&str, &str)]) -> Result<CombatRules, crate::game_data::GameDataError> {
+        let directory = std::env::temp_dir().join(format!("yaug-combat-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        for (name, content) in files {
+            std::fs::write(directory.join(name), content).unwrap();
+        }
+        let rules = CombatRules::load(&directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+        rules
+    }
+
+    #[test]
+    fn shipped_rules_are_valid() {
+        let rules = assert_ok!(get_combat_rules());
+        for class in CharacterClass::ALL {
+            assert!(!rules.class_abilities(class).is_empty());
+        }
+        assert!(rules.creatures().count() > 0);
+    }
+
+    #[test]
+    fn files_are_merged() {
+        let rules = assert_ok!(load(&[
+            ("classes.toml", CLASSES),
+            ("creatures.toml", "[[creatures]]\nid = \"rat\"\nname = \"Rat\"\nlevel = 1\nabilities = [\"strike\"]\n[creatures.attributes]\nstrength = 2\ndexterity = 4\nintelligence = 1\nvitality = 1\n"),
+        ]));
+        let rat = rules.creature("rat").unwrap();
+        assert_eq!(4, rat.attributes.dexterity);
+        assert_eq!(Some(3.0), rules.ability("strike").and_then(|a| a.damage.as_ref()).map(|f| f.base));
+    }
+
+    #[test]
+    fn unknown_references_are_rejected() {
+        assert_err!(load(&[
+            ("classes.toml", CLASSES),
+            ("effects.toml", "[[abilities]]\nid = \"bite\"\nname = \"Bite\"\ntarget = \"enemy\"\n[[abilities.effects]]\neffect = \"rabies\"\n"),
+        ]));
+        assert_err!(load(&[
+            ("classes.toml", CLASSES),
+            ("creatures.toml", "[[creatures]]\nid = \"rat\"\nname = \"Rat\"\nlevel = 1\nabilities = [\"gnaw\"]\n[creatures.attributes]\nstrength = 2\ndexterity = 4\nintelligence = 1\nvitality = 1\n"),
+        ]));
+    }
+
+    #[test]
+    fn every_class_needs_abilities() {
+        let without_clerics = CLASSES.replace("class = \"cleric\"", "class = \"warrior\"");
+        assert_err!(load(&[("classes.toml", &without_clerics)]));
+    }
+
+    #[test]
+    fn loadouts_need_an_attack_without_cooldown() {
+        let slow = CLASSES.replace("target = \"enemy\"", "target = \"enemy\"\ncooldown = 2");
+        assert_err!(load(&[("classes.toml", &slow)]));
+    }
+}
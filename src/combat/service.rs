@@ -0,0 +1,384 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::characters::Character;
+use crate::combat::{ActionError, CombatAction, CombatEvent, CombatRules, Encounter};
+use crate::combat::store::{store_combat_record, CombatRecord};
+use crate::configuration::CombatSettings;
+use crate::events::{GameEvent, GameEvents};
+use crate::gateway::{ClientError, CombatantView, ConnectionRegistry, ErrorCode, ServerMessage};
+use crate::parties::PartyService;
+use crate::utils::error_chain_fmt;
+use crate::world::WorldService;
+
+#[derive(thiserror::Error)]
+pub enum CombatError {
+    #[error("Select a character before fighting")]
+    NoCharacter,
+    #[error("You are already in a fight")]
+    AlreadyFighting,
+    #[error("You are not in a fight")]
+    NotFighting,
+    #[error("There is no {0} around here")]
+    UnknownCreature(String),
+    #[error(transparent)]
+    Action(#[from] ActionError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for CombatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for CombatError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            CombatError::NoCharacter => ErrorCode::Forbidden,
+            CombatError::AlreadyFighting | CombatError::Action(_) => ErrorCode::InvalidAction,
+            CombatError::NotFighting | CombatError::UnknownCreature(_) => ErrorCode::NotFound,
+            CombatError::UnexpectedError(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+/// Who fights on a side
+pub enum Participant {
+    Player { user_id: UserId, character: Character },
+    Creature(String),
+}
+
+struct ActiveEncounter {
+    encounter: Encounter,
+    /// Combatant index and the player controlling it, everyone else is played by the AI
+    players: Vec<(usize, UserId)>,
+    started_at: DateTime<Utc>,
+    /// Game time the player whose turn it is has been given so far
+    waited: chrono::Duration,
+}
+
+impl ActiveEncounter {
+    fn player(&self, combatant: usize) -> Option<UserId> {
+        self.players.iter().find(|(i, _)| *i == combatant).map(|(_, user_id)| *user_id)
+    }
+
+    fn combatant(&self, user_id: UserId) -> Option<usize> {
+        self.players.iter().find(|(_, u)| *u == user_id).map(|(i, _)| *i)
+    }
+
+    fn waiting_on_player(&self) -> bool {
+        self.encounter.current_actor().is_some_and(|actor| self.player(actor).is_some())
+    }
+
+    fn record(&self, id: Uuid) -> CombatRecord {
+        let outcome = self.encounter.outcome();
+        CombatRecord {
+            id,
+            seed: self.encounter.seed(),
+            setup: self.encounter.setup().clone(),
+            actions: self.encounter.actions().to_vec(),
+            winner: outcome.and_then(|o| o.winner),
+            rounds: outcome.map_or(self.encounter.round(), |o| o.rounds),
+            started_at: self.started_at,
+            finished_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct CombatState {
+    encounters: HashMap<Uuid, ActiveEncounter>,
+    fighting: HashMap<UserId, Uuid>,
+    /// Finished fights whose record is being written
+    storing: HashSet<Uuid>,
+}
+
+/// Runs every fight in progress. Fights live in memory until they end and are then stored with
+/// their seed and actions, which is all it takes to replay them.
+pub struct CombatService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    world: Arc<WorldService>,
+    rules: Arc<CombatRules>,
+    events: GameEvents,
+    parties: Arc<PartyService>,
+    settings: CombatSettings,
+    state: Mutex<CombatState>,
+}
+
+impl CombatService {
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        world: Arc<WorldService>,
+        rules: Arc<CombatRules>,
+        events: GameEvents,
+        parties: Arc<PartyService>,
+        settings: CombatSettings,
+    ) -> Self {
+        CombatService { pool, registry, world, rules, events, parties, settings, state: Mutex::new(CombatState::default()) }
+    }
+
+    pub fn rules(&self) -> &CombatRules {
+        &self.rules
+    }
+
+    /// The fight the player is in, if any
+    pub fn encounter_id(&self, user_id: UserId) -> Option<Uuid> {
+        self.state.lock().expect("Combat lock poisoned").fighting.get(&user_id).copied()
+    }
+
+    /// Whether the fight is still on or its record is still being written. Once this is false the
+    /// fight either has a record or never will.
+    pub fn is_running(&self, encounter_id: Uuid) -> bool {
+        let state = self.state.lock().expect("Combat lock poisoned");
+        state.encounters.contains_key(&encounter_id) || state.storing.contains(&encounter_id)
+    }
+
+    /// Starts a fight against a creature that roams the zone the player is in
+    #[tracing::instrument(
+    name = "Fight creature",
+    skip(self, character),
+    fields(character_id = % character.id)
+    )]
+    pub async fn fight_creature(&self, user_id: UserId, character: Character, creature_id: &str) -> Result<Uuid, CombatError> {
+        let position = self.world.position(user_id).ok_or(CombatError::NoCharacter)?;
+        let roams_here = self.rules.creature(creature_id).is_some_and(|c| c.zones.contains(&position.zone_id));
+        if !roams_here {
+            return Err(CombatError::UnknownCreature(creature_id.to_string()));
+        }
+        self.start(vec![
+            vec![Participant::Player { user_id, character }],
+            vec![Participant::Creature(creature_id.to_string())],
+        ]).await
+    }
+
+    /// Starts a fight between `sides`, none of the players can be in another fight
+    pub async fn start(&self, sides: Vec<Vec<Participant>>) -> Result<Uuid, CombatError> {
+        let mut combatants = Vec::new();
+        let mut players = Vec::new();
+        for (side, participants) in sides.into_iter().enumerate() {
+            for participant in participants {
+                match participant {
+                    Participant::Player { user_id, character } => {
+                        players.push((combatants.len(), user_id));
+                        combatants.push(self.rules.character_combatant(&character, side));
+                    }
+                    Participant::Creature(creature_id) => {
+                        let creature = self.rules
+                            .creature(&creature_id)
+                            .ok_or(CombatError::UnknownCreature(creature_id))?;
+                        combatants.push(self.rules.creature_combatant(creature, side));
+                    }
+                }
+            }
+        }
+
+        let setup = self.rules.setup(combatants, self.settings.max_rounds);
+        let seed = rand::thread_rng().gen();
+        let encounter = Encounter::new(setup, seed)
+            .map_err(|e| anyhow::anyhow!(e))
+            .context("Failed to set up fight")?;
+        let id = Uuid::new_v4();
+
+        let finished = {
+            let mut state = self.state.lock().expect("Combat lock poisoned");
+            if players.iter().any(|(_, user_id)| state.fighting.contains_key(user_id)) {
+                return Err(CombatError::AlreadyFighting);
+            }
+            let mut active = ActiveEncounter { encounter, players, started_at: Utc::now(), waited: chrono::Duration::zero() };
+            for (combatant, user_id) in &active.players {
+                state.fighting.insert(*user_id, id);
+                self.registry.send_to_user(*user_id, &ServerMessage::CombatStarted {
+                    encounter_id: id,
+                    you: *combatant,
+                    combatants: self.views(&active.encounter),
+                });
+            }
+            let mut events = active.encounter.log().to_vec();
+            events.extend(Self::play_ai_turns(&mut active.encounter, &active.players));
+            self.notify(id, &active, events);
+            tracing::info!(encounter_id = %id, seed, "Fight started");
+
+            state.encounters.insert(id, active);
+            // the AI may have won before any player got a turn
+            Self::take_if_finished(&mut state, id)
+        };
+        if let Some((id, active)) = finished {
+            self.store(id, &active).await?;
+        }
+        Ok(id)
+    }
+
+    /// Uses `ability` on `target` for the player, AI turns up to the next player turn are played right away
+    #[tracing::instrument(
+    name = "Combat action",
+    skip(self)
+    )]
+    pub async fn act(&self, user_id: UserId, ability: &str, target: usize) -> Result<(), CombatError> {
+        let finished = {
+            let mut state = self.state.lock().expect("Combat lock poisoned");
+            let id = *state.fighting.get(&user_id).ok_or(CombatError::NotFighting)?;
+            let active = state.encounters.get_mut(&id).expect("Players only fight in running encounters");
+            let actor = active.combatant(user_id).expect("Players fight as their own combatant");
+
+            let mut events = active.encounter.act(CombatAction { actor, ability: ability.to_string(), target })?;
+            active.waited = chrono::Duration::zero();
+            events.extend(Self::play_ai_turns(&mut active.encounter, &active.players));
+            self.notify(id, active, events);
+            Self::take_if_finished(&mut state, id)
+        };
+
+        if let Some((id, active)) = finished {
+            self.store(id, &active).await?;
+        }
+        Ok(())
+    }
+
+    /// Adds `elapsed` game time to every turn a player is taking, turns that run out are played for them
+    pub async fn expire_turns(&self, elapsed: chrono::Duration) -> Result<usize, anyhow::Error> {
+        let mut expired = 0;
+        let finished: Vec<(Uuid, ActiveEncounter)> = {
+            let mut state = self.state.lock().expect("Combat lock poisoned");
+            let mut ended = Vec::new();
+            for (id, active) in state.encounters.iter_mut().filter(|(_, a)| a.waiting_on_player()) {
+                active.waited += elapsed;
+                if active.waited < self.settings.turn_timeout() {
+                    continue;
+                }
+                let action = active.encounter.auto_action().expect("Running fights always have something to do");
+                let mut events = active.encounter.act(action).context("Automatic action was refused")?;
+                active.waited = chrono::Duration::zero();
+                events.extend(Self::play_ai_turns(&mut active.encounter, &active.players));
+                self.notify(*id, active, events);
+                expired += 1;
+                if active.encounter.outcome().is_some() {
+                    ended.push(*id);
+                }
+            }
+            ended.into_iter().filter_map(|id| Self::take_if_finished(&mut state, id)).collect()
+        };
+
+        for (id, active) in finished {
+            self.store(id, &active).await?;
+        }
+        Ok(expired)
+    }
+
+    fn play_ai_turns(encounter: &mut Encounter, players: &[(usize, UserId)]) -> Vec<CombatEvent> {
+        let mut events = Vec::new();
+        while let Some(actor) = encounter.current_actor() {
+            if players.iter().any(|(i, _)| *i == actor) {
+                break;
+            }
+            let Some(action) = encounter.auto_action() else { break };
+            match encounter.act(action) {
+                Ok(more) => events.extend(more),
+                Err(e) => {
+                    tracing::error!(error.cause_chain = ?e, error.message = %e, "AI picked an invalid action");
+                    break;
+                }
+            }
+        }
+        events
+    }
+
+    fn take_if_finished(state: &mut CombatState, id: Uuid) -> Option<(Uuid, ActiveEncounter)> {
+        state.encounters.get(&id)?.encounter.outcome()?;
+        let active = state.encounters.remove(&id)?;
+        for (_, user_id) in &active.players {
+            state.fighting.remove(user_id);
+        }
+        state.storing.insert(id);
+        Some((id, active))
+    }
+
+    fn notify(&self, id: Uuid, active: &ActiveEncounter, events: Vec<CombatEvent>) {
+        let update = ServerMessage::CombatUpdate { encounter_id: id, events, turn: active.encounter.current_actor() };
+        for (_, user_id) in &active.players {
+            self.registry.send_to_user(*user_id, &update);
+        }
+    }
+
+    fn views(&self, encounter: &Encounter) -> Vec<CombatantView> {
+        encounter.setup().combatants.iter()
+            .enumerate()
+            .map(|(i, c)| CombatantView {
+                name: c.name.clone(),
+                side: c.side,
+                level: c.level,
+                health: encounter.health(i),
+                max_health: c.max_health,
+                abilities: c.abilities.clone(),
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(
+    name = "Store finished fight",
+    skip(self, active)
+    )]
+    async fn store(&self, id: Uuid, active: &ActiveEncounter) -> Result<(), anyhow::Error> {
+        let record = active.record(id);
+        let stored = self.store_record(&record).await;
+        self.state.lock().expect("Combat lock poisoned").storing.remove(&id);
+        stored?;
+        tracing::info!(encounter_id = %id, winner = ?record.winner, rounds = record.rounds, "Fight ended");
+        self.publish_victories(&record, &active.players);
+        self.publish_deaths(active);
+        Ok(())
+    }
+
+    async fn store_record(&self, record: &CombatRecord) -> Result<(), anyhow::Error> {
+        let mut transaction = self.pool.begin().await.context("Failed to begin transaction")?;
+        store_combat_record(&mut transaction, record).await?;
+        transaction.commit().await.context("Failed to commit combat record")?;
+        Ok(())
+    }
+
+    /// Every player whose character has no health left, winning side or not
+    fn publish_deaths(&self, active: &ActiveEncounter) {
+        let combatants = &active.encounter.setup().combatants;
+        for (index, user_id) in &active.players {
+            let Some(character_id) = combatants[*index].character_id else { continue };
+            if active.encounter.health(*index) <= 0 {
+                self.events.publish(GameEvent::CharacterDied { user_id: *user_id, character_id });
+            }
+        }
+    }
+
+    /// Every winning player defeated every creature on the other sides, their party members who
+    /// weren't in the fight share the kills
+    fn publish_victories(&self, record: &CombatRecord, players: &[(usize, UserId)]) {
+        let Some(winner) = record.winner else { return };
+        let combatants = &record.setup.combatants;
+        let defeated: Vec<String> = combatants.iter()
+            .filter(|c| c.side != winner)
+            .filter_map(|c| c.creature_id.clone())
+            .collect();
+        let mut winners = Vec::new();
+        for (index, user_id) in players.iter().filter(|(i, _)| combatants[*i].side == winner) {
+            let Some(character_id) = combatants[*index].character_id else { continue };
+            winners.push(*user_id);
+            for creature_id in &defeated {
+                self.events.publish(GameEvent::CreatureDefeated { user_id: *user_id, character_id, creature_id: creature_id.clone() });
+            }
+        }
+        if defeated.is_empty() {
+            return;
+        }
+        for (user_id, character_id) in self.parties.members_besides(&winners) {
+            for creature_id in &defeated {
+                self.events.publish(GameEvent::PartyKill { user_id, character_id, creature_id: creature_id.clone() });
+            }
+        }
+    }
+}
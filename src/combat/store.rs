@@ -0,0 +1,165 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::combat::{CombatAction, CombatSetup};
+
+/// Everything needed to replay a finished fight
+#[derive(Debug, Clone)]
+pub struct CombatRecord {
+    pub id: Uuid,
+    pub seed: u64,
+    pub setup: CombatSetup,
+    pub actions: Vec<CombatAction>,
+    pub winner: Option<usize>,
+    pub rounds: u32,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(
+name = "Store combat record",
+skip(tx, record),
+fields(encounter_id = % record.id)
+)]
+pub async fn store_combat_record(
+    tx: &mut Transaction<'_, Postgres>,
+    record: &CombatRecord,
+) -> Result<(), anyhow::Error> {
+    let setup = serde_json::to_string(&record.setup).context("Failed to serialize combat setup")?;
+    let actions = serde_json::to_string(&record.actions).context("Failed to serialize combat actions")?;
+    sqlx::query!(
+        r#"
+        INSERT INTO combat_encounters (id, seed, setup, actions, winner, rounds, started_at, finished_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+        record.id,
+        // stored bit for bit, Postgres has no unsigned types
+        record.seed as i64,
+        setup,
+        actions,
+        record.winner.map(|w| w as i32),
+        record.rounds as i32,
+        record.started_at,
+        record.finished_at
+    )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to store combat encounter")?;
+
+    let (character_ids, sides): (Vec<Uuid>, Vec<i32>) = record.setup.combatants.iter()
+        .filter_map(|c| c.character_id.map(|id| (id, c.side as i32)))
+        .unzip();
+    sqlx::query!(
+        r#"
+        INSERT INTO combat_participants (encounter_id, character_id, side)
+        SELECT $1, * FROM UNNEST($2::uuid[], $3::int[])
+        "#,
+        record.id,
+        &character_ids,
+        &sides
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store combat participants")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Get combat record",
+skip(executor)
+)]
+pub async fn get_combat_record(
+    executor: impl PgExecutor<'_>,
+    encounter_id: Uuid,
+) -> Result<Option<CombatRecord>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id, seed, setup, actions, winner, rounds, started_at, finished_at
+        FROM combat_encounters
+        WHERE id = $1
+        "#,
+        encounter_id
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch combat encounter")?;
+
+    row.map(|r| Ok(CombatRecord {
+        id: r.id,
+        seed: r.seed as u64,
+        setup: serde_json::from_str(&r.setup).context("Stored combat setup is invalid")?,
+        actions: serde_json::from_str(&r.actions).context("Stored combat actions are invalid")?,
+        winner: r.winner.map(|w| w as usize),
+        rounds: r.rounds as u32,
+        started_at: r.started_at,
+        finished_at: r.finished_at,
+    }))
+        .transpose()
+}
+
+/// Whether any of `user_id`'s characters took part in the fight
+#[tracing::instrument(
+name = "Is combat participant",
+skip(executor)
+)]
+pub async fn is_combat_participant(
+    executor: impl PgExecutor<'_>,
+    encounter_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM combat_participants p
+            JOIN characters c ON c.id = p.character_id
+            WHERE p.encounter_id = $1 AND c.user_id = $2
+        ) AS "participant!"
+        "#,
+        encounter_id,
+        user_id
+    )
+        .fetch_one(executor)
+        .await
+        .context("Failed to check combat participants")?;
+    Ok(row.participant)
+}
+
+pub struct CombatSummary {
+    pub id: Uuid,
+    pub character_name: String,
+    pub won: Option<bool>,
+    pub rounds: i32,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// The latest fights of all of `user_id`'s characters, `won` is unset for draws
+#[tracing::instrument(
+name = "Get recent combats",
+skip(executor)
+)]
+pub async fn get_recent_combats(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    limit: i64,
+) -> Result<Vec<CombatSummary>, anyhow::Error> {
+    sqlx::query_as!(
+        CombatSummary,
+        r#"
+        SELECT e.id, c.name AS character_name, (e.winner = p.side) AS won, e.rounds, e.finished_at
+        FROM combat_participants p
+        JOIN characters c ON c.id = p.character_id
+        JOIN combat_encounters e ON e.id = p.encounter_id
+        WHERE c.user_id = $1
+        ORDER BY e.finished_at DESC
+        LIMIT $2
+        "#,
+        user_id,
+        limit
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch recent combats")
+}
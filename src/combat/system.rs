@@ -0,0 +1,31 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::combat::CombatService;
+use crate::game_loop::{GameSystem, TickContext};
+
+/// Plays the turn for players who took longer than the turn timeout, so fights always end
+pub struct CombatTurnTimeoutSystem {
+    combat: Arc<CombatService>,
+}
+
+impl CombatTurnTimeoutSystem {
+    pub fn new(combat: Arc<CombatService>) -> Self {
+        CombatTurnTimeoutSystem { combat }
+    }
+}
+
+#[async_trait]
+impl GameSystem for CombatTurnTimeoutSystem {
+    fn name(&self) -> &'static str {
+        "combat_turn_timeout"
+    }
+
+    async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error> {
+        let elapsed = chrono::Duration::from_std(ctx.delta)?;
+        let expired = self.combat.expire_turns(elapsed).await?;
+        if expired > 0 {
+            tracing::debug!(tick = ctx.tick, expired, "Played timed out combat turns");
+        }
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@ use sqlx::postgres::{PgConnectOptions, PgPoolOptions, PgSslMode};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use tera::Tera;
 use crate::domain::AccountEmail;
-use crate::email_client::EmailClient;
+use crate::email_client::{EmailClient, FileDropTransport, HttpTransport, SmtpTransport};
 
 // region Enums & Implementations
 pub enum Environment {
@@ -51,6 +51,42 @@ pub struct Settings {
     pub app: ApplicationSettings,
     pub db: DatabaseSettings,
     pub email: EmailSettings,
+    #[serde(default)]
+    pub registration: RegistrationSettings,
+    #[serde(default)]
+    pub characters: CharacterSettings,
+    #[serde(default)]
+    pub gateway: GatewaySettings,
+    #[serde(default)]
+    pub chat: ChatSettings,
+    #[serde(default)]
+    pub game: GameSettings,
+    #[serde(default)]
+    pub world: WorldSettings,
+    #[serde(default)]
+    pub combat: CombatSettings,
+    #[serde(default)]
+    pub leaderboards: LeaderboardSettings,
+    #[serde(default)]
+    pub guilds: GuildSettings,
+    #[serde(default)]
+    pub presence: PresenceSettings,
+    #[serde(default)]
+    pub friends: FriendSettings,
+    #[serde(default)]
+    pub mail: MailSettings,
+    #[serde(default)]
+    pub auctions: AuctionSettings,
+    #[serde(default)]
+    pub crafting: CraftingSettings,
+    #[serde(default)]
+    pub achievements: AchievementSettings,
+    #[serde(default)]
+    pub matchmaking: MatchmakingSettings,
+    #[serde(default)]
+    pub parties: PartySettings,
+    #[serde(default)]
+    pub npcs: NpcSettings,
 }
 
 impl Settings {
@@ -109,7 +145,7 @@ impl DatabaseSettings {
             .host(&self.host)
             .port(self.port)
             .username(&self.username)
-            .password(&self.password.expose_secret())
+            .password(self.password.expose_secret())
             .ssl_mode(ssl)
     }
 
@@ -122,12 +158,25 @@ impl DatabaseSettings {
     }
 }
 
+#[derive(serde::Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailTransportKind {
+    #[default]
+    Http,
+    Smtp,
+    File,
+}
+
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailSettings {
+    #[serde(default)]
+    pub transport: EmailTransportKind,
     pub base_url: String,
     pub sender_email: String,
     pub auth_token: Secret<String>,
     pub timeout_milliseconds: u64,
+    pub smtp: Option<SmtpSettings>,
+    pub file: Option<FileDropSettings>,
 }
 
 impl EmailSettings {
@@ -142,12 +191,554 @@ impl EmailSettings {
     pub fn client(self) -> EmailClient {
         let sender_email = self.sender().expect("Invalid sender email address");
         let timeout = self.timeout();
-        EmailClient::new(
-            self.base_url,
-            sender_email,
-            self.auth_token,
-            timeout,
-        )
+        match self.transport {
+            EmailTransportKind::Http => EmailClient::new(
+                sender_email,
+                HttpTransport::new(self.base_url, self.auth_token, timeout),
+            ),
+            EmailTransportKind::Smtp => {
+                let smtp = self.smtp.expect("The smtp transport needs an [email.smtp] section");
+                let transport = SmtpTransport::new(
+                    &smtp.host,
+                    smtp.port,
+                    smtp.username.zip(smtp.password),
+                    smtp.starttls,
+                    timeout,
+                ).expect("Invalid SMTP settings");
+                EmailClient::new(sender_email, transport)
+            }
+            EmailTransportKind::File => {
+                let file = self.file.expect("The file transport needs an [email.file] section");
+                EmailClient::new(sender_email, FileDropTransport::new(file.directory))
+            }
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    #[serde(default = "default_starttls")]
+    pub starttls: bool,
+}
+
+fn default_starttls() -> bool {
+    true
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct FileDropSettings {
+    pub directory: String,
+}
+
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct RegistrationSettings {
+    #[serde(default)]
+    pub block_disposable_emails: bool,
+    #[serde(default)]
+    pub disposable_email_domains: Vec<String>,
+}
+
+impl RegistrationSettings {
+    /// Sub-domains of a listed domain are blocked as well
+    pub fn is_blocked(&self, email: &AccountEmail) -> bool {
+        if !self.block_disposable_emails {
+            return false;
+        }
+
+        let domain = email.domain();
+        self.disposable_email_domains.iter().any(|blocked| {
+            let blocked = blocked.trim().to_lowercase();
+            domain == blocked || domain.ends_with(&format!(".{}", blocked))
+        })
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct CharacterSettings {
+    #[serde(default = "default_max_characters")]
+    pub max_per_account: i64,
+    #[serde(default = "default_deletion_grace_period_hours")]
+    pub deletion_grace_period_hours: i64,
+}
+
+impl CharacterSettings {
+    pub fn grace_period(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.deletion_grace_period_hours)
+    }
+}
+
+impl Default for CharacterSettings {
+    fn default() -> Self {
+        CharacterSettings {
+            max_per_account: default_max_characters(),
+            deletion_grace_period_hours: default_deletion_grace_period_hours(),
+        }
+    }
+}
+
+fn default_max_characters() -> i64 {
+    5
+}
+
+fn default_deletion_grace_period_hours() -> i64 {
+    72
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GatewaySettings {
+    pub heartbeat_interval_seconds: u64,
+    /// Connections that stay silent for longer than this, pongs included, are closed
+    pub client_timeout_seconds: u64,
+    pub messages_per_second: u32,
+    pub message_burst: u32,
+    /// Connections that get this many messages behind are disconnected
+    pub outbound_queue_size: usize,
+    pub max_message_bytes: usize,
+}
+
+impl GatewaySettings {
+    pub fn heartbeat_interval(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_interval_seconds)
+    }
+
+    pub fn client_timeout(&self) -> Duration {
+        Duration::from_secs(self.client_timeout_seconds)
+    }
+}
+
+impl Default for GatewaySettings {
+    fn default() -> Self {
+        GatewaySettings {
+            heartbeat_interval_seconds: 5,
+            client_timeout_seconds: 15,
+            messages_per_second: 5,
+            message_burst: 10,
+            outbound_queue_size: 64,
+            max_message_bytes: 4096,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ChatSettings {
+    /// Chat has its own, stricter, limit on top of the gateway one
+    pub messages_per_second: u32,
+    pub message_burst: u32,
+    pub max_message_length: usize,
+    pub history_page_size: i64,
+    /// Matched as whole words after folding the usual character substitutions
+    pub filtered_words: Vec<String>,
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        ChatSettings {
+            messages_per_second: 1,
+            message_burst: 5,
+            max_message_length: 300,
+            history_page_size: 50,
+            filtered_words: vec![],
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GameSettings {
+    /// Simulation steps per second
+    pub tick_rate: u32,
+    /// How often tick duration statistics are logged
+    pub stats_interval_seconds: u64,
+    /// Game events a slow subscriber can fall behind by before it starts missing some
+    pub event_queue_size: usize,
+}
+
+impl GameSettings {
+    pub fn tick_interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.tick_rate.max(1) as f64)
+    }
+
+    pub fn stats_interval_ticks(&self) -> u64 {
+        (self.stats_interval_seconds * self.tick_rate.max(1) as u64).max(1)
+    }
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            tick_rate: 10,
+            stats_interval_seconds: 60,
+            event_queue_size: 4096,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WorldSettings {
+    /// New characters arrive at the first spawn point of this zone
+    pub starting_zone: String,
+    pub steps_per_second: u32,
+    pub step_burst: u32,
+    /// Players only hear about entities at most this many tiles away
+    pub area_of_interest_radius: i32,
+    /// Positions are saved this often while moving, and always when leaving the world
+    pub position_flush_seconds: i64,
+}
+
+impl WorldSettings {
+    pub fn position_flush_interval(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.position_flush_seconds)
+    }
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        WorldSettings {
+            starting_zone: "greenvale".to_string(),
+            steps_per_second: 5,
+            step_burst: 3,
+            area_of_interest_radius: 8,
+            position_flush_seconds: 5,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CombatSettings {
+    /// Fights still going after this many rounds end in a draw
+    pub max_rounds: u32,
+    /// Players who don't act in time have their turn played for them, counted in game time
+    pub turn_timeout_seconds: i64,
+}
+
+impl CombatSettings {
+    pub fn turn_timeout(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.turn_timeout_seconds)
+    }
+}
+
+impl Default for CombatSettings {
+    fn default() -> Self {
+        CombatSettings {
+            max_rounds: 30,
+            turn_timeout_seconds: 30,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LeaderboardSettings {
+    /// Redis keys are `<key_prefix>:<board>`, tests give every app its own prefix
+    pub key_prefix: String,
+    /// How often the boards are rebuilt from Postgres, counted in game time
+    pub refresh_seconds: i64,
+    pub page_size: usize,
+    /// Characters shown above and below a character's own rank
+    pub neighbours: usize,
+    /// How much of each board is kept when a season ends
+    pub snapshot_size: usize,
+}
+
+impl LeaderboardSettings {
+    pub fn refresh_interval(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.refresh_seconds)
+    }
+}
+
+impl Default for LeaderboardSettings {
+    fn default() -> Self {
+        LeaderboardSettings {
+            key_prefix: "leaderboard".to_string(),
+            refresh_seconds: 60,
+            page_size: 50,
+            neighbours: 5,
+            snapshot_size: 100,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GuildSettings {
+    pub max_members: usize,
+    /// How many audit entries the guild page shows
+    pub audit_entries: i64,
+}
+impl Default for GuildSettings {
+    fn default() -> Self {
+        GuildSettings {
+            max_members: 50,
+            audit_entries: 50,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PresenceSettings {
+    /// Redis keys are `<key_prefix>:<user_id>`, tests give every app its own prefix
+    pub key_prefix: String,
+    /// Must outlive a few gateway heartbeats, otherwise connected players flicker offline
+    pub ttl_seconds: u64,
+    /// How long a connection may stay silent before its player shows as away
+    pub away_seconds: u64,
+}
+
+impl PresenceSettings {
+    pub fn ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.ttl_seconds)
+    }
+
+    pub fn away_after(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.away_seconds)
+    }
+}
+
+impl Default for PresenceSettings {
+    fn default() -> Self {
+        PresenceSettings {
+            key_prefix: "presence".to_string(),
+            ttl_seconds: 30,
+            away_seconds: 300,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct FriendSettings {
+    pub max_friends: usize,
+}
+
+impl Default for FriendSettings {
+    fn default() -> Self {
+        FriendSettings {
+            max_friends: 100,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MailSettings {
+    /// How long mail stays in a mailbox, unclaimed attachments go back to the sender after that
+    pub expiry_days: i64,
+    /// Item stacks per mail
+    pub max_attachments: usize,
+    /// How often expired mail is looked for, counted in game time
+    pub sweep_seconds: i64,
+    /// Who system mail appears to come from
+    pub system_sender: String,
+}
+
+impl MailSettings {
+    pub fn expiry(&self) -> chrono::Duration {
+        chrono::Duration::days(self.expiry_days)
+    }
+
+    pub fn sweep_interval(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.sweep_seconds)
+    }
+}
+
+impl Default for MailSettings {
+    fn default() -> Self {
+        MailSettings {
+            expiry_days: 30,
+            max_attachments: 6,
+            sweep_seconds: 60,
+            system_sender: "Postmaster".to_string(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AuctionSettings {
+    /// Share of the buyout price, or the starting bid without one, paid up front and kept either way
+    pub listing_fee_percent: i64,
+    pub min_listing_fee: i64,
+    /// How long a listing can run, the seller picks one of these
+    pub durations_hours: Vec<i64>,
+    /// How much a bid has to beat the current one by, at least 1 gold
+    pub min_bid_increment_percent: i64,
+    /// Active listings per character
+    pub max_listings: i64,
+    /// How often finished listings are looked for, counted in game time
+    pub sweep_seconds: i64,
+    /// Who auction house mail appears to come from
+    pub mail_sender: String,
+}
+
+impl AuctionSettings {
+    pub fn sweep_interval(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.sweep_seconds)
+    }
+}
+
+impl Default for AuctionSettings {
+    fn default() -> Self {
+        AuctionSettings {
+            listing_fee_percent: 5,
+            min_listing_fee: 1,
+            durations_hours: vec![12, 24, 48],
+            min_bid_increment_percent: 5,
+            max_listings: 20,
+            sweep_seconds: 30,
+            mail_sender: "Auction House".to_string(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CraftingSettings {
+    /// Jobs a character can have queued, the one in progress included
+    pub max_queue: i64,
+    /// How often finished jobs are looked for, counted in game time
+    pub sweep_seconds: i64,
+    /// Who mail with crafted items that didn't fit the backpack appears to come from
+    pub mail_sender: String,
+}
+
+impl CraftingSettings {
+    pub fn sweep_interval(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.sweep_seconds)
+    }
+}
+
+impl Default for CraftingSettings {
+    fn default() -> Self {
+        CraftingSettings {
+            max_queue: 10,
+            sweep_seconds: 1,
+            mail_sender: "Workshop".to_string(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AchievementSettings {
+    /// Who mail with achievement rewards appears to come from
+    pub mail_sender: String,
+}
+
+impl Default for AchievementSettings {
+    fn default() -> Self {
+        AchievementSettings {
+            mail_sender: "Hall of Records".to_string(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MatchmakingSettings {
+    /// How often the matchmaker looks for matches, expired ready checks and finished fights
+    pub tick_milliseconds: u64,
+    /// Rating difference a ticket accepts right away
+    pub base_tolerance: i32,
+    /// And how much more for every second it waits
+    pub tolerance_per_second: i32,
+    pub max_tolerance: i32,
+    /// Players per side in team battles
+    pub team_size: usize,
+    /// Players in a dungeon group
+    pub dungeon_size: usize,
+    /// Creatures a dungeon group fights
+    pub dungeon_creatures: Vec<String>,
+    /// How long everyone has to accept a match
+    pub ready_check_seconds: i64,
+}
+
+impl MatchmakingSettings {
+    pub fn tick_interval(&self) -> Duration {
+        Duration::from_millis(self.tick_milliseconds.max(1))
+    }
+
+    pub fn ready_check_timeout(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.ready_check_seconds)
+    }
+}
+
+impl Default for MatchmakingSettings {
+    fn default() -> Self {
+        MatchmakingSettings {
+            tick_milliseconds: 500,
+            base_tolerance: 100,
+            tolerance_per_second: 10,
+            max_tolerance: 500,
+            team_size: 2,
+            dungeon_size: 3,
+            dungeon_creatures: vec!["forest_spider".to_string(), "forest_spider".to_string(), "boar".to_string()],
+            ready_check_seconds: 20,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PartySettings {
+    pub max_size: usize,
+    pub invite_seconds: i64,
+    /// How long a member can be without a connection before they are dropped from the party
+    pub disconnect_grace_seconds: i64,
+    /// How long everyone has to roll need, greed or pass
+    pub loot_roll_seconds: i64,
+    /// How close party members have to stand to whoever won a fight to get a share of its loot
+    pub loot_range: i32,
+    /// Who loot mail comes from
+    pub loot_sender: String,
+}
+
+impl PartySettings {
+    pub fn invite_timeout(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.invite_seconds)
+    }
+
+    pub fn disconnect_grace(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.disconnect_grace_seconds)
+    }
+
+    pub fn loot_roll_timeout(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.loot_roll_seconds)
+    }
+}
+
+impl Default for PartySettings {
+    fn default() -> Self {
+        PartySettings {
+            max_size: 5,
+            invite_seconds: 60,
+            disconnect_grace_seconds: 120,
+            loot_roll_seconds: 30,
+            loot_range: 10,
+            loot_sender: "Party loot".to_string(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NpcSettings {
+    /// How close players have to stand to talk to an npc
+    pub talk_radius: i32,
+}
+
+impl Default for NpcSettings {
+    fn default() -> Self {
+        NpcSettings { talk_radius: 2 }
     }
 }
 
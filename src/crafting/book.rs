@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::crafting::{CraftingSkill, RecipeDefinition};
+use crate::game_data::{data_directory, load_data_files, GameDataError};
+use crate::items::ItemCatalog;
+use crate::world::WorldMap;
+
+#[derive(serde::Deserialize)]
+struct RecipeFile {
+    #[serde(default)]
+    recipes: Vec<RecipeDefinition>,
+}
+
+/// Every recipe from `data/recipes`. Items and stations are checked by `check_references`
+/// once the rest of the data is loaded.
+#[derive(Debug, Default)]
+pub struct RecipeBook {
+    recipes: HashMap<String, RecipeDefinition>,
+}
+
+impl RecipeBook {
+    pub fn new(definitions: Vec<RecipeDefinition>) -> Result<Self, GameDataError> {
+        let mut book = RecipeBook::default();
+        for definition in definitions {
+            definition.validate().map_err(GameDataError::Invalid)?;
+            if book.recipes.contains_key(&definition.id) {
+                return Err(GameDataError::Invalid(format!("Recipe {} is defined twice", definition.id)));
+            }
+            book.recipes.insert(definition.id.clone(), definition);
+        }
+        Ok(book)
+    }
+
+    pub fn load(directory: &Path) -> Result<Self, GameDataError> {
+        let mut definitions = Vec::new();
+        for (path, file) in load_data_files::<RecipeFile>(directory)? {
+            for definition in file.recipes {
+                definition.validate()
+                    .map_err(|e| GameDataError::Invalid(format!("{}: {}", path.display(), e)))?;
+                definitions.push(definition);
+            }
+        }
+        Self::new(definitions)
+    }
+
+    /// Items that don't exist and stations no zone has, empty when all is well
+    pub fn check_references(&self, items: &ItemCatalog, map: &WorldMap) -> Vec<String> {
+        let mut problems = Vec::new();
+        for recipe in self.sorted() {
+            for input in recipe.inputs.iter().filter(|i| !items.contains(&i.item)) {
+                problems.push(format!("Recipe {} takes unknown item {}", recipe.id, input.item));
+            }
+            for output in recipe.outputs.iter().filter(|o| !items.contains(&o.item)) {
+                problems.push(format!("Recipe {} makes unknown item {}", recipe.id, output.item));
+            }
+            if let Some(station) = recipe.station.as_ref().filter(|s| !map.has_station(s)) {
+                problems.push(format!("Recipe {} needs station {} which no zone has", recipe.id, station));
+            }
+        }
+        problems
+    }
+
+    pub fn get(&self, id: &str) -> Option<&RecipeDefinition> {
+        self.recipes.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.recipes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty()
+    }
+
+    /// By skill, then level, then name, the way the crafting page lists them
+    pub fn sorted(&self) -> Vec<&RecipeDefinition> {
+        let mut recipes: Vec<&RecipeDefinition> = self.recipes.values().collect();
+        recipes.sort_by_key(|r| (skill_order(r.skill), r.skill_level, r.name.as_str()));
+        recipes
+    }
+}
+
+fn skill_order(skill: CraftingSkill) -> usize {
+    CraftingSkill::ALL.iter().position(|s| *s == skill).unwrap_or_default()
+}
+
+pub fn get_recipe_book() -> Result<RecipeBook, GameDataError> {
+    RecipeBook::load(&data_directory().join("recipes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use uuid::Uuid;
+    use crate::crafting::{get_recipe_book, RecipeBook};
+    use crate::items::get_item_catalog;
+    use crate::world::get_world_map;
+
+    fn load(content: &str) -> Result<RecipeBook, crate::game_data::GameDataError> {
+        let directory = std::env::temp_dir().join(format!("yaug-recipes-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("recipes.toml"), content).unwrap();
+        let book = RecipeBook::load(&directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+        book
+    }
+
+    fn recipe(id: &str, input: &str, output: &str, station: &str) -> String {
+        format!(
+            "[[recipes]]\nid = \"{}\"\nname = \"{}\"\nskill = \"cooking\"\nstation = \"{}\"\nseconds = 5\n\
+             inputs = [{{ item = \"{}\", quantity = 2 }}]\noutputs = [{{ item = \"{}\" }}]\n",
+            id, id, station, input, output
+        )
+    }
+
+    #[test]
+    fn shipped_recipes_reference_existing_data() {
+        let book = assert_ok!(get_recipe_book());
+        assert!(!book.is_empty());
+        let problems = book.check_references(&get_item_catalog().unwrap(), &get_world_map().unwrap());
+        assert_eq!(Vec::<String>::new(), problems);
+    }
+
+    #[test]
+    fn recipes_are_read_with_defaults() {
+        let book = assert_ok!(load(&recipe("toast", "bread", "bread", "cooking_fire")));
+        let toast = book.get("toast").unwrap();
+        assert_eq!(1, toast.skill_level);
+        assert_eq!(2, toast.inputs[0].quantity);
+        assert_eq!(1, toast.outputs[0].quantity);
+        assert_err!(load(&recipe("toast", "bread", "bread", "cooking_fire").replace("cooking\"", "juggling\"")));
+    }
+
+    #[test]
+    fn recipes_are_defined_once() {
+        let toast = recipe("toast", "bread", "bread", "cooking_fire");
+        assert_err!(load(&[toast.clone(), toast].join("\n")));
+    }
+
+    #[test]
+    fn unknown_references_are_reported() {
+        let book = assert_ok!(load(&[
+            recipe("stew", "moon_dust", "bread", "cooking_fire"),
+            recipe("pie", "bread", "star_pie", "cooking_fire"),
+            recipe("toast", "bread", "bread", "toaster"),
+        ].join("\n")));
+
+        let problems = book.check_references(&get_item_catalog().unwrap(), &get_world_map().unwrap());
+
+        assert_eq!(3, problems.len(), "{:?}", problems);
+    }
+}
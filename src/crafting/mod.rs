@@ -0,0 +1,13 @@
+mod book;
+mod recipe;
+mod service;
+mod skill;
+mod store;
+mod system;
+
+pub use book::{get_recipe_book, RecipeBook};
+pub use recipe::{RecipeDefinition, RecipeItem};
+pub use service::{CraftingError, CraftingService, SkillProgress};
+pub use skill::{experience_for_level, experience_gain, level_for_experience, CraftingSkill, MAX_SKILL_LEVEL};
+pub use store::CraftingJob;
+pub use system::CraftingQueueSystem;
\ No newline at end of file
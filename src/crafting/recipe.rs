@@ -0,0 +1,117 @@
+use crate::crafting::{CraftingSkill, MAX_SKILL_LEVEL};
+use crate::game_data::is_valid_id;
+
+fn one() -> i32 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct RecipeItem {
+    pub item: String,
+    #[serde(default = "one")]
+    pub quantity: i32,
+}
+
+/// One recipe as written in `data/recipes`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct RecipeDefinition {
+    pub id: String,
+    pub name: String,
+    pub skill: CraftingSkill,
+    /// Skill level needed to craft it
+    #[serde(default = "one")]
+    pub skill_level: i32,
+    /// Crafting station that has to be in the character's zone, anywhere when unset
+    #[serde(default)]
+    pub station: Option<String>,
+    pub seconds: i64,
+    /// Skill experience for every craft, before easy recipes are scaled down
+    #[serde(default)]
+    pub experience: i64,
+    pub inputs: Vec<RecipeItem>,
+    pub outputs: Vec<RecipeItem>,
+}
+
+impl RecipeDefinition {
+    pub fn duration(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.seconds)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_id(&self.id) {
+            return Err(format!("{:?} is not a valid recipe id", self.id));
+        }
+        if self.name.trim().is_empty() {
+            return Err(format!("Recipe {} needs a name", self.id));
+        }
+        if !(1..=MAX_SKILL_LEVEL).contains(&self.skill_level) {
+            return Err(format!("Recipe {} needs a skill level between 1 and {}", self.id, MAX_SKILL_LEVEL));
+        }
+        if let Some(station) = self.station.as_ref().filter(|s| !is_valid_id(s)) {
+            return Err(format!("Recipe {}: {:?} is not a valid station id", self.id, station));
+        }
+        if self.seconds < 0 || self.experience < 0 {
+            return Err(format!("Recipe {} can't take negative time or give negative experience", self.id));
+        }
+        if self.inputs.is_empty() || self.outputs.is_empty() {
+            return Err(format!("Recipe {} needs inputs and outputs", self.id));
+        }
+        for item in self.inputs.iter().chain(&self.outputs) {
+            if !is_valid_id(&item.item) {
+                return Err(format!("Recipe {}: {:?} is not a valid item id", self.id, item.item));
+            }
+            if item.quantity <= 0 {
+                return Err(format!("Recipe {}: {} is not a valid quantity", self.id, item.quantity));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use crate::crafting::{CraftingSkill, RecipeDefinition, RecipeItem};
+
+    fn recipe() -> RecipeDefinition {
+        RecipeDefinition {
+            id: "iron_sword".to_string(),
+            name: "Iron Sword".to_string(),
+            skill: CraftingSkill::Blacksmithing,
+            skill_level: 1,
+            station: Some("forge".to_string()),
+            seconds: 30,
+            experience: 20,
+            inputs: vec![RecipeItem { item: "iron_ore".to_string(), quantity: 5 }],
+            outputs: vec![RecipeItem { item: "iron_sword".to_string(), quantity: 1 }],
+        }
+    }
+
+    #[test]
+    fn a_well_formed_recipe_is_valid() {
+        assert_ok!(recipe().validate());
+    }
+
+    #[test]
+    fn recipes_need_inputs_and_outputs() {
+        let mut nothing_in = recipe();
+        nothing_in.inputs.clear();
+        assert_err!(nothing_in.validate());
+        let mut nothing_out = recipe();
+        nothing_out.outputs.clear();
+        assert_err!(nothing_out.validate());
+    }
+
+    #[test]
+    fn quantities_and_levels_must_make_sense() {
+        let mut recipe = recipe();
+        recipe.inputs[0].quantity = 0;
+        assert_err!(recipe.validate());
+        recipe.inputs[0].quantity = 1;
+        recipe.skill_level = 0;
+        assert_err!(recipe.validate());
+        recipe.skill_level = 1;
+        recipe.station = Some("Big Anvil".to_string());
+        assert_err!(recipe.validate());
+    }
+}
@@ -0,0 +1,342 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::characters::Character;
+use crate::configuration::CraftingSettings;
+use crate::crafting::{experience_for_level, experience_gain, level_for_experience, CraftingSkill, RecipeBook, RecipeDefinition, RecipeItem, MAX_SKILL_LEVEL};
+use crate::crafting::store::{add_skill_experience, get_finished_job_ids, get_jobs, get_skill_experience, remove_finished_job, remove_unfinished_job, shift_jobs, store_job, CraftingJob};
+use crate::events::{GameEvent, GameEvents};
+use crate::gateway::{ClientError, ConnectionRegistry, ErrorCode, ServerMessage};
+use crate::items::{InventoryError, InventoryService, ItemStack};
+use crate::mail::{get_character_owner, MailError, MailKind, MailService, SystemMail};
+use crate::utils::error_chain_fmt;
+use crate::world::{WorldError, WorldService};
+
+/// Finished jobs handed out per sweep, the rest waits for the next one
+const COMPLETION_BATCH_SIZE: i64 = 100;
+
+#[derive(thiserror::Error)]
+pub enum CraftingError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("There is no recipe called {0}")]
+    UnknownRecipe(String),
+    #[error("That needs {} level {level}", skill.title())]
+    SkillTooLow { skill: CraftingSkill, level: i32 },
+    #[error("That needs a {} nearby", station.replace('_', " "))]
+    MissingStation { station: String },
+    #[error("You can have at most {0} crafts queued")]
+    QueueFull(i64),
+    #[error("That craft is no longer in the queue")]
+    JobNotFound,
+    #[error(transparent)]
+    Inventory(#[from] InventoryError),
+    #[error(transparent)]
+    Mail(#[from] MailError),
+    #[error(transparent)]
+    World(#[from] WorldError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for CraftingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for CraftingError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            CraftingError::ValidationError(_) => ErrorCode::InvalidMessage,
+            CraftingError::UnknownRecipe(_) | CraftingError::JobNotFound => ErrorCode::NotFound,
+            CraftingError::SkillTooLow { .. } | CraftingError::MissingStation { .. } | CraftingError::QueueFull(_) => ErrorCode::InvalidAction,
+            CraftingError::Inventory(e) => e.code(),
+            CraftingError::Mail(e) => e.code(),
+            CraftingError::World(e) => e.code(),
+            CraftingError::UnexpectedError(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+/// How far along a character is with one skill
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SkillProgress {
+    pub skill: &'static str,
+    pub title: &'static str,
+    pub level: i32,
+    pub experience: i64,
+    /// Total experience for the next level, none at the top
+    pub next_level_at: Option<i64>,
+}
+
+impl SkillProgress {
+    fn new(skill: CraftingSkill, experience: i64) -> Self {
+        let level = level_for_experience(experience);
+        SkillProgress {
+            skill: skill.as_str(),
+            title: skill.title(),
+            level,
+            experience,
+            next_level_at: (level < MAX_SKILL_LEVEL).then(|| experience_for_level(level + 1)),
+        }
+    }
+}
+
+/// Inputs are taken from the inventory when a craft is queued and outputs handed out by
+/// `CraftingQueueSystem` once it is done, a cancelled craft gives its inputs back. A character's
+/// crafts run one after the other. Queueing and handing out both go through the inventory lock,
+/// so the queue of one character never changes from two places at once.
+pub struct CraftingService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    inventory: Arc<InventoryService>,
+    mail: Arc<MailService>,
+    world: Arc<WorldService>,
+    events: GameEvents,
+    book: Arc<RecipeBook>,
+    settings: CraftingSettings,
+}
+
+impl CraftingService {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        inventory: Arc<InventoryService>,
+        mail: Arc<MailService>,
+        world: Arc<WorldService>,
+        events: GameEvents,
+        book: Arc<RecipeBook>,
+        settings: CraftingSettings,
+    ) -> Self {
+        CraftingService { pool, registry, inventory, mail, world, events, book, settings }
+    }
+
+    pub fn book(&self) -> &RecipeBook {
+        &self.book
+    }
+
+    pub fn settings(&self) -> &CraftingSettings {
+        &self.settings
+    }
+
+    /// The character's queue, the craft in progress first
+    pub async fn queue(&self, character_id: Uuid) -> Result<Vec<CraftingJob>, CraftingError> {
+        Ok(get_jobs(&self.pool, character_id).await?)
+    }
+
+    /// Every skill, including the ones never used
+    pub async fn skills(&self, character_id: Uuid) -> Result<Vec<SkillProgress>, CraftingError> {
+        let experience = get_skill_experience(&self.pool, character_id).await?;
+        Ok(CraftingSkill::ALL
+            .into_iter()
+            .map(|skill| SkillProgress::new(skill, experience.get(&skill).copied().unwrap_or_default()))
+            .collect())
+    }
+
+    /// `Iron Ore x5`
+    pub fn describe(&self, item: &RecipeItem) -> String {
+        let name = self.inventory
+            .catalog()
+            .get(&item.item)
+            .map(|item| item.name.clone())
+            .unwrap_or_else(|| item.item.clone());
+        format!("{} x{}", name, item.quantity)
+    }
+
+    /// Queues `count` crafts of a recipe behind whatever is queued already, taking the inputs for
+    /// all of them at once
+    #[tracing::instrument(
+    name = "Queue crafts",
+    skip(self, character),
+    fields(character_id = % character.id)
+    )]
+    pub async fn enqueue(
+        &self,
+        character: &Character,
+        recipe_id: &str,
+        count: i64,
+    ) -> Result<Vec<CraftingJob>, CraftingError> {
+        let recipe = self.book
+            .get(recipe_id)
+            .ok_or_else(|| CraftingError::UnknownRecipe(recipe_id.to_string()))?;
+        if !(1..=self.settings.max_queue).contains(&count) {
+            return Err(CraftingError::ValidationError(format!(
+                "You can queue between 1 and {} crafts", self.settings.max_queue
+            )));
+        }
+        let experience = get_skill_experience(&self.pool, character.id).await?;
+        if level_for_experience(experience.get(&recipe.skill).copied().unwrap_or_default()) < recipe.skill_level {
+            return Err(CraftingError::SkillTooLow { skill: recipe.skill, level: recipe.skill_level });
+        }
+        if let Some(station) = &recipe.station {
+            let position = self.world.locate(character.id).await?;
+            if !self.world.map().get(&position.zone_id).is_some_and(|zone| zone.has_station(station)) {
+                return Err(CraftingError::MissingStation { station: station.clone() });
+            }
+        }
+
+        let inputs = scaled(&recipe.inputs, count)?;
+        let mut tx = self.begin().await?;
+        self.inventory.remove_stacks(&mut tx, character.id, &inputs).await?;
+        // read under the inventory lock, nothing else can queue for the character until commit
+        let queued = get_jobs(&mut tx, character.id).await?;
+        if queued.len() as i64 + count > self.settings.max_queue {
+            return Err(CraftingError::QueueFull(self.settings.max_queue));
+        }
+        let now = Utc::now();
+        let mut start = queued.last().map_or(now, |job| job.finishes_at.max(now));
+        let mut jobs = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let job = CraftingJob {
+                id: Uuid::new_v4(),
+                character_id: character.id,
+                recipe_id: recipe.id.clone(),
+                started_at: start,
+                finishes_at: start + recipe.duration(),
+            };
+            store_job(&mut tx, &job).await?;
+            start = job.finishes_at;
+            jobs.push(job);
+        }
+        commit(tx).await?;
+        Ok(jobs)
+    }
+
+    /// Gives the inputs back and moves everything queued after it up, crafts that are done can't
+    /// be cancelled any more
+    #[tracing::instrument(
+    name = "Cancel craft",
+    skip(self)
+    )]
+    pub async fn cancel(&self, character_id: Uuid, job_id: Uuid) -> Result<CraftingJob, CraftingError> {
+        let now = Utc::now();
+        let mut tx = self.begin().await?;
+        let job = remove_unfinished_job(&mut tx, character_id, job_id, now)
+            .await?
+            .ok_or(CraftingError::JobNotFound)?;
+        if let Some(recipe) = self.book.get(&job.recipe_id) {
+            let inputs = scaled(&recipe.inputs, 1)?;
+            self.hand_out(&mut tx, character_id, inputs, format!("Cancelled: {}", recipe.name)).await?;
+        }
+        shift_jobs(&mut tx, character_id, job.finishes_at, job.finishes_at - job.started_at.max(now)).await?;
+        commit(tx).await?;
+        Ok(job)
+    }
+
+    /// Hands out the outputs of every craft done by `now` and the skill experience for it
+    #[tracing::instrument(
+    name = "Complete crafts",
+    skip(self)
+    )]
+    pub async fn complete(&self, now: DateTime<Utc>) -> Result<usize, CraftingError> {
+        let mut completed = 0;
+        for job_id in get_finished_job_ids(&self.pool, now, COMPLETION_BATCH_SIZE).await? {
+            let mut tx = self.begin().await?;
+            // cancelled or completed in the meantime
+            let Some(job) = remove_finished_job(&mut tx, job_id, now).await? else {
+                continue;
+            };
+            let Some(recipe) = self.book.get(&job.recipe_id) else {
+                tracing::warn!(job_id = %job.id, recipe_id = %job.recipe_id, "Dropped a craft of a recipe that no longer exists");
+                commit(tx).await?;
+                continue;
+            };
+            let outputs = scaled(&recipe.outputs, 1)?;
+            self.hand_out(&mut tx, job.character_id, outputs, format!("Crafted: {}", recipe.name)).await?;
+            let level_up = self.train(&mut tx, job.character_id, recipe).await?;
+            commit(tx).await?;
+
+            if let Some(user_id) = get_character_owner(&self.pool, job.character_id).await? {
+                let user_id = UserId::from(user_id);
+                let made: Vec<String> = recipe.outputs.iter().map(|o| self.describe(o)).collect();
+                self.notify(user_id, format!("You crafted {}", made.join(", ")));
+                if let Some(level) = level_up {
+                    self.notify(user_id, format!("Your {} is now level {}", recipe.skill.title(), level));
+                }
+                self.events.publish(GameEvent::ItemCrafted {
+                    user_id,
+                    character_id: job.character_id,
+                    recipe_id: recipe.id.clone(),
+                });
+            }
+            completed += 1;
+        }
+        Ok(completed)
+    }
+
+    /// Into the backpack, or by mail when it doesn't all fit
+    async fn hand_out(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        items: Vec<ItemStack>,
+        subject: String,
+    ) -> Result<(), CraftingError> {
+        match self.inventory.add_stacks(tx, character_id, &items).await {
+            Ok(()) => Ok(()),
+            Err(InventoryError::InventoryFull) => {
+                let mail = SystemMail {
+                    recipient_id: character_id,
+                    subject,
+                    body: "Your backpack was full, so it was sent here instead.".to_string(),
+                    money: None,
+                    items,
+                };
+                self.mail.deliver(tx, MailKind::System, &self.settings.mail_sender, mail).await?;
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Adds the experience for one craft, returns the new level if it went up
+    async fn train(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        recipe: &RecipeDefinition,
+    ) -> Result<Option<i32>, CraftingError> {
+        let before = get_skill_experience(&mut *tx, character_id)
+            .await?
+            .get(&recipe.skill)
+            .copied()
+            .unwrap_or_default();
+        let level = level_for_experience(before);
+        let gain = experience_gain(level, recipe.skill_level, recipe.experience);
+        if gain == 0 {
+            return Ok(None);
+        }
+        let after = level_for_experience(add_skill_experience(tx, character_id, recipe.skill, gain).await?);
+        Ok((after > level).then_some(after))
+    }
+
+    fn notify(&self, user_id: UserId, message: String) {
+        self.registry.send_to_user(user_id, &ServerMessage::Notice { message });
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, CraftingError> {
+        Ok(self.pool.begin().await.context("Failed to begin crafting transaction")?)
+    }
+}
+
+/// Recipe items `count` times over as inventory stacks
+fn scaled(items: &[RecipeItem], count: i64) -> Result<Vec<ItemStack>, CraftingError> {
+    items
+        .iter()
+        .map(|item| {
+            let quantity = i32::try_from(item.quantity as i64 * count)
+                .map_err(|_| CraftingError::ValidationError("That is too many at once".to_string()))?;
+            Ok(ItemStack { item_id: item.item.clone(), quantity })
+        })
+        .collect()
+}
+
+async fn commit(tx: Transaction<'_, Postgres>) -> Result<(), CraftingError> {
+    Ok(tx.commit().await.context("Failed to commit crafting transaction")?)
+}
@@ -0,0 +1,122 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CraftingSkill {
+    Blacksmithing,
+    Leatherworking,
+    Alchemy,
+    Cooking,
+}
+
+impl CraftingSkill {
+    pub const ALL: [CraftingSkill; 4] = [
+        CraftingSkill::Blacksmithing,
+        CraftingSkill::Leatherworking,
+        CraftingSkill::Alchemy,
+        CraftingSkill::Cooking,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CraftingSkill::Blacksmithing => "blacksmithing",
+            CraftingSkill::Leatherworking => "leatherworking",
+            CraftingSkill::Alchemy => "alchemy",
+            CraftingSkill::Cooking => "cooking",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            CraftingSkill::Blacksmithing => "Blacksmithing",
+            CraftingSkill::Leatherworking => "Leatherworking",
+            CraftingSkill::Alchemy => "Alchemy",
+            CraftingSkill::Cooking => "Cooking",
+        }
+    }
+}
+
+impl TryFrom<String> for CraftingSkill {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "blacksmithing" => Ok(CraftingSkill::Blacksmithing),
+            "leatherworking" => Ok(CraftingSkill::Leatherworking),
+            "alchemy" => Ok(CraftingSkill::Alchemy),
+            "cooking" => Ok(CraftingSkill::Cooking),
+            other => Err(format!("{} is not a crafting skill", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for CraftingSkill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+pub const MAX_SKILL_LEVEL: i32 = 50;
+/// Recipes this many levels below the skill are learned from at half the rate
+const EASY_RECIPE_LEVELS: i32 = 5;
+/// And this many levels below teach nothing at all
+const TRIVIAL_RECIPE_LEVELS: i32 = 10;
+
+/// Total experience needed to reach `level`, every level takes 100 more than the one before
+pub fn experience_for_level(level: i32) -> i64 {
+    let level = level.clamp(1, MAX_SKILL_LEVEL) as i64;
+    50 * (level - 1) * level
+}
+
+pub fn level_for_experience(experience: i64) -> i32 {
+    let mut level = 1;
+    while level < MAX_SKILL_LEVEL && experience >= experience_for_level(level + 1) {
+        level += 1;
+    }
+    level
+}
+
+/// What crafting a recipe of `recipe_level` teaches at `skill_level`, less the further the skill
+/// has grown past the recipe
+pub fn experience_gain(skill_level: i32, recipe_level: i32, experience: i64) -> i64 {
+    let lead = skill_level - recipe_level;
+    if lead >= TRIVIAL_RECIPE_LEVELS {
+        0
+    } else if lead >= EASY_RECIPE_LEVELS {
+        experience / 2
+    } else {
+        experience
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok_eq};
+    use crate::crafting::{experience_for_level, experience_gain, level_for_experience, CraftingSkill, MAX_SKILL_LEVEL};
+
+    #[test]
+    fn skills_round_trip() {
+        for skill in CraftingSkill::ALL {
+            assert_ok_eq!(CraftingSkill::try_from(skill.as_str().to_string()), skill);
+        }
+        assert_err!(CraftingSkill::try_from("juggling".to_string()));
+    }
+
+    #[test]
+    fn levels_follow_experience() {
+        assert_eq!(0, experience_for_level(1));
+        assert_eq!(100, experience_for_level(2));
+        assert_eq!(300, experience_for_level(3));
+        assert_eq!(1, level_for_experience(0));
+        assert_eq!(1, level_for_experience(99));
+        assert_eq!(2, level_for_experience(100));
+        assert_eq!(3, level_for_experience(300));
+        assert_eq!(MAX_SKILL_LEVEL, level_for_experience(i64::MAX));
+    }
+
+    #[test]
+    fn easy_recipes_teach_less_and_trivial_ones_nothing() {
+        assert_eq!(20, experience_gain(1, 1, 20));
+        assert_eq!(20, experience_gain(5, 1, 20));
+        assert_eq!(10, experience_gain(6, 1, 20));
+        assert_eq!(0, experience_gain(11, 1, 20));
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::crafting::CraftingSkill;
+
+/// One craft of a recipe, its inputs were taken when it was queued
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CraftingJob {
+    pub id: Uuid,
+    pub character_id: Uuid,
+    pub recipe_id: String,
+    pub started_at: DateTime<Utc>,
+    pub finishes_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(
+name = "Store crafting job",
+skip(tx, job),
+fields(job_id = % job.id)
+)]
+pub async fn store_job(
+    tx: &mut Transaction<'_, Postgres>,
+    job: &CraftingJob,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO crafting_jobs (id, character_id, recipe_id, started_at, finishes_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        job.id,
+        job.character_id,
+        job.recipe_id,
+        job.started_at,
+        job.finishes_at
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store crafting job")?;
+    Ok(())
+}
+
+/// The character's queue, the job in progress first
+pub async fn get_jobs(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+) -> Result<Vec<CraftingJob>, anyhow::Error> {
+    sqlx::query_as!(
+        CraftingJob,
+        r#"
+        SELECT id, character_id, recipe_id, started_at, finishes_at
+        FROM crafting_jobs
+        WHERE character_id = $1
+        ORDER BY finishes_at
+        "#,
+        character_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch crafting jobs")
+}
+
+/// Removes a job of the character that hasn't finished by `now`. Deleting is what claims it, so a
+/// job is only ever cancelled or completed once.
+#[tracing::instrument(
+name = "Remove unfinished crafting job",
+skip(tx)
+)]
+pub async fn remove_unfinished_job(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    job_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Option<CraftingJob>, anyhow::Error> {
+    sqlx::query_as!(
+        CraftingJob,
+        r#"
+        DELETE FROM crafting_jobs
+        WHERE id = $1 AND character_id = $2 AND finishes_at > $3
+        RETURNING id, character_id, recipe_id, started_at, finishes_at
+        "#,
+        job_id,
+        character_id,
+        now
+    )
+        .fetch_optional(tx)
+        .await
+        .context("Failed to remove crafting job")
+}
+
+#[tracing::instrument(
+name = "Remove finished crafting job",
+skip(tx)
+)]
+pub async fn remove_finished_job(
+    tx: &mut Transaction<'_, Postgres>,
+    job_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Option<CraftingJob>, anyhow::Error> {
+    sqlx::query_as!(
+        CraftingJob,
+        r#"
+        DELETE FROM crafting_jobs
+        WHERE id = $1 AND finishes_at <= $2
+        RETURNING id, character_id, recipe_id, started_at, finishes_at
+        "#,
+        job_id,
+        now
+    )
+        .fetch_optional(tx)
+        .await
+        .context("Failed to remove crafting job")
+}
+
+/// Moves every job queued from `from` on earlier by `by`, to close the gap a cancelled job left
+#[tracing::instrument(
+name = "Shift crafting jobs",
+skip(tx)
+)]
+pub async fn shift_jobs(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    from: DateTime<Utc>,
+    by: chrono::Duration,
+) -> Result<(), anyhow::Error> {
+    let seconds = by.num_milliseconds() as f64 / 1000.0;
+    sqlx::query!(
+        r#"
+        UPDATE crafting_jobs
+        SET started_at  = started_at - make_interval(secs => $3),
+            finishes_at = finishes_at - make_interval(secs => $3)
+        WHERE character_id = $1 AND started_at >= $2
+        "#,
+        character_id,
+        from,
+        seconds
+    )
+        .execute(tx)
+        .await
+        .context("Failed to shift crafting jobs")?;
+    Ok(())
+}
+
+/// Soonest first, each one is claimed again before anything happens to it
+#[tracing::instrument(
+name = "Get finished crafting jobs",
+skip(executor)
+)]
+pub async fn get_finished_job_ids(
+    executor: impl PgExecutor<'_>,
+    now: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Uuid>, anyhow::Error> {
+    let ids = sqlx::query!(
+        r#"
+        SELECT id
+        FROM crafting_jobs
+        WHERE finishes_at <= $1
+        ORDER BY finishes_at
+        LIMIT $2
+        "#,
+        now,
+        limit
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch finished crafting jobs")?
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+    Ok(ids)
+}
+
+/// Skills the character never used are left out
+pub async fn get_skill_experience(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+) -> Result<HashMap<CraftingSkill, i64>, anyhow::Error> {
+    sqlx::query!(
+        "SELECT skill, experience FROM character_crafting_skills WHERE character_id = $1",
+        character_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch crafting skills")?
+        .into_iter()
+        .map(|r| Ok((CraftingSkill::try_from(r.skill).map_err(|e| anyhow!(e))?, r.experience)))
+        .collect()
+}
+
+/// Returns the new total
+#[tracing::instrument(
+name = "Add skill experience",
+skip(tx)
+)]
+pub async fn add_skill_experience(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    skill: CraftingSkill,
+    experience: i64,
+) -> Result<i64, anyhow::Error> {
+    let total = sqlx::query!(
+        r#"
+        INSERT INTO character_crafting_skills (character_id, skill, experience)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (character_id, skill) DO UPDATE
+            SET experience = character_crafting_skills.experience + excluded.experience
+        RETURNING experience
+        "#,
+        character_id,
+        skill.as_str(),
+        experience
+    )
+        .fetch_one(tx)
+        .await
+        .context("Failed to add skill experience")?
+        .experience;
+    Ok(total)
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use crate::crafting::CraftingService;
+use crate::game_loop::{GameSystem, TickContext};
+
+/// Hands out finished crafts every so often in game time, the first tick included
+pub struct CraftingQueueSystem {
+    crafting: Arc<CraftingService>,
+    interval: chrono::Duration,
+    last_sweep: Option<DateTime<Utc>>,
+}
+
+impl CraftingQueueSystem {
+    pub fn new(crafting: Arc<CraftingService>, interval: chrono::Duration) -> Self {
+        CraftingQueueSystem { crafting, interval, last_sweep: None }
+    }
+}
+
+#[async_trait]
+impl GameSystem for CraftingQueueSystem {
+    fn name(&self) -> &'static str {
+        "crafting_queue"
+    }
+
+    async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error> {
+        if let Some(last_sweep) = self.last_sweep {
+            if ctx.now - last_sweep < self.interval {
+                return Ok(());
+            }
+        }
+        self.last_sweep = Some(ctx.now);
+        let completed = self.crafting.complete(ctx.now).await?;
+        if completed > 0 {
+            tracing::info!(tick = ctx.tick, completed, "Handed out finished crafts");
+        }
+        Ok(())
+    }
+}
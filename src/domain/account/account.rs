@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::domain::{AccountEmail, AccountRole, AccountStatus};
+
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub id: Uuid,
+    pub email: AccountEmail,
+    pub status: AccountStatus,
+    pub roles: Vec<AccountRole>,
+    pub created_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub display_name: Option<String>,
+}
+
+impl Account {
+    /// Account as it looks right after registration, before anything else touched it
+    pub fn new(id: Uuid, email: AccountEmail) -> Self {
+        Account {
+            id,
+            email,
+            status: AccountStatus::Pending,
+            roles: vec![AccountRole::Player],
+            created_at: Utc::now(),
+            last_login_at: None,
+            display_name: None,
+        }
+    }
+
+    pub fn has_role(&self, role: AccountRole) -> bool {
+        self.roles.contains(&role)
+    }
+
+    /// Name that is safe to show on pages, the email is never shown to anyone but the owner
+    pub fn name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or("Unnamed adventurer")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::domain::{Account, AccountEmail, AccountRole, AccountStatus};
+
+    fn account() -> Account {
+        Account::new(
+            Uuid::new_v4(),
+            AccountEmail::parse("user@domain.com".to_string()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn new_account_is_a_pending_player() {
+        let account = account();
+        assert_eq!(AccountStatus::Pending, account.status);
+        assert!(account.has_role(AccountRole::Player));
+        assert!(!account.has_role(AccountRole::Admin));
+        assert!(account.last_login_at.is_none());
+    }
+
+    #[test]
+    fn statuses_and_roles_round_trip_through_strings() {
+        for status in [AccountStatus::Pending, AccountStatus::Active, AccountStatus::Suspended] {
+            assert_eq!(Ok(status), AccountStatus::try_from(status.as_str().to_string()));
+        }
+        for role in [AccountRole::Player, AccountRole::Moderator, AccountRole::Admin] {
+            assert_eq!(Ok(role), AccountRole::try_from(role.as_str().to_string()));
+        }
+    }
+
+    #[test]
+    fn unknown_status_is_rejected() {
+        assert!(AccountStatus::try_from("banana".to_string()).is_err());
+    }
+
+    #[test]
+    fn account_without_display_name_does_not_leak_email() {
+        let account = account();
+        assert!(!account.name().contains(account.email.as_ref()));
+    }
+}
@@ -0,0 +1,8 @@
+#[allow(clippy::module_inception)]
+mod account;
+mod role;
+mod status;
+
+pub use account::Account;
+pub use role::AccountRole;
+pub use status::AccountStatus;
\ No newline at end of file
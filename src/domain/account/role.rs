@@ -0,0 +1,35 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountRole {
+    Player,
+    Moderator,
+    Admin,
+}
+
+impl AccountRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountRole::Player => "player",
+            AccountRole::Moderator => "moderator",
+            AccountRole::Admin => "admin",
+        }
+    }
+}
+
+impl TryFrom<String> for AccountRole {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "player" => Ok(AccountRole::Player),
+            "moderator" => Ok(AccountRole::Moderator),
+            "admin" => Ok(AccountRole::Admin),
+            other => Err(format!("{} is not a valid account role", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for AccountRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
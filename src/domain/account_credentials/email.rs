@@ -3,11 +3,15 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use validator::validate_email;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AccountEmail(String);
 
 impl AccountEmail {
+    /// Surrounding whitespace is dropped and the domain is lower-cased and converted to punycode,
+    /// the local part is kept as typed since some providers treat it as case-sensitive.
     pub fn parse(v: String) -> Result<Self, String> {
+        let v = normalize(&v).ok_or_else(|| format!("{} is not a valid email address", v))?;
+
         if validate_email(&v) {
             lazy_static! {
                 static ref RE:Regex = Regex::new(
@@ -24,6 +28,16 @@ impl AccountEmail {
             Err(format!("{} is not a valid email address", v))
         }
     }
+
+    pub fn domain(&self) -> &str {
+        self.0.rsplit_once('@').map(|(_, domain)| domain).unwrap_or_default()
+    }
+}
+
+fn normalize(v: &str) -> Option<String> {
+    let (local, domain) = v.trim().rsplit_once('@')?;
+    let domain = idna::domain_to_ascii(domain).ok()?;
+    Some(format!("{}@{}", local, domain))
 }
 
 impl AsRef<str> for AccountEmail {
@@ -64,7 +78,7 @@ mod tests {
 
     #[test]
     fn valid_long_domain_emails_are_parsed_successfully() {
-        let email = format!("user@active.few.sub.domain");
+        let email = "user@active.few.sub.domain".to_string();
         assert_ok!(AccountEmail::parse(email));
     }
 
@@ -97,4 +111,23 @@ mod tests {
         let email = "user@domain".to_string();
         assert_err!(AccountEmail::parse(email));
     }
+
+    #[test]
+    fn whitespace_is_trimmed() {
+        let email = AccountEmail::parse("  user@domain.com \n".to_string()).unwrap();
+        assert_eq!("user@domain.com", email.as_ref());
+    }
+
+    #[test]
+    fn domain_is_lower_cased_but_local_part_is_kept() {
+        let email = AccountEmail::parse("Bob.Smith@Example.COM".to_string()).unwrap();
+        assert_eq!("Bob.Smith@example.com", email.as_ref());
+        assert_eq!("example.com", email.domain());
+    }
+
+    #[test]
+    fn international_domain_is_converted_to_punycode() {
+        let email = AccountEmail::parse("user@Bücher.example".to_string()).unwrap();
+        assert_eq!("user@xn--bcher-kva.example", email.as_ref());
+    }
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use anyhow::Context;
-use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, Version};
+use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
 use argon2::password_hash::SaltString;
 use rand::Rng;
 use secrecy::{ExposeSecret, Secret};
@@ -23,10 +23,7 @@ impl AccountPassword {
         let pass = v.expose_secret();
 
         let empty_or_whitespace = pass.trim().is_empty();
-        let correct_length = match pass.len() {
-            8..=120 => true,
-            _ => false
-        };
+        let correct_length = matches!(pass.len(), 8..=120);
 
         let required_characters = ['~', '`', '!', '@', '#', '$', '%', '^', '&', '*', '(', ')', '_', '-', '+', '=', '{', '[', '}', ']', '|', '\\', ':', ';', '"', '\'', '<', ',', '>', '.', '?', '/'];
         let contains_required_characters = pass.chars().any(|c| required_characters.contains(&c));
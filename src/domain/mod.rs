@@ -1,3 +1,7 @@
+mod account;
 mod account_credentials;
+mod profile;
 
-pub use account_credentials::{AccountCredentials, AccountEmail, AccountPassword};
\ No newline at end of file
+pub use account::{Account, AccountRole, AccountStatus};
+pub use account_credentials::{AccountCredentials, AccountEmail, AccountPassword};
+pub use profile::{check_name_is_acceptable, Avatar, DisplayName, Locale, Profile, ProfileBio, ProfileDetails, SUPPORTED_LOCALES};
\ No newline at end of file
@@ -0,0 +1,34 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Avatar {
+    #[default]
+    Knight,
+    Mage,
+    Rogue,
+    Ranger,
+    Cleric,
+}
+
+impl Avatar {
+    pub const ALL: [Avatar; 5] = [Avatar::Knight, Avatar::Mage, Avatar::Rogue, Avatar::Ranger, Avatar::Cleric];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Avatar::Knight => "knight",
+            Avatar::Mage => "mage",
+            Avatar::Rogue => "rogue",
+            Avatar::Ranger => "ranger",
+            Avatar::Cleric => "cleric",
+        }
+    }
+}
+
+impl TryFrom<String> for Avatar {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Avatar::ALL
+            .into_iter()
+            .find(|a| a.as_str() == value.to_lowercase())
+            .ok_or_else(|| format!("{} is not a valid avatar", value))
+    }
+}
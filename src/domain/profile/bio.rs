@@ -0,0 +1,20 @@
+const MAX_LENGTH: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProfileBio(String);
+
+impl ProfileBio {
+    pub fn parse(v: String) -> Result<Self, String> {
+        let bio = v.trim();
+        if bio.chars().count() > MAX_LENGTH {
+            return Err(format!("Bio can be at most {} characters long", MAX_LENGTH));
+        }
+        Ok(ProfileBio(bio.to_string()))
+    }
+}
+
+impl AsRef<str> for ProfileBio {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
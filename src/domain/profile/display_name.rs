@@ -0,0 +1,139 @@
+use std::fmt::Formatter;
+
+const MIN_LENGTH: usize = 3;
+const MAX_LENGTH: usize = 20;
+
+// Names that could be mistaken for staff or the system itself
+const RESERVED_NAMES: &[&str] = &[
+    "admin", "administrator", "moderator", "mod", "gamemaster", "staff", "support",
+    "system", "server", "yaug", "root", "null", "undefined", "anonymous", "everyone",
+];
+
+// Checked as substrings after folding the usual character substitutions
+const PROFANE_WORDS: &[&str] = &[
+    "fuck", "shit", "cunt", "bitch", "whore", "slut", "nigger", "faggot", "retard",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayName(String);
+
+impl DisplayName {
+    pub fn parse(v: String) -> Result<Self, String> {
+        let name = v.trim();
+
+        let length = name.chars().count();
+        if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) {
+            return Err(format!(
+                "Display name must be between {} and {} characters long",
+                MIN_LENGTH, MAX_LENGTH
+            ));
+        }
+
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return Err("Display name must start with a letter".to_string());
+        }
+
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err("Display name can only contain letters, numbers, '_' and '-'".to_string());
+        }
+
+        check_name_is_acceptable(name)?;
+
+        Ok(DisplayName(name.to_string()))
+    }
+}
+
+/// Reserved and profanity checks, shared by every name a player gets to pick
+pub fn check_name_is_acceptable(name: &str) -> Result<(), String> {
+    let lowercase = name.to_lowercase();
+    if RESERVED_NAMES.contains(&lowercase.as_str()) {
+        return Err(format!("{} is a reserved name", name));
+    }
+
+    let folded = fold_substitutions(&lowercase);
+    if PROFANE_WORDS.iter().any(|w| folded.contains(w)) {
+        return Err(format!("{} is not an acceptable name", name));
+    }
+
+    Ok(())
+}
+
+// Undo the common tricks used to get around the word list, `5h1t` -> `shit`, `f_u_c_k` -> `fuck`
+fn fold_substitutions(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .map(|c| match c {
+            '0' => 'o',
+            '1' => 'i',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            '8' => 'b',
+            other => other,
+        })
+        .collect()
+}
+
+impl AsRef<str> for DisplayName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for DisplayName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use crate::domain::DisplayName;
+
+    #[test]
+    fn valid_names_are_accepted() {
+        for name in ["Bob", "dragon_slayer", "x-wing-42", "ABCDEFGHIJKLMNOPQRST"] {
+            assert_ok!(DisplayName::parse(name.to_string()));
+        }
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_trimmed() {
+        let name = DisplayName::parse("  Bob ".to_string()).unwrap();
+        assert_eq!("Bob", name.as_ref());
+    }
+
+    #[test]
+    fn too_short_and_too_long_names_are_rejected() {
+        assert_err!(DisplayName::parse("ab".to_string()));
+        assert_err!(DisplayName::parse("a".repeat(21)));
+    }
+
+    #[test]
+    fn names_must_start_with_a_letter() {
+        assert_err!(DisplayName::parse("1bob".to_string()));
+        assert_err!(DisplayName::parse("_bob".to_string()));
+    }
+
+    #[test]
+    fn names_with_disallowed_characters_are_rejected() {
+        for name in ["bob smith", "bob@mail", "böb", "bob!"] {
+            assert_err!(DisplayName::parse(name.to_string()));
+        }
+    }
+
+    #[test]
+    fn reserved_names_are_rejected_regardless_of_case() {
+        assert_err!(DisplayName::parse("Admin".to_string()));
+        assert_err!(DisplayName::parse("SYSTEM".to_string()));
+    }
+
+    #[test]
+    fn profane_names_are_rejected_even_when_disguised() {
+        assert_err!(DisplayName::parse("bigShit".to_string()));
+        assert_err!(DisplayName::parse("sh1t_lord".to_string()));
+        assert_err!(DisplayName::parse("f_u_c_k".to_string()));
+    }
+}
@@ -0,0 +1,27 @@
+pub const SUPPORTED_LOCALES: &[&str] = &["en", "de", "es", "fr", "pl", "pt", "ru"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale(String);
+
+impl Locale {
+    pub fn parse(v: String) -> Result<Self, String> {
+        let locale = v.trim().to_lowercase();
+        if SUPPORTED_LOCALES.contains(&locale.as_str()) {
+            Ok(Locale(locale))
+        } else {
+            Err(format!("{} is not a supported locale", v))
+        }
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale(SUPPORTED_LOCALES[0].to_string())
+    }
+}
+
+impl AsRef<str> for Locale {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
@@ -0,0 +1,12 @@
+mod avatar;
+mod bio;
+mod display_name;
+mod locale;
+#[allow(clippy::module_inception)]
+mod profile;
+
+pub use avatar::Avatar;
+pub use bio::ProfileBio;
+pub use display_name::{check_name_is_acceptable, DisplayName};
+pub use locale::{Locale, SUPPORTED_LOCALES};
+pub use profile::{Profile, ProfileDetails};
\ No newline at end of file
@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::domain::{Avatar, DisplayName, Locale, ProfileBio};
+
+/// Public face of an account, this is what other players get to see instead of the email
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub user_id: Uuid,
+    pub display_name: DisplayName,
+    pub avatar: Avatar,
+    pub bio: ProfileBio,
+    pub locale: Locale,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Validated contents of the profile form
+pub struct ProfileDetails {
+    pub display_name: DisplayName,
+    pub avatar: Avatar,
+    pub bio: ProfileBio,
+    pub locale: Locale,
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use uuid::Uuid;
+use crate::email_client::{EmailMessage, EmailTransport};
+
+/// Development transport, every message ends up as an `.eml` file in `directory`
+/// so it can be opened with any mail client instead of being sent anywhere.
+pub struct FileDropTransport {
+    directory: PathBuf,
+}
+
+impl FileDropTransport {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        FileDropTransport { directory: directory.into() }
+    }
+}
+
+#[async_trait]
+impl EmailTransport for FileDropTransport {
+    async fn send(&self, message: &EmailMessage<'_>) -> Result<(), anyhow::Error> {
+        tokio::fs::create_dir_all(&self.directory)
+            .await
+            .context("Failed to create email drop directory")?;
+
+        // timestamp first so the files sort in the order they were sent
+        let file_name = format!("{}-{}.eml", Utc::now().format("%Y%m%dT%H%M%S%.3f"), Uuid::new_v4());
+        let path = self.directory.join(file_name);
+        tokio::fs::write(&path, message.to_mime()?.formatted())
+            .await
+            .with_context(|| format!("Failed to write email to {}", path.display()))?;
+
+        tracing::info!("Email to {} written to {}", message.to.as_ref(), path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::domain::AccountEmail;
+    use crate::email_client::{EmailClient, FileDropTransport};
+
+    fn email(v: &str) -> AccountEmail {
+        AccountEmail::parse(v.to_string()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn message_is_written_to_the_directory() {
+        let directory = std::env::temp_dir().join(format!("yaug-mail-{}", Uuid::new_v4()));
+        let client = EmailClient::new(email("game@yaug.test"), FileDropTransport::new(&directory));
+
+        client
+            .send_email(&email("player@yaug.test"), "Welcome", "<p>Hi</p>", "Hi")
+            .await
+            .unwrap();
+
+        let mut entries = std::fs::read_dir(&directory).unwrap();
+        let path = entries.next().unwrap().unwrap().path();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(Some("eml"), path.extension().and_then(|e| e.to_str()));
+        assert!(content.contains("Subject: Welcome"));
+        assert!(content.contains("To: player@yaug.test"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1,12 +1,14 @@
 use std::time::Duration;
+use anyhow::Context;
+use async_trait::async_trait;
 use reqwest::Client;
 use secrecy::{ExposeSecret, Secret};
-use crate::domain::AccountEmail;
+use crate::email_client::{EmailMessage, EmailTransport};
 
-pub struct EmailClient {
+/// Our original provider, a JSON API that takes the message on `POST {base_url}/email`
+pub struct HttpTransport {
     http_client: Client,
     base_url: String,
-    sender: AccountEmail,
     auth_token: Secret<String>,
 }
 
@@ -20,37 +22,32 @@ struct SendEmailRequest<'s> {
     plain_message: &'s str,
 }
 
-impl EmailClient {
+impl HttpTransport {
     pub fn new(
         base_url: String,
-        sender: AccountEmail,
         auth_token: Secret<String>,
         timeout: Duration,
     ) -> Self {
         let http_client = Client::builder().timeout(timeout).build().unwrap();
-        EmailClient {
+        HttpTransport {
             http_client,
             base_url,
-            sender,
             auth_token,
         }
     }
+}
 
-    pub async fn send_email(
-        &self,
-        recipient: &AccountEmail,
-        subject: &str,
-        html_message: &str,
-        plain_message: &str,
-    ) -> Result<(), reqwest::Error> {
+#[async_trait]
+impl EmailTransport for HttpTransport {
+    async fn send(&self, message: &EmailMessage<'_>) -> Result<(), anyhow::Error> {
         let url = format!("{}/email", self.base_url);
 
         let request_body = SendEmailRequest {
-            from: self.sender.as_ref(),
-            to: recipient.as_ref(),
-            subject,
-            html_message,
-            plain_message,
+            from: message.from.as_ref(),
+            to: message.to.as_ref(),
+            subject: message.subject,
+            html_message: message.html_message,
+            plain_message: message.plain_message,
         };
 
         let _builder = self.http_client
@@ -61,8 +58,10 @@ impl EmailClient {
             )
             .json(&request_body)
             .send()
-            .await?
-            .error_for_status()?;
+            .await
+            .context("Failed to reach the email API")?
+            .error_for_status()
+            .context("Email API rejected the message")?;
 
         Ok(())
     }
@@ -79,7 +78,7 @@ mod tests {
     use wiremock::{Match, Mock, MockServer, Request, ResponseTemplate};
     use wiremock::matchers::{any, header, header_exists, method, path};
     use crate::domain::AccountEmail;
-    use crate::email_client::EmailClient;
+    use crate::email_client::{EmailClient, HttpTransport};
 
     struct EmailBodyMatcher;
 
@@ -118,10 +117,12 @@ mod tests {
 
     fn email_client(base_url: String) -> EmailClient {
         EmailClient::new(
-            base_url,
             email(),
-            Secret::new(Faker.fake()),
-            Duration::from_millis(200),
+            HttpTransport::new(
+                base_url,
+                Secret::new(Faker.fake()),
+                Duration::from_millis(200),
+            ),
         )
     }
 
@@ -0,0 +1,77 @@
+mod file;
+mod http;
+mod smtp;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use lettre::Message;
+use lettre::message::{Mailbox, MultiPart};
+use crate::domain::AccountEmail;
+
+pub use file::FileDropTransport;
+pub use http::HttpTransport;
+pub use smtp::SmtpTransport;
+
+pub struct EmailMessage<'m> {
+    pub from: &'m AccountEmail,
+    pub to: &'m AccountEmail,
+    pub subject: &'m str,
+    pub html_message: &'m str,
+    pub plain_message: &'m str,
+}
+
+impl EmailMessage<'_> {
+    /// Multipart MIME message with both the plain and html bodies, for transports that speak raw email
+    pub fn to_mime(&self) -> Result<Message, anyhow::Error> {
+        let from: Mailbox = self.from.as_ref().parse().context("Invalid sender address")?;
+        let to: Mailbox = self.to.as_ref().parse().context("Invalid recipient address")?;
+        Message::builder()
+            .from(from)
+            .to(to)
+            .subject(self.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                self.plain_message.to_string(),
+                self.html_message.to_string(),
+            ))
+            .context("Failed to build email message")
+    }
+}
+
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, message: &EmailMessage<'_>) -> Result<(), anyhow::Error>;
+}
+
+pub struct EmailClient {
+    sender: AccountEmail,
+    transport: Box<dyn EmailTransport>,
+}
+
+impl EmailClient {
+    pub fn new(
+        sender: AccountEmail,
+        transport: impl EmailTransport + 'static,
+    ) -> Self {
+        EmailClient {
+            sender,
+            transport: Box::new(transport),
+        }
+    }
+
+    pub async fn send_email(
+        &self,
+        recipient: &AccountEmail,
+        subject: &str,
+        html_message: &str,
+        plain_message: &str,
+    ) -> Result<(), anyhow::Error> {
+        let message = EmailMessage {
+            from: &self.sender,
+            to: recipient,
+            subject,
+            html_message,
+            plain_message,
+        };
+        self.transport.send(&message).await
+    }
+}
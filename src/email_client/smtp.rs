@@ -0,0 +1,151 @@
+use std::time::Duration;
+use anyhow::Context;
+use async_trait::async_trait;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use lettre::transport::smtp::authentication::Credentials;
+use secrecy::{ExposeSecret, Secret};
+use crate::email_client::{EmailMessage, EmailTransport};
+
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    /// Without `starttls` the connection stays in plain text, only meant for local relays and tests
+    pub fn new(
+        host: &str,
+        port: u16,
+        credentials: Option<(String, Secret<String>)>,
+        starttls: bool,
+        timeout: Duration,
+    ) -> Result<Self, anyhow::Error> {
+        let mut builder = if starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                .context("Failed to set up STARTTLS relay")?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+        }
+            .port(port)
+            .timeout(Some(timeout));
+
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(
+                Credentials::new(username, password.expose_secret().to_string())
+            );
+        }
+
+        Ok(SmtpTransport { mailer: builder.build() })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpTransport {
+    async fn send(&self, message: &EmailMessage<'_>) -> Result<(), anyhow::Error> {
+        self.mailer
+            .send(message.to_mime()?)
+            .await
+            .context("SMTP server rejected the message")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use claim::{assert_err, assert_ok};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use crate::domain::AccountEmail;
+    use crate::email_client::{EmailClient, SmtpTransport};
+
+    /// Bare bones SMTP server that accepts everything and keeps whatever was sent after DATA
+    async fn smtp_stub(reject_recipients: bool) -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let messages = received.clone();
+
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let (reader, mut writer) = socket.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                writer.write_all(b"220 stub ESMTP\r\n").await.unwrap();
+
+                let mut data: Option<String> = None;
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if let Some(body) = data.as_mut() {
+                        if line == "." {
+                            messages.lock().unwrap().push(data.take().unwrap());
+                            writer.write_all(b"250 queued\r\n").await.unwrap();
+                        } else {
+                            body.push_str(&line);
+                            body.push('\n');
+                        }
+                        continue;
+                    }
+
+                    let command = line.to_uppercase();
+                    let reply: &[u8] = if command.starts_with("EHLO") {
+                        b"250-stub\r\n250 8BITMIME\r\n"
+                    } else if command.starts_with("RCPT") && reject_recipients {
+                        b"550 no such user\r\n"
+                    } else if command.starts_with("DATA") {
+                        data = Some(String::new());
+                        b"354 go ahead\r\n"
+                    } else if command.starts_with("QUIT") {
+                        writer.write_all(b"221 bye\r\n").await.unwrap();
+                        break;
+                    } else {
+                        b"250 ok\r\n"
+                    };
+                    writer.write_all(reply).await.unwrap();
+                }
+            }
+        });
+
+        (port, received)
+    }
+
+    fn email(v: &str) -> AccountEmail {
+        AccountEmail::parse(v.to_string()).unwrap()
+    }
+
+    fn client(port: u16) -> EmailClient {
+        let transport = SmtpTransport::new(
+            "127.0.0.1",
+            port,
+            None,
+            false,
+            Duration::from_secs(2),
+        ).unwrap();
+        EmailClient::new(email("game@yaug.test"), transport)
+    }
+
+    #[tokio::test]
+    async fn message_is_delivered_to_the_smtp_server() {
+        let (port, received) = smtp_stub(false).await;
+
+        let outcome = client(port)
+            .send_email(&email("player@yaug.test"), "Welcome", "<p>Hi</p>", "Hi")
+            .await;
+
+        assert_ok!(outcome);
+        let received = received.lock().unwrap();
+        assert_eq!(1, received.len());
+        assert!(received[0].contains("Subject: Welcome"));
+        assert!(received[0].contains("To: player@yaug.test"));
+        assert!(received[0].contains("<p>Hi</p>"));
+    }
+
+    #[tokio::test]
+    async fn rejected_recipient_is_an_error() {
+        let (port, _) = smtp_stub(true).await;
+
+        let outcome = client(port)
+            .send_email(&email("player@yaug.test"), "Welcome", "<p>Hi</p>", "Hi")
+            .await;
+
+        assert_err!(outcome);
+    }
+}
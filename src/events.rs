@@ -0,0 +1,165 @@
+use tokio::sync::broadcast;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::world::Position;
+
+/// Something a character did that other parts of the game keep track of, quest progress for one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    /// Sent once per creature to every player on the winning side
+    CreatureDefeated {
+        user_id: UserId,
+        character_id: Uuid,
+        creature_id: String,
+    },
+    /// Sent once per creature to the online party members of the winners who weren't in the fight
+    PartyKill {
+        user_id: UserId,
+        character_id: Uuid,
+        creature_id: String,
+    },
+    /// Every accepted step, including the ones into another zone
+    PositionChanged {
+        user_id: UserId,
+        character_id: Uuid,
+        position: Position,
+    },
+    TalkedTo {
+        user_id: UserId,
+        character_id: Uuid,
+        npc_id: String,
+        zone_id: String,
+    },
+    /// A crafting job finished and its outputs were handed out
+    ItemCrafted {
+        user_id: UserId,
+        character_id: Uuid,
+        recipe_id: String,
+    },
+    /// A player's character ended a fight with no health left
+    CharacterDied {
+        user_id: UserId,
+        character_id: Uuid,
+    },
+    QuestCompleted {
+        user_id: UserId,
+        character_id: Uuid,
+        quest_id: String,
+    },
+    /// Gold that came from someone or something else, never the character's own gold coming back
+    GoldEarned {
+        user_id: UserId,
+        character_id: Uuid,
+        amount: i64,
+    },
+}
+
+impl GameEvent {
+    pub fn user_id(&self) -> UserId {
+        match self {
+            GameEvent::CreatureDefeated { user_id, .. }
+            | GameEvent::PartyKill { user_id, .. }
+            | GameEvent::PositionChanged { user_id, .. }
+            | GameEvent::TalkedTo { user_id, .. }
+            | GameEvent::ItemCrafted { user_id, .. }
+            | GameEvent::CharacterDied { user_id, .. }
+            | GameEvent::QuestCompleted { user_id, .. }
+            | GameEvent::GoldEarned { user_id, .. } => *user_id,
+        }
+    }
+
+    pub fn character_id(&self) -> Uuid {
+        match self {
+            GameEvent::CreatureDefeated { character_id, .. }
+            | GameEvent::PartyKill { character_id, .. }
+            | GameEvent::PositionChanged { character_id, .. }
+            | GameEvent::TalkedTo { character_id, .. }
+            | GameEvent::ItemCrafted { character_id, .. }
+            | GameEvent::CharacterDied { character_id, .. }
+            | GameEvent::QuestCompleted { character_id, .. }
+            | GameEvent::GoldEarned { character_id, .. } => *character_id,
+        }
+    }
+}
+
+/// Fan out of game events. Publishing never blocks, subscribers that fall more than the queue size
+/// behind miss the oldest events and are told how many.
+#[derive(Clone)]
+pub struct GameEvents {
+    sender: broadcast::Sender<GameEvent>,
+}
+
+impl GameEvents {
+    pub fn new(queue_size: usize) -> Self {
+        let (sender, _) = broadcast::channel(queue_size.max(1));
+        GameEvents { sender }
+    }
+
+    pub fn publish(&self, event: GameEvent) {
+        // nobody listening is fine, the event just didn't matter to anyone
+        let _ = self.sender.send(event);
+    }
+
+    /// Only sees events published after subscribing
+    pub fn subscribe(&self) -> broadcast::Receiver<GameEvent> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::broadcast::error::TryRecvError;
+    use uuid::Uuid;
+    use crate::authentication::UserId;
+    use crate::events::{GameEvent, GameEvents};
+
+    fn talked_to(npc_id: &str) -> GameEvent {
+        GameEvent::TalkedTo {
+            user_id: UserId::from(Uuid::new_v4()),
+            character_id: Uuid::new_v4(),
+            npc_id: npc_id.to_string(),
+            zone_id: "greenvale".to_string(),
+        }
+    }
+
+    #[test]
+    fn every_subscriber_sees_every_event() {
+        let events = GameEvents::new(8);
+        let mut first = events.subscribe();
+        let mut second = events.subscribe();
+
+        events.publish(talked_to("elder"));
+
+        assert_eq!("elder", talked_to_npc(&mut first));
+        assert_eq!("elder", talked_to_npc(&mut second));
+        assert_eq!(Err(TryRecvError::Empty), first.try_recv().map(|_| ()));
+    }
+
+    #[test]
+    fn publishing_without_subscribers_is_fine() {
+        let events = GameEvents::new(8);
+        events.publish(talked_to("elder"));
+        let mut late = events.subscribe();
+        assert_eq!(Err(TryRecvError::Empty), late.try_recv().map(|_| ()));
+    }
+
+    #[test]
+    fn slow_subscribers_lose_the_oldest_events() {
+        let events = GameEvents::new(2);
+        let mut slow = events.subscribe();
+        for npc in ["a", "b", "c"] {
+            events.publish(talked_to(npc));
+        }
+
+        assert_eq!(Err(TryRecvError::Lagged(1)), slow.try_recv().map(|_| ()));
+        assert_eq!("b", talked_to_npc(&mut slow));
+        assert_eq!("c", talked_to_npc(&mut slow));
+    }
+
+    fn talked_to_npc(receiver: &mut tokio::sync::broadcast::Receiver<GameEvent>) -> String {
+        match receiver.try_recv() {
+            Ok(GameEvent::TalkedTo { npc_id, .. }) => npc_id,
+            other => panic!("Expected a talk event, got {:?}", other),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,5 @@
+mod service;
+mod store;
+
+pub use service::{Friend, FriendError, FriendRequestOutcome, FriendService};
+pub use store::{get_incoming_friend_requests, get_outgoing_friend_requests, FriendRow};
\ No newline at end of file
@@ -0,0 +1,330 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::chat::{is_blocked, ChatError, ChatService};
+use crate::configuration::{FriendSettings, PresenceSettings};
+use crate::domain::Profile;
+use crate::friends::store::{are_friends, count_friends, get_friends, remove_friend_request, remove_friendship, store_friend_request, store_friendship};
+use crate::gateway::{ClientError, ConnectionRegistry, ErrorCode, ServerMessage};
+use crate::presence::{PresenceStatus, PresenceStore};
+use crate::store::{get_profile_by_display_name, get_profile_by_user_id};
+use crate::utils::error_chain_fmt;
+
+#[derive(thiserror::Error)]
+pub enum FriendError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("Pick a display name on your profile before adding friends")]
+    NoDisplayName,
+    #[error("There is no player called {0}")]
+    PlayerNotFound(String),
+    #[error("{0} is already your friend")]
+    AlreadyFriends(String),
+    #[error("You already sent {0} a friend request")]
+    AlreadyRequested(String),
+    #[error("{0} is not accepting friend requests from you")]
+    RequestRefused(String),
+    #[error("{0} hasn't sent you a friend request")]
+    NoRequest(String),
+    #[error("{0} is not your friend")]
+    NotFriends(String),
+    #[error("You can't have more than {0} friends")]
+    TooManyFriends(usize),
+    #[error("{0} can't add any more friends")]
+    FriendListFull(String),
+    #[error(transparent)]
+    Chat(#[from] ChatError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for FriendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for FriendError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            FriendError::ValidationError(_) => ErrorCode::InvalidMessage,
+            FriendError::PlayerNotFound(_) | FriendError::NoRequest(_) | FriendError::NotFriends(_) => ErrorCode::NotFound,
+            FriendError::NoDisplayName
+            | FriendError::AlreadyFriends(_)
+            | FriendError::AlreadyRequested(_)
+            | FriendError::RequestRefused(_)
+            | FriendError::TooManyFriends(_)
+            | FriendError::FriendListFull(_) => ErrorCode::Forbidden,
+            FriendError::Chat(e) => e.code(),
+            FriendError::UnexpectedError(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FriendRequestOutcome {
+    Sent(String),
+    /// The other player had already asked, so the two are friends now
+    Accepted(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Friend {
+    pub user_id: Uuid,
+    pub display_name: String,
+    pub status: PresenceStatus,
+    pub since: DateTime<Utc>,
+}
+
+/// Friend requests and lists live in Postgres, presence in Redis. Every connection refreshes its
+/// player's presence on the gateway heartbeat and friends hear about it whenever it changes.
+pub struct FriendService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    chat: Arc<ChatService>,
+    presence: PresenceStore,
+    settings: FriendSettings,
+    away_after: Duration,
+}
+
+impl FriendService {
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        chat: Arc<ChatService>,
+        presence: PresenceStore,
+        settings: FriendSettings,
+        presence_settings: &PresenceSettings,
+    ) -> Self {
+        FriendService {
+            pool,
+            registry,
+            chat,
+            presence,
+            settings,
+            away_after: presence_settings.away_after(),
+        }
+    }
+
+    //region Requests
+    /// Asking someone who already asked you makes you friends right away
+    #[tracing::instrument(
+    name = "Send friend request",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn request(&self, user_id: UserId, display_name: &str) -> Result<FriendRequestOutcome, FriendError> {
+        let requester = self.profile(user_id).await?;
+        let addressee = self.find_player(display_name).await?;
+        let name = addressee.display_name.to_string();
+        if addressee.user_id == *user_id {
+            return Err(FriendError::ValidationError("You can't add yourself as a friend".to_string()));
+        }
+        if is_blocked(&self.pool, addressee.user_id, *user_id).await? {
+            return Err(FriendError::RequestRefused(name));
+        }
+        if is_blocked(&self.pool, *user_id, addressee.user_id).await? {
+            return Err(FriendError::ValidationError(format!("Unblock {} before adding them as a friend", name)));
+        }
+        if are_friends(&self.pool, *user_id, addressee.user_id).await? {
+            return Err(FriendError::AlreadyFriends(name));
+        }
+
+        let mut tx = self.pool.begin().await.context("Failed to begin friend transaction")?;
+        if remove_friend_request(&mut tx, addressee.user_id, *user_id).await? {
+            self.befriend(tx, *user_id, &addressee).await?;
+            self.notify(addressee.user_id, format!("{} accepted your friend request", requester.display_name));
+            return Ok(FriendRequestOutcome::Accepted(name));
+        }
+        if count_friends(&mut tx, *user_id).await? >= self.settings.max_friends {
+            return Err(FriendError::TooManyFriends(self.settings.max_friends));
+        }
+        if !store_friend_request(&mut tx, *user_id, addressee.user_id).await? {
+            return Err(FriendError::AlreadyRequested(name));
+        }
+        tx.commit().await.context("Failed to commit friend request")?;
+
+        self.notify(addressee.user_id, format!("{} wants to be your friend", requester.display_name));
+        Ok(FriendRequestOutcome::Sent(name))
+    }
+
+    #[tracing::instrument(
+    name = "Accept friend request",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn accept(&self, user_id: UserId, display_name: &str) -> Result<String, FriendError> {
+        let profile = self.profile(user_id).await?;
+        let requester = self.find_player(display_name).await?;
+        let mut tx = self.pool.begin().await.context("Failed to begin friend transaction")?;
+        if !remove_friend_request(&mut tx, requester.user_id, *user_id).await? {
+            return Err(FriendError::NoRequest(requester.display_name.to_string()));
+        }
+        self.befriend(tx, *user_id, &requester).await?;
+
+        self.notify(requester.user_id, format!("{} accepted your friend request", profile.display_name));
+        Ok(requester.display_name.to_string())
+    }
+
+    pub async fn decline(&self, user_id: UserId, display_name: &str) -> Result<String, FriendError> {
+        let requester = self.find_player(display_name).await?;
+        if !remove_friend_request(&self.pool, requester.user_id, *user_id).await? {
+            return Err(FriendError::NoRequest(requester.display_name.to_string()));
+        }
+        Ok(requester.display_name.to_string())
+    }
+
+    /// Takes back a request that wasn't answered yet
+    pub async fn cancel(&self, user_id: UserId, display_name: &str) -> Result<String, FriendError> {
+        let addressee = self.find_player(display_name).await?;
+        if !remove_friend_request(&self.pool, *user_id, addressee.user_id).await? {
+            return Err(FriendError::ValidationError(format!(
+                "You haven't sent {} a friend request", addressee.display_name
+            )));
+        }
+        Ok(addressee.display_name.to_string())
+    }
+
+    pub async fn remove(&self, user_id: UserId, display_name: &str) -> Result<String, FriendError> {
+        let friend = self.find_player(display_name).await?;
+        if !remove_friendship(&self.pool, *user_id, friend.user_id).await? {
+            return Err(FriendError::NotFriends(friend.display_name.to_string()));
+        }
+        Ok(friend.display_name.to_string())
+    }
+
+    /// Both lists are checked under the same transaction as the insert, the request that was
+    /// answered is already gone from it
+    async fn befriend(
+        &self,
+        mut tx: sqlx::Transaction<'static, sqlx::Postgres>,
+        user_id: Uuid,
+        other: &Profile,
+    ) -> Result<(), FriendError> {
+        if count_friends(&mut tx, user_id).await? >= self.settings.max_friends {
+            return Err(FriendError::TooManyFriends(self.settings.max_friends));
+        }
+        if count_friends(&mut tx, other.user_id).await? >= self.settings.max_friends {
+            return Err(FriendError::FriendListFull(other.display_name.to_string()));
+        }
+        // a request the other way round is answered by this too
+        remove_friend_request(&mut tx, user_id, other.user_id).await?;
+        store_friendship(&mut tx, user_id, other.user_id).await?;
+        tx.commit().await.context("Failed to commit friendship")?;
+        Ok(())
+    }
+    //endregion
+
+    //region Blocking
+    /// Blocking ends any friendship and drops pending requests either way, on top of what
+    /// blocking does in chat
+    #[tracing::instrument(
+    name = "Block player",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn block(&self, user_id: UserId, display_name: &str) -> Result<String, FriendError> {
+        let blocked = self.find_player(display_name).await?;
+        let mut tx = self.pool.begin().await.context("Failed to begin block transaction")?;
+        let name = self.chat.block(&mut tx, user_id, display_name).await?;
+        remove_friendship(&mut tx, *user_id, blocked.user_id).await?;
+        remove_friend_request(&mut tx, *user_id, blocked.user_id).await?;
+        remove_friend_request(&mut tx, blocked.user_id, *user_id).await?;
+        tx.commit().await.context("Failed to commit block")?;
+        Ok(name)
+    }
+
+    pub async fn unblock(&self, user_id: UserId, display_name: &str) -> Result<String, FriendError> {
+        Ok(self.chat.unblock(user_id, display_name).await?)
+    }
+
+    pub async fn blocked(&self, user_id: UserId) -> Result<Vec<String>, FriendError> {
+        Ok(self.chat.blocked(user_id).await?)
+    }
+    //endregion
+
+    //region Presence
+    /// Online friends first, then by name
+    pub async fn friends(&self, user_id: UserId) -> Result<Vec<Friend>, FriendError> {
+        let mut friends = Vec::new();
+        for row in get_friends(&self.pool, *user_id).await? {
+            friends.push(Friend {
+                user_id: row.user_id,
+                display_name: row.display_name.unwrap_or_else(|| "Unknown".to_string()),
+                status: self.status(row.user_id).await?,
+                since: row.created_at,
+            });
+        }
+        friends.sort_by_key(|f| (f.status == PresenceStatus::Offline, f.display_name.to_lowercase()));
+        Ok(friends)
+    }
+
+    pub async fn status(&self, user_id: Uuid) -> Result<PresenceStatus, FriendError> {
+        Ok(self.presence.get(user_id).await.context("Failed to read presence")?)
+    }
+
+    pub async fn connected(&self, user_id: UserId) -> Result<(), FriendError> {
+        self.set_status(user_id, PresenceStatus::Online).await
+    }
+
+    /// Called on every gateway heartbeat, `idle` is how long the client hasn't sent anything
+    pub async fn heartbeat(&self, user_id: UserId, in_combat: bool, idle: Duration) -> Result<(), FriendError> {
+        self.set_status(user_id, PresenceStatus::of_connection(in_combat, idle, self.away_after)).await
+    }
+
+    /// Only goes offline once the last connection of the player closed
+    pub async fn disconnected(&self, user_id: UserId) -> Result<(), FriendError> {
+        if self.registry.is_online(user_id) {
+            return Ok(());
+        }
+        let previous = self.presence.clear(*user_id).await.context("Failed to clear presence")?;
+        if previous != PresenceStatus::Offline {
+            self.broadcast_status(user_id, PresenceStatus::Offline).await?;
+        }
+        Ok(())
+    }
+
+    async fn set_status(&self, user_id: UserId, status: PresenceStatus) -> Result<(), FriendError> {
+        let previous = self.presence.heartbeat(*user_id, status).await.context("Failed to store presence")?;
+        if previous != status {
+            self.broadcast_status(user_id, status).await?;
+        }
+        Ok(())
+    }
+
+    async fn broadcast_status(&self, user_id: UserId, status: PresenceStatus) -> Result<(), FriendError> {
+        // players without a display name can't have friends
+        let profile = match get_profile_by_user_id(&self.pool, *user_id).await? {
+            Some(profile) => profile,
+            None => return Ok(()),
+        };
+        let message = ServerMessage::FriendStatus { display_name: profile.display_name.to_string(), status };
+        for friend in get_friends(&self.pool, *user_id).await? {
+            self.registry.send_to_user(UserId::from(friend.user_id), &message);
+        }
+        Ok(())
+    }
+    //endregion
+
+    async fn profile(&self, user_id: UserId) -> Result<Profile, FriendError> {
+        get_profile_by_user_id(&self.pool, *user_id)
+            .await?
+            .ok_or(FriendError::NoDisplayName)
+    }
+
+    async fn find_player(&self, display_name: &str) -> Result<Profile, FriendError> {
+        let display_name = display_name.trim();
+        get_profile_by_display_name(&self.pool, display_name)
+            .await?
+            .ok_or_else(|| FriendError::PlayerNotFound(display_name.to_string()))
+    }
+
+    fn notify(&self, user_id: Uuid, message: String) {
+        self.registry.send_to_user(UserId::from(user_id), &ServerMessage::Notice { message });
+    }
+}
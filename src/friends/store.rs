@@ -0,0 +1,232 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+
+/// The other side of a friendship or of a pending request
+#[derive(Debug, Clone)]
+pub struct FriendRow {
+    pub user_id: Uuid,
+    pub display_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `false` when the request was already pending
+#[tracing::instrument(
+name = "Store friend request",
+skip(tx)
+)]
+pub async fn store_friend_request(
+    tx: &mut Transaction<'_, Postgres>,
+    requester_id: Uuid,
+    addressee_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let stored = sqlx::query!(
+        r#"
+        INSERT INTO friend_requests (requester_id, addressee_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        requester_id,
+        addressee_id
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store friend request")?;
+    Ok(stored.rows_affected() > 0)
+}
+
+#[tracing::instrument(
+name = "Remove friend request",
+skip(executor)
+)]
+pub async fn remove_friend_request(
+    executor: impl PgExecutor<'_>,
+    requester_id: Uuid,
+    addressee_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let removed = sqlx::query!(
+        r#"
+        DELETE FROM friend_requests
+        WHERE requester_id = $1 AND addressee_id = $2
+        "#,
+        requester_id,
+        addressee_id
+    )
+        .execute(executor)
+        .await
+        .context("Failed to remove friend request")?;
+    Ok(removed.rows_affected() > 0)
+}
+
+/// Requests sent to `user_id`, newest first
+#[tracing::instrument(
+name = "Get incoming friend requests",
+skip(executor)
+)]
+pub async fn get_incoming_friend_requests(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<Vec<FriendRow>, anyhow::Error> {
+    sqlx::query_as!(
+        FriendRow,
+        r#"
+        SELECT r.requester_id as user_id, p.display_name as "display_name?", r.created_at
+        FROM friend_requests r
+        LEFT JOIN profiles p ON p.user_id = r.requester_id
+        WHERE r.addressee_id = $1
+        ORDER BY r.created_at DESC
+        "#,
+        user_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch incoming friend requests")
+}
+
+/// Requests `user_id` sent that are still waiting for an answer, newest first
+#[tracing::instrument(
+name = "Get outgoing friend requests",
+skip(executor)
+)]
+pub async fn get_outgoing_friend_requests(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<Vec<FriendRow>, anyhow::Error> {
+    sqlx::query_as!(
+        FriendRow,
+        r#"
+        SELECT r.addressee_id as user_id, p.display_name as "display_name?", r.created_at
+        FROM friend_requests r
+        LEFT JOIN profiles p ON p.user_id = r.addressee_id
+        WHERE r.requester_id = $1
+        ORDER BY r.created_at DESC
+        "#,
+        user_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch outgoing friend requests")
+}
+
+/// Stores both directions, `false` when the two already were friends
+#[tracing::instrument(
+name = "Store friendship",
+skip(tx)
+)]
+pub async fn store_friendship(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    friend_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let stored = sqlx::query!(
+        r#"
+        INSERT INTO friendships (user_id, friend_id)
+        VALUES ($1, $2), ($2, $1)
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        friend_id
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store friendship")?;
+    Ok(stored.rows_affected() > 0)
+}
+
+/// Removes both directions
+#[tracing::instrument(
+name = "Remove friendship",
+skip(executor)
+)]
+pub async fn remove_friendship(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    friend_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let removed = sqlx::query!(
+        r#"
+        DELETE FROM friendships
+        WHERE (user_id = $1 AND friend_id = $2) OR (user_id = $2 AND friend_id = $1)
+        "#,
+        user_id,
+        friend_id
+    )
+        .execute(executor)
+        .await
+        .context("Failed to remove friendship")?;
+    Ok(removed.rows_affected() > 0)
+}
+
+#[tracing::instrument(
+name = "Get friends",
+skip(executor)
+)]
+pub async fn get_friends(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<Vec<FriendRow>, anyhow::Error> {
+    sqlx::query_as!(
+        FriendRow,
+        r#"
+        SELECT f.friend_id as user_id, p.display_name as "display_name?", f.created_at
+        FROM friendships f
+        LEFT JOIN profiles p ON p.user_id = f.friend_id
+        WHERE f.user_id = $1
+        ORDER BY lower(p.display_name)
+        "#,
+        user_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch friends")
+}
+
+#[tracing::instrument(
+name = "Count friends",
+skip(executor)
+)]
+pub async fn count_friends(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<usize, anyhow::Error> {
+    let count = sqlx::query!(
+        r#"
+        SELECT count(*) as "count!"
+        FROM friendships
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+        .fetch_one(executor)
+        .await
+        .context("Failed to count friends")?
+        .count;
+    Ok(count as usize)
+}
+
+#[tracing::instrument(
+name = "Check friendship",
+skip(executor)
+)]
+pub async fn are_friends(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    friend_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let friends = sqlx::query!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM friendships WHERE user_id = $1 AND friend_id = $2
+        ) AS "friends!"
+        "#,
+        user_id,
+        friend_id
+    )
+        .fetch_one(executor)
+        .await
+        .context("Failed to check friendship")?
+        .friends;
+    Ok(friends)
+}
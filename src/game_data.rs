@@ -0,0 +1,55 @@
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use crate::utils::error_chain_fmt;
+
+#[derive(thiserror::Error)]
+pub enum GameDataError {
+    #[error("Failed to read data directory {0}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("Failed to load {0}")]
+    Parse(PathBuf, #[source] config::ConfigError),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl Debug for GameDataError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Game data lives in `data/`, right next to `configuration/`
+pub fn data_directory() -> PathBuf {
+    std::env::current_dir()
+        .expect("Failed to determine current directory.")
+        .join("data")
+}
+
+/// Every `.toml` and `.json` file in `directory`, in file name order so load errors are reproducible.
+/// Keep in mind the config crate lowercases table keys.
+pub fn load_data_files<T: DeserializeOwned>(directory: &Path) -> Result<Vec<(PathBuf, T)>, GameDataError> {
+    let mut paths = std::fs::read_dir(directory)
+        .map_err(|e| GameDataError::Io(directory.to_path_buf(), e))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| GameDataError::Io(directory.to_path_buf(), e))?;
+    paths.retain(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("toml" | "json")));
+    paths.sort();
+
+    paths.into_iter()
+        .map(|path| {
+            let data = config::Config::builder()
+                .add_source(config::File::from(path.as_path()))
+                .build()
+                .and_then(|c| c.try_deserialize::<T>())
+                .map_err(|e| GameDataError::Parse(path.clone(), e))?;
+            Ok((path, data))
+        })
+        .collect()
+}
+
+/// Ids referenced from other data files, `iron_sword` or `goblin_camp`
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
@@ -0,0 +1,7 @@
+mod scheduler;
+mod stats;
+mod system;
+
+pub use scheduler::GameLoop;
+pub use stats::TickStats;
+pub use system::{GameSystem, TickContext};
\ No newline at end of file
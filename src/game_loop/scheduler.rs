@@ -0,0 +1,258 @@
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use tokio::time::MissedTickBehavior;
+use crate::configuration::GameSettings;
+use crate::game_loop::{GameSystem, TickContext, TickStats};
+
+/// The authoritative simulation. Systems run one after another in the order they were added,
+/// `run_until_stopped` drives it on a fixed interval and tests call `tick` themselves.
+pub struct GameLoop {
+    tick_interval: Duration,
+    stats_interval_ticks: u64,
+    tick: u64,
+    now: DateTime<Utc>,
+    systems: Vec<Box<dyn GameSystem>>,
+    lifetime_stats: TickStats,
+    window_stats: TickStats,
+}
+
+impl GameLoop {
+    /// `start` is the game time before the first tick
+    pub fn new(settings: &GameSettings, start: DateTime<Utc>) -> Self {
+        GameLoop {
+            tick_interval: settings.tick_interval(),
+            stats_interval_ticks: settings.stats_interval_ticks(),
+            tick: 0,
+            now: start,
+            systems: vec![],
+            lifetime_stats: TickStats::default(),
+            window_stats: TickStats::default(),
+        }
+    }
+
+    pub fn add_system(&mut self, system: impl GameSystem + 'static) {
+        tracing::info!(system = system.name(), position = self.systems.len(), "Registered game system");
+        self.systems.push(Box::new(system));
+    }
+
+    pub fn system_names(&self) -> Vec<&'static str> {
+        self.systems.iter().map(|s| s.name()).collect()
+    }
+
+    pub fn current_tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+
+    pub fn tick_interval(&self) -> Duration {
+        self.tick_interval
+    }
+
+    pub fn stats(&self) -> &TickStats {
+        &self.lifetime_stats
+    }
+
+    /// Advances game time by one interval and runs every system once, never waits on the clock
+    pub async fn tick(&mut self) {
+        let now = self.now + chrono::Duration::from_std(self.tick_interval).unwrap_or_else(|_| chrono::Duration::zero());
+        self.tick_at(now).await
+    }
+
+    /// Runs every system once with game time set to `now`, which never goes backwards. The delta
+    /// is however much game time actually passed since the last tick.
+    async fn tick_at(&mut self, now: DateTime<Utc>) {
+        let now = now.max(self.now);
+        let delta = (now - self.now).to_std().unwrap_or(Duration::ZERO);
+        self.tick += 1;
+        self.now = now;
+        let ctx = TickContext { tick: self.tick, now: self.now, delta };
+
+        let started = Instant::now();
+        let mut slowest: Option<(&'static str, Duration)> = None;
+        for system in self.systems.iter_mut() {
+            let system_started = Instant::now();
+            if let Err(e) = system.run(&ctx).await {
+                tracing::error!(
+                    tick = ctx.tick,
+                    system = system.name(),
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Game system failed"
+                );
+            }
+            let elapsed = system_started.elapsed();
+            if slowest.is_none_or(|(_, longest)| elapsed > longest) {
+                slowest = Some((system.name(), elapsed));
+            }
+        }
+        let elapsed = started.elapsed();
+
+        let overrun = elapsed > self.tick_interval;
+        if overrun {
+            let (system, system_duration) = slowest.unwrap_or(("none", Duration::ZERO));
+            tracing::warn!(
+                tick = ctx.tick,
+                duration_ms = elapsed.as_secs_f64() * 1000.0,
+                budget_ms = self.tick_interval.as_secs_f64() * 1000.0,
+                slowest_system = system,
+                slowest_system_ms = system_duration.as_secs_f64() * 1000.0,
+                "Game tick overran its budget"
+            );
+        }
+        self.lifetime_stats.record(elapsed, overrun);
+        self.window_stats.record(elapsed, overrun);
+
+        if self.window_stats.ticks >= self.stats_interval_ticks {
+            let window = std::mem::take(&mut self.window_stats);
+            tracing::info!(
+                tick = ctx.tick,
+                ticks = window.ticks,
+                overruns = window.overruns,
+                average_ms = window.average().as_secs_f64() * 1000.0,
+                max_ms = window.max.as_secs_f64() * 1000.0,
+                "Game tick statistics"
+            );
+        }
+    }
+
+    /// Ticks on the wall clock forever. Ticks that can't start on time are skipped rather than
+    /// bunched up, and every tick takes its time from the wall clock so game time stays in step
+    /// with the deadlines services write with `Utc::now()`.
+    pub async fn run_until_stopped(mut self) -> Result<(), anyhow::Error> {
+        tracing::info!(
+            tick_interval_ms = self.tick_interval.as_secs_f64() * 1000.0,
+            systems = ?self.system_names(),
+            "Starting game loop"
+        );
+        let mut interval = tokio::time::interval(self.tick_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            self.tick_at(Utc::now()).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use async_trait::async_trait;
+    use chrono::{TimeZone, Utc};
+    use crate::configuration::GameSettings;
+    use crate::game_loop::{GameLoop, GameSystem, TickContext};
+
+    type Log = Arc<Mutex<Vec<(&'static str, u64)>>>;
+
+    struct Recorder {
+        name: &'static str,
+        log: Log,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl GameSystem for Recorder {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error> {
+            self.log.lock().unwrap().push((self.name, ctx.tick));
+            if self.fail {
+                anyhow::bail!("{} always fails", self.name);
+            }
+            Ok(())
+        }
+    }
+
+    struct Sleeper(Duration);
+
+    #[async_trait]
+    impl GameSystem for Sleeper {
+        fn name(&self) -> &'static str {
+            "sleeper"
+        }
+
+        async fn run(&mut self, _ctx: &TickContext) -> Result<(), anyhow::Error> {
+            std::thread::sleep(self.0);
+            Ok(())
+        }
+    }
+
+    fn game_loop(tick_rate: u32) -> GameLoop {
+        let settings = GameSettings { tick_rate, stats_interval_seconds: 1, ..GameSettings::default() };
+        GameLoop::new(&settings, Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap())
+    }
+
+    fn recorder(name: &'static str, log: &Log, fail: bool) -> Recorder {
+        Recorder { name, log: log.clone(), fail }
+    }
+
+    #[tokio::test]
+    async fn systems_run_in_registration_order_every_tick() {
+        let log = Log::default();
+        let mut game_loop = game_loop(10);
+        game_loop.add_system(recorder("movement", &log, false));
+        game_loop.add_system(recorder("combat", &log, false));
+
+        game_loop.tick().await;
+        game_loop.tick().await;
+
+        assert_eq!(
+            vec![("movement", 1), ("combat", 1), ("movement", 2), ("combat", 2)],
+            *log.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn game_time_advances_by_exactly_one_interval_per_tick() {
+        let mut game_loop = game_loop(20);
+        let start = game_loop.now();
+
+        for _ in 0..40 {
+            game_loop.tick().await;
+        }
+
+        assert_eq!(40, game_loop.current_tick());
+        assert_eq!(start + chrono::Duration::seconds(2), game_loop.now());
+    }
+
+    #[tokio::test]
+    async fn wall_clock_ticks_catch_up_on_skipped_time_and_never_go_back() {
+        let mut game_loop = game_loop(20);
+        let start = game_loop.now();
+
+        game_loop.tick_at(start + chrono::Duration::seconds(5)).await;
+        assert_eq!(start + chrono::Duration::seconds(5), game_loop.now());
+        game_loop.tick_at(start).await;
+        assert_eq!(start + chrono::Duration::seconds(5), game_loop.now());
+        assert_eq!(2, game_loop.current_tick());
+    }
+
+    #[tokio::test]
+    async fn a_failing_system_does_not_stop_the_ones_after_it() {
+        let log = Log::default();
+        let mut game_loop = game_loop(10);
+        game_loop.add_system(recorder("broken", &log, true));
+        game_loop.add_system(recorder("healthy", &log, false));
+
+        game_loop.tick().await;
+
+        assert_eq!(vec![("broken", 1), ("healthy", 1)], *log.lock().unwrap());
+    }
+
+    #[tokio::test]
+    async fn slow_ticks_are_counted_as_overruns() {
+        let mut game_loop = game_loop(1000);
+        game_loop.add_system(Sleeper(Duration::from_millis(5)));
+
+        game_loop.tick().await;
+
+        assert_eq!(1, game_loop.stats().ticks);
+        assert_eq!(1, game_loop.stats().overruns);
+        assert!(game_loop.stats().max >= Duration::from_millis(5));
+    }
+}
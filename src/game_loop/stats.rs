@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TickStats {
+    pub ticks: u64,
+    /// Ticks that took longer than the tick interval
+    pub overruns: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl TickStats {
+    pub fn record(&mut self, duration: Duration, overrun: bool) {
+        self.ticks += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+        if overrun {
+            self.overruns += 1;
+        }
+    }
+
+    pub fn average(&self) -> Duration {
+        match u32::try_from(self.ticks) {
+            Ok(0) => Duration::ZERO,
+            Ok(ticks) => self.total / ticks,
+            Err(_) => Duration::from_secs_f64(self.total.as_secs_f64() / self.ticks as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::game_loop::TickStats;
+
+    #[test]
+    fn stats_track_average_max_and_overruns() {
+        let mut stats = TickStats::default();
+        assert_eq!(Duration::ZERO, stats.average());
+
+        stats.record(Duration::from_millis(10), false);
+        stats.record(Duration::from_millis(30), true);
+
+        assert_eq!(2, stats.ticks);
+        assert_eq!(1, stats.overruns);
+        assert_eq!(Duration::from_millis(20), stats.average());
+        assert_eq!(Duration::from_millis(30), stats.max);
+    }
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+/// What a system gets to know about the tick it runs in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickContext {
+    /// Starts at 1 and goes up by one every tick
+    pub tick: u64,
+    /// Game time, advances by exactly one tick interval per tick so replays and tests line up
+    pub now: DateTime<Utc>,
+    pub delta: Duration,
+}
+
+/// Simulation logic that runs once per tick, after every system registered before it
+#[async_trait]
+pub trait GameSystem: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Errors are logged, the tick carries on with the next system
+    async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error>;
+}
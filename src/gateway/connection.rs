@@ -0,0 +1,341 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use chrono::Utc;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::characters::Character;
+use crate::chat::ChatService;
+use crate::combat::{CombatError, CombatService};
+use crate::configuration::GatewaySettings;
+use crate::friends::FriendService;
+use crate::guilds::GuildService;
+use crate::matchmaking::{MatchmakingError, MatchmakingService};
+use crate::npcs::{NpcError, NpcService};
+use crate::parties::{PartyError, PartyService};
+use crate::quests::{QuestError, QuestService};
+use crate::world::WorldService;
+use crate::gateway::{ClientMessage, ConnectionRegistry, ErrorCode, FriendView, RateLimitStrikes, RateLimiter, ServerMessage};
+
+/// Clients that keep sending after being told to slow down this many times are disconnected
+const MAX_RATE_LIMIT_STRIKES: u32 = 20;
+/// A client that stays under the rate limit this long starts over without strikes
+const RATE_LIMIT_STRIKE_MEMORY: Duration = Duration::from_secs(60);
+
+/// The services every connection talks to, handed to the WebSocket route as one
+#[derive(Clone)]
+pub struct GatewayServices {
+    pub registry: ConnectionRegistry,
+    pub chat: Arc<ChatService>,
+    pub world: Arc<WorldService>,
+    pub combat: Arc<CombatService>,
+    pub quests: Arc<QuestService>,
+    pub guilds: Arc<GuildService>,
+    pub friends: Arc<FriendService>,
+    pub matchmaking: Arc<MatchmakingService>,
+    pub parties: Arc<PartyService>,
+    pub npcs: Arc<NpcService>,
+    pub settings: GatewaySettings,
+}
+
+pub struct ConnectionContext {
+    pub user_id: UserId,
+    pub connection_id: Uuid,
+    pub registry: ConnectionRegistry,
+    pub chat: Arc<ChatService>,
+    pub world: Arc<WorldService>,
+    pub combat: Arc<CombatService>,
+    pub quests: Arc<QuestService>,
+    pub friends: Arc<FriendService>,
+    pub matchmaking: Arc<MatchmakingService>,
+    pub parties: Arc<PartyService>,
+    pub npcs: Arc<NpcService>,
+    /// The character selected when the connection was opened
+    pub character: Option<Character>,
+}
+
+/// Errors from the services behind the gateway, their message goes to the client as is
+/// unless the code says something unexpected went wrong
+pub trait ClientError: std::fmt::Display + std::fmt::Debug {
+    fn code(&self) -> ErrorCode;
+}
+
+/// Drives one WebSocket connection until either side closes it, the client goes quiet or falls
+/// too far behind on outgoing messages
+#[tracing::instrument(
+name = "WebSocket connection",
+skip(session, stream, character, services),
+fields(connection_id = tracing::field::Empty)
+)]
+pub async fn run_connection(
+    mut session: Session,
+    stream: MessageStream,
+    user_id: UserId,
+    character: Option<Character>,
+    services: GatewayServices,
+) {
+    let GatewayServices { registry, chat, world, combat, quests, guilds: _, friends, matchmaking, parties, npcs, settings } = services;
+    let mut stream = stream.max_frame_size(settings.max_message_bytes);
+    let (connection_id, mut outbound) = registry.register(user_id, settings.outbound_queue_size);
+    tracing::Span::current().record("connection_id", tracing::field::display(connection_id));
+    let ctx = ConnectionContext {
+        user_id,
+        connection_id,
+        registry: registry.clone(),
+        chat: chat.clone(),
+        world: world.clone(),
+        combat: combat.clone(),
+        quests,
+        friends: friends.clone(),
+        matchmaking,
+        parties: parties.clone(),
+        npcs,
+        character: character.clone(),
+    };
+
+    let mut limiter = RateLimiter::new(settings.message_burst, settings.messages_per_second, Instant::now());
+    let mut strikes = RateLimitStrikes::new(RATE_LIMIT_STRIKE_MEMORY);
+    let mut heartbeat = tokio::time::interval(settings.heartbeat_interval());
+    let mut last_seen = Instant::now();
+    // pings and pongs keep the connection alive but don't count as the player doing something
+    let mut last_active = Instant::now();
+
+    tracing::info!("Player connected");
+    let welcome = ServerMessage::Welcome { connection_id };
+    let mut close_reason = match session.text(welcome.to_json()).await {
+        Ok(()) => None,
+        Err(_) => Some(None),
+    };
+
+    if let Err(e) = friends.connected(user_id).await {
+        tracing::warn!(error.cause_chain = ?e, "Failed to update presence");
+    }
+    parties.connected(user_id);
+
+    // without a character the connection is only good for chat
+    if let Some(character) = &character {
+        if let Some(error) = reply(world.enter(user_id, character).await.map(|_| None)) {
+            let _ = session.text(error.to_json()).await;
+        }
+    }
+
+    while close_reason.is_none() {
+        tokio::select! {
+            incoming = stream.recv() => {
+                let message = match incoming {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        tracing::info!(error = %e, "WebSocket protocol error");
+                        close_reason = Some(Some(CloseReason::from((CloseCode::Protocol, e.to_string()))));
+                        continue;
+                    }
+                    None => {
+                        close_reason = Some(None);
+                        continue;
+                    }
+                };
+                last_seen = Instant::now();
+
+                let reply = match message {
+                    Message::Text(text) => {
+                        last_active = last_seen;
+                        if limiter.try_acquire(last_seen) {
+                            match ClientMessage::parse(&text) {
+                                Ok(message) => dispatch(&ctx, message).await,
+                                Err(e) => Some(ServerMessage::error(ErrorCode::InvalidMessage, e)),
+                            }
+                        } else {
+                            if strikes.strike(last_seen) >= MAX_RATE_LIMIT_STRIKES {
+                                close_reason = Some(Some(CloseReason::from((CloseCode::Policy, "Rate limit exceeded"))));
+                                continue;
+                            }
+                            Some(ServerMessage::error(ErrorCode::RateLimited, "Too many messages, slow down"))
+                        }
+                    }
+                    Message::Binary(_) => Some(ServerMessage::error(ErrorCode::InvalidMessage, "Only JSON text messages are supported")),
+                    Message::Ping(bytes) => {
+                        if session.pong(&bytes).await.is_err() {
+                            close_reason = Some(None);
+                        }
+                        None
+                    }
+                    Message::Close(reason) => {
+                        close_reason = Some(reason);
+                        None
+                    }
+                    Message::Pong(_) | Message::Continuation(_) | Message::Nop => None,
+                };
+
+                if let Some(reply) = reply {
+                    if session.text(reply.to_json()).await.is_err() {
+                        close_reason = Some(None);
+                    }
+                }
+            }
+            pushed = outbound.recv() => match pushed {
+                Some(message) => {
+                    if session.text(message.to_json()).await.is_err() {
+                        close_reason = Some(None);
+                    }
+                }
+                // the registry dropped us for not keeping up
+                None => close_reason = Some(Some(CloseReason::from((CloseCode::Again, "Too far behind, reconnect")))),
+            },
+            _ = heartbeat.tick() => {
+                if last_seen.elapsed() > settings.client_timeout() {
+                    tracing::info!("Client stopped responding");
+                    close_reason = Some(Some(CloseReason::from((CloseCode::Away, "Heartbeat timeout"))));
+                } else if session.ping(b"").await.is_err() {
+                    close_reason = Some(None);
+                } else {
+                    let in_combat = combat.encounter_id(user_id).is_some();
+                    if let Err(e) = friends.heartbeat(user_id, in_combat, last_active.elapsed()).await {
+                        tracing::warn!(error.cause_chain = ?e, "Failed to update presence");
+                    }
+                }
+            }
+        }
+    }
+
+    registry.unregister(user_id, connection_id);
+    chat.disconnected(user_id);
+    world.disconnected(user_id).await;
+    if let Err(e) = friends.disconnected(user_id).await {
+        tracing::warn!(error.cause_chain = ?e, "Failed to update presence");
+    }
+    parties.disconnected(user_id, Utc::now());
+    tracing::info!("Player disconnected");
+    let _ = session.close(close_reason.flatten()).await;
+}
+
+/// One place that knows what every client message does
+async fn dispatch(ctx: &ConnectionContext, message: ClientMessage) -> Option<ServerMessage> {
+    match message {
+        ClientMessage::Ping { nonce } => Some(ServerMessage::Pong { nonce }),
+        // delivered messages come back through the registry like everyone else's
+        ClientMessage::ChatSend { channel, body } => reply(
+            ctx.chat.send(ctx.user_id, channel, &body).await.map(|_| None)
+        ),
+        ClientMessage::ChatWhisper { to, body } => reply(
+            ctx.chat.whisper(ctx.user_id, &to, &body).await.map(|_| None)
+        ),
+        ClientMessage::ChatHistory { channel, with, before } => reply(
+            ctx.chat
+                .history(ctx.user_id, channel, with.as_deref(), before)
+                .await
+                .map(|(messages, more)| Some(ServerMessage::ChatHistory { channel, messages, more }))
+        ),
+        ClientMessage::ChatReport { message_id, reason } => reply(
+            ctx.chat
+                .report(ctx.user_id, message_id, &reason)
+                .await
+                .map(|_| Some(ServerMessage::Notice { message: "Thanks, a moderator will look into it".to_string() }))
+        ),
+        // blocking someone also ends the friendship
+        ClientMessage::ChatBlock { display_name } => reply(
+            ctx.friends
+                .block(ctx.user_id, &display_name)
+                .await
+                .map(|name| Some(ServerMessage::Notice { message: format!("You blocked {}", name) }))
+        ),
+        ClientMessage::ChatUnblock { display_name } => reply(
+            ctx.chat
+                .unblock(ctx.user_id, &display_name)
+                .await
+                .map(|name| Some(ServerMessage::Notice { message: format!("You unblocked {}", name) }))
+        ),
+        ClientMessage::ChatBlockList => reply(
+            ctx.chat
+                .blocked(ctx.user_id)
+                .await
+                .map(|display_names| Some(ServerMessage::ChatBlockList { display_names }))
+        ),
+        // the new position is pushed to every connection of the player
+        ClientMessage::Move { dx, dy } => reply(ctx.world.step(ctx.user_id, dx, dy).map(|_| None)),
+        // the fight itself is pushed to everyone in it
+        ClientMessage::CombatStart { creature } => match &ctx.character {
+            Some(character) => reply(
+                ctx.combat.fight_creature(ctx.user_id, character.clone(), &creature).await.map(|_| None)
+            ),
+            None => reply::<CombatError>(Err(CombatError::NoCharacter)),
+        },
+        ClientMessage::CombatAct { ability, target } => reply(
+            ctx.combat.act(ctx.user_id, &ability, target).await.map(|_| None)
+        ),
+        // the npc answers with npc_said, progress shows up as quest_progress once the quest system picked it up
+        ClientMessage::Talk { npc } => match &ctx.character {
+            Some(character) => {
+                let answered = ctx.npcs.talk(ctx.user_id, &npc);
+                match ctx.quests.talk(ctx.user_id, character, &npc) {
+                    Err(QuestError::UnknownNpc(_)) if answered => None,
+                    talked => reply(talked.map(|_| None)),
+                }
+            }
+            None => reply::<QuestError>(Err(QuestError::NoCharacter)),
+        },
+        // combat_started follows like for any other fight
+        ClientMessage::NpcAttack { npc_id } => match &ctx.character {
+            Some(character) => reply(ctx.npcs.engage(ctx.user_id, character.clone(), npc_id).await.map(|_| None)),
+            None => reply::<NpcError>(Err(NpcError::NoCharacter)),
+        },
+        ClientMessage::FriendList => reply(
+            ctx.friends
+                .friends(ctx.user_id)
+                .await
+                .map(|friends| Some(ServerMessage::FriendList {
+                    friends: friends
+                        .into_iter()
+                        .map(|f| FriendView { display_name: f.display_name, status: f.status })
+                        .collect(),
+                }))
+        ),
+        // queue_joined goes to every member of the ticket
+        ClientMessage::QueueJoin { queue } => match &ctx.character {
+            Some(character) => match ctx.parties.queue_group(ctx.user_id, character) {
+                Ok(group) => reply(ctx.matchmaking.join_group(queue, group, Utc::now()).await.map(|_| None)),
+                Err(e) => reply::<PartyError>(Err(e)),
+            },
+            None => reply::<MatchmakingError>(Err(MatchmakingError::NoCharacter)),
+        },
+        ClientMessage::QueueLeave => reply(ctx.matchmaking.leave(ctx.user_id).map(|_| None)),
+        // the fight is pushed to everyone in it once the last player accepted
+        ClientMessage::ReadyCheck { ready_check_id, accept } => reply(
+            ctx.matchmaking.ready(ctx.user_id, ready_check_id, accept, Utc::now()).await.map(|_| None)
+        ),
+        // everyone in the party gets party_update whenever it changes
+        ClientMessage::PartyInvite { display_name } => match &ctx.character {
+            Some(character) => reply(
+                ctx.parties
+                    .invite(ctx.user_id, character, &display_name, Utc::now())
+                    .await
+                    .map(|name| Some(ServerMessage::Notice { message: format!("You invited {} to your party", name) }))
+            ),
+            None => reply::<PartyError>(Err(PartyError::NoCharacter)),
+        },
+        ClientMessage::PartyAccept { party_id } => match &ctx.character {
+            Some(character) => reply(ctx.parties.accept(ctx.user_id, character, party_id, Utc::now()).await.map(|_| None)),
+            None => reply::<PartyError>(Err(PartyError::NoCharacter)),
+        },
+        ClientMessage::PartyDecline { party_id } => reply(ctx.parties.decline(ctx.user_id, party_id).map(|_| None)),
+        ClientMessage::PartyLeave => reply(ctx.parties.leave(ctx.user_id).map(|_| None)),
+        ClientMessage::PartyKick { display_name } => reply(
+            ctx.parties
+                .kick(ctx.user_id, &display_name)
+                .map(|name| Some(ServerMessage::Notice { message: format!("You removed {} from the party", name) }))
+        ),
+        ClientMessage::PartyPromote { display_name } => reply(ctx.parties.promote(ctx.user_id, &display_name).map(|_| None)),
+        ClientMessage::PartyLootMode { mode } => reply(ctx.parties.set_loot_mode(ctx.user_id, mode).map(|_| None)),
+        // loot_roll_ended goes to everyone who could roll once the last answer is in
+        ClientMessage::LootRoll { roll_id, choice } => reply(ctx.parties.roll(ctx.user_id, roll_id, choice).await.map(|_| None)),
+    }
+}
+
+fn reply<E: ClientError>(outcome: Result<Option<ServerMessage>, E>) -> Option<ServerMessage> {
+    outcome.unwrap_or_else(|e| match e.code() {
+        ErrorCode::Internal => {
+            tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to handle client message");
+            Some(ServerMessage::error(ErrorCode::Internal, "Something went wrong, try again later"))
+        }
+        code => Some(ServerMessage::error(code, e.to_string())),
+    })
+}
@@ -0,0 +1,9 @@
+mod connection;
+mod protocol;
+mod rate_limit;
+mod registry;
+
+pub use connection::{run_connection, ClientError, ConnectionContext, GatewayServices};
+pub use protocol::{ChatLine, ClientMessage, CombatantView, EntityKind, EntityView, ErrorCode, FriendView, PartyMemberView, PartyView, ServerMessage};
+pub use rate_limit::{RateLimitStrikes, RateLimiter};
+pub use registry::ConnectionRegistry;
\ No newline at end of file
@@ -0,0 +1,392 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::chat::ChatChannelKind;
+use crate::combat::CombatEvent;
+use crate::matchmaking::QueueKind;
+use crate::parties::{LootChoice, LootMode};
+use crate::presence::PresenceStatus;
+
+/// Everything a client can send, as `{"type": "ping", ...}`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Application level ping, echoes the nonce back so clients can measure latency
+    Ping {
+        #[serde(default)]
+        nonce: Option<u64>,
+    },
+    /// Says something in the global chat or the zone, party or guild the player is in
+    ChatSend {
+        channel: ChatChannelKind,
+        body: String,
+    },
+    ChatWhisper {
+        to: String,
+        body: String,
+    },
+    /// Whisper history needs `with`, the display name of the other player.
+    /// Older pages are fetched by passing the oldest message id seen so far as `before`.
+    ChatHistory {
+        channel: ChatChannelKind,
+        #[serde(default)]
+        with: Option<String>,
+        #[serde(default)]
+        before: Option<Uuid>,
+    },
+    ChatReport {
+        message_id: Uuid,
+        #[serde(default)]
+        reason: String,
+    },
+    ChatBlock {
+        display_name: String,
+    },
+    ChatUnblock {
+        display_name: String,
+    },
+    ChatBlockList,
+    /// One tile in any of the eight directions, `dx` and `dy` are -1, 0 or 1
+    Move {
+        dx: i32,
+        dy: i32,
+    },
+    /// Picks a fight with a creature that roams the current zone
+    CombatStart {
+        creature: String,
+    },
+    /// `target` is the index of a combatant from `combat_started`
+    CombatAct {
+        ability: String,
+        target: usize,
+    },
+    /// Talks to an npc in the current zone, for quests that send the player to someone
+    Talk {
+        npc: String,
+    },
+    /// Fights an npc standing right next to the player, by its entity id
+    NpcAttack {
+        npc_id: Uuid,
+    },
+    FriendList,
+    /// Invites someone to the player's party, starting one with the selected character if needed
+    PartyInvite {
+        display_name: String,
+    },
+    PartyAccept {
+        party_id: Uuid,
+    },
+    PartyDecline {
+        party_id: Uuid,
+    },
+    PartyLeave,
+    /// Leader only, like the two below
+    PartyKick {
+        display_name: String,
+    },
+    PartyPromote {
+        display_name: String,
+    },
+    PartyLootMode {
+        mode: LootMode,
+    },
+    /// Answers a `loot_roll_started`
+    LootRoll {
+        roll_id: Uuid,
+        choice: LootChoice,
+    },
+    /// Queues the player with the selected character, a party leader queues the whole party
+    QueueJoin {
+        queue: QueueKind,
+    },
+    QueueLeave,
+    /// Answers a `match_found`
+    ReadyCheck {
+        ready_check_id: Uuid,
+        accept: bool,
+    },
+}
+
+/// Everything the server pushes, tagged the same way as `ClientMessage`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Welcome {
+        connection_id: Uuid,
+    },
+    Pong {
+        nonce: Option<u64>,
+    },
+    Error {
+        code: ErrorCode,
+        message: String,
+    },
+    /// Anything informational that isn't an answer to a specific request
+    Notice {
+        message: String,
+    },
+    ChatMessage(ChatLine),
+    /// Oldest message first, `more` tells whether there are older pages
+    ChatHistory {
+        channel: ChatChannelKind,
+        messages: Vec<ChatLine>,
+        more: bool,
+    },
+    ChatBlockList {
+        display_names: Vec<String>,
+    },
+    /// Sent on entering the world and on every zone change, with everything in view
+    ZoneEntered {
+        zone_id: String,
+        zone_name: String,
+        tiles: Vec<String>,
+        x: i32,
+        y: i32,
+        entities: Vec<EntityView>,
+    },
+    /// The server accepted a step, this is where the character is now
+    Moved {
+        x: i32,
+        y: i32,
+    },
+    EntityAppeared {
+        entity: EntityView,
+    },
+    EntityMoved {
+        id: Uuid,
+        x: i32,
+        y: i32,
+    },
+    EntityLeft {
+        id: Uuid,
+    },
+    /// `you` is the player's own index among the combatants
+    CombatStarted {
+        encounter_id: Uuid,
+        you: usize,
+        combatants: Vec<CombatantView>,
+    },
+    /// `turn` is whoever has to act next, unset once the fight has ended
+    CombatUpdate {
+        encounter_id: Uuid,
+        events: Vec<CombatEvent>,
+        turn: Option<usize>,
+    },
+    /// An npc in view said something, to the player talking to it
+    NpcSaid {
+        npc_id: Uuid,
+        name: String,
+        line: String,
+    },
+    /// An objective of an active quest moved forward, `objective` indexes the quest's objectives
+    QuestProgress {
+        quest_id: String,
+        objective: usize,
+        progress: i32,
+        required: i32,
+    },
+    /// Every friend with their current presence, online ones first
+    FriendList {
+        friends: Vec<FriendView>,
+    },
+    /// A friend came online, went away, got into a fight or left
+    FriendStatus {
+        display_name: String,
+        status: PresenceStatus,
+    },
+    /// Answer with `party_accept` or `party_decline`
+    PartyInvite {
+        party_id: Uuid,
+        from: String,
+    },
+    /// The whole party, whenever anything about it changes
+    PartyUpdate {
+        party: PartyView,
+    },
+    /// The player is no longer in the party, a notice says why
+    PartyLeft {
+        party_id: Uuid,
+    },
+    /// Everyone who can roll has `seconds` to answer with `loot_roll`
+    LootRollStarted {
+        roll_id: Uuid,
+        item_id: String,
+        quantity: i32,
+        seconds: i64,
+    },
+    /// `winner` is a character name, nobody wins when everyone passed
+    LootRollEnded {
+        roll_id: Uuid,
+        winner: Option<String>,
+        roll: Option<u32>,
+    },
+    /// An item was handed out to `to`, it arrives by mail
+    LootAwarded {
+        item_id: String,
+        quantity: i32,
+        to: String,
+    },
+    QueueJoined {
+        queue: QueueKind,
+    },
+    QueueLeft {
+        queue: QueueKind,
+    },
+    /// Everyone matched has `seconds` to accept with `ready_check`, the fight starts once they all did
+    MatchFound {
+        ready_check_id: Uuid,
+        queue: QueueKind,
+        seconds: i64,
+    },
+    /// Someone declined or didn't answer in time, `requeued` tells whether the player is back in
+    /// the queue with their place in line
+    ReadyCheckFailed {
+        ready_check_id: Uuid,
+        requeued: bool,
+    },
+    /// After a rated match
+    RatingChanged {
+        rating: i32,
+        change: i32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Character,
+    Npc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct EntityView {
+    pub id: Uuid,
+    pub kind: EntityKind,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CombatantView {
+    pub name: String,
+    pub side: usize,
+    pub level: i32,
+    pub health: i32,
+    pub max_health: i32,
+    pub abilities: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FriendView {
+    pub display_name: String,
+    pub status: PresenceStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PartyMemberView {
+    pub display_name: String,
+    pub character: String,
+    pub level: i32,
+    pub online: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PartyView {
+    pub id: Uuid,
+    /// Display name of the leader
+    pub leader: String,
+    pub loot_mode: LootMode,
+    pub members: Vec<PartyMemberView>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ChatLine {
+    pub id: Uuid,
+    pub channel: ChatChannelKind,
+    pub sender: String,
+    /// Only set on whispers
+    pub recipient: Option<String>,
+    pub body: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidMessage,
+    RateLimited,
+    Muted,
+    NotFound,
+    Forbidden,
+    InvalidMove,
+    InvalidAction,
+    Internal,
+}
+
+impl ServerMessage {
+    pub fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        ServerMessage::Error { code, message: message.into() }
+    }
+
+    pub fn to_json(&self) -> String {
+        // only plain structs and enums in here, serialization can't fail
+        serde_json::to_string(self).expect("Server messages always serialize")
+    }
+}
+
+impl ClientMessage {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| format!("Invalid message: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok_eq};
+    use uuid::Uuid;
+    use crate::chat::ChatChannelKind;
+    use crate::gateway::{ClientMessage, ErrorCode, ServerMessage};
+
+    #[test]
+    fn ping_is_parsed_with_and_without_nonce() {
+        assert_ok_eq!(ClientMessage::parse(r#"{"type": "ping", "nonce": 7}"#), ClientMessage::Ping { nonce: Some(7) });
+        assert_ok_eq!(ClientMessage::parse(r#"{"type": "ping"}"#), ClientMessage::Ping { nonce: None });
+    }
+
+    #[test]
+    fn unknown_or_malformed_messages_are_rejected() {
+        assert_err!(ClientMessage::parse(r#"{"type": "teleport"}"#));
+        assert_err!(ClientMessage::parse("ping"));
+        assert_err!(ClientMessage::parse(r#"{"type": "chat_send", "channel": "trade", "body": "wts"}"#));
+    }
+
+    #[test]
+    fn chat_history_paging_fields_are_optional() {
+        assert_ok_eq!(
+            ClientMessage::parse(r#"{"type": "chat_history", "channel": "global"}"#),
+            ClientMessage::ChatHistory { channel: ChatChannelKind::Global, with: None, before: None }
+        );
+    }
+
+    #[test]
+    fn combat_actions_name_their_target_by_index() {
+        assert_ok_eq!(
+            ClientMessage::parse(r#"{"type": "combat_act", "ability": "strike", "target": 1}"#),
+            ClientMessage::CombatAct { ability: "strike".to_string(), target: 1 }
+        );
+        assert_err!(ClientMessage::parse(r#"{"type": "combat_act", "ability": "strike", "target": -1}"#));
+    }
+
+    #[test]
+    fn server_messages_are_tagged_with_their_type() {
+        let id = Uuid::new_v4();
+        assert_eq!(
+            format!(r#"{{"type":"welcome","connection_id":"{}"}}"#, id),
+            ServerMessage::Welcome { connection_id: id }.to_json()
+        );
+        assert_eq!(
+            r#"{"type":"error","code":"rate_limited","message":"slow down"}"#,
+            ServerMessage::error(ErrorCode::RateLimited, "slow down").to_json()
+        );
+    }
+}
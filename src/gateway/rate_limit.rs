@@ -0,0 +1,105 @@
+use std::time::{Duration, Instant};
+
+/// Token bucket, starts full and refills continuously. Time is passed in so tests don't have to sleep.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    capacity: f64,
+    tokens: f64,
+    refill_per_second: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(capacity: u32, refill_per_second: u32, now: Instant) -> Self {
+        RateLimiter {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill_per_second: refill_per_second as f64,
+            last_refill: now,
+        }
+    }
+
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Counts how often a client ran into the rate limit, strikes are forgotten once the client went
+/// `forget_after` without a new one
+#[derive(Debug, Clone)]
+pub struct RateLimitStrikes {
+    count: u32,
+    last: Option<Instant>,
+    forget_after: Duration,
+}
+
+impl RateLimitStrikes {
+    pub fn new(forget_after: Duration) -> Self {
+        RateLimitStrikes { count: 0, last: None, forget_after }
+    }
+
+    /// Records a strike, returns how many are counted including this one
+    pub fn strike(&mut self, now: Instant) -> u32 {
+        if self.last.is_some_and(|last| now.saturating_duration_since(last) >= self.forget_after) {
+            self.count = 0;
+        }
+        self.count += 1;
+        self.last = Some(now);
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use crate::gateway::{RateLimitStrikes, RateLimiter};
+
+    #[test]
+    fn burst_is_allowed_then_limited() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(3, 1, now);
+
+        assert!(limiter.try_acquire(now));
+        assert!(limiter.try_acquire(now));
+        assert!(limiter.try_acquire(now));
+        assert!(!limiter.try_acquire(now));
+    }
+
+    #[test]
+    fn tokens_refill_over_time_up_to_capacity() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(2, 2, start);
+        assert!(limiter.try_acquire(start));
+        assert!(limiter.try_acquire(start));
+        assert!(!limiter.try_acquire(start));
+
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.try_acquire(later));
+        assert!(!limiter.try_acquire(later));
+
+        let much_later = later + Duration::from_secs(60);
+        assert!(limiter.try_acquire(much_later));
+        assert!(limiter.try_acquire(much_later));
+        assert!(!limiter.try_acquire(much_later));
+    }
+
+    #[test]
+    fn strikes_are_forgotten_after_a_quiet_while() {
+        let start = Instant::now();
+        let mut strikes = RateLimitStrikes::new(Duration::from_secs(60));
+        assert_eq!(1, strikes.strike(start));
+        assert_eq!(2, strikes.strike(start + Duration::from_secs(30)));
+        assert_eq!(3, strikes.strike(start + Duration::from_secs(89)));
+
+        assert_eq!(1, strikes.strike(start + Duration::from_secs(149)));
+    }
+}
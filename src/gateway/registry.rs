@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::gateway::ServerMessage;
+
+struct Connection {
+    id: Uuid,
+    sender: mpsc::Sender<ServerMessage>,
+}
+
+/// Open WebSocket connections per player, a player can be connected from several tabs at once.
+/// Cheap to clone, every clone sees the same connections.
+#[derive(Clone, Default)]
+pub struct ConnectionRegistry {
+    connections: Arc<RwLock<HashMap<UserId, Vec<Connection>>>>,
+}
+
+impl ConnectionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The receiver gets everything sent to `user_id` until the connection is unregistered or evicted
+    pub fn register(&self, user_id: UserId, queue_size: usize) -> (Uuid, mpsc::Receiver<ServerMessage>) {
+        let (sender, receiver) = mpsc::channel(queue_size.max(1));
+        let id = Uuid::new_v4();
+        self.connections
+            .write()
+            .expect("Connection registry lock poisoned")
+            .entry(user_id)
+            .or_default()
+            .push(Connection { id, sender });
+        (id, receiver)
+    }
+
+    pub fn unregister(&self, user_id: UserId, connection_id: Uuid) {
+        let mut connections = self.connections.write().expect("Connection registry lock poisoned");
+        if let Some(user_connections) = connections.get_mut(&user_id) {
+            user_connections.retain(|c| c.id != connection_id);
+            if user_connections.is_empty() {
+                connections.remove(&user_id);
+            }
+        }
+    }
+
+    /// Never waits. Connections whose queue is full are too slow to keep up and get dropped,
+    /// their receiver drains what is queued and then reports the channel as closed.
+    /// Returns how many connections the message was queued for.
+    pub fn send_to_user(&self, user_id: UserId, message: &ServerMessage) -> usize {
+        let mut connections = self.connections.write().expect("Connection registry lock poisoned");
+        let delivered = match connections.get_mut(&user_id) {
+            Some(user_connections) => deliver(user_id, user_connections, message),
+            None => return 0,
+        };
+        if connections.get(&user_id).is_some_and(|c| c.is_empty()) {
+            connections.remove(&user_id);
+        }
+        delivered
+    }
+
+    pub fn broadcast(&self, message: &ServerMessage) -> usize {
+        let mut connections = self.connections.write().expect("Connection registry lock poisoned");
+        let delivered = connections
+            .iter_mut()
+            .map(|(user_id, user_connections)| deliver(*user_id, user_connections, message))
+            .sum();
+        connections.retain(|_, c| !c.is_empty());
+        delivered
+    }
+
+    pub fn is_online(&self, user_id: UserId) -> bool {
+        self.connections
+            .read()
+            .expect("Connection registry lock poisoned")
+            .contains_key(&user_id)
+    }
+
+    pub fn online_users(&self) -> Vec<UserId> {
+        self.connections
+            .read()
+            .expect("Connection registry lock poisoned")
+            .keys()
+            .copied()
+            .collect()
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.connections
+            .read()
+            .expect("Connection registry lock poisoned")
+            .values()
+            .map(|c| c.len())
+            .sum()
+    }
+}
+
+fn deliver(user_id: UserId, connections: &mut Vec<Connection>, message: &ServerMessage) -> usize {
+    let mut delivered = 0;
+    connections.retain(|connection| match connection.sender.try_send(message.clone()) {
+        Ok(()) => {
+            delivered += 1;
+            true
+        }
+        Err(TrySendError::Full(_)) => {
+            tracing::warn!(%user_id, connection_id = %connection.id, "Dropping connection that can't keep up");
+            false
+        }
+        Err(TrySendError::Closed(_)) => false,
+    });
+    delivered
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::authentication::UserId;
+    use crate::gateway::{ConnectionRegistry, ServerMessage};
+
+    fn pong(nonce: u64) -> ServerMessage {
+        ServerMessage::Pong { nonce: Some(nonce) }
+    }
+
+    #[tokio::test]
+    async fn messages_reach_every_connection_of_the_user() {
+        let registry = ConnectionRegistry::new();
+        let user = UserId::from(Uuid::new_v4());
+        let other = UserId::from(Uuid::new_v4());
+        let (_, mut first) = registry.register(user, 4);
+        let (_, mut second) = registry.register(user, 4);
+        let (_, mut others) = registry.register(other, 4);
+
+        assert_eq!(2, registry.send_to_user(user, &pong(1)));
+
+        assert_eq!(Some(pong(1)), first.recv().await);
+        assert_eq!(Some(pong(1)), second.recv().await);
+        assert!(others.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn unregistered_users_are_offline() {
+        let registry = ConnectionRegistry::new();
+        let user = UserId::from(Uuid::new_v4());
+        let (id, _receiver) = registry.register(user, 4);
+        assert!(registry.is_online(user));
+
+        registry.unregister(user, id);
+
+        assert!(!registry.is_online(user));
+        assert_eq!(0, registry.send_to_user(user, &pong(1)));
+    }
+
+    #[tokio::test]
+    async fn slow_connections_are_dropped_once_their_queue_is_full() {
+        let registry = ConnectionRegistry::new();
+        let user = UserId::from(Uuid::new_v4());
+        let (_, mut receiver) = registry.register(user, 2);
+
+        assert_eq!(1, registry.send_to_user(user, &pong(1)));
+        assert_eq!(1, registry.send_to_user(user, &pong(2)));
+        assert_eq!(0, registry.send_to_user(user, &pong(3)));
+
+        assert!(!registry.is_online(user));
+        assert_eq!(Some(pong(1)), receiver.recv().await);
+        assert_eq!(Some(pong(2)), receiver.recv().await);
+        assert_eq!(None, receiver.recv().await);
+    }
+
+    #[tokio::test]
+    async fn broadcast_skips_closed_connections() {
+        let registry = ConnectionRegistry::new();
+        let (_, closed) = registry.register(UserId::from(Uuid::new_v4()), 2);
+        let (_, _open) = registry.register(UserId::from(Uuid::new_v4()), 2);
+        drop(closed);
+
+        assert_eq!(1, registry.broadcast(&pong(1)));
+        assert_eq!(1, registry.connection_count());
+    }
+}
@@ -0,0 +1,47 @@
+use std::future::Future;
+use std::pin::Pin;
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpRequest};
+use anyhow::anyhow;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::guilds::GuildRank;
+use crate::guilds::store::get_membership;
+use crate::utils::{e500, see_other};
+
+/// A player in a guild, everyone else is sent to `/guilds` to find or found one
+pub struct GuildMember {
+    pub user_id: UserId,
+    pub guild_id: Uuid,
+    pub rank: GuildRank,
+}
+
+impl FromRequest for GuildMember {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output=Result<GuildMember, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user_id = UserId::from_request(req, payload).into_inner();
+        let pool = req.app_data::<Data<PgPool>>().cloned();
+
+        Box::pin(async move {
+            let user_id = user_id?;
+            let pool = pool.ok_or_else(|| e500("Database pool is not configured"))?;
+
+            match get_membership(pool.get_ref(), *user_id).await.map_err(e500)? {
+                Some(membership) => Ok(GuildMember {
+                    user_id,
+                    guild_id: membership.guild_id,
+                    rank: membership.rank,
+                }),
+                None => {
+                    let e = anyhow!("Not in a guild");
+                    Err(InternalError::from_response(e, see_other("/guilds")).into())
+                }
+            }
+        })
+    }
+}
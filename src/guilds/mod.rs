@@ -0,0 +1,11 @@
+mod extractor;
+mod name;
+mod permission;
+mod service;
+mod store;
+
+pub use extractor::GuildMember;
+pub use name::{GuildName, GuildTag};
+pub use permission::{GuildPermission, GuildRank, LEADER_RANK};
+pub use service::{GuildError, GuildService};
+pub use store::{get_guild, get_guild_applications, get_guild_invitations, get_guild_members, get_guild_ranks, get_guilds, get_membership, Guild, GuildApplication, GuildInvitation, GuildMemberRow, GuildSummary, Membership};
\ No newline at end of file
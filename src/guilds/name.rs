@@ -0,0 +1,127 @@
+use std::fmt::Formatter;
+use crate::domain::check_name_is_acceptable;
+
+const MIN_NAME_LENGTH: usize = 3;
+const MAX_NAME_LENGTH: usize = 24;
+const MIN_TAG_LENGTH: usize = 2;
+const MAX_TAG_LENGTH: usize = 5;
+
+/// Letters, digits and single spaces between words, `  Iron   Wolves ` becomes `Iron Wolves`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuildName(String);
+
+impl GuildName {
+    pub fn parse(v: String) -> Result<Self, String> {
+        let name = v.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        let length = name.chars().count();
+        if !(MIN_NAME_LENGTH..=MAX_NAME_LENGTH).contains(&length) {
+            return Err(format!(
+                "Guild name must be between {} and {} characters long",
+                MIN_NAME_LENGTH, MAX_NAME_LENGTH
+            ));
+        }
+
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ') {
+            return Err("Guild name can only contain letters, digits and spaces".to_string());
+        }
+
+        for word in name.split(' ') {
+            check_name_is_acceptable(word)?;
+        }
+        check_name_is_acceptable(&name.replace(' ', ""))?;
+        Ok(GuildName(name))
+    }
+}
+
+impl AsRef<str> for GuildName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for GuildName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// The short name shown next to members, always stored in capitals
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuildTag(String);
+
+impl GuildTag {
+    pub fn parse(v: String) -> Result<Self, String> {
+        let tag = v.trim();
+
+        let length = tag.chars().count();
+        if !(MIN_TAG_LENGTH..=MAX_TAG_LENGTH).contains(&length) {
+            return Err(format!(
+                "Guild tag must be between {} and {} characters long",
+                MIN_TAG_LENGTH, MAX_TAG_LENGTH
+            ));
+        }
+
+        if !tag.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err("Guild tag can only contain letters and digits".to_string());
+        }
+
+        check_name_is_acceptable(tag)?;
+        Ok(GuildTag(tag.to_ascii_uppercase()))
+    }
+}
+
+impl AsRef<str> for GuildTag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for GuildTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::assert_err;
+    use crate::guilds::{GuildName, GuildTag};
+
+    #[test]
+    fn names_collapse_whitespace() {
+        let name = GuildName::parse("  Iron   Wolves ".to_string()).unwrap();
+        assert_eq!("Iron Wolves", name.as_ref());
+    }
+
+    #[test]
+    fn names_must_be_letters_digits_and_spaces() {
+        for name in ["Iron_Wolves", "Iron-Wolves", "Wölfe"] {
+            assert_err!(GuildName::parse(name.to_string()));
+        }
+    }
+
+    #[test]
+    fn names_must_have_a_sensible_length() {
+        assert_err!(GuildName::parse("Ab".to_string()));
+        assert_err!(GuildName::parse("A".repeat(25)));
+    }
+
+    #[test]
+    fn reserved_words_are_rejected_anywhere_in_the_name() {
+        assert_err!(GuildName::parse("The Admin Club".to_string()));
+    }
+
+    #[test]
+    fn tags_are_capitalised() {
+        let tag = GuildTag::parse(" iw1 ".to_string()).unwrap();
+        assert_eq!("IW1", tag.as_ref());
+    }
+
+    #[test]
+    fn tags_must_be_short_and_alphanumeric() {
+        for tag in ["I", "WOLVES", "I W", "I-W"] {
+            assert_err!(GuildTag::parse(tag.to_string()));
+        }
+    }
+}
@@ -0,0 +1,131 @@
+/// What a rank lets its members do besides chatting and depositing, the leader can always do everything
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuildPermission {
+    Invite,
+    Kick,
+    ManageBank,
+    EditMotd,
+}
+
+impl GuildPermission {
+    pub const ALL: [GuildPermission; 4] = [
+        GuildPermission::Invite,
+        GuildPermission::Kick,
+        GuildPermission::ManageBank,
+        GuildPermission::EditMotd,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GuildPermission::Invite => "invite",
+            GuildPermission::Kick => "kick",
+            GuildPermission::ManageBank => "manage_bank",
+            GuildPermission::EditMotd => "edit_motd",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            GuildPermission::Invite => "Invite and accept applications",
+            GuildPermission::Kick => "Kick lower ranks",
+            GuildPermission::ManageBank => "Withdraw from the bank",
+            GuildPermission::EditMotd => "Edit the message of the day",
+        }
+    }
+}
+
+impl TryFrom<String> for GuildPermission {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "invite" => Ok(GuildPermission::Invite),
+            "kick" => Ok(GuildPermission::Kick),
+            "manage_bank" => Ok(GuildPermission::ManageBank),
+            "edit_motd" => Ok(GuildPermission::EditMotd),
+            other => Err(format!("{} is not a valid guild permission", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for GuildPermission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+pub const LEADER_RANK: i32 = 0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuildRank {
+    /// 0 leads the guild, higher numbers sit further down
+    pub rank: i32,
+    pub name: String,
+    pub permissions: Vec<GuildPermission>,
+}
+
+impl GuildRank {
+    pub fn is_leader(&self) -> bool {
+        self.rank == LEADER_RANK
+    }
+
+    pub fn can(&self, permission: GuildPermission) -> bool {
+        self.is_leader() || self.permissions.contains(&permission)
+    }
+
+    /// Kicking only works downwards, an officer can't kick another officer
+    pub fn outranks(&self, other: i32) -> bool {
+        self.rank < other
+    }
+
+    /// The ranks every new guild starts with
+    pub fn defaults() -> Vec<GuildRank> {
+        vec![
+            GuildRank {
+                rank: LEADER_RANK,
+                name: "Guild Master".to_string(),
+                permissions: GuildPermission::ALL.to_vec(),
+            },
+            GuildRank {
+                rank: 1,
+                name: "Officer".to_string(),
+                permissions: GuildPermission::ALL.to_vec(),
+            },
+            GuildRank {
+                rank: 2,
+                name: "Member".to_string(),
+                permissions: vec![],
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok_eq};
+    use crate::guilds::{GuildPermission, GuildRank};
+
+    #[test]
+    fn all_permissions_round_trip() {
+        for permission in GuildPermission::ALL {
+            assert_ok_eq!(GuildPermission::try_from(permission.as_str().to_uppercase()), permission);
+        }
+        assert_err!(GuildPermission::try_from("disband".to_string()));
+    }
+
+    #[test]
+    fn the_leader_can_do_anything() {
+        let leader = GuildRank { rank: 0, name: "Boss".to_string(), permissions: vec![] };
+        assert!(GuildPermission::ALL.iter().all(|p| leader.can(*p)));
+    }
+
+    #[test]
+    fn members_only_get_what_their_rank_grants() {
+        let defaults = GuildRank::defaults();
+        let member = defaults.last().unwrap();
+        assert!(!member.can(GuildPermission::Kick));
+        assert!(defaults[1].can(GuildPermission::Kick));
+        assert!(defaults[1].outranks(member.rank));
+        assert!(!defaults[1].outranks(defaults[1].rank));
+    }
+}
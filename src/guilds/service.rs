@@ -0,0 +1,627 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::audit::{record_audit_entry, AuditAction, AuditEntry};
+use crate::authentication::UserId;
+use crate::chat::{is_blocked, ChatService};
+use crate::configuration::GuildSettings;
+use crate::domain::Profile;
+use crate::gateway::{ClientError, ConnectionRegistry, ErrorCode, ServerMessage};
+use crate::guilds::{GuildName, GuildPermission, GuildRank, GuildTag, LEADER_RANK};
+use crate::guilds::store::{clear_pending_guild_requests, delete_guild, get_guild_members, get_guild_ranks, get_membership, lock_guild, remove_guild_application, remove_guild_invitation, remove_guild_member, store_guild, store_guild_application, store_guild_invitation, store_guild_member, store_guild_ranks, update_guild_member_rank, update_guild_motd, update_guild_rank_permissions, Guild, Membership};
+use crate::items::{InventoryError, InventoryOwner, InventoryService, ItemStack};
+use crate::store::{get_profile_by_display_name, get_profile_by_user_id, is_unique_violation};
+use crate::utils::error_chain_fmt;
+
+const TAG_CONSTRAINT: &str = "guilds_tag_key";
+const MAX_MOTD_LENGTH: usize = 200;
+const MAX_APPLICATION_LENGTH: usize = 200;
+
+#[derive(thiserror::Error)]
+pub enum GuildError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("Pick a display name on your profile before joining a guild")]
+    NoDisplayName,
+    #[error("The name {0} is already taken")]
+    NameTaken(String),
+    #[error("The tag {0} is already taken")]
+    TagTaken(String),
+    #[error("You are already in a guild")]
+    AlreadyInGuild,
+    #[error("{0} is already in a guild")]
+    PlayerAlreadyInGuild(String),
+    #[error("{0} is not accepting invitations from you")]
+    InviteRefused(String),
+    #[error("You are not in a guild")]
+    NotInGuild,
+    #[error("That guild doesn't exist")]
+    GuildNotFound,
+    #[error("There is no player called {0}")]
+    PlayerNotFound(String),
+    #[error("That player is not in your guild")]
+    NotAMember,
+    #[error("You have not been invited to that guild")]
+    NoInvitation,
+    #[error("That player has not applied to your guild")]
+    NoApplication,
+    #[error("The guild is full, it can't have more than {0} members")]
+    GuildFull(usize),
+    #[error("Your rank is not allowed to do that")]
+    NotAllowed,
+    #[error("Make someone else guild master before leaving")]
+    LeaderCannotLeave,
+    #[error("The guild has no rank {0}")]
+    UnknownRank(i32),
+    #[error(transparent)]
+    Inventory(#[from] InventoryError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for GuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for GuildError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            GuildError::ValidationError(_) => ErrorCode::InvalidMessage,
+            GuildError::GuildNotFound
+            | GuildError::PlayerNotFound(_)
+            | GuildError::NotAMember
+            | GuildError::NoInvitation
+            | GuildError::NoApplication
+            | GuildError::UnknownRank(_) => ErrorCode::NotFound,
+            GuildError::NotAllowed | GuildError::InviteRefused(_) | GuildError::NoDisplayName => ErrorCode::Forbidden,
+            GuildError::Inventory(e) => e.code(),
+            GuildError::UnexpectedError(_) => ErrorCode::Internal,
+            _ => ErrorCode::InvalidAction,
+        }
+    }
+}
+
+/// Guild membership, ranks and the shared bank. Every change locks the guild row, is recorded in the
+/// audit log with the guild as subject and keeps the guild chat channel in step.
+pub struct GuildService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    chat: Arc<ChatService>,
+    inventory: Arc<InventoryService>,
+    settings: GuildSettings,
+}
+
+impl GuildService {
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        chat: Arc<ChatService>,
+        inventory: Arc<InventoryService>,
+        settings: GuildSettings,
+    ) -> Self {
+        GuildService { pool, registry, chat, inventory, settings }
+    }
+
+    pub fn settings(&self) -> &GuildSettings {
+        &self.settings
+    }
+
+    /// Puts a freshly connected player in their guild's chat channel
+    pub async fn connected(&self, user_id: UserId) -> Result<(), GuildError> {
+        let membership = get_membership(&self.pool, *user_id).await?;
+        self.chat.set_guild(user_id, membership.map(|m| m.guild_id));
+        Ok(())
+    }
+
+    //region Founding and joining
+    #[tracing::instrument(
+    name = "Create guild",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn create(&self, user_id: UserId, name: GuildName, tag: GuildTag) -> Result<Guild, GuildError> {
+        let founder = self.profile(user_id).await?;
+        let guild = Guild {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            tag: tag.to_string(),
+            motd: String::new(),
+            created_at: Utc::now(),
+        };
+
+        let mut tx = self.begin().await?;
+        store_guild(&mut tx, &guild)
+            .await
+            .map_err(|e| name_or_tag_taken_or_unexpected(e, &guild))?;
+        store_guild_ranks(&mut tx, guild.id, &GuildRank::defaults()).await?;
+        store_guild_member(&mut tx, guild.id, *user_id, LEADER_RANK)
+            .await
+            .map_err(|e| already_in_guild_or_unexpected(e, GuildError::AlreadyInGuild))?;
+        clear_pending_guild_requests(&mut tx, *user_id).await?;
+        audit(&mut tx, user_id, AuditAction::GuildCreated, guild.id, format!(
+            "{} founded {} [{}]", founder.display_name, guild.name, guild.tag
+        )).await?;
+        commit(tx).await?;
+
+        self.chat.set_guild(user_id, Some(guild.id));
+        Ok(guild)
+    }
+
+    #[tracing::instrument(
+    name = "Invite to guild",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn invite(&self, user_id: UserId, display_name: &str) -> Result<String, GuildError> {
+        let inviter = self.profile(user_id).await?;
+        let invitee = get_profile_by_display_name(&self.pool, display_name.trim())
+            .await?
+            .ok_or_else(|| GuildError::PlayerNotFound(display_name.trim().to_string()))?;
+        let invitee_name = invitee.display_name.to_string();
+        if is_blocked(&self.pool, invitee.user_id, *user_id).await? {
+            return Err(GuildError::InviteRefused(invitee_name));
+        }
+
+        let (mut tx, membership) = self.begin_as_member(user_id).await?;
+        require(&membership, GuildPermission::Invite)?;
+        if get_membership(&mut tx, invitee.user_id).await?.is_some() {
+            return Err(GuildError::PlayerAlreadyInGuild(invitee_name));
+        }
+        store_guild_invitation(&mut tx, membership.guild_id, invitee.user_id, *user_id).await?;
+        audit(&mut tx, user_id, AuditAction::GuildMemberInvited, membership.guild_id, format!(
+            "{} invited {}", inviter.display_name, invitee_name
+        )).await?;
+        commit(tx).await?;
+
+        self.notify(invitee.user_id, format!("{} invited you to their guild", inviter.display_name));
+        Ok(invitee_name)
+    }
+
+    #[tracing::instrument(
+    name = "Accept guild invitation",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn accept_invitation(&self, user_id: UserId, guild_id: Uuid) -> Result<(), GuildError> {
+        let profile = self.profile(user_id).await?;
+        let mut tx = self.begin().await?;
+        if !lock_guild(&mut tx, guild_id).await? {
+            return Err(GuildError::GuildNotFound);
+        }
+        if !remove_guild_invitation(&mut tx, guild_id, *user_id).await? {
+            return Err(GuildError::NoInvitation);
+        }
+        self.join(tx, guild_id, &profile, GuildError::AlreadyInGuild).await
+    }
+
+    pub async fn decline_invitation(&self, user_id: UserId, guild_id: Uuid) -> Result<(), GuildError> {
+        let mut tx = self.begin().await?;
+        if !remove_guild_invitation(&mut tx, guild_id, *user_id).await? {
+            return Err(GuildError::NoInvitation);
+        }
+        commit(tx).await
+    }
+
+    /// Applying again replaces the earlier message
+    #[tracing::instrument(
+    name = "Apply to guild",
+    skip(self, message),
+    fields(user_id = % user_id)
+    )]
+    pub async fn apply(&self, user_id: UserId, guild_id: Uuid, message: &str) -> Result<(), GuildError> {
+        let message = message.trim();
+        if message.chars().count() > MAX_APPLICATION_LENGTH {
+            return Err(GuildError::ValidationError(format!(
+                "Applications can be at most {} characters long", MAX_APPLICATION_LENGTH
+            )));
+        }
+        self.profile(user_id).await?;
+
+        let mut tx = self.begin().await?;
+        if !lock_guild(&mut tx, guild_id).await? {
+            return Err(GuildError::GuildNotFound);
+        }
+        if get_membership(&mut tx, *user_id).await?.is_some() {
+            return Err(GuildError::AlreadyInGuild);
+        }
+        store_guild_application(&mut tx, guild_id, *user_id, message).await?;
+        commit(tx).await
+    }
+
+    #[tracing::instrument(
+    name = "Accept guild application",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn accept_application(&self, user_id: UserId, applicant_id: Uuid) -> Result<String, GuildError> {
+        let applicant = get_profile_by_user_id(&self.pool, applicant_id)
+            .await?
+            .ok_or(GuildError::NoApplication)?;
+        let applicant_name = applicant.display_name.to_string();
+
+        let (mut tx, membership) = self.begin_as_member(user_id).await?;
+        require(&membership, GuildPermission::Invite)?;
+        if !remove_guild_application(&mut tx, membership.guild_id, applicant_id).await? {
+            return Err(GuildError::NoApplication);
+        }
+        let already_in_guild = GuildError::PlayerAlreadyInGuild(applicant_name.clone());
+        self.join(tx, membership.guild_id, &applicant, already_in_guild).await?;
+        Ok(applicant_name)
+    }
+
+    pub async fn reject_application(&self, user_id: UserId, applicant_id: Uuid) -> Result<(), GuildError> {
+        let (mut tx, membership) = self.begin_as_member(user_id).await?;
+        require(&membership, GuildPermission::Invite)?;
+        if !remove_guild_application(&mut tx, membership.guild_id, applicant_id).await? {
+            return Err(GuildError::NoApplication);
+        }
+        commit(tx).await
+    }
+
+    /// New members start at the bottom rank, the guild must already be locked
+    async fn join(
+        &self,
+        mut tx: Transaction<'_, Postgres>,
+        guild_id: Uuid,
+        profile: &Profile,
+        already_in_guild: GuildError,
+    ) -> Result<(), GuildError> {
+        let members = get_guild_members(&mut tx, guild_id).await?;
+        if members.len() >= self.settings.max_members {
+            return Err(GuildError::GuildFull(self.settings.max_members));
+        }
+        let lowest_rank = get_guild_ranks(&mut tx, guild_id)
+            .await?
+            .last()
+            .map(|r| r.rank)
+            .unwrap_or(LEADER_RANK);
+
+        store_guild_member(&mut tx, guild_id, profile.user_id, lowest_rank)
+            .await
+            .map_err(|e| already_in_guild_or_unexpected(e, already_in_guild))?;
+        clear_pending_guild_requests(&mut tx, profile.user_id).await?;
+        audit(&mut tx, UserId::from(profile.user_id), AuditAction::GuildMemberJoined, guild_id, format!(
+            "{} joined", profile.display_name
+        )).await?;
+        commit(tx).await?;
+
+        self.chat.set_guild(UserId::from(profile.user_id), Some(guild_id));
+        self.notify_guild(guild_id, format!("{} joined the guild", profile.display_name)).await
+    }
+    //endregion
+
+    //region Leaving
+    /// The guild master can only leave last, which disbands the guild
+    #[tracing::instrument(
+    name = "Leave guild",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn leave(&self, user_id: UserId) -> Result<(), GuildError> {
+        let profile = self.profile(user_id).await?;
+        let (mut tx, membership) = self.begin_as_member(user_id).await?;
+        let guild_id = membership.guild_id;
+        let others = get_guild_members(&mut tx, guild_id)
+            .await?
+            .into_iter()
+            .filter(|m| m.user_id != *user_id)
+            .count();
+
+        let disbanded = if others == 0 {
+            delete_guild(&mut tx, guild_id).await?;
+            audit(&mut tx, user_id, AuditAction::GuildDisbanded, guild_id, format!(
+                "{} left as the last member", profile.display_name
+            )).await?;
+            true
+        } else if membership.rank.is_leader() {
+            return Err(GuildError::LeaderCannotLeave);
+        } else {
+            remove_guild_member(&mut tx, guild_id, *user_id).await?;
+            audit(&mut tx, user_id, AuditAction::GuildMemberLeft, guild_id, format!(
+                "{} left", profile.display_name
+            )).await?;
+            false
+        };
+        commit(tx).await?;
+
+        self.chat.set_guild(user_id, None);
+        if !disbanded {
+            self.notify_guild(guild_id, format!("{} left the guild", profile.display_name)).await?;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(
+    name = "Kick guild member",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn kick(&self, user_id: UserId, member_id: Uuid) -> Result<String, GuildError> {
+        let kicker = self.profile(user_id).await?;
+        let (mut tx, membership) = self.begin_as_member(user_id).await?;
+        require(&membership, GuildPermission::Kick)?;
+
+        let member = get_guild_members(&mut tx, membership.guild_id)
+            .await?
+            .into_iter()
+            .find(|m| m.user_id == member_id)
+            .ok_or(GuildError::NotAMember)?;
+        if !membership.rank.outranks(member.rank) {
+            return Err(GuildError::NotAllowed);
+        }
+        let member_name = member.display_name.unwrap_or_else(|| "someone".to_string());
+
+        remove_guild_member(&mut tx, membership.guild_id, member_id).await?;
+        audit(&mut tx, user_id, AuditAction::GuildMemberKicked, membership.guild_id, format!(
+            "{} kicked {}", kicker.display_name, member_name
+        )).await?;
+        commit(tx).await?;
+
+        self.chat.set_guild(UserId::from(member_id), None);
+        self.notify(member_id, format!("{} removed you from the guild", kicker.display_name));
+        self.notify_guild(membership.guild_id, format!("{} was removed from the guild", member_name)).await?;
+        Ok(member_name)
+    }
+    //endregion
+
+    //region Ranks
+    /// Only the guild master hands out ranks, making someone else master steps the old one down to rank 1
+    #[tracing::instrument(
+    name = "Change guild rank",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn set_rank(&self, user_id: UserId, member_id: Uuid, rank: i32) -> Result<(), GuildError> {
+        let leader = self.profile(user_id).await?;
+        let (mut tx, membership) = self.begin_as_member(user_id).await?;
+        if !membership.rank.is_leader() {
+            return Err(GuildError::NotAllowed);
+        }
+        let ranks = get_guild_ranks(&mut tx, membership.guild_id).await?;
+        let new_rank = ranks.iter()
+            .find(|r| r.rank == rank)
+            .ok_or(GuildError::UnknownRank(rank))?;
+        let member = get_guild_members(&mut tx, membership.guild_id)
+            .await?
+            .into_iter()
+            .find(|m| m.user_id == member_id)
+            .ok_or(GuildError::NotAMember)?;
+        if member.user_id == *user_id {
+            return Err(GuildError::ValidationError("Make someone else guild master instead".to_string()));
+        }
+        let member_name = member.display_name.unwrap_or_else(|| "someone".to_string());
+
+        if new_rank.is_leader() {
+            let step_down = ranks.iter()
+                .map(|r| r.rank)
+                .find(|r| *r > LEADER_RANK)
+                .ok_or(GuildError::UnknownRank(LEADER_RANK + 1))?;
+            update_guild_member_rank(&mut tx, membership.guild_id, *user_id, step_down).await?;
+        }
+        update_guild_member_rank(&mut tx, membership.guild_id, member_id, new_rank.rank).await?;
+        audit(&mut tx, user_id, AuditAction::GuildRankChanged, membership.guild_id, format!(
+            "{} made {} {}", leader.display_name, member_name, new_rank.name
+        )).await?;
+        commit(tx).await?;
+
+        self.notify(member_id, format!("You are now {} of your guild", new_rank.name));
+        Ok(())
+    }
+
+    /// The guild master's own rank always has every permission and can't be edited
+    #[tracing::instrument(
+    name = "Change guild rank permissions",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn set_permissions(
+        &self,
+        user_id: UserId,
+        rank: i32,
+        permissions: Vec<GuildPermission>,
+    ) -> Result<(), GuildError> {
+        let leader = self.profile(user_id).await?;
+        let (mut tx, membership) = self.begin_as_member(user_id).await?;
+        if !membership.rank.is_leader() || rank == LEADER_RANK {
+            return Err(GuildError::NotAllowed);
+        }
+        let edited = get_guild_ranks(&mut tx, membership.guild_id)
+            .await?
+            .into_iter()
+            .find(|r| r.rank == rank)
+            .ok_or(GuildError::UnknownRank(rank))?;
+
+        update_guild_rank_permissions(&mut tx, membership.guild_id, rank, &permissions).await?;
+        let names: Vec<&str> = permissions.iter().map(|p| p.as_str()).collect();
+        audit(&mut tx, user_id, AuditAction::GuildPermissionsChanged, membership.guild_id, format!(
+            "{} set {} permissions to [{}]", leader.display_name, edited.name, names.join(", ")
+        )).await?;
+        commit(tx).await
+    }
+
+    #[tracing::instrument(
+    name = "Change guild motd",
+    skip(self, motd),
+    fields(user_id = % user_id)
+    )]
+    pub async fn set_motd(&self, user_id: UserId, motd: &str) -> Result<(), GuildError> {
+        let motd = motd.trim();
+        if motd.chars().count() > MAX_MOTD_LENGTH {
+            return Err(GuildError::ValidationError(format!(
+                "The message of the day can be at most {} characters long", MAX_MOTD_LENGTH
+            )));
+        }
+        let editor = self.profile(user_id).await?;
+        let (mut tx, membership) = self.begin_as_member(user_id).await?;
+        require(&membership, GuildPermission::EditMotd)?;
+
+        update_guild_motd(&mut tx, membership.guild_id, motd).await?;
+        audit(&mut tx, user_id, AuditAction::GuildMotdChanged, membership.guild_id, format!(
+            "{} changed the message of the day to \"{}\"", editor.display_name, motd
+        )).await?;
+        commit(tx).await?;
+
+        self.notify_guild(membership.guild_id, format!("Message of the day: {}", motd)).await
+    }
+    //endregion
+
+    //region Bank
+    /// Any member can put items in, the character has to belong to the member
+    #[tracing::instrument(
+    name = "Deposit in guild bank",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn deposit(
+        &self,
+        user_id: UserId,
+        character_id: Uuid,
+        slot: usize,
+        quantity: i32,
+    ) -> Result<ItemStack, GuildError> {
+        let profile = self.profile(user_id).await?;
+        let (mut tx, membership) = self.begin_as_member(user_id).await?;
+        let stack = self.inventory.transfer_stack(
+            &mut tx,
+            InventoryOwner::Character(character_id),
+            slot,
+            quantity,
+            InventoryOwner::GuildBank(membership.guild_id),
+        ).await?;
+        audit(&mut tx, user_id, AuditAction::GuildBankDeposit, membership.guild_id, format!(
+            "{} deposited {} {}", profile.display_name, stack.quantity, self.item_name(&stack)
+        )).await?;
+        commit(tx).await?;
+        Ok(stack)
+    }
+
+    #[tracing::instrument(
+    name = "Withdraw from guild bank",
+    skip(self),
+    fields(user_id = % user_id)
+    )]
+    pub async fn withdraw(
+        &self,
+        user_id: UserId,
+        character_id: Uuid,
+        slot: usize,
+        quantity: i32,
+    ) -> Result<ItemStack, GuildError> {
+        let profile = self.profile(user_id).await?;
+        let (mut tx, membership) = self.begin_as_member(user_id).await?;
+        require(&membership, GuildPermission::ManageBank)?;
+        let stack = self.inventory.transfer_stack(
+            &mut tx,
+            InventoryOwner::GuildBank(membership.guild_id),
+            slot,
+            quantity,
+            InventoryOwner::Character(character_id),
+        ).await?;
+        audit(&mut tx, user_id, AuditAction::GuildBankWithdrawal, membership.guild_id, format!(
+            "{} withdrew {} {}", profile.display_name, stack.quantity, self.item_name(&stack)
+        )).await?;
+        commit(tx).await?;
+        Ok(stack)
+    }
+
+    fn item_name(&self, stack: &ItemStack) -> String {
+        self.inventory
+            .catalog()
+            .get(&stack.item_id)
+            .map(|item| item.name.clone())
+            .unwrap_or_else(|| stack.item_id.clone())
+    }
+    //endregion
+
+    async fn profile(&self, user_id: UserId) -> Result<Profile, GuildError> {
+        get_profile_by_user_id(&self.pool, *user_id)
+            .await?
+            .ok_or(GuildError::NoDisplayName)
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, GuildError> {
+        Ok(self.pool.begin().await.context("Failed to begin guild transaction")?)
+    }
+
+    /// Locks the player's guild and reads their membership again under the lock, so a kick or rank
+    /// change that just happened is seen
+    async fn begin_as_member(&self, user_id: UserId) -> Result<(Transaction<'static, Postgres>, Membership), GuildError> {
+        let guild_id = get_membership(&self.pool, *user_id)
+            .await?
+            .ok_or(GuildError::NotInGuild)?
+            .guild_id;
+
+        let mut tx = self.begin().await?;
+        lock_guild(&mut tx, guild_id).await?;
+        let membership = get_membership(&mut tx, *user_id)
+            .await?
+            .filter(|m| m.guild_id == guild_id)
+            .ok_or(GuildError::NotInGuild)?;
+        Ok((tx, membership))
+    }
+
+    fn notify(&self, user_id: Uuid, message: String) {
+        self.registry.send_to_user(UserId::from(user_id), &ServerMessage::Notice { message });
+    }
+
+    async fn notify_guild(&self, guild_id: Uuid, message: String) -> Result<(), GuildError> {
+        let notice = ServerMessage::Notice { message };
+        for member in get_guild_members(&self.pool, guild_id).await? {
+            self.registry.send_to_user(UserId::from(member.user_id), &notice);
+        }
+        Ok(())
+    }
+}
+
+fn require(membership: &Membership, permission: GuildPermission) -> Result<(), GuildError> {
+    if membership.rank.can(permission) {
+        Ok(())
+    } else {
+        Err(GuildError::NotAllowed)
+    }
+}
+
+async fn audit(
+    tx: &mut Transaction<'_, Postgres>,
+    actor: UserId,
+    action: AuditAction,
+    guild_id: Uuid,
+    details: String,
+) -> Result<(), GuildError> {
+    let entry = AuditEntry { actor_id: Some(*actor), action, subject_id: Some(guild_id), details };
+    record_audit_entry(&mut *tx, &entry).await?;
+    Ok(())
+}
+
+async fn commit(tx: Transaction<'_, Postgres>) -> Result<(), GuildError> {
+    tx.commit().await.context("Failed to commit guild transaction")?;
+    Ok(())
+}
+
+fn name_or_tag_taken_or_unexpected(e: sqlx::Error, guild: &Guild) -> GuildError {
+    if !is_unique_violation(&e) {
+        return GuildError::UnexpectedError(anyhow::Error::from(e).context("Failed to store guild"));
+    }
+    let is_tag = e.as_database_error()
+        .and_then(|db| db.constraint())
+        .is_some_and(|constraint| constraint == TAG_CONSTRAINT);
+    if is_tag {
+        GuildError::TagTaken(guild.tag.clone())
+    } else {
+        GuildError::NameTaken(guild.name.clone())
+    }
+}
+
+/// guild_members is keyed by account, a second row for the same player is a second guild
+fn already_in_guild_or_unexpected(e: sqlx::Error, already_in_guild: GuildError) -> GuildError {
+    if is_unique_violation(&e) {
+        already_in_guild
+    } else {
+        GuildError::UnexpectedError(anyhow::Error::from(e).context("Failed to store guild member"))
+    }
+}
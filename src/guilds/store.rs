@@ -0,0 +1,536 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::guilds::{GuildPermission, GuildRank};
+
+#[derive(Debug, Clone)]
+pub struct Guild {
+    pub id: Uuid,
+    pub name: String,
+    pub tag: String,
+    pub motd: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GuildSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub tag: String,
+    pub members: i64,
+}
+
+/// Which guild an account is in and the rank it holds there
+#[derive(Debug, Clone)]
+pub struct Membership {
+    pub guild_id: Uuid,
+    pub rank: GuildRank,
+}
+
+#[derive(Debug, Clone)]
+pub struct GuildMemberRow {
+    pub user_id: Uuid,
+    pub display_name: Option<String>,
+    pub rank: i32,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GuildApplication {
+    pub user_id: Uuid,
+    pub display_name: Option<String>,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GuildInvitation {
+    pub guild_id: Uuid,
+    pub guild_name: String,
+    pub guild_tag: String,
+    pub invited_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Unknown permissions were dropped from the code, they grant nothing
+fn parse_permissions(permissions: Vec<String>) -> Vec<GuildPermission> {
+    permissions.into_iter()
+        .filter_map(|p| GuildPermission::try_from(p).ok())
+        .collect()
+}
+
+fn permission_names(permissions: &[GuildPermission]) -> Vec<String> {
+    permissions.iter().map(|p| p.as_str().to_string()).collect()
+}
+
+#[tracing::instrument(
+name = "Store guild",
+skip(tx, guild),
+fields(guild_name = % guild.name)
+)]
+pub async fn store_guild(
+    tx: &mut Transaction<'_, Postgres>,
+    guild: &Guild,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO guilds (id, name, tag, motd, created_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        guild.id,
+        guild.name,
+        guild.tag,
+        guild.motd,
+        guild.created_at
+    )
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Store guild ranks",
+skip(tx, ranks)
+)]
+pub async fn store_guild_ranks(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+    ranks: &[GuildRank],
+) -> Result<(), anyhow::Error> {
+    for rank in ranks {
+        sqlx::query!(
+            r#"
+            INSERT INTO guild_ranks (guild_id, rank, name, permissions)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            guild_id,
+            rank.rank,
+            rank.name,
+            &permission_names(&rank.permissions)
+        )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to store guild rank")?;
+    }
+    Ok(())
+}
+
+/// Every change to a guild's members, ranks or bank locks the guild first
+#[tracing::instrument(
+name = "Lock guild",
+skip(tx)
+)]
+pub async fn lock_guild(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let locked = sqlx::query!(
+        r#"SELECT id FROM guilds WHERE id = $1 FOR UPDATE"#,
+        guild_id
+    )
+        .fetch_optional(tx)
+        .await
+        .context("Failed to lock guild")?;
+    Ok(locked.is_some())
+}
+
+#[tracing::instrument(
+name = "Get guild",
+skip(executor)
+)]
+pub async fn get_guild(
+    executor: impl PgExecutor<'_>,
+    guild_id: Uuid,
+) -> Result<Option<Guild>, anyhow::Error> {
+    sqlx::query_as!(
+        Guild,
+        r#"SELECT id, name, tag, motd, created_at FROM guilds WHERE id = $1"#,
+        guild_id
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch guild")
+}
+
+#[tracing::instrument(
+name = "Get guilds",
+skip(executor)
+)]
+pub async fn get_guilds(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<GuildSummary>, anyhow::Error> {
+    sqlx::query_as!(
+        GuildSummary,
+        r#"
+        SELECT g.id, g.name, g.tag, count(m.user_id) as "members!"
+        FROM guilds g
+        LEFT JOIN guild_members m ON m.guild_id = g.id
+        GROUP BY g.id
+        ORDER BY lower(g.name)
+        "#
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch guilds")
+}
+
+#[tracing::instrument(
+name = "Get guild membership",
+skip(executor)
+)]
+pub async fn get_membership(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<Option<Membership>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT m.guild_id, m.rank, r.name, r.permissions
+        FROM guild_members m
+        JOIN guild_ranks r ON r.guild_id = m.guild_id AND r.rank = m.rank
+        WHERE m.user_id = $1
+        "#,
+        user_id
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch guild membership")?;
+
+    Ok(row.map(|r| Membership {
+        guild_id: r.guild_id,
+        rank: GuildRank { rank: r.rank, name: r.name, permissions: parse_permissions(r.permissions) },
+    }))
+}
+
+#[tracing::instrument(
+name = "Get guild members",
+skip(executor)
+)]
+pub async fn get_guild_members(
+    executor: impl PgExecutor<'_>,
+    guild_id: Uuid,
+) -> Result<Vec<GuildMemberRow>, anyhow::Error> {
+    sqlx::query_as!(
+        GuildMemberRow,
+        r#"
+        SELECT m.user_id, p.display_name as "display_name?", m.rank, m.joined_at
+        FROM guild_members m
+        LEFT JOIN profiles p ON p.user_id = m.user_id
+        WHERE m.guild_id = $1
+        ORDER BY m.rank, lower(p.display_name)
+        "#,
+        guild_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch guild members")
+}
+
+#[tracing::instrument(
+name = "Get guild ranks",
+skip(executor)
+)]
+pub async fn get_guild_ranks(
+    executor: impl PgExecutor<'_>,
+    guild_id: Uuid,
+) -> Result<Vec<GuildRank>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"SELECT rank, name, permissions FROM guild_ranks WHERE guild_id = $1 ORDER BY rank"#,
+        guild_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch guild ranks")?;
+
+    Ok(rows.into_iter()
+        .map(|r| GuildRank { rank: r.rank, name: r.name, permissions: parse_permissions(r.permissions) })
+        .collect())
+}
+
+#[tracing::instrument(
+name = "Store guild member",
+skip(tx)
+)]
+pub async fn store_guild_member(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+    user_id: Uuid,
+    rank: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"INSERT INTO guild_members (user_id, guild_id, rank) VALUES ($1, $2, $3)"#,
+        user_id,
+        guild_id,
+        rank
+    )
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Update guild member rank",
+skip(tx)
+)]
+pub async fn update_guild_member_rank(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+    user_id: Uuid,
+    rank: i32,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"UPDATE guild_members SET rank = $3 WHERE guild_id = $1 AND user_id = $2"#,
+        guild_id,
+        user_id,
+        rank
+    )
+        .execute(tx)
+        .await
+        .context("Failed to update guild member rank")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Remove guild member",
+skip(tx)
+)]
+pub async fn remove_guild_member(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"DELETE FROM guild_members WHERE guild_id = $1 AND user_id = $2"#,
+        guild_id,
+        user_id
+    )
+        .execute(tx)
+        .await
+        .context("Failed to remove guild member")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Update guild rank permissions",
+skip(tx, permissions)
+)]
+pub async fn update_guild_rank_permissions(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+    rank: i32,
+    permissions: &[GuildPermission],
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"UPDATE guild_ranks SET permissions = $3 WHERE guild_id = $1 AND rank = $2"#,
+        guild_id,
+        rank,
+        &permission_names(permissions)
+    )
+        .execute(tx)
+        .await
+        .context("Failed to update guild rank permissions")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Update guild motd",
+skip(tx, motd)
+)]
+pub async fn update_guild_motd(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+    motd: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"UPDATE guilds SET motd = $2 WHERE id = $1"#,
+        guild_id,
+        motd
+    )
+        .execute(tx)
+        .await
+        .context("Failed to update guild motd")?;
+    Ok(())
+}
+
+/// Ranks, invitations, applications and the bank go with it
+#[tracing::instrument(
+name = "Delete guild",
+skip(tx)
+)]
+pub async fn delete_guild(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(r#"DELETE FROM guilds WHERE id = $1"#, guild_id)
+        .execute(tx)
+        .await
+        .context("Failed to delete guild")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Store guild invitation",
+skip(tx)
+)]
+pub async fn store_guild_invitation(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+    user_id: Uuid,
+    invited_by: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO guild_invitations (guild_id, user_id, invited_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id, user_id) DO UPDATE
+        SET invited_by = EXCLUDED.invited_by, created_at = now()
+        "#,
+        guild_id,
+        user_id,
+        invited_by
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store guild invitation")?;
+    Ok(())
+}
+
+/// `false` when there was no invitation to remove
+#[tracing::instrument(
+name = "Remove guild invitation",
+skip(tx)
+)]
+pub async fn remove_guild_invitation(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let removed = sqlx::query!(
+        r#"DELETE FROM guild_invitations WHERE guild_id = $1 AND user_id = $2"#,
+        guild_id,
+        user_id
+    )
+        .execute(tx)
+        .await
+        .context("Failed to remove guild invitation")?;
+    Ok(removed.rows_affected() > 0)
+}
+
+#[tracing::instrument(
+name = "Get guild invitations for player",
+skip(executor)
+)]
+pub async fn get_guild_invitations(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<Vec<GuildInvitation>, anyhow::Error> {
+    sqlx::query_as!(
+        GuildInvitation,
+        r#"
+        SELECT i.guild_id, g.name as guild_name, g.tag as guild_tag,
+               p.display_name as "invited_by?", i.created_at
+        FROM guild_invitations i
+        JOIN guilds g ON g.id = i.guild_id
+        LEFT JOIN profiles p ON p.user_id = i.invited_by
+        WHERE i.user_id = $1
+        ORDER BY i.created_at DESC
+        "#,
+        user_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch guild invitations")
+}
+
+#[tracing::instrument(
+name = "Store guild application",
+skip(tx, message)
+)]
+pub async fn store_guild_application(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+    user_id: Uuid,
+    message: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO guild_applications (guild_id, user_id, message)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id, user_id) DO UPDATE
+        SET message = EXCLUDED.message, created_at = now()
+        "#,
+        guild_id,
+        user_id,
+        message
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store guild application")?;
+    Ok(())
+}
+
+/// `false` when there was no application to remove
+#[tracing::instrument(
+name = "Remove guild application",
+skip(tx)
+)]
+pub async fn remove_guild_application(
+    tx: &mut Transaction<'_, Postgres>,
+    guild_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let removed = sqlx::query!(
+        r#"DELETE FROM guild_applications WHERE guild_id = $1 AND user_id = $2"#,
+        guild_id,
+        user_id
+    )
+        .execute(tx)
+        .await
+        .context("Failed to remove guild application")?;
+    Ok(removed.rows_affected() > 0)
+}
+
+#[tracing::instrument(
+name = "Get guild applications",
+skip(executor)
+)]
+pub async fn get_guild_applications(
+    executor: impl PgExecutor<'_>,
+    guild_id: Uuid,
+) -> Result<Vec<GuildApplication>, anyhow::Error> {
+    sqlx::query_as!(
+        GuildApplication,
+        r#"
+        SELECT a.user_id, p.display_name as "display_name?", a.message, a.created_at
+        FROM guild_applications a
+        LEFT JOIN profiles p ON p.user_id = a.user_id
+        WHERE a.guild_id = $1
+        ORDER BY a.created_at
+        "#,
+        guild_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch guild applications")
+}
+
+/// Once a player joins a guild their other invitations and applications are moot
+#[tracing::instrument(
+name = "Clear pending guild requests",
+skip(tx)
+)]
+pub async fn clear_pending_guild_requests(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(r#"DELETE FROM guild_invitations WHERE user_id = $1"#, user_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to clear guild invitations")?;
+    sqlx::query!(r#"DELETE FROM guild_applications WHERE user_id = $1"#, user_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to clear guild applications")?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::game_data::{data_directory, load_data_files, GameDataError};
+use crate::items::ItemDefinition;
+
+#[derive(serde::Deserialize)]
+struct ItemFile {
+    #[serde(default)]
+    items: Vec<ItemDefinition>,
+}
+
+/// Every item the game knows about, stored rows only keep the item id
+#[derive(Debug, Default)]
+pub struct ItemCatalog {
+    items: HashMap<String, ItemDefinition>,
+}
+
+impl ItemCatalog {
+    pub fn new(definitions: Vec<ItemDefinition>) -> Result<Self, GameDataError> {
+        let mut items = HashMap::new();
+        for definition in definitions {
+            definition.validate().map_err(GameDataError::Invalid)?;
+            if items.contains_key(&definition.id) {
+                return Err(GameDataError::Invalid(format!("Item {} is defined twice", definition.id)));
+            }
+            items.insert(definition.id.clone(), definition);
+        }
+        Ok(ItemCatalog { items })
+    }
+
+    pub fn load(directory: &Path) -> Result<Self, GameDataError> {
+        let mut definitions = Vec::new();
+        for (path, file) in load_data_files::<ItemFile>(directory)? {
+            for definition in file.items {
+                definition.validate()
+                    .map_err(|e| GameDataError::Invalid(format!("{}: {}", path.display(), e)))?;
+                definitions.push(definition);
+            }
+        }
+        Self::new(definitions)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ItemDefinition> {
+        self.items.get(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.items.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&ItemDefinition> {
+        self.items.values()
+    }
+}
+
+pub fn get_item_catalog() -> Result<ItemCatalog, GameDataError> {
+    ItemCatalog::load(&data_directory().join("items"))
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use uuid::Uuid;
+    use crate::items::{get_item_catalog, ItemCatalog, ItemType};
+
+    fn write_files(files: &[(&str, &str)]) -> std::path::PathBuf {
+        let directory = std::env::temp_dir().join(format!("yaug-items-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        for (name, content) in files {
+            std::fs::write(directory.join(name), content).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn shipped_catalog_is_valid() {
+        let catalog = assert_ok!(get_item_catalog());
+        assert!(!catalog.is_empty());
+    }
+
+    #[test]
+    fn toml_and_json_files_are_merged() {
+        let directory = write_files(&[
+            ("weapons.toml", "[[items]]\nid = \"stick\"\nname = \"Stick\"\ntype = \"weapon\"\n[items.attributes]\nstrength = 1\n"),
+            ("materials.json", r#"{"items": [{"id": "pebble", "name": "Pebble", "type": "material", "stack_size": 50}]}"#),
+            ("notes.txt", "not an item file"),
+        ]);
+
+        let catalog = ItemCatalog::load(&directory).unwrap();
+
+        assert_eq!(2, catalog.len());
+        assert_eq!(ItemType::Weapon, catalog.get("stick").unwrap().item_type);
+        assert_eq!(Some(&1), catalog.get("stick").unwrap().attributes.get("strength"));
+        assert_eq!(50, catalog.get("pebble").unwrap().stack_size);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn duplicate_ids_across_files_are_rejected() {
+        let directory = write_files(&[
+            ("a.toml", "[[items]]\nid = \"stick\"\nname = \"Stick\"\ntype = \"weapon\"\n"),
+            ("b.toml", "[[items]]\nid = \"stick\"\nname = \"Other stick\"\ntype = \"material\"\n"),
+        ]);
+
+        assert_err!(ItemCatalog::load(&directory));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn unknown_item_type_is_rejected() {
+        let directory = write_files(&[
+            ("a.toml", "[[items]]\nid = \"stick\"\nname = \"Stick\"\ntype = \"furniture\"\n"),
+        ]);
+
+        assert_err!(ItemCatalog::load(&directory));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use crate::game_data::is_valid_id;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemType {
+    Weapon,
+    Armor,
+    Accessory,
+    Consumable,
+    Material,
+    Quest,
+}
+
+impl ItemType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemType::Weapon => "weapon",
+            ItemType::Armor => "armor",
+            ItemType::Accessory => "accessory",
+            ItemType::Consumable => "consumable",
+            ItemType::Material => "material",
+            ItemType::Quest => "quest",
+        }
+    }
+
+    pub fn is_equippable(&self) -> bool {
+        matches!(self, ItemType::Weapon | ItemType::Armor | ItemType::Accessory)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl Rarity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rarity::Common => "common",
+            Rarity::Uncommon => "uncommon",
+            Rarity::Rare => "rare",
+            Rarity::Epic => "epic",
+            Rarity::Legendary => "legendary",
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ItemDefinition {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "type")]
+    pub item_type: ItemType,
+    #[serde(default)]
+    pub rarity: Rarity,
+    #[serde(default = "default_stack_size")]
+    pub stack_size: i32,
+    /// Free form bonuses like `strength = 2` or `armor = 5`
+    #[serde(default)]
+    pub attributes: BTreeMap<String, i32>,
+}
+
+fn default_stack_size() -> i32 {
+    1
+}
+
+impl ItemDefinition {
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_id(&self.id) {
+            return Err(format!("{:?} is not a valid item id, use lowercase letters, digits and _", self.id));
+        }
+        if self.name.trim().is_empty() {
+            return Err(format!("Item {} has no name", self.id));
+        }
+        if self.stack_size < 1 {
+            return Err(format!("Item {} needs a stack size of at least 1", self.id));
+        }
+        if self.item_type.is_equippable() && self.stack_size != 1 {
+            return Err(format!("Item {} is a {} and can't stack", self.id, self.item_type.as_str()));
+        }
+        if let Some(key) = self.attributes.keys().find(|k| !is_valid_id(k)) {
+            return Err(format!("Item {} has an invalid attribute name {:?}", self.id, key));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use claim::{assert_err, assert_ok};
+    use crate::items::{ItemDefinition, ItemType, Rarity};
+
+    fn item(item_type: ItemType, stack_size: i32) -> ItemDefinition {
+        ItemDefinition {
+            id: "test_item".to_string(),
+            name: "Test item".to_string(),
+            description: String::new(),
+            item_type,
+            rarity: Rarity::Common,
+            stack_size,
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn stackable_material_is_valid() {
+        assert_ok!(item(ItemType::Material, 99).validate());
+    }
+
+    #[test]
+    fn equipment_cannot_stack() {
+        assert_err!(item(ItemType::Weapon, 5).validate());
+    }
+
+    #[test]
+    fn stack_size_must_be_positive() {
+        assert_err!(item(ItemType::Consumable, 0).validate());
+    }
+
+    #[test]
+    fn ids_must_be_lowercase_identifiers() {
+        let mut definition = item(ItemType::Material, 1);
+        definition.id = "Iron Ore".to_string();
+        assert_err!(definition.validate());
+    }
+
+    #[test]
+    fn rarity_is_ordered() {
+        assert!(Rarity::Common < Rarity::Rare);
+        assert!(Rarity::Epic < Rarity::Legendary);
+    }
+}
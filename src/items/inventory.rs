@@ -0,0 +1,358 @@
+use crate::items::{InventoryError, ItemCatalog, ItemDefinition};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemStack {
+    pub item_id: String,
+    pub quantity: i32,
+}
+
+/// Fixed number of slots, each holding at most one stack. Operations either apply completely or
+/// leave the inventory untouched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+}
+
+impl Inventory {
+    pub fn new(size: usize) -> Self {
+        Inventory { slots: vec![None; size] }
+    }
+
+    pub fn from_stacks(size: usize, stacks: Vec<(usize, ItemStack)>) -> Result<Self, InventoryError> {
+        let mut inventory = Inventory::new(size);
+        for (slot, stack) in stacks {
+            *inventory.slot_mut(slot)? = Some(stack);
+        }
+        Ok(inventory)
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    pub fn count(&self, item_id: &str) -> i32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|s| s.item_id == item_id)
+            .map(|s| s.quantity)
+            .sum()
+    }
+
+    /// Tops up existing stacks first, then fills empty slots from the front
+    pub fn add(&mut self, item: &ItemDefinition, quantity: i32) -> Result<(), InventoryError> {
+        check_quantity(quantity)?;
+
+        let room: i64 = self.slots
+            .iter()
+            .map(|slot| match slot {
+                Some(stack) if stack.item_id == item.id => (item.stack_size - stack.quantity).max(0) as i64,
+                Some(_) => 0,
+                None => item.stack_size as i64,
+            })
+            .sum();
+        if room < quantity as i64 {
+            return Err(InventoryError::InventoryFull);
+        }
+
+        let mut remaining = quantity;
+        for stack in self.slots.iter_mut().flatten().filter(|s| s.item_id == item.id) {
+            let added = remaining.min((item.stack_size - stack.quantity).max(0));
+            stack.quantity += added;
+            remaining -= added;
+        }
+        for slot in self.slots.iter_mut().filter(|s| s.is_none()) {
+            if remaining == 0 {
+                break;
+            }
+            let added = remaining.min(item.stack_size);
+            *slot = Some(ItemStack { item_id: item.id.clone(), quantity: added });
+            remaining -= added;
+        }
+        Ok(())
+    }
+
+    /// Takes from the last stacks first so the front of the inventory stays put
+    pub fn remove(&mut self, item_id: &str, quantity: i32) -> Result<(), InventoryError> {
+        check_quantity(quantity)?;
+
+        let available = self.count(item_id);
+        if available < quantity {
+            return Err(InventoryError::NotEnoughItems {
+                item_id: item_id.to_string(),
+                wanted: quantity,
+                available,
+            });
+        }
+
+        let mut remaining = quantity;
+        for slot in self.slots.iter_mut().rev() {
+            if remaining == 0 {
+                break;
+            }
+            if let Some(stack) = slot.as_mut().filter(|s| s.item_id == item_id) {
+                let taken = remaining.min(stack.quantity);
+                stack.quantity -= taken;
+                remaining -= taken;
+                if stack.quantity == 0 {
+                    *slot = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges into a stack of the same item as far as it fits, otherwise swaps the two slots
+    pub fn move_stack(&mut self, catalog: &ItemCatalog, from: usize, to: usize) -> Result<(), InventoryError> {
+        self.slot_mut(to)?;
+        let moving = self.slot_mut(from)?.take().ok_or(InventoryError::EmptySlot(from))?;
+        if from == to {
+            self.slots[from] = Some(moving);
+            return Ok(());
+        }
+
+        match self.slots[to].as_mut() {
+            Some(target) if target.item_id == moving.item_id => {
+                let stack_size = catalog.get(&moving.item_id)
+                    .map(|item| item.stack_size)
+                    .unwrap_or(1);
+                let moved = moving.quantity.min((stack_size - target.quantity).max(0));
+                target.quantity += moved;
+                let left = moving.quantity - moved;
+                if left > 0 {
+                    self.slots[from] = Some(ItemStack { item_id: moving.item_id, quantity: left });
+                }
+            }
+            _ => {
+                self.slots[from] = self.slots[to].take();
+                self.slots[to] = Some(moving);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn split_stack(&mut self, from: usize, to: usize, quantity: i32) -> Result<(), InventoryError> {
+        check_quantity(quantity)?;
+        if self.slot_mut(to)?.is_some() {
+            return Err(InventoryError::SlotOccupied(to));
+        }
+
+        let source = self.slot_mut(from)?.as_mut().ok_or(InventoryError::EmptySlot(from))?;
+        if source.quantity <= quantity {
+            return Err(InventoryError::NotEnoughItems {
+                item_id: source.item_id.clone(),
+                wanted: quantity + 1,
+                available: source.quantity,
+            });
+        }
+        source.quantity -= quantity;
+        let item_id = source.item_id.clone();
+        self.slots[to] = Some(ItemStack { item_id, quantity });
+        Ok(())
+    }
+
+    /// Takes `quantity` out of one slot, the whole stack at most, for handing it to another inventory
+    pub fn take(&mut self, slot: usize, quantity: i32) -> Result<ItemStack, InventoryError> {
+        check_quantity(quantity)?;
+        let source = self.slot_mut(slot)?.as_mut().ok_or(InventoryError::EmptySlot(slot))?;
+        if source.quantity < quantity {
+            return Err(InventoryError::NotEnoughItems {
+                item_id: source.item_id.clone(),
+                wanted: quantity,
+                available: source.quantity,
+            });
+        }
+        source.quantity -= quantity;
+        let taken = ItemStack { item_id: source.item_id.clone(), quantity };
+        if source.quantity == 0 {
+            self.slots[slot] = None;
+        }
+        Ok(taken)
+    }
+
+    /// Slots whose content differs from `other`, these are the ones that have to be written back
+    pub fn changed_slots(&self, other: &Inventory) -> Vec<usize> {
+        (0..self.slots.len().max(other.slots.len()))
+            .filter(|i| self.slots.get(*i) != other.slots.get(*i))
+            .collect()
+    }
+
+    fn slot_mut(&mut self, slot: usize) -> Result<&mut Option<ItemStack>, InventoryError> {
+        self.slots.get_mut(slot).ok_or(InventoryError::InvalidSlot(slot))
+    }
+}
+
+fn check_quantity(quantity: i32) -> Result<(), InventoryError> {
+    if quantity < 1 {
+        return Err(InventoryError::InvalidQuantity(quantity));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use claim::{assert_err, assert_ok};
+    use quickcheck_macros::quickcheck;
+    use crate::items::{Inventory, ItemCatalog, ItemDefinition, ItemStack, ItemType, Rarity};
+
+    fn definition(id: &str, item_type: ItemType, stack_size: i32) -> ItemDefinition {
+        ItemDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            item_type,
+            rarity: Rarity::Common,
+            stack_size,
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    fn catalog() -> ItemCatalog {
+        ItemCatalog::new(vec![
+            definition("ore", ItemType::Material, 20),
+            definition("sword", ItemType::Weapon, 1),
+        ]).unwrap()
+    }
+
+    fn stack(item_id: &str, quantity: i32) -> Option<ItemStack> {
+        Some(ItemStack { item_id: item_id.to_string(), quantity })
+    }
+
+    #[test]
+    fn adding_tops_up_existing_stacks_before_using_new_slots() {
+        let catalog = catalog();
+        let mut inventory = Inventory::from_stacks(3, vec![
+            (1, stack("ore", 15).unwrap()),
+        ]).unwrap();
+
+        inventory.add(catalog.get("ore").unwrap(), 10).unwrap();
+
+        assert_eq!(inventory.slots(), &[stack("ore", 5), stack("ore", 20), None]);
+    }
+
+    #[test]
+    fn adding_more_than_fits_changes_nothing() {
+        let catalog = catalog();
+        let mut inventory = Inventory::new(2);
+        inventory.add(catalog.get("sword").unwrap(), 1).unwrap();
+        let before = inventory.clone();
+
+        assert_err!(inventory.add(catalog.get("ore").unwrap(), 21));
+        assert_eq!(before, inventory);
+    }
+
+    #[test]
+    fn removing_takes_from_the_back() {
+        let mut inventory = Inventory::from_stacks(3, vec![
+            (0, stack("ore", 20).unwrap()),
+            (2, stack("ore", 4).unwrap()),
+        ]).unwrap();
+
+        inventory.remove("ore", 6).unwrap();
+
+        assert_eq!(inventory.slots(), &[stack("ore", 18), None, None]);
+    }
+
+    #[test]
+    fn removing_more_than_owned_changes_nothing() {
+        let mut inventory = Inventory::from_stacks(2, vec![(0, stack("ore", 3).unwrap())]).unwrap();
+
+        assert_err!(inventory.remove("ore", 4));
+        assert_eq!(3, inventory.count("ore"));
+    }
+
+    #[test]
+    fn non_positive_quantities_are_rejected() {
+        let catalog = catalog();
+        let mut inventory = Inventory::new(2);
+        assert_err!(inventory.add(catalog.get("ore").unwrap(), 0));
+        assert_err!(inventory.remove("ore", -1));
+    }
+
+    #[test]
+    fn moving_onto_same_item_merges_up_to_stack_size() {
+        let catalog = catalog();
+        let mut inventory = Inventory::from_stacks(2, vec![
+            (0, stack("ore", 15).unwrap()),
+            (1, stack("ore", 10).unwrap()),
+        ]).unwrap();
+
+        inventory.move_stack(&catalog, 0, 1).unwrap();
+
+        assert_eq!(inventory.slots(), &[stack("ore", 5), stack("ore", 20)]);
+    }
+
+    #[test]
+    fn moving_onto_other_item_swaps() {
+        let catalog = catalog();
+        let mut inventory = Inventory::from_stacks(2, vec![
+            (0, stack("ore", 15).unwrap()),
+            (1, stack("sword", 1).unwrap()),
+        ]).unwrap();
+
+        inventory.move_stack(&catalog, 0, 1).unwrap();
+
+        assert_eq!(inventory.slots(), &[stack("sword", 1), stack("ore", 15)]);
+    }
+
+    #[test]
+    fn moving_from_an_empty_or_missing_slot_fails() {
+        let catalog = catalog();
+        let mut inventory = Inventory::new(2);
+        assert_err!(inventory.move_stack(&catalog, 0, 1));
+        assert_err!(inventory.move_stack(&catalog, 0, 5));
+    }
+
+    #[test]
+    fn splitting_needs_an_empty_target_and_leaves_something_behind() {
+        let mut inventory = Inventory::from_stacks(3, vec![
+            (0, stack("ore", 10).unwrap()),
+            (1, stack("sword", 1).unwrap()),
+        ]).unwrap();
+
+        assert_err!(inventory.split_stack(0, 1, 5));
+        assert_err!(inventory.split_stack(0, 2, 10));
+        assert_ok!(inventory.split_stack(0, 2, 4));
+        assert_eq!(inventory.slots(), &[stack("ore", 6), stack("sword", 1), stack("ore", 4)]);
+    }
+
+    #[test]
+    fn taking_empties_the_slot_only_when_all_of_it_goes() {
+        let mut inventory = Inventory::from_stacks(2, vec![(0, stack("ore", 10).unwrap())]).unwrap();
+
+        assert_eq!(stack("ore", 4), inventory.take(0, 4).ok());
+        assert_err!(inventory.take(0, 7));
+        assert_err!(inventory.take(1, 1));
+        assert_eq!(stack("ore", 6), inventory.take(0, 6).ok());
+        assert_eq!(inventory.slots(), &[None, None]);
+    }
+
+    #[test]
+    fn changed_slots_are_reported() {
+        let catalog = catalog();
+        let before = Inventory::from_stacks(3, vec![(2, stack("ore", 19).unwrap())]).unwrap();
+        let mut after = before.clone();
+        after.add(catalog.get("ore").unwrap(), 5).unwrap();
+
+        assert_eq!(vec![0, 2], after.changed_slots(&before));
+    }
+
+    #[quickcheck]
+    fn add_then_remove_keeps_counts(quantities: Vec<u8>) -> bool {
+        let catalog = catalog();
+        let ore = catalog.get("ore").unwrap();
+        let mut inventory = Inventory::new(10);
+        let mut expected = 0;
+        for quantity in quantities.into_iter().map(|q| q as i32 % 30 + 1) {
+            if inventory.add(ore, quantity).is_ok() {
+                expected += quantity;
+            }
+            if quantity % 3 == 0 && inventory.remove("ore", quantity / 3).is_ok() {
+                expected -= quantity / 3;
+            }
+        }
+        inventory.count("ore") == expected
+            && inventory.slots().iter().flatten().all(|s| s.quantity >= 1 && s.quantity <= ore.stack_size)
+    }
+}
@@ -0,0 +1,10 @@
+mod catalog;
+mod definition;
+mod inventory;
+mod service;
+mod store;
+
+pub use catalog::{get_item_catalog, ItemCatalog};
+pub use definition::{ItemDefinition, ItemType, Rarity};
+pub use inventory::{Inventory, ItemStack};
+pub use service::{GUILD_BANK_SLOTS, InventoryError, InventoryOwner, InventoryService, INVENTORY_SLOTS};
\ No newline at end of file
@@ -0,0 +1,286 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::gateway::{ClientError, ErrorCode};
+use crate::items::{Inventory, ItemCatalog, ItemStack};
+use crate::items::store::{get_inventory_stacks, lock_inventory, store_inventory_slot};
+use crate::utils::error_chain_fmt;
+
+pub const INVENTORY_SLOTS: usize = 30;
+pub const GUILD_BANK_SLOTS: usize = 50;
+
+/// Whose slots an inventory operation works on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventoryOwner {
+    Character(Uuid),
+    GuildBank(Uuid),
+}
+
+impl InventoryOwner {
+    pub fn size(&self) -> usize {
+        match self {
+            InventoryOwner::Character(_) => INVENTORY_SLOTS,
+            InventoryOwner::GuildBank(_) => GUILD_BANK_SLOTS,
+        }
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum InventoryError {
+    #[error("There is no item called {0}")]
+    UnknownItem(String),
+    #[error("There is not enough room in the inventory")]
+    InventoryFull,
+    #[error("Not enough {item_id}, wanted {wanted} but there are only {available}")]
+    NotEnoughItems { item_id: String, wanted: i32, available: i32 },
+    #[error("{0} is not a valid quantity")]
+    InvalidQuantity(i32),
+    #[error("Slot {0} does not exist")]
+    InvalidSlot(usize),
+    #[error("Slot {0} is empty")]
+    EmptySlot(usize),
+    #[error("Slot {0} is already taken")]
+    SlotOccupied(usize),
+    #[error("Character not found")]
+    CharacterNotFound,
+    #[error("Guild not found")]
+    GuildNotFound,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for InventoryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for InventoryError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            InventoryError::UnknownItem(_) | InventoryError::CharacterNotFound | InventoryError::GuildNotFound => ErrorCode::NotFound,
+            InventoryError::UnexpectedError(_) => ErrorCode::Internal,
+            _ => ErrorCode::InvalidAction,
+        }
+    }
+}
+
+/// The only way items get into or out of an inventory. Methods work inside the caller's transaction
+/// so item changes can be committed together with whatever paid for them.
+pub struct InventoryService {
+    catalog: Arc<ItemCatalog>,
+}
+
+impl InventoryService {
+    pub fn new(catalog: Arc<ItemCatalog>) -> Self {
+        InventoryService { catalog }
+    }
+
+    pub fn catalog(&self) -> &ItemCatalog {
+        &self.catalog
+    }
+
+    /// Read only snapshot, use the mutating methods to change anything
+    pub async fn get_inventory(
+        &self,
+        executor: impl PgExecutor<'_>,
+        character_id: Uuid,
+    ) -> Result<Inventory, InventoryError> {
+        let stacks = get_inventory_stacks(executor, InventoryOwner::Character(character_id)).await?;
+        Inventory::from_stacks(INVENTORY_SLOTS, stacks)
+    }
+
+    pub async fn get_guild_bank(
+        &self,
+        executor: impl PgExecutor<'_>,
+        guild_id: Uuid,
+    ) -> Result<Inventory, InventoryError> {
+        let stacks = get_inventory_stacks(executor, InventoryOwner::GuildBank(guild_id)).await?;
+        Inventory::from_stacks(GUILD_BANK_SLOTS, stacks)
+    }
+
+    pub async fn add_items(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        item_id: &str,
+        quantity: i32,
+    ) -> Result<(), InventoryError> {
+        let item = self.catalog
+            .get(item_id)
+            .ok_or_else(|| InventoryError::UnknownItem(item_id.to_string()))?;
+        self.change(tx, character_id, |inventory| inventory.add(item, quantity)).await
+    }
+
+    pub async fn remove_items(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        item_id: &str,
+        quantity: i32,
+    ) -> Result<(), InventoryError> {
+        self.change(tx, character_id, |inventory| inventory.remove(item_id, quantity)).await
+    }
+
+    pub async fn move_stack(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        from: usize,
+        to: usize,
+    ) -> Result<(), InventoryError> {
+        let catalog = &self.catalog;
+        self.change(tx, character_id, |inventory| inventory.move_stack(catalog, from, to)).await
+    }
+
+    pub async fn split_stack(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        from: usize,
+        to: usize,
+        quantity: i32,
+    ) -> Result<(), InventoryError> {
+        self.change(tx, character_id, |inventory| inventory.split_stack(from, to, quantity)).await
+    }
+
+    /// Takes `(slot, quantity)` out of a character's inventory for items that leave it without
+    /// going into another one right away, like mail attachments. All or nothing.
+    pub async fn take_stacks(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        slots: &[(usize, i32)],
+    ) -> Result<Vec<ItemStack>, InventoryError> {
+        let mut taken = Vec::with_capacity(slots.len());
+        self.change(tx, character_id, |inventory| {
+            for (slot, quantity) in slots {
+                taken.push(inventory.take(*slot, *quantity)?);
+            }
+            Ok(())
+        }).await?;
+        Ok(taken)
+    }
+
+    /// Adds every stack or none of them
+    pub async fn add_stacks(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        stacks: &[ItemStack],
+    ) -> Result<(), InventoryError> {
+        let catalog = &self.catalog;
+        self.change(tx, character_id, |inventory| {
+            for stack in stacks {
+                let item = catalog
+                    .get(&stack.item_id)
+                    .ok_or_else(|| InventoryError::UnknownItem(stack.item_id.clone()))?;
+                inventory.add(item, stack.quantity)?;
+            }
+            Ok(())
+        }).await
+    }
+
+    /// Removes every stack by item from wherever it is in the inventory, or none of them
+    pub async fn remove_stacks(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        stacks: &[ItemStack],
+    ) -> Result<(), InventoryError> {
+        self.change(tx, character_id, |inventory| {
+            for stack in stacks {
+                inventory.remove(&stack.item_id, stack.quantity)?;
+            }
+            Ok(())
+        }).await
+    }
+
+    /// Moves `quantity` out of a slot of one inventory into wherever it fits in another. Both
+    /// inventories are locked characters first and then by id, so two opposite transfers can't
+    /// deadlock.
+    #[tracing::instrument(
+    name = "Transfer stack",
+    skip(self, tx)
+    )]
+    pub async fn transfer_stack(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        from: InventoryOwner,
+        slot: usize,
+        quantity: i32,
+        to: InventoryOwner,
+    ) -> Result<ItemStack, InventoryError> {
+        let mut owners = [from, to];
+        owners.sort_by_key(|o| match o {
+            InventoryOwner::Character(id) => (false, *id),
+            InventoryOwner::GuildBank(id) => (true, *id),
+        });
+        for owner in owners {
+            lock(tx, owner).await?;
+        }
+
+        let mut source = load(&mut *tx, from).await?;
+        let mut target = load(&mut *tx, to).await?;
+        let source_before = source.clone();
+        let target_before = target.clone();
+
+        let stack = source.take(slot, quantity)?;
+        let item = self.catalog
+            .get(&stack.item_id)
+            .ok_or_else(|| InventoryError::UnknownItem(stack.item_id.clone()))?;
+        target.add(item, stack.quantity)?;
+
+        save(tx, from, &source_before, &source).await?;
+        save(tx, to, &target_before, &target).await?;
+        Ok(stack)
+    }
+
+    #[tracing::instrument(
+    name = "Change inventory",
+    skip(self, tx, operation)
+    )]
+    async fn change(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        operation: impl FnOnce(&mut Inventory) -> Result<(), InventoryError>,
+    ) -> Result<(), InventoryError> {
+        let owner = InventoryOwner::Character(character_id);
+        lock(tx, owner).await?;
+
+        let before = load(&mut *tx, owner).await?;
+        let mut after = before.clone();
+        operation(&mut after)?;
+
+        save(tx, owner, &before, &after).await
+    }
+}
+
+async fn lock(tx: &mut Transaction<'_, Postgres>, owner: InventoryOwner) -> Result<(), InventoryError> {
+    if lock_inventory(tx, owner).await? {
+        return Ok(());
+    }
+    Err(match owner {
+        InventoryOwner::Character(_) => InventoryError::CharacterNotFound,
+        InventoryOwner::GuildBank(_) => InventoryError::GuildNotFound,
+    })
+}
+
+async fn load(executor: impl PgExecutor<'_>, owner: InventoryOwner) -> Result<Inventory, InventoryError> {
+    Inventory::from_stacks(owner.size(), get_inventory_stacks(executor, owner).await?)
+}
+
+async fn save(
+    tx: &mut Transaction<'_, Postgres>,
+    owner: InventoryOwner,
+    before: &Inventory,
+    after: &Inventory,
+) -> Result<(), InventoryError> {
+    for slot in after.changed_slots(before) {
+        store_inventory_slot(tx, owner, slot, after.slots()[slot].as_ref()).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use anyhow::Context;
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use crate::items::{InventoryOwner, ItemStack};
+
+/// Every inventory change locks the owning character or guild first, concurrent changes to the same
+/// inventory queue up behind each other instead of working on stale copies
+#[tracing::instrument(
+name = "Lock inventory",
+skip(tx)
+)]
+pub async fn lock_inventory(
+    tx: &mut Transaction<'_, Postgres>,
+    owner: InventoryOwner,
+) -> Result<bool, anyhow::Error> {
+    let locked = match owner {
+        InventoryOwner::Character(character_id) => sqlx::query!(
+            r#"SELECT id FROM characters WHERE id = $1 AND deleted_at IS NULL FOR UPDATE"#,
+            character_id
+        )
+            .fetch_optional(tx)
+            .await
+            .map(|r| r.is_some()),
+        InventoryOwner::GuildBank(guild_id) => sqlx::query!(
+            r#"SELECT id FROM guilds WHERE id = $1 FOR UPDATE"#,
+            guild_id
+        )
+            .fetch_optional(tx)
+            .await
+            .map(|r| r.is_some()),
+    }
+        .context("Failed to lock inventory")?;
+    Ok(locked)
+}
+
+#[tracing::instrument(
+name = "Get inventory stacks",
+skip(executor)
+)]
+pub async fn get_inventory_stacks(
+    executor: impl PgExecutor<'_>,
+    owner: InventoryOwner,
+) -> Result<Vec<(usize, ItemStack)>, anyhow::Error> {
+    let rows: Vec<(i32, String, i32)> = match owner {
+        InventoryOwner::Character(character_id) => sqlx::query!(
+            r#"
+            SELECT slot, item_id, quantity
+            FROM inventory_items
+            WHERE character_id = $1
+            ORDER BY slot
+            "#,
+            character_id
+        )
+            .fetch_all(executor)
+            .await
+            .map(|rows| rows.into_iter().map(|r| (r.slot, r.item_id, r.quantity)).collect()),
+        InventoryOwner::GuildBank(guild_id) => sqlx::query!(
+            r#"
+            SELECT slot, item_id, quantity
+            FROM guild_bank_items
+            WHERE guild_id = $1
+            ORDER BY slot
+            "#,
+            guild_id
+        )
+            .fetch_all(executor)
+            .await
+            .map(|rows| rows.into_iter().map(|r| (r.slot, r.item_id, r.quantity)).collect()),
+    }
+        .context("Failed to fetch inventory")?;
+
+    Ok(rows.into_iter()
+        .map(|(slot, item_id, quantity)| (slot as usize, ItemStack { item_id, quantity }))
+        .collect())
+}
+
+#[tracing::instrument(
+name = "Store inventory slot",
+skip(tx, stack)
+)]
+pub async fn store_inventory_slot(
+    tx: &mut Transaction<'_, Postgres>,
+    owner: InventoryOwner,
+    slot: usize,
+    stack: Option<&ItemStack>,
+) -> Result<(), anyhow::Error> {
+    match (owner, stack) {
+        (InventoryOwner::Character(character_id), Some(stack)) => sqlx::query!(
+            r#"
+            INSERT INTO inventory_items (character_id, slot, item_id, quantity)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (character_id, slot) DO UPDATE
+            SET item_id = EXCLUDED.item_id, quantity = EXCLUDED.quantity
+            "#,
+            character_id,
+            slot as i32,
+            stack.item_id,
+            stack.quantity
+        )
+            .execute(tx)
+            .await,
+        (InventoryOwner::Character(character_id), None) => sqlx::query!(
+            r#"DELETE FROM inventory_items WHERE character_id = $1 AND slot = $2"#,
+            character_id,
+            slot as i32
+        )
+            .execute(tx)
+            .await,
+        (InventoryOwner::GuildBank(guild_id), Some(stack)) => sqlx::query!(
+            r#"
+            INSERT INTO guild_bank_items (guild_id, slot, item_id, quantity)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (guild_id, slot) DO UPDATE
+            SET item_id = EXCLUDED.item_id, quantity = EXCLUDED.quantity
+            "#,
+            guild_id,
+            slot as i32,
+            stack.item_id,
+            stack.quantity
+        )
+            .execute(tx)
+            .await,
+        (InventoryOwner::GuildBank(guild_id), None) => sqlx::query!(
+            r#"DELETE FROM guild_bank_items WHERE guild_id = $1 AND slot = $2"#,
+            guild_id,
+            slot as i32
+        )
+            .execute(tx)
+            .await,
+    }
+        .context("Failed to store inventory slot")?;
+    Ok(())
+}
+
@@ -0,0 +1,86 @@
+use crate::characters::level_for_experience;
+
+/// Where every character's PvP rating starts, and goes back to when a season ends
+pub const STARTING_RATING: i32 = 1500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Leaderboard {
+    /// Ranked on total experience, the level follows from it
+    Level,
+    PvpRating,
+    Wealth,
+}
+
+impl Leaderboard {
+    pub const ALL: [Leaderboard; 3] = [Leaderboard::Level, Leaderboard::PvpRating, Leaderboard::Wealth];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Leaderboard::Level => "level",
+            Leaderboard::PvpRating => "pvp",
+            Leaderboard::Wealth => "wealth",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Leaderboard::Level => "Level",
+            Leaderboard::PvpRating => "PvP rating",
+            Leaderboard::Wealth => "Wealth",
+        }
+    }
+
+    /// Only the PvP rating starts over with a new season, levels and gold are earned for good
+    pub fn resets_with_season(&self) -> bool {
+        matches!(self, Leaderboard::PvpRating)
+    }
+
+    pub fn format_score(&self, score: i64) -> String {
+        match self {
+            Leaderboard::Level => format!("Level {} ({} xp)", level_for_experience(score), score),
+            Leaderboard::PvpRating => score.to_string(),
+            Leaderboard::Wealth => format!("{} gold", score),
+        }
+    }
+}
+
+impl TryFrom<String> for Leaderboard {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "level" => Ok(Leaderboard::Level),
+            "pvp" => Ok(Leaderboard::PvpRating),
+            "wealth" => Ok(Leaderboard::Wealth),
+            other => Err(format!("There is no {} leaderboard", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for Leaderboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok_eq};
+    use crate::characters::experience_for_level;
+    use crate::leaderboards::Leaderboard;
+
+    #[test]
+    fn all_boards_round_trip() {
+        for board in Leaderboard::ALL {
+            assert_ok_eq!(Leaderboard::try_from(board.as_str().to_uppercase()), board);
+        }
+        assert_err!(Leaderboard::try_from("fishing".to_string()));
+    }
+
+    #[test]
+    fn level_scores_show_the_level_their_experience_reached() {
+        assert_eq!("Level 1 (150 xp)", Leaderboard::Level.format_score(150));
+        assert_eq!("Level 3 (600 xp)", Leaderboard::Level.format_score(experience_for_level(3)));
+        assert_eq!("Level 60 (5000000000000 xp)", Leaderboard::Level.format_score(5_000_000_000_000));
+    }
+}
@@ -0,0 +1,79 @@
+use redis::aio::ConnectionManager;
+use redis::RedisError;
+use uuid::Uuid;
+use crate::leaderboards::{BoardEntry, Leaderboard};
+
+/// Members are added this many at a time so a big board doesn't turn into one huge command
+const CHUNK_SIZE: usize = 1000;
+
+/// One sorted set per board, members are character ids. Postgres has the real scores,
+/// the sets are rebuilt from it and only answer the rank and range questions quickly.
+#[derive(Clone)]
+pub struct LeaderboardCache {
+    redis: ConnectionManager,
+    key_prefix: String,
+}
+
+impl LeaderboardCache {
+    pub fn new(redis: ConnectionManager, key_prefix: String) -> Self {
+        LeaderboardCache { redis, key_prefix }
+    }
+
+    fn key(&self, board: Leaderboard) -> String {
+        format!("{}:{}", self.key_prefix, board.as_str())
+    }
+
+    /// Fills a fresh set and renames it over the old one, readers never see a half built board
+    pub async fn rebuild(&self, board: Leaderboard, entries: &[BoardEntry]) -> Result<(), RedisError> {
+        let mut redis = self.redis.clone();
+        let key = self.key(board);
+        if entries.is_empty() {
+            return redis::cmd("DEL").arg(&key).query_async(&mut redis).await;
+        }
+
+        let building = format!("{}:building:{}", key, Uuid::new_v4());
+        for chunk in entries.chunks(CHUNK_SIZE) {
+            let mut zadd = redis::cmd("ZADD");
+            zadd.arg(&building);
+            for entry in chunk {
+                zadd.arg(entry.score).arg(entry.character_id.to_string());
+            }
+            zadd.query_async::<_, ()>(&mut redis).await?;
+        }
+        redis::cmd("RENAME").arg(&building).arg(&key).query_async(&mut redis).await
+    }
+
+    /// `count` character ids and scores from `offset`, best first
+    pub async fn range(&self, board: Leaderboard, offset: usize, count: usize) -> Result<Vec<(Uuid, i64)>, RedisError> {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        let mut redis = self.redis.clone();
+        let members: Vec<(String, f64)> = redis::cmd("ZREVRANGE")
+            .arg(self.key(board))
+            .arg(offset)
+            .arg(offset + count - 1)
+            .arg("WITHSCORES")
+            .query_async(&mut redis)
+            .await?;
+        Ok(members.into_iter()
+            // only this cache writes the members, anything else in there is skipped rather than failing the page
+            .filter_map(|(member, score)| Uuid::parse_str(&member).ok().map(|id| (id, score as i64)))
+            .collect())
+    }
+
+    /// Where `character_id` stands counted from 0, `None` when it isn't ranked
+    pub async fn rank(&self, board: Leaderboard, character_id: Uuid) -> Result<Option<usize>, RedisError> {
+        let mut redis = self.redis.clone();
+        redis::cmd("ZREVRANK")
+            .arg(self.key(board))
+            .arg(character_id.to_string())
+            .query_async(&mut redis)
+            .await
+    }
+
+    pub async fn len(&self, board: Leaderboard) -> Result<usize, RedisError> {
+        let mut redis = self.redis.clone();
+        redis::cmd("ZCARD").arg(self.key(board)).query_async(&mut redis).await
+    }
+}
@@ -0,0 +1,11 @@
+mod board;
+mod cache;
+mod service;
+mod store;
+mod system;
+
+pub use board::{Leaderboard, STARTING_RATING};
+pub use cache::LeaderboardCache;
+pub use service::{LeaderboardPage, LeaderboardService};
+pub use store::{find_character_id_by_name, get_season_snapshot, get_seasons, BoardEntry, RankedEntry, Season};
+pub use system::LeaderboardRefreshSystem;
\ No newline at end of file
@@ -0,0 +1,160 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::audit::{record_audit_entry, AuditAction, AuditEntry};
+use crate::configuration::LeaderboardSettings;
+use crate::leaderboards::{BoardEntry, Leaderboard, LeaderboardCache, RankedEntry};
+use crate::leaderboards::store::{count_board_scores, get_board_rank, get_board_scores, get_character_names, lock_running_season, reset_pvp_ratings, start_next_season, store_season_snapshot};
+
+#[derive(Debug, Clone)]
+pub struct LeaderboardPage {
+    pub entries: Vec<RankedEntry>,
+    /// How many characters are on the board
+    pub total: usize,
+}
+
+/// Reads the boards from Redis and keeps them filled from Postgres. Until the first refresh,
+/// or whenever Redis is unreachable, answers come straight from Postgres instead.
+pub struct LeaderboardService {
+    pool: PgPool,
+    cache: LeaderboardCache,
+    settings: LeaderboardSettings,
+    warm: AtomicBool,
+}
+
+impl LeaderboardService {
+    pub fn new(pool: PgPool, cache: LeaderboardCache, settings: LeaderboardSettings) -> Self {
+        LeaderboardService { pool, cache, settings, warm: AtomicBool::new(false) }
+    }
+
+    pub fn settings(&self) -> &LeaderboardSettings {
+        &self.settings
+    }
+
+    #[tracing::instrument(
+    name = "Refresh leaderboards",
+    skip(self)
+    )]
+    pub async fn refresh_all(&self) -> Result<(), anyhow::Error> {
+        for board in Leaderboard::ALL {
+            let entries = get_board_scores(&self.pool, board, 0, None).await?;
+            self.cache.rebuild(board, &entries)
+                .await
+                .with_context(|| format!("Failed to rebuild the {} leaderboard", board))?;
+        }
+        self.warm.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Page `page` of `board`, counted from 1
+    #[tracing::instrument(
+    name = "Get leaderboard page",
+    skip(self),
+    fields(board = % board)
+    )]
+    pub async fn top(&self, board: Leaderboard, page: usize) -> Result<LeaderboardPage, anyhow::Error> {
+        let offset = page.saturating_sub(1) * self.settings.page_size;
+        if self.warm.load(Ordering::Relaxed) {
+            match self.cached_range(board, offset, self.settings.page_size).await {
+                Ok(entries) => match self.cache.len(board).await {
+                    Ok(total) => return Ok(LeaderboardPage { entries, total }),
+                    Err(e) => tracing::warn!(error.message = %e, "Falling back to Postgres for the leaderboard"),
+                },
+                Err(e) => tracing::warn!(error.message = %e, "Falling back to Postgres for the leaderboard"),
+            }
+        }
+        let entries = get_board_scores(&self.pool, board, offset as i64, Some(self.settings.page_size as i64)).await?;
+        let total = count_board_scores(&self.pool, board).await?;
+        Ok(LeaderboardPage { entries: rank_entries(entries, offset), total: total as usize })
+    }
+
+    /// The character's own rank with its neighbours above and below, `None` when it isn't on the board
+    #[tracing::instrument(
+    name = "Get leaderboard neighbours",
+    skip(self),
+    fields(board = % board)
+    )]
+    pub async fn around(&self, board: Leaderboard, character_id: Uuid) -> Result<Option<Vec<RankedEntry>>, anyhow::Error> {
+        let neighbours = self.settings.neighbours;
+        if self.warm.load(Ordering::Relaxed) {
+            let cached = match self.cache.rank(board, character_id).await {
+                Ok(None) => return Ok(None),
+                Ok(Some(rank)) => {
+                    let offset = rank.saturating_sub(neighbours);
+                    self.cached_range(board, offset, rank - offset + neighbours + 1).await
+                }
+                Err(e) => Err(e.into()),
+            };
+            match cached {
+                Ok(entries) => return Ok(Some(entries)),
+                Err(e) => tracing::warn!(error.message = %e, "Falling back to Postgres for the leaderboard"),
+            }
+        }
+        let Some(rank) = get_board_rank(&self.pool, board, character_id).await? else {
+            return Ok(None);
+        };
+        let offset = (rank as usize).saturating_sub(neighbours);
+        let count = rank as usize - offset + neighbours + 1;
+        let entries = get_board_scores(&self.pool, board, offset as i64, Some(count as i64)).await?;
+        Ok(Some(rank_entries(entries, offset)))
+    }
+
+    /// Ranks from Redis with names from Postgres, characters deleted since the last refresh are left out
+    async fn cached_range(&self, board: Leaderboard, offset: usize, count: usize) -> Result<Vec<RankedEntry>, anyhow::Error> {
+        let members = self.cache.range(board, offset, count).await?;
+        let ids: Vec<Uuid> = members.iter().map(|(id, _)| *id).collect();
+        let mut names = get_character_names(&self.pool, &ids).await?;
+        Ok(members.into_iter()
+            .enumerate()
+            .filter_map(|(i, (character_id, score))| names.remove(&character_id).map(|character_name| RankedEntry {
+                rank: offset + i + 1,
+                character_id,
+                character_name,
+                score,
+            }))
+            .collect())
+    }
+
+    /// Keeps the top of every board as it stands, starts the PvP ratings over and opens the next season.
+    /// Returns the number of the new season.
+    #[tracing::instrument(
+    name = "End leaderboard season",
+    skip(self)
+    )]
+    pub async fn end_season(&self, admin_id: Uuid) -> Result<i32, anyhow::Error> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await.context("Failed to begin season transaction")?;
+        let season = lock_running_season(&mut tx)
+            .await?
+            .context("There is no running season to end")?;
+        for board in Leaderboard::ALL {
+            let entries = get_board_scores(&mut tx, board, 0, Some(self.settings.snapshot_size as i64)).await?;
+            store_season_snapshot(&mut tx, season.number, board, &entries).await?;
+        }
+        let reset = reset_pvp_ratings(&mut tx, now).await?;
+        let next = start_next_season(&mut tx, season.number, now).await?;
+        record_audit_entry(&mut tx, &AuditEntry {
+            actor_id: Some(admin_id),
+            action: AuditAction::LeaderboardSeasonEnded,
+            subject_id: None,
+            details: format!("Season {} ended, {} PvP ratings were reset", season.number, reset),
+        }).await?;
+        tx.commit().await.context("Failed to commit season transaction")?;
+
+        // the season is over either way, the boards catch up on the next refresh when this fails
+        if let Err(e) = self.refresh_all().await {
+            tracing::warn!(error.cause_chain = ?e, error.message = %e, "Failed to refresh leaderboards after the season ended");
+        }
+        Ok(next)
+    }
+}
+
+/// Ranks entries that start after the first `offset` on the board
+fn rank_entries(entries: Vec<BoardEntry>, offset: usize) -> Vec<RankedEntry> {
+    entries.into_iter()
+        .enumerate()
+        .map(|(i, e)| RankedEntry { rank: offset + i + 1, character_id: e.character_id, character_name: e.character_name, score: e.score })
+        .collect()
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::leaderboards::{Leaderboard, STARTING_RATING};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardEntry {
+    pub character_id: Uuid,
+    pub character_name: String,
+    pub score: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankedEntry {
+    /// Counted from 1
+    pub rank: usize,
+    pub character_id: Uuid,
+    pub character_name: String,
+    pub score: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Season {
+    pub number: i32,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+/// Ranked characters on `board` best first, skipping `offset` of them and taking at most `limit`,
+/// all of them without a limit. Equal scores go to the larger id first like `ZREVRANGE` does with
+/// its members, uuids order the same as their text so both agree on ranks.
+#[tracing::instrument(
+name = "Get board scores",
+skip(executor),
+fields(board = % board)
+)]
+pub async fn get_board_scores(
+    executor: impl PgExecutor<'_>,
+    board: Leaderboard,
+    offset: i64,
+    limit: Option<i64>,
+) -> Result<Vec<BoardEntry>, anyhow::Error> {
+    let entries = match board {
+        Leaderboard::Level => sqlx::query!(
+            r#"
+            SELECT id, name, experience
+            FROM characters
+            WHERE deleted_at IS NULL
+            ORDER BY experience DESC, id DESC
+            OFFSET $1 LIMIT $2
+            "#,
+            offset,
+            limit
+        )
+            .fetch_all(executor)
+            .await
+            .context("Failed to fetch level scores")?
+            .into_iter()
+            .map(|r| BoardEntry { character_id: r.id, character_name: r.name, score: r.experience })
+            .collect(),
+        Leaderboard::PvpRating => sqlx::query!(
+            r#"
+            SELECT c.id, c.name, r.rating
+            FROM character_ratings r
+                     JOIN characters c ON c.id = r.character_id
+            WHERE c.deleted_at IS NULL AND r.games > 0
+            ORDER BY r.rating DESC, c.id DESC
+            OFFSET $1 LIMIT $2
+            "#,
+            offset,
+            limit
+        )
+            .fetch_all(executor)
+            .await
+            .context("Failed to fetch PvP ratings")?
+            .into_iter()
+            .map(|r| BoardEntry { character_id: r.id, character_name: r.name, score: r.rating as i64 })
+            .collect(),
+        Leaderboard::Wealth => sqlx::query!(
+            r#"
+            SELECT c.id, c.name, a.balance
+            FROM ledger_accounts a
+                     JOIN characters c ON c.id = a.character_id
+            WHERE c.deleted_at IS NULL AND a.currency = 'gold' AND a.balance > 0
+            ORDER BY a.balance DESC, c.id DESC
+            OFFSET $1 LIMIT $2
+            "#,
+            offset,
+            limit
+        )
+            .fetch_all(executor)
+            .await
+            .context("Failed to fetch gold balances")?
+            .into_iter()
+            .map(|r| BoardEntry { character_id: r.id, character_name: r.name, score: r.balance })
+            .collect(),
+    };
+    Ok(entries)
+}
+
+/// How many characters are ranked on `board`
+#[tracing::instrument(
+name = "Count board scores",
+skip(executor),
+fields(board = % board)
+)]
+pub async fn count_board_scores(
+    executor: impl PgExecutor<'_>,
+    board: Leaderboard,
+) -> Result<i64, anyhow::Error> {
+    let count = match board {
+        Leaderboard::Level => sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM characters WHERE deleted_at IS NULL"#
+        )
+            .fetch_one(executor)
+            .await,
+        Leaderboard::PvpRating => sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM character_ratings r
+                     JOIN characters c ON c.id = r.character_id
+            WHERE c.deleted_at IS NULL AND r.games > 0
+            "#
+        )
+            .fetch_one(executor)
+            .await,
+        Leaderboard::Wealth => sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM ledger_accounts a
+                     JOIN characters c ON c.id = a.character_id
+            WHERE c.deleted_at IS NULL AND a.currency = 'gold' AND a.balance > 0
+            "#
+        )
+            .fetch_one(executor)
+            .await,
+    };
+    count.context("Failed to count board scores")
+}
+
+/// How many characters are ranked above the character on `board`, `None` when it isn't ranked
+#[tracing::instrument(
+name = "Get board rank",
+skip(executor),
+fields(board = % board)
+)]
+pub async fn get_board_rank(
+    executor: impl PgExecutor<'_>,
+    board: Leaderboard,
+    character_id: Uuid,
+) -> Result<Option<i64>, anyhow::Error> {
+    let ahead = match board {
+        Leaderboard::Level => sqlx::query_scalar!(
+            r#"
+            SELECT (
+                SELECT COUNT(*)
+                FROM characters o
+                WHERE o.deleted_at IS NULL AND (o.experience, o.id) > (c.experience, c.id)
+            ) AS "ahead!"
+            FROM characters c
+            WHERE c.id = $1 AND c.deleted_at IS NULL
+            "#,
+            character_id
+        )
+            .fetch_optional(executor)
+            .await,
+        Leaderboard::PvpRating => sqlx::query_scalar!(
+            r#"
+            SELECT (
+                SELECT COUNT(*)
+                FROM character_ratings o
+                         JOIN characters oc ON oc.id = o.character_id
+                WHERE oc.deleted_at IS NULL AND o.games > 0 AND (o.rating, o.character_id) > (r.rating, r.character_id)
+            ) AS "ahead!"
+            FROM character_ratings r
+                     JOIN characters c ON c.id = r.character_id
+            WHERE r.character_id = $1 AND c.deleted_at IS NULL AND r.games > 0
+            "#,
+            character_id
+        )
+            .fetch_optional(executor)
+            .await,
+        Leaderboard::Wealth => sqlx::query_scalar!(
+            r#"
+            SELECT (
+                SELECT COUNT(*)
+                FROM ledger_accounts o
+                         JOIN characters oc ON oc.id = o.character_id
+                WHERE oc.deleted_at IS NULL AND o.currency = 'gold' AND o.balance > 0
+                  AND (o.balance, o.character_id) > (a.balance, a.character_id)
+            ) AS "ahead!"
+            FROM ledger_accounts a
+                     JOIN characters c ON c.id = a.character_id
+            WHERE a.character_id = $1 AND c.deleted_at IS NULL AND a.currency = 'gold' AND a.balance > 0
+            "#,
+            character_id
+        )
+            .fetch_optional(executor)
+            .await,
+    };
+    ahead.context("Failed to fetch board rank")
+}
+
+#[tracing::instrument(
+name = "Get character names",
+skip(executor, character_ids)
+)]
+pub async fn get_character_names(
+    executor: impl PgExecutor<'_>,
+    character_ids: &[Uuid],
+) -> Result<HashMap<Uuid, String>, anyhow::Error> {
+    let rows = sqlx::query!(
+        "SELECT id, name FROM characters WHERE id = ANY($1) AND deleted_at IS NULL",
+        character_ids
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch character names")?;
+    Ok(rows.into_iter().map(|r| (r.id, r.name)).collect())
+}
+
+/// Character names are unique among the living regardless of case
+#[tracing::instrument(
+name = "Find character by name",
+skip(executor)
+)]
+pub async fn find_character_id_by_name(
+    executor: impl PgExecutor<'_>,
+    name: &str,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let row = sqlx::query!(
+        "SELECT id FROM characters WHERE lower(name) = lower($1) AND deleted_at IS NULL",
+        name
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to look up character by name")?;
+    Ok(row.map(|r| r.id))
+}
+
+#[tracing::instrument(
+name = "Get leaderboard seasons",
+skip(executor)
+)]
+pub async fn get_seasons(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<Season>, anyhow::Error> {
+    sqlx::query_as!(
+        Season,
+        "SELECT number, started_at, ended_at FROM leaderboard_seasons ORDER BY number DESC"
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch leaderboard seasons")
+}
+
+/// The running season, held until the transaction ends so it can only be ended once
+#[tracing::instrument(
+name = "Lock running season",
+skip(tx)
+)]
+pub async fn lock_running_season(
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<Option<Season>, anyhow::Error> {
+    sqlx::query_as!(
+        Season,
+        "SELECT number, started_at, ended_at FROM leaderboard_seasons WHERE ended_at IS NULL FOR UPDATE"
+    )
+        .fetch_optional(tx)
+        .await
+        .context("Failed to lock the running season")
+}
+
+#[tracing::instrument(
+name = "Store season snapshot",
+skip(tx, entries),
+fields(board = % board)
+)]
+pub async fn store_season_snapshot(
+    tx: &mut Transaction<'_, Postgres>,
+    season: i32,
+    board: Leaderboard,
+    entries: &[BoardEntry],
+) -> Result<(), anyhow::Error> {
+    let ranks: Vec<i32> = (1..=entries.len() as i32).collect();
+    let character_ids: Vec<Uuid> = entries.iter().map(|e| e.character_id).collect();
+    let names: Vec<String> = entries.iter().map(|e| e.character_name.clone()).collect();
+    let scores: Vec<i64> = entries.iter().map(|e| e.score).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO leaderboard_snapshots (season, board, rank, character_id, character_name, score)
+        SELECT $1, $2, * FROM UNNEST($3::int[], $4::uuid[], $5::text[], $6::bigint[])
+        "#,
+        season,
+        board.as_str(),
+        &ranks,
+        &character_ids,
+        &names,
+        &scores
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store season snapshot")?;
+    Ok(())
+}
+
+/// Closes `season` and opens the one after it, returns the new season's number
+#[tracing::instrument(
+name = "Start next season",
+skip(tx)
+)]
+pub async fn start_next_season(
+    tx: &mut Transaction<'_, Postgres>,
+    season: i32,
+    now: DateTime<Utc>,
+) -> Result<i32, anyhow::Error> {
+    sqlx::query!(
+        "UPDATE leaderboard_seasons SET ended_at = $2 WHERE number = $1",
+        season,
+        now
+    )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to end season")?;
+    sqlx::query!(
+        "INSERT INTO leaderboard_seasons (number, started_at) VALUES ($1, $2)",
+        season + 1,
+        now
+    )
+        .execute(tx)
+        .await
+        .context("Failed to start season")?;
+    Ok(season + 1)
+}
+
+#[tracing::instrument(
+name = "Reset PvP ratings",
+skip(tx)
+)]
+pub async fn reset_pvp_ratings(
+    tx: &mut Transaction<'_, Postgres>,
+    now: DateTime<Utc>,
+) -> Result<u64, anyhow::Error> {
+    let result = sqlx::query!(
+        "UPDATE character_ratings SET rating = $1, games = 0, updated_at = $2 WHERE games > 0",
+        STARTING_RATING,
+        now
+    )
+        .execute(tx)
+        .await
+        .context("Failed to reset PvP ratings")?;
+    Ok(result.rows_affected())
+}
+
+#[tracing::instrument(
+name = "Get season snapshot",
+skip(executor),
+fields(board = % board)
+)]
+pub async fn get_season_snapshot(
+    executor: impl PgExecutor<'_>,
+    season: i32,
+    board: Leaderboard,
+) -> Result<Vec<RankedEntry>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT rank, character_id, character_name, score
+        FROM leaderboard_snapshots
+        WHERE season = $1 AND board = $2
+        ORDER BY rank
+        "#,
+        season,
+        board.as_str()
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch season snapshot")?;
+    Ok(rows.into_iter()
+        .map(|r| RankedEntry {
+            rank: r.rank as usize,
+            character_id: r.character_id,
+            character_name: r.character_name,
+            score: r.score,
+        })
+        .collect())
+}
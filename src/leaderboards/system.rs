@@ -0,0 +1,37 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use crate::game_loop::{GameSystem, TickContext};
+use crate::leaderboards::LeaderboardService;
+
+/// Rebuilds the cached boards from Postgres on the first tick and every so often in game time after that
+pub struct LeaderboardRefreshSystem {
+    leaderboards: Arc<LeaderboardService>,
+    interval: chrono::Duration,
+    last_refresh: Option<DateTime<Utc>>,
+}
+
+impl LeaderboardRefreshSystem {
+    pub fn new(leaderboards: Arc<LeaderboardService>, interval: chrono::Duration) -> Self {
+        LeaderboardRefreshSystem { leaderboards, interval, last_refresh: None }
+    }
+}
+
+#[async_trait]
+impl GameSystem for LeaderboardRefreshSystem {
+    fn name(&self) -> &'static str {
+        "leaderboard_refresh"
+    }
+
+    async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error> {
+        if let Some(last_refresh) = self.last_refresh {
+            if ctx.now - last_refresh < self.interval {
+                return Ok(());
+            }
+        }
+        self.last_refresh = Some(ctx.now);
+        self.leaderboards.refresh_all().await?;
+        tracing::debug!(tick = ctx.tick, "Refreshed leaderboards");
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use uuid::Uuid;
+
+/// Accounts owned by the game itself. Money enters the economy from a source and leaves through a
+/// sink, so the sum over all accounts of a currency is always zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SystemAccount {
+    /// Quest rewards, loot and other money created out of thin air
+    Rewards,
+    /// NPC merchants, buying from them is a sink and selling to them a source
+    Vendors,
+    /// Listing fees, repair costs and other money that disappears
+    Fees,
+    /// Money held on behalf of players while mail or auctions are pending
+    Escrow,
+    /// Manual corrections by admins
+    Adjustments,
+}
+
+impl SystemAccount {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SystemAccount::Rewards => "rewards",
+            SystemAccount::Vendors => "vendors",
+            SystemAccount::Fees => "fees",
+            SystemAccount::Escrow => "escrow",
+            SystemAccount::Adjustments => "adjustments",
+        }
+    }
+}
+
+impl TryFrom<String> for SystemAccount {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "rewards" => Ok(SystemAccount::Rewards),
+            "vendors" => Ok(SystemAccount::Vendors),
+            "fees" => Ok(SystemAccount::Fees),
+            "escrow" => Ok(SystemAccount::Escrow),
+            "adjustments" => Ok(SystemAccount::Adjustments),
+            other => Err(format!("{} is not a valid system account", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LedgerAccount {
+    Character(Uuid),
+    System(SystemAccount),
+}
+
+impl LedgerAccount {
+    /// Only system accounts may be overdrawn
+    pub fn can_go_negative(&self) -> bool {
+        matches!(self, LedgerAccount::System(_))
+    }
+}
+
+impl std::fmt::Display for LedgerAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LedgerAccount::Character(id) => write!(f, "character:{}", id),
+            LedgerAccount::System(account) => write!(f, "system:{}", account.as_str()),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Currency {
+    Gold,
+    Honor,
+    Gems,
+}
+
+impl Currency {
+    pub const ALL: [Currency; 3] = [Currency::Gold, Currency::Honor, Currency::Gems];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Currency::Gold => "gold",
+            Currency::Honor => "honor",
+            Currency::Gems => "gems",
+        }
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "gold" => Ok(Currency::Gold),
+            "honor" => Ok(Currency::Honor),
+            "gems" => Ok(Currency::Gems),
+            other => Err(format!("{} is not a valid currency", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
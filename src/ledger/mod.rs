@@ -0,0 +1,13 @@
+mod account;
+mod currency;
+mod report;
+mod service;
+mod store;
+mod transfer;
+
+pub use account::{LedgerAccount, SystemAccount};
+pub use currency::Currency;
+pub use report::{AccountReconciliation, ReconciliationReport};
+pub use service::{reconcile, transfer, transfer_and_commit, transfer_once, LedgerError};
+pub use store::{get_balance, get_balances};
+pub use transfer::{Transfer, TransferOutcome};
\ No newline at end of file
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use crate::ledger::{Currency, LedgerAccount};
+
+#[derive(Debug, Clone)]
+pub struct AccountReconciliation {
+    pub account: LedgerAccount,
+    pub currency: Currency,
+    /// The cached balance on the account row
+    pub balance: i64,
+    /// What the entries add up to
+    pub ledger_balance: i64,
+    pub entries: i64,
+}
+
+impl AccountReconciliation {
+    pub fn is_consistent(&self) -> bool {
+        self.balance == self.ledger_balance
+    }
+}
+
+#[derive(Debug)]
+pub struct ReconciliationReport {
+    pub accounts: Vec<AccountReconciliation>,
+    pub unbalanced_transfers: Vec<Uuid>,
+    /// Sum of all cached balances per currency, double-entry keeps these at zero
+    pub currency_totals: BTreeMap<Currency, i64>,
+}
+
+impl ReconciliationReport {
+    pub fn new(accounts: Vec<AccountReconciliation>, unbalanced_transfers: Vec<Uuid>) -> Self {
+        let mut currency_totals = BTreeMap::new();
+        for account in &accounts {
+            *currency_totals.entry(account.currency).or_insert(0) += account.balance;
+        }
+        ReconciliationReport { accounts, unbalanced_transfers, currency_totals }
+    }
+
+    pub fn mismatched_accounts(&self) -> impl Iterator<Item=&AccountReconciliation> {
+        self.accounts.iter().filter(|a| !a.is_consistent())
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.unbalanced_transfers.is_empty()
+            && self.mismatched_accounts().next().is_none()
+            && self.currency_totals.values().all(|total| *total == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::ledger::{AccountReconciliation, Currency, LedgerAccount, ReconciliationReport, SystemAccount};
+
+    fn account(account: LedgerAccount, balance: i64, ledger_balance: i64) -> AccountReconciliation {
+        AccountReconciliation { account, currency: Currency::Gold, balance, ledger_balance, entries: 1 }
+    }
+
+    #[test]
+    fn matching_balances_that_sum_to_zero_are_consistent() {
+        let report = ReconciliationReport::new(vec![
+            account(LedgerAccount::System(SystemAccount::Rewards), -50, -50),
+            account(LedgerAccount::Character(Uuid::new_v4()), 50, 50),
+        ], vec![]);
+
+        assert!(report.is_consistent());
+    }
+
+    #[test]
+    fn cached_balance_differing_from_entries_is_reported() {
+        let report = ReconciliationReport::new(vec![
+            account(LedgerAccount::System(SystemAccount::Rewards), -50, -50),
+            account(LedgerAccount::Character(Uuid::new_v4()), 50, 40),
+        ], vec![]);
+
+        assert!(!report.is_consistent());
+        assert_eq!(1, report.mismatched_accounts().count());
+    }
+
+    #[test]
+    fn currency_not_summing_to_zero_is_inconsistent() {
+        let report = ReconciliationReport::new(vec![
+            account(LedgerAccount::Character(Uuid::new_v4()), 50, 50),
+        ], vec![]);
+
+        assert!(!report.is_consistent());
+        assert_eq!(Some(&50), report.currency_totals.get(&Currency::Gold));
+    }
+
+    #[test]
+    fn unbalanced_transfers_are_inconsistent() {
+        let report = ReconciliationReport::new(vec![], vec![Uuid::new_v4()]);
+        assert!(!report.is_consistent());
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt::{Debug, Formatter};
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use crate::gateway::{ClientError, ErrorCode};
+use crate::ledger::{Currency, ReconciliationReport, Transfer, TransferOutcome};
+use crate::ledger::store::{ensure_ledger_account, get_account_reconciliation, get_transfer_by_idempotency_key, get_unbalanced_transfers, lock_ledger_accounts, store_ledger_entry, store_ledger_transfer};
+use crate::utils::error_chain_fmt;
+
+#[derive(thiserror::Error)]
+pub enum LedgerError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("Not enough {currency}, {needed} needed but only {available} available")]
+    InsufficientFunds { currency: Currency, needed: i64, available: i64 },
+    #[error("The idempotency key {0} was already used for a different transfer")]
+    IdempotencyConflict(String),
+    #[error("That request went through already")]
+    AlreadyApplied(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for LedgerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for LedgerError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            LedgerError::ValidationError(_) => ErrorCode::InvalidMessage,
+            LedgerError::InsufficientFunds { .. }
+            | LedgerError::IdempotencyConflict(_)
+            | LedgerError::AlreadyApplied(_) => ErrorCode::InvalidAction,
+            LedgerError::UnexpectedError(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+/// Moves money between two accounts inside the caller's transaction. Nothing is written unless the
+/// caller commits, and the ledger rows can't be changed after that.
+#[tracing::instrument(
+name = "Ledger transfer",
+skip(tx, transfer),
+fields(from = % transfer.from, to = % transfer.to, currency = % transfer.currency, amount = transfer.amount)
+)]
+pub async fn transfer(
+    tx: &mut Transaction<'_, Postgres>,
+    transfer: &Transfer,
+) -> Result<TransferOutcome, LedgerError> {
+    transfer.validate().map_err(LedgerError::ValidationError)?;
+
+    let from = ensure_ledger_account(tx, transfer.from, transfer.currency).await?;
+    let to = ensure_ledger_account(tx, transfer.to, transfer.currency).await?;
+    let transfer_id = match store_ledger_transfer(tx, transfer, from, to).await? {
+        Some(id) => id,
+        None => {
+            let key = transfer.idempotency_key.clone().unwrap_or_default();
+            let stored = get_transfer_by_idempotency_key(&mut *tx, from, &key).await?;
+            let same = stored.to_account == to
+                && stored.currency == transfer.currency.as_str()
+                && stored.amount == transfer.amount
+                && stored.reason == transfer.reason;
+            if !same {
+                return Err(LedgerError::IdempotencyConflict(key));
+            }
+            return Ok(TransferOutcome::AlreadyApplied(stored.id));
+        }
+    };
+    let locked = lock_ledger_accounts(tx, &[from, to]).await?;
+
+    if !transfer.from.can_go_negative() {
+        let available = locked.iter()
+            .find(|(id, _)| *id == from)
+            .map(|(_, balance)| *balance)
+            .unwrap_or(0);
+        if available < transfer.amount {
+            return Err(LedgerError::InsufficientFunds {
+                currency: transfer.currency,
+                needed: transfer.amount,
+                available,
+            });
+        }
+    }
+
+    store_ledger_entry(tx, transfer_id, from, -transfer.amount).await?;
+    store_ledger_entry(tx, transfer_id, to, transfer.amount).await?;
+    Ok(TransferOutcome::Applied(transfer_id))
+}
+
+/// For transfers keyed by a client request. A repeated request fails with `AlreadyApplied`, so the
+/// caller's transaction rolls back instead of doing the rest of the request a second time.
+pub async fn transfer_once(
+    tx: &mut Transaction<'_, Postgres>,
+    transfer: &Transfer,
+) -> Result<uuid::Uuid, LedgerError> {
+    match self::transfer(tx, transfer).await? {
+        TransferOutcome::Applied(id) => Ok(id),
+        TransferOutcome::AlreadyApplied(_) => Err(LedgerError::AlreadyApplied(
+            transfer.idempotency_key.clone().unwrap_or_default()
+        )),
+    }
+}
+
+pub async fn reconcile(
+    executor: impl PgExecutor<'_> + Copy,
+) -> Result<ReconciliationReport, LedgerError> {
+    let accounts = get_account_reconciliation(executor).await?;
+    let unbalanced = get_unbalanced_transfers(executor).await?;
+    Ok(ReconciliationReport::new(accounts, unbalanced))
+}
+
+/// Convenience for transfers that don't need to be part of a bigger transaction
+pub async fn transfer_and_commit(
+    pool: &PgPool,
+    transfer: &Transfer,
+) -> Result<TransferOutcome, LedgerError> {
+    let mut tx = pool.begin()
+        .await
+        .context("Failed to begin ledger transaction")?;
+    let outcome = self::transfer(&mut tx, transfer).await?;
+    tx.commit()
+        .await
+        .context("Failed to commit ledger transaction")?;
+    Ok(outcome)
+}
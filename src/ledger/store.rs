@@ -0,0 +1,283 @@
+use anyhow::{anyhow, Context};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::ledger::{AccountReconciliation, Currency, LedgerAccount, SystemAccount, Transfer};
+
+/// Ledger accounts are opened on first use
+#[tracing::instrument(
+name = "Ensure ledger account",
+skip(tx)
+)]
+pub async fn ensure_ledger_account(
+    tx: &mut Transaction<'_, Postgres>,
+    account: LedgerAccount,
+    currency: Currency,
+) -> Result<Uuid, anyhow::Error> {
+    let (character_id, system_name) = match account {
+        LedgerAccount::Character(id) => (Some(id), None),
+        LedgerAccount::System(system) => (None, Some(system.as_str())),
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO ledger_accounts (id, character_id, system_name, currency)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        character_id,
+        system_name,
+        currency.as_str()
+    )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to open ledger account")?;
+
+    let id = sqlx::query!(
+        r#"
+        SELECT id
+        FROM ledger_accounts
+        WHERE (character_id = $1 OR system_name = $2) AND currency = $3
+        "#,
+        character_id,
+        system_name,
+        currency.as_str()
+    )
+        .fetch_one(tx)
+        .await
+        .context("Failed to fetch ledger account")?
+        .id;
+    Ok(id)
+}
+
+/// Locks in id order so two transfers between the same accounts can't deadlock
+#[tracing::instrument(
+name = "Lock ledger accounts",
+skip(tx)
+)]
+pub async fn lock_ledger_accounts(
+    tx: &mut Transaction<'_, Postgres>,
+    account_ids: &[Uuid],
+) -> Result<Vec<(Uuid, i64)>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, balance
+        FROM ledger_accounts
+        WHERE id = ANY($1)
+        ORDER BY id
+        FOR UPDATE
+        "#,
+        account_ids
+    )
+        .fetch_all(tx)
+        .await
+        .context("Failed to lock ledger accounts")?;
+    Ok(rows.into_iter().map(|r| (r.id, r.balance)).collect())
+}
+
+/// Returns `None` when the debiting account already used the idempotency key. A concurrent
+/// transfer with the same key makes this wait until the other transaction is done.
+#[tracing::instrument(
+name = "Store ledger transfer",
+skip(tx, transfer)
+)]
+pub async fn store_ledger_transfer(
+    tx: &mut Transaction<'_, Postgres>,
+    transfer: &Transfer,
+    from_account: Uuid,
+    to_account: Uuid,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO ledger_transfers (id, idempotency_key, from_account, to_account, currency, amount, reason)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (from_account, idempotency_key) DO NOTHING
+        RETURNING id
+        "#,
+        Uuid::new_v4(),
+        transfer.idempotency_key,
+        from_account,
+        to_account,
+        transfer.currency.as_str(),
+        transfer.amount,
+        transfer.reason
+    )
+        .fetch_optional(tx)
+        .await
+        .context("Failed to store ledger transfer")?
+        .map(|r| r.id);
+    Ok(id)
+}
+
+pub struct StoredTransfer {
+    pub id: Uuid,
+    pub to_account: Uuid,
+    pub currency: String,
+    pub amount: i64,
+    pub reason: String,
+}
+
+#[tracing::instrument(
+name = "Get ledger transfer by idempotency key",
+skip(executor)
+)]
+pub async fn get_transfer_by_idempotency_key(
+    executor: impl PgExecutor<'_>,
+    from_account: Uuid,
+    key: &str,
+) -> Result<StoredTransfer, anyhow::Error> {
+    sqlx::query_as!(
+        StoredTransfer,
+        r#"
+        SELECT id, to_account, currency, amount, reason
+        FROM ledger_transfers
+        WHERE from_account = $1 AND idempotency_key = $2
+        "#,
+        from_account,
+        key
+    )
+        .fetch_one(executor)
+        .await
+        .context("Failed to fetch ledger transfer")
+}
+
+#[tracing::instrument(
+name = "Store ledger entry",
+skip(tx)
+)]
+pub async fn store_ledger_entry(
+    tx: &mut Transaction<'_, Postgres>,
+    transfer_id: Uuid,
+    account_id: Uuid,
+    amount: i64,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"INSERT INTO ledger_entries (transfer_id, account_id, amount) VALUES ($1, $2, $3)"#,
+        transfer_id,
+        account_id,
+        amount
+    )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to store ledger entry")?;
+
+    sqlx::query!(
+        r#"UPDATE ledger_accounts SET balance = balance + $2 WHERE id = $1"#,
+        account_id,
+        amount
+    )
+        .execute(tx)
+        .await
+        .context("Failed to update ledger balance")?;
+    Ok(())
+}
+
+/// Accounts that were never used have a balance of zero
+#[tracing::instrument(
+name = "Get character balance",
+skip(executor)
+)]
+pub async fn get_balance(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+    currency: Currency,
+) -> Result<i64, anyhow::Error> {
+    let balance = sqlx::query!(
+        r#"SELECT balance FROM ledger_accounts WHERE character_id = $1 AND currency = $2"#,
+        character_id,
+        currency.as_str()
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch balance")?
+        .map(|r| r.balance)
+        .unwrap_or(0);
+    Ok(balance)
+}
+
+#[tracing::instrument(
+name = "Get character balances",
+skip(executor)
+)]
+pub async fn get_balances(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+) -> Result<Vec<(Currency, i64)>, anyhow::Error> {
+    sqlx::query!(
+        r#"SELECT currency, balance FROM ledger_accounts WHERE character_id = $1 ORDER BY currency"#,
+        character_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch balances")?
+        .into_iter()
+        .map(|r| {
+            let currency = Currency::try_from(r.currency).map_err(|e| anyhow!(e))?;
+            Ok((currency, r.balance))
+        })
+        .collect()
+}
+
+#[tracing::instrument(
+name = "Get ledger account reconciliation",
+skip(executor)
+)]
+pub async fn get_account_reconciliation(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<AccountReconciliation>, anyhow::Error> {
+    sqlx::query!(
+        r#"
+        SELECT a.id, a.character_id, a.system_name, a.currency, a.balance,
+               coalesce(sum(e.amount), 0)::BIGINT as "ledger_balance!",
+               count(e.id) as "entries!"
+        FROM ledger_accounts a
+        LEFT JOIN ledger_entries e ON e.account_id = a.id
+        GROUP BY a.id
+        ORDER BY a.currency, a.system_name NULLS LAST, a.character_id
+        "#
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to reconcile ledger accounts")?
+        .into_iter()
+        .map(|r| {
+            let account = match (r.character_id, r.system_name) {
+                (Some(id), _) => LedgerAccount::Character(id),
+                (None, Some(name)) => LedgerAccount::System(SystemAccount::try_from(name).map_err(|e| anyhow!(e))?),
+                (None, None) => return Err(anyhow!("Ledger account {} has no owner", r.id)),
+            };
+            Ok(AccountReconciliation {
+                account,
+                currency: Currency::try_from(r.currency).map_err(|e| anyhow!(e))?,
+                balance: r.balance,
+                ledger_balance: r.ledger_balance,
+                entries: r.entries,
+            })
+        })
+        .collect()
+}
+
+/// Transfers whose entries don't cancel each other out, this should always be empty
+#[tracing::instrument(
+name = "Get unbalanced ledger transfers",
+skip(executor)
+)]
+pub async fn get_unbalanced_transfers(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<Uuid>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT t.id
+        FROM ledger_transfers t
+        LEFT JOIN ledger_entries e ON e.transfer_id = t.id
+        GROUP BY t.id
+        HAVING count(e.id) <> 2 OR coalesce(sum(e.amount), 0) <> 0
+            OR coalesce(max(e.amount), 0) <> max(t.amount)
+        "#
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to check ledger transfers")?;
+    Ok(rows.into_iter().map(|r| r.id).collect())
+}
@@ -0,0 +1,120 @@
+use crate::ledger::{Currency, LedgerAccount};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    pub from: LedgerAccount,
+    pub to: LedgerAccount,
+    pub currency: Currency,
+    pub amount: i64,
+    pub reason: String,
+    /// Making the transfer again with the same key returns the original transfer instead of paying
+    /// twice, for server side retries like quest rewards and forms the client sent twice. Keys are
+    /// per debiting account.
+    pub idempotency_key: Option<String>,
+}
+
+impl Transfer {
+    pub fn new(from: LedgerAccount, to: LedgerAccount, currency: Currency, amount: i64, reason: &str) -> Self {
+        Transfer {
+            from,
+            to,
+            currency,
+            amount,
+            reason: reason.to_string(),
+            idempotency_key: None,
+        }
+    }
+
+    pub fn idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Keys the transfer by the request the client sent, if it sent a key. `scope` tells apart the
+    /// actions one request key may be used for.
+    pub fn request_key(self, scope: &str, key: Option<&str>) -> Self {
+        match key {
+            Some(key) => self.idempotency_key(format!("{}:{}", scope, key)),
+            None => self,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.amount <= 0 {
+            return Err(format!("{} is not a valid amount", self.amount));
+        }
+        if self.from == self.to {
+            return Err("Can't transfer to the same account".to_string());
+        }
+        if self.reason.trim().is_empty() {
+            return Err("Transfers need a reason".to_string());
+        }
+        if self.idempotency_key.as_ref().is_some_and(|k| k.trim().is_empty() || k.len() > 100) {
+            return Err("Idempotency keys must be between 1 and 100 characters".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferOutcome {
+    Applied(uuid::Uuid),
+    /// The idempotency key was used before, nothing moved this time
+    AlreadyApplied(uuid::Uuid),
+}
+
+impl TransferOutcome {
+    pub fn transfer_id(&self) -> uuid::Uuid {
+        match self {
+            TransferOutcome::Applied(id) | TransferOutcome::AlreadyApplied(id) => *id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use uuid::Uuid;
+    use crate::ledger::{Currency, LedgerAccount, SystemAccount, Transfer};
+
+    fn transfer(amount: i64) -> Transfer {
+        Transfer::new(
+            LedgerAccount::System(SystemAccount::Rewards),
+            LedgerAccount::Character(Uuid::new_v4()),
+            Currency::Gold,
+            amount,
+            "quest reward",
+        )
+    }
+
+    #[test]
+    fn positive_amount_is_valid() {
+        assert_ok!(transfer(10).validate());
+    }
+
+    #[test]
+    fn zero_and_negative_amounts_are_rejected() {
+        assert_err!(transfer(0).validate());
+        assert_err!(transfer(-5).validate());
+    }
+
+    #[test]
+    fn transfer_to_self_is_rejected() {
+        let mut t = transfer(10);
+        t.to = t.from;
+        assert_err!(t.validate());
+    }
+
+    #[test]
+    fn blank_idempotency_key_is_rejected() {
+        assert_err!(transfer(10).idempotency_key(" ").validate());
+        assert_err!(transfer(10).idempotency_key("k".repeat(101)).validate());
+        assert_ok!(transfer(10).idempotency_key("retry-1").validate());
+    }
+
+    #[test]
+    fn request_keys_are_scoped_to_the_action() {
+        assert_eq!(Some("bid:abc".to_string()), transfer(10).request_key("bid", Some("abc")).idempotency_key);
+        assert_eq!(None, transfer(10).request_key("bid", None).idempotency_key);
+    }
+}
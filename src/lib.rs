@@ -7,4 +7,28 @@ pub mod routes;
 pub mod domain;
 pub mod email_client;
 pub mod store;
-pub mod helpers;
\ No newline at end of file
+pub mod helpers;
+pub mod characters;
+pub mod game_data;
+pub mod items;
+pub mod ledger;
+pub mod gateway;
+pub mod audit;
+pub mod chat;
+pub mod game_loop;
+pub mod events;
+pub mod world;
+pub mod combat;
+pub mod quests;
+pub mod leaderboards;
+pub mod guilds;
+pub mod presence;
+pub mod friends;
+pub mod mail;
+pub mod auctions;
+pub mod crafting;
+pub mod achievements;
+pub mod matchmaking;
+pub mod parties;
+pub mod npcs;
+pub mod loot;
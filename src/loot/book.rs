@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::game_data::{data_directory, load_data_files, GameDataError};
+use crate::items::ItemCatalog;
+use crate::loot::{LootRoller, LootTable};
+
+#[derive(serde::Deserialize)]
+struct LootFile {
+    #[serde(default)]
+    tables: Vec<LootTable>,
+}
+
+/// Every loot table from `data/loot`. Nested tables are checked to exist and never to roll
+/// themselves again, items by `check_references` once the item catalog is loaded.
+#[derive(Debug, Default)]
+pub struct LootBook {
+    tables: HashMap<String, LootTable>,
+}
+
+impl LootBook {
+    pub fn new(tables: Vec<LootTable>) -> Result<Self, GameDataError> {
+        let mut book = LootBook::default();
+        for table in tables {
+            table.validate().map_err(GameDataError::Invalid)?;
+            if book.tables.contains_key(&table.id) {
+                return Err(GameDataError::Invalid(format!("Loot table {} is defined twice", table.id)));
+            }
+            book.tables.insert(table.id.clone(), table);
+        }
+        let mut ids: Vec<&String> = book.tables.keys().collect();
+        ids.sort();
+        for id in ids {
+            let table = &book.tables[id];
+            if let Some(nested) = table.nested().find(|n| !book.tables.contains_key(*n)) {
+                return Err(GameDataError::Invalid(format!("Loot table {} rolls unknown table {}", id, nested)));
+            }
+            if book.rolls_itself(id, id, &mut Vec::new()) {
+                return Err(GameDataError::Invalid(format!("Loot table {} ends up rolling itself", id)));
+            }
+        }
+        Ok(book)
+    }
+
+    pub fn load(directory: &Path) -> Result<Self, GameDataError> {
+        let mut tables = Vec::new();
+        for (_, file) in load_data_files::<LootFile>(directory)? {
+            tables.extend(file.tables);
+        }
+        Self::new(tables)
+    }
+
+    fn rolls_itself<'a>(&'a self, id: &str, from: &'a str, seen: &mut Vec<&'a str>) -> bool {
+        if seen.contains(&from) {
+            return false;
+        }
+        seen.push(from);
+        self.tables.get(from).is_some_and(|table| {
+            table.nested().any(|nested| nested == id || self.rolls_itself(id, nested, seen))
+        })
+    }
+
+    /// Items that don't exist and pity that can never be paid out, empty when all is well
+    pub fn check_references(&self, items: &ItemCatalog) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut tables: Vec<&LootTable> = self.tables.values().collect();
+        tables.sort_by_key(|t| t.id.as_str());
+        for table in &tables {
+            let drops = table.guaranteed.iter().chain(table.entries.iter().map(|e| &e.drop));
+            for item in drops.filter_map(|d| d.item.as_ref()).filter(|i| !items.contains(i)) {
+                problems.push(format!("Loot table {} drops unknown item {}", table.id, item));
+            }
+        }
+        if !problems.is_empty() {
+            return problems;
+        }
+        let roller = LootRoller::new(self, items);
+        for table in tables {
+            if let Some(pity) = table.pity.filter(|p| !roller.can_drop_table(&table.id, p.rarity)) {
+                problems.push(format!("Loot table {} has pity for {} items but can't drop any", table.id, pity.rarity.as_str()));
+            }
+        }
+        problems
+    }
+
+    pub fn get(&self, id: &str) -> Option<&LootTable> {
+        self.tables.get(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.tables.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+}
+
+pub fn get_loot_book() -> Result<LootBook, GameDataError> {
+    LootBook::load(&data_directory().join("loot"))
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use uuid::Uuid;
+    use crate::items::get_item_catalog;
+    use crate::loot::{get_loot_book, LootBook};
+
+    fn load(content: &str) -> Result<LootBook, crate::game_data::GameDataError> {
+        let directory = std::env::temp_dir().join(format!("yaug-loot-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("loot.toml"), content).unwrap();
+        let book = LootBook::load(&directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+        book
+    }
+
+    fn table(id: &str, entries: &str) -> String {
+        format!("[[tables]]\nid = \"{}\"\nentries = [{}]\n", id, entries)
+    }
+
+    #[test]
+    fn shipped_loot_tables_reference_existing_items() {
+        let book = assert_ok!(get_loot_book());
+        assert!(!book.is_empty());
+        let problems = book.check_references(&get_item_catalog().unwrap());
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn nested_tables_exist_and_never_loop() {
+        let gems = table("gems", "{ weight = 1, item = \"copper_ring\" }");
+        let chest = table("chest", "{ weight = 1, table = \"gems\" }, { weight = 3, item = \"bread\" }");
+        assert_ok!(load(&format!("{}{}", chest, gems)));
+        assert_err!(load(&chest));
+        let looping = table("gems", "{ weight = 1, table = \"pouch\" }");
+        let pouch = table("pouch", "{ weight = 1, table = \"chest\" }");
+        assert_err!(load(&format!("{}{}{}", chest, looping, pouch)));
+        assert_err!(load(&format!("{}{}", gems, gems)));
+    }
+
+    #[test]
+    fn pity_has_to_be_payable() {
+        let items = get_item_catalog().unwrap();
+        let pity = "pity = { rarity = \"uncommon\", after = 5 }\n";
+        let book = load(&format!("{}{}", table("chest", "{ weight = 9, item = \"bread\" }, { weight = 1, item = \"iron_sword\" }"), pity)).unwrap();
+        assert!(book.check_references(&items).is_empty());
+        let book = load(&format!("{}{}", table("chest", "{ weight = 1, item = \"bread\" }"), pity)).unwrap();
+        assert_eq!(1, book.check_references(&items).len());
+        let book = load(&table("chest", "{ weight = 1, item = \"dragon_egg\" }")).unwrap();
+        assert_eq!(1, book.check_references(&items).len());
+    }
+}
@@ -0,0 +1,12 @@
+mod book;
+mod roll;
+mod service;
+mod simulation;
+mod store;
+mod table;
+
+pub use book::{get_loot_book, LootBook};
+pub use roll::{LootRoller, PityCounters};
+pub use service::LootService;
+pub use simulation::{simulate, Simulation, SimulatedDrop};
+pub use table::{LootDrop, LootEntry, LootTable, Pity};
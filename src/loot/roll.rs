@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use rand::Rng;
+use crate::items::{ItemCatalog, ItemStack, Rarity};
+use crate::loot::{LootBook, LootDrop, LootTable, Pity};
+
+/// How many times in a row one player rolled each table with pity without getting lucky,
+/// tables they never rolled or just got lucky on are left out
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PityCounters {
+    misses: HashMap<String, i32>,
+}
+
+impl PityCounters {
+    pub fn new(misses: HashMap<String, i32>) -> Self {
+        PityCounters { misses }
+    }
+
+    pub fn misses(&self, table_id: &str) -> i32 {
+        self.misses.get(table_id).copied().unwrap_or(0)
+    }
+
+    /// Every table that is counting, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item=(&str, i32)> {
+        self.misses.iter().map(|(id, misses)| (id.as_str(), *misses))
+    }
+
+    fn record(&mut self, table_id: &str, lucky: bool) {
+        if lucky {
+            self.misses.remove(table_id);
+        } else {
+            *self.misses.entry(table_id.to_string()).or_default() += 1;
+        }
+    }
+}
+
+/// Rolls the tables of a book, looking up item rarities in the catalog. All randomness comes from
+/// the rng handed in, so the same seed and counters always drop the same items.
+pub struct LootRoller<'a> {
+    book: &'a LootBook,
+    items: &'a ItemCatalog,
+}
+
+impl<'a> LootRoller<'a> {
+    pub fn new(book: &'a LootBook, items: &'a ItemCatalog) -> Self {
+        LootRoller { book, items }
+    }
+
+    pub fn table_pity(&self, table_id: &str) -> Option<Pity> {
+        self.book.get(table_id).and_then(|t| t.pity)
+    }
+
+    pub fn rarity(&self, item_id: &str) -> Rarity {
+        self.items.get(item_id).map(|i| i.rarity).unwrap_or_default()
+    }
+
+    /// Whether rolling the table can ever give something of `rarity` or better
+    pub fn can_drop_table(&self, table_id: &str, rarity: Rarity) -> bool {
+        self.book.get(table_id).is_some_and(|table| {
+            let picked = table.entries.iter().filter(|e| table.rolls > 0 && e.weight > 0).map(|e| &e.drop);
+            table.guaranteed.iter().chain(picked).any(|d| self.can_drop(d, rarity))
+        })
+    }
+
+    fn can_drop(&self, drop: &LootDrop, rarity: Rarity) -> bool {
+        match (&drop.item, &drop.table) {
+            (Some(item), _) => self.rarity(item) >= rarity,
+            (_, Some(table)) => self.can_drop_table(table, rarity),
+            _ => false,
+        }
+    }
+
+    /// Rolls the table once for a player and counts their pity, stacks of the same item are
+    /// merged. Unknown tables drop nothing.
+    pub fn roll(&self, table_id: &str, rng: &mut impl Rng, pity: &mut PityCounters) -> Vec<ItemStack> {
+        let mut dropped: Vec<ItemStack> = Vec::new();
+        if let Some(table) = self.book.get(table_id) {
+            self.roll_table(table, None, rng, pity, &mut dropped);
+        }
+        dropped
+    }
+
+    /// `need` is a rarity one of the drops has to reach, it is passed on to whichever guaranteed
+    /// drop or picked entry can give it
+    fn roll_table(
+        &self,
+        table: &LootTable,
+        need: Option<Rarity>,
+        rng: &mut impl Rng,
+        pity: &mut PityCounters,
+        dropped: &mut Vec<ItemStack>,
+    ) {
+        let owed = table.pity.filter(|p| pity.misses(&table.id) >= p.after).map(|p| p.rarity);
+        // the rarer of the two wins when the table can give it, "or better" covers the other
+        let mut need = need.into_iter()
+            .chain(owed)
+            .filter(|r| self.can_drop_table(&table.id, *r))
+            .max();
+        let start = dropped.len();
+
+        for drop in &table.guaranteed {
+            let passed = need.filter(|r| self.can_drop(drop, *r));
+            need = need.filter(|_| passed.is_none());
+            self.apply(drop, passed, rng, pity, dropped);
+        }
+        let total = table.total_weight();
+        for _ in 0..table.rolls {
+            let passed = need.take();
+            let candidates = |drop: &LootDrop| passed.is_none_or(|r| self.can_drop(drop, r));
+            let weight: u32 = table.entries.iter().filter(|e| candidates(&e.drop)).map(|e| e.weight).sum();
+            let mut pick = rng.gen_range(0..if passed.is_some() { weight } else { total });
+            let entry = table.entries
+                .iter()
+                .filter(|e| candidates(&e.drop))
+                .find(|e| {
+                    if pick < e.weight {
+                        return true;
+                    }
+                    pick -= e.weight;
+                    false
+                })
+                .expect("The pick is below the total weight");
+            self.apply(&entry.drop, passed, rng, pity, dropped);
+        }
+
+        if let Some(p) = table.pity {
+            let lucky = dropped[start..].iter().any(|s| self.rarity(&s.item_id) >= p.rarity);
+            pity.record(&table.id, lucky);
+        }
+    }
+
+    fn apply(
+        &self,
+        drop: &LootDrop,
+        need: Option<Rarity>,
+        rng: &mut impl Rng,
+        pity: &mut PityCounters,
+        dropped: &mut Vec<ItemStack>,
+    ) {
+        if let Some(table) = drop.table.as_ref().and_then(|t| self.book.get(t)) {
+            return self.roll_table(table, need, rng, pity, dropped);
+        }
+        let Some(item_id) = &drop.item else { return };
+        let quantity = rng.gen_range(drop.quantity..=drop.max());
+        match dropped.iter_mut().find(|s| &s.item_id == item_id) {
+            Some(stack) => stack.quantity += quantity,
+            None => dropped.push(ItemStack { item_id: item_id.clone(), quantity }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::items::{get_item_catalog, Rarity};
+    use crate::loot::{LootBook, LootDrop, LootEntry, LootRoller, LootTable, Pity, PityCounters};
+
+    fn item(id: &str) -> LootDrop {
+        LootDrop { item: Some(id.to_string()), ..LootDrop::default() }
+    }
+
+    fn table(id: &str, entries: Vec<(u32, LootDrop)>) -> LootTable {
+        LootTable {
+            id: id.to_string(),
+            rolls: 1,
+            guaranteed: vec![],
+            entries: entries.into_iter().map(|(weight, drop)| LootEntry { weight, drop }).collect(),
+            pity: None,
+        }
+    }
+
+    #[test]
+    fn guaranteed_drops_come_on_top_of_every_roll_and_stacks_merge() {
+        let items = get_item_catalog().unwrap();
+        let bread = LootDrop { quantity: 2, max_quantity: Some(4), ..item("bread") };
+        let chest = LootTable { rolls: 3, guaranteed: vec![item("silverleaf")], ..table("chest", vec![(1, bread)]) };
+        let book = LootBook::new(vec![chest]).unwrap();
+        let roller = LootRoller::new(&book, &items);
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let dropped = roller.roll("chest", &mut rng, &mut PityCounters::default());
+            assert_eq!(2, dropped.len());
+            assert_eq!(("silverleaf", 1), (dropped[0].item_id.as_str(), dropped[0].quantity));
+            assert!((6..=12).contains(&dropped[1].quantity));
+        }
+        assert!(roller.roll("nothing", &mut rng, &mut PityCounters::default()).is_empty());
+    }
+
+    #[test]
+    fn pity_forces_a_rare_drop_after_enough_misses_even_from_a_nested_table() {
+        let items = get_item_catalog().unwrap();
+        let gems = table("gems", vec![(99, item("bread")), (1, item("copper_ring"))]);
+        let chest = LootTable {
+            pity: Some(Pity { rarity: Rarity::Uncommon, after: 3 }),
+            ..table("chest", vec![(1000, LootDrop::default()), (1, LootDrop { table: Some("gems".to_string()), ..LootDrop::default() })])
+        };
+        let book = LootBook::new(vec![chest, gems]).unwrap();
+        let roller = LootRoller::new(&book, &items);
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut pity = PityCounters::default();
+
+        for misses in 1..=3 {
+            assert!(roller.roll("chest", &mut rng, &mut pity).is_empty());
+            assert_eq!(misses, pity.misses("chest"));
+        }
+        let dropped = roller.roll("chest", &mut rng, &mut pity);
+        assert_eq!("copper_ring", dropped[0].item_id);
+        assert_eq!(0, pity.misses("chest"));
+        assert_eq!(0, pity.iter().count());
+    }
+
+    #[test]
+    fn the_same_seed_drops_the_same_items() {
+        let items = get_item_catalog().unwrap();
+        let book = LootBook::new(vec![table("chest", vec![(1, item("bread")), (1, item("iron_ore")), (1, item("flour"))])]).unwrap();
+        let roller = LootRoller::new(&book, &items);
+        let rolls = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..20).map(|_| roller.roll("chest", &mut rng, &mut PityCounters::default())).collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(3), rolls(3));
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::items::{InventoryService, ItemStack};
+use crate::loot::{LootBook, LootRoller};
+use crate::loot::store::{get_pity, lock_pity, store_misses};
+
+/// Rolls loot tables for characters, keeping their pity counters in the database so bad luck
+/// carries over between sessions
+pub struct LootService {
+    pool: PgPool,
+    inventory: Arc<InventoryService>,
+    book: Arc<LootBook>,
+}
+
+impl LootService {
+    pub fn new(pool: PgPool, inventory: Arc<InventoryService>, book: Arc<LootBook>) -> Self {
+        LootService { pool, inventory, book }
+    }
+
+    pub fn book(&self) -> &LootBook {
+        &self.book
+    }
+
+    pub fn roller(&self) -> LootRoller<'_> {
+        LootRoller::new(&self.book, self.inventory.catalog())
+    }
+
+    /// Rolls the table once for the character, nothing drops for characters that are gone.
+    /// The items are only rolled, handing them out is up to the caller.
+    #[tracing::instrument(
+    name = "Roll loot",
+    skip(self)
+    )]
+    pub async fn roll(&self, character_id: Uuid, table_id: &str) -> Result<Vec<ItemStack>, anyhow::Error> {
+        let mut tx = self.pool.begin().await.context("Failed to start loot transaction")?;
+        let dropped = self.roll_in(&mut tx, character_id, table_id).await?;
+        tx.commit().await.context("Failed to commit loot transaction")?;
+        Ok(dropped)
+    }
+
+    /// Like `roll` inside the caller's transaction, so the pity only moves when whatever hands
+    /// out the items commits too
+    #[tracing::instrument(
+    name = "Roll loot in transaction",
+    skip(self, tx)
+    )]
+    pub async fn roll_in(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        character_id: Uuid,
+        table_id: &str,
+    ) -> Result<Vec<ItemStack>, anyhow::Error> {
+        if !lock_pity(tx, character_id).await? {
+            return Ok(Vec::new());
+        }
+        let before = get_pity(tx, character_id).await?;
+        let mut after = before.clone();
+        let dropped = self.roller().roll(table_id, &mut rand::thread_rng(), &mut after);
+
+        let mut changed: Vec<&str> = after.iter().map(|(id, _)| id).chain(before.iter().map(|(id, _)| id)).collect();
+        changed.sort_unstable();
+        changed.dedup();
+        for table_id in changed.into_iter().filter(|id| before.misses(id) != after.misses(id)) {
+            store_misses(tx, character_id, table_id, after.misses(table_id)).await?;
+        }
+        Ok(dropped)
+    }
+}
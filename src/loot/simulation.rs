@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use crate::items::Rarity;
+use crate::loot::{LootRoller, PityCounters};
+
+/// How often one item dropped over a simulation
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedDrop {
+    pub item_id: String,
+    pub rarity: Rarity,
+    /// Rolls that dropped the item at all
+    pub rolls: u64,
+    pub quantity: u64,
+}
+
+/// What rolling one table over and over gave, for checking drop rates against what was intended
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    pub table_id: String,
+    pub rolls: u64,
+    pub seed: u64,
+    /// Whether every roll was made by the same player, so pity kicked in
+    pub pity: bool,
+    /// Most common first
+    pub drops: Vec<SimulatedDrop>,
+    /// Rolls that dropped at least one item of each rarity
+    pub rarities: Vec<(Rarity, u64)>,
+    pub empty_rolls: u64,
+    /// Most rolls in a row without anything of the table's pity rarity, none without pity
+    pub longest_dry_streak: Option<u64>,
+}
+
+impl Simulation {
+    /// Share of the rolls that dropped the item, 0 to 1
+    pub fn rate(&self, item_id: &str) -> f64 {
+        let rolls = self.drops.iter().find(|d| d.item_id == item_id).map_or(0, |d| d.rolls);
+        rolls as f64 / self.rolls.max(1) as f64
+    }
+}
+
+/// Rolls `table_id` `rolls` times from one `StdRng` seeded with `seed`. With `pity` every roll is
+/// made by the same player, without it every roll comes with fresh counters.
+pub fn simulate(roller: &LootRoller, table_id: &str, rolls: u64, seed: u64, pity: bool) -> Simulation {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut counters = PityCounters::default();
+    let pity_rarity = roller.table_pity(table_id).map(|p| p.rarity);
+    let mut drops: HashMap<String, SimulatedDrop> = HashMap::new();
+    let mut rarities: BTreeMap<Rarity, u64> = BTreeMap::new();
+    let (mut empty_rolls, mut dry, mut longest_dry) = (0, 0, 0);
+
+    for _ in 0..rolls {
+        if !pity {
+            counters = PityCounters::default();
+        }
+        let dropped = roller.roll(table_id, &mut rng, &mut counters);
+        if dropped.is_empty() {
+            empty_rolls += 1;
+        }
+        let mut seen = Vec::new();
+        for stack in dropped {
+            let rarity = roller.rarity(&stack.item_id);
+            if !seen.contains(&rarity) {
+                seen.push(rarity);
+                *rarities.entry(rarity).or_default() += 1;
+            }
+            let drop = drops.entry(stack.item_id.clone()).or_insert_with(|| SimulatedDrop {
+                item_id: stack.item_id,
+                rarity,
+                rolls: 0,
+                quantity: 0,
+            });
+            drop.rolls += 1;
+            drop.quantity += stack.quantity as u64;
+        }
+        if let Some(wanted) = pity_rarity {
+            dry = if seen.iter().any(|r| *r >= wanted) { 0 } else { dry + 1 };
+            longest_dry = longest_dry.max(dry);
+        }
+    }
+
+    let mut drops: Vec<SimulatedDrop> = drops.into_values().collect();
+    drops.sort_by(|a, b| b.rolls.cmp(&a.rolls).then_with(|| a.item_id.cmp(&b.item_id)));
+    Simulation {
+        table_id: table_id.to_string(),
+        rolls,
+        seed,
+        pity,
+        drops,
+        rarities: rarities.into_iter().collect(),
+        empty_rolls,
+        longest_dry_streak: pity_rarity.map(|_| longest_dry),
+    }
+}
+
+impl Display for Simulation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let share = |count: u64| 100.0 * count as f64 / self.rolls.max(1) as f64;
+        writeln!(
+            f,
+            "{} rolls of {} with seed {}, {}",
+            self.rolls, self.table_id, self.seed, if self.pity { "as one player" } else { "without pity" }
+        )?;
+        writeln!(f, "{:<24} {:<10} {:>9} {:>10}", "item", "rarity", "dropped", "avg qty")?;
+        for drop in &self.drops {
+            writeln!(
+                f,
+                "{:<24} {:<10} {:>8.4}% {:>10.2}",
+                drop.item_id, drop.rarity.as_str(), share(drop.rolls), drop.quantity as f64 / drop.rolls as f64
+            )?;
+        }
+        writeln!(f, "{:<35} {:>8.4}%", "nothing", share(self.empty_rolls))?;
+        for (rarity, count) in &self.rarities {
+            writeln!(f, "{:<35} {:>8.4}%", format!("any {}", rarity.as_str()), share(*count))?;
+        }
+        if let Some(streak) = self.longest_dry_streak {
+            writeln!(f, "longest dry streak: {} rolls", streak)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::items::{get_item_catalog, Rarity};
+    use crate::loot::{get_loot_book, simulate, LootBook, LootDrop, LootEntry, LootRoller, LootTable, Pity};
+
+    fn entry(weight: u32, item: &str) -> LootEntry {
+        LootEntry { weight, drop: LootDrop { item: Some(item.to_string()), ..LootDrop::default() } }
+    }
+
+    fn chest(pity: Option<Pity>) -> LootTable {
+        LootTable {
+            id: "chest".to_string(),
+            rolls: 1,
+            guaranteed: vec![],
+            entries: vec![entry(70, "bread"), entry(25, "iron_ore"), entry(4, "copper_ring"), entry(1, "iron_sword")],
+            pity,
+        }
+    }
+
+    #[test]
+    fn a_million_rolls_land_on_the_weights() {
+        let items = get_item_catalog().unwrap();
+        let book = LootBook::new(vec![chest(None)]).unwrap();
+        let simulation = simulate(&LootRoller::new(&book, &items), "chest", 1_000_000, 42, false);
+        for (item, expected) in [("bread", 0.70), ("iron_ore", 0.25), ("copper_ring", 0.04), ("iron_sword", 0.01)] {
+            let observed = simulation.rate(item);
+            assert!((observed - expected).abs() < 0.002, "{} dropped {} of the time, expected {}", item, observed, expected);
+        }
+        assert_eq!(0, simulation.empty_rolls);
+        assert_eq!(None, simulation.longest_dry_streak);
+        assert_eq!(simulation, simulate(&LootRoller::new(&book, &items), "chest", 1_000_000, 42, false));
+    }
+
+    #[test]
+    fn pity_caps_the_dry_streak_and_raises_the_rate() {
+        let items = get_item_catalog().unwrap();
+        let book = LootBook::new(vec![chest(Some(Pity { rarity: Rarity::Uncommon, after: 10 }))]).unwrap();
+        let roller = LootRoller::new(&book, &items);
+        let with = simulate(&roller, "chest", 100_000, 42, true);
+        let without = simulate(&roller, "chest", 100_000, 42, false);
+        assert!(with.longest_dry_streak.unwrap() <= 10);
+        assert!(without.longest_dry_streak.unwrap() > 10);
+        assert!(with.rate("copper_ring") > without.rate("copper_ring"));
+    }
+
+    #[test]
+    fn shipped_tables_simulate_and_report() {
+        let items = get_item_catalog().unwrap();
+        let book = get_loot_book().unwrap();
+        let simulation = simulate(&LootRoller::new(&book, &items), "forest_spider", 10_000, 1, true);
+        assert!(!simulation.drops.is_empty());
+        assert!(simulation.to_string().contains("forest_spider"));
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use anyhow::Context;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use crate::loot::PityCounters;
+
+/// Locks the character's row so two rolls of theirs count pity one after the other, false when
+/// the character is gone
+#[tracing::instrument(
+name = "Lock loot pity",
+skip(tx)
+)]
+pub async fn lock_pity(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let locked = sqlx::query!(
+        r#"SELECT id FROM characters WHERE id = $1 AND deleted_at IS NULL FOR UPDATE"#,
+        character_id
+    )
+        .fetch_optional(tx)
+        .await
+        .context("Failed to lock loot pity")?
+        .is_some();
+    Ok(locked)
+}
+
+pub async fn get_pity(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+) -> Result<PityCounters, anyhow::Error> {
+    let misses: HashMap<String, i32> = sqlx::query!(
+        "SELECT table_id, misses FROM character_loot_pity WHERE character_id = $1",
+        character_id
+    )
+        .fetch_all(tx)
+        .await
+        .context("Failed to fetch loot pity")?
+        .into_iter()
+        .map(|r| (r.table_id, r.misses))
+        .collect();
+    Ok(PityCounters::new(misses))
+}
+
+/// No misses deletes the row, the table is not counting anymore
+#[tracing::instrument(
+name = "Store loot pity",
+skip(tx)
+)]
+pub async fn store_misses(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    table_id: &str,
+    misses: i32,
+) -> Result<(), anyhow::Error> {
+    if misses == 0 {
+        sqlx::query!(
+            "DELETE FROM character_loot_pity WHERE character_id = $1 AND table_id = $2",
+            character_id,
+            table_id
+        )
+            .execute(tx)
+            .await
+            .context("Failed to reset loot pity")?;
+        return Ok(());
+    }
+    sqlx::query!(
+        r#"
+        INSERT INTO character_loot_pity (character_id, table_id, misses)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (character_id, table_id) DO UPDATE SET misses = excluded.misses
+        "#,
+        character_id,
+        table_id,
+        misses
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store loot pity")?;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use crate::game_data::is_valid_id;
+use crate::items::Rarity;
+
+fn one() -> i32 {
+    1
+}
+
+fn one_roll() -> u32 {
+    1
+}
+
+/// An item, a nested table to roll, or with neither nothing at all
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct LootDrop {
+    #[serde(default)]
+    pub item: Option<String>,
+    #[serde(default)]
+    pub table: Option<String>,
+    #[serde(default = "one")]
+    pub quantity: i32,
+    /// Drops between `quantity` and this many, exactly `quantity` when unset
+    #[serde(default)]
+    pub max_quantity: Option<i32>,
+}
+
+impl Default for LootDrop {
+    fn default() -> Self {
+        LootDrop { item: None, table: None, quantity: one(), max_quantity: None }
+    }
+}
+
+impl LootDrop {
+    pub fn max(&self) -> i32 {
+        self.max_quantity.unwrap_or(self.quantity)
+    }
+
+    fn validate(&self, table_id: &str) -> Result<(), String> {
+        if self.item.is_some() && self.table.is_some() {
+            return Err(format!("Loot table {} has a drop with both an item and a table", table_id));
+        }
+        if let Some(id) = self.item.iter().chain(&self.table).find(|id| !is_valid_id(id)) {
+            return Err(format!("Loot table {}: {:?} is not a valid item or table id", table_id, id));
+        }
+        if self.quantity <= 0 || self.max() < self.quantity {
+            return Err(format!("Loot table {} has a drop with a quantity out of range", table_id));
+        }
+        Ok(())
+    }
+}
+
+/// One of the entries a roll picks from, the chance is its weight over the table's total weight
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct LootEntry {
+    pub weight: u32,
+    #[serde(flatten)]
+    pub drop: LootDrop,
+}
+
+/// Bad luck protection: once a player rolled the table `after` times in a row without getting
+/// anything of `rarity` or better, the next roll only picks what can give them one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+pub struct Pity {
+    pub rarity: Rarity,
+    pub after: i32,
+}
+
+/// One loot table as written in `data/loot`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct LootTable {
+    pub id: String,
+    /// Entries picked on every roll of the table, one at a time
+    #[serde(default = "one_roll")]
+    pub rolls: u32,
+    /// Dropped on every roll of the table on top of the picked entries
+    #[serde(default)]
+    pub guaranteed: Vec<LootDrop>,
+    #[serde(default)]
+    pub entries: Vec<LootEntry>,
+    #[serde(default)]
+    pub pity: Option<Pity>,
+}
+
+impl LootTable {
+    pub fn total_weight(&self) -> u32 {
+        self.entries.iter().map(|e| e.weight).sum()
+    }
+
+    /// Every table rolled from this one
+    pub fn nested(&self) -> impl Iterator<Item=&str> {
+        self.guaranteed.iter().chain(self.entries.iter().map(|e| &e.drop)).filter_map(|d| d.table.as_deref())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_id(&self.id) {
+            return Err(format!("{:?} is not a valid loot table id", self.id));
+        }
+        if self.rolls > 0 && self.total_weight() == 0 {
+            return Err(format!("Loot table {} rolls {} times but has no weighted entries", self.id, self.rolls));
+        }
+        if self.rolls == 0 && self.guaranteed.is_empty() {
+            return Err(format!("Loot table {} never drops anything", self.id));
+        }
+        for drop in self.guaranteed.iter().chain(self.entries.iter().map(|e| &e.drop)) {
+            drop.validate(&self.id)?;
+        }
+        if self.guaranteed.iter().any(|d| d.item.is_none() && d.table.is_none()) {
+            return Err(format!("Loot table {} guarantees a drop of nothing", self.id));
+        }
+        if self.pity.is_some_and(|p| p.after < 1) {
+            return Err(format!("Loot table {} needs at least one roll before pity kicks in", self.id));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use crate::items::Rarity;
+    use crate::loot::{LootDrop, LootEntry, LootTable, Pity};
+
+    fn item(id: &str) -> LootDrop {
+        LootDrop { item: Some(id.to_string()), ..LootDrop::default() }
+    }
+
+    fn entry(weight: u32, drop: LootDrop) -> LootEntry {
+        LootEntry { weight, drop }
+    }
+
+    fn chest(entries: Vec<LootEntry>) -> LootTable {
+        LootTable { id: "chest".to_string(), rolls: 1, guaranteed: vec![], entries, pity: None }
+    }
+
+    #[test]
+    fn entries_drop_an_item_a_table_or_nothing() {
+        let gems = LootDrop { table: Some("gems".to_string()), ..LootDrop::default() };
+        let bread = LootDrop { max_quantity: Some(2), ..item("bread") };
+        let table = chest(vec![entry(3, LootDrop::default()), entry(1, bread), entry(1, gems)]);
+        assert_ok!(table.validate());
+        assert_eq!(5, table.total_weight());
+        assert_eq!(vec!["gems"], table.nested().collect::<Vec<_>>());
+        assert_eq!(2, table.entries[1].drop.max());
+
+        let both = LootDrop { table: Some("gems".to_string()), ..item("bread") };
+        assert_err!(chest(vec![entry(1, both)]).validate());
+        let backwards = LootDrop { quantity: 3, max_quantity: Some(2), ..item("bread") };
+        assert_err!(chest(vec![entry(1, backwards)]).validate());
+    }
+
+    #[test]
+    fn tables_have_to_drop_something() {
+        assert_err!(chest(vec![entry(0, item("bread"))]).validate());
+        assert_err!(LootTable { rolls: 0, ..chest(vec![]) }.validate());
+        assert_err!(LootTable { rolls: 0, guaranteed: vec![LootDrop { quantity: 2, ..LootDrop::default() }], ..chest(vec![]) }.validate());
+        assert_ok!(LootTable { rolls: 0, guaranteed: vec![item("bread")], ..chest(vec![]) }.validate());
+        let impatient = Pity { rarity: Rarity::Rare, after: 0 };
+        assert_err!(LootTable { pity: Some(impatient), ..chest(vec![entry(1, item("bread"))]) }.validate());
+    }
+}
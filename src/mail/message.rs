@@ -0,0 +1,145 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::items::ItemStack;
+use crate::ledger::Currency;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailKind {
+    /// Written by a player, goes back to them if it expires with attachments
+    Player,
+    /// A player mail that expired or was sent back, its attachments are lost if it expires again
+    Returned,
+    /// Cash on delivery paid by the recipient of a player mail
+    Payment,
+    /// Compensation, event rewards and anything else the game hands out
+    System,
+    /// Proceeds, purchases and unsold items from the auction house, already paid for
+    Auction,
+}
+
+impl MailKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MailKind::Player => "player",
+            MailKind::Returned => "returned",
+            MailKind::Payment => "payment",
+            MailKind::System => "system",
+            MailKind::Auction => "auction",
+        }
+    }
+}
+
+impl TryFrom<String> for MailKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "player" => Ok(MailKind::Player),
+            "returned" => Ok(MailKind::Returned),
+            "payment" => Ok(MailKind::Payment),
+            "system" => Ok(MailKind::System),
+            "auction" => Ok(MailKind::Auction),
+            other => Err(format!("{} is not a valid mail kind", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for MailKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub id: Uuid,
+    pub recipient_id: Uuid,
+    /// `None` for system mail
+    pub sender_id: Option<Uuid>,
+    pub sender_name: String,
+    pub kind: MailKind,
+    pub subject: String,
+    pub body: String,
+    pub money: Option<(Currency, i64)>,
+    /// Gold the recipient pays to take the attachments, 0 when it isn't cash on delivery
+    pub cod_amount: i64,
+    pub items: Vec<ItemStack>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+    pub claimed_at: Option<DateTime<Utc>>,
+}
+
+impl Mail {
+    /// Money or items that are still waiting to be taken
+    pub fn has_attachments(&self) -> bool {
+        self.claimed_at.is_none() && (self.money.is_some() || !self.items.is_empty())
+    }
+
+    /// Only mail a player wrote goes back, anything else would bounce forever
+    pub fn is_returnable(&self) -> bool {
+        self.kind == MailKind::Player && self.sender_id.is_some() && self.has_attachments()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use claim::{assert_err, assert_ok_eq};
+    use uuid::Uuid;
+    use crate::items::ItemStack;
+    use crate::ledger::Currency;
+    use crate::mail::{Mail, MailKind};
+
+    fn mail(kind: MailKind) -> Mail {
+        let now = Utc::now();
+        Mail {
+            id: Uuid::new_v4(),
+            recipient_id: Uuid::new_v4(),
+            sender_id: Some(Uuid::new_v4()),
+            sender_name: "Garrett".to_string(),
+            kind,
+            subject: "Loot".to_string(),
+            body: String::new(),
+            money: None,
+            cod_amount: 0,
+            items: vec![],
+            created_at: now,
+            expires_at: now + Duration::days(30),
+            read_at: None,
+            claimed_at: None,
+        }
+    }
+
+    #[test]
+    fn kinds_round_trip() {
+        for kind in [MailKind::Player, MailKind::Returned, MailKind::Payment, MailKind::System, MailKind::Auction] {
+            assert_ok_eq!(MailKind::try_from(kind.as_str().to_string()), kind);
+        }
+        assert_err!(MailKind::try_from("parcel".to_string()));
+    }
+
+    #[test]
+    fn claimed_mail_has_no_attachments_left() {
+        let mut m = mail(MailKind::Player);
+        assert!(!m.has_attachments());
+        m.money = Some((Currency::Gold, 10));
+        assert!(m.has_attachments());
+        m.money = None;
+        m.items.push(ItemStack { item_id: "potion".to_string(), quantity: 1 });
+        assert!(m.has_attachments());
+        m.claimed_at = Some(Utc::now());
+        assert!(!m.has_attachments());
+    }
+
+    #[test]
+    fn only_player_mail_with_attachments_is_returned() {
+        let mut m = mail(MailKind::Player);
+        m.money = Some((Currency::Gold, 10));
+        assert!(m.is_returnable());
+        for kind in [MailKind::Returned, MailKind::Payment, MailKind::System, MailKind::Auction] {
+            m.kind = kind;
+            assert!(!m.is_returnable());
+        }
+    }
+}
@@ -0,0 +1,9 @@
+mod message;
+mod service;
+mod store;
+mod system;
+
+pub use message::{Mail, MailKind};
+pub use service::{describe_attachments, MailDraft, MailError, MailService, SystemMail};
+pub use store::{get_character_owner, get_mail_recipient, MailRecipient};
+pub use system::MailExpirySystem;
\ No newline at end of file
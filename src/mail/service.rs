@@ -0,0 +1,524 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::audit::{record_audit_entry, AuditAction, AuditEntry};
+use crate::authentication::UserId;
+use crate::characters::Character;
+use crate::chat::is_blocked;
+use crate::configuration::MailSettings;
+use crate::events::{GameEvent, GameEvents};
+use crate::gateway::{ClientError, ConnectionRegistry, ErrorCode, ServerMessage};
+use crate::items::{InventoryError, InventoryService, ItemStack};
+use crate::ledger::{transfer, transfer_once, Currency, LedgerAccount, LedgerError, SystemAccount, Transfer};
+use crate::mail::{Mail, MailKind};
+use crate::mail::store::{delete_mail, get_character_name, get_character_owner, get_expired_mail_ids, get_mail_recipient, get_mailbox, lock_mail, mark_mail_claimed, mark_mail_read, store_mail};
+use crate::utils::error_chain_fmt;
+
+const MAX_SUBJECT_LENGTH: usize = 64;
+const MAX_BODY_LENGTH: usize = 2000;
+/// Expired mail handled per sweep, the rest waits for the next one
+const EXPIRY_BATCH_SIZE: i64 = 100;
+
+#[derive(thiserror::Error)]
+pub enum MailError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("There is no character called {0}")]
+    RecipientNotFound(String),
+    #[error("{0} is not accepting mail from you")]
+    MailRefused(String),
+    #[error("That mail doesn't exist")]
+    MailNotFound,
+    #[error("There is nothing attached to take")]
+    NothingToClaim,
+    #[error("Take the attachments before deleting the mail")]
+    AttachmentsLeft,
+    #[error("Only unclaimed mail from other players can be sent back")]
+    NotReturnable,
+    #[error(transparent)]
+    Inventory(#[from] InventoryError),
+    #[error(transparent)]
+    Ledger(#[from] LedgerError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for MailError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for MailError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            MailError::ValidationError(_) => ErrorCode::InvalidMessage,
+            MailError::RecipientNotFound(_) | MailError::MailNotFound => ErrorCode::NotFound,
+            MailError::MailRefused(_) => ErrorCode::Forbidden,
+            MailError::NothingToClaim | MailError::AttachmentsLeft | MailError::NotReturnable => ErrorCode::InvalidAction,
+            MailError::Inventory(e) => e.code(),
+            MailError::Ledger(e) => e.code(),
+            MailError::UnexpectedError(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+/// What a player puts in the mail, items are `(inventory slot, quantity)`
+#[derive(Debug, Clone, Default)]
+pub struct MailDraft {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub money: Option<(Currency, i64)>,
+    pub items: Vec<(usize, i32)>,
+    pub cod_amount: i64,
+    /// Made by the compose form, sending the same form twice only pays once
+    pub request_key: Option<String>,
+}
+
+/// Mail the game sends on its own, money and items are created for it
+#[derive(Debug, Clone)]
+pub struct SystemMail {
+    pub recipient_id: Uuid,
+    pub subject: String,
+    pub body: String,
+    pub money: Option<(Currency, i64)>,
+    pub items: Vec<ItemStack>,
+}
+
+/// Mailboxes belong to characters. Attached money moves into the escrow account when mail is sent
+/// and out of it when it is claimed, attached items only exist in the mail in between. Either
+/// happens in the same transaction as the mail row, so nothing is lost or handed out twice.
+pub struct MailService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    inventory: Arc<InventoryService>,
+    events: GameEvents,
+    settings: MailSettings,
+}
+
+impl MailService {
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        inventory: Arc<InventoryService>,
+        events: GameEvents,
+        settings: MailSettings,
+    ) -> Self {
+        MailService { pool, registry, inventory, events, settings }
+    }
+
+    pub fn settings(&self) -> &MailSettings {
+        &self.settings
+    }
+
+    pub async fn mailbox(&self, character_id: Uuid) -> Result<Vec<Mail>, MailError> {
+        Ok(get_mailbox(&self.pool, character_id).await?)
+    }
+
+    /// Marks the mail read on the way
+    pub async fn read(&self, character_id: Uuid, mail_id: Uuid) -> Result<Mail, MailError> {
+        mark_mail_read(&self.pool, mail_id, character_id).await?;
+        self.mailbox(character_id)
+            .await?
+            .into_iter()
+            .find(|m| m.id == mail_id)
+            .ok_or(MailError::MailNotFound)
+    }
+
+    #[tracing::instrument(
+    name = "Send mail",
+    skip(self, sender, draft),
+    fields(character_id = % sender.id)
+    )]
+    pub async fn send(&self, sender: &Character, draft: MailDraft) -> Result<Mail, MailError> {
+        let subject = draft.subject.trim().to_string();
+        let body = draft.body.trim().to_string();
+        self.validate(&subject, &body, draft.money, draft.items.len())?;
+        if draft.cod_amount < 0 {
+            return Err(MailError::ValidationError(format!("{} is not a valid amount", draft.cod_amount)));
+        }
+        if draft.cod_amount > 0 && draft.items.is_empty() {
+            return Err(MailError::ValidationError("Cash on delivery needs items attached".to_string()));
+        }
+
+        let to = draft.to.trim();
+        let recipient = get_mail_recipient(&self.pool, to)
+            .await?
+            .ok_or_else(|| MailError::RecipientNotFound(to.to_string()))?;
+        if recipient.character_id == sender.id {
+            return Err(MailError::ValidationError("You can't send mail to yourself".to_string()));
+        }
+        if is_blocked(&self.pool, recipient.user_id, sender.user_id).await? {
+            return Err(MailError::MailRefused(recipient.name));
+        }
+
+        let mut tx = self.begin().await?;
+        let items = self.inventory.take_stacks(&mut tx, sender.id, &draft.items).await?;
+        if let Some((currency, amount)) = draft.money {
+            let escrow = Transfer::new(
+                LedgerAccount::Character(sender.id),
+                LedgerAccount::System(SystemAccount::Escrow),
+                currency,
+                amount,
+                "mail attachment",
+            ).request_key("mail", draft.request_key.as_deref());
+            transfer_once(&mut tx, &escrow).await?;
+        }
+        let now = Utc::now();
+        let mail = Mail {
+            id: Uuid::new_v4(),
+            recipient_id: recipient.character_id,
+            sender_id: Some(sender.id),
+            sender_name: sender.name.to_string(),
+            kind: MailKind::Player,
+            subject,
+            body,
+            money: draft.money,
+            cod_amount: draft.cod_amount,
+            items,
+            created_at: now,
+            expires_at: now + self.settings.expiry(),
+            read_at: None,
+            claimed_at: None,
+        };
+        store_mail(&mut tx, &mail).await?;
+        commit(tx).await?;
+
+        self.notify(recipient.user_id, format!("{} got mail from {}", recipient.name, mail.sender_name));
+        Ok(mail)
+    }
+
+    /// For compensation and event rewards, `actor_id` is whoever asked for it, if anyone
+    #[tracing::instrument(
+    name = "Send system mail",
+    skip(self, mail),
+    fields(recipient_id = % mail.recipient_id)
+    )]
+    pub async fn send_system(&self, actor_id: Option<Uuid>, mail: SystemMail) -> Result<Mail, MailError> {
+        let subject = mail.subject.trim().to_string();
+        let body = mail.body.trim().to_string();
+        self.validate(&subject, &body, mail.money, mail.items.len())?;
+        for stack in &mail.items {
+            if self.inventory.catalog().get(&stack.item_id).is_none() {
+                return Err(MailError::Inventory(InventoryError::UnknownItem(stack.item_id.clone())));
+            }
+            if stack.quantity <= 0 {
+                return Err(MailError::Inventory(InventoryError::InvalidQuantity(stack.quantity)));
+            }
+        }
+        let owner = get_character_owner(&self.pool, mail.recipient_id)
+            .await?
+            .ok_or_else(|| MailError::RecipientNotFound(mail.recipient_id.to_string()))?;
+
+        let mut tx = self.begin().await?;
+        if let Some((currency, amount)) = mail.money {
+            let funding = Transfer::new(
+                LedgerAccount::System(SystemAccount::Rewards),
+                LedgerAccount::System(SystemAccount::Escrow),
+                currency,
+                amount,
+                "system mail",
+            );
+            transfer(&mut tx, &funding).await?;
+        }
+        let now = Utc::now();
+        let mail = Mail {
+            id: Uuid::new_v4(),
+            recipient_id: mail.recipient_id,
+            sender_id: None,
+            sender_name: self.settings.system_sender.clone(),
+            kind: MailKind::System,
+            subject,
+            body,
+            money: mail.money,
+            cod_amount: 0,
+            items: mail.items,
+            created_at: now,
+            expires_at: now + self.settings.expiry(),
+            read_at: None,
+            claimed_at: None,
+        };
+        store_mail(&mut tx, &mail).await?;
+        let entry = AuditEntry {
+            actor_id,
+            action: AuditAction::MailSystemSent,
+            subject_id: Some(mail.recipient_id),
+            details: format!("{}: {}", mail.subject, describe_attachments(&mail)),
+        };
+        record_audit_entry(&mut tx, &entry).await?;
+        commit(tx).await?;
+
+        self.notify(owner, format!("You got mail from {}", mail.sender_name));
+        Ok(mail)
+    }
+
+    /// Stores mail in the caller's transaction for attachments that are already in escrow, like
+    /// the proceeds of an auction. The caller tells the recipient once it has committed.
+    pub async fn deliver(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        kind: MailKind,
+        sender_name: &str,
+        mail: SystemMail,
+    ) -> Result<Mail, MailError> {
+        let now = Utc::now();
+        let mail = Mail {
+            id: Uuid::new_v4(),
+            recipient_id: mail.recipient_id,
+            sender_id: None,
+            sender_name: sender_name.to_string(),
+            kind,
+            subject: mail.subject,
+            body: mail.body,
+            money: mail.money,
+            cod_amount: 0,
+            items: mail.items,
+            created_at: now,
+            expires_at: now + self.settings.expiry(),
+            read_at: None,
+            claimed_at: None,
+        };
+        store_mail(tx, &mail).await?;
+        Ok(mail)
+    }
+
+    /// Takes everything attached at once, paying for it first if it is cash on delivery
+    #[tracing::instrument(
+    name = "Claim mail",
+    skip(self, character),
+    fields(character_id = % character.id)
+    )]
+    pub async fn claim(&self, character: &Character, mail_id: Uuid, request_key: Option<&str>) -> Result<Mail, MailError> {
+        let mut tx = self.begin().await?;
+        let mut mail = lock_mail(&mut tx, mail_id, Some(character.id))
+            .await?
+            .ok_or(MailError::MailNotFound)?;
+        if !mail.has_attachments() {
+            return Err(MailError::NothingToClaim);
+        }
+
+        let mut payment = None;
+        if mail.cod_amount > 0 {
+            if let Some(sender_id) = mail.sender_id {
+                let escrow = Transfer::new(
+                    LedgerAccount::Character(character.id),
+                    LedgerAccount::System(SystemAccount::Escrow),
+                    Currency::Gold,
+                    mail.cod_amount,
+                    "cash on delivery",
+                ).request_key(&format!("cod:{}", mail.id), request_key);
+                transfer_once(&mut tx, &escrow).await?;
+                let now = Utc::now();
+                let paid = Mail {
+                    id: Uuid::new_v4(),
+                    recipient_id: sender_id,
+                    sender_id: Some(character.id),
+                    sender_name: character.name.to_string(),
+                    kind: MailKind::Payment,
+                    subject: format!("Payment for: {}", mail.subject),
+                    body: String::new(),
+                    money: Some((Currency::Gold, mail.cod_amount)),
+                    cod_amount: 0,
+                    items: vec![],
+                    created_at: now,
+                    expires_at: now + self.settings.expiry(),
+                    read_at: None,
+                    claimed_at: None,
+                };
+                store_mail(&mut tx, &paid).await?;
+                payment = Some(paid);
+            }
+        }
+
+        self.inventory.add_stacks(&mut tx, character.id, &mail.items).await?;
+        if let Some((currency, amount)) = mail.money {
+            let payout = Transfer::new(
+                LedgerAccount::System(SystemAccount::Escrow),
+                LedgerAccount::Character(character.id),
+                currency,
+                amount,
+                "mail attachment",
+            );
+            transfer(&mut tx, &payout).await?;
+        }
+        mark_mail_claimed(&mut tx, mail.id).await?;
+        commit(tx).await?;
+
+        if let Some(payment) = payment {
+            self.notify_character(payment.recipient_id, format!("{} paid for your mail", character.name)).await?;
+        }
+        // player and returned mail only move gold the characters already had
+        let earned = matches!(mail.kind, MailKind::Auction | MailKind::Payment | MailKind::System);
+        match mail.money {
+            Some((Currency::Gold, amount)) if earned => {
+                self.events.publish(GameEvent::GoldEarned {
+                    user_id: UserId::from(character.user_id),
+                    character_id: character.id,
+                    amount,
+                });
+            }
+            _ => {}
+        }
+        mail.claimed_at = Some(Utc::now());
+        Ok(mail)
+    }
+
+    /// Sends the attachments back without paying for them
+    #[tracing::instrument(
+    name = "Return mail",
+    skip(self),
+    )]
+    pub async fn return_to_sender(&self, character_id: Uuid, mail_id: Uuid) -> Result<Mail, MailError> {
+        let mut tx = self.begin().await?;
+        let mail = lock_mail(&mut tx, mail_id, Some(character_id))
+            .await?
+            .ok_or(MailError::MailNotFound)?;
+        if !mail.is_returnable() {
+            return Err(MailError::NotReturnable);
+        }
+        let returned = self.bounce(&mut tx, &mail, Utc::now()).await?;
+        commit(tx).await?;
+
+        self.notify_character(returned.recipient_id, format!("{} sent your mail back", returned.sender_name)).await?;
+        Ok(returned)
+    }
+
+    pub async fn delete(&self, character_id: Uuid, mail_id: Uuid) -> Result<(), MailError> {
+        let mut tx = self.begin().await?;
+        let mail = lock_mail(&mut tx, mail_id, Some(character_id))
+            .await?
+            .ok_or(MailError::MailNotFound)?;
+        if mail.has_attachments() {
+            return Err(MailError::AttachmentsLeft);
+        }
+        delete_mail(&mut tx, mail.id).await?;
+        commit(tx).await
+    }
+
+    /// Player mail that expires with attachments goes back to the sender, anything else that
+    /// expires is gone along with whatever is still attached
+    #[tracing::instrument(
+    name = "Expire mail",
+    skip(self)
+    )]
+    pub async fn expire(&self, now: DateTime<Utc>) -> Result<usize, MailError> {
+        let mut expired = 0;
+        for mail_id in get_expired_mail_ids(&self.pool, now, EXPIRY_BATCH_SIZE).await? {
+            let mut tx = self.begin().await?;
+            let mail = match lock_mail(&mut tx, mail_id, None).await? {
+                // claimed, returned or expired by someone else in the meantime
+                Some(mail) if mail.expires_at <= now => mail,
+                _ => continue,
+            };
+            if mail.is_returnable() {
+                self.bounce(&mut tx, &mail, now).await?;
+            } else {
+                if let (Some((currency, amount)), true) = (mail.money, mail.has_attachments()) {
+                    let lost = Transfer::new(
+                        LedgerAccount::System(SystemAccount::Escrow),
+                        LedgerAccount::System(SystemAccount::Fees),
+                        currency,
+                        amount,
+                        "expired mail",
+                    );
+                    transfer(&mut tx, &lost).await?;
+                }
+                delete_mail(&mut tx, mail.id).await?;
+            }
+            commit(tx).await?;
+            expired += 1;
+        }
+        Ok(expired)
+    }
+
+    /// Replaces the mail with one to its sender carrying the same attachments, the money stays in
+    /// escrow the whole time
+    async fn bounce(&self, tx: &mut Transaction<'_, Postgres>, mail: &Mail, now: DateTime<Utc>) -> Result<Mail, MailError> {
+        let sender_id = mail.sender_id.ok_or(MailError::NotReturnable)?;
+        let recipient_name = get_character_name(&mut *tx, mail.recipient_id)
+            .await?
+            .unwrap_or_else(|| "Unknown".to_string());
+        let returned = Mail {
+            id: Uuid::new_v4(),
+            recipient_id: sender_id,
+            sender_id: Some(mail.recipient_id),
+            sender_name: recipient_name,
+            kind: MailKind::Returned,
+            subject: format!("Returned: {}", mail.subject),
+            body: mail.body.clone(),
+            money: mail.money,
+            cod_amount: 0,
+            items: mail.items.clone(),
+            created_at: now,
+            expires_at: now + self.settings.expiry(),
+            read_at: None,
+            claimed_at: None,
+        };
+        delete_mail(tx, mail.id).await?;
+        store_mail(tx, &returned).await?;
+        Ok(returned)
+    }
+
+    fn validate(&self, subject: &str, body: &str, money: Option<(Currency, i64)>, items: usize) -> Result<(), MailError> {
+        if subject.is_empty() || subject.chars().count() > MAX_SUBJECT_LENGTH {
+            return Err(MailError::ValidationError(format!(
+                "Subjects must be between 1 and {} characters long", MAX_SUBJECT_LENGTH
+            )));
+        }
+        if body.chars().count() > MAX_BODY_LENGTH {
+            return Err(MailError::ValidationError(format!(
+                "Mail can be at most {} characters long", MAX_BODY_LENGTH
+            )));
+        }
+        if let Some((_, amount)) = money {
+            if amount <= 0 {
+                return Err(MailError::ValidationError(format!("{} is not a valid amount", amount)));
+            }
+        }
+        if items > self.settings.max_attachments {
+            return Err(MailError::ValidationError(format!(
+                "At most {} stacks can be attached", self.settings.max_attachments
+            )));
+        }
+        Ok(())
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, MailError> {
+        Ok(self.pool.begin().await.context("Failed to begin mail transaction")?)
+    }
+
+    fn notify(&self, user_id: Uuid, message: String) {
+        self.registry.send_to_user(UserId::from(user_id), &ServerMessage::Notice { message });
+    }
+
+    async fn notify_character(&self, character_id: Uuid, message: String) -> Result<(), MailError> {
+        if let Some(user_id) = get_character_owner(&self.pool, character_id).await? {
+            self.notify(user_id, message);
+        }
+        Ok(())
+    }
+}
+
+async fn commit(tx: Transaction<'_, Postgres>) -> Result<(), MailError> {
+    Ok(tx.commit().await.context("Failed to commit mail transaction")?)
+}
+
+/// For the audit log, like `50 gold, 2 x potion`
+pub fn describe_attachments(mail: &Mail) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    if let Some((currency, amount)) = mail.money {
+        parts.push(format!("{} {}", amount, currency));
+    }
+    for stack in &mail.items {
+        parts.push(format!("{} x {}", stack.quantity, stack.item_id));
+    }
+    if parts.is_empty() {
+        "no attachments".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
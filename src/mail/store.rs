@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::items::ItemStack;
+use crate::ledger::Currency;
+use crate::mail::{Mail, MailKind};
+
+struct MailRow {
+    id: Uuid,
+    recipient_id: Uuid,
+    sender_id: Option<Uuid>,
+    sender_name: String,
+    kind: String,
+    subject: String,
+    body: String,
+    currency: Option<String>,
+    amount: i64,
+    cod_amount: i64,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    read_at: Option<DateTime<Utc>>,
+    claimed_at: Option<DateTime<Utc>>,
+}
+
+impl MailRow {
+    fn into_mail(self, items: Vec<ItemStack>) -> Result<Mail, anyhow::Error> {
+        let money = match self.currency {
+            Some(currency) if self.amount > 0 => Some((Currency::try_from(currency).map_err(|e| anyhow!(e))?, self.amount)),
+            _ => None,
+        };
+        Ok(Mail {
+            id: self.id,
+            recipient_id: self.recipient_id,
+            sender_id: self.sender_id,
+            sender_name: self.sender_name,
+            kind: MailKind::try_from(self.kind).map_err(|e| anyhow!(e))?,
+            subject: self.subject,
+            body: self.body,
+            money,
+            cod_amount: self.cod_amount,
+            items,
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            read_at: self.read_at,
+            claimed_at: self.claimed_at,
+        })
+    }
+}
+
+/// A live character mail can be sent to
+#[derive(Debug, Clone)]
+pub struct MailRecipient {
+    pub character_id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+}
+
+#[tracing::instrument(
+name = "Get mail recipient by name",
+skip(executor)
+)]
+pub async fn get_mail_recipient(
+    executor: impl PgExecutor<'_>,
+    name: &str,
+) -> Result<Option<MailRecipient>, anyhow::Error> {
+    sqlx::query_as!(
+        MailRecipient,
+        r#"
+        SELECT id as character_id, user_id, name
+        FROM characters
+        WHERE lower(name) = lower($1) AND deleted_at IS NULL
+        "#,
+        name
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch mail recipient")
+}
+
+/// Deleted characters still own their mail, they may be restored
+#[tracing::instrument(
+name = "Get character owner",
+skip(executor)
+)]
+pub async fn get_character_owner(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let owner = sqlx::query!(
+        "SELECT user_id FROM characters WHERE id = $1",
+        character_id
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch character owner")?
+        .map(|r| r.user_id);
+    Ok(owner)
+}
+
+#[tracing::instrument(
+name = "Get character name",
+skip(executor)
+)]
+pub async fn get_character_name(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+) -> Result<Option<String>, anyhow::Error> {
+    let name = sqlx::query!(
+        "SELECT name FROM characters WHERE id = $1",
+        character_id
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch character name")?
+        .map(|r| r.name);
+    Ok(name)
+}
+
+#[tracing::instrument(
+name = "Store mail",
+skip(tx, mail),
+fields(mail_id = % mail.id)
+)]
+pub async fn store_mail(
+    tx: &mut Transaction<'_, Postgres>,
+    mail: &Mail,
+) -> Result<(), anyhow::Error> {
+    let (currency, amount) = match mail.money {
+        Some((currency, amount)) => (Some(currency.as_str()), amount),
+        None => (None, 0),
+    };
+    sqlx::query!(
+        r#"
+        INSERT INTO mail (id, recipient_id, sender_id, sender_name, kind, subject, body,
+                          currency, amount, cod_amount, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        "#,
+        mail.id,
+        mail.recipient_id,
+        mail.sender_id,
+        mail.sender_name,
+        mail.kind.as_str(),
+        mail.subject,
+        mail.body,
+        currency,
+        amount,
+        mail.cod_amount,
+        mail.created_at,
+        mail.expires_at
+    )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to store mail")?;
+
+    for (position, stack) in mail.items.iter().enumerate() {
+        sqlx::query!(
+            r#"
+            INSERT INTO mail_items (mail_id, position, item_id, quantity)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            mail.id,
+            position as i32,
+            stack.item_id,
+            stack.quantity
+        )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to store mail item")?;
+    }
+    Ok(())
+}
+
+/// Locks the mail for claiming, returning or expiring it. `recipient_id` limits it to one mailbox.
+#[tracing::instrument(
+name = "Lock mail",
+skip(tx)
+)]
+pub async fn lock_mail(
+    tx: &mut Transaction<'_, Postgres>,
+    mail_id: Uuid,
+    recipient_id: Option<Uuid>,
+) -> Result<Option<Mail>, anyhow::Error> {
+    let row = sqlx::query_as!(
+        MailRow,
+        r#"
+        SELECT id, recipient_id, sender_id, sender_name, kind, subject, body,
+               currency, amount, cod_amount, created_at, expires_at, read_at, claimed_at
+        FROM mail
+        WHERE id = $1 AND ($2::uuid IS NULL OR recipient_id = $2)
+        FOR UPDATE
+        "#,
+        mail_id,
+        recipient_id
+    )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to lock mail")?;
+    match row {
+        Some(row) => {
+            let mut items = get_mail_items(&mut *tx, &[mail_id]).await?;
+            Ok(Some(row.into_mail(items.remove(&mail_id).unwrap_or_default())?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Newest first
+#[tracing::instrument(
+name = "Get mailbox",
+skip(executor)
+)]
+pub async fn get_mailbox(
+    executor: impl PgExecutor<'_> + Copy,
+    recipient_id: Uuid,
+) -> Result<Vec<Mail>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        MailRow,
+        r#"
+        SELECT id, recipient_id, sender_id, sender_name, kind, subject, body,
+               currency, amount, cod_amount, created_at, expires_at, read_at, claimed_at
+        FROM mail
+        WHERE recipient_id = $1
+        ORDER BY created_at DESC
+        "#,
+        recipient_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch mailbox")?;
+    let ids: Vec<Uuid> = rows.iter().map(|r| r.id).collect();
+    let mut items = get_mail_items(executor, &ids).await?;
+    rows.into_iter()
+        .map(|row| {
+            let stacks = items.remove(&row.id).unwrap_or_default();
+            row.into_mail(stacks)
+        })
+        .collect()
+}
+
+async fn get_mail_items(
+    executor: impl PgExecutor<'_>,
+    mail_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<ItemStack>>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT mail_id, item_id, quantity
+        FROM mail_items
+        WHERE mail_id = ANY($1)
+        ORDER BY mail_id, position
+        "#,
+        mail_ids
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch mail items")?;
+    let mut items: HashMap<Uuid, Vec<ItemStack>> = HashMap::new();
+    for row in rows {
+        items.entry(row.mail_id).or_default().push(ItemStack { item_id: row.item_id, quantity: row.quantity });
+    }
+    Ok(items)
+}
+
+#[tracing::instrument(
+name = "Mark mail read",
+skip(executor)
+)]
+pub async fn mark_mail_read(
+    executor: impl PgExecutor<'_>,
+    mail_id: Uuid,
+    recipient_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE mail SET read_at = now()
+        WHERE id = $1 AND recipient_id = $2 AND read_at IS NULL
+        "#,
+        mail_id,
+        recipient_id
+    )
+        .execute(executor)
+        .await
+        .context("Failed to mark mail read")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Mark mail claimed",
+skip(tx)
+)]
+pub async fn mark_mail_claimed(
+    tx: &mut Transaction<'_, Postgres>,
+    mail_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE mail SET claimed_at = now(), read_at = coalesce(read_at, now())
+        WHERE id = $1
+        "#,
+        mail_id
+    )
+        .execute(tx)
+        .await
+        .context("Failed to mark mail claimed")?;
+    Ok(())
+}
+
+/// Attached items go with it
+#[tracing::instrument(
+name = "Delete mail",
+skip(tx)
+)]
+pub async fn delete_mail(
+    tx: &mut Transaction<'_, Postgres>,
+    mail_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!("DELETE FROM mail WHERE id = $1", mail_id)
+        .execute(tx)
+        .await
+        .context("Failed to delete mail")?;
+    Ok(())
+}
+
+/// Oldest first, each one is locked again before anything happens to it
+#[tracing::instrument(
+name = "Get expired mail",
+skip(executor)
+)]
+pub async fn get_expired_mail_ids(
+    executor: impl PgExecutor<'_>,
+    now: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Uuid>, anyhow::Error> {
+    let ids = sqlx::query!(
+        r#"
+        SELECT id
+        FROM mail
+        WHERE expires_at <= $1
+        ORDER BY expires_at
+        LIMIT $2
+        "#,
+        now,
+        limit
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch expired mail")?
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+    Ok(ids)
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use crate::game_loop::{GameSystem, TickContext};
+use crate::mail::MailService;
+
+/// Looks for expired mail on the first tick and every so often in game time after that
+pub struct MailExpirySystem {
+    mail: Arc<MailService>,
+    interval: chrono::Duration,
+    last_sweep: Option<DateTime<Utc>>,
+}
+
+impl MailExpirySystem {
+    pub fn new(mail: Arc<MailService>, interval: chrono::Duration) -> Self {
+        MailExpirySystem { mail, interval, last_sweep: None }
+    }
+}
+
+#[async_trait]
+impl GameSystem for MailExpirySystem {
+    fn name(&self) -> &'static str {
+        "mail_expiry"
+    }
+
+    async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error> {
+        if let Some(last_sweep) = self.last_sweep {
+            if ctx.now - last_sweep < self.interval {
+                return Ok(());
+            }
+        }
+        self.last_sweep = Some(ctx.now);
+        let expired = self.mail.expire(ctx.now).await?;
+        if expired > 0 {
+            tracing::info!(tick = ctx.tick, expired, "Expired mail");
+        }
+        Ok(())
+    }
+}
@@ -12,8 +12,10 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("Fetching configuration");
     let settings = get_configuration().expect("Failed to get application configuration");
     tracing::info!("Building application");
-    let app = Application::build(settings.clone()).await?;
+    let mut app = Application::build(settings.clone()).await?;
     tracing::info!("Spawning threads");
+    let game_loop_task = tokio::spawn(app.take_game_loop().run_until_stopped());
+    let matchmaker_task = tokio::spawn(app.take_matchmaker().run_until_stopped());
     let app_task = tokio::spawn(app.run_until_stopped());
 
     tracing::info!(
@@ -22,7 +24,9 @@ async fn main() -> anyhow::Result<()> {
     );
 
     tokio::select! {
-        o = app_task => report_exit("API", o)
+        o = app_task => report_exit("API", o),
+        o = game_loop_task => report_exit("Game loop", o),
+        o = matchmaker_task => report_exit("Matchmaker", o),
     }
 
     Ok(())
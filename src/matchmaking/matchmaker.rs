@@ -0,0 +1,31 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use tokio::time::MissedTickBehavior;
+use crate::matchmaking::MatchmakingService;
+
+/// Drives matchmaking on the wall clock as a task of its own, next to the HTTP server and the game
+/// loop. Tests leave it out and call `MatchmakingService::tick` with whatever time they need.
+pub struct Matchmaker {
+    service: Arc<MatchmakingService>,
+    interval: Duration,
+}
+
+impl Matchmaker {
+    pub fn new(service: Arc<MatchmakingService>, interval: Duration) -> Self {
+        Matchmaker { service, interval }
+    }
+
+    pub async fn run_until_stopped(self) -> Result<(), anyhow::Error> {
+        tracing::info!(interval_ms = self.interval.as_secs_f64() * 1000.0, "Starting matchmaker");
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            // a failed round is tried again on the next one
+            if let Err(e) = self.service.tick(Utc::now()).await {
+                tracing::error!(error.cause_chain = ?e, error.message = %e, "Matchmaking failed");
+            }
+        }
+    }
+}
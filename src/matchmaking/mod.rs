@@ -0,0 +1,11 @@
+mod matchmaker;
+mod queue;
+mod rating;
+mod service;
+mod store;
+
+pub use matchmaker::Matchmaker;
+pub use queue::{find_match, tolerance, QueueKind, QueuedPlayer, Ticket};
+pub use rating::{expected_score, rating_after, team_rating, PROVISIONAL_GAMES, RATING_FLOOR};
+pub use service::{MatchmakingError, MatchmakingService, QueueStatus};
+pub use store::{get_ratings, Rating};
\ No newline at end of file
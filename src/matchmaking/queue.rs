@@ -0,0 +1,276 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::characters::Character;
+use crate::configuration::MatchmakingSettings;
+use crate::matchmaking::team_rating;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueKind {
+    /// One against one, rated
+    Duel,
+    /// Two teams, rated
+    Team,
+    /// One group against the dungeon's creatures, matched first come first served
+    Dungeon,
+}
+
+impl QueueKind {
+    pub const ALL: [QueueKind; 3] = [QueueKind::Duel, QueueKind::Team, QueueKind::Dungeon];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueueKind::Duel => "duel",
+            QueueKind::Team => "team",
+            QueueKind::Dungeon => "dungeon",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            QueueKind::Duel => "Duel",
+            QueueKind::Team => "Team battle",
+            QueueKind::Dungeon => "Dungeon",
+        }
+    }
+
+    /// Player sides in a match, a dungeon group fights the creatures on a side of their own
+    pub fn sides(&self) -> usize {
+        match self {
+            QueueKind::Duel | QueueKind::Team => 2,
+            QueueKind::Dungeon => 1,
+        }
+    }
+
+    pub fn players_per_side(&self, settings: &MatchmakingSettings) -> usize {
+        match self {
+            QueueKind::Duel => 1,
+            QueueKind::Team => settings.team_size,
+            QueueKind::Dungeon => settings.dungeon_size,
+        }
+    }
+
+    /// Rated queues match by rating and change it after the fight
+    pub fn is_rated(&self) -> bool {
+        matches!(self, QueueKind::Duel | QueueKind::Team)
+    }
+}
+
+impl TryFrom<String> for QueueKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "duel" => Ok(QueueKind::Duel),
+            "team" => Ok(QueueKind::Team),
+            "dungeon" => Ok(QueueKind::Dungeon),
+            other => Err(format!("{} is not a queue", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for QueueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QueuedPlayer {
+    pub user_id: UserId,
+    pub character: Character,
+    pub rating: i32,
+    pub games: i32,
+}
+
+/// A player, or a party, waiting in a queue. Everyone on a ticket ends up on the same side.
+#[derive(Debug, Clone)]
+pub struct Ticket {
+    pub id: Uuid,
+    pub queue: QueueKind,
+    pub members: Vec<QueuedPlayer>,
+    pub queued_at: DateTime<Utc>,
+}
+
+impl Ticket {
+    pub fn size(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn rating(&self) -> i32 {
+        team_rating(&self.members.iter().map(|m| m.rating).collect::<Vec<_>>())
+    }
+
+    pub fn has_player(&self, user_id: UserId) -> bool {
+        self.members.iter().any(|m| m.user_id == user_id)
+    }
+}
+
+/// How far apart ratings can be for a ticket that has waited since `queued_at`, it widens the
+/// longer nobody close enough turns up
+pub fn tolerance(settings: &MatchmakingSettings, queued_at: DateTime<Utc>, now: DateTime<Utc>) -> i32 {
+    let waited = (now - queued_at).num_seconds().max(0);
+    let widened = settings.base_tolerance as i64 + settings.tolerance_per_second as i64 * waited;
+    widened.min(settings.max_tolerance as i64) as i32
+}
+
+/// Picks tickets of one queue for a match, as indexes into `tickets` per side. `tickets` is
+/// expected oldest first, the oldest ticket that can be matched goes first and is joined by the
+/// oldest tickets within its tolerance, each onto the side with the fewest players that it fits.
+pub fn find_match(
+    tickets: &[&Ticket],
+    queue: QueueKind,
+    settings: &MatchmakingSettings,
+    now: DateTime<Utc>,
+) -> Option<Vec<Vec<usize>>> {
+    let per_side = queue.players_per_side(settings);
+    for (anchor_index, anchor) in tickets.iter().enumerate() {
+        if anchor.size() > per_side {
+            continue;
+        }
+        let reach = tolerance(settings, anchor.queued_at, now);
+        let mut sides: Vec<Vec<usize>> = vec![Vec::new(); queue.sides()];
+        let mut players = vec![0; queue.sides()];
+        let mut ratings = vec![0i64; queue.sides()];
+        sides[0].push(anchor_index);
+        players[0] = anchor.size();
+        ratings[0] = anchor.rating() as i64 * anchor.size() as i64;
+
+        for (index, ticket) in tickets.iter().enumerate() {
+            if index == anchor_index {
+                continue;
+            }
+            if queue.is_rated() && (ticket.rating() - anchor.rating()).abs() > reach {
+                continue;
+            }
+            let side = (0..sides.len())
+                .filter(|side| players[*side] + ticket.size() <= per_side)
+                .min_by_key(|side| (players[*side], ratings[*side]));
+            if let Some(side) = side {
+                sides[side].push(index);
+                players[side] += ticket.size();
+                ratings[side] += ticket.rating() as i64 * ticket.size() as i64;
+            }
+            if players.iter().all(|p| *p == per_side) {
+                return Some(sides);
+            }
+        }
+        if players.iter().all(|p| *p == per_side) {
+            return Some(sides);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use uuid::Uuid;
+    use crate::authentication::UserId;
+    use crate::characters::{Attributes, Character, CharacterClass, CharacterName};
+    use crate::configuration::MatchmakingSettings;
+    use crate::matchmaking::{find_match, tolerance, QueueKind, QueuedPlayer, Ticket};
+
+    fn start() -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 20, 12, 0, 0).unwrap()
+    }
+
+    fn player(rating: i32) -> QueuedPlayer {
+        let user_id = Uuid::new_v4();
+        QueuedPlayer {
+            user_id: UserId::from(user_id),
+            character: Character {
+                id: Uuid::new_v4(),
+                user_id,
+                name: CharacterName::parse("Aldric".to_string()).unwrap(),
+                class: CharacterClass::Warrior,
+                level: 1,
+                experience: 0,
+                attributes: Attributes::default(),
+                created_at: start(),
+                deleted_at: None,
+            },
+            rating,
+            games: 0,
+        }
+    }
+
+    fn ticket(queue: QueueKind, ratings: &[i32], seconds_ago: i64) -> Ticket {
+        Ticket {
+            id: Uuid::new_v4(),
+            queue,
+            members: ratings.iter().map(|r| player(*r)).collect(),
+            queued_at: start() - Duration::seconds(seconds_ago),
+        }
+    }
+
+    fn settings() -> MatchmakingSettings {
+        MatchmakingSettings {
+            base_tolerance: 50,
+            tolerance_per_second: 10,
+            max_tolerance: 400,
+            team_size: 2,
+            dungeon_size: 3,
+            ..MatchmakingSettings::default()
+        }
+    }
+
+    #[test]
+    fn tolerance_widens_with_waiting_up_to_a_limit() {
+        let settings = settings();
+        assert_eq!(50, tolerance(&settings, start(), start()));
+        assert_eq!(150, tolerance(&settings, start(), start() + Duration::seconds(10)));
+        assert_eq!(400, tolerance(&settings, start(), start() + Duration::hours(1)));
+    }
+
+    #[test]
+    fn duels_wait_until_ratings_are_close_enough() {
+        let settings = settings();
+        let (first, second) = (ticket(QueueKind::Duel, &[1500], 0), ticket(QueueKind::Duel, &[1620], 0));
+
+        assert_eq!(None, find_match(&[&first, &second], QueueKind::Duel, &settings, start()));
+        let later = start() + Duration::seconds(7);
+        assert_eq!(Some(vec![vec![0], vec![1]]), find_match(&[&first, &second], QueueKind::Duel, &settings, later));
+    }
+
+    #[test]
+    fn the_oldest_ticket_is_matched_first() {
+        let settings = settings();
+        let tickets = [
+            ticket(QueueKind::Duel, &[1500], 30),
+            ticket(QueueKind::Duel, &[1510], 20),
+            ticket(QueueKind::Duel, &[1505], 10),
+        ];
+        let tickets: Vec<&Ticket> = tickets.iter().collect();
+        assert_eq!(Some(vec![vec![0], vec![1]]), find_match(&tickets, QueueKind::Duel, &settings, start()));
+    }
+
+    #[test]
+    fn parties_stay_together_and_teams_fill_up() {
+        let settings = settings();
+        let tickets = [
+            ticket(QueueKind::Team, &[1500], 30),
+            ticket(QueueKind::Team, &[1500, 1520], 20),
+            ticket(QueueKind::Team, &[1490], 10),
+        ];
+        let tickets: Vec<&Ticket> = tickets.iter().collect();
+        assert_eq!(Some(vec![vec![0, 2], vec![1]]), find_match(&tickets, QueueKind::Team, &settings, start()));
+    }
+
+    #[test]
+    fn parties_too_big_for_a_side_are_never_matched() {
+        let settings = settings();
+        let tickets = [ticket(QueueKind::Team, &[1500, 1500, 1500], 30), ticket(QueueKind::Team, &[1500, 1500], 0)];
+        let tickets: Vec<&Ticket> = tickets.iter().collect();
+        assert_eq!(None, find_match(&tickets, QueueKind::Team, &settings, start()));
+    }
+
+    #[test]
+    fn dungeons_ignore_ratings() {
+        let settings = settings();
+        let tickets = [ticket(QueueKind::Dungeon, &[800, 2200], 5), ticket(QueueKind::Dungeon, &[1500], 0)];
+        let tickets: Vec<&Ticket> = tickets.iter().collect();
+        assert_eq!(Some(vec![vec![0, 1]]), find_match(&tickets, QueueKind::Dungeon, &settings, start()));
+    }
+}
@@ -0,0 +1,71 @@
+/// Games a character plays before its rating settles down and moves at the normal pace
+pub const PROVISIONAL_GAMES: i32 = 20;
+const PROVISIONAL_K: f64 = 40.0;
+const SETTLED_K: f64 = 20.0;
+/// Ratings never drop below this, so a losing streak can't dig an endless hole
+pub const RATING_FLOOR: i32 = 100;
+
+/// Chance of winning against `opponent`, between 0 and 1
+pub fn expected_score(rating: i32, opponent: i32) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) as f64 / 400.0))
+}
+
+/// Elo with a larger step for new characters. `score` is 1 for a win, 0.5 for a draw and 0
+/// for a loss, teams play as the average of their ratings.
+pub fn rating_after(rating: i32, games: i32, opponent: i32, score: f64) -> i32 {
+    let k = if games < PROVISIONAL_GAMES { PROVISIONAL_K } else { SETTLED_K };
+    let change = (k * (score - expected_score(rating, opponent))).round() as i32;
+    (rating + change).max(RATING_FLOOR)
+}
+
+/// Average rating of a side, rounded
+pub fn team_rating(ratings: &[i32]) -> i32 {
+    if ratings.is_empty() {
+        return 0;
+    }
+    let total: i64 = ratings.iter().map(|r| *r as i64).sum();
+    (total as f64 / ratings.len() as f64).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matchmaking::{expected_score, rating_after, team_rating, PROVISIONAL_GAMES, RATING_FLOOR};
+
+    #[test]
+    fn even_ratings_expect_an_even_result() {
+        assert!((expected_score(1500, 1500) - 0.5).abs() < 1e-9);
+        assert!(expected_score(1700, 1500) > 0.75);
+        assert!((expected_score(1700, 1500) + expected_score(1500, 1700) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn winners_gain_what_losers_lose() {
+        let settled = PROVISIONAL_GAMES;
+        assert_eq!(1510, rating_after(1500, settled, 1500, 1.0));
+        assert_eq!(1490, rating_after(1500, settled, 1500, 0.0));
+        assert_eq!(1500, rating_after(1500, settled, 1500, 0.5));
+    }
+
+    #[test]
+    fn new_characters_move_faster() {
+        assert_eq!(1520, rating_after(1500, 0, 1500, 1.0));
+    }
+
+    #[test]
+    fn upsets_are_worth_more() {
+        let favourite = rating_after(1800, PROVISIONAL_GAMES, 1400, 1.0) - 1800;
+        let underdog = rating_after(1400, PROVISIONAL_GAMES, 1800, 1.0) - 1400;
+        assert!(underdog > favourite * 5, "{} vs {}", underdog, favourite);
+    }
+
+    #[test]
+    fn ratings_have_a_floor() {
+        assert_eq!(RATING_FLOOR, rating_after(RATING_FLOOR, 0, 1500, 0.0));
+    }
+
+    #[test]
+    fn teams_play_as_their_average() {
+        assert_eq!(1550, team_rating(&[1400, 1700]));
+        assert_eq!(0, team_rating(&[]));
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::characters::Character;
+use crate::combat::{get_combat_record, CombatError, CombatService, Participant};
+use crate::configuration::MatchmakingSettings;
+use crate::gateway::{ClientError, ConnectionRegistry, ErrorCode, ServerMessage};
+use crate::mail::get_character_owner;
+use crate::matchmaking::{find_match, rating_after, team_rating, tolerance, QueueKind, QueuedPlayer, Ticket};
+use crate::matchmaking::store::{finish_match, get_ratings, get_unfinished_match_ids, store_match, store_rating, store_rating_after, void_match, MatchPlayer};
+use crate::utils::error_chain_fmt;
+
+/// Finished matches settled per tick, the rest waits for the next one
+const SETTLE_BATCH_SIZE: i64 = 100;
+
+#[derive(thiserror::Error)]
+pub enum MatchmakingError {
+    #[error("Select a character before queueing")]
+    NoCharacter,
+    #[error("You are already in a queue")]
+    AlreadyQueued,
+    #[error("You are not in a queue")]
+    NotQueued,
+    #[error("Finish your fight before queueing")]
+    AlreadyFighting,
+    #[error("Groups of up to {0} can queue for that")]
+    GroupTooLarge(usize),
+    #[error("That ready check is over")]
+    ReadyCheckNotFound,
+    #[error(transparent)]
+    Combat(#[from] CombatError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for MatchmakingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for MatchmakingError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            MatchmakingError::NoCharacter => ErrorCode::Forbidden,
+            MatchmakingError::AlreadyQueued
+            | MatchmakingError::AlreadyFighting
+            | MatchmakingError::GroupTooLarge(_) => ErrorCode::InvalidAction,
+            MatchmakingError::NotQueued | MatchmakingError::ReadyCheckNotFound => ErrorCode::NotFound,
+            MatchmakingError::Combat(e) => e.code(),
+            MatchmakingError::UnexpectedError(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+/// Where a player is in matchmaking
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum QueueStatus {
+    /// `tolerance` is the rating difference accepted by now, rated queues only
+    Queued {
+        queue: QueueKind,
+        since: DateTime<Utc>,
+        tolerance: Option<i32>,
+    },
+    ReadyCheck {
+        ready_check_id: Uuid,
+        queue: QueueKind,
+        deadline: DateTime<Utc>,
+        accepted: bool,
+    },
+}
+
+/// A match waiting for everyone in it to accept. `sides` holds the tickets, so whoever was ready
+/// can go back into the queue with their place in line if someone else wasn't.
+struct ReadyCheck {
+    id: Uuid,
+    queue: QueueKind,
+    sides: Vec<Vec<Ticket>>,
+    accepted: HashSet<UserId>,
+    deadline: DateTime<Utc>,
+}
+
+impl ReadyCheck {
+    fn players(&self) -> impl Iterator<Item=&QueuedPlayer> {
+        self.sides.iter().flatten().flat_map(|ticket| ticket.members.iter())
+    }
+
+    fn has_player(&self, user_id: UserId) -> bool {
+        self.players().any(|p| p.user_id == user_id)
+    }
+
+    fn everyone_accepted(&self) -> bool {
+        self.players().all(|p| self.accepted.contains(&p.user_id))
+    }
+}
+
+#[derive(Default)]
+struct MatchmakingState {
+    /// Every queue, oldest ticket first
+    tickets: Vec<Ticket>,
+    ready_checks: HashMap<Uuid, ReadyCheck>,
+}
+
+impl MatchmakingState {
+    fn is_waiting(&self, user_id: UserId) -> bool {
+        self.tickets.iter().any(|t| t.has_player(user_id))
+            || self.ready_checks.values().any(|c| c.has_player(user_id))
+    }
+
+    /// Back in line where it was before
+    fn requeue(&mut self, ticket: Ticket) {
+        let position = self.tickets.partition_point(|t| t.queued_at <= ticket.queued_at);
+        self.tickets.insert(position, ticket);
+    }
+}
+
+/// Queues, ready checks and rating updates. Everyone waiting lives in memory, rated matches are
+/// stored when their fight starts and settled by `tick` once the fight is stored as finished.
+/// `tick` is driven by the `Matchmaker` task on the wall clock and by tests with a made up one.
+pub struct MatchmakingService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    combat: Arc<CombatService>,
+    settings: MatchmakingSettings,
+    state: Mutex<MatchmakingState>,
+}
+
+impl MatchmakingService {
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        combat: Arc<CombatService>,
+        settings: MatchmakingSettings,
+    ) -> Self {
+        MatchmakingService { pool, registry, combat, settings, state: Mutex::new(MatchmakingState::default()) }
+    }
+
+    pub fn settings(&self) -> &MatchmakingSettings {
+        &self.settings
+    }
+
+    /// Queues a single player
+    pub async fn join(
+        &self,
+        user_id: UserId,
+        character: Character,
+        queue: QueueKind,
+        now: DateTime<Utc>,
+    ) -> Result<Uuid, MatchmakingError> {
+        self.join_group(queue, vec![(user_id, character)], now).await
+    }
+
+    /// Queues players that want to end up on the same side, like a party. Nobody in the group can
+    /// be fighting or waiting in any queue already.
+    #[tracing::instrument(
+    name = "Join queue",
+    skip(self, members)
+    )]
+    pub async fn join_group(
+        &self,
+        queue: QueueKind,
+        members: Vec<(UserId, Character)>,
+        now: DateTime<Utc>,
+    ) -> Result<Uuid, MatchmakingError> {
+        if members.is_empty() {
+            return Err(MatchmakingError::NoCharacter);
+        }
+        let per_side = queue.players_per_side(&self.settings);
+        if members.len() > per_side {
+            return Err(MatchmakingError::GroupTooLarge(per_side));
+        }
+        if members.iter().any(|(user_id, _)| self.combat.encounter_id(*user_id).is_some()) {
+            return Err(MatchmakingError::AlreadyFighting);
+        }
+        let character_ids: Vec<Uuid> = members.iter().map(|(_, c)| c.id).collect();
+        let ratings = get_ratings(&self.pool, &character_ids).await?;
+
+        let ticket = Ticket {
+            id: Uuid::new_v4(),
+            queue,
+            members: members
+                .into_iter()
+                .map(|(user_id, character)| {
+                    let rating = ratings.get(&character.id).copied().unwrap_or_default();
+                    QueuedPlayer { user_id, character, rating: rating.rating, games: rating.games }
+                })
+                .collect(),
+            queued_at: now,
+        };
+        let mut state = self.state.lock().expect("Matchmaking lock poisoned");
+        if ticket.members.iter().any(|m| state.is_waiting(m.user_id)) {
+            return Err(MatchmakingError::AlreadyQueued);
+        }
+        for member in &ticket.members {
+            self.registry.send_to_user(member.user_id, &ServerMessage::QueueJoined { queue });
+        }
+        tracing::info!(ticket_id = %ticket.id, players = ticket.size(), rating = ticket.rating(), "Queued");
+        let id = ticket.id;
+        state.requeue(ticket);
+        Ok(id)
+    }
+
+    /// Takes the player's whole ticket out of the queue, leaving during a ready check declines it
+    #[tracing::instrument(
+    name = "Leave queue",
+    skip(self)
+    )]
+    pub fn leave(&self, user_id: UserId) -> Result<QueueKind, MatchmakingError> {
+        let mut state = self.state.lock().expect("Matchmaking lock poisoned");
+        if let Some(position) = state.tickets.iter().position(|t| t.has_player(user_id)) {
+            let ticket = state.tickets.remove(position);
+            for member in &ticket.members {
+                self.registry.send_to_user(member.user_id, &ServerMessage::QueueLeft { queue: ticket.queue });
+            }
+            return Ok(ticket.queue);
+        }
+        let check_id = state.ready_checks
+            .values()
+            .find(|c| c.has_player(user_id))
+            .map(|c| c.id)
+            .ok_or(MatchmakingError::NotQueued)?;
+        let check = state.ready_checks.remove(&check_id).expect("Ready check was just found");
+        let queue = check.queue;
+        self.fail(&mut state, check, &HashSet::from([user_id]));
+        Ok(queue)
+    }
+
+    /// Accepts or declines a match. Returns the id of the fight once the last player accepted.
+    #[tracing::instrument(
+    name = "Answer ready check",
+    skip(self)
+    )]
+    pub async fn ready(
+        &self,
+        user_id: UserId,
+        ready_check_id: Uuid,
+        accept: bool,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Uuid>, MatchmakingError> {
+        let check = {
+            let mut state = self.state.lock().expect("Matchmaking lock poisoned");
+            let check = state.ready_checks
+                .get_mut(&ready_check_id)
+                .filter(|c| c.has_player(user_id))
+                .ok_or(MatchmakingError::ReadyCheckNotFound)?;
+            if accept {
+                check.accepted.insert(user_id);
+                if !check.everyone_accepted() {
+                    return Ok(None);
+                }
+            }
+            let check = state.ready_checks.remove(&ready_check_id).expect("Ready check was just found");
+            if !accept {
+                self.fail(&mut state, check, &HashSet::from([user_id]));
+                return Ok(None);
+            }
+            check
+        };
+        self.start(check, now).await.map(Some)
+    }
+
+    /// Where the player is in matchmaking, if anywhere
+    pub fn status(&self, user_id: UserId, now: DateTime<Utc>) -> Option<QueueStatus> {
+        let state = self.state.lock().expect("Matchmaking lock poisoned");
+        if let Some(ticket) = state.tickets.iter().find(|t| t.has_player(user_id)) {
+            return Some(QueueStatus::Queued {
+                queue: ticket.queue,
+                since: ticket.queued_at,
+                tolerance: ticket.queue.is_rated().then(|| tolerance(&self.settings, ticket.queued_at, now)),
+            });
+        }
+        state.ready_checks.values().find(|c| c.has_player(user_id)).map(|check| QueueStatus::ReadyCheck {
+            ready_check_id: check.id,
+            queue: check.queue,
+            deadline: check.deadline,
+            accepted: check.accepted.contains(&user_id),
+        })
+    }
+
+    /// Players waiting in each queue, not counting ready checks
+    pub fn waiting(&self) -> HashMap<QueueKind, usize> {
+        let state = self.state.lock().expect("Matchmaking lock poisoned");
+        let mut waiting = HashMap::new();
+        for ticket in &state.tickets {
+            *waiting.entry(ticket.queue).or_default() += ticket.size();
+        }
+        waiting
+    }
+
+    /// Drops whoever didn't answer a ready check in time, puts matched tickets into ready checks
+    /// and settles rated matches whose fight is over
+    #[tracing::instrument(
+    name = "Matchmaking tick",
+    skip(self)
+    )]
+    pub async fn tick(&self, now: DateTime<Utc>) -> Result<(), MatchmakingError> {
+        {
+            let mut state = self.state.lock().expect("Matchmaking lock poisoned");
+            let expired: Vec<Uuid> = state.ready_checks
+                .values()
+                .filter(|c| c.deadline <= now)
+                .map(|c| c.id)
+                .collect();
+            for id in expired {
+                let check = state.ready_checks.remove(&id).expect("Expired ready check exists");
+                let missing: HashSet<UserId> = check.players()
+                    .map(|p| p.user_id)
+                    .filter(|user_id| !check.accepted.contains(user_id))
+                    .collect();
+                self.fail(&mut state, check, &missing);
+            }
+
+            for queue in QueueKind::ALL {
+                while let Some(check) = self.next_match(&mut state, queue, now) {
+                    for player in check.players() {
+                        self.registry.send_to_user(player.user_id, &ServerMessage::MatchFound {
+                            ready_check_id: check.id,
+                            queue,
+                            seconds: self.settings.ready_check_seconds,
+                        });
+                    }
+                    state.ready_checks.insert(check.id, check);
+                }
+            }
+        }
+        self.settle(now).await
+    }
+
+    /// Takes the tickets of the next match in `queue` out of the queue
+    fn next_match(&self, state: &mut MatchmakingState, queue: QueueKind, now: DateTime<Utc>) -> Option<ReadyCheck> {
+        let tickets: Vec<&Ticket> = state.tickets.iter().filter(|t| t.queue == queue).collect();
+        let sides = find_match(&tickets, queue, &self.settings, now)?;
+        let ids: Vec<Vec<Uuid>> = sides
+            .iter()
+            .map(|side| side.iter().map(|i| tickets[*i].id).collect())
+            .collect();
+        let mut take = |id: &Uuid| {
+            let position = state.tickets.iter().position(|t| t.id == *id).expect("Matched ticket is queued");
+            state.tickets.remove(position)
+        };
+        let sides = ids.iter().map(|side| side.iter().map(&mut take).collect()).collect();
+        Some(ReadyCheck {
+            id: Uuid::new_v4(),
+            queue,
+            sides,
+            accepted: HashSet::new(),
+            deadline: now + self.settings.ready_check_timeout(),
+        })
+    }
+
+    /// Tickets with someone in `dropped` leave the queue, everyone else goes back into it
+    fn fail(&self, state: &mut MatchmakingState, check: ReadyCheck, dropped: &HashSet<UserId>) {
+        tracing::info!(ready_check_id = %check.id, dropped = dropped.len(), "Ready check failed");
+        for ticket in check.sides.into_iter().flatten() {
+            let requeued = !ticket.members.iter().any(|m| dropped.contains(&m.user_id));
+            for member in &ticket.members {
+                self.registry.send_to_user(
+                    member.user_id,
+                    &ServerMessage::ReadyCheckFailed { ready_check_id: check.id, requeued },
+                );
+            }
+            if requeued {
+                state.requeue(ticket);
+            }
+        }
+    }
+
+    /// Starts the fight of a ready check everyone accepted, a rated one is stored to be settled
+    async fn start(&self, check: ReadyCheck, now: DateTime<Utc>) -> Result<Uuid, MatchmakingError> {
+        let mut sides: Vec<Vec<Participant>> = check.sides
+            .iter()
+            .map(|side| {
+                side.iter()
+                    .flat_map(|ticket| ticket.members.iter())
+                    .map(|p| Participant::Player { user_id: p.user_id, character: p.character.clone() })
+                    .collect()
+            })
+            .collect();
+        if check.queue == QueueKind::Dungeon {
+            sides.push(self.settings.dungeon_creatures.iter().cloned().map(Participant::Creature).collect());
+        }
+
+        let encounter_id = match self.combat.start(sides).await {
+            Ok(id) => id,
+            Err(e) => {
+                // someone got into another fight in the meantime, nobody is to blame
+                let mut state = self.state.lock().expect("Matchmaking lock poisoned");
+                self.fail(&mut state, check, &HashSet::new());
+                return Err(e.into());
+            }
+        };
+        tracing::info!(ready_check_id = %check.id, encounter_id = %encounter_id, queue = %check.queue, "Match started");
+        if check.queue.is_rated() {
+            let players: Vec<MatchPlayer> = check.sides
+                .iter()
+                .enumerate()
+                .flat_map(|(side, tickets)| {
+                    tickets.iter().flat_map(|t| t.members.iter()).map(move |p| MatchPlayer {
+                        character_id: p.character.id,
+                        side,
+                        rating_before: p.rating,
+                    })
+                })
+                .collect();
+            let mut tx = self.pool.begin().await.context("Failed to begin matchmaking transaction")?;
+            store_match(&mut tx, encounter_id, check.queue, now, &players).await?;
+            tx.commit().await.context("Failed to commit match")?;
+        }
+        Ok(encounter_id)
+    }
+
+    /// Rates every match whose fight has been stored as finished
+    async fn settle(&self, now: DateTime<Utc>) -> Result<(), MatchmakingError> {
+        for match_id in get_unfinished_match_ids(&self.pool, SETTLE_BATCH_SIZE).await? {
+            // still being fought, checked before the record so a fight finishing in between is not voided
+            if self.combat.is_running(match_id) {
+                continue;
+            }
+            // gone without a record, lost to a restart or a failed store
+            let Some(record) = get_combat_record(&self.pool, match_id).await? else {
+                if void_match(&self.pool, match_id, now).await? {
+                    tracing::warn!(match_id = %match_id, "Match voided, its fight left no record");
+                }
+                continue;
+            };
+            let mut tx = self.pool.begin().await.context("Failed to begin matchmaking transaction")?;
+            let Some((_, players)) = finish_match(&mut tx, match_id, record.winner, now).await? else {
+                continue;
+            };
+            let character_ids: Vec<Uuid> = players.iter().map(|p| p.character_id).collect();
+            let current = get_ratings(&mut tx, &character_ids).await?;
+            let mut changes = Vec::with_capacity(players.len());
+            for player in &players {
+                let opponents: Vec<i32> = players
+                    .iter()
+                    .filter(|p| p.side != player.side)
+                    .map(|p| p.rating_before)
+                    .collect();
+                let score = match record.winner {
+                    Some(winner) if winner == player.side => 1.0,
+                    Some(_) => 0.0,
+                    None => 0.5,
+                };
+                let before = current.get(&player.character_id).copied().unwrap_or_default();
+                let after = rating_after(before.rating, before.games, team_rating(&opponents), score);
+                store_rating(&mut tx, player.character_id, after, now).await?;
+                store_rating_after(&mut tx, match_id, player.character_id, after).await?;
+                changes.push((player.character_id, after, after - before.rating));
+            }
+            tx.commit().await.context("Failed to commit match result")?;
+            tracing::info!(match_id = %match_id, winner = ?record.winner, "Match settled");
+
+            for (character_id, rating, change) in changes {
+                if let Some(user_id) = get_character_owner(&self.pool, character_id).await? {
+                    self.registry.send_to_user(UserId::from(user_id), &ServerMessage::RatingChanged { rating, change });
+                }
+            }
+        }
+        Ok(())
+    }
+}
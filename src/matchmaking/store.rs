@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::leaderboards::STARTING_RATING;
+use crate::matchmaking::QueueKind;
+
+/// A character's rating and how many rated games it was worked out from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rating {
+    pub rating: i32,
+    pub games: i32,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating { rating: STARTING_RATING, games: 0 }
+    }
+}
+
+/// Characters that never played a rated match start at `STARTING_RATING`
+pub async fn get_ratings(
+    executor: impl PgExecutor<'_>,
+    character_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Rating>, anyhow::Error> {
+    let stored: HashMap<Uuid, Rating> = sqlx::query!(
+        "SELECT character_id, rating, games FROM character_ratings WHERE character_id = ANY($1)",
+        character_ids
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch ratings")?
+        .into_iter()
+        .map(|r| (r.character_id, Rating { rating: r.rating, games: r.games }))
+        .collect();
+    Ok(character_ids
+        .iter()
+        .map(|id| (*id, stored.get(id).copied().unwrap_or_default()))
+        .collect())
+}
+
+/// Counts one more game
+#[tracing::instrument(
+name = "Store rating",
+skip(tx)
+)]
+pub async fn store_rating(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    rating: i32,
+    now: DateTime<Utc>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO character_ratings (character_id, rating, games, updated_at)
+        VALUES ($1, $2, 1, $3)
+        ON CONFLICT (character_id) DO UPDATE
+        SET rating = EXCLUDED.rating, games = character_ratings.games + 1, updated_at = EXCLUDED.updated_at
+        "#,
+        character_id,
+        rating,
+        now
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store rating")?;
+    Ok(())
+}
+
+/// Who played on which side of a rated match and their rating going in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchPlayer {
+    pub character_id: Uuid,
+    pub side: usize,
+    pub rating_before: i32,
+}
+
+#[tracing::instrument(
+name = "Store match",
+skip(tx, players)
+)]
+pub async fn store_match(
+    tx: &mut Transaction<'_, Postgres>,
+    match_id: Uuid,
+    queue: QueueKind,
+    started_at: DateTime<Utc>,
+    players: &[MatchPlayer],
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "INSERT INTO matchmaking_matches (id, queue, started_at) VALUES ($1, $2, $3)",
+        match_id,
+        queue.as_str(),
+        started_at
+    )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to store match")?;
+    let character_ids: Vec<Uuid> = players.iter().map(|p| p.character_id).collect();
+    let sides: Vec<i32> = players.iter().map(|p| p.side as i32).collect();
+    let ratings: Vec<i32> = players.iter().map(|p| p.rating_before).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO matchmaking_match_players (match_id, character_id, side, rating_before)
+        SELECT $1, * FROM UNNEST($2::uuid[], $3::int[], $4::int[])
+        "#,
+        match_id,
+        &character_ids,
+        &sides,
+        &ratings
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store match players")?;
+    Ok(())
+}
+
+/// Oldest first
+pub async fn get_unfinished_match_ids(
+    executor: impl PgExecutor<'_>,
+    limit: i64,
+) -> Result<Vec<Uuid>, anyhow::Error> {
+    let ids = sqlx::query!(
+        "SELECT id FROM matchmaking_matches WHERE finished_at IS NULL ORDER BY started_at LIMIT $1",
+        limit
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch unfinished matches")?
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+    Ok(ids)
+}
+
+/// Claims the match for settling, none when it was settled already
+#[tracing::instrument(
+name = "Finish match",
+skip(tx)
+)]
+pub async fn finish_match(
+    tx: &mut Transaction<'_, Postgres>,
+    match_id: Uuid,
+    winner: Option<usize>,
+    now: DateTime<Utc>,
+) -> Result<Option<(QueueKind, Vec<MatchPlayer>)>, anyhow::Error> {
+    let Some(row) = sqlx::query!(
+        r#"
+        UPDATE matchmaking_matches SET finished_at = $2, winner = $3
+        WHERE id = $1 AND finished_at IS NULL
+        RETURNING queue
+        "#,
+        match_id,
+        now,
+        winner.map(|w| w as i32)
+    )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to finish match")? else {
+        return Ok(None);
+    };
+    let queue = QueueKind::try_from(row.queue).map_err(|e| anyhow!(e))?;
+    let players = sqlx::query!(
+        r#"
+        SELECT character_id, side, rating_before
+        FROM matchmaking_match_players
+        WHERE match_id = $1
+        ORDER BY side, character_id
+        "#,
+        match_id
+    )
+        .fetch_all(tx)
+        .await
+        .context("Failed to fetch match players")?
+        .into_iter()
+        .map(|r| MatchPlayer { character_id: r.character_id, side: r.side as usize, rating_before: r.rating_before })
+        .collect();
+    Ok(Some((queue, players)))
+}
+
+/// Finishes the match without rating anyone, false when it was settled already
+#[tracing::instrument(
+name = "Void match",
+skip(executor)
+)]
+pub async fn void_match(
+    executor: impl PgExecutor<'_>,
+    match_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        "UPDATE matchmaking_matches SET finished_at = $2, voided = true WHERE id = $1 AND finished_at IS NULL",
+        match_id,
+        now
+    )
+        .execute(executor)
+        .await
+        .context("Failed to void match")?;
+    Ok(result.rows_affected() == 1)
+}
+
+#[tracing::instrument(
+name = "Store rating change",
+skip(tx)
+)]
+pub async fn store_rating_after(
+    tx: &mut Transaction<'_, Postgres>,
+    match_id: Uuid,
+    character_id: Uuid,
+    rating_after: i32,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "UPDATE matchmaking_match_players SET rating_after = $3 WHERE match_id = $1 AND character_id = $2",
+        match_id,
+        character_id,
+        rating_after
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store rating change")?;
+    Ok(())
+}
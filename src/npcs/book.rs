@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::combat::CombatRules;
+use crate::game_data::{data_directory, load_data_files, GameDataError};
+use crate::items::ItemCatalog;
+use crate::loot::LootBook;
+use crate::npcs::{NpcTemplate, SpawnerDefinition};
+use crate::world::WorldMap;
+
+#[derive(serde::Deserialize)]
+struct NpcFile {
+    #[serde(default)]
+    npcs: Vec<NpcTemplate>,
+    #[serde(default)]
+    spawners: Vec<SpawnerDefinition>,
+}
+
+/// Every npc template and spawner from `data/npcs`. Spawners are checked to spawn known npcs,
+/// creatures, items, loot tables and zones are checked by `check_references` once the rest of the data is loaded.
+#[derive(Debug, Default)]
+pub struct NpcBook {
+    templates: HashMap<String, NpcTemplate>,
+    spawners: Vec<SpawnerDefinition>,
+}
+
+impl NpcBook {
+    pub fn new(templates: Vec<NpcTemplate>, spawners: Vec<SpawnerDefinition>) -> Result<Self, GameDataError> {
+        let mut book = NpcBook::default();
+        for template in templates {
+            template.validate().map_err(GameDataError::Invalid)?;
+            if book.templates.contains_key(&template.id) {
+                return Err(GameDataError::Invalid(format!("Npc {} is defined twice", template.id)));
+            }
+            book.templates.insert(template.id.clone(), template);
+        }
+        for spawner in spawners {
+            spawner.validate().map_err(GameDataError::Invalid)?;
+            if book.spawners.iter().any(|s| s.id == spawner.id) {
+                return Err(GameDataError::Invalid(format!("Spawner {} is defined twice", spawner.id)));
+            }
+            if !book.templates.contains_key(&spawner.npc) {
+                return Err(GameDataError::Invalid(format!("Spawner {} spawns unknown npc {}", spawner.id, spawner.npc)));
+            }
+            book.spawners.push(spawner);
+        }
+        Ok(book)
+    }
+
+    pub fn load(directory: &Path) -> Result<Self, GameDataError> {
+        let (mut templates, mut spawners) = (Vec::new(), Vec::new());
+        for (_, file) in load_data_files::<NpcFile>(directory)? {
+            templates.extend(file.npcs);
+            spawners.extend(file.spawners);
+        }
+        Self::new(templates, spawners)
+    }
+
+    /// Creatures, items and loot tables that don't exist and spawners placed somewhere npcs can't
+    /// stand, empty when all is well
+    pub fn check_references(&self, items: &ItemCatalog, map: &WorldMap, rules: &CombatRules, loot: &LootBook) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut templates: Vec<&NpcTemplate> = self.templates.values().collect();
+        templates.sort_by_key(|t| t.id.as_str());
+        for template in templates {
+            if let Some(creature) = template.creature.as_ref().filter(|c| rules.creature(c).is_none()) {
+                problems.push(format!("Npc {} fights as unknown creature {}", template.id, creature));
+            }
+            for drop in template.loot.iter().filter(|d| !items.contains(&d.item)) {
+                problems.push(format!("Npc {} drops unknown item {}", template.id, drop.item));
+            }
+            if let Some(table) = template.loot_table.as_ref().filter(|t| !loot.contains(t)) {
+                problems.push(format!("Npc {} drops from unknown loot table {}", template.id, table));
+            }
+        }
+        for spawner in &self.spawners {
+            let Some(zone) = map.get(&spawner.zone) else {
+                problems.push(format!("Spawner {} is in unknown zone {}", spawner.id, spawner.zone));
+                continue;
+            };
+            let spots = std::iter::once((spawner.x, spawner.y)).chain(spawner.patrol.iter().map(|w| (w.x, w.y)));
+            for (x, y) in spots {
+                if !zone.is_walkable(x, y) || zone.exit_at(x, y).is_some() {
+                    problems.push(format!("Spawner {} uses {},{} in zone {} which npcs can't stand on", spawner.id, x, y, zone.id));
+                }
+            }
+        }
+        problems
+    }
+
+    pub fn template(&self, id: &str) -> Option<&NpcTemplate> {
+        self.templates.get(id)
+    }
+
+    pub fn spawner(&self, id: &str) -> Option<&SpawnerDefinition> {
+        self.spawners.iter().find(|s| s.id == id)
+    }
+
+    pub fn spawners(&self) -> &[SpawnerDefinition] {
+        &self.spawners
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+}
+
+pub fn get_npc_book() -> Result<NpcBook, GameDataError> {
+    NpcBook::load(&data_directory().join("npcs"))
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use uuid::Uuid;
+    use crate::combat::get_combat_rules;
+    use crate::items::get_item_catalog;
+    use crate::loot::get_loot_book;
+    use crate::npcs::{get_npc_book, NpcBook};
+    use crate::world::get_world_map;
+
+    fn load(content: &str) -> Result<NpcBook, crate::game_data::GameDataError> {
+        let directory = std::env::temp_dir().join(format!("yaug-npcs-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("npcs.toml"), content).unwrap();
+        let book = NpcBook::load(&directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+        book
+    }
+
+    const WOLF: &str = "[[npcs]]\nid = \"grey_wolf\"\nname = \"Grey Wolf\"\ncreature = \"wolf\"\naggro_radius = 3\n";
+
+    fn spawner(npc: &str, zone: &str, x: i32, y: i32) -> String {
+        format!("[[spawners]]\nid = \"pack\"\nzone = \"{}\"\nnpc = \"{}\"\nx = {}\ny = {}\ncount = 2\n", zone, npc, x, y)
+    }
+
+    #[test]
+    fn shipped_npcs_reference_existing_data() {
+        let book = assert_ok!(get_npc_book());
+        assert!(!book.is_empty());
+        let problems = book.check_references(&get_item_catalog().unwrap(), &get_world_map().unwrap(), &get_combat_rules().unwrap(), &get_loot_book().unwrap());
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn spawners_spawn_known_npcs() {
+        assert_ok!(load(&format!("{}{}", WOLF, spawner("grey_wolf", "greenvale", 3, 6))));
+        assert_err!(load(&format!("{}{}", WOLF, spawner("dire_wolf", "greenvale", 3, 6))));
+    }
+
+    #[test]
+    fn spawners_stand_on_walkable_tiles_of_known_zones() {
+        let items = get_item_catalog().unwrap();
+        let map = get_world_map().unwrap();
+        let rules = get_combat_rules().unwrap();
+        let loot = get_loot_book().unwrap();
+        let book = load(&format!("{}{}", WOLF, spawner("grey_wolf", "greenvale", 3, 6))).unwrap();
+        assert!(book.check_references(&items, &map, &rules, &loot).is_empty());
+        // a wall, an exit and nowhere
+        for (zone, x, y) in [("greenvale", 0, 0), ("greenvale", 15, 4), ("atlantis", 1, 1)] {
+            let book = load(&format!("{}{}", WOLF, spawner("grey_wolf", zone, x, y))).unwrap();
+            assert_eq!(1, book.check_references(&items, &map, &rules, &loot).len());
+        }
+    }
+
+    #[test]
+    fn npcs_drop_from_known_loot_tables() {
+        let items = get_item_catalog().unwrap();
+        let map = get_world_map().unwrap();
+        let rules = get_combat_rules().unwrap();
+        let loot = get_loot_book().unwrap();
+        let book = load(&format!("{}loot_table = \"forest_spider\"\n", WOLF)).unwrap();
+        assert!(book.check_references(&items, &map, &rules, &loot).is_empty());
+        let book = load(&format!("{}loot_table = \"dragon_hoard\"\n", WOLF)).unwrap();
+        assert_eq!(1, book.check_references(&items, &map, &rules, &loot).len());
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::{HashMap, VecDeque};
+use crate::authentication::UserId;
+use crate::npcs::{NpcTemplate, SpawnerDefinition};
+use crate::world::{Position, Zone};
+
+/// What an npc knows when it gets to act
+#[derive(Debug, Clone)]
+pub struct Perception {
+    pub position: Position,
+    pub home: Position,
+    /// Share of its health left, 0 to 1
+    pub health: f64,
+    /// Players in the npc's zone that are free to fight, nearest first
+    pub players: Vec<(UserId, Position)>,
+}
+
+impl Perception {
+    fn nearest(&self) -> Option<(UserId, &Position, i32)> {
+        self.players
+            .first()
+            .and_then(|(user_id, p)| p.distance(&self.position).map(|d| (*user_id, p, d)))
+    }
+
+    fn distance_from_home(&self) -> i32 {
+        self.position.distance(&self.home).unwrap_or(i32::MAX)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Intent {
+    /// Start a fight with the player next to it
+    Attack(UserId),
+    Chase(Position),
+    /// Get away from whoever stands there
+    Flee(Position),
+    GoHome,
+    Patrol,
+    Wander,
+    Idle,
+}
+
+/// Rates how much an npc wants to do one thing right now, `None` when it doesn't apply
+type Behaviour = fn(&NpcTemplate, &SpawnerDefinition, &Perception) -> Option<(f64, Intent)>;
+
+/// Earlier behaviours win ties
+const BEHAVIOURS: [Behaviour; 7] = [flee, attack, go_home, chase, patrol, wander, idle];
+
+/// Utility AI: every behaviour scores the situation and the best score wins
+pub fn choose(template: &NpcTemplate, spawner: &SpawnerDefinition, perception: &Perception) -> Intent {
+    let mut best: Option<(f64, Intent)> = None;
+    for behaviour in BEHAVIOURS {
+        if let Some((score, intent)) = behaviour(template, spawner, perception) {
+            if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+                best = Some((score, intent));
+            }
+        }
+    }
+    best.map_or(Intent::Idle, |(_, intent)| intent)
+}
+
+/// Timid npcs run from anyone close, hostile ones only once they are hurt badly enough
+fn flee(template: &NpcTemplate, _: &SpawnerDefinition, perception: &Perception) -> Option<(f64, Intent)> {
+    let (_, position, distance) = perception.nearest()?;
+    if template.flee_radius > 0 && distance <= template.flee_radius {
+        return Some((0.9, Intent::Flee(position.clone())));
+    }
+    let hurt = perception.health < template.flee_below;
+    (template.is_hostile() && hurt && distance <= template.aggro_radius).then(|| (0.95, Intent::Flee(position.clone())))
+}
+
+fn attack(template: &NpcTemplate, _: &SpawnerDefinition, perception: &Perception) -> Option<(f64, Intent)> {
+    let (user_id, _, distance) = perception.nearest()?;
+    let in_reach = template.is_hostile() && distance <= 1 && perception.distance_from_home() <= template.leash_radius;
+    in_reach.then_some((0.8, Intent::Attack(user_id)))
+}
+
+/// Past the leash nothing but home matters, idle npcs drift back towards their spawner
+fn go_home(template: &NpcTemplate, spawner: &SpawnerDefinition, perception: &Perception) -> Option<(f64, Intent)> {
+    let distance = perception.distance_from_home();
+    if distance > template.leash_radius {
+        return Some((0.75, Intent::GoHome));
+    }
+    (spawner.patrol.is_empty() && distance > spawner.wander_radius).then_some((0.25, Intent::GoHome))
+}
+
+/// Closer players are more tempting
+fn chase(template: &NpcTemplate, _: &SpawnerDefinition, perception: &Perception) -> Option<(f64, Intent)> {
+    let (_, position, distance) = perception.nearest()?;
+    if !template.is_hostile() || distance > template.aggro_radius {
+        return None;
+    }
+    let closeness = 1.0 - distance as f64 / (template.aggro_radius + 1) as f64;
+    Some((0.6 + 0.1 * closeness, Intent::Chase(position.clone())))
+}
+
+fn patrol(_: &NpcTemplate, spawner: &SpawnerDefinition, _: &Perception) -> Option<(f64, Intent)> {
+    (!spawner.patrol.is_empty()).then_some((0.3, Intent::Patrol))
+}
+
+fn wander(_: &NpcTemplate, spawner: &SpawnerDefinition, _: &Perception) -> Option<(f64, Intent)> {
+    (spawner.wander_radius > 0).then_some((0.2, Intent::Wander))
+}
+
+fn idle(_: &NpcTemplate, _: &SpawnerDefinition, _: &Perception) -> Option<(f64, Intent)> {
+    Some((0.1, Intent::Idle))
+}
+
+/// The eight steps, in the order ties are broken
+pub const DIRECTIONS: [(i32, i32); 8] = [(0, -1), (1, 0), (0, 1), (-1, 0), (1, -1), (1, 1), (-1, 1), (-1, -1)];
+
+/// Same rules as players walking, except npcs never take exits
+pub fn can_step(zone: &Zone, from: &Position, dx: i32, dy: i32) -> bool {
+    let (x, y) = (from.x + dx, from.y + dy);
+    if !zone.is_walkable(x, y) || zone.exit_at(x, y).is_some() {
+        return false;
+    }
+    dx == 0 || dy == 0 || (zone.is_walkable(from.x + dx, from.y) && zone.is_walkable(from.x, from.y + dy))
+}
+
+/// Diagonal distance first, straight line distance to break ties, so npcs keep more room
+fn closeness(a: &Position, b: &Position) -> (i32, i32) {
+    let (dx, dy) = ((a.x - b.x).abs(), (a.y - b.y).abs());
+    (dx.max(dy), dx + dy)
+}
+
+/// Every step npcs may take from `from`, in direction order
+pub fn steps<'a>(zone: &'a Zone, from: &'a Position) -> impl Iterator<Item=Position> + 'a {
+    DIRECTIONS
+        .iter()
+        .filter(move |(dx, dy)| can_step(zone, from, *dx, *dy))
+        .map(move |(dx, dy)| Position::new(from.zone_id.clone(), from.x + dx, from.y + dy))
+}
+
+/// The first step of a shortest walk to `target`, none when already there or there is no way
+pub fn step_towards(zone: &Zone, from: &Position, target: &Position) -> Option<Position> {
+    if from == target || from.zone_id != target.zone_id {
+        return None;
+    }
+    // breadth first from the npc, zones are small enough to search whole
+    let mut first_steps: HashMap<(i32, i32), Position> = HashMap::new();
+    let mut queue = VecDeque::new();
+    for step in steps(zone, from) {
+        first_steps.insert((step.x, step.y), step.clone());
+        queue.push_back(step);
+    }
+    while let Some(position) = queue.pop_front() {
+        let first = first_steps[&(position.x, position.y)].clone();
+        if position == *target {
+            return Some(first);
+        }
+        for next in steps(zone, &position) {
+            if (next.x, next.y) != (from.x, from.y) && !first_steps.contains_key(&(next.x, next.y)) {
+                first_steps.insert((next.x, next.y), first.clone());
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// The step that gets furthest from `threat`, none when cornered
+pub fn step_away(zone: &Zone, from: &Position, threat: &Position) -> Option<Position> {
+    // max_by_key keeps the last of equals, reversing keeps ties in direction order
+    let best = steps(zone, from).collect::<Vec<_>>().into_iter().rev().max_by_key(|p| closeness(p, threat))?;
+    (closeness(&best, threat) > closeness(from, threat)).then_some(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::authentication::UserId;
+    use crate::npcs::{choose, step_away, step_towards, Intent, NpcTemplate, Perception, SpawnerDefinition, Waypoint};
+    use crate::world::{Position, SpawnPoint, Zone, ZoneDefinition};
+
+    fn zone() -> Zone {
+        Zone::try_from(ZoneDefinition {
+            id: "meadow".to_string(),
+            name: "Meadow".to_string(),
+            tiles: vec![
+                "........".to_string(),
+                "...#....".to_string(),
+                "........".to_string(),
+            ],
+            spawns: vec![SpawnPoint { id: "start".to_string(), x: 0, y: 0 }],
+            exits: vec![],
+            stations: vec![],
+        }).unwrap()
+    }
+
+    fn template(aggro_radius: i32, flee_radius: i32) -> NpcTemplate {
+        NpcTemplate {
+            id: "wolf".to_string(),
+            name: "Wolf".to_string(),
+            level: 1,
+            creature: Some("wolf".to_string()),
+            health: None,
+            dialogue: vec![],
+            loot: vec![],
+            loot_table: None,
+            aggro_radius,
+            flee_radius,
+            flee_below: 0.3,
+            leash_radius: 4,
+            move_every: 1,
+        }
+    }
+
+    fn spawner(wander_radius: i32, patrol: Vec<Waypoint>) -> SpawnerDefinition {
+        SpawnerDefinition {
+            id: "pack".to_string(),
+            zone: "meadow".to_string(),
+            npc: "wolf".to_string(),
+            x: 1,
+            y: 1,
+            count: 1,
+            respawn_seconds: 60,
+            wander_radius,
+            patrol,
+        }
+    }
+
+    fn at(x: i32, y: i32) -> Position {
+        Position::new("meadow", x, y)
+    }
+
+    fn perceive(position: Position, health: f64, players: &[(i32, i32)]) -> Perception {
+        Perception {
+            position,
+            home: at(1, 1),
+            health,
+            players: players.iter().map(|(x, y)| (UserId::from(Uuid::nil()), at(*x, *y))).collect(),
+        }
+    }
+
+    #[test]
+    fn hostile_npcs_chase_then_attack() {
+        let wolf = template(3, 0);
+        let pack = spawner(2, vec![]);
+        assert_eq!(Intent::Chase(at(4, 1)), choose(&wolf, &pack, &perceive(at(1, 1), 1.0, &[(4, 1)])));
+        assert_eq!(Intent::Attack(UserId::from(Uuid::nil())), choose(&wolf, &pack, &perceive(at(1, 1), 1.0, &[(2, 2)])));
+        assert_eq!(Intent::Wander, choose(&wolf, &pack, &perceive(at(1, 1), 1.0, &[(5, 1)])));
+    }
+
+    #[test]
+    fn hurt_npcs_flee_and_timid_ones_always_do() {
+        let pack = spawner(0, vec![]);
+        assert_eq!(Intent::Flee(at(2, 1)), choose(&template(3, 0), &pack, &perceive(at(1, 1), 0.2, &[(2, 1)])));
+        assert_eq!(Intent::Flee(at(3, 1)), choose(&template(0, 2), &pack, &perceive(at(1, 1), 1.0, &[(3, 1)])));
+    }
+
+    #[test]
+    fn the_leash_beats_the_chase() {
+        let wolf = template(3, 0);
+        let pack = spawner(0, vec![]);
+        assert_eq!(Intent::GoHome, choose(&wolf, &pack, &perceive(at(6, 1), 1.0, &[(7, 1)])));
+        assert_eq!(Intent::Idle, choose(&template(0, 0), &pack, &perceive(at(1, 1), 1.0, &[])));
+        assert_eq!(Intent::Patrol, choose(&wolf, &spawner(0, vec![Waypoint { x: 1, y: 1 }]), &perceive(at(1, 1), 1.0, &[])));
+    }
+
+    #[test]
+    fn walks_go_around_walls_without_cutting_corners() {
+        let zone = zone();
+        let mut position = at(2, 1);
+        let mut walked = 0;
+        while let Some(step) = step_towards(&zone, &position, &at(5, 1)) {
+            // diagonals next to the wall at 3,1 would cut its corner
+            assert!(![at(3, 0), at(3, 2)].contains(&position) || step.x == position.x || step.y == position.y);
+            position = step;
+            walked += 1;
+        }
+        assert_eq!((at(5, 1), 4), (position, walked));
+        assert_eq!(None, step_towards(&zone, &at(2, 1), &at(2, 1)));
+    }
+
+    #[test]
+    fn fleeing_gets_as_far_as_one_step_can_until_cornered() {
+        let zone = zone();
+        assert_eq!(Some(at(0, 2)), step_away(&zone, &at(1, 1), &at(3, 1)));
+        assert_eq!(None, step_away(&zone, &at(0, 0), &at(1, 1)));
+    }
+}
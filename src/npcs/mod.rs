@@ -0,0 +1,15 @@
+mod book;
+mod brain;
+mod npc;
+mod service;
+mod spawner;
+mod system;
+mod template;
+
+pub use book::{get_npc_book, NpcBook};
+pub use brain::{choose, step_away, step_towards, Intent, Perception};
+pub use npc::{Engagement, Npc, NpcAction};
+pub use service::{NpcError, NpcService};
+pub use spawner::Spawner;
+pub use system::NpcSystem;
+pub use template::{NpcDrop, NpcTemplate, SpawnerDefinition, Waypoint};
\ No newline at end of file
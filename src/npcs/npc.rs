@@ -0,0 +1,326 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::items::ItemStack;
+use crate::npcs::{choose, step_away, step_towards, Intent, NpcTemplate, Perception, SpawnerDefinition};
+use crate::npcs::brain::steps;
+use crate::world::{Position, Zone};
+
+/// The player an npc is fighting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Engagement {
+    pub user_id: UserId,
+    pub character_id: Uuid,
+    /// The character's name, loot is handed out in it
+    pub name: String,
+    /// Nil until the fight has started
+    pub encounter_id: Uuid,
+}
+
+/// What an npc decided to do this tick
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NpcAction {
+    Step(Position),
+    Attack(UserId),
+}
+
+/// One living npc. Everything random about it comes from its own seeded generator, so the same
+/// seed and the same ticks always play out the same way.
+#[derive(Debug)]
+pub struct Npc {
+    pub id: Uuid,
+    pub template_id: String,
+    pub spawner_id: String,
+    pub position: Position,
+    pub home: Position,
+    pub health: i32,
+    pub max_health: i32,
+    pub engaged: Option<Engagement>,
+    patrol_index: usize,
+    next_line: usize,
+    last_acted: Option<u64>,
+    rng: StdRng,
+}
+
+impl Npc {
+    pub fn new(template: &NpcTemplate, spawner: &SpawnerDefinition, max_health: i32, seed: u64) -> Self {
+        let home = Position::new(spawner.zone.clone(), spawner.x, spawner.y);
+        Npc {
+            id: Uuid::new_v4(),
+            template_id: template.id.clone(),
+            spawner_id: spawner.id.clone(),
+            position: home.clone(),
+            home,
+            health: max_health,
+            max_health,
+            engaged: None,
+            patrol_index: 0,
+            next_line: 0,
+            last_acted: None,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Decides what to do about `players` in the zone, at most once every `move_every` ticks and
+    /// never while fighting. Steps are taken right away, attacks are up to the caller.
+    pub fn think(
+        &mut self,
+        zone: &Zone,
+        template: &NpcTemplate,
+        spawner: &SpawnerDefinition,
+        players: &[(UserId, Position)],
+        tick: u64,
+    ) -> Option<NpcAction> {
+        if self.engaged.is_some() || self.last_acted.is_some_and(|t| tick < t + template.move_every) {
+            return None;
+        }
+        self.last_acted = Some(tick);
+
+        let mut players: Vec<(UserId, Position)> = players
+            .iter()
+            .filter(|(_, p)| p.zone_id == self.position.zone_id)
+            .cloned()
+            .collect();
+        players.sort_by_key(|(_, p)| p.distance(&self.position));
+        let perception = Perception {
+            position: self.position.clone(),
+            home: self.home.clone(),
+            health: self.health as f64 / self.max_health as f64,
+            players,
+        };
+        let intent = choose(template, spawner, &perception);
+        if !matches!(intent, Intent::Attack(_) | Intent::Chase(_) | Intent::Flee(_)) {
+            // a tenth of its health back whenever nobody is bothering it
+            self.health = (self.health + (self.max_health / 10).max(1)).min(self.max_health);
+        }
+
+        let step = match intent {
+            Intent::Attack(user_id) => return Some(NpcAction::Attack(user_id)),
+            Intent::Chase(target) => step_towards(zone, &self.position, &target),
+            Intent::Flee(threat) => step_away(zone, &self.position, &threat),
+            Intent::GoHome => step_towards(zone, &self.position, &self.home),
+            Intent::Patrol => self.patrol_step(zone, spawner),
+            Intent::Wander => self.wander_step(zone, spawner),
+            Intent::Idle => None,
+        };
+        step.map(|position| {
+            self.position = position.clone();
+            NpcAction::Step(position)
+        })
+    }
+
+    /// Heads for the current waypoint, the next one once it got there
+    fn patrol_step(&mut self, zone: &Zone, spawner: &SpawnerDefinition) -> Option<Position> {
+        let waypoint = |i: usize| {
+            let w = spawner.patrol[i % spawner.patrol.len()];
+            Position::new(zone.id.clone(), w.x, w.y)
+        };
+        if self.position == waypoint(self.patrol_index) {
+            self.patrol_index = (self.patrol_index + 1) % spawner.patrol.len();
+        }
+        step_towards(zone, &self.position, &waypoint(self.patrol_index))
+    }
+
+    /// Every other turn or so a random step that stays within the wander radius
+    fn wander_step(&mut self, zone: &Zone, spawner: &SpawnerDefinition) -> Option<Position> {
+        let options: Vec<Position> = steps(zone, &self.position)
+            .filter(|p| p.is_within(&self.home, spawner.wander_radius))
+            .collect();
+        if !self.rng.gen_bool(0.5) {
+            return None;
+        }
+        options.choose(&mut self.rng).cloned()
+    }
+
+    /// The next line of the npc's dialogue, round and round
+    pub fn next_line<'a>(&mut self, template: &'a NpcTemplate) -> Option<&'a str> {
+        let line = template.dialogue.get(self.next_line % template.dialogue.len().max(1))?;
+        self.next_line += 1;
+        Some(line)
+    }
+
+    /// Rolls every drop of the template once
+    pub fn roll_loot(&mut self, template: &NpcTemplate) -> Vec<ItemStack> {
+        template.loot
+            .iter()
+            .filter(|drop| self.rng.gen_bool(drop.chance))
+            .map(|drop| ItemStack { item_id: drop.item.clone(), quantity: drop.quantity })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::authentication::UserId;
+    use crate::npcs::{Npc, NpcAction, NpcTemplate, SpawnerDefinition, Waypoint};
+    use crate::world::{Position, SpawnPoint, Zone, ZoneDefinition};
+
+    fn zone() -> Zone {
+        Zone::try_from(ZoneDefinition {
+            id: "meadow".to_string(),
+            name: "Meadow".to_string(),
+            tiles: vec![
+                "..........".to_string(),
+                "..........".to_string(),
+                "..........".to_string(),
+                "..........".to_string(),
+            ],
+            spawns: vec![SpawnPoint { id: "start".to_string(), x: 0, y: 0 }],
+            exits: vec![],
+            stations: vec![],
+        }).unwrap()
+    }
+
+    fn wolf() -> NpcTemplate {
+        NpcTemplate {
+            id: "grey_wolf".to_string(),
+            name: "Grey Wolf".to_string(),
+            level: 1,
+            creature: Some("wolf".to_string()),
+            health: None,
+            dialogue: vec![],
+            loot: vec![],
+            loot_table: None,
+            aggro_radius: 3,
+            flee_radius: 0,
+            flee_below: 0.3,
+            leash_radius: 4,
+            move_every: 1,
+        }
+    }
+
+    fn spawner(wander_radius: i32, patrol: Vec<Waypoint>) -> SpawnerDefinition {
+        SpawnerDefinition {
+            id: "den".to_string(),
+            zone: "meadow".to_string(),
+            npc: "grey_wolf".to_string(),
+            x: 2,
+            y: 1,
+            count: 1,
+            respawn_seconds: 60,
+            wander_radius,
+            patrol,
+        }
+    }
+
+    fn at(x: i32, y: i32) -> Position {
+        Position::new("meadow", x, y)
+    }
+
+    fn player() -> UserId {
+        UserId::from(Uuid::nil())
+    }
+
+    /// Runs the npc for `ticks` ticks starting at `first`, returns every action taken
+    fn run(npc: &mut Npc, template: &NpcTemplate, spawner: &SpawnerDefinition, players: &[(UserId, Position)], first: u64, ticks: u64) -> Vec<NpcAction> {
+        let zone = zone();
+        (first..first + ticks).filter_map(|tick| npc.think(&zone, template, spawner, players, tick)).collect()
+    }
+
+    #[test]
+    fn patrols_walk_their_waypoints_round_and_round() {
+        let wolf = NpcTemplate { aggro_radius: 0, ..wolf() };
+        let route = spawner(0, vec![Waypoint { x: 4, y: 1 }, Waypoint { x: 2, y: 1 }]);
+        let mut npc = Npc::new(&wolf, &route, 30, 1);
+
+        let visited: Vec<Position> = run(&mut npc, &wolf, &route, &[], 1, 8)
+            .into_iter()
+            .map(|action| match action {
+                NpcAction::Step(position) => position,
+                other => panic!("{:?} on patrol", other),
+            })
+            .collect();
+
+        assert_eq!(vec![at(3, 1), at(4, 1), at(3, 1), at(2, 1), at(3, 1), at(4, 1), at(3, 1), at(2, 1)], visited);
+    }
+
+    #[test]
+    fn hostile_npcs_run_at_players_and_attack_once_next_to_them() {
+        let wolf = wolf();
+        let den = spawner(0, vec![]);
+        let mut npc = Npc::new(&wolf, &den, 30, 1);
+        let players = [(player(), at(5, 1))];
+
+        let actions = run(&mut npc, &wolf, &den, &players, 1, 3);
+
+        assert_eq!(vec![NpcAction::Step(at(3, 1)), NpcAction::Step(at(4, 1)), NpcAction::Attack(player())], actions);
+    }
+
+    #[test]
+    fn npcs_wait_between_steps_and_never_act_while_fighting() {
+        let wolf = NpcTemplate { move_every: 3, ..wolf() };
+        let den = spawner(0, vec![]);
+        let mut npc = Npc::new(&wolf, &den, 30, 1);
+        let players = [(player(), at(5, 1))];
+
+        assert_eq!(2, run(&mut npc, &wolf, &den, &players, 1, 6).len());
+        npc.engaged = Some(crate::npcs::Engagement {
+            user_id: player(),
+            character_id: Uuid::nil(),
+            name: "Tomas".to_string(),
+            encounter_id: Uuid::nil(),
+        });
+        assert!(run(&mut npc, &wolf, &den, &players, 7, 6).is_empty());
+    }
+
+    #[test]
+    fn hurt_npcs_flee_and_heal_up_once_left_alone() {
+        let wolf = wolf();
+        let den = spawner(0, vec![]);
+        let mut npc = Npc::new(&wolf, &den, 30, 1);
+        npc.health = 6;
+
+        let actions = run(&mut npc, &wolf, &den, &[(player(), at(3, 1))], 1, 1);
+        assert_eq!(vec![NpcAction::Step(at(1, 2))], actions);
+        assert_eq!(6, npc.health);
+
+        run(&mut npc, &wolf, &den, &[], 2, 3);
+        assert_eq!(at(2, 1), npc.position);
+        assert_eq!(15, npc.health);
+    }
+
+    #[test]
+    fn npcs_dragged_past_their_leash_go_home() {
+        let wolf = wolf();
+        let den = spawner(0, vec![]);
+        let mut npc = Npc::new(&wolf, &den, 30, 1);
+        npc.position = at(8, 1);
+
+        // the player right next to it is ignored until it is back within the leash
+        let actions = run(&mut npc, &wolf, &den, &[(player(), at(9, 1))], 1, 2);
+
+        assert_eq!(vec![NpcAction::Step(at(7, 1)), NpcAction::Step(at(6, 1))], actions);
+    }
+
+    #[test]
+    fn wandering_is_random_but_repeatable_and_stays_close_to_home() {
+        let wolf = NpcTemplate { aggro_radius: 0, ..wolf() };
+        let den = spawner(1, vec![]);
+        let walk = |seed: u64| {
+            let mut npc = Npc::new(&wolf, &den, 30, seed);
+            run(&mut npc, &wolf, &den, &[], 1, 100)
+        };
+
+        let first = walk(7);
+        assert_eq!(first, walk(7));
+        assert!(first.len() > 10);
+        for action in first {
+            let NpcAction::Step(position) = action else { panic!("Wandering npcs don't attack") };
+            assert!(position.is_within(&at(2, 1), 1), "{:?} is too far from home", position);
+        }
+    }
+
+    #[test]
+    fn dialogue_goes_round_in_order() {
+        let elder = NpcTemplate { dialogue: vec!["Welcome".to_string(), "Mind the wolves".to_string()], ..wolf() };
+        let mut npc = Npc::new(&elder, &spawner(0, vec![]), 30, 1);
+
+        let lines: Vec<_> = (0..3).filter_map(|_| npc.next_line(&elder)).collect();
+
+        assert_eq!(vec!["Welcome", "Mind the wolves", "Welcome"], lines);
+        assert_eq!(None, npc.next_line(&wolf()));
+    }
+}
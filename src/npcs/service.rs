@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::characters::{get_character, Character};
+use crate::combat::{get_combat_record, CombatError, CombatRecord, CombatService, Encounter, Participant};
+use crate::configuration::NpcSettings;
+use crate::gateway::{ClientError, ConnectionRegistry, ErrorCode, ServerMessage};
+use crate::items::ItemStack;
+use crate::loot::LootService;
+use crate::npcs::{Engagement, Npc, NpcAction, NpcBook, NpcTemplate, Spawner};
+use crate::parties::{Looter, PartyService};
+use crate::utils::error_chain_fmt;
+use crate::world::{Position, WorldService};
+
+/// Health of npcs that never fight, nothing hurts them anyway
+const PEACEFUL_HEALTH: i32 = 100;
+
+#[derive(thiserror::Error)]
+pub enum NpcError {
+    #[error("Select a character before fighting")]
+    NoCharacter,
+    #[error("There is nobody like that around here")]
+    NotFound,
+    #[error("{0} won't fight")]
+    Peaceful(String),
+    #[error("{0} is too far away")]
+    TooFar(String),
+    #[error("{0} is already fighting someone")]
+    Busy(String),
+    #[error(transparent)]
+    Combat(#[from] CombatError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for NpcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for NpcError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            NpcError::NoCharacter => ErrorCode::Forbidden,
+            NpcError::NotFound => ErrorCode::NotFound,
+            NpcError::Peaceful(_) | NpcError::TooFar(_) | NpcError::Busy(_) => ErrorCode::InvalidAction,
+            NpcError::Combat(e) => e.code(),
+            NpcError::UnexpectedError(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+#[derive(Default)]
+struct NpcState {
+    npcs: HashMap<Uuid, Npc>,
+    spawners: HashMap<String, Spawner>,
+}
+
+/// Spawns the npcs of every spawner, lets them act once per tick and settles their fights.
+/// Npcs only live in memory, a restart spawns them all afresh.
+pub struct NpcService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    world: Arc<WorldService>,
+    combat: Arc<CombatService>,
+    parties: Arc<PartyService>,
+    loot: Arc<LootService>,
+    book: Arc<NpcBook>,
+    settings: NpcSettings,
+    state: Mutex<NpcState>,
+}
+
+impl NpcService {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        world: Arc<WorldService>,
+        combat: Arc<CombatService>,
+        parties: Arc<PartyService>,
+        loot: Arc<LootService>,
+        book: Arc<NpcBook>,
+        settings: NpcSettings,
+    ) -> Self {
+        NpcService { pool, registry, world, combat, parties, loot, book, settings, state: Mutex::new(NpcState::default()) }
+    }
+
+    pub fn book(&self) -> &NpcBook {
+        &self.book
+    }
+
+    /// Every living npc of `template_id` with where it stands
+    pub fn npcs_of(&self, template_id: &str) -> Vec<(Uuid, Position)> {
+        self.state
+            .lock()
+            .expect("Npc lock poisoned")
+            .npcs
+            .values()
+            .filter(|npc| npc.template_id == template_id)
+            .map(|npc| (npc.id, npc.position.clone()))
+            .collect()
+    }
+
+    /// Settles finished fights, spawns what is due and lets every npc act. No lock is held while
+    /// waiting on the database or another service.
+    pub async fn tick(&self, now: DateTime<Utc>, tick: u64) -> Result<(), anyhow::Error> {
+        self.settle_fights(now).await?;
+        self.spawn(now);
+
+        let players: Vec<(UserId, Uuid, Position)> = self.world
+            .players_in_world()
+            .into_iter()
+            .filter(|(user_id, _, _)| self.combat.encounter_id(*user_id).is_none())
+            .collect();
+        let positions: Vec<(UserId, Position)> = players.iter().map(|(user_id, _, p)| (*user_id, p.clone())).collect();
+        let mut steps = Vec::new();
+        let mut attacks = Vec::new();
+        {
+            let mut state = self.state.lock().expect("Npc lock poisoned");
+            for npc in state.npcs.values_mut() {
+                let (Some(template), Some(spawner)) = (self.book.template(&npc.template_id), self.book.spawner(&npc.spawner_id)) else {
+                    continue;
+                };
+                let zone = self.world.map().get(&npc.position.zone_id).expect("Spawners are checked to be in known zones");
+                match npc.think(zone, template, spawner, &positions, tick) {
+                    Some(NpcAction::Step(position)) => steps.push((npc.id, position)),
+                    Some(NpcAction::Attack(user_id)) => attacks.push((npc.id, user_id)),
+                    None => {}
+                }
+            }
+        }
+
+        for (npc_id, position) in steps {
+            self.world.move_npc(npc_id, position);
+        }
+        for (npc_id, user_id) in attacks {
+            let Some((_, character_id, _)) = players.iter().find(|(u, _, _)| *u == user_id) else { continue };
+            let Some(character) = get_character(&self.pool, *user_id, *character_id).await? else { continue };
+            match self.fight(npc_id, user_id, character).await {
+                Ok(_) => {}
+                // the player got into another fight or the npc into one with someone else since
+                Err(NpcError::Combat(CombatError::AlreadyFighting) | NpcError::Busy(_) | NpcError::NotFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Npcs whose fight is over: defeated ones drop their loot and wait to respawn, the others
+    /// carry on with the health they had left
+    async fn settle_fights(&self, now: DateTime<Utc>) -> Result<(), anyhow::Error> {
+        let engagements: Vec<(Uuid, Engagement)> = self.state
+            .lock()
+            .expect("Npc lock poisoned")
+            .npcs
+            .values()
+            .filter_map(|npc| npc.engaged.clone().map(|e| (npc.id, e)))
+            .filter(|(_, e)| !e.encounter_id.is_nil() && self.combat.encounter_id(e.user_id) != Some(e.encounter_id))
+            .collect();
+
+        for (npc_id, engagement) in engagements {
+            // one fight that can't be settled doesn't hold up the others
+            if let Err(e) = self.settle_fight(npc_id, engagement, now).await {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    %npc_id,
+                    "Failed to settle npc fight, trying again next tick"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Rolls and hands out the loot in one transaction. The npc is only taken out of the world
+    /// once that committed, until then it stays engaged and the next tick tries again.
+    async fn settle_fight(&self, npc_id: Uuid, engagement: Engagement, now: DateTime<Utc>) -> Result<(), anyhow::Error> {
+        // fights are stored right after they end, the next tick picks up what isn't there yet
+        let Some(record) = get_combat_record(&self.pool, engagement.encounter_id).await? else { return Ok(()) };
+        let Some((mut loot, table)) = self.settle(npc_id, &record) else { return Ok(()) };
+
+        let mut tx = self.pool.begin().await.context("Failed to begin npc loot transaction")?;
+        // the loot table is rolled with the pity of whoever landed the killing blow
+        if let Some(table) = table {
+            loot.extend(self.loot.roll_in(&mut tx, engagement.character_id, &table).await?);
+        }
+        let winner = Looter { user_id: engagement.user_id, character_id: engagement.character_id, name: engagement.name };
+        // party members nearby get their share the way the party hands out loot
+        let looters = self.parties.looters_near(winner, &self.world.players_in_world());
+        let distribution = self.parties.distribute_loot_in(&mut tx, &looters, loot, now).await?;
+        tx.commit().await.context("Failed to commit npc loot")?;
+
+        self.remove_defeated(npc_id, now);
+        self.parties.announce_loot(distribution);
+        Ok(())
+    }
+
+    /// The npc's own drops and the loot table to roll when the player won, `None` when the npc
+    /// lives on
+    fn settle(&self, npc_id: Uuid, record: &CombatRecord) -> Option<(Vec<ItemStack>, Option<String>)> {
+        let mut state = self.state.lock().expect("Npc lock poisoned");
+        let npc = state.npcs.get_mut(&npc_id)?;
+        // the player is the first combatant and on the first side
+        if record.winner != Some(0) {
+            let left = Encounter::replay(record.setup.clone(), record.seed, &record.actions).map(|e| e.health(1));
+            npc.health = left.unwrap_or(npc.max_health).clamp(1, npc.max_health);
+            npc.engaged = None;
+            return None;
+        }
+        let template = self.book.template(&npc.template_id)?;
+        Some((npc.roll_loot(template), template.loot_table.clone()))
+    }
+
+    /// Takes the defeated npc out of the world and schedules its respawn
+    fn remove_defeated(&self, npc_id: Uuid, now: DateTime<Utc>) {
+        let mut state = self.state.lock().expect("Npc lock poisoned");
+        let Some(npc) = state.npcs.remove(&npc_id) else {
+            return;
+        };
+        if let Some(spawner) = self.book.spawner(&npc.spawner_id) {
+            state.spawners.entry(spawner.id.clone()).or_default().defeated(npc_id, now + spawner.respawn_delay());
+        }
+        drop(state);
+        self.world.remove_npc(npc_id);
+        tracing::info!(%npc_id, template_id = %npc.template_id, "Npc defeated");
+    }
+
+    fn spawn(&self, now: DateTime<Utc>) {
+        let mut spawned = Vec::new();
+        {
+            let mut state = self.state.lock().expect("Npc lock poisoned");
+            for definition in self.book.spawners() {
+                let spawner = state.spawners.entry(definition.id.clone()).or_default();
+                let due = spawner.due(definition.count as usize, now);
+                let template = self.book.template(&definition.npc).expect("Spawners are checked to spawn known npcs");
+                let mut npcs = Vec::new();
+                for _ in 0..due {
+                    let npc = Npc::new(template, definition, self.max_health(template), rand::thread_rng().gen());
+                    spawner.spawned(npc.id);
+                    npcs.push(npc);
+                }
+                for npc in npcs {
+                    spawned.push((npc.id, template.name.clone(), npc.position.clone()));
+                    state.npcs.insert(npc.id, npc);
+                }
+            }
+        }
+        for (id, name, position) in spawned {
+            self.world.place_npc(id, &name, position);
+        }
+    }
+
+    fn max_health(&self, template: &NpcTemplate) -> i32 {
+        template.creature
+            .as_ref()
+            .and_then(|c| self.combat.rules().creature(c))
+            .map(|c| self.combat.rules().creature_combatant(c, 1).max_health)
+            .or(template.health)
+            .unwrap_or(PEACEFUL_HEALTH)
+    }
+
+    /// The player picks a fight with the npc standing next to them
+    #[tracing::instrument(
+    name = "Engage npc",
+    skip(self, character),
+    fields(character_id = % character.id)
+    )]
+    pub async fn engage(&self, user_id: UserId, character: Character, npc_id: Uuid) -> Result<Uuid, NpcError> {
+        let position = self.world.position(user_id).ok_or(NpcError::NoCharacter)?;
+        {
+            let state = self.state.lock().expect("Npc lock poisoned");
+            let npc = state.npcs.get(&npc_id).ok_or(NpcError::NotFound)?;
+            let template = self.book.template(&npc.template_id).ok_or(NpcError::NotFound)?;
+            if template.creature.is_none() {
+                return Err(NpcError::Peaceful(template.name.clone()));
+            }
+            if !npc.position.is_within(&position, 1) {
+                return Err(NpcError::TooFar(template.name.clone()));
+            }
+        }
+        self.fight(npc_id, user_id, character).await
+    }
+
+    /// Claims the npc before starting the fight so two players can't both fight it
+    async fn fight(&self, npc_id: Uuid, user_id: UserId, character: Character) -> Result<Uuid, NpcError> {
+        let creature = {
+            let mut state = self.state.lock().expect("Npc lock poisoned");
+            let npc = state.npcs.get_mut(&npc_id).ok_or(NpcError::NotFound)?;
+            let template = self.book.template(&npc.template_id).ok_or(NpcError::NotFound)?;
+            let creature = template.creature.clone().ok_or_else(|| NpcError::Peaceful(template.name.clone()))?;
+            if npc.engaged.is_some() {
+                return Err(NpcError::Busy(template.name.clone()));
+            }
+            npc.engaged = Some(Engagement {
+                user_id,
+                character_id: character.id,
+                name: character.name.to_string(),
+                encounter_id: Uuid::nil(),
+            });
+            creature
+        };
+
+        let started = self.combat.start(vec![
+            vec![Participant::Player { user_id, character }],
+            vec![Participant::Creature(creature)],
+        ]).await;
+        let mut state = self.state.lock().expect("Npc lock poisoned");
+        let engaged = state.npcs.get_mut(&npc_id).and_then(|npc| npc.engaged.as_mut());
+        match (started, engaged) {
+            (Ok(encounter_id), Some(engagement)) => {
+                engagement.encounter_id = encounter_id;
+                Ok(encounter_id)
+            }
+            (Ok(encounter_id), None) => Ok(encounter_id),
+            (Err(e), _) => {
+                if let Some(npc) = state.npcs.get_mut(&npc_id) {
+                    npc.engaged = None;
+                }
+                Err(e.into())
+            }
+        }
+    }
+
+    /// The nearest npc of `template_id` within talking distance says its next line, returns
+    /// whether anyone answered
+    pub fn talk(&self, user_id: UserId, template_id: &str) -> bool {
+        let Some(position) = self.world.position(user_id) else { return false };
+        let Some(template) = self.book.template(template_id) else { return false };
+        let said = {
+            let mut state = self.state.lock().expect("Npc lock poisoned");
+            let nearest = state.npcs
+                .values_mut()
+                .filter(|npc| npc.template_id == template_id && npc.position.is_within(&position, self.settings.talk_radius))
+                .min_by_key(|npc| npc.position.distance(&position));
+            nearest.and_then(|npc| npc.next_line(template).map(|line| (npc.id, line.to_string())))
+        };
+        match said {
+            Some((npc_id, line)) => {
+                self.registry.send_to_user(user_id, &ServerMessage::NpcSaid { npc_id, name: template.name.clone(), line });
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Which npcs a spawner has out and when the defeated ones come back
+#[derive(Debug, Default)]
+pub struct Spawner {
+    alive: Vec<Uuid>,
+    respawns: Vec<DateTime<Utc>>,
+}
+
+impl Spawner {
+    /// How many npcs to spawn to get back to `count`, respawns that are due free up their place
+    pub fn due(&mut self, count: usize, now: DateTime<Utc>) -> usize {
+        self.respawns.retain(|at| *at > now);
+        count.saturating_sub(self.alive.len() + self.respawns.len())
+    }
+
+    pub fn spawned(&mut self, id: Uuid) {
+        self.alive.push(id);
+    }
+
+    /// The npc's place stays taken until `respawn_at`
+    pub fn defeated(&mut self, id: Uuid, respawn_at: DateTime<Utc>) {
+        if let Some(i) = self.alive.iter().position(|alive| *alive == id) {
+            self.alive.remove(i);
+            self.respawns.push(respawn_at);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+    use crate::npcs::Spawner;
+
+    #[test]
+    fn spawners_fill_up_right_away() {
+        let mut spawner = Spawner::default();
+        let now = Utc::now();
+        assert_eq!(3, spawner.due(3, now));
+        spawner.spawned(Uuid::new_v4());
+        assert_eq!(2, spawner.due(3, now));
+    }
+
+    #[test]
+    fn defeated_npcs_come_back_after_their_respawn_time() {
+        let mut spawner = Spawner::default();
+        let now = Utc::now();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        spawner.spawned(first);
+        spawner.spawned(second);
+
+        spawner.defeated(first, now + Duration::seconds(60));
+        spawner.defeated(first, now + Duration::seconds(1));
+
+        assert_eq!(0, spawner.due(2, now + Duration::seconds(59)));
+        assert_eq!(1, spawner.due(2, now + Duration::seconds(60)));
+    }
+}
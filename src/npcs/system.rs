@@ -0,0 +1,26 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::game_loop::{GameSystem, TickContext};
+use crate::npcs::NpcService;
+
+/// Spawns, moves and fights with npcs, every tick
+pub struct NpcSystem {
+    npcs: Arc<NpcService>,
+}
+
+impl NpcSystem {
+    pub fn new(npcs: Arc<NpcService>) -> Self {
+        NpcSystem { npcs }
+    }
+}
+
+#[async_trait]
+impl GameSystem for NpcSystem {
+    fn name(&self) -> &'static str {
+        "npcs"
+    }
+
+    async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error> {
+        self.npcs.tick(ctx.now, ctx.tick).await
+    }
+}
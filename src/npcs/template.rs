@@ -0,0 +1,208 @@
+use crate::game_data::is_valid_id;
+
+fn one() -> i32 {
+    1
+}
+
+fn two() -> u64 {
+    2
+}
+
+fn default_leash() -> i32 {
+    6
+}
+
+fn default_respawn() -> i64 {
+    60
+}
+
+/// An item the npc may drop when it is defeated, rolled on its own
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct NpcDrop {
+    pub item: String,
+    /// Above 0 and at most 1
+    pub chance: f64,
+    #[serde(default = "one")]
+    pub quantity: i32,
+}
+
+/// One kind of npc as written in `data/npcs`
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct NpcTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "one")]
+    pub level: i32,
+    /// The combat creature the npc fights as, npcs without one can't be fought
+    #[serde(default)]
+    pub creature: Option<String>,
+    /// Health for npcs that don't fight, fighters take it from their creature
+    #[serde(default)]
+    pub health: Option<i32>,
+    /// Lines the npc says when talked to, in turn
+    #[serde(default)]
+    pub dialogue: Vec<String>,
+    #[serde(default)]
+    pub loot: Vec<NpcDrop>,
+    /// Rolled for whoever defeats the npc, on top of `loot`
+    #[serde(default)]
+    pub loot_table: Option<String>,
+    /// Attacks players who come this close, 0 never does
+    #[serde(default)]
+    pub aggro_radius: i32,
+    /// Runs from players who come this close, 0 never does
+    #[serde(default)]
+    pub flee_radius: i32,
+    /// Runs instead of attacking below this share of its health
+    #[serde(default)]
+    pub flee_below: f64,
+    /// Goes back home once it is this far from its spawner
+    #[serde(default = "default_leash")]
+    pub leash_radius: i32,
+    /// Ticks between two steps, 1 moves every tick
+    #[serde(default = "two")]
+    pub move_every: u64,
+}
+
+impl NpcTemplate {
+    pub fn is_hostile(&self) -> bool {
+        self.aggro_radius > 0 && self.creature.is_some()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_id(&self.id) {
+            return Err(format!("{:?} is not a valid npc id", self.id));
+        }
+        if self.name.trim().is_empty() {
+            return Err(format!("Npc {} needs a name", self.id));
+        }
+        if self.level < 1 {
+            return Err(format!("Npc {} needs a level of at least 1", self.id));
+        }
+        if let Some(creature) = self.creature.as_ref().filter(|c| !is_valid_id(c)) {
+            return Err(format!("Npc {}: {:?} is not a valid creature id", self.id, creature));
+        }
+        if self.health.is_some_and(|h| h <= 0) {
+            return Err(format!("Npc {} needs positive health", self.id));
+        }
+        if self.aggro_radius > 0 && self.creature.is_none() {
+            return Err(format!("Npc {} can't attack without a creature to fight as", self.id));
+        }
+        if self.aggro_radius < 0 || self.flee_radius < 0 || self.leash_radius < 1 {
+            return Err(format!("Npc {} has a negative radius or a leash below 1", self.id));
+        }
+        if !(0.0..1.0).contains(&self.flee_below) {
+            return Err(format!("Npc {} has to flee below a share of at least 0 and below 1", self.id));
+        }
+        if self.move_every == 0 {
+            return Err(format!("Npc {} has to wait at least one tick between steps", self.id));
+        }
+        if let Some(table) = self.loot_table.as_ref().filter(|t| !is_valid_id(t)) {
+            return Err(format!("Npc {}: {:?} is not a valid loot table id", self.id, table));
+        }
+        for drop in &self.loot {
+            if !is_valid_id(&drop.item) {
+                return Err(format!("Npc {}: {:?} is not a valid item id", self.id, drop.item));
+            }
+            if !(drop.chance > 0.0 && drop.chance <= 1.0) || drop.quantity <= 0 {
+                return Err(format!("Npc {} drops {} with a chance or quantity out of range", self.id, drop.item));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+pub struct Waypoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Keeps `count` npcs of one template alive around a spot in a zone
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct SpawnerDefinition {
+    pub id: String,
+    pub zone: String,
+    pub npc: String,
+    pub x: i32,
+    pub y: i32,
+    #[serde(default = "one")]
+    pub count: i32,
+    /// Game time between an npc being defeated and the next one showing up
+    #[serde(default = "default_respawn")]
+    pub respawn_seconds: i64,
+    /// How far from the spawner idle npcs wander, 0 stands still
+    #[serde(default)]
+    pub wander_radius: i32,
+    /// Walked in order and round again, wandering is ignored when set
+    #[serde(default)]
+    pub patrol: Vec<Waypoint>,
+}
+
+impl SpawnerDefinition {
+    pub fn respawn_delay(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.respawn_seconds)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_id(&self.id) {
+            return Err(format!("{:?} is not a valid spawner id", self.id));
+        }
+        if !is_valid_id(&self.zone) || !is_valid_id(&self.npc) {
+            return Err(format!("Spawner {} needs a valid zone and npc id", self.id));
+        }
+        if self.count < 1 {
+            return Err(format!("Spawner {} has to spawn at least one npc", self.id));
+        }
+        if self.respawn_seconds < 0 || self.wander_radius < 0 {
+            return Err(format!("Spawner {} can't have a negative respawn time or wander radius", self.id));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use crate::npcs::{NpcDrop, NpcTemplate};
+
+    fn wolf() -> NpcTemplate {
+        NpcTemplate {
+            id: "grey_wolf".to_string(),
+            name: "Grey Wolf".to_string(),
+            level: 1,
+            creature: Some("wolf".to_string()),
+            health: None,
+            dialogue: vec![],
+            loot: vec![NpcDrop { item: "wolf_pelt".to_string(), chance: 0.5, quantity: 1 }],
+            loot_table: None,
+            aggro_radius: 3,
+            flee_radius: 0,
+            flee_below: 0.2,
+            leash_radius: 6,
+            move_every: 2,
+        }
+    }
+
+    #[test]
+    fn a_valid_template_passes() {
+        assert_ok!(wolf().validate());
+        assert!(wolf().is_hostile());
+    }
+
+    #[test]
+    fn npcs_only_attack_with_a_creature_to_fight_as() {
+        let mut wolf = wolf();
+        wolf.creature = None;
+        assert_err!(wolf.validate());
+    }
+
+    #[test]
+    fn drop_chances_have_to_be_a_probability() {
+        for chance in [0.0, 1.5, -0.1] {
+            let mut wolf = wolf();
+            wolf.loot[0].chance = chance;
+            assert_err!(wolf.validate());
+        }
+    }
+}
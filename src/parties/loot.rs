@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::items::ItemStack;
+
+/// A player who won a fight and the character their share of the loot goes to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Looter {
+    pub user_id: UserId,
+    pub character_id: Uuid,
+    /// The character's name, for telling everyone who got what
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LootChoice {
+    Need,
+    Greed,
+    Pass,
+}
+
+/// One item the looters of a need before greed party roll for
+#[derive(Debug, Clone)]
+pub struct LootRoll {
+    pub id: Uuid,
+    pub party_id: Uuid,
+    pub item: ItemStack,
+    pub looters: Vec<Looter>,
+    /// In the order they came in, passing rolls nothing
+    pub choices: Vec<(UserId, LootChoice, u32)>,
+    pub deadline: DateTime<Utc>,
+}
+
+impl LootRoll {
+    pub fn new(party_id: Uuid, item: ItemStack, looters: Vec<Looter>, deadline: DateTime<Utc>) -> Self {
+        LootRoll { id: Uuid::new_v4(), party_id, item, looters, choices: Vec::new(), deadline }
+    }
+
+    pub fn is_looter(&self, user_id: UserId) -> bool {
+        self.looters.iter().any(|l| l.user_id == user_id)
+    }
+
+    pub fn has_chosen(&self, user_id: UserId) -> bool {
+        self.choices.iter().any(|(u, _, _)| *u == user_id)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.looters.iter().all(|l| self.has_chosen(l.user_id))
+    }
+
+    /// Need beats greed, then the highest roll and then whoever answered first. Nobody gets the
+    /// item when everyone passed or didn't answer.
+    pub fn winner(&self) -> Option<(&Looter, u32)> {
+        let mut best: Option<(UserId, bool, u32)> = None;
+        for (user_id, choice, roll) in &self.choices {
+            let need = match choice {
+                LootChoice::Need => true,
+                LootChoice::Greed => false,
+                LootChoice::Pass => continue,
+            };
+            if best.is_none_or(|(_, best_need, best_roll)| (need, *roll) > (best_need, best_roll)) {
+                best = Some((*user_id, need, *roll));
+            }
+        }
+        let (user_id, _, roll) = best?;
+        self.looters.iter().find(|l| l.user_id == user_id).map(|l| (l, roll))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+    use crate::authentication::UserId;
+    use crate::items::ItemStack;
+    use crate::parties::{LootChoice, LootRoll, Looter};
+
+    fn looter(name: &str) -> Looter {
+        Looter { user_id: UserId::from(Uuid::new_v4()), character_id: Uuid::new_v4(), name: name.to_string() }
+    }
+
+    fn roll(choices: &[(usize, LootChoice, u32)]) -> (LootRoll, Vec<Looter>) {
+        let looters = vec![looter("Tomas"), looter("Maria"), looter("Ilona")];
+        let item = ItemStack { item_id: "wolf_pelt".to_string(), quantity: 1 };
+        let mut roll = LootRoll::new(Uuid::new_v4(), item, looters.clone(), Utc::now());
+        for (looter, choice, number) in choices {
+            roll.choices.push((looters[*looter].user_id, *choice, *number));
+        }
+        (roll, looters)
+    }
+
+    fn winner(roll: &LootRoll) -> Option<&str> {
+        roll.winner().map(|(l, _)| l.name.as_str())
+    }
+
+    #[test]
+    fn need_beats_greed_whatever_the_roll() {
+        let (roll, _) = roll(&[(0, LootChoice::Greed, 99), (1, LootChoice::Need, 2), (2, LootChoice::Pass, 0)]);
+        assert_eq!(Some("Maria"), winner(&roll));
+    }
+
+    #[test]
+    fn the_highest_roll_wins_and_ties_go_to_the_first_answer() {
+        let (roll, _) = roll(&[(0, LootChoice::Greed, 40), (1, LootChoice::Greed, 75), (2, LootChoice::Greed, 75)]);
+        assert_eq!(Some("Maria"), winner(&roll));
+        assert_eq!(Some(75), roll.winner().map(|(_, n)| n));
+    }
+
+    #[test]
+    fn nobody_wins_when_everyone_passes() {
+        let (roll, _) = roll(&[(0, LootChoice::Pass, 0), (1, LootChoice::Pass, 0)]);
+        assert_eq!(None, winner(&roll));
+        assert!(!roll.is_complete());
+    }
+
+    #[test]
+    fn the_roll_is_complete_once_everyone_answered() {
+        let (roll, looters) = roll(&[(0, LootChoice::Pass, 0), (2, LootChoice::Need, 10)]);
+        assert!(roll.has_chosen(looters[2].user_id));
+        assert!(!roll.has_chosen(looters[1].user_id));
+        assert!(!roll.is_complete());
+        let (roll, _) = self::roll(&[(0, LootChoice::Pass, 0), (1, LootChoice::Greed, 1), (2, LootChoice::Need, 10)]);
+        assert!(roll.is_complete());
+    }
+}
@@ -0,0 +1,9 @@
+mod loot;
+mod party;
+mod service;
+mod system;
+
+pub use loot::{LootChoice, LootRoll, Looter};
+pub use party::{LootMode, Party, PartyMember};
+pub use service::{LootDistribution, PartyError, PartyService};
+pub use system::PartySystem;
\ No newline at end of file
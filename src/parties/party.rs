@@ -0,0 +1,242 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::characters::Character;
+
+/// How the items a party wins are shared between the members who fought for them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LootMode {
+    /// Every item goes to a random member, as if whoever got there first took it
+    FreeForAll,
+    /// Members take turns, one item each
+    #[default]
+    RoundRobin,
+    /// Everyone rolls need, greed or pass on every item, need beats greed
+    NeedGreed,
+}
+
+impl LootMode {
+    pub const ALL: [LootMode; 3] = [LootMode::FreeForAll, LootMode::RoundRobin, LootMode::NeedGreed];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LootMode::FreeForAll => "free_for_all",
+            LootMode::RoundRobin => "round_robin",
+            LootMode::NeedGreed => "need_greed",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            LootMode::FreeForAll => "Free for all",
+            LootMode::RoundRobin => "Round robin",
+            LootMode::NeedGreed => "Need before greed",
+        }
+    }
+}
+
+impl TryFrom<String> for LootMode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "free_for_all" => Ok(LootMode::FreeForAll),
+            "round_robin" => Ok(LootMode::RoundRobin),
+            "need_greed" => Ok(LootMode::NeedGreed),
+            other => Err(format!("{} is not a loot mode", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for LootMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PartyMember {
+    pub user_id: UserId,
+    pub display_name: String,
+    /// The character the player joined with
+    pub character: Character,
+    /// Set while the player has no connection open, they are dropped once the grace period is over
+    pub disconnected_at: Option<DateTime<Utc>>,
+}
+
+impl PartyMember {
+    pub fn new(user_id: UserId, display_name: String, character: Character) -> Self {
+        PartyMember { user_id, display_name, character, disconnected_at: None }
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.disconnected_at.is_none()
+    }
+}
+
+/// A group of players, kept in memory for as long as it has members
+#[derive(Debug, Clone)]
+pub struct Party {
+    pub id: Uuid,
+    /// In the order they joined
+    pub members: Vec<PartyMember>,
+    pub leader: UserId,
+    pub loot_mode: LootMode,
+    /// Where round robin looks first, an index into `members`
+    next_looter: usize,
+}
+
+impl Party {
+    pub fn new(leader: PartyMember) -> Self {
+        Party {
+            id: Uuid::new_v4(),
+            leader: leader.user_id,
+            members: vec![leader],
+            loot_mode: LootMode::default(),
+            next_looter: 0,
+        }
+    }
+
+    pub fn member(&self, user_id: UserId) -> Option<&PartyMember> {
+        self.members.iter().find(|m| m.user_id == user_id)
+    }
+
+    pub fn member_mut(&mut self, user_id: UserId) -> Option<&mut PartyMember> {
+        self.members.iter_mut().find(|m| m.user_id == user_id)
+    }
+
+    /// Display names are unique but players don't always type them the same way
+    pub fn member_named(&self, display_name: &str) -> Option<&PartyMember> {
+        let display_name = display_name.trim();
+        self.members.iter().find(|m| m.display_name.eq_ignore_ascii_case(display_name))
+    }
+
+    pub fn is_leader(&self, user_id: UserId) -> bool {
+        self.leader == user_id
+    }
+
+    pub fn leader_name(&self) -> &str {
+        self.member(self.leader).map_or("", |m| m.display_name.as_str())
+    }
+
+    /// Takes the member out, a leaving leader hands over to whoever has been in the party longest
+    pub fn remove(&mut self, user_id: UserId) -> Option<PartyMember> {
+        let position = self.members.iter().position(|m| m.user_id == user_id)?;
+        let member = self.members.remove(position);
+        if position < self.next_looter {
+            self.next_looter -= 1;
+        }
+        if self.next_looter >= self.members.len() {
+            self.next_looter = 0;
+        }
+        if self.leader == user_id {
+            if let Some(next) = self.members.first() {
+                self.leader = next.user_id;
+            }
+        }
+        Some(member)
+    }
+
+    /// Whose turn it is for the next round robin item out of `eligible`, members that weren't in
+    /// the fight are skipped and keep their turn
+    pub fn next_looter(&mut self, eligible: &[UserId]) -> Option<UserId> {
+        let count = self.members.len();
+        let position = (0..count)
+            .map(|offset| (self.next_looter + offset) % count)
+            .find(|i| eligible.contains(&self.members[*i].user_id))?;
+        self.next_looter = (position + 1) % count;
+        Some(self.members[position].user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+    use crate::authentication::UserId;
+    use crate::characters::{Attributes, Character, CharacterClass, CharacterName};
+    use crate::parties::{LootMode, Party, PartyMember};
+
+    fn member(name: &str) -> PartyMember {
+        let character = Character {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: CharacterName::parse(name.to_string()).unwrap(),
+            class: CharacterClass::Warrior,
+            level: 1,
+            experience: 0,
+            attributes: Attributes::default(),
+            created_at: Utc::now(),
+            deleted_at: None,
+        };
+        PartyMember::new(UserId::from(character.user_id), name.to_string(), character)
+    }
+
+    fn party(names: &[&str]) -> Party {
+        let mut party = Party::new(member(names[0]));
+        for name in &names[1..] {
+            party.members.push(member(name));
+        }
+        party
+    }
+
+    fn ids(party: &Party) -> Vec<UserId> {
+        party.members.iter().map(|m| m.user_id).collect()
+    }
+
+    #[test]
+    fn loot_modes_round_trip() {
+        for mode in LootMode::ALL {
+            assert_eq!(Ok(mode), LootMode::try_from(mode.as_str().to_string()));
+        }
+        assert!(LootMode::try_from("dibs".to_string()).is_err());
+    }
+
+    #[test]
+    fn leadership_goes_to_the_longest_member() {
+        let mut party = party(&["Tomas", "Maria", "Ilona"]);
+        let [tomas, maria, ilona] = ids(&party)[..] else { unreachable!() };
+
+        party.remove(ilona);
+        assert!(party.is_leader(tomas));
+        party.remove(tomas);
+        assert!(party.is_leader(maria));
+        assert_eq!("Maria", party.leader_name());
+    }
+
+    #[test]
+    fn members_are_found_by_name_in_any_case() {
+        let party = party(&["Tomas", "Maria"]);
+        assert_eq!(Some(party.members[1].user_id), party.member_named(" maria ").map(|m| m.user_id));
+        assert!(party.member_named("Ilona").is_none());
+    }
+
+    #[test]
+    fn round_robin_takes_turns_among_those_who_fought() {
+        let mut party = party(&["Tomas", "Maria", "Ilona"]);
+        let [tomas, maria, ilona] = ids(&party)[..] else { unreachable!() };
+
+        let everyone = [tomas, maria, ilona];
+        let turns: Vec<_> = (0..4).map(|_| party.next_looter(&everyone).unwrap()).collect();
+        assert_eq!(vec![tomas, maria, ilona, tomas], turns);
+
+        // Maria wasn't there, Ilona is next after her
+        assert_eq!(Some(ilona), party.next_looter(&[tomas, ilona]));
+        assert_eq!(Some(tomas), party.next_looter(&[tomas, ilona]));
+        assert_eq!(None, party.next_looter(&[]));
+    }
+
+    #[test]
+    fn round_robin_keeps_its_place_when_members_leave() {
+        let mut party = party(&["Tomas", "Maria", "Ilona"]);
+        let [tomas, maria, ilona] = ids(&party)[..] else { unreachable!() };
+        assert_eq!(Some(tomas), party.next_looter(&[tomas, maria, ilona]));
+        assert_eq!(Some(maria), party.next_looter(&[tomas, maria, ilona]));
+
+        party.remove(tomas);
+        assert_eq!(Some(ilona), party.next_looter(&[maria, ilona]));
+        party.remove(ilona);
+        assert_eq!(Some(maria), party.next_looter(&[maria]));
+    }
+}
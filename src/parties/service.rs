@@ -0,0 +1,696 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::characters::Character;
+use crate::chat::{is_blocked, ChatService};
+use crate::configuration::PartySettings;
+use crate::gateway::{ClientError, ConnectionRegistry, ErrorCode, PartyMemberView, PartyView, ServerMessage};
+use crate::items::{InventoryService, ItemStack};
+use crate::mail::{MailError, MailKind, MailService, SystemMail};
+use crate::parties::{LootChoice, LootMode, LootRoll, Looter, Party, PartyMember};
+use crate::store::{get_profile_by_display_name, get_profile_by_user_id};
+use crate::utils::error_chain_fmt;
+use crate::world::Position;
+
+#[derive(thiserror::Error)]
+pub enum PartyError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("Select a character before joining a party")]
+    NoCharacter,
+    #[error("Pick a display name on your profile before joining a party")]
+    NoDisplayName,
+    #[error("There is no player called {0}")]
+    PlayerNotFound(String),
+    #[error("{0} is not online")]
+    PlayerOffline(String),
+    #[error("{0} is not accepting invitations from you")]
+    InviteRefused(String),
+    #[error("You are already in a party")]
+    AlreadyInParty,
+    #[error("{0} is already in a party")]
+    PlayerAlreadyInParty(String),
+    #[error("You are not in a party")]
+    NotInParty,
+    #[error("Only the party leader can do that")]
+    NotLeader,
+    #[error("{0} is not in your party")]
+    NotAMember(String),
+    #[error("The party is full, it can't have more than {0} members")]
+    PartyFull(usize),
+    #[error("You have not been invited to that party")]
+    NoInvitation,
+    #[error("Wait for {0} to come back before queueing")]
+    MemberOffline(String),
+    #[error("That roll is over")]
+    LootRollNotFound,
+    #[error(transparent)]
+    Mail(#[from] MailError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for PartyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for PartyError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            PartyError::ValidationError(_) => ErrorCode::InvalidMessage,
+            PartyError::NoCharacter
+            | PartyError::NoDisplayName
+            | PartyError::InviteRefused(_)
+            | PartyError::NotLeader => ErrorCode::Forbidden,
+            PartyError::PlayerNotFound(_)
+            | PartyError::PlayerOffline(_)
+            | PartyError::NotInParty
+            | PartyError::NotAMember(_)
+            | PartyError::NoInvitation
+            | PartyError::LootRollNotFound => ErrorCode::NotFound,
+            PartyError::AlreadyInParty
+            | PartyError::PlayerAlreadyInParty(_)
+            | PartyError::PartyFull(_)
+            | PartyError::MemberOffline(_) => ErrorCode::InvalidAction,
+            PartyError::Mail(_) | PartyError::UnexpectedError(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+struct Invitation {
+    party_id: Uuid,
+    invitee: UserId,
+    expires_at: DateTime<Utc>,
+}
+
+/// Loot that was mailed or put up for rolls in a transaction that is not committed yet
+#[derive(Debug, Default)]
+pub struct LootDistribution {
+    awards: Vec<(Looter, ItemStack, Vec<Looter>)>,
+    rolls: Vec<LootRoll>,
+}
+
+#[derive(Default)]
+struct PartyState {
+    parties: HashMap<Uuid, Party>,
+    /// The party every member is in
+    members: HashMap<UserId, Uuid>,
+    invitations: Vec<Invitation>,
+    rolls: HashMap<Uuid, LootRoll>,
+}
+
+/// Parties live in memory only, keyed by the players in them. A member whose connections all
+/// close keeps their place for a grace period, `tick` drops them once it is over and also
+/// expires invitations and need before greed rolls.
+pub struct PartyService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    chat: Arc<ChatService>,
+    inventory: Arc<InventoryService>,
+    mail: Arc<MailService>,
+    settings: PartySettings,
+    state: Mutex<PartyState>,
+}
+
+impl PartyService {
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        chat: Arc<ChatService>,
+        inventory: Arc<InventoryService>,
+        mail: Arc<MailService>,
+        settings: PartySettings,
+    ) -> Self {
+        PartyService { pool, registry, chat, inventory, mail, settings, state: Mutex::new(PartyState::default()) }
+    }
+
+    /// The party the player is in, as its members see it
+    pub fn party(&self, user_id: UserId) -> Option<PartyView> {
+        let state = self.state.lock().expect("Party lock poisoned");
+        let party_id = state.members.get(&user_id)?;
+        state.parties.get(party_id).map(view)
+    }
+
+    //region Membership
+    /// Starts a party with the inviter as leader when they aren't in one yet
+    #[tracing::instrument(
+    name = "Invite to party",
+    skip(self, character)
+    )]
+    pub async fn invite(
+        &self,
+        user_id: UserId,
+        character: &Character,
+        display_name: &str,
+        now: DateTime<Utc>,
+    ) -> Result<String, PartyError> {
+        let inviter = get_profile_by_user_id(&self.pool, *user_id).await?.ok_or(PartyError::NoDisplayName)?;
+        let invitee = get_profile_by_display_name(&self.pool, display_name.trim())
+            .await?
+            .ok_or_else(|| PartyError::PlayerNotFound(display_name.trim().to_string()))?;
+        let invitee_name = invitee.display_name.to_string();
+        let invitee_id = UserId::from(invitee.user_id);
+        if invitee_id == user_id {
+            return Err(PartyError::ValidationError("You can't invite yourself".to_string()));
+        }
+        if is_blocked(&self.pool, invitee.user_id, *user_id).await? {
+            return Err(PartyError::InviteRefused(invitee_name));
+        }
+        if is_blocked(&self.pool, *user_id, invitee.user_id).await? {
+            return Err(PartyError::ValidationError(format!("Unblock {} before inviting them", invitee_name)));
+        }
+        if !self.registry.is_online(invitee_id) {
+            return Err(PartyError::PlayerOffline(invitee_name));
+        }
+
+        let mut state = self.state.lock().expect("Party lock poisoned");
+        if state.members.contains_key(&invitee_id) {
+            return Err(PartyError::PlayerAlreadyInParty(invitee_name));
+        }
+        let party_id = match state.members.get(&user_id) {
+            Some(party_id) => {
+                let party = &state.parties[party_id];
+                if !party.is_leader(user_id) {
+                    return Err(PartyError::NotLeader);
+                }
+                if party.members.len() >= self.settings.max_size {
+                    return Err(PartyError::PartyFull(self.settings.max_size));
+                }
+                party.id
+            }
+            None => {
+                let leader = PartyMember::new(user_id, inviter.display_name.to_string(), character.clone());
+                let party = Party::new(leader);
+                let party_id = party.id;
+                tracing::info!(party_id = %party_id, "Party formed");
+                state.members.insert(user_id, party_id);
+                state.parties.insert(party_id, party);
+                self.chat.set_party(user_id, Some(party_id));
+                self.broadcast(&state.parties[&party_id]);
+                party_id
+            }
+        };
+        // inviting again only pushes the invitation back out
+        state.invitations.retain(|i| !(i.party_id == party_id && i.invitee == invitee_id));
+        state.invitations.push(Invitation {
+            party_id,
+            invitee: invitee_id,
+            expires_at: now + self.settings.invite_timeout(),
+        });
+        self.registry.send_to_user(invitee_id, &ServerMessage::PartyInvite {
+            party_id,
+            from: inviter.display_name.to_string(),
+        });
+        Ok(invitee_name)
+    }
+
+    #[tracing::instrument(
+    name = "Accept party invitation",
+    skip(self, character)
+    )]
+    pub async fn accept(
+        &self,
+        user_id: UserId,
+        character: &Character,
+        party_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<(), PartyError> {
+        let profile = get_profile_by_user_id(&self.pool, *user_id).await?.ok_or(PartyError::NoDisplayName)?;
+        let mut state = self.state.lock().expect("Party lock poisoned");
+        let invited = state.invitations
+            .iter()
+            .any(|i| i.party_id == party_id && i.invitee == user_id && i.expires_at > now);
+        if !invited || !state.parties.contains_key(&party_id) {
+            return Err(PartyError::NoInvitation);
+        }
+        if state.members.contains_key(&user_id) {
+            return Err(PartyError::AlreadyInParty);
+        }
+        let party = state.parties.get_mut(&party_id).expect("Party was just found");
+        if party.members.len() >= self.settings.max_size {
+            return Err(PartyError::PartyFull(self.settings.max_size));
+        }
+        party.members.push(PartyMember::new(user_id, profile.display_name.to_string(), character.clone()));
+        state.members.insert(user_id, party_id);
+        // the other parties that invited the player are told by the notices of their own members
+        state.invitations.retain(|i| i.invitee != user_id);
+        self.chat.set_party(user_id, Some(party_id));
+
+        let party = &state.parties[&party_id];
+        self.notify_others(party, user_id, format!("{} joined the party", profile.display_name));
+        self.broadcast(party);
+        Ok(())
+    }
+
+    pub fn decline(&self, user_id: UserId, party_id: Uuid) -> Result<(), PartyError> {
+        let mut state = self.state.lock().expect("Party lock poisoned");
+        let before = state.invitations.len();
+        state.invitations.retain(|i| !(i.party_id == party_id && i.invitee == user_id));
+        if state.invitations.len() == before {
+            return Err(PartyError::NoInvitation);
+        }
+        self.disband_if_alone(&mut state, party_id);
+        Ok(())
+    }
+
+    pub fn leave(&self, user_id: UserId) -> Result<(), PartyError> {
+        let mut state = self.state.lock().expect("Party lock poisoned");
+        if !state.members.contains_key(&user_id) {
+            return Err(PartyError::NotInParty);
+        }
+        self.remove_member(&mut state, user_id, "left the party");
+        Ok(())
+    }
+
+    /// Returns the display name of whoever was kicked
+    pub fn kick(&self, user_id: UserId, display_name: &str) -> Result<String, PartyError> {
+        let mut state = self.state.lock().expect("Party lock poisoned");
+        let party = led_party(&state, user_id)?;
+        let member = party
+            .member_named(display_name)
+            .ok_or_else(|| PartyError::NotAMember(display_name.trim().to_string()))?;
+        if member.user_id == user_id {
+            return Err(PartyError::ValidationError("Leave the party instead of kicking yourself".to_string()));
+        }
+        let (kicked, name) = (member.user_id, member.display_name.clone());
+        self.registry.send_to_user(kicked, &ServerMessage::Notice { message: "You were removed from the party".to_string() });
+        self.remove_member(&mut state, kicked, "was removed from the party");
+        Ok(name)
+    }
+
+    /// Hands leadership over, returns the display name of the new leader
+    pub fn promote(&self, user_id: UserId, display_name: &str) -> Result<String, PartyError> {
+        let mut state = self.state.lock().expect("Party lock poisoned");
+        let party_id = led_party(&state, user_id)?.id;
+        let party = state.parties.get_mut(&party_id).expect("Party was just found");
+        let member = party
+            .member_named(display_name)
+            .ok_or_else(|| PartyError::NotAMember(display_name.trim().to_string()))?;
+        let (leader, name) = (member.user_id, member.display_name.clone());
+        party.leader = leader;
+        self.notify_others(party, user_id, format!("{} now leads the party", name));
+        self.broadcast(party);
+        Ok(name)
+    }
+
+    pub fn set_loot_mode(&self, user_id: UserId, mode: LootMode) -> Result<(), PartyError> {
+        let mut state = self.state.lock().expect("Party lock poisoned");
+        let party_id = led_party(&state, user_id)?.id;
+        let party = state.parties.get_mut(&party_id).expect("Party was just found");
+        party.loot_mode = mode;
+        self.notify_others(party, user_id, format!("Loot is now {}", mode.title().to_lowercase()));
+        self.broadcast(party);
+        Ok(())
+    }
+
+    /// A player opened a connection, anyone who dropped out of a party just now is back in it
+    pub fn connected(&self, user_id: UserId) {
+        let mut state = self.state.lock().expect("Party lock poisoned");
+        let Some(party_id) = state.members.get(&user_id).copied() else {
+            return;
+        };
+        let party = state.parties.get_mut(&party_id).expect("Members are in existing parties");
+        let member = party.member_mut(user_id).expect("Members are in their party");
+        let returned = member.disconnected_at.take().is_some();
+        self.chat.set_party(user_id, Some(party_id));
+        if returned {
+            let name = member.display_name.clone();
+            self.notify_others(party, user_id, format!("{} is back", name));
+        }
+        self.broadcast(party);
+    }
+
+    /// The grace period starts once the player's last connection is gone
+    pub fn disconnected(&self, user_id: UserId, now: DateTime<Utc>) {
+        if self.registry.is_online(user_id) {
+            return;
+        }
+        let mut state = self.state.lock().expect("Party lock poisoned");
+        let Some(party_id) = state.members.get(&user_id).copied() else {
+            return;
+        };
+        let party = state.parties.get_mut(&party_id).expect("Members are in existing parties");
+        party.member_mut(user_id).expect("Members are in their party").disconnected_at = Some(now);
+        self.broadcast(party);
+    }
+
+    /// Everyone who queues along with the player, only a leader can queue a party and only with
+    /// everyone online
+    pub fn queue_group(&self, user_id: UserId, character: &Character) -> Result<Vec<(UserId, Character)>, PartyError> {
+        let state = self.state.lock().expect("Party lock poisoned");
+        let Some(party_id) = state.members.get(&user_id) else {
+            return Ok(vec![(user_id, character.clone())]);
+        };
+        let party = &state.parties[party_id];
+        if !party.is_leader(user_id) {
+            return Err(PartyError::NotLeader);
+        }
+        if let Some(offline) = party.members.iter().find(|m| !m.is_online()) {
+            return Err(PartyError::MemberOffline(offline.display_name.clone()));
+        }
+        Ok(party.members
+            .iter()
+            .map(|m| if m.user_id == user_id { (user_id, character.clone()) } else { (m.user_id, m.character.clone()) })
+            .collect())
+    }
+
+    /// Online members of the players' parties that aren't among the players, with the character
+    /// they are in the party with
+    pub fn members_besides(&self, players: &[UserId]) -> Vec<(UserId, Uuid)> {
+        let state = self.state.lock().expect("Party lock poisoned");
+        let mut party_ids: Vec<Uuid> = players.iter().filter_map(|p| state.members.get(p).copied()).collect();
+        party_ids.sort();
+        party_ids.dedup();
+        party_ids
+            .iter()
+            .flat_map(|id| state.parties[id].members.iter())
+            .filter(|m| m.is_online() && !players.contains(&m.user_id))
+            .map(|m| (m.user_id, m.character.id))
+            .collect()
+    }
+    //endregion
+
+    //region Loot
+    /// Whoever won a fight first, then the online members of their party that stand within loot
+    /// range of them with the character they joined with. `players` are everyone in the world
+    /// with their character and position.
+    pub fn looters_near(&self, winner: Looter, players: &[(UserId, Uuid, Position)]) -> Vec<Looter> {
+        let Some((_, _, at)) = players.iter().find(|(user_id, _, _)| *user_id == winner.user_id) else {
+            return vec![winner];
+        };
+        let state = self.state.lock().expect("Party lock poisoned");
+        let Some(party) = state.members.get(&winner.user_id).map(|id| &state.parties[id]) else {
+            return vec![winner];
+        };
+        let nearby = party.members
+            .iter()
+            .filter(|m| m.user_id != winner.user_id && m.is_online())
+            .filter(|m| players.iter().any(|(user_id, character_id, position)| {
+                *user_id == m.user_id && *character_id == m.character.id && position.is_within(at, self.settings.loot_range)
+            }))
+            .map(|m| Looter { user_id: m.user_id, character_id: m.character.id, name: m.character.name.to_string() })
+            .collect::<Vec<_>>();
+        std::iter::once(winner).chain(nearby).collect()
+    }
+
+    /// Shares `items` between the winners of a fight the way the first looter's party wants it,
+    /// winners that aren't in that party get nothing. Without a party it is free for all.
+    /// Items arrive by mail, need before greed items once their roll is over.
+    pub async fn distribute_loot(
+        &self,
+        looters: &[Looter],
+        items: Vec<ItemStack>,
+        now: DateTime<Utc>,
+    ) -> Result<(), PartyError> {
+        let mut tx = self.pool.begin().await.context("Failed to begin loot transaction")?;
+        let distribution = self.distribute_loot_in(&mut tx, looters, items, now).await?;
+        tx.commit().await.context("Failed to commit loot")?;
+        self.announce_loot(distribution);
+        Ok(())
+    }
+
+    /// Like `distribute_loot` inside the caller's transaction, for loot that has to be handed out
+    /// together with other writes. Nobody hears about it until the caller commits and passes the
+    /// distribution to `announce_loot`.
+    #[tracing::instrument(
+    name = "Distribute loot",
+    skip(self, tx, looters, items)
+    )]
+    pub async fn distribute_loot_in(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        looters: &[Looter],
+        items: Vec<ItemStack>,
+        now: DateTime<Utc>,
+    ) -> Result<LootDistribution, PartyError> {
+        let Some(first) = looters.first() else {
+            return Ok(LootDistribution::default());
+        };
+        let mut awards = Vec::new();
+        let mut rolls = Vec::new();
+        {
+            let mut state = self.state.lock().expect("Party lock poisoned");
+            let party_id = state.members.get(&first.user_id).copied();
+            let eligible: Vec<Looter> = match party_id {
+                Some(party_id) => looters
+                    .iter()
+                    .filter(|l| state.members.get(&l.user_id) == Some(&party_id))
+                    .cloned()
+                    .collect(),
+                None => looters.to_vec(),
+            };
+            let mode = party_id.map_or(LootMode::FreeForAll, |id| state.parties[&id].loot_mode);
+            let mut rng = rand::thread_rng();
+            for item in items {
+                let looter = match (mode, party_id) {
+                    _ if eligible.len() == 1 => eligible[0].clone(),
+                    (LootMode::RoundRobin, Some(party_id)) => {
+                        let ids: Vec<UserId> = eligible.iter().map(|l| l.user_id).collect();
+                        let party = state.parties.get_mut(&party_id).expect("Members are in existing parties");
+                        let next = party.next_looter(&ids).expect("Every looter is a member");
+                        eligible.iter().find(|l| l.user_id == next).cloned().expect("Round robin picks a looter")
+                    }
+                    (LootMode::NeedGreed, Some(party_id)) => {
+                        rolls.push(LootRoll::new(party_id, item, eligible.clone(), now + self.settings.loot_roll_timeout()));
+                        continue;
+                    }
+                    _ => eligible.choose(&mut rng).cloned().expect("There is at least one looter"),
+                };
+                awards.push((looter, item, eligible.clone()));
+            }
+        }
+        self.mail_awards(tx, &awards).await?;
+        Ok(LootDistribution { awards, rolls })
+    }
+
+    /// Starts the need or greed rolls of a committed distribution and tells everyone who got what
+    pub fn announce_loot(&self, distribution: LootDistribution) {
+        let mut state = self.state.lock().expect("Party lock poisoned");
+        for roll in distribution.rolls {
+            let started = ServerMessage::LootRollStarted {
+                roll_id: roll.id,
+                item_id: roll.item.item_id.clone(),
+                quantity: roll.item.quantity,
+                seconds: self.settings.loot_roll_seconds,
+            };
+            for looter in &roll.looters {
+                self.registry.send_to_user(looter.user_id, &started);
+            }
+            state.rolls.insert(roll.id, roll);
+        }
+        drop(state);
+        self.tell_awards(distribution.awards);
+    }
+
+    /// Rolls need or greed for the player, the item is handed out once everyone answered
+    #[tracing::instrument(
+    name = "Roll for loot",
+    skip(self)
+    )]
+    pub async fn roll(&self, user_id: UserId, roll_id: Uuid, choice: LootChoice) -> Result<(), PartyError> {
+        let finished = {
+            let mut state = self.state.lock().expect("Party lock poisoned");
+            let roll = state.rolls
+                .get_mut(&roll_id)
+                .filter(|r| r.is_looter(user_id))
+                .ok_or(PartyError::LootRollNotFound)?;
+            if roll.has_chosen(user_id) {
+                return Err(PartyError::ValidationError("You already rolled for that".to_string()));
+            }
+            let number = match choice {
+                LootChoice::Pass => 0,
+                LootChoice::Need | LootChoice::Greed => rand::thread_rng().gen_range(1..=100),
+            };
+            roll.choices.push((user_id, choice, number));
+            if !roll.is_complete() {
+                return Ok(());
+            }
+            state.rolls.remove(&roll_id).expect("Roll was just found")
+        };
+        self.finish_roll(finished).await
+    }
+
+    async fn finish_roll(&self, roll: LootRoll) -> Result<(), PartyError> {
+        let winner = roll.winner().map(|(looter, number)| (looter.clone(), number));
+        let ended = ServerMessage::LootRollEnded {
+            roll_id: roll.id,
+            winner: winner.as_ref().map(|(l, _)| l.name.clone()),
+            roll: winner.as_ref().map(|(_, n)| *n),
+        };
+        for looter in &roll.looters {
+            self.registry.send_to_user(looter.user_id, &ended);
+        }
+        match winner {
+            Some((looter, _)) => self.award(vec![(looter, roll.item, roll.looters)]).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Mails every item to its looter and tells everyone who shared in the loot
+    async fn award(&self, awards: Vec<(Looter, ItemStack, Vec<Looter>)>) -> Result<(), PartyError> {
+        let mut tx = self.pool.begin().await.context("Failed to begin loot transaction")?;
+        self.mail_awards(&mut tx, &awards).await?;
+        tx.commit().await.context("Failed to commit loot")?;
+        self.tell_awards(awards);
+        Ok(())
+    }
+
+    async fn mail_awards(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        awards: &[(Looter, ItemStack, Vec<Looter>)],
+    ) -> Result<(), PartyError> {
+        for (looter, item, _) in awards {
+            let name = self.inventory
+                .catalog()
+                .get(&item.item_id)
+                .map_or(item.item_id.clone(), |i| i.name.clone());
+            let mail = SystemMail {
+                recipient_id: looter.character_id,
+                subject: "Loot".to_string(),
+                body: format!("You looted {} x{}.", name, item.quantity),
+                money: None,
+                items: vec![item.clone()],
+            };
+            self.mail.deliver(tx, MailKind::System, &self.settings.loot_sender, mail).await?;
+        }
+        Ok(())
+    }
+
+    fn tell_awards(&self, awards: Vec<(Looter, ItemStack, Vec<Looter>)>) {
+        for (looter, item, sharing) in awards {
+            let awarded = ServerMessage::LootAwarded { item_id: item.item_id, quantity: item.quantity, to: looter.name };
+            for other in sharing {
+                self.registry.send_to_user(other.user_id, &awarded);
+            }
+        }
+    }
+    //endregion
+
+    /// Expires invitations and rolls, drops members whose grace period is over
+    #[tracing::instrument(
+    name = "Party tick",
+    skip(self)
+    )]
+    pub async fn tick(&self, now: DateTime<Utc>) -> Result<(), PartyError> {
+        let expired_rolls = {
+            let mut state = self.state.lock().expect("Party lock poisoned");
+            let (expired, invitations): (Vec<Invitation>, Vec<Invitation>) = std::mem::take(&mut state.invitations)
+                .into_iter()
+                .partition(|i| i.expires_at <= now);
+            state.invitations = invitations;
+            for invitation in expired {
+                self.disband_if_alone(&mut state, invitation.party_id);
+            }
+
+            let gone: Vec<UserId> = state.parties
+                .values()
+                .flat_map(|p| p.members.iter())
+                .filter(|m| m.disconnected_at.is_some_and(|at| now - at >= self.settings.disconnect_grace()))
+                .map(|m| m.user_id)
+                .collect();
+            for user_id in gone {
+                self.remove_member(&mut state, user_id, "was disconnected");
+            }
+
+            let rolls: Vec<Uuid> = state.rolls.values().filter(|r| r.deadline <= now).map(|r| r.id).collect();
+            rolls.into_iter().filter_map(|id| state.rolls.remove(&id)).collect::<Vec<_>>()
+        };
+        for roll in expired_rolls {
+            self.finish_roll(roll).await?;
+        }
+        Ok(())
+    }
+
+    /// Takes the player out of their party and disbands it when nobody would be left to play with
+    fn remove_member(&self, state: &mut PartyState, user_id: UserId, reason: &str) {
+        let Some(party_id) = state.members.remove(&user_id) else {
+            return;
+        };
+        let party = state.parties.get_mut(&party_id).expect("Members are in existing parties");
+        let Some(member) = party.remove(user_id) else {
+            return;
+        };
+        self.chat.set_party(user_id, None);
+        self.registry.send_to_user(user_id, &ServerMessage::PartyLeft { party_id });
+        tracing::info!(party_id = %party_id, "{} {}", member.display_name, reason);
+
+        if !self.disband_if_alone(state, party_id) {
+            let party = &state.parties[&party_id];
+            self.notify_others(party, user_id, format!("{} {}", member.display_name, reason));
+            self.broadcast(party);
+        }
+    }
+
+    /// A party needs two members, or one waiting on an invitation
+    fn disband_if_alone(&self, state: &mut PartyState, party_id: Uuid) -> bool {
+        let Some(party) = state.parties.get(&party_id) else {
+            return false;
+        };
+        let invited = state.invitations.iter().any(|i| i.party_id == party_id);
+        if party.members.len() > 1 || (party.members.len() == 1 && invited) {
+            return false;
+        }
+        let party = state.parties.remove(&party_id).expect("Party was just found");
+        state.invitations.retain(|i| i.party_id != party_id);
+        state.rolls.retain(|_, r| r.party_id != party_id);
+        for member in party.members {
+            state.members.remove(&member.user_id);
+            self.chat.set_party(member.user_id, None);
+            self.registry.send_to_user(member.user_id, &ServerMessage::PartyLeft { party_id });
+            self.registry.send_to_user(member.user_id, &ServerMessage::Notice { message: "Your party was disbanded".to_string() });
+        }
+        tracing::info!(party_id = %party_id, "Party disbanded");
+        true
+    }
+
+    fn broadcast(&self, party: &Party) {
+        let update = ServerMessage::PartyUpdate { party: view(party) };
+        for member in &party.members {
+            self.registry.send_to_user(member.user_id, &update);
+        }
+    }
+
+    fn notify_others(&self, party: &Party, user_id: UserId, message: String) {
+        let notice = ServerMessage::Notice { message };
+        for member in party.members.iter().filter(|m| m.user_id != user_id) {
+            self.registry.send_to_user(member.user_id, &notice);
+        }
+    }
+}
+
+/// The party the player leads
+fn led_party(state: &PartyState, user_id: UserId) -> Result<&Party, PartyError> {
+    let party_id = state.members.get(&user_id).ok_or(PartyError::NotInParty)?;
+    let party = &state.parties[party_id];
+    if !party.is_leader(user_id) {
+        return Err(PartyError::NotLeader);
+    }
+    Ok(party)
+}
+
+fn view(party: &Party) -> PartyView {
+    PartyView {
+        id: party.id,
+        leader: party.leader_name().to_string(),
+        loot_mode: party.loot_mode,
+        members: party.members
+            .iter()
+            .map(|m| PartyMemberView {
+                display_name: m.display_name.clone(),
+                character: m.character.name.as_ref().to_string(),
+                level: m.character.level,
+                online: m.is_online(),
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::game_loop::{GameSystem, TickContext};
+use crate::parties::PartyService;
+
+/// Expires party invitations and loot rolls and drops members who didn't come back in time
+pub struct PartySystem {
+    parties: Arc<PartyService>,
+}
+
+impl PartySystem {
+    pub fn new(parties: Arc<PartyService>) -> Self {
+        PartySystem { parties }
+    }
+}
+
+#[async_trait]
+impl GameSystem for PartySystem {
+    fn name(&self) -> &'static str {
+        "parties"
+    }
+
+    async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error> {
+        self.parties.tick(ctx.now).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+mod status;
+mod store;
+
+pub use status::PresenceStatus;
+pub use store::PresenceStore;
\ No newline at end of file
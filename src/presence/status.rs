@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+/// What friends see next to a player's name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    Online,
+    Away,
+    InCombat,
+    Offline,
+}
+
+impl PresenceStatus {
+    pub const ALL: [PresenceStatus; 4] = [
+        PresenceStatus::Online,
+        PresenceStatus::Away,
+        PresenceStatus::InCombat,
+        PresenceStatus::Offline,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PresenceStatus::Online => "online",
+            PresenceStatus::Away => "away",
+            PresenceStatus::InCombat => "in_combat",
+            PresenceStatus::Offline => "offline",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            PresenceStatus::Online => "Online",
+            PresenceStatus::Away => "Away",
+            PresenceStatus::InCombat => "In combat",
+            PresenceStatus::Offline => "Offline",
+        }
+    }
+
+    /// A fight counts as activity, only a connected player who stopped sending anything is away
+    pub fn of_connection(in_combat: bool, idle: Duration, away_after: Duration) -> Self {
+        if in_combat {
+            PresenceStatus::InCombat
+        } else if idle >= away_after {
+            PresenceStatus::Away
+        } else {
+            PresenceStatus::Online
+        }
+    }
+}
+
+impl TryFrom<String> for PresenceStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "online" => Ok(PresenceStatus::Online),
+            "away" => Ok(PresenceStatus::Away),
+            "in_combat" => Ok(PresenceStatus::InCombat),
+            "offline" => Ok(PresenceStatus::Offline),
+            other => Err(format!("{} is not a valid presence status", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for PresenceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use claim::{assert_err, assert_ok_eq};
+    use crate::presence::PresenceStatus;
+
+    #[test]
+    fn all_statuses_round_trip() {
+        for status in PresenceStatus::ALL {
+            assert_ok_eq!(PresenceStatus::try_from(status.as_str().to_uppercase()), status);
+        }
+        assert_err!(PresenceStatus::try_from("busy".to_string()));
+    }
+
+    #[test]
+    fn fighting_beats_being_idle() {
+        let away_after = Duration::from_secs(300);
+        assert_eq!(PresenceStatus::Online, PresenceStatus::of_connection(false, Duration::from_secs(299), away_after));
+        assert_eq!(PresenceStatus::Away, PresenceStatus::of_connection(false, away_after, away_after));
+        assert_eq!(PresenceStatus::InCombat, PresenceStatus::of_connection(true, Duration::from_secs(900), away_after));
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+use redis::aio::ConnectionManager;
+use redis::RedisError;
+use uuid::Uuid;
+use crate::presence::PresenceStatus;
+
+/// One key per connected account holding its status. Keys expire on their own when the heartbeats
+/// stop, a crashed server leaves nobody online for longer than the TTL.
+#[derive(Clone)]
+pub struct PresenceStore {
+    redis: ConnectionManager,
+    key_prefix: String,
+    ttl: Duration,
+}
+
+impl PresenceStore {
+    pub fn new(redis: ConnectionManager, key_prefix: String, ttl: Duration) -> Self {
+        PresenceStore { redis, key_prefix, ttl }
+    }
+
+    fn key(&self, user_id: Uuid) -> String {
+        format!("{}:{}", self.key_prefix, user_id)
+    }
+
+    /// Offline when there is no key, or one this code didn't write
+    pub async fn get(&self, user_id: Uuid) -> Result<PresenceStatus, RedisError> {
+        let mut redis = self.redis.clone();
+        let value: Option<String> = redis::cmd("GET").arg(self.key(user_id)).query_async(&mut redis).await?;
+        Ok(value
+            .and_then(|v| PresenceStatus::try_from(v).ok())
+            .unwrap_or(PresenceStatus::Offline))
+    }
+
+    /// Stores the status with a fresh TTL and returns the one it replaced
+    pub async fn heartbeat(&self, user_id: Uuid, status: PresenceStatus) -> Result<PresenceStatus, RedisError> {
+        let previous = self.get(user_id).await?;
+        let mut redis = self.redis.clone();
+        redis::cmd("SET")
+            .arg(self.key(user_id))
+            .arg(status.as_str())
+            .arg("EX")
+            .arg(self.ttl.as_secs().max(1))
+            .query_async::<_, ()>(&mut redis)
+            .await?;
+        Ok(previous)
+    }
+
+    /// Returns the status the player had before going offline
+    pub async fn clear(&self, user_id: Uuid) -> Result<PresenceStatus, RedisError> {
+        let previous = self.get(user_id).await?;
+        let mut redis = self.redis.clone();
+        redis::cmd("DEL").arg(self.key(user_id)).query_async::<_, ()>(&mut redis).await?;
+        Ok(previous)
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use crate::combat::CombatRules;
+use crate::events::GameEvent;
+use crate::game_data::{data_directory, load_data_files, GameDataError};
+use crate::items::ItemCatalog;
+use crate::quests::{Objective, QuestDefinition};
+use crate::world::WorldMap;
+
+#[derive(serde::Deserialize)]
+struct QuestFile {
+    #[serde(default)]
+    quests: Vec<QuestDefinition>,
+}
+
+/// Every quest from `data/quests`. Prerequisites are checked to exist and not go in circles,
+/// creatures, items and zones are checked by `check_references` once the rest of the data is loaded.
+#[derive(Debug, Default)]
+pub struct QuestBook {
+    quests: HashMap<String, QuestDefinition>,
+    /// What the quests listen for, so events nobody cares about skip the database
+    creatures: HashSet<String>,
+    reach_zones: HashSet<String>,
+    npcs: HashSet<(String, String)>,
+}
+
+impl QuestBook {
+    pub fn new(definitions: Vec<QuestDefinition>) -> Result<Self, GameDataError> {
+        let mut book = QuestBook::default();
+        for definition in definitions {
+            definition.validate().map_err(GameDataError::Invalid)?;
+            if book.quests.contains_key(&definition.id) {
+                return Err(GameDataError::Invalid(format!("Quest {} is defined twice", definition.id)));
+            }
+            for objective in &definition.objectives {
+                match objective {
+                    Objective::Kill { creature, .. } => {
+                        book.creatures.insert(creature.clone());
+                    }
+                    Objective::Reach { zone, .. } => {
+                        book.reach_zones.insert(zone.clone());
+                    }
+                    Objective::Talk { npc, zone, .. } => {
+                        book.npcs.insert((npc.clone(), zone.clone()));
+                    }
+                    Objective::Collect { .. } => {}
+                }
+            }
+            book.quests.insert(definition.id.clone(), definition);
+        }
+
+        for quest in book.quests.values() {
+            if let Some(missing) = quest.prerequisites.iter().find(|p| !book.quests.contains_key(*p)) {
+                return Err(GameDataError::Invalid(format!("Quest {} requires unknown quest {}", quest.id, missing)));
+            }
+        }
+        book.check_prerequisite_cycles()?;
+        Ok(book)
+    }
+
+    pub fn load(directory: &Path) -> Result<Self, GameDataError> {
+        let mut definitions = Vec::new();
+        for (path, file) in load_data_files::<QuestFile>(directory)? {
+            for definition in file.quests {
+                definition.validate()
+                    .map_err(|e| GameDataError::Invalid(format!("{}: {}", path.display(), e)))?;
+                definitions.push(definition);
+            }
+        }
+        Self::new(definitions)
+    }
+
+    /// Depth first from every quest, a quest showing up again on its own path is a cycle
+    fn check_prerequisite_cycles(&self) -> Result<(), GameDataError> {
+        fn visit<'a>(
+            book: &'a QuestBook,
+            id: &'a str,
+            path: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+        ) -> Result<(), GameDataError> {
+            if done.contains(id) {
+                return Ok(());
+            }
+            if let Some(start) = path.iter().position(|p| *p == id) {
+                let mut cycle = path[start..].to_vec();
+                cycle.push(id);
+                return Err(GameDataError::Invalid(format!("Quest prerequisites go in a circle: {}", cycle.join(" -> "))));
+            }
+            path.push(id);
+            for prerequisite in &book.quests[id].prerequisites {
+                visit(book, prerequisite, path, done)?;
+            }
+            path.pop();
+            done.insert(id);
+            Ok(())
+        }
+
+        let mut ids: Vec<&str> = self.quests.keys().map(String::as_str).collect();
+        ids.sort();
+        let mut done = HashSet::new();
+        for id in ids {
+            visit(self, id, &mut Vec::new(), &mut done)?;
+        }
+        Ok(())
+    }
+
+    /// Everything the quests reference that doesn't exist in the other data files, empty when all is well
+    pub fn check_references(&self, items: &ItemCatalog, map: &WorldMap, rules: &CombatRules) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut ids: Vec<&String> = self.quests.keys().collect();
+        ids.sort();
+        for quest in ids.into_iter().map(|id| &self.quests[id]) {
+            for (i, objective) in quest.objectives.iter().enumerate() {
+                let problem = match objective {
+                    Objective::Kill { creature, .. } if rules.creature(creature).is_none() => {
+                        Some(format!("unknown creature {}", creature))
+                    }
+                    Objective::Collect { item, .. } if !items.contains(item) => Some(format!("unknown item {}", item)),
+                    Objective::Reach { zone, x, y, .. } => match map.get(zone) {
+                        None => Some(format!("unknown zone {}", zone)),
+                        Some(z) if !z.is_walkable(*x, *y) => Some(format!("{},{} in zone {} can't be walked on", x, y, zone)),
+                        Some(_) => None,
+                    },
+                    Objective::Talk { zone, .. } if !map.contains(zone) => Some(format!("unknown zone {}", zone)),
+                    _ => None,
+                };
+                if let Some(problem) = problem {
+                    problems.push(format!("Quest {} objective {}: {}", quest.id, i + 1, problem));
+                }
+            }
+            for reward in quest.rewards.items.iter().filter(|r| !items.contains(&r.item)) {
+                problems.push(format!("Quest {} rewards unknown item {}", quest.id, reward.item));
+            }
+        }
+        problems
+    }
+
+    pub fn get(&self, id: &str) -> Option<&QuestDefinition> {
+        self.quests.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.quests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quests.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&QuestDefinition> {
+        self.quests.values()
+    }
+
+    /// Whether a talk objective has the npc standing in that zone
+    pub fn has_npc(&self, npc_id: &str, zone_id: &str) -> bool {
+        self.npcs.contains(&(npc_id.to_string(), zone_id.to_string()))
+    }
+
+    /// Whether any quest could move forward because of `event`
+    pub fn is_interested(&self, event: &GameEvent) -> bool {
+        match event {
+            GameEvent::CreatureDefeated { creature_id, .. } | GameEvent::PartyKill { creature_id, .. } => {
+                self.creatures.contains(creature_id)
+            }
+            GameEvent::PositionChanged { position, .. } => self.reach_zones.contains(&position.zone_id),
+            GameEvent::TalkedTo { npc_id, zone_id, .. } => self.has_npc(npc_id, zone_id),
+            GameEvent::ItemCrafted { .. }
+            | GameEvent::CharacterDied { .. }
+            | GameEvent::QuestCompleted { .. }
+            | GameEvent::GoldEarned { .. } => false,
+        }
+    }
+}
+
+pub fn get_quest_book() -> Result<QuestBook, GameDataError> {
+    QuestBook::load(&data_directory().join("quests"))
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use uuid::Uuid;
+    use crate::combat::get_combat_rules;
+    use crate::items::get_item_catalog;
+    use crate::quests::{get_quest_book, QuestBook};
+    use crate::world::get_world_map;
+
+    fn load(content: &str) -> Result<QuestBook, crate::game_data::GameDataError> {
+        let directory = std::env::temp_dir().join(format!("yaug-quests-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("quests.toml"), content).unwrap();
+        let book = QuestBook::load(&directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+        book
+    }
+
+    fn quest(id: &str, prerequisites: &[&str], objective: &str) -> String {
+        format!(
+            "[[quests]]\nid = \"{}\"\nname = \"{}\"\nprerequisites = {:?}\n[[quests.objectives]]\n{}\n",
+            id, id, prerequisites, objective
+        )
+    }
+
+    #[test]
+    fn shipped_quests_reference_existing_data() {
+        let book = assert_ok!(get_quest_book());
+        assert!(!book.is_empty());
+        let problems = book.check_references(
+            &get_item_catalog().unwrap(),
+            &get_world_map().unwrap(),
+            &get_combat_rules().unwrap(),
+        );
+        assert_eq!(Vec::<String>::new(), problems);
+    }
+
+    #[test]
+    fn objectives_are_read_by_type() {
+        let book = assert_ok!(load(&quest(
+            "lost_ring", &[], "type = \"reach\"\nzone = \"greenvale\"\nx = 2\ny = 3\nradius = 1",
+        )));
+        assert_eq!(1, book.get("lost_ring").unwrap().objectives[0].required());
+        assert_err!(load(&quest("lost_ring", &[], "type = \"dance\"")));
+    }
+
+    #[test]
+    fn prerequisites_must_exist() {
+        assert_err!(load(&quest("second", &["first"], "type = \"talk\"\nnpc = \"elder\"\nzone = \"greenvale\"")));
+    }
+
+    #[test]
+    fn prerequisites_cannot_go_in_circles() {
+        let talk = "type = \"talk\"\nnpc = \"elder\"\nzone = \"greenvale\"";
+        let quests = [
+            quest("first", &["third"], talk),
+            quest("second", &["first"], talk),
+            quest("third", &["second"], talk),
+        ];
+        assert_err!(load(&quests.join("\n")));
+        assert_ok!(load(&quests[1..].join("\n").replace("[\"first\"]", "[]")));
+    }
+
+    #[test]
+    fn unknown_references_are_reported() {
+        let book = assert_ok!(load(&[
+            quest("hunt", &[], "type = \"kill\"\ncreature = \"dragon\""),
+            quest("gather", &[], "type = \"collect\"\nitem = \"moon_dust\""),
+            quest("travel", &[], "type = \"reach\"\nzone = \"greenvale\"\nx = 0\ny = 0"),
+            quest("chat", &[], "type = \"talk\"\nnpc = \"elder\"\nzone = \"atlantis\""),
+        ].join("\n")));
+
+        let problems = book.check_references(
+            &get_item_catalog().unwrap(),
+            &get_world_map().unwrap(),
+            &get_combat_rules().unwrap(),
+        );
+
+        assert_eq!(4, problems.len(), "{:?}", problems);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use crate::game_data::is_valid_id;
+use crate::ledger::Currency;
+
+fn one() -> i32 {
+    1
+}
+
+fn first_level() -> i32 {
+    1
+}
+
+/// What a quest asks for, every objective of a quest can be worked on at the same time
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Objective {
+    Kill {
+        creature: String,
+        #[serde(default = "one")]
+        count: i32,
+        #[serde(default)]
+        description: Option<String>,
+    },
+    /// Counted from the inventory, the items are taken when the quest is handed in
+    Collect {
+        item: String,
+        #[serde(default = "one")]
+        count: i32,
+        #[serde(default)]
+        description: Option<String>,
+    },
+    /// Standing within `radius` tiles of `x`,`y` in `zone`
+    Reach {
+        zone: String,
+        x: i32,
+        y: i32,
+        #[serde(default)]
+        radius: i32,
+        #[serde(default)]
+        description: Option<String>,
+    },
+    Talk {
+        npc: String,
+        zone: String,
+        #[serde(default)]
+        description: Option<String>,
+    },
+}
+
+impl Objective {
+    /// What the progress counter has to reach
+    pub fn required(&self) -> i32 {
+        match self {
+            Objective::Kill { count, .. } | Objective::Collect { count, .. } => *count,
+            Objective::Reach { .. } | Objective::Talk { .. } => 1,
+        }
+    }
+
+    /// Written by hand in the data files, otherwise made up from ids where that makes sense
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            Objective::Kill { description, .. }
+            | Objective::Collect { description, .. }
+            | Objective::Reach { description, .. }
+            | Objective::Talk { description, .. } => description.as_deref(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Objective::Kill { creature: id, count, .. } | Objective::Collect { item: id, count, .. } => {
+                if !is_valid_id(id) {
+                    return Err(format!("{:?} is not a valid id", id));
+                }
+                if *count <= 0 {
+                    return Err(format!("{} is not a valid count", count));
+                }
+            }
+            Objective::Reach { zone, radius, .. } => {
+                if !is_valid_id(zone) {
+                    return Err(format!("{:?} is not a valid zone id", zone));
+                }
+                if *radius < 0 {
+                    return Err(format!("{} is not a valid radius", radius));
+                }
+            }
+            Objective::Talk { npc, zone, .. } => {
+                if !is_valid_id(npc) || !is_valid_id(zone) {
+                    return Err(format!("{:?} in {:?} is not a valid npc", npc, zone));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct ItemReward {
+    pub item: String,
+    #[serde(default = "one")]
+    pub quantity: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+pub struct QuestRewards {
+    pub experience: i64,
+    pub currency: BTreeMap<Currency, i64>,
+    pub items: Vec<ItemReward>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct QuestDefinition {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Characters below this level can't accept the quest
+    #[serde(default = "first_level")]
+    pub level: i32,
+    /// Quests that have to be completed first
+    #[serde(default)]
+    pub prerequisites: Vec<String>,
+    /// Can be accepted again after handing it in
+    #[serde(default)]
+    pub repeatable: bool,
+    pub objectives: Vec<Objective>,
+    #[serde(default)]
+    pub rewards: QuestRewards,
+}
+
+impl QuestDefinition {
+    /// Checks the quest on its own, references to other data are checked by the quest book
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_id(&self.id) {
+            return Err(format!("{:?} is not a valid quest id", self.id));
+        }
+        if self.name.trim().is_empty() {
+            return Err(format!("Quest {} has no name", self.id));
+        }
+        if self.level < 1 {
+            return Err(format!("Quest {} needs a level of at least 1", self.id));
+        }
+        if self.objectives.is_empty() {
+            return Err(format!("Quest {} has no objectives", self.id));
+        }
+        for (i, objective) in self.objectives.iter().enumerate() {
+            objective.validate().map_err(|e| format!("Quest {} objective {}: {}", self.id, i + 1, e))?;
+        }
+        if self.prerequisites.contains(&self.id) {
+            return Err(format!("Quest {} requires itself", self.id));
+        }
+        if self.rewards.experience < 0 {
+            return Err(format!("Quest {} takes experience away", self.id));
+        }
+        if let Some((currency, amount)) = self.rewards.currency.iter().find(|(_, amount)| **amount <= 0) {
+            return Err(format!("Quest {} rewards {} {}, rewards have to be positive", self.id, amount, currency));
+        }
+        if let Some(reward) = self.rewards.items.iter().find(|r| r.quantity <= 0) {
+            return Err(format!("Quest {} rewards {} {}, rewards have to be positive", self.id, reward.quantity, reward.item));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use crate::quests::{Objective, QuestDefinition, QuestRewards};
+
+    fn quest() -> QuestDefinition {
+        QuestDefinition {
+            id: "wolf_trouble".to_string(),
+            name: "Wolf Trouble".to_string(),
+            description: String::new(),
+            level: 1,
+            prerequisites: vec![],
+            repeatable: false,
+            objectives: vec![Objective::Kill { creature: "wolf".to_string(), count: 3, description: None }],
+            rewards: QuestRewards::default(),
+        }
+    }
+
+    #[test]
+    fn a_simple_quest_is_valid() {
+        assert_ok!(quest().validate());
+    }
+
+    #[test]
+    fn quests_need_objectives() {
+        let mut quest = quest();
+        quest.objectives.clear();
+        assert_err!(quest.validate());
+    }
+
+    #[test]
+    fn objective_counts_must_be_positive() {
+        let mut quest = quest();
+        quest.objectives = vec![Objective::Collect { item: "wolf_pelt".to_string(), count: 0, description: None }];
+        assert_err!(quest.validate());
+    }
+
+    #[test]
+    fn quests_cannot_require_themselves() {
+        let mut quest = quest();
+        quest.prerequisites.push(quest.id.clone());
+        assert_err!(quest.validate());
+    }
+
+    #[test]
+    fn rewards_must_be_positive() {
+        let mut quest = quest();
+        quest.rewards.currency.insert(crate::ledger::Currency::Gold, -5);
+        assert_err!(quest.validate());
+    }
+}
\ No newline at end of file
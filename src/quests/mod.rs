@@ -0,0 +1,13 @@
+mod book;
+mod definition;
+mod progress;
+mod service;
+mod store;
+mod system;
+
+pub use book::{get_quest_book, QuestBook};
+pub use definition::{ItemReward, Objective, QuestDefinition, QuestRewards};
+pub use progress::{advance, current_progress, is_finished};
+pub use service::{ActiveQuest, QuestError, QuestJournal, QuestService};
+pub use store::{get_character_quests, CharacterQuest, QuestStatus};
+pub use system::QuestProgressSystem;
\ No newline at end of file
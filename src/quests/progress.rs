@@ -0,0 +1,146 @@
+use crate::events::GameEvent;
+use crate::items::Inventory;
+use crate::quests::{Objective, QuestDefinition};
+use crate::world::Position;
+
+/// Moves the counters of `progress` forward for `event`, returns the objectives that changed.
+/// Counters never go past what the objective requires.
+pub fn advance(definition: &QuestDefinition, progress: &mut [i32], event: &GameEvent) -> Vec<usize> {
+    let mut changed = Vec::new();
+    for (i, (objective, counter)) in definition.objectives.iter().zip(progress.iter_mut()).enumerate() {
+        let counts = match (objective, event) {
+            (Objective::Kill { creature, .. }, GameEvent::CreatureDefeated { creature_id, .. } | GameEvent::PartyKill { creature_id, .. }) => {
+                creature == creature_id
+            }
+            (Objective::Reach { zone, x, y, radius, .. }, GameEvent::PositionChanged { position, .. }) => {
+                position.is_within(&Position::new(zone.as_str(), *x, *y), *radius)
+            }
+            (Objective::Talk { npc, zone, .. }, GameEvent::TalkedTo { npc_id, zone_id, .. }) => npc == npc_id && zone == zone_id,
+            _ => false,
+        };
+        if counts && *counter < objective.required() {
+            *counter += 1;
+            changed.push(i);
+        }
+    }
+    changed
+}
+
+/// Progress as the player sees it, collect objectives count what is in the inventory right now
+pub fn current_progress(definition: &QuestDefinition, progress: &[i32], inventory: &Inventory) -> Vec<i32> {
+    definition.objectives.iter()
+        .enumerate()
+        .map(|(i, objective)| match objective {
+            Objective::Collect { item, count, .. } => inventory.count(item).min(*count),
+            _ => progress.get(i).copied().unwrap_or(0),
+        })
+        .collect()
+}
+
+pub fn is_finished(definition: &QuestDefinition, progress: &[i32], inventory: &Inventory) -> bool {
+    definition.objectives.iter()
+        .zip(current_progress(definition, progress, inventory))
+        .all(|(objective, current)| current >= objective.required())
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::authentication::UserId;
+    use crate::events::GameEvent;
+    use crate::items::{Inventory, ItemStack};
+    use crate::quests::{advance, current_progress, is_finished, Objective, QuestDefinition, QuestRewards};
+    use crate::world::Position;
+
+    fn quest() -> QuestDefinition {
+        QuestDefinition {
+            id: "wolf_trouble".to_string(),
+            name: "Wolf Trouble".to_string(),
+            description: String::new(),
+            level: 1,
+            prerequisites: vec![],
+            repeatable: false,
+            objectives: vec![
+                Objective::Kill { creature: "wolf".to_string(), count: 2, description: None },
+                Objective::Collect { item: "wolf_pelt".to_string(), count: 3, description: None },
+                Objective::Reach { zone: "old_forest".to_string(), x: 5, y: 5, radius: 1, description: None },
+                Objective::Talk { npc: "elder_maren".to_string(), zone: "greenvale".to_string(), description: None },
+            ],
+            rewards: QuestRewards::default(),
+        }
+    }
+
+    fn user_id() -> UserId {
+        UserId::from(Uuid::nil())
+    }
+
+    fn defeated(creature_id: &str) -> GameEvent {
+        GameEvent::CreatureDefeated { user_id: user_id(), character_id: Uuid::nil(), creature_id: creature_id.to_string() }
+    }
+
+    fn moved(zone_id: &str, x: i32, y: i32) -> GameEvent {
+        GameEvent::PositionChanged { user_id: user_id(), character_id: Uuid::nil(), position: Position::new(zone_id, x, y) }
+    }
+
+    fn talked(npc_id: &str, zone_id: &str) -> GameEvent {
+        GameEvent::TalkedTo {
+            user_id: user_id(),
+            character_id: Uuid::nil(),
+            npc_id: npc_id.to_string(),
+            zone_id: zone_id.to_string(),
+        }
+    }
+
+    fn pelts(quantity: i32) -> Inventory {
+        Inventory::from_stacks(10, vec![(0, ItemStack { item_id: "wolf_pelt".to_string(), quantity })]).unwrap()
+    }
+
+    #[test]
+    fn kills_count_up_to_what_is_required() {
+        let quest = quest();
+        let mut progress = vec![0; 4];
+
+        assert_eq!(vec![0], advance(&quest, &mut progress, &defeated("wolf")));
+        assert_eq!(Vec::<usize>::new(), advance(&quest, &mut progress, &defeated("boar")));
+        advance(&quest, &mut progress, &defeated("wolf"));
+        assert_eq!(Vec::<usize>::new(), advance(&quest, &mut progress, &defeated("wolf")));
+
+        assert_eq!(vec![2, 0, 0, 0], progress);
+    }
+
+    #[test]
+    fn locations_are_reached_within_the_radius() {
+        let quest = quest();
+        let mut progress = vec![0; 4];
+
+        assert!(advance(&quest, &mut progress, &moved("greenvale", 5, 5)).is_empty());
+        assert!(advance(&quest, &mut progress, &moved("old_forest", 3, 5)).is_empty());
+        assert_eq!(vec![2], advance(&quest, &mut progress, &moved("old_forest", 6, 4)));
+    }
+
+    #[test]
+    fn npcs_have_to_be_talked_to_in_their_zone() {
+        let quest = quest();
+        let mut progress = vec![0; 4];
+
+        assert!(advance(&quest, &mut progress, &talked("elder_maren", "old_forest")).is_empty());
+        assert_eq!(vec![3], advance(&quest, &mut progress, &talked("elder_maren", "greenvale")));
+    }
+
+    #[test]
+    fn collected_items_come_from_the_inventory() {
+        let quest = quest();
+        let progress = vec![2, 0, 1, 1];
+
+        assert_eq!(vec![2, 2, 1, 1], current_progress(&quest, &progress, &pelts(2)));
+        assert!(!is_finished(&quest, &progress, &pelts(2)));
+        assert_eq!(vec![2, 3, 1, 1], current_progress(&quest, &progress, &pelts(7)));
+        assert!(is_finished(&quest, &progress, &pelts(7)));
+    }
+
+    #[test]
+    fn unfinished_objectives_keep_the_quest_open() {
+        let quest = quest();
+        assert!(!is_finished(&quest, &[2, 0, 0, 1], &pelts(3)));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,322 @@
+use std::collections::HashSet;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use anyhow::Context;
+use sqlx::PgPool;
+use crate::authentication::UserId;
+use crate::characters::{add_experience, Character};
+use crate::combat::CombatRules;
+use crate::events::{GameEvent, GameEvents};
+use crate::gateway::{ClientError, ConnectionRegistry, ErrorCode, ServerMessage};
+use crate::items::{InventoryError, InventoryService};
+use crate::ledger::{transfer, Currency, LedgerAccount, SystemAccount, Transfer};
+use crate::quests::{advance, current_progress, is_finished, Objective, QuestBook, QuestDefinition};
+use crate::quests::store::{get_character_quests, lock_active_quests, lock_character_quest, store_abandoned_quest, store_accepted_quest, store_completed_quest, store_quest_progress, QuestStatus};
+use crate::utils::error_chain_fmt;
+use crate::world::WorldService;
+
+#[derive(thiserror::Error)]
+pub enum QuestError {
+    #[error("Select a character first")]
+    NoCharacter,
+    #[error("There is no quest called {0}")]
+    UnknownQuest(String),
+    #[error("There is nobody called {0} around here")]
+    UnknownNpc(String),
+    #[error("{quest} needs level {level}")]
+    LevelTooLow { quest: String, level: i32 },
+    #[error("{0} needs other quests done first")]
+    PrerequisitesMissing(String),
+    #[error("{0} is already in your journal")]
+    AlreadyActive(String),
+    #[error("{0} was already done")]
+    AlreadyCompleted(String),
+    #[error("{0} is not in your journal")]
+    NotActive(String),
+    #[error("{0} is not finished yet")]
+    NotFinished(String),
+    #[error(transparent)]
+    Inventory(#[from] InventoryError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for QuestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for QuestError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            QuestError::NoCharacter => ErrorCode::Forbidden,
+            QuestError::UnknownQuest(_) | QuestError::UnknownNpc(_) | QuestError::NotActive(_) => ErrorCode::NotFound,
+            QuestError::Inventory(InventoryError::UnexpectedError(_)) | QuestError::UnexpectedError(_) => ErrorCode::Internal,
+            _ => ErrorCode::InvalidAction,
+        }
+    }
+}
+
+/// A quest in progress, collect objectives already counted from the inventory
+pub struct ActiveQuest<'a> {
+    pub quest: &'a QuestDefinition,
+    pub progress: Vec<i32>,
+    pub finished: bool,
+}
+
+/// Everything the quest page shows, quests are sorted by level and then name
+pub struct QuestJournal<'a> {
+    pub active: Vec<ActiveQuest<'a>>,
+    pub available: Vec<&'a QuestDefinition>,
+    pub completed: Vec<&'a QuestDefinition>,
+}
+
+/// Accepting, progressing and handing in quests. Progress comes from game events handled by
+/// `QuestProgressSystem`, rewards are paid in the same transaction that completes the quest.
+pub struct QuestService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    world: Arc<WorldService>,
+    inventory: Arc<InventoryService>,
+    rules: Arc<CombatRules>,
+    book: Arc<QuestBook>,
+    events: GameEvents,
+}
+
+impl QuestService {
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        world: Arc<WorldService>,
+        inventory: Arc<InventoryService>,
+        rules: Arc<CombatRules>,
+        book: Arc<QuestBook>,
+        events: GameEvents,
+    ) -> Self {
+        QuestService { pool, registry, world, inventory, rules, book, events }
+    }
+
+    pub fn book(&self) -> &QuestBook {
+        &self.book
+    }
+
+    fn definition(&self, quest_id: &str) -> Result<&QuestDefinition, QuestError> {
+        self.book.get(quest_id).ok_or_else(|| QuestError::UnknownQuest(quest_id.to_string()))
+    }
+
+    /// Names instead of ids wherever the data has them
+    pub fn describe(&self, objective: &Objective) -> String {
+        if let Some(description) = objective.description() {
+            return description.to_string();
+        }
+        let zone_name = |id: &str| self.world.map().get(id).map_or_else(|| id.to_string(), |z| z.name.clone());
+        match objective {
+            Objective::Kill { creature, .. } => {
+                format!("Defeat {}", self.rules.creature(creature).map_or(creature.as_str(), |c| c.name.as_str()))
+            }
+            Objective::Collect { item, .. } => {
+                format!("Collect {}", self.inventory.catalog().get(item).map_or(item.as_str(), |i| i.name.as_str()))
+            }
+            Objective::Reach { zone, x, y, .. } => format!("Go to {},{} in {}", x, y, zone_name(zone)),
+            Objective::Talk { npc, zone, .. } => format!("Talk to {} in {}", npc, zone_name(zone)),
+        }
+    }
+
+    #[tracing::instrument(
+    name = "Get quest journal",
+    skip(self, character),
+    fields(character_id = % character.id)
+    )]
+    pub async fn journal(&self, character: &Character) -> Result<QuestJournal<'_>, QuestError> {
+        let quests = get_character_quests(&self.pool, character.id).await?;
+        let inventory = self.inventory.get_inventory(&self.pool, character.id).await?;
+        let completed: HashSet<&str> = quests.iter()
+            .filter(|q| q.completions > 0)
+            .map(|q| q.quest_id.as_str())
+            .collect();
+        let active_ids: HashSet<&str> = quests.iter()
+            .filter(|q| q.status == QuestStatus::Active)
+            .map(|q| q.quest_id.as_str())
+            .collect();
+
+        let mut journal = QuestJournal { active: Vec::new(), available: Vec::new(), completed: Vec::new() };
+        for stored in &quests {
+            // quests removed from the data files are kept in the database but not shown
+            let Some(quest) = self.book.get(&stored.quest_id) else { continue };
+            if stored.status == QuestStatus::Active {
+                journal.active.push(ActiveQuest {
+                    quest,
+                    progress: current_progress(quest, &stored.progress, &inventory),
+                    finished: is_finished(quest, &stored.progress, &inventory),
+                });
+            }
+            if stored.completions > 0 {
+                journal.completed.push(quest);
+            }
+        }
+        journal.available = self.book.iter()
+            .filter(|q| !active_ids.contains(q.id.as_str()))
+            .filter(|q| q.repeatable || !completed.contains(q.id.as_str()))
+            .filter(|q| q.level <= character.level)
+            .filter(|q| q.prerequisites.iter().all(|p| completed.contains(p.as_str())))
+            .collect();
+
+        let order = |a: &&QuestDefinition, b: &&QuestDefinition| (a.level, &a.name).cmp(&(b.level, &b.name));
+        journal.active.sort_by(|a, b| order(&a.quest, &b.quest));
+        journal.available.sort_by(order);
+        journal.completed.sort_by(order);
+        Ok(journal)
+    }
+
+    #[tracing::instrument(
+    name = "Accept quest",
+    skip(self, character),
+    fields(character_id = % character.id)
+    )]
+    pub async fn accept(&self, character: &Character, quest_id: &str) -> Result<&QuestDefinition, QuestError> {
+        let quest = self.definition(quest_id)?;
+        if character.level < quest.level {
+            return Err(QuestError::LevelTooLow { quest: quest.name.clone(), level: quest.level });
+        }
+
+        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+        let quests = get_character_quests(&mut tx, character.id).await?;
+        let completed = |id: &str| quests.iter().any(|q| q.quest_id == id && q.completions > 0);
+        if !quest.prerequisites.iter().all(|p| completed(p)) {
+            return Err(QuestError::PrerequisitesMissing(quest.name.clone()));
+        }
+        match lock_character_quest(&mut tx, character.id, quest_id).await? {
+            Some(stored) if stored.status == QuestStatus::Active => return Err(QuestError::AlreadyActive(quest.name.clone())),
+            Some(_) if !quest.repeatable => return Err(QuestError::AlreadyCompleted(quest.name.clone())),
+            _ => {}
+        }
+        store_accepted_quest(&mut tx, character.id, quest_id, &vec![0; quest.objectives.len()]).await?;
+        tx.commit().await.context("Failed to commit accepted quest")?;
+        tracing::info!(quest_id, "Quest accepted");
+        Ok(quest)
+    }
+
+    #[tracing::instrument(
+    name = "Abandon quest",
+    skip(self, character),
+    fields(character_id = % character.id)
+    )]
+    pub async fn abandon(&self, character: &Character, quest_id: &str) -> Result<&QuestDefinition, QuestError> {
+        let quest = self.definition(quest_id)?;
+        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+        match lock_character_quest(&mut tx, character.id, quest_id).await? {
+            Some(stored) if stored.status == QuestStatus::Active => {}
+            _ => return Err(QuestError::NotActive(quest.name.clone())),
+        }
+        store_abandoned_quest(&mut tx, character.id, quest_id).await?;
+        tx.commit().await.context("Failed to commit abandoned quest")?;
+        tracing::info!(quest_id, "Quest abandoned");
+        Ok(quest)
+    }
+
+    /// Takes the collected items and pays the rewards, all or nothing
+    #[tracing::instrument(
+    name = "Complete quest",
+    skip(self, character),
+    fields(character_id = % character.id)
+    )]
+    pub async fn complete(&self, character: &Character, quest_id: &str) -> Result<&QuestDefinition, QuestError> {
+        let quest = self.definition(quest_id)?;
+        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+        let stored = match lock_character_quest(&mut tx, character.id, quest_id).await? {
+            Some(stored) if stored.status == QuestStatus::Active => stored,
+            _ => return Err(QuestError::NotActive(quest.name.clone())),
+        };
+        let inventory = self.inventory.get_inventory(&mut tx, character.id).await?;
+        if !is_finished(quest, &stored.progress, &inventory) {
+            return Err(QuestError::NotFinished(quest.name.clone()));
+        }
+
+        for objective in &quest.objectives {
+            if let Objective::Collect { item, count, .. } = objective {
+                self.inventory.remove_items(&mut tx, character.id, item, *count).await?;
+            }
+        }
+        for reward in &quest.rewards.items {
+            self.inventory.add_items(&mut tx, character.id, &reward.item, reward.quantity).await?;
+        }
+        let completion = stored.completions + 1;
+        for (currency, amount) in &quest.rewards.currency {
+            let reward = Transfer::new(
+                LedgerAccount::System(SystemAccount::Rewards),
+                LedgerAccount::Character(character.id),
+                *currency,
+                *amount,
+                &format!("Quest reward for {}", quest.id),
+            ).idempotency_key(format!("quest:{}:{}:{}", character.id, quest.id, completion));
+            transfer(&mut tx, &reward).await.context("Failed to pay quest reward")?;
+        }
+        if quest.rewards.experience > 0 {
+            add_experience(&mut tx, character.id, quest.rewards.experience).await?;
+        }
+        store_completed_quest(&mut tx, character.id, quest_id).await?;
+        tx.commit().await.context("Failed to commit completed quest")?;
+        tracing::info!(quest_id, completion, "Quest completed");
+
+        let user_id = UserId::from(character.user_id);
+        self.events.publish(GameEvent::QuestCompleted {
+            user_id,
+            character_id: character.id,
+            quest_id: quest.id.clone(),
+        });
+        if let Some(amount) = quest.rewards.currency.get(&Currency::Gold) {
+            self.events.publish(GameEvent::GoldEarned { user_id, character_id: character.id, amount: *amount });
+        }
+        Ok(quest)
+    }
+
+    /// Talks to an npc in the zone the character is in
+    pub fn talk(&self, user_id: UserId, character: &Character, npc_id: &str) -> Result<(), QuestError> {
+        let position = self.world.position(user_id).ok_or(QuestError::NoCharacter)?;
+        if !self.book.has_npc(npc_id, &position.zone_id) {
+            return Err(QuestError::UnknownNpc(npc_id.to_string()));
+        }
+        self.events.publish(GameEvent::TalkedTo {
+            user_id,
+            character_id: character.id,
+            npc_id: npc_id.to_string(),
+            zone_id: position.zone_id,
+        });
+        Ok(())
+    }
+
+    /// Moves every active quest of the character forward that `event` counts for, the player
+    /// is told about each objective that changed
+    pub async fn handle(&self, event: &GameEvent) -> Result<(), anyhow::Error> {
+        if !self.book.is_interested(event) {
+            return Ok(());
+        }
+        let character_id = event.character_id();
+        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+        let mut updates = Vec::new();
+        for mut stored in lock_active_quests(&mut tx, character_id).await? {
+            let Some(quest) = self.book.get(&stored.quest_id) else { continue };
+            stored.progress.resize(quest.objectives.len(), 0);
+            let changed = advance(quest, &mut stored.progress, event);
+            if changed.is_empty() {
+                continue;
+            }
+            store_quest_progress(&mut tx, character_id, &quest.id, &stored.progress).await?;
+            for objective in changed {
+                updates.push(ServerMessage::QuestProgress {
+                    quest_id: quest.id.clone(),
+                    objective,
+                    progress: stored.progress[objective],
+                    required: quest.objectives[objective].required(),
+                });
+            }
+        }
+        tx.commit().await.context("Failed to commit quest progress")?;
+
+        for update in &updates {
+            self.registry.send_to_user(event.user_id(), update);
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,269 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuestStatus {
+    Active,
+    Completed,
+}
+
+impl QuestStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuestStatus::Active => "active",
+            QuestStatus::Completed => "completed",
+        }
+    }
+}
+
+impl TryFrom<String> for QuestStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "active" => Ok(QuestStatus::Active),
+            "completed" => Ok(QuestStatus::Completed),
+            other => Err(format!("{} is not a valid quest status", other)),
+        }
+    }
+}
+
+/// A quest a character has accepted at some point
+#[derive(Debug, Clone)]
+pub struct CharacterQuest {
+    pub quest_id: String,
+    pub status: QuestStatus,
+    pub progress: Vec<i32>,
+    pub completions: i32,
+    pub accepted_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+struct CharacterQuestRow {
+    quest_id: String,
+    status: String,
+    progress: Vec<i32>,
+    completions: i32,
+    accepted_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<CharacterQuestRow> for CharacterQuest {
+    type Error = String;
+
+    fn try_from(row: CharacterQuestRow) -> Result<Self, Self::Error> {
+        Ok(CharacterQuest {
+            quest_id: row.quest_id,
+            status: QuestStatus::try_from(row.status)?,
+            progress: row.progress,
+            completions: row.completions,
+            accepted_at: row.accepted_at,
+            completed_at: row.completed_at,
+        })
+    }
+}
+
+fn parse_rows(rows: Vec<CharacterQuestRow>) -> Result<Vec<CharacterQuest>, anyhow::Error> {
+    rows.into_iter()
+        .map(CharacterQuest::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("Failed to parse stored quest")
+}
+
+#[tracing::instrument(
+name = "Get character quests",
+skip(executor)
+)]
+pub async fn get_character_quests(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+) -> Result<Vec<CharacterQuest>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        CharacterQuestRow,
+        r#"
+        SELECT quest_id, status, progress, completions, accepted_at, completed_at
+        FROM character_quests
+        WHERE character_id = $1
+        ORDER BY accepted_at
+        "#,
+        character_id
+    )
+        .fetch_all(executor)
+        .await
+        .context("Failed to fetch character quests")?;
+    parse_rows(rows)
+}
+
+/// Active quests of the character, locked until the transaction ends so progress from
+/// events and handing quests in don't overwrite each other
+#[tracing::instrument(
+name = "Lock active quests",
+skip(tx)
+)]
+pub async fn lock_active_quests(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+) -> Result<Vec<CharacterQuest>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        CharacterQuestRow,
+        r#"
+        SELECT quest_id, status, progress, completions, accepted_at, completed_at
+        FROM character_quests
+        WHERE character_id = $1 AND status = 'active'
+        ORDER BY accepted_at
+        FOR UPDATE
+        "#,
+        character_id
+    )
+        .fetch_all(tx)
+        .await
+        .context("Failed to lock active quests")?;
+    parse_rows(rows)
+}
+
+#[tracing::instrument(
+name = "Lock character quest",
+skip(tx)
+)]
+pub async fn lock_character_quest(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    quest_id: &str,
+) -> Result<Option<CharacterQuest>, anyhow::Error> {
+    let row = sqlx::query_as!(
+        CharacterQuestRow,
+        r#"
+        SELECT quest_id, status, progress, completions, accepted_at, completed_at
+        FROM character_quests
+        WHERE character_id = $1 AND quest_id = $2
+        FOR UPDATE
+        "#,
+        character_id,
+        quest_id
+    )
+        .fetch_optional(tx)
+        .await
+        .context("Failed to lock character quest")?;
+    row.map(CharacterQuest::try_from)
+        .transpose()
+        .map_err(|e| anyhow::anyhow!(e))
+        .context("Failed to parse stored quest")
+}
+
+/// Starts the quest over with fresh progress, completions of repeatable quests are kept
+#[tracing::instrument(
+name = "Store accepted quest",
+skip(tx, progress)
+)]
+pub async fn store_accepted_quest(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    quest_id: &str,
+    progress: &[i32],
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO character_quests (character_id, quest_id, status, progress)
+        VALUES ($1, $2, 'active', $3)
+        ON CONFLICT (character_id, quest_id) DO UPDATE
+        SET status = 'active', progress = $3, accepted_at = now()
+        "#,
+        character_id,
+        quest_id,
+        progress
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store accepted quest")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Store quest progress",
+skip(tx, progress)
+)]
+pub async fn store_quest_progress(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    quest_id: &str,
+    progress: &[i32],
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE character_quests
+        SET progress = $3
+        WHERE character_id = $1 AND quest_id = $2 AND status = 'active'
+        "#,
+        character_id,
+        quest_id,
+        progress
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store quest progress")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+name = "Store completed quest",
+skip(tx)
+)]
+pub async fn store_completed_quest(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    quest_id: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE character_quests
+        SET status = 'completed', completions = completions + 1, completed_at = now()
+        WHERE character_id = $1 AND quest_id = $2
+        "#,
+        character_id,
+        quest_id
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store completed quest")?;
+    Ok(())
+}
+
+/// Quests that were never completed are forgotten, repeated ones go back to completed
+#[tracing::instrument(
+name = "Store abandoned quest",
+skip(tx)
+)]
+pub async fn store_abandoned_quest(
+    tx: &mut Transaction<'_, Postgres>,
+    character_id: Uuid,
+    quest_id: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM character_quests
+        WHERE character_id = $1 AND quest_id = $2 AND status = 'active' AND completions = 0
+        "#,
+        character_id,
+        quest_id
+    )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete abandoned quest")?;
+    sqlx::query!(
+        r#"
+        UPDATE character_quests
+        SET status = 'completed'
+        WHERE character_id = $1 AND quest_id = $2 AND status = 'active'
+        "#,
+        character_id,
+        quest_id
+    )
+        .execute(tx)
+        .await
+        .context("Failed to store abandoned quest")?;
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::sync::broadcast::error::TryRecvError;
+use tokio::sync::broadcast::Receiver;
+use crate::events::GameEvent;
+use crate::game_loop::{GameSystem, TickContext};
+use crate::quests::QuestService;
+
+/// Applies the game events of the last tick to quest progress
+pub struct QuestProgressSystem {
+    quests: Arc<QuestService>,
+    events: Receiver<GameEvent>,
+}
+
+impl QuestProgressSystem {
+    pub fn new(quests: Arc<QuestService>, events: Receiver<GameEvent>) -> Self {
+        QuestProgressSystem { quests, events }
+    }
+}
+
+#[async_trait]
+impl GameSystem for QuestProgressSystem {
+    fn name(&self) -> &'static str {
+        "quest_progress"
+    }
+
+    async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error> {
+        let mut handled = 0;
+        loop {
+            let event = match self.events.try_recv() {
+                Ok(event) => event,
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+                Err(TryRecvError::Lagged(missed)) => {
+                    tracing::warn!(tick = ctx.tick, missed, "Quest progress fell behind and missed game events");
+                    continue;
+                }
+            };
+            // one bad event shouldn't hold up everyone else's progress
+            if let Err(e) = self.quests.handle(&event).await {
+                tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to update quest progress");
+            }
+            handled += 1;
+        }
+        if handled > 0 {
+            tracing::debug!(tick = ctx.tick, handled, "Handled game events for quests");
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -2,12 +2,34 @@ use actix_web::http::header::ContentType;
 use actix_web::HttpResponse;
 use actix_web::web::Data;
 use tera::{Context, Tera};
+use crate::authentication::YaugSession;
+use crate::characters::ActiveCharacter;
+use crate::domain::Account;
 use crate::utils::e500;
 
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M UTC";
+
 pub async fn get_account_home(
-    tpl: Data<Tera>
+    tpl: Data<Tera>,
+    session: YaugSession,
+    account: Account,
+    active_character: Option<ActiveCharacter>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    let roles: Vec<&str> = account.roles.iter().map(|r| r.as_str()).collect();
+    // the stored value is this session's own login, the one before it is what the player wants
+    let last_login = session.get_previous_login()
+        .map_err(e500)?
+        .unwrap_or(account.last_login_at)
+        .map(|at| at.format(DATE_FORMAT).to_string());
+
     let mut ctx = Context::new();
+    ctx.insert("name", account.name());
+    ctx.insert("email", account.email.as_ref());
+    ctx.insert("status", account.status.as_str());
+    ctx.insert("roles", &roles);
+    ctx.insert("created_at", &account.created_at.format(DATE_FORMAT).to_string());
+    ctx.insert("last_login_at", &last_login);
+    ctx.insert("active_character", &active_character.map(|c| c.name.to_string()));
 
     Ok(
         HttpResponse::Ok()
@@ -1,3 +1,5 @@
 mod home;
+mod profile;
 
-pub use home::get_account_home;
\ No newline at end of file
+pub use home::get_account_home;
+pub use profile::{get_profile_form, post_profile};
\ No newline at end of file
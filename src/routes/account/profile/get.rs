@@ -0,0 +1,64 @@
+use actix_web::HttpResponse;
+use actix_web_flash_messages::IncomingFlashMessages;
+use actix_web::http::header::ContentType;
+use actix_web::web::Data;
+use anyhow::Context as _;
+use tera::{Context, Tera};
+use crate::authentication::UserId;
+use crate::domain::{Avatar, SUPPORTED_LOCALES};
+use crate::store::Store;
+use crate::utils::e500;
+
+#[tracing::instrument(
+name = "Get profile form",
+skip(flash_messages, tpl, store)
+)]
+pub async fn get_profile_form(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    store: Data<dyn Store>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+
+    let profile = store.begin()
+        .await
+        .context("Failed to begin profile lookup transaction")
+        .map_err(e500)?
+        .get_profile_by_user_id(*user_id)
+        .await
+        .context("Failed to fetch profile")
+        .map_err(e500)?;
+
+    let avatars: Vec<&str> = Avatar::ALL.iter().map(|a| a.as_str()).collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash);
+    ctx.insert("avatars", &avatars);
+    ctx.insert("locales", SUPPORTED_LOCALES);
+    match &profile {
+        Some(profile) => {
+            ctx.insert("display_name", profile.display_name.as_ref());
+            ctx.insert("avatar", profile.avatar.as_str());
+            ctx.insert("bio", profile.bio.as_ref());
+            ctx.insert("locale", profile.locale.as_ref());
+        }
+        None => {
+            ctx.insert("display_name", "");
+            ctx.insert("avatar", Avatar::default().as_str());
+            ctx.insert("bio", "");
+            ctx.insert("locale", SUPPORTED_LOCALES[0]);
+        }
+    }
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("account/profile.html", &ctx).map_err(e500)?
+            )
+    )
+}
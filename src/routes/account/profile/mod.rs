@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::get_profile_form;
+pub use post::post_profile;
\ No newline at end of file
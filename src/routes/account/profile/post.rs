@@ -0,0 +1,74 @@
+use actix_web::HttpResponse;
+use actix_web::web::{Data, Form};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use crate::authentication::UserId;
+use crate::domain::{Avatar, DisplayName, Locale, ProfileBio, ProfileDetails};
+use crate::store::{Store, StoreError};
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct ProfileFormData {
+    pub display_name: String,
+    pub avatar: String,
+    pub bio: String,
+    pub locale: String,
+}
+
+impl TryFrom<ProfileFormData> for ProfileDetails {
+    type Error = String;
+
+    fn try_from(value: ProfileFormData) -> Result<Self, Self::Error> {
+        Ok(ProfileDetails {
+            display_name: DisplayName::parse(value.display_name)?,
+            avatar: Avatar::try_from(value.avatar)?,
+            bio: ProfileBio::parse(value.bio)?,
+            locale: Locale::parse(value.locale)?,
+        })
+    }
+}
+
+#[tracing::instrument(
+name = "Update profile",
+skip(data, store),
+fields(display_name = % data.display_name)
+)]
+pub async fn post_profile(
+    data: Form<ProfileFormData>,
+    store: Data<dyn Store>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let details: ProfileDetails = match data.0.try_into() {
+        Ok(details) => details,
+        Err(e) => return Ok(profile_redirect(FlashMessage::error(e))),
+    };
+
+    let mut tx = store.begin()
+        .await
+        .context("Failed to begin profile transaction")
+        .map_err(e500)?;
+
+    match tx.store_profile(*user_id, &details).await {
+        Ok(()) => {}
+        Err(StoreError::Conflict(_)) => {
+            return Ok(profile_redirect(FlashMessage::error(
+                format!("The name {} is already taken", details.display_name)
+            )));
+        }
+        Err(e) => return Err(e500(anyhow::Error::from(e).context("Failed to store profile"))),
+    }
+
+    match tx.commit().await {
+        Ok(()) => Ok(profile_redirect(FlashMessage::success("Your profile has been saved"))),
+        // someone claimed the name between our insert and the commit
+        Err(StoreError::Conflict(_)) => Ok(profile_redirect(FlashMessage::error(
+            format!("The name {} is already taken", details.display_name)
+        ))),
+        Err(e) => Err(e500(anyhow::Error::from(e).context("Failed to commit profile transaction"))),
+    }
+}
+
+fn profile_redirect(message: FlashMessage) -> HttpResponse {
+    message.send();
+    see_other("/account/profile")
+}
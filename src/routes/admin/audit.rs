@@ -0,0 +1,54 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::Data;
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use crate::audit::get_recent_audit_entries;
+use crate::authentication::Moderator;
+use crate::utils::e500;
+
+const AUDIT_PAGE_SIZE: i64 = 100;
+
+#[derive(serde::Serialize)]
+struct AuditEntryView {
+    created_at: String,
+    actor: String,
+    action: String,
+    subject: String,
+    details: String,
+}
+
+#[tracing::instrument(
+name = "Get audit log",
+skip(tpl, pool, moderator),
+fields(moderator_id = % moderator.0.id)
+)]
+pub async fn get_audit_log(
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    moderator: Moderator,
+) -> Result<HttpResponse, actix_web::Error> {
+    let entries: Vec<AuditEntryView> = get_recent_audit_entries(pool.get_ref(), AUDIT_PAGE_SIZE)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|e| AuditEntryView {
+            created_at: e.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            actor: e.actor_id.map(|id| id.to_string()).unwrap_or_else(|| "system".to_string()),
+            action: e.action,
+            subject: e.subject_id.map(|id| id.to_string()).unwrap_or_default(),
+            details: e.details,
+        })
+        .collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("entries", &entries);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("admin/audit.html", &ctx).map_err(e500)?
+            )
+    )
+}
@@ -0,0 +1,111 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Form};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use crate::authentication::Moderator;
+use crate::chat::{get_active_mutes, ChatError, ChatService};
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Serialize)]
+struct MuteView {
+    display_name: String,
+    muted_until: String,
+    reason: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct MuteFormData {
+    pub display_name: String,
+    pub minutes: i64,
+    pub reason: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct UnmuteFormData {
+    pub display_name: String,
+}
+
+#[tracing::instrument(
+name = "Get chat moderation",
+skip(flash_messages, tpl, pool, moderator),
+fields(moderator_id = % moderator.0.id)
+)]
+pub async fn get_chat_moderation(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    moderator: Moderator,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+
+    let mutes: Vec<MuteView> = get_active_mutes(pool.get_ref())
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|m| MuteView {
+            // players can drop their profile, moderators still need to tell mutes apart
+            display_name: m.display_name.unwrap_or_else(|| m.user_id.to_string()),
+            muted_until: m.muted_until.format("%Y-%m-%d %H:%M").to_string(),
+            reason: m.reason,
+        })
+        .collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash);
+    ctx.insert("mutes", &mutes);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("admin/chat.html", &ctx).map_err(e500)?
+            )
+    )
+}
+
+#[tracing::instrument(
+name = "Mute player",
+skip(data, chat, moderator),
+fields(moderator_id = % moderator.0.id, display_name = % data.display_name)
+)]
+pub async fn post_mute_player(
+    data: Form<MuteFormData>,
+    chat: Data<ChatService>,
+    moderator: Moderator,
+) -> Result<HttpResponse, actix_web::Error> {
+    let duration = chrono::Duration::minutes(data.minutes);
+    match chat.mute(moderator.0.id, &data.display_name, duration, &data.reason).await {
+        Ok(muted_until) => FlashMessage::info(format!(
+            "{} is muted until {}",
+            data.display_name,
+            muted_until.format("%Y-%m-%d %H:%M")
+        )).send(),
+        Err(ChatError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => FlashMessage::error(e.to_string()).send(),
+    }
+    Ok(see_other("/admin/chat"))
+}
+
+#[tracing::instrument(
+name = "Unmute player",
+skip(data, chat, moderator),
+fields(moderator_id = % moderator.0.id, display_name = % data.display_name)
+)]
+pub async fn post_unmute_player(
+    data: Form<UnmuteFormData>,
+    chat: Data<ChatService>,
+    moderator: Moderator,
+) -> Result<HttpResponse, actix_web::Error> {
+    match chat.unmute(moderator.0.id, &data.display_name).await {
+        Ok(true) => FlashMessage::info(format!("{} is no longer muted", data.display_name)).send(),
+        Ok(false) => FlashMessage::error(format!("{} is not muted", data.display_name)).send(),
+        Err(ChatError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => FlashMessage::error(e.to_string()).send(),
+    }
+    Ok(see_other("/admin/chat"))
+}
@@ -0,0 +1,70 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::Data;
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use crate::authentication::Admin;
+use crate::leaderboards::{get_seasons, LeaderboardService};
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Serialize)]
+struct SeasonView {
+    number: i32,
+    started_at: String,
+    ended_at: Option<String>,
+}
+
+#[tracing::instrument(
+name = "Get leaderboard administration",
+skip(flash_messages, tpl, pool, admin),
+fields(admin_id = % admin.0.id)
+)]
+pub async fn get_leaderboard_admin(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    admin: Admin,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+
+    let seasons: Vec<SeasonView> = get_seasons(pool.get_ref())
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|s| SeasonView {
+            number: s.number,
+            started_at: s.started_at.format("%Y-%m-%d %H:%M").to_string(),
+            ended_at: s.ended_at.map(|e| e.format("%Y-%m-%d %H:%M").to_string()),
+        })
+        .collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash);
+    ctx.insert("seasons", &seasons);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("admin/leaderboards.html", &ctx).map_err(e500)?
+            )
+    )
+}
+
+#[tracing::instrument(
+name = "End leaderboard season",
+skip(leaderboards, admin),
+fields(admin_id = % admin.0.id)
+)]
+pub async fn post_end_season(
+    leaderboards: Data<LeaderboardService>,
+    admin: Admin,
+) -> Result<HttpResponse, actix_web::Error> {
+    let season = leaderboards.end_season(admin.0.id).await.map_err(e500)?;
+    FlashMessage::info(format!("Season {} has started", season)).send();
+    Ok(see_other("/admin/leaderboards"))
+}
@@ -0,0 +1,65 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::Data;
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use crate::authentication::Admin;
+use crate::ledger::reconcile;
+use crate::utils::e500;
+
+#[derive(serde::Serialize)]
+struct AccountView {
+    account: String,
+    currency: &'static str,
+    balance: i64,
+    ledger_balance: i64,
+    entries: i64,
+    consistent: bool,
+}
+
+#[tracing::instrument(
+name = "Get ledger reconciliation report",
+skip(tpl, pool, admin),
+fields(admin_id = % admin.0.id)
+)]
+pub async fn get_ledger_report(
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    admin: Admin,
+) -> Result<HttpResponse, actix_web::Error> {
+    let report = reconcile(pool.get_ref()).await.map_err(e500)?;
+    if !report.is_consistent() {
+        tracing::warn!("Ledger reconciliation found inconsistencies");
+    }
+
+    let accounts: Vec<AccountView> = report.accounts
+        .iter()
+        .map(|a| AccountView {
+            account: a.account.to_string(),
+            currency: a.currency.as_str(),
+            balance: a.balance,
+            ledger_balance: a.ledger_balance,
+            entries: a.entries,
+            consistent: a.is_consistent(),
+        })
+        .collect();
+    let totals: Vec<(&str, i64)> = report.currency_totals
+        .iter()
+        .map(|(currency, total)| (currency.as_str(), *total))
+        .collect();
+    let unbalanced: Vec<String> = report.unbalanced_transfers.iter().map(|id| id.to_string()).collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("consistent", &report.is_consistent());
+    ctx.insert("accounts", &accounts);
+    ctx.insert("totals", &totals);
+    ctx.insert("unbalanced", &unbalanced);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("admin/ledger.html", &ctx).map_err(e500)?
+            )
+    )
+}
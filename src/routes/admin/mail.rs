@@ -0,0 +1,107 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Form};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use crate::authentication::Admin;
+use crate::items::{InventoryError, ItemStack};
+use crate::ledger::{Currency, LedgerError};
+use crate::mail::{get_mail_recipient, MailError, MailService, SystemMail};
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct SystemMailFormData {
+    pub character: String,
+    pub subject: String,
+    pub body: String,
+    pub currency: String,
+    #[serde(default)]
+    pub amount: i64,
+    #[serde(default)]
+    pub item_id: String,
+    #[serde(default)]
+    pub quantity: i32,
+}
+
+#[tracing::instrument(
+name = "Get mail administration",
+skip(flash_messages, tpl, admin),
+fields(admin_id = % admin.0.id)
+)]
+pub async fn get_mail_admin(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    admin: Admin,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+    let currencies: Vec<&'static str> = Currency::ALL.iter().map(|c| c.as_str()).collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash);
+    ctx.insert("currencies", &currencies);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("admin/mail.html", &ctx).map_err(e500)?
+            )
+    )
+}
+
+/// Compensation and rewards, the money comes out of the rewards account and the items out of nowhere
+#[tracing::instrument(
+name = "Send system mail",
+skip(data, pool, mail, admin),
+fields(admin_id = % admin.0.id)
+)]
+pub async fn post_system_mail(
+    data: Form<SystemMailFormData>,
+    pool: Data<PgPool>,
+    mail: Data<MailService>,
+    admin: Admin,
+) -> Result<HttpResponse, actix_web::Error> {
+    let data = data.into_inner();
+    let outcome = match build_system_mail(pool.get_ref(), &data).await {
+        Ok(message) => mail.send_system(Some(admin.0.id), message).await,
+        Err(e) => Err(e),
+    };
+    match outcome {
+        Ok(_) => FlashMessage::info(format!("Mail sent to {}", data.character.trim())).send(),
+        Err(MailError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(MailError::Inventory(InventoryError::UnexpectedError(e))) => return Err(e500(e)),
+        Err(MailError::Ledger(LedgerError::UnexpectedError(e))) => return Err(e500(e)),
+        Err(e) => FlashMessage::error(e.to_string()).send(),
+    }
+    Ok(see_other("/admin/mail"))
+}
+
+async fn build_system_mail(pool: &PgPool, data: &SystemMailFormData) -> Result<SystemMail, MailError> {
+    let name = data.character.trim();
+    let recipient = get_mail_recipient(pool, name)
+        .await?
+        .ok_or_else(|| MailError::RecipientNotFound(name.to_string()))?;
+    let money = if data.amount != 0 {
+        let currency = Currency::try_from(data.currency.clone()).map_err(MailError::ValidationError)?;
+        Some((currency, data.amount))
+    } else {
+        None
+    };
+    let item_id = data.item_id.trim();
+    let items = if item_id.is_empty() {
+        vec![]
+    } else {
+        vec![ItemStack { item_id: item_id.to_string(), quantity: data.quantity }]
+    };
+    Ok(SystemMail {
+        recipient_id: recipient.character_id,
+        subject: data.subject.clone(),
+        body: data.body.clone(),
+        money,
+        items,
+    })
+}
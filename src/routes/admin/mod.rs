@@ -0,0 +1,11 @@
+mod audit;
+mod chat;
+mod leaderboards;
+mod ledger;
+mod mail;
+
+pub use audit::get_audit_log;
+pub use chat::{get_chat_moderation, post_mute_player, post_unmute_player};
+pub use leaderboards::{get_leaderboard_admin, post_end_season};
+pub use ledger::get_ledger_report;
+pub use mail::{get_mail_admin, post_system_mail};
\ No newline at end of file
@@ -0,0 +1,125 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Query};
+use actix_web_flash_messages::IncomingFlashMessages;
+use chrono::Utc;
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use uuid::Uuid;
+use crate::auctions::{AuctionService, Listing, ListingKind};
+use crate::characters::ActiveCharacter;
+use crate::items::InventoryService;
+use crate::utils::e500;
+
+#[derive(serde::Deserialize, Debug)]
+pub struct SearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+#[derive(serde::Serialize)]
+struct ListingView {
+    id: String,
+    item: String,
+    seller: String,
+    auction: bool,
+    buyout_price: Option<i64>,
+    high_bid: Option<i64>,
+    minimum_bid: Option<i64>,
+    mine: bool,
+    leading: bool,
+    expires_at: String,
+}
+
+#[derive(serde::Serialize)]
+struct SlotView {
+    slot: usize,
+    name: String,
+    quantity: i32,
+}
+
+fn listing_view(auctions: &AuctionService, character_id: Uuid, listing: Listing) -> ListingView {
+    ListingView {
+        id: listing.id.to_string(),
+        item: auctions.describe(&listing.item),
+        seller: listing.seller_name.clone(),
+        auction: listing.kind == ListingKind::Auction,
+        buyout_price: listing.buyout_price,
+        high_bid: listing.high_bid,
+        minimum_bid: listing.minimum_bid(auctions.settings().min_bid_increment_percent),
+        mine: listing.seller_id == character_id,
+        leading: listing.high_bidder_id == Some(character_id),
+        expires_at: listing.expires_at.format("%Y-%m-%d %H:%M").to_string(),
+    }
+}
+
+fn flash_lines(flash_messages: &IncomingFlashMessages) -> Vec<String> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+    flash
+}
+
+#[tracing::instrument(
+name = "Get auction house",
+skip(flash_messages, tpl, pool, auctions, inventory, character),
+fields(character_id = % character.id)
+)]
+pub async fn get_auctions(
+    flash_messages: IncomingFlashMessages,
+    query: Query<SearchQuery>,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    auctions: Data<AuctionService>,
+    inventory: Data<InventoryService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let listings: Vec<ListingView> = auctions.search(&query.q)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|l| listing_view(&auctions, character.id, l))
+        .collect();
+    let own: Vec<ListingView> = auctions.listings_of(character.id)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .filter(|l| l.is_open(Utc::now()))
+        .map(|l| listing_view(&auctions, character.id, l))
+        .collect();
+
+    let catalog = inventory.catalog();
+    let backpack: Vec<SlotView> = inventory.get_inventory(pool.get_ref(), character.id)
+        .await
+        .map_err(e500)?
+        .slots()
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, stack)| stack.as_ref().map(|stack| SlotView {
+            slot,
+            name: catalog.get(&stack.item_id)
+                .map(|item| item.name.clone())
+                .unwrap_or_else(|| stack.item_id.clone()),
+            quantity: stack.quantity,
+        }))
+        .collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash_lines(&flash_messages));
+    ctx.insert("query", &query.q);
+    ctx.insert("listings", &listings);
+    ctx.insert("own", &own);
+    ctx.insert("backpack", &backpack);
+    ctx.insert("durations", &auctions.settings().durations_hours);
+    ctx.insert("fee_percent", &auctions.settings().listing_fee_percent);
+    ctx.insert("request_key", &Uuid::new_v4().to_string());
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("auctions/list.html", &ctx).map_err(e500)?
+            )
+    )
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::get_auctions;
+pub use post::{post_create_listing, post_bid, post_buyout, post_cancel_listing};
\ No newline at end of file
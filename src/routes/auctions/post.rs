@@ -0,0 +1,116 @@
+use actix_web::HttpResponse;
+use actix_web::web::{Data, Form, Path};
+use uuid::Uuid;
+use crate::auctions::{AuctionError, AuctionService, BidOutcome, ListingDraft};
+use crate::characters::ActiveCharacter;
+use crate::routes::{finish, request_key, RequestKeyForm};
+
+/// Prices are left empty, or at 0, when they don't apply
+#[derive(serde::Deserialize)]
+pub struct ListingForm {
+    slot: usize,
+    quantity: i32,
+    #[serde(default)]
+    start_price: String,
+    #[serde(default)]
+    buyout_price: String,
+    hours: i64,
+}
+
+impl TryFrom<ListingForm> for ListingDraft {
+    type Error = AuctionError;
+
+    fn try_from(form: ListingForm) -> Result<Self, Self::Error> {
+        Ok(ListingDraft {
+            slot: form.slot,
+            quantity: form.quantity,
+            start_price: parse_price(&form.start_price)?,
+            buyout_price: parse_price(&form.buyout_price)?,
+            hours: form.hours,
+        })
+    }
+}
+
+fn parse_price(value: &str) -> Result<Option<i64>, AuctionError> {
+    match value.trim() {
+        "" | "0" => Ok(None),
+        value => value
+            .parse()
+            .map(Some)
+            .map_err(|_| AuctionError::ValidationError(format!("{} is not a valid price", value))),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct BidForm {
+    amount: i64,
+    #[serde(default)]
+    request_key: Option<String>,
+}
+
+#[tracing::instrument(
+name = "Create listing",
+skip(form, auctions, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_create_listing(
+    form: Form<ListingForm>,
+    auctions: Data<AuctionService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = match ListingDraft::try_from(form.0) {
+        Ok(draft) => auctions.list(&character, draft).await,
+        Err(e) => Err(e),
+    };
+    finish(outcome, "/auctions", |listing| format!(
+        "{} is up for sale, the listing fee was {} gold",
+        auctions.describe(&listing.item), listing.listing_fee
+    ))
+}
+
+#[tracing::instrument(
+name = "Bid",
+skip(form, auctions, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_bid(
+    path: Path<Uuid>,
+    form: Form<BidForm>,
+    auctions: Data<AuctionService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = auctions.bid(&character, path.into_inner(), form.amount, request_key(&form.request_key)).await;
+    finish(outcome, "/auctions", |outcome| match outcome {
+        BidOutcome::Leading(listing) => format!("You have the highest bid on {}", auctions.describe(&listing.item)),
+        BidOutcome::Bought(listing) => format!("You bought {}, it is on its way by mail", auctions.describe(&listing.item)),
+    })
+}
+
+#[tracing::instrument(
+name = "Buy out listing",
+skip(form, auctions, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_buyout(
+    path: Path<Uuid>,
+    form: Form<RequestKeyForm>,
+    auctions: Data<AuctionService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = auctions.buy(&character, path.into_inner(), request_key(&form.request_key)).await;
+    finish(outcome, "/auctions", |listing| format!("You bought {}, it is on its way by mail", auctions.describe(&listing.item)))
+}
+
+#[tracing::instrument(
+name = "Cancel listing",
+skip(auctions, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_cancel_listing(
+    path: Path<Uuid>,
+    auctions: Data<AuctionService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = auctions.cancel(character.id, path.into_inner()).await;
+    finish(outcome, "/auctions", |listing| format!("Listing cancelled, {} is on its way back by mail", auctions.describe(&listing.item)))
+}
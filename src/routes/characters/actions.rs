@@ -0,0 +1,130 @@
+use actix_web::HttpResponse;
+use actix_web::web::{Data, Form, Path};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use uuid::Uuid;
+use crate::achievements::{AchievementError, AchievementService};
+use crate::authentication::{UserId, YaugSession};
+use crate::characters::{delete_character, restore_character, Character, CharacterError};
+use crate::configuration::CharacterSettings;
+use crate::store::Store;
+use crate::utils::{e500, see_other};
+
+/// The account's character unless it is deleted
+async fn live_character(store: &dyn Store, user_id: UserId, character_id: Uuid) -> Result<Option<Character>, actix_web::Error> {
+    let character = store.begin()
+        .await
+        .context("Failed to begin character lookup transaction")
+        .map_err(e500)?
+        .get_character(*user_id, character_id)
+        .await
+        .context("Failed to fetch character")
+        .map_err(e500)?;
+    Ok(character.filter(|c| !c.is_deleted()))
+}
+
+#[tracing::instrument(
+name = "Select active character",
+skip(session, store)
+)]
+pub async fn post_select_character(
+    character_id: Path<Uuid>,
+    session: YaugSession,
+    store: Data<dyn Store>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    match live_character(store.get_ref(), user_id, *character_id).await? {
+        Some(character) => {
+            session.insert_active_character(character.id).map_err(e500)?;
+            FlashMessage::info(format!("You are now playing {}", character.name)).send();
+        }
+        None => FlashMessage::error(CharacterError::NotFound.to_string()).send(),
+    }
+    Ok(see_other("/characters"))
+}
+
+#[tracing::instrument(
+name = "Delete character route",
+skip(session, store, settings)
+)]
+pub async fn post_delete_character(
+    character_id: Path<Uuid>,
+    session: YaugSession,
+    store: Data<dyn Store>,
+    settings: Data<CharacterSettings>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    match delete_character(store.get_ref(), *user_id, *character_id).await {
+        Ok(character) => {
+            if session.get_active_character().map_err(e500)? == Some(character.id) {
+                session.remove_active_character();
+            }
+            FlashMessage::info(format!(
+                "{} has been deleted, you can restore them within {} hours",
+                character.name, settings.deletion_grace_period_hours
+            )).send();
+        }
+        Err(CharacterError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => FlashMessage::error(e.to_string()).send(),
+    }
+    Ok(see_other("/characters"))
+}
+
+#[tracing::instrument(
+name = "Restore character route",
+skip(store, settings)
+)]
+pub async fn post_restore_character(
+    character_id: Path<Uuid>,
+    store: Data<dyn Store>,
+    settings: Data<CharacterSettings>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    match restore_character(store.get_ref(), &settings, *user_id, *character_id).await {
+        Ok(character) => FlashMessage::success(format!("{} has been restored", character.name)).send(),
+        Err(CharacterError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => FlashMessage::error(e.to_string()).send(),
+    }
+    Ok(see_other("/characters"))
+}
+
+#[derive(serde::Deserialize)]
+pub struct TitleForm {
+    /// Empty takes the title off
+    achievement_id: String,
+}
+
+#[tracing::instrument(
+name = "Select character title",
+skip(form, store, achievements)
+)]
+pub async fn post_character_title(
+    character_id: Path<Uuid>,
+    form: Form<TitleForm>,
+    store: Data<dyn Store>,
+    achievements: Data<AchievementService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(character) = live_character(store.get_ref(), user_id, *character_id).await? else {
+        FlashMessage::error(CharacterError::NotFound.to_string()).send();
+        return Ok(see_other("/characters"));
+    };
+
+    let achievement_id = Some(form.achievement_id.trim()).filter(|id| !id.is_empty());
+    match achievements.select_title(character.id, achievement_id).await {
+        Ok(()) => {
+            let title = achievement_id.and_then(|id| achievements.book().get(id)).and_then(|a| a.title.as_deref());
+            let message = match title {
+                Some(title) => format!("{} is now known as {} {}", character.name, character.name, title),
+                None => format!("{} no longer shows a title", character.name),
+            };
+            FlashMessage::info(message).send();
+        }
+        // flash messages aren't escaped, so the id from the form isn't repeated back
+        Err(AchievementError::UnknownTitle(_)) | Err(AchievementError::TitleNotEarned) => {
+            FlashMessage::error(AchievementError::TitleNotEarned.to_string()).send()
+        }
+        Err(e) => return Err(e500(e)),
+    }
+    Ok(see_other("/characters"))
+}
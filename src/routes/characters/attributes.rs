@@ -0,0 +1,98 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Form};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use tera::{Context, Tera};
+use crate::authentication::{UserId, YaugSession};
+use crate::characters::{create_character, Attributes, CharacterError, NewCharacter, BONUS_POINTS};
+use crate::configuration::CharacterSettings;
+use crate::store::Store;
+use crate::utils::{e500, see_other};
+
+#[tracing::instrument(
+name = "Get character attributes form",
+skip(flash_messages, tpl, session)
+)]
+pub async fn get_character_attributes_form(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    session: YaugSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+
+    let draft = match session.get_character_draft().map_err(e500)? {
+        Some(draft) => draft,
+        None => return Ok(see_other("/characters/new")),
+    };
+    let (name, class) = match draft.parse() {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            session.remove_character_draft();
+            return Ok(see_other("/characters/new"));
+        }
+    };
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash);
+    ctx.insert("name", name.as_ref());
+    ctx.insert("class", class.as_str());
+    ctx.insert("base", &class.base_attributes());
+    ctx.insert("bonus_points", &BONUS_POINTS);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("characters/attributes.html", &ctx).map_err(e500)?
+            )
+    )
+}
+
+#[tracing::instrument(
+name = "Create character from draft",
+skip(bonus, session, store, settings)
+)]
+pub async fn post_character_attributes(
+    bonus: Form<Attributes>,
+    session: YaugSession,
+    store: Data<dyn Store>,
+    settings: Data<CharacterSettings>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (name, class) = match session.get_character_draft().map_err(e500)?.map(|d| d.parse()) {
+        Some(Ok(parsed)) => parsed,
+        _ => {
+            session.remove_character_draft();
+            FlashMessage::error("Pick a name and a class first").send();
+            return Ok(see_other("/characters/new"));
+        }
+    };
+
+    let new_character = NewCharacter { name, class, bonus: bonus.0 };
+    match create_character(store.get_ref(), &settings, *user_id, new_character).await {
+        Ok(character) => {
+            session.remove_character_draft();
+            session.insert_active_character(character.id).map_err(e500)?;
+            FlashMessage::success(format!("{} is ready for adventure", character.name)).send();
+            Ok(see_other("/characters"))
+        }
+        Err(e @ CharacterError::ValidationError(_)) => {
+            FlashMessage::error(e.to_string()).send();
+            Ok(see_other("/characters/new/attributes"))
+        }
+        // the name has to change, the draft stays so step one is prefilled
+        Err(e @ CharacterError::NameTaken(_)) => {
+            FlashMessage::error(e.to_string()).send();
+            Ok(see_other("/characters/new"))
+        }
+        Err(e @ CharacterError::LimitReached(_)) => {
+            session.remove_character_draft();
+            FlashMessage::error(e.to_string()).send();
+            Ok(see_other("/characters"))
+        }
+        Err(e) => Err(e500(e)),
+    }
+}
@@ -0,0 +1,102 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::Data;
+use actix_web_flash_messages::IncomingFlashMessages;
+use chrono::Utc;
+use anyhow::Context as _;
+use tera::{Context, Tera};
+use uuid::Uuid;
+use crate::achievements::AchievementService;
+use crate::authentication::{UserId, YaugSession};
+use crate::configuration::CharacterSettings;
+use crate::store::Store;
+use crate::utils::e500;
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M UTC";
+
+#[derive(serde::Serialize)]
+struct CharacterView {
+    id: Uuid,
+    name: String,
+    class: &'static str,
+    level: i32,
+    active: bool,
+    restorable_until: Option<String>,
+    title: Option<String>,
+    title_id: Option<String>,
+    /// `(achievement id, title)` of every title the character earned
+    titles: Vec<(String, String)>,
+}
+
+#[tracing::instrument(
+name = "Get characters",
+skip(flash_messages, tpl, store, settings, achievements, session)
+)]
+pub async fn get_characters(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    store: Data<dyn Store>,
+    settings: Data<CharacterSettings>,
+    achievements: Data<AchievementService>,
+    session: YaugSession,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+
+    let active = session.get_active_character().map_err(e500)?;
+    let now = Utc::now();
+    let grace_period = settings.grace_period();
+
+    let mut characters = Vec::new();
+    let mut deleted = Vec::new();
+    let stored = store.begin()
+        .await
+        .context("Failed to begin character list transaction")
+        .map_err(e500)?
+        .get_characters_by_user_id(*user_id)
+        .await
+        .context("Failed to fetch characters")
+        .map_err(e500)?;
+    for character in stored {
+        let overview = achievements.overview(character.id).await.map_err(e500)?;
+        let view = CharacterView {
+            id: character.id,
+            name: character.name.to_string(),
+            class: character.class.as_str(),
+            level: character.level,
+            active: Some(character.id) == active,
+            restorable_until: character.restorable_until(grace_period)
+                .map(|until| until.format(DATE_FORMAT).to_string()),
+            title: overview.title,
+            title_id: overview.title_id,
+            titles: overview.achievements
+                .into_iter()
+                .filter(|a| a.earned_at.is_some())
+                .filter_map(|a| Some((a.id, a.title?)))
+                .collect(),
+        };
+        if !character.is_deleted() {
+            characters.push(view);
+        } else if character.is_restorable(now, grace_period) {
+            deleted.push(view);
+        }
+    }
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash);
+    ctx.insert("can_create", &((characters.len() as i64) < settings.max_per_account));
+    ctx.insert("max_characters", &settings.max_per_account);
+    ctx.insert("characters", &characters);
+    ctx.insert("deleted", &deleted);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("characters/list.html", &ctx).map_err(e500)?
+            )
+    )
+}
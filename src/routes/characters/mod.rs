@@ -0,0 +1,9 @@
+mod actions;
+mod attributes;
+mod list;
+mod new;
+
+pub use actions::{post_character_title, post_delete_character, post_restore_character, post_select_character};
+pub use attributes::{get_character_attributes_form, post_character_attributes};
+pub use list::get_characters;
+pub use new::{get_new_character_form, post_new_character};
\ No newline at end of file
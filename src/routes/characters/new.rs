@@ -0,0 +1,70 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Form};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use tera::{Context, Tera};
+use crate::authentication::YaugSession;
+use crate::characters::{CharacterClass, CharacterDraft, CharacterName};
+use crate::utils::{e500, see_other};
+
+#[tracing::instrument(
+name = "Get new character form",
+skip(flash_messages, tpl, session)
+)]
+pub async fn get_new_character_form(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    session: YaugSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+
+    // going back from the attributes step keeps what was picked
+    let draft = session.get_character_draft().map_err(e500)?;
+    let classes: Vec<&str> = CharacterClass::ALL.iter().map(|c| c.as_str()).collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash);
+    ctx.insert("classes", &classes);
+    ctx.insert("name", draft.as_ref().map(|d| d.name.as_str()).unwrap_or(""));
+    ctx.insert("class", draft.as_ref().map(|d| d.class.as_str()).unwrap_or(classes[0]));
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("characters/new.html", &ctx).map_err(e500)?
+            )
+    )
+}
+
+#[derive(serde::Deserialize)]
+pub struct NewCharacterFormData {
+    pub name: String,
+    pub class: String,
+}
+
+#[tracing::instrument(
+name = "Pick character name and class",
+skip(data, session),
+fields(character_name = % data.name)
+)]
+pub async fn post_new_character(
+    data: Form<NewCharacterFormData>,
+    session: YaugSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let parsed = CharacterName::parse(data.0.name)
+        .and_then(|name| Ok((name, CharacterClass::try_from(data.0.class)?)));
+    let (name, class) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/characters/new"));
+        }
+    };
+
+    session.insert_character_draft(&CharacterDraft::new(&name, class)).map_err(e500)?;
+    Ok(see_other("/characters/new/attributes"))
+}
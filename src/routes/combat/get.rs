@@ -0,0 +1,134 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Path};
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::combat::{describe_event, get_combat_record, get_recent_combats, is_combat_participant, CombatEvent, Encounter};
+use crate::domain::{Account, AccountRole};
+use crate::utils::{e403, e404, e500};
+
+const RECENT_COMBATS: i64 = 20;
+
+#[derive(serde::Serialize)]
+struct CombatSummaryView {
+    id: Uuid,
+    character: String,
+    result: &'static str,
+    rounds: i32,
+    finished_at: String,
+}
+
+#[derive(serde::Serialize)]
+struct CombatantLine {
+    name: String,
+    side: usize,
+    level: i32,
+    max_health: i32,
+}
+
+#[derive(serde::Serialize)]
+struct LogLine {
+    round: bool,
+    text: String,
+}
+
+#[tracing::instrument(
+name = "Get combat history",
+skip(tpl, pool)
+)]
+pub async fn get_combat_history(
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let combats: Vec<CombatSummaryView> = get_recent_combats(pool.get_ref(), *user_id, RECENT_COMBATS)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|c| CombatSummaryView {
+            id: c.id,
+            character: c.character_name,
+            result: match c.won {
+                Some(true) => "won",
+                Some(false) => "lost",
+                None => "draw",
+            },
+            rounds: c.rounds,
+            finished_at: c.finished_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        })
+        .collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("combats", &combats);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("combat/history.html", &ctx).map_err(e500)?
+            )
+    )
+}
+
+/// Replays the stored fight to show what happened, only for the players involved and moderators
+#[tracing::instrument(
+name = "Get combat log",
+skip(tpl, pool, account),
+fields(user_id = % account.id)
+)]
+pub async fn get_combat_log(
+    encounter_id: Path<Uuid>,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    account: Account,
+) -> Result<HttpResponse, actix_web::Error> {
+    let encounter_id = encounter_id.into_inner();
+    let record = get_combat_record(pool.get_ref(), encounter_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404(format!("There is no fight {}", encounter_id)))?;
+    let moderator = account.has_role(AccountRole::Moderator) || account.has_role(AccountRole::Admin);
+    if !moderator && !is_combat_participant(pool.get_ref(), encounter_id, account.id).await.map_err(e500)? {
+        return Err(e403(format!("Account {} did not take part in fight {}", account.id, encounter_id)));
+    }
+
+    let combatants: Vec<CombatantLine> = record.setup.combatants.iter()
+        .map(|c| CombatantLine { name: c.name.clone(), side: c.side, level: c.level, max_health: c.max_health })
+        .collect();
+    // a replay that disagrees with what was stored means the rules changed under it, show it anyway
+    let (lines, replay_error) = match Encounter::replay(record.setup.clone(), record.seed, &record.actions) {
+        Ok(encounter) => {
+            let lines: Vec<LogLine> = encounter.log().iter()
+                .filter_map(|event| describe_event(encounter.setup(), event).map(|text| LogLine {
+                    round: matches!(event, CombatEvent::RoundStarted { .. }),
+                    text,
+                }))
+                .collect();
+            let matches = encounter.outcome().map(|o| o.winner) == Some(record.winner);
+            (lines, (!matches).then(|| "Replaying this fight gives a different result than the one recorded".to_string()))
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "Stored fight can't be replayed");
+            (vec![], Some(e))
+        }
+    };
+
+    let mut ctx = Context::new();
+    ctx.insert("encounter_id", &encounter_id);
+    ctx.insert("seed", &record.seed.to_string());
+    ctx.insert("started_at", &record.started_at.format("%Y-%m-%d %H:%M:%S").to_string());
+    ctx.insert("rounds", &record.rounds);
+    ctx.insert("combatants", &combatants);
+    ctx.insert("lines", &lines);
+    ctx.insert("replay_error", &replay_error);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("combat/log.html", &ctx).map_err(e500)?
+            )
+    )
+}
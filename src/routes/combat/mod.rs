@@ -0,0 +1,3 @@
+mod get;
+
+pub use get::{get_combat_history, get_combat_log};
\ No newline at end of file
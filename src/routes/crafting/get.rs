@@ -0,0 +1,100 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::Data;
+use actix_web_flash_messages::IncomingFlashMessages;
+use chrono::Utc;
+use tera::{Context, Tera};
+use crate::characters::ActiveCharacter;
+use crate::crafting::{CraftingService, RecipeItem};
+use crate::utils::e500;
+
+#[derive(serde::Serialize)]
+struct RecipeView {
+    id: String,
+    name: String,
+    skill: &'static str,
+    skill_level: i32,
+    station: Option<String>,
+    seconds: i64,
+    inputs: String,
+    outputs: String,
+    /// Skill level is there, the station is checked on queueing
+    learned: bool,
+}
+
+#[derive(serde::Serialize)]
+struct JobView {
+    id: String,
+    recipe: String,
+    seconds_left: i64,
+}
+
+fn flash_lines(flash_messages: &IncomingFlashMessages) -> Vec<String> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+    flash
+}
+
+#[tracing::instrument(
+name = "Get crafting",
+skip(flash_messages, tpl, crafting, character),
+fields(character_id = % character.id)
+)]
+pub async fn get_crafting(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    crafting: Data<CraftingService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let skills = crafting.skills(character.id).await.map_err(e500)?;
+    let book = crafting.book();
+    let recipes: Vec<RecipeView> = book
+        .sorted()
+        .into_iter()
+        .map(|recipe| {
+            let list = |items: &[RecipeItem]| {
+                items.iter().map(|i| crafting.describe(i)).collect::<Vec<_>>().join(", ")
+            };
+            RecipeView {
+                id: recipe.id.clone(),
+                name: recipe.name.clone(),
+                skill: recipe.skill.title(),
+                skill_level: recipe.skill_level,
+                station: recipe.station.as_ref().map(|s| s.replace('_', " ")),
+                seconds: recipe.seconds,
+                inputs: list(&recipe.inputs),
+                outputs: list(&recipe.outputs),
+                learned: skills.iter().any(|s| s.skill == recipe.skill.as_str() && s.level >= recipe.skill_level),
+            }
+        })
+        .collect();
+
+    let now = Utc::now();
+    let queue: Vec<JobView> = crafting.queue(character.id)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|job| JobView {
+            id: job.id.to_string(),
+            recipe: book.get(&job.recipe_id).map_or(job.recipe_id.clone(), |r| r.name.clone()),
+            seconds_left: (job.finishes_at - now).num_seconds().max(0),
+        })
+        .collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash_lines(&flash_messages));
+    ctx.insert("skills", &skills);
+    ctx.insert("recipes", &recipes);
+    ctx.insert("queue", &queue);
+    ctx.insert("max_queue", &crafting.settings().max_queue);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("crafting/list.html", &ctx).map_err(e500)?
+            )
+    )
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::get_crafting;
+pub use post::{post_queue_craft, post_cancel_craft};
\ No newline at end of file
@@ -0,0 +1,57 @@
+use actix_web::HttpResponse;
+use actix_web::web::{Data, Form, Path};
+use uuid::Uuid;
+use crate::characters::ActiveCharacter;
+use crate::crafting::CraftingService;
+use crate::routes::finish;
+
+fn one() -> i64 {
+    1
+}
+
+#[derive(serde::Deserialize)]
+pub struct CraftForm {
+    recipe_id: String,
+    #[serde(default = "one")]
+    count: i64,
+}
+
+#[tracing::instrument(
+name = "Queue craft",
+skip(form, crafting, character),
+fields(character_id = % character.id, recipe_id = % form.recipe_id)
+)]
+pub async fn post_queue_craft(
+    form: Form<CraftForm>,
+    crafting: Data<CraftingService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = crafting.enqueue(&character, &form.recipe_id, form.count).await;
+    finish(outcome, "/crafting", |jobs| {
+        let name = crafting.book().get(&form.recipe_id).map_or("", |r| r.name.as_str());
+        match jobs.last() {
+            Some(last) if jobs.len() > 1 => format!(
+                "Queued {} x{}, done at {}", name, jobs.len(), last.finishes_at.format("%H:%M:%S")
+            ),
+            Some(last) => format!("Queued {}, done at {}", name, last.finishes_at.format("%H:%M:%S")),
+            None => format!("Queued {}", name),
+        }
+    })
+}
+
+#[tracing::instrument(
+name = "Cancel craft",
+skip(crafting, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_cancel_craft(
+    path: Path<Uuid>,
+    crafting: Data<CraftingService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = crafting.cancel(character.id, path.into_inner()).await;
+    finish(outcome, "/crafting", |job| {
+        let name = crafting.book().get(&job.recipe_id).map_or(job.recipe_id.as_str(), |r| r.name.as_str());
+        format!("Cancelled {}, the materials are back", name)
+    })
+}
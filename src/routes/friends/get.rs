@@ -0,0 +1,79 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::Data;
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use crate::authentication::UserId;
+use crate::friends::{get_incoming_friend_requests, get_outgoing_friend_requests, FriendRow, FriendService};
+use crate::utils::e500;
+
+#[derive(serde::Serialize)]
+struct FriendView {
+    name: String,
+    status: &'static str,
+    status_title: &'static str,
+    since: String,
+}
+
+#[derive(serde::Serialize)]
+struct RequestView {
+    name: String,
+    sent_at: String,
+}
+
+fn request_views(rows: Vec<FriendRow>) -> Vec<RequestView> {
+    rows.into_iter()
+        .map(|r| RequestView {
+            name: r.display_name.unwrap_or_else(|| "Unknown".to_string()),
+            sent_at: r.created_at.format("%Y-%m-%d").to_string(),
+        })
+        .collect()
+}
+
+#[tracing::instrument(
+name = "Get friends",
+skip(flash_messages, tpl, pool, friends),
+fields(user_id = % user_id)
+)]
+pub async fn get_friends(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    friends: Data<FriendService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+    let friend_views: Vec<FriendView> = friends.friends(user_id)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|f| FriendView {
+            name: f.display_name,
+            status: f.status.as_str(),
+            status_title: f.status.title(),
+            since: f.since.format("%Y-%m-%d").to_string(),
+        })
+        .collect();
+    let incoming = get_incoming_friend_requests(pool.get_ref(), *user_id).await.map_err(e500)?;
+    let outgoing = get_outgoing_friend_requests(pool.get_ref(), *user_id).await.map_err(e500)?;
+    let blocked = friends.blocked(user_id).await.map_err(e500)?;
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash);
+    ctx.insert("friends", &friend_views);
+    ctx.insert("incoming", &request_views(incoming));
+    ctx.insert("outgoing", &request_views(outgoing));
+    ctx.insert("blocked", &blocked);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("friends/list.html", &ctx).map_err(e500)?
+            )
+    )
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::get_friends;
+pub use post::{post_friend_request, post_accept_friend, post_decline_friend, post_cancel_friend_request, post_remove_friend, post_block_player, post_unblock_player};
\ No newline at end of file
@@ -0,0 +1,111 @@
+use actix_web::HttpResponse;
+use actix_web::web::{Data, Form};
+use crate::authentication::UserId;
+use crate::friends::{FriendRequestOutcome, FriendService};
+use crate::routes::finish;
+
+#[derive(serde::Deserialize)]
+pub struct PlayerFormData {
+    pub display_name: String,
+}
+
+#[tracing::instrument(
+name = "Send friend request",
+skip(data, friends),
+fields(user_id = % user_id)
+)]
+pub async fn post_friend_request(
+    data: Form<PlayerFormData>,
+    friends: Data<FriendService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = friends.request(user_id, &data.display_name).await;
+    finish(outcome, "/friends", |outcome| match outcome {
+        FriendRequestOutcome::Sent(name) => format!("Friend request sent to {}", name),
+        FriendRequestOutcome::Accepted(name) => format!("You and {} are now friends", name),
+    })
+}
+
+#[tracing::instrument(
+name = "Accept friend request",
+skip(data, friends),
+fields(user_id = % user_id)
+)]
+pub async fn post_accept_friend(
+    data: Form<PlayerFormData>,
+    friends: Data<FriendService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = friends.accept(user_id, &data.display_name).await;
+    finish(outcome, "/friends", |name| format!("You and {} are now friends", name))
+}
+
+#[tracing::instrument(
+name = "Decline friend request",
+skip(data, friends),
+fields(user_id = % user_id)
+)]
+pub async fn post_decline_friend(
+    data: Form<PlayerFormData>,
+    friends: Data<FriendService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = friends.decline(user_id, &data.display_name).await;
+    finish(outcome, "/friends", |name| format!("Declined the friend request from {}", name))
+}
+
+#[tracing::instrument(
+name = "Cancel friend request",
+skip(data, friends),
+fields(user_id = % user_id)
+)]
+pub async fn post_cancel_friend_request(
+    data: Form<PlayerFormData>,
+    friends: Data<FriendService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = friends.cancel(user_id, &data.display_name).await;
+    finish(outcome, "/friends", |name| format!("Cancelled the friend request to {}", name))
+}
+
+#[tracing::instrument(
+name = "Remove friend",
+skip(data, friends),
+fields(user_id = % user_id)
+)]
+pub async fn post_remove_friend(
+    data: Form<PlayerFormData>,
+    friends: Data<FriendService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = friends.remove(user_id, &data.display_name).await;
+    finish(outcome, "/friends", |name| format!("{} is no longer your friend", name))
+}
+
+#[tracing::instrument(
+name = "Block player",
+skip(data, friends),
+fields(user_id = % user_id)
+)]
+pub async fn post_block_player(
+    data: Form<PlayerFormData>,
+    friends: Data<FriendService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = friends.block(user_id, &data.display_name).await;
+    finish(outcome, "/friends", |name| format!("You blocked {}", name))
+}
+
+#[tracing::instrument(
+name = "Unblock player",
+skip(data, friends),
+fields(user_id = % user_id)
+)]
+pub async fn post_unblock_player(
+    data: Form<PlayerFormData>,
+    friends: Data<FriendService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = friends.unblock(user_id, &data.display_name).await;
+    finish(outcome, "/friends", |name| format!("You unblocked {}", name))
+}
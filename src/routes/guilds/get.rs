@@ -0,0 +1,252 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::Data;
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use crate::audit::get_audit_entries_for_subject;
+use crate::authentication::UserId;
+use crate::characters::ActiveCharacter;
+use crate::gateway::ConnectionRegistry;
+use crate::guilds::{get_guild, get_guild_applications, get_guild_invitations, get_guild_members, get_guild_ranks, get_guilds, get_membership, GuildMember, GuildPermission, GuildService};
+use crate::items::{Inventory, InventoryService};
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Serialize)]
+struct GuildListView {
+    id: String,
+    name: String,
+    tag: String,
+    members: i64,
+}
+
+#[derive(serde::Serialize)]
+struct InvitationView {
+    guild_id: String,
+    guild_name: String,
+    guild_tag: String,
+    invited_by: String,
+}
+
+#[derive(serde::Serialize)]
+struct MemberView {
+    user_id: String,
+    name: String,
+    rank: i32,
+    rank_name: String,
+    online: bool,
+    joined_at: String,
+    /// Whether the viewer may kick this member
+    kickable: bool,
+}
+
+#[derive(serde::Serialize)]
+struct PermissionView {
+    id: &'static str,
+    title: &'static str,
+    granted: bool,
+}
+
+#[derive(serde::Serialize)]
+struct RankView {
+    rank: i32,
+    name: String,
+    permissions: Vec<PermissionView>,
+}
+
+#[derive(serde::Serialize)]
+struct ApplicationView {
+    user_id: String,
+    name: String,
+    message: String,
+}
+
+#[derive(serde::Serialize)]
+struct SlotView {
+    slot: usize,
+    name: String,
+    quantity: i32,
+}
+
+#[derive(serde::Serialize)]
+struct AuditView {
+    at: String,
+    details: String,
+}
+
+fn flash_lines(flash_messages: &IncomingFlashMessages) -> Vec<String> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+    flash
+}
+
+fn slot_views(inventory: &InventoryService, slots: &Inventory) -> Vec<SlotView> {
+    let catalog = inventory.catalog();
+    slots.slots()
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, stack)| stack.as_ref().map(|stack| SlotView {
+            slot,
+            name: catalog.get(&stack.item_id)
+                .map(|item| item.name.clone())
+                .unwrap_or_else(|| stack.item_id.clone()),
+            quantity: stack.quantity,
+        }))
+        .collect()
+}
+
+#[tracing::instrument(
+name = "Get guilds",
+skip(flash_messages, tpl, pool),
+fields(user_id = % user_id)
+)]
+pub async fn get_guild_list(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let in_guild = get_membership(pool.get_ref(), *user_id).await.map_err(e500)?.is_some();
+    let guilds: Vec<GuildListView> = get_guilds(pool.get_ref())
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|g| GuildListView { id: g.id.to_string(), name: g.name, tag: g.tag, members: g.members })
+        .collect();
+    let invitations: Vec<InvitationView> = get_guild_invitations(pool.get_ref(), *user_id)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|i| InvitationView {
+            guild_id: i.guild_id.to_string(),
+            guild_name: i.guild_name,
+            guild_tag: i.guild_tag,
+            invited_by: i.invited_by.unwrap_or_else(|| "someone".to_string()),
+        })
+        .collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash_lines(&flash_messages));
+    ctx.insert("in_guild", &in_guild);
+    ctx.insert("guilds", &guilds);
+    ctx.insert("invitations", &invitations);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("guilds/list.html", &ctx).map_err(e500)?
+            )
+    )
+}
+
+#[tracing::instrument(
+name = "Get guild",
+skip(flash_messages, tpl, pool, registry, guilds, inventory, member, character),
+fields(user_id = % member.user_id, guild_id = % member.guild_id)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_own_guild(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    registry: Data<ConnectionRegistry>,
+    guilds: Data<GuildService>,
+    inventory: Data<InventoryService>,
+    member: GuildMember,
+    character: Option<ActiveCharacter>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let pool = pool.get_ref();
+    let guild = match get_guild(pool, member.guild_id).await.map_err(e500)? {
+        Some(guild) => guild,
+        // disbanded between the extractor and here
+        None => return Ok(see_other("/guilds")),
+    };
+    let ranks = get_guild_ranks(pool, guild.id).await.map_err(e500)?;
+    let rank_name = |rank: i32| ranks.iter()
+        .find(|r| r.rank == rank)
+        .map(|r| r.name.clone())
+        .unwrap_or_default();
+
+    let can_kick = member.rank.can(GuildPermission::Kick);
+    let members: Vec<MemberView> = get_guild_members(pool, guild.id)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|m| MemberView {
+            user_id: m.user_id.to_string(),
+            name: m.display_name.unwrap_or_else(|| "Unknown".to_string()),
+            rank: m.rank,
+            rank_name: rank_name(m.rank),
+            online: registry.is_online(UserId::from(m.user_id)),
+            joined_at: m.joined_at.format("%Y-%m-%d").to_string(),
+            kickable: can_kick && member.rank.outranks(m.rank),
+        })
+        .collect();
+    let rank_views: Vec<RankView> = ranks.iter()
+        .map(|r| RankView {
+            rank: r.rank,
+            name: r.name.clone(),
+            permissions: GuildPermission::ALL
+                .into_iter()
+                .map(|p| PermissionView { id: p.as_str(), title: p.title(), granted: r.can(p) })
+                .collect(),
+        })
+        .collect();
+
+    let applications: Vec<ApplicationView> = if member.rank.can(GuildPermission::Invite) {
+        get_guild_applications(pool, guild.id)
+            .await
+            .map_err(e500)?
+            .into_iter()
+            .map(|a| ApplicationView {
+                user_id: a.user_id.to_string(),
+                name: a.display_name.unwrap_or_else(|| "Unknown".to_string()),
+                message: a.message,
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+
+    let bank = inventory.get_guild_bank(pool, guild.id).await.map_err(e500)?;
+    let backpack = match &character {
+        Some(character) => Some(slot_views(&inventory, &inventory.get_inventory(pool, character.id).await.map_err(e500)?)),
+        None => None,
+    };
+
+    let audit: Vec<AuditView> = get_audit_entries_for_subject(pool, guild.id, guilds.settings().audit_entries)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|e| AuditView { at: e.created_at.format("%Y-%m-%d %H:%M").to_string(), details: e.details })
+        .collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash_lines(&flash_messages));
+    ctx.insert("guild_name", &guild.name);
+    ctx.insert("guild_tag", &guild.tag);
+    ctx.insert("motd", &guild.motd);
+    ctx.insert("my_rank", &member.rank.name);
+    ctx.insert("is_leader", &member.rank.is_leader());
+    ctx.insert("can_invite", &member.rank.can(GuildPermission::Invite));
+    ctx.insert("can_edit_motd", &member.rank.can(GuildPermission::EditMotd));
+    ctx.insert("can_withdraw", &member.rank.can(GuildPermission::ManageBank));
+    ctx.insert("members", &members);
+    ctx.insert("ranks", &rank_views);
+    ctx.insert("applications", &applications);
+    ctx.insert("bank", &slot_views(&inventory, &bank));
+    ctx.insert("character", &character.as_ref().map(|c| c.name.to_string()));
+    ctx.insert("backpack", &backpack);
+    ctx.insert("audit", &audit);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("guilds/guild.html", &ctx).map_err(e500)?
+            )
+    )
+}
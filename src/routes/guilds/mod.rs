@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::{get_guild_list, get_own_guild};
+pub use post::{post_create_guild, post_apply_to_guild, post_accept_invitation, post_decline_invitation, post_guild_invite, post_accept_application, post_reject_application, post_kick_member, post_member_rank, post_rank_permissions, post_guild_motd, post_leave_guild, post_bank_deposit, post_bank_withdraw};
\ No newline at end of file
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use actix_web::HttpResponse;
+use actix_web::web::{Data, Form, Path};
+use actix_web_flash_messages::FlashMessage;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::characters::ActiveCharacter;
+use crate::guilds::{GuildError, GuildMember, GuildName, GuildPermission, GuildService, GuildTag};
+use crate::items::{InventoryService, ItemStack};
+use crate::routes::{fail, finish};
+use crate::utils::see_other;
+
+#[derive(serde::Deserialize)]
+pub struct CreateFormData {
+    pub name: String,
+    pub tag: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ApplyFormData {
+    pub message: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct InviteFormData {
+    pub display_name: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct RankFormData {
+    pub rank: i32,
+}
+
+#[derive(serde::Deserialize)]
+pub struct MotdFormData {
+    pub motd: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct BankFormData {
+    pub slot: usize,
+    pub quantity: i32,
+}
+
+#[tracing::instrument(
+name = "Create guild",
+skip(data, guilds),
+fields(user_id = % user_id)
+)]
+pub async fn post_create_guild(
+    data: Form<CreateFormData>,
+    guilds: Data<GuildService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let data = data.into_inner();
+    let parsed = GuildName::parse(data.name)
+        .and_then(|name| GuildTag::parse(data.tag).map(|tag| (name, tag)));
+    let outcome = match parsed {
+        Ok((name, tag)) => guilds.create(user_id, name, tag).await,
+        Err(e) => Err(GuildError::ValidationError(e)),
+    };
+    match outcome {
+        Ok(guild) => {
+            FlashMessage::info(format!("You founded {} [{}]", guild.name, guild.tag)).send();
+            Ok(see_other("/guild"))
+        }
+        Err(e) => fail(e, "/guilds"),
+    }
+}
+
+#[tracing::instrument(
+name = "Apply to guild",
+skip(data, guilds),
+fields(user_id = % user_id)
+)]
+pub async fn post_apply_to_guild(
+    path: Path<Uuid>,
+    data: Form<ApplyFormData>,
+    guilds: Data<GuildService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = guilds.apply(user_id, path.into_inner(), &data.message).await;
+    finish(outcome, "/guilds", |_| "Your application was sent".to_string())
+}
+
+#[tracing::instrument(
+name = "Accept guild invitation",
+skip(guilds),
+fields(user_id = % user_id)
+)]
+pub async fn post_accept_invitation(
+    path: Path<Uuid>,
+    guilds: Data<GuildService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    match guilds.accept_invitation(user_id, path.into_inner()).await {
+        Ok(()) => {
+            FlashMessage::info("Welcome to the guild").send();
+            Ok(see_other("/guild"))
+        }
+        Err(e) => fail(e, "/guilds"),
+    }
+}
+
+#[tracing::instrument(
+name = "Decline guild invitation",
+skip(guilds),
+fields(user_id = % user_id)
+)]
+pub async fn post_decline_invitation(
+    path: Path<Uuid>,
+    guilds: Data<GuildService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = guilds.decline_invitation(user_id, path.into_inner()).await;
+    finish(outcome, "/guilds", |_| "Invitation declined".to_string())
+}
+
+#[tracing::instrument(
+name = "Invite to guild",
+skip(data, guilds, member),
+fields(user_id = % member.user_id)
+)]
+pub async fn post_guild_invite(
+    data: Form<InviteFormData>,
+    guilds: Data<GuildService>,
+    member: GuildMember,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = guilds.invite(member.user_id, &data.display_name).await;
+    finish(outcome, "/guild", |name| format!("{} was invited", name))
+}
+
+#[tracing::instrument(
+name = "Accept guild application",
+skip(guilds, member),
+fields(user_id = % member.user_id)
+)]
+pub async fn post_accept_application(
+    path: Path<Uuid>,
+    guilds: Data<GuildService>,
+    member: GuildMember,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = guilds.accept_application(member.user_id, path.into_inner()).await;
+    finish(outcome, "/guild", |name| format!("{} joined the guild", name))
+}
+
+#[tracing::instrument(
+name = "Reject guild application",
+skip(guilds, member),
+fields(user_id = % member.user_id)
+)]
+pub async fn post_reject_application(
+    path: Path<Uuid>,
+    guilds: Data<GuildService>,
+    member: GuildMember,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = guilds.reject_application(member.user_id, path.into_inner()).await;
+    finish(outcome, "/guild", |_| "Application rejected".to_string())
+}
+
+#[tracing::instrument(
+name = "Kick guild member",
+skip(guilds, member),
+fields(user_id = % member.user_id)
+)]
+pub async fn post_kick_member(
+    path: Path<Uuid>,
+    guilds: Data<GuildService>,
+    member: GuildMember,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = guilds.kick(member.user_id, path.into_inner()).await;
+    finish(outcome, "/guild", |name| format!("{} was removed from the guild", name))
+}
+
+#[tracing::instrument(
+name = "Change guild rank",
+skip(data, guilds, member),
+fields(user_id = % member.user_id)
+)]
+pub async fn post_member_rank(
+    path: Path<Uuid>,
+    data: Form<RankFormData>,
+    guilds: Data<GuildService>,
+    member: GuildMember,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = guilds.set_rank(member.user_id, path.into_inner(), data.rank).await;
+    finish(outcome, "/guild", |_| "Rank changed".to_string())
+}
+
+/// Checkboxes only show up in the form when ticked, so every permission is looked up by name
+#[tracing::instrument(
+name = "Change guild rank permissions",
+skip(data, guilds, member),
+fields(user_id = % member.user_id)
+)]
+pub async fn post_rank_permissions(
+    path: Path<i32>,
+    data: Form<HashMap<String, String>>,
+    guilds: Data<GuildService>,
+    member: GuildMember,
+) -> Result<HttpResponse, actix_web::Error> {
+    let permissions = GuildPermission::ALL
+        .into_iter()
+        .filter(|p| data.contains_key(p.as_str()))
+        .collect();
+    let outcome = guilds.set_permissions(member.user_id, path.into_inner(), permissions).await;
+    finish(outcome, "/guild", |_| "Permissions saved".to_string())
+}
+
+#[tracing::instrument(
+name = "Change guild motd",
+skip(data, guilds, member),
+fields(user_id = % member.user_id)
+)]
+pub async fn post_guild_motd(
+    data: Form<MotdFormData>,
+    guilds: Data<GuildService>,
+    member: GuildMember,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = guilds.set_motd(member.user_id, &data.motd).await;
+    finish(outcome, "/guild", |_| "Message of the day saved".to_string())
+}
+
+#[tracing::instrument(
+name = "Leave guild",
+skip(guilds, member),
+fields(user_id = % member.user_id)
+)]
+pub async fn post_leave_guild(
+    guilds: Data<GuildService>,
+    member: GuildMember,
+) -> Result<HttpResponse, actix_web::Error> {
+    match guilds.leave(member.user_id).await {
+        Ok(()) => {
+            FlashMessage::info("You left the guild").send();
+            Ok(see_other("/guilds"))
+        }
+        Err(e) => fail(e, "/guild"),
+    }
+}
+
+#[tracing::instrument(
+name = "Deposit in guild bank",
+skip(data, guilds, inventory, member, character),
+fields(user_id = % member.user_id, character_id = % character.id)
+)]
+pub async fn post_bank_deposit(
+    data: Form<BankFormData>,
+    guilds: Data<GuildService>,
+    inventory: Data<InventoryService>,
+    member: GuildMember,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = guilds.deposit(member.user_id, character.id, data.slot, data.quantity).await;
+    finish(outcome, "/guild", |stack| format!("Deposited {} {}", stack.quantity, stack_name(&inventory, &stack)))
+}
+
+#[tracing::instrument(
+name = "Withdraw from guild bank",
+skip(data, guilds, inventory, member, character),
+fields(user_id = % member.user_id, character_id = % character.id)
+)]
+pub async fn post_bank_withdraw(
+    data: Form<BankFormData>,
+    guilds: Data<GuildService>,
+    inventory: Data<InventoryService>,
+    member: GuildMember,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = guilds.withdraw(member.user_id, character.id, data.slot, data.quantity).await;
+    finish(outcome, "/guild", |stack| format!("Withdrew {} {}", stack.quantity, stack_name(&inventory, &stack)))
+}
+
+fn stack_name(inventory: &InventoryService, stack: &ItemStack) -> String {
+    inventory.catalog()
+        .get(&stack.item_id)
+        .map(|item| item.name.clone())
+        .unwrap_or_else(|| stack.item_id.clone())
+}
@@ -0,0 +1,90 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::Data;
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use crate::characters::ActiveCharacter;
+use crate::items::InventoryService;
+use crate::ledger::get_balances;
+use crate::utils::e500;
+
+#[derive(serde::Serialize)]
+struct SlotView {
+    slot: usize,
+    item: Option<StackView>,
+}
+
+#[derive(serde::Serialize)]
+struct StackView {
+    name: String,
+    item_type: &'static str,
+    rarity: &'static str,
+    quantity: i32,
+}
+
+#[tracing::instrument(
+name = "Get inventory",
+skip(flash_messages, tpl, pool, inventory, character),
+fields(character_id = % character.id)
+)]
+pub async fn get_inventory(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    inventory: Data<InventoryService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+
+    let catalog = inventory.catalog();
+    let slots: Vec<SlotView> = inventory.get_inventory(pool.get_ref(), character.id)
+        .await
+        .map_err(e500)?
+        .slots()
+        .iter()
+        .enumerate()
+        .map(|(slot, stack)| SlotView {
+            slot,
+            item: stack.as_ref().map(|stack| match catalog.get(&stack.item_id) {
+                Some(item) => StackView {
+                    name: item.name.clone(),
+                    item_type: item.item_type.as_str(),
+                    rarity: item.rarity.as_str(),
+                    quantity: stack.quantity,
+                },
+                // the item was removed from the data files, keep showing what is stored
+                None => StackView {
+                    name: stack.item_id.clone(),
+                    item_type: "unknown",
+                    rarity: "unknown",
+                    quantity: stack.quantity,
+                },
+            }),
+        })
+        .collect();
+
+    let balances: Vec<(&str, i64)> = get_balances(pool.get_ref(), character.id)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|(currency, balance)| (currency.as_str(), balance))
+        .collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash);
+    ctx.insert("character", character.name.as_ref());
+    ctx.insert("balances", &balances);
+    ctx.insert("slots", &slots);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("inventory/inventory.html", &ctx).map_err(e500)?
+            )
+    )
+}
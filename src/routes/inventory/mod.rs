@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::get_inventory;
+pub use post::{post_move_stack, post_split_stack};
\ No newline at end of file
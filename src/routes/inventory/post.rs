@@ -0,0 +1,77 @@
+use actix_web::HttpResponse;
+use actix_web::web::{Data, Form};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use crate::characters::ActiveCharacter;
+use crate::items::{InventoryError, InventoryService};
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct MoveFormData {
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(serde::Deserialize)]
+pub struct SplitFormData {
+    pub from: usize,
+    pub to: usize,
+    pub quantity: i32,
+}
+
+#[tracing::instrument(
+name = "Move inventory stack",
+skip(data, pool, inventory, character),
+fields(character_id = % character.id, from = data.from, to = data.to)
+)]
+pub async fn post_move_stack(
+    data: Form<MoveFormData>,
+    pool: Data<PgPool>,
+    inventory: Data<InventoryService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut tx = pool.begin()
+        .await
+        .context("Failed to begin inventory transaction")
+        .map_err(e500)?;
+    let outcome = inventory.move_stack(&mut tx, character.id, data.from, data.to).await;
+    finish(tx, outcome).await
+}
+
+#[tracing::instrument(
+name = "Split inventory stack",
+skip(data, pool, inventory, character),
+fields(character_id = % character.id, from = data.from, to = data.to)
+)]
+pub async fn post_split_stack(
+    data: Form<SplitFormData>,
+    pool: Data<PgPool>,
+    inventory: Data<InventoryService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut tx = pool.begin()
+        .await
+        .context("Failed to begin inventory transaction")
+        .map_err(e500)?;
+    let outcome = inventory.split_stack(&mut tx, character.id, data.from, data.to, data.quantity).await;
+    finish(tx, outcome).await
+}
+
+/// Rule violations are shown to the player, the dropped transaction rolls back whatever was started
+async fn finish(
+    tx: sqlx::Transaction<'_, sqlx::Postgres>,
+    outcome: Result<(), InventoryError>,
+) -> Result<HttpResponse, actix_web::Error> {
+    match outcome {
+        Ok(()) => {
+            tx.commit()
+                .await
+                .context("Failed to commit inventory transaction")
+                .map_err(e500)?;
+        }
+        Err(InventoryError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => FlashMessage::error(e.to_string()).send(),
+    }
+    Ok(see_other("/inventory"))
+}
@@ -0,0 +1,191 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Path, Query};
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use crate::authentication::YaugSession;
+use crate::leaderboards::{find_character_id_by_name, get_season_snapshot, get_seasons, Leaderboard, LeaderboardService, RankedEntry};
+use crate::utils::{e404, e500};
+
+#[derive(serde::Deserialize)]
+pub struct BoardQuery {
+    page: Option<usize>,
+    /// A character name to show the neighbourhood of, the session's character when left out
+    around: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct BoardView {
+    id: &'static str,
+    title: &'static str,
+}
+
+#[derive(serde::Serialize)]
+struct EntryView {
+    rank: usize,
+    name: String,
+    score: String,
+    highlight: bool,
+}
+
+#[derive(serde::Serialize)]
+struct SeasonView {
+    number: i32,
+    started_at: String,
+    ended_at: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct SnapshotView {
+    board: BoardView,
+    entries: Vec<EntryView>,
+}
+
+fn board_view(board: Leaderboard) -> BoardView {
+    BoardView { id: board.as_str(), title: board.title() }
+}
+
+fn entry_views(board: Leaderboard, entries: Vec<RankedEntry>, highlight: Option<uuid::Uuid>) -> Vec<EntryView> {
+    entries.into_iter()
+        .map(|e| EntryView {
+            rank: e.rank,
+            score: board.format_score(e.score),
+            highlight: Some(e.character_id) == highlight,
+            name: e.character_name,
+        })
+        .collect()
+}
+
+fn parse_board(board: &str) -> Result<Leaderboard, actix_web::Error> {
+    Leaderboard::try_from(board.to_string()).map_err(e404)
+}
+
+#[tracing::instrument(
+name = "Get leaderboards",
+skip(tpl, pool)
+)]
+pub async fn get_leaderboards(
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let seasons: Vec<SeasonView> = get_seasons(pool.get_ref())
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|s| SeasonView {
+            number: s.number,
+            started_at: s.started_at.format("%Y-%m-%d").to_string(),
+            ended_at: s.ended_at.map(|e| e.format("%Y-%m-%d").to_string()),
+        })
+        .collect();
+    let boards: Vec<BoardView> = Leaderboard::ALL.into_iter().map(board_view).collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("boards", &boards);
+    ctx.insert("seasons", &seasons);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("leaderboards/index.html", &ctx).map_err(e500)?
+            )
+    )
+}
+
+#[tracing::instrument(
+name = "Get leaderboard",
+skip(query, tpl, pool, leaderboards, session)
+)]
+pub async fn get_leaderboard(
+    board: Path<String>,
+    query: Query<BoardQuery>,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    leaderboards: Data<LeaderboardService>,
+    session: YaugSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let board = parse_board(&board)?;
+    let page_number = query.page.unwrap_or(1).max(1);
+    let page = leaderboards.top(board, page_number).await.map_err(e500)?;
+
+    let around_name = query.around.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    let character_id = match around_name {
+        Some(name) => find_character_id_by_name(pool.get_ref(), name).await.map_err(e500)?,
+        None => session.get_active_character().map_err(e500)?,
+    };
+    let around = match character_id {
+        Some(character_id) => leaderboards.around(board, character_id)
+            .await
+            .map_err(e500)?
+            .map(|entries| entry_views(board, entries, Some(character_id))),
+        None => None,
+    };
+    // a character of your own that hasn't made it onto the board yet needs no explaining
+    let missing = match (around_name, character_id, &around) {
+        (Some(name), None, _) => Some(format!("There is no character called {}", name)),
+        (Some(name), Some(_), None) => Some(format!("{} is not on this leaderboard", name)),
+        _ => None,
+    };
+
+    let page_size = leaderboards.settings().page_size;
+    let mut ctx = Context::new();
+    ctx.insert("board", &board_view(board));
+    ctx.insert("entries", &entry_views(board, page.entries, character_id));
+    ctx.insert("total", &page.total);
+    ctx.insert("page", &page_number);
+    ctx.insert("has_previous", &(page_number > 1));
+    ctx.insert("has_next", &(page_number * page_size < page.total));
+    ctx.insert("around", &around);
+    ctx.insert("missing", &missing);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("leaderboards/board.html", &ctx).map_err(e500)?
+            )
+    )
+}
+
+#[tracing::instrument(
+name = "Get leaderboard season",
+skip(tpl, pool)
+)]
+pub async fn get_leaderboard_season(
+    number: Path<i32>,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let number = number.into_inner();
+    let season = get_seasons(pool.get_ref())
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .find(|s| s.number == number)
+        .ok_or_else(|| e404(format!("There is no season {}", number)))?;
+    let ended_at = season.ended_at
+        .ok_or_else(|| e404(format!("Season {} is still running", number)))?;
+
+    let mut snapshots = Vec::new();
+    for board in Leaderboard::ALL {
+        let entries = get_season_snapshot(pool.get_ref(), number, board).await.map_err(e500)?;
+        snapshots.push(SnapshotView { board: board_view(board), entries: entry_views(board, entries, None) });
+    }
+
+    let mut ctx = Context::new();
+    ctx.insert("season", &SeasonView {
+        number,
+        started_at: season.started_at.format("%Y-%m-%d").to_string(),
+        ended_at: Some(ended_at.format("%Y-%m-%d").to_string()),
+    });
+    ctx.insert("snapshots", &snapshots);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("leaderboards/season.html", &ctx).map_err(e500)?
+            )
+    )
+}
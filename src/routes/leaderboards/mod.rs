@@ -0,0 +1,3 @@
+mod get;
+
+pub use get::{get_leaderboard, get_leaderboard_season, get_leaderboards};
\ No newline at end of file
@@ -5,10 +5,13 @@ use actix_web::http::header::LOCATION;
 use actix_web::http::StatusCode;
 use actix_web::web::{Data, Form};
 use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
 use secrecy::{Secret};
 use serde::Deserialize;
-use sqlx::PgPool;
+use uuid::Uuid;
 use crate::authentication::{AuthenticationError, Credentials, validate_login_credentials, YaugSession};
+use crate::store::Store;
 use crate::utils::{error_chain_fmt, see_other};
 
 #[derive(Deserialize)]
@@ -42,32 +45,38 @@ impl ResponseError for LoginError {
 
 impl std::fmt::Debug for LoginError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        error_chain_fmt(&self, f)
+        error_chain_fmt(self, f)
     }
 }
 
 #[tracing::instrument(
 name = "Post login",
-skip(data, pool, session),
+skip(data, store, session),
 fields(email = tracing::field::Empty, user_id = tracing::field::Empty)
 )]
 pub async fn post_login(
     data: Form<LoginFormData>,
-    pool: Data<PgPool>,
+    store: Data<dyn Store>,
     session: YaugSession,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
     let credentials = Credentials {
         email: data.0.email,
         password: data.0.password,
     };
-    tracing::Span::current().record("email", &tracing::field::display(&credentials.email));
+    tracing::Span::current().record("email", tracing::field::display(&credentials.email));
 
-    match validate_login_credentials(credentials, &pool).await {
+    match validate_login_credentials(credentials, store.get_ref()).await {
         Ok(user_id) => {
             session.renew();
             session.insert_user_id(user_id)
                 .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
-            tracing::Span::current().record("user_id", &tracing::field::display(&user_id));
+            tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+            // the login itself went through, failing to note it shouldn't lock the player out
+            match record_login(store.get_ref(), user_id).await {
+                Ok(previous) => session.insert_previous_login(previous)
+                    .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?,
+                Err(e) => tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to record login"),
+            }
             Ok(see_other("/account"))
         }
         Err(e) => {
@@ -80,6 +89,20 @@ pub async fn post_login(
     }
 }
 
+/// Returns when the account logged in before this
+async fn record_login(store: &dyn Store, user_id: Uuid) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+    let mut tx = store.begin()
+        .await
+        .context("Failed to begin login transaction")?;
+    let previous = tx.record_login(user_id)
+        .await
+        .context("Failed to record login")?;
+    tx.commit()
+        .await
+        .context("Failed to commit login transaction")?;
+    Ok(previous)
+}
+
 fn login_redirect(e: LoginError) -> InternalError<LoginError> {
     FlashMessage::error(e.to_string()).send();
     let response = HttpResponse::SeeOther()
@@ -0,0 +1,163 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Path};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use uuid::Uuid;
+use crate::characters::ActiveCharacter;
+use crate::items::InventoryService;
+use crate::ledger::Currency;
+use crate::mail::{Mail, MailError, MailService};
+use crate::utils::{e404, e500};
+
+#[derive(serde::Serialize)]
+struct MailView {
+    id: String,
+    from: String,
+    subject: String,
+    body: String,
+    kind: &'static str,
+    unread: bool,
+    attachments: Vec<String>,
+    cod_amount: i64,
+    claimable: bool,
+    returnable: bool,
+    sent_at: String,
+    expires_at: String,
+}
+
+#[derive(serde::Serialize)]
+struct SlotView {
+    slot: usize,
+    name: String,
+    quantity: i32,
+}
+
+fn flash_lines(flash_messages: &IncomingFlashMessages) -> Vec<String> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+    flash
+}
+
+fn mail_view(inventory: &InventoryService, mail: Mail) -> MailView {
+    let catalog = inventory.catalog();
+    let mut attachments: Vec<String> = Vec::new();
+    if mail.claimed_at.is_none() {
+        if let Some((currency, amount)) = mail.money {
+            attachments.push(format!("{} {}", amount, currency));
+        }
+        for stack in &mail.items {
+            let name = catalog.get(&stack.item_id)
+                .map(|item| item.name.clone())
+                .unwrap_or_else(|| stack.item_id.clone());
+            attachments.push(format!("{} x{}", name, stack.quantity));
+        }
+    }
+    MailView {
+        id: mail.id.to_string(),
+        claimable: mail.has_attachments(),
+        returnable: mail.is_returnable(),
+        from: mail.sender_name,
+        subject: mail.subject,
+        body: mail.body,
+        kind: mail.kind.as_str(),
+        unread: mail.read_at.is_none(),
+        attachments,
+        cod_amount: if mail.claimed_at.is_none() { mail.cod_amount } else { 0 },
+        sent_at: mail.created_at.format("%Y-%m-%d %H:%M").to_string(),
+        expires_at: mail.expires_at.format("%Y-%m-%d").to_string(),
+    }
+}
+
+#[tracing::instrument(
+name = "Get mailbox",
+skip(flash_messages, tpl, pool, mail, inventory, character),
+fields(character_id = % character.id)
+)]
+pub async fn get_mailbox(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    mail: Data<MailService>,
+    inventory: Data<InventoryService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mailbox: Vec<MailView> = mail.mailbox(character.id)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|m| mail_view(&inventory, m))
+        .collect();
+    let unread = mailbox.iter().filter(|m| m.unread).count();
+
+    let catalog = inventory.catalog();
+    let backpack: Vec<SlotView> = inventory.get_inventory(pool.get_ref(), character.id)
+        .await
+        .map_err(e500)?
+        .slots()
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, stack)| stack.as_ref().map(|stack| SlotView {
+            slot,
+            name: catalog.get(&stack.item_id)
+                .map(|item| item.name.clone())
+                .unwrap_or_else(|| stack.item_id.clone()),
+            quantity: stack.quantity,
+        }))
+        .collect();
+    let currencies: Vec<&'static str> = Currency::ALL.iter().map(|c| c.as_str()).collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash_lines(&flash_messages));
+    ctx.insert("character", &character.name.to_string());
+    ctx.insert("mailbox", &mailbox);
+    ctx.insert("unread", &unread);
+    ctx.insert("backpack", &backpack);
+    ctx.insert("currencies", &currencies);
+    ctx.insert("max_attachments", &mail.settings().max_attachments);
+    ctx.insert("request_key", &Uuid::new_v4().to_string());
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("mail/mailbox.html", &ctx).map_err(e500)?
+            )
+    )
+}
+
+#[tracing::instrument(
+name = "Read mail",
+skip(flash_messages, tpl, mail, inventory, character),
+fields(character_id = % character.id)
+)]
+pub async fn get_mail(
+    flash_messages: IncomingFlashMessages,
+    path: Path<Uuid>,
+    tpl: Data<Tera>,
+    mail: Data<MailService>,
+    inventory: Data<InventoryService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let message = match mail.read(character.id, path.into_inner()).await {
+        Ok(message) => message,
+        Err(MailError::MailNotFound) => return Err(e404("Mail not found")),
+        Err(e) => return Err(e500(e)),
+    };
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash_lines(&flash_messages));
+    ctx.insert("mail", &mail_view(&inventory, message));
+    ctx.insert("request_key", &Uuid::new_v4().to_string());
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("mail/mail.html", &ctx).map_err(e500)?
+            )
+    )
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::{get_mailbox, get_mail};
+pub use post::{post_send_mail, post_claim_mail, post_return_mail, post_delete_mail};
\ No newline at end of file
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use actix_web::HttpResponse;
+use actix_web::web::{Data, Form, Path};
+use uuid::Uuid;
+use crate::characters::ActiveCharacter;
+use crate::ledger::Currency;
+use crate::mail::{MailDraft, MailError, MailService};
+use crate::routes::{finish, request_key, RequestKeyForm};
+
+/// Turns the compose form into a draft. Every backpack stack has a `slot_<n>` quantity field, the
+/// ones left at 0 aren't attached.
+fn parse_draft(form: &HashMap<String, String>) -> Result<MailDraft, MailError> {
+    let field = |name: &str| form.get(name).map(|v| v.trim()).unwrap_or_default();
+    let number = |name: &str| -> Result<i64, MailError> {
+        match field(name) {
+            "" => Ok(0),
+            value => value.parse().map_err(|_| MailError::ValidationError(format!("{} is not a valid amount", value))),
+        }
+    };
+
+    let amount = number("amount")?;
+    let money = if amount != 0 {
+        let currency = Currency::try_from(field("currency").to_string()).map_err(MailError::ValidationError)?;
+        Some((currency, amount))
+    } else {
+        None
+    };
+
+    let mut items = Vec::new();
+    for (key, value) in form {
+        if let Some(slot) = key.strip_prefix("slot_").and_then(|s| s.parse::<usize>().ok()) {
+            let quantity: i32 = value.trim()
+                .parse()
+                .map_err(|_| MailError::ValidationError(format!("{} is not a valid quantity", value)))?;
+            if quantity != 0 {
+                items.push((slot, quantity));
+            }
+        }
+    }
+    items.sort();
+
+    Ok(MailDraft {
+        to: field("to").to_string(),
+        subject: field("subject").to_string(),
+        body: field("body").to_string(),
+        money,
+        items,
+        cod_amount: number("cod_amount")?,
+        request_key: Some(field("request_key")).filter(|k| !k.is_empty()).map(str::to_string),
+    })
+}
+
+#[tracing::instrument(
+name = "Send mail",
+skip(form, mail, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_send_mail(
+    form: Form<HashMap<String, String>>,
+    mail: Data<MailService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = match parse_draft(&form) {
+        Ok(draft) => mail.send(&character, draft).await,
+        Err(e) => Err(e),
+    };
+    finish(outcome, "/mail", |_| "Your mail is on its way".to_string())
+}
+
+#[tracing::instrument(
+name = "Claim mail",
+skip(form, mail, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_claim_mail(
+    path: Path<Uuid>,
+    form: Form<RequestKeyForm>,
+    mail: Data<MailService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let id = path.into_inner();
+    let outcome = mail.claim(&character, id, request_key(&form.request_key)).await;
+    // mail that doesn't exist has no page to go back to
+    let location = match outcome {
+        Err(MailError::MailNotFound) => "/mail".to_string(),
+        _ => format!("/mail/{}", id),
+    };
+    finish(outcome, &location, |_| "You took the attachments".to_string())
+}
+
+#[tracing::instrument(
+name = "Return mail",
+skip(mail, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_return_mail(
+    path: Path<Uuid>,
+    mail: Data<MailService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = mail.return_to_sender(character.id, path.into_inner()).await;
+    finish(outcome, "/mail", |_| "The mail was sent back".to_string())
+}
+
+#[tracing::instrument(
+name = "Delete mail",
+skip(mail, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_delete_mail(
+    path: Path<Uuid>,
+    mail: Data<MailService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = mail.delete(character.id, path.into_inner()).await;
+    finish(outcome, "/mail", |_| "Mail deleted".to_string())
+}
@@ -0,0 +1,81 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::Data;
+use actix_web_flash_messages::IncomingFlashMessages;
+use chrono::Utc;
+use sqlx::PgPool;
+use tera::{Context, Tera};
+use crate::authentication::UserId;
+use crate::characters::ActiveCharacter;
+use crate::matchmaking::{get_ratings, MatchmakingService, QueueKind, QueueStatus};
+use crate::utils::e500;
+
+#[derive(serde::Serialize)]
+struct QueueView {
+    id: &'static str,
+    title: &'static str,
+    players_per_side: usize,
+    rated: bool,
+    waiting: usize,
+}
+
+fn flash_lines(flash_messages: &IncomingFlashMessages) -> Vec<String> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+    flash
+}
+
+#[tracing::instrument(
+name = "Get matchmaking",
+skip(flash_messages, tpl, pool, matchmaking, character),
+fields(character_id = % character.id)
+)]
+pub async fn get_matchmaking(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    pool: Data<PgPool>,
+    matchmaking: Data<MatchmakingService>,
+    user_id: UserId,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let rating = get_ratings(pool.get_ref(), &[character.id])
+        .await
+        .map_err(e500)?
+        .remove(&character.id)
+        .unwrap_or_default();
+    let waiting = matchmaking.waiting();
+    let queues: Vec<QueueView> = QueueKind::ALL
+        .into_iter()
+        .map(|queue| QueueView {
+            id: queue.as_str(),
+            title: queue.title(),
+            players_per_side: queue.players_per_side(matchmaking.settings()),
+            rated: queue.is_rated(),
+            waiting: waiting.get(&queue).copied().unwrap_or_default(),
+        })
+        .collect();
+    let now = Utc::now();
+    let status = matchmaking.status(user_id, now);
+    let seconds_left = match &status {
+        Some(QueueStatus::ReadyCheck { deadline, .. }) => (*deadline - now).num_seconds().max(0),
+        _ => 0,
+    };
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash_lines(&flash_messages));
+    ctx.insert("rating", &rating.rating);
+    ctx.insert("games", &rating.games);
+    ctx.insert("queues", &queues);
+    ctx.insert("status", &status);
+    ctx.insert("seconds_left", &seconds_left);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("matchmaking/queues.html", &ctx).map_err(e500)?
+            )
+    )
+}
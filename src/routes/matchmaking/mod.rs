@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::get_matchmaking;
+pub use post::{post_join_queue, post_leave_queue, post_ready_check};
\ No newline at end of file
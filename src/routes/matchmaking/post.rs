@@ -0,0 +1,70 @@
+use actix_web::HttpResponse;
+use actix_web::web::{Data, Form, Path};
+use chrono::Utc;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::characters::ActiveCharacter;
+use crate::matchmaking::{MatchmakingService, QueueKind};
+use crate::parties::PartyService;
+use crate::routes::{fail, finish};
+
+#[derive(serde::Deserialize)]
+pub struct QueueForm {
+    queue: QueueKind,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ReadyForm {
+    accept: bool,
+}
+
+#[tracing::instrument(
+name = "Join queue",
+skip(form, matchmaking, parties, character),
+fields(character_id = % character.id, queue = % form.queue)
+)]
+pub async fn post_join_queue(
+    form: Form<QueueForm>,
+    matchmaking: Data<MatchmakingService>,
+    parties: Data<PartyService>,
+    user_id: UserId,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    // a party leader queues the whole party
+    let group = match parties.queue_group(user_id, &character) {
+        Ok(group) => group,
+        Err(e) => return fail(e, "/matchmaking"),
+    };
+    let outcome = matchmaking.join_group(form.queue, group, Utc::now()).await;
+    finish(outcome, "/matchmaking", |_| format!("You joined the {} queue", form.queue.title()))
+}
+
+#[tracing::instrument(
+name = "Leave queue",
+skip(matchmaking)
+)]
+pub async fn post_leave_queue(
+    matchmaking: Data<MatchmakingService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    finish(matchmaking.leave(user_id), "/matchmaking", |queue| format!("You left the {} queue", queue.title()))
+}
+
+#[tracing::instrument(
+name = "Answer ready check",
+skip(form, matchmaking),
+fields(accept = form.accept)
+)]
+pub async fn post_ready_check(
+    path: Path<Uuid>,
+    form: Form<ReadyForm>,
+    matchmaking: Data<MatchmakingService>,
+    user_id: UserId,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = matchmaking.ready(user_id, path.into_inner(), form.accept, Utc::now()).await;
+    finish(outcome, "/matchmaking", |started| match (form.accept, started) {
+        (true, Some(_)) => "Everyone is ready, the fight is on".to_string(),
+        (true, None) => "You are ready, waiting for the others".to_string(),
+        (false, _) => "You declined the match and left the queue".to_string(),
+    })
+}
@@ -1,9 +1,77 @@
+use actix_web::HttpResponse;
+use actix_web_flash_messages::FlashMessage;
+use crate::gateway::{ClientError, ErrorCode};
+use crate::utils::{e500, see_other};
+
 mod account;
+mod admin;
+mod auctions;
+mod characters;
+mod combat;
+mod crafting;
+mod friends;
+mod guilds;
 mod login;
 mod home;
+mod inventory;
+mod leaderboards;
+mod mail;
+mod matchmaking;
+mod players;
+mod quests;
 mod register;
+mod ws;
 
 pub use login::*;
-pub use account::get_account_home;
+pub use account::{get_account_home, get_profile_form, post_profile};
+pub use admin::{get_ledger_report, get_audit_log, get_chat_moderation, post_mute_player, post_unmute_player, get_leaderboard_admin, post_end_season, get_mail_admin, post_system_mail};
+pub use auctions::{get_auctions, post_create_listing, post_bid, post_buyout, post_cancel_listing};
+pub use characters::{get_characters, get_new_character_form, post_new_character, get_character_attributes_form, post_character_attributes, post_select_character, post_delete_character, post_restore_character, post_character_title};
+pub use combat::{get_combat_history, get_combat_log};
+pub use crafting::{get_crafting, post_queue_craft, post_cancel_craft};
+pub use friends::{get_friends, post_friend_request, post_accept_friend, post_decline_friend, post_cancel_friend_request, post_remove_friend, post_block_player, post_unblock_player};
+pub use guilds::{get_guild_list, get_own_guild, post_create_guild, post_apply_to_guild, post_accept_invitation, post_decline_invitation, post_guild_invite, post_accept_application, post_reject_application, post_kick_member, post_member_rank, post_rank_permissions, post_guild_motd, post_leave_guild, post_bank_deposit, post_bank_withdraw};
 pub use home::get_home_page;
-pub use register::{get_register_form, post_register};
\ No newline at end of file
+pub use inventory::{get_inventory, post_move_stack, post_split_stack};
+pub use leaderboards::{get_leaderboards, get_leaderboard, get_leaderboard_season};
+pub use mail::{get_mailbox, get_mail, post_send_mail, post_claim_mail, post_return_mail, post_delete_mail};
+pub use matchmaking::{get_matchmaking, post_join_queue, post_leave_queue, post_ready_check};
+pub use players::get_player;
+pub use quests::{get_quests, post_accept_quest, post_abandon_quest, post_complete_quest};
+
+/// Forms that pay carry a key made when the page was rendered, so posting one twice only pays once
+#[derive(serde::Deserialize)]
+pub struct RequestKeyForm {
+    #[serde(default)]
+    pub request_key: Option<String>,
+}
+
+/// Left out or blank means the client sent no key
+pub fn request_key(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|k| !k.is_empty())
+}
+pub use register::{get_register_form, post_register};
+pub use ws::get_ws;
+/// Flashes how a form post went and sends the player back to `location`
+fn finish<T, E: ClientError + 'static>(
+    outcome: Result<T, E>,
+    location: &str,
+    message: impl FnOnce(T) -> String,
+) -> Result<HttpResponse, actix_web::Error> {
+    match outcome {
+        Ok(value) => {
+            FlashMessage::info(message(value)).send();
+            Ok(see_other(location))
+        }
+        Err(e) => fail(e, location),
+    }
+}
+
+/// Rule violations are shown to the player, anything unexpected is a 500
+fn fail<E: ClientError + 'static>(e: E, location: &str) -> Result<HttpResponse, actix_web::Error> {
+    if e.code() == ErrorCode::Internal {
+        return Err(e500(e));
+    }
+    FlashMessage::error(e.to_string()).send();
+    Ok(see_other(location))
+}
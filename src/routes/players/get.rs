@@ -0,0 +1,94 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Path};
+use anyhow::Context as _;
+use tera::{Context, Tera};
+use crate::achievements::{AchievementProgress, AchievementService, StatisticTotal};
+use crate::store::Store;
+use crate::utils::{e404, e500};
+
+#[derive(serde::Serialize)]
+struct CharacterView {
+    name: String,
+    title: Option<String>,
+    class: &'static str,
+    level: i32,
+    statistics: Vec<StatisticTotal>,
+    earned: Vec<EarnedView>,
+    /// Achievements not earned yet, with how far along each criterion is
+    in_progress: Vec<AchievementProgress>,
+}
+
+#[derive(serde::Serialize)]
+struct EarnedView {
+    name: String,
+    description: String,
+    earned_on: String,
+}
+
+#[tracing::instrument(
+name = "Get player page",
+skip(tpl, store, achievements)
+)]
+pub async fn get_player(
+    name: Path<String>,
+    tpl: Data<Tera>,
+    store: Data<dyn Store>,
+    achievements: Data<AchievementService>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut tx = store.begin()
+        .await
+        .context("Failed to begin profile lookup transaction")
+        .map_err(e500)?;
+    let profile = tx.get_profile_by_display_name(&name)
+        .await
+        .context("Failed to fetch profile")
+        .map_err(e500)?
+        .ok_or_else(|| e404(format!("There is no player called {}", name)))?;
+    let stored = tx.get_characters_by_user_id(profile.user_id)
+        .await
+        .context("Failed to fetch characters")
+        .map_err(e500)?;
+
+    let mut characters = Vec::new();
+    for character in stored {
+        if character.is_deleted() {
+            continue;
+        }
+        let overview = achievements.overview(character.id).await.map_err(e500)?;
+        let (earned, in_progress): (Vec<_>, Vec<_>) = overview.achievements
+            .into_iter()
+            .partition(|a| a.earned_at.is_some());
+        characters.push(CharacterView {
+            name: character.name.to_string(),
+            title: overview.title,
+            class: character.class.as_str(),
+            level: character.level,
+            statistics: overview.statistics,
+            earned: earned
+                .into_iter()
+                .map(|a| EarnedView {
+                    name: a.name,
+                    description: a.description,
+                    earned_on: a.earned_at.map(|at| at.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+                })
+                .collect(),
+            in_progress,
+        });
+    }
+
+    let mut ctx = Context::new();
+    ctx.insert("display_name", profile.display_name.as_ref());
+    ctx.insert("avatar", profile.avatar.as_str());
+    ctx.insert("bio", profile.bio.as_ref());
+    ctx.insert("member_since", &profile.created_at.format("%Y-%m-%d").to_string());
+    ctx.insert("characters", &characters);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("players/profile.html", &ctx).map_err(e500)?
+            )
+    )
+}
@@ -0,0 +1,3 @@
+mod get;
+
+pub use get::get_player;
\ No newline at end of file
@@ -0,0 +1,107 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::ContentType;
+use actix_web::web::Data;
+use actix_web_flash_messages::IncomingFlashMessages;
+use tera::{Context, Tera};
+use crate::characters::ActiveCharacter;
+use crate::items::{InventoryService, ItemCatalog};
+use crate::quests::{QuestDefinition, QuestService};
+use crate::utils::e500;
+
+#[derive(serde::Serialize)]
+struct QuestView {
+    id: String,
+    name: String,
+    description: String,
+    level: i32,
+    rewards: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+struct ObjectiveView {
+    text: String,
+    progress: i32,
+    required: i32,
+}
+
+#[derive(serde::Serialize)]
+struct ActiveQuestView {
+    quest: QuestView,
+    objectives: Vec<ObjectiveView>,
+    finished: bool,
+}
+
+fn quest_view(catalog: &ItemCatalog, quest: &QuestDefinition) -> QuestView {
+    let mut rewards = Vec::new();
+    if quest.rewards.experience > 0 {
+        rewards.push(format!("{} experience", quest.rewards.experience));
+    }
+    for (currency, amount) in &quest.rewards.currency {
+        rewards.push(format!("{} {}", amount, currency));
+    }
+    for reward in &quest.rewards.items {
+        let name = catalog.get(&reward.item).map_or(reward.item.as_str(), |i| i.name.as_str());
+        rewards.push(format!("{} x{}", name, reward.quantity));
+    }
+    QuestView {
+        id: quest.id.clone(),
+        name: quest.name.clone(),
+        description: quest.description.clone(),
+        level: quest.level,
+        rewards,
+    }
+}
+
+#[tracing::instrument(
+name = "Get quests",
+skip(flash_messages, tpl, quests, inventory, character),
+fields(character_id = % character.id)
+)]
+pub async fn get_quests(
+    flash_messages: IncomingFlashMessages,
+    tpl: Data<Tera>,
+    quests: Data<QuestService>,
+    inventory: Data<InventoryService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut flash: Vec<String> = Vec::new();
+    for fm in flash_messages.iter() {
+        flash.push(format!("<p><i>{}</i></p>", fm.content()))
+    }
+
+    let catalog = inventory.catalog();
+    let journal = quests.journal(&character).await.map_err(e500)?;
+    let active: Vec<ActiveQuestView> = journal.active
+        .iter()
+        .map(|a| ActiveQuestView {
+            quest: quest_view(catalog, a.quest),
+            objectives: a.quest.objectives
+                .iter()
+                .zip(&a.progress)
+                .map(|(objective, progress)| ObjectiveView {
+                    text: quests.describe(objective),
+                    progress: *progress,
+                    required: objective.required(),
+                })
+                .collect(),
+            finished: a.finished,
+        })
+        .collect();
+    let available: Vec<QuestView> = journal.available.iter().map(|q| quest_view(catalog, q)).collect();
+    let completed: Vec<QuestView> = journal.completed.iter().map(|q| quest_view(catalog, q)).collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("flash", &flash);
+    ctx.insert("character", character.name.as_ref());
+    ctx.insert("active", &active);
+    ctx.insert("available", &available);
+    ctx.insert("completed", &completed);
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(
+                tpl.render("quests/journal.html", &ctx).map_err(e500)?
+            )
+    )
+}
\ No newline at end of file
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::get_quests;
+pub use post::{post_abandon_quest, post_accept_quest, post_complete_quest};
\ No newline at end of file
@@ -0,0 +1,47 @@
+use actix_web::HttpResponse;
+use actix_web::web::{Data, Path};
+use crate::characters::ActiveCharacter;
+use crate::quests::QuestService;
+use crate::routes::finish;
+
+#[tracing::instrument(
+name = "Accept quest",
+skip(quests, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_accept_quest(
+    path: Path<String>,
+    quests: Data<QuestService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = quests.accept(&character, &path).await;
+    finish(outcome, "/quests", |quest| format!("{} was added to your journal", quest.name))
+}
+
+#[tracing::instrument(
+name = "Abandon quest",
+skip(quests, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_abandon_quest(
+    path: Path<String>,
+    quests: Data<QuestService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = quests.abandon(&character, &path).await;
+    finish(outcome, "/quests", |quest| format!("You abandoned {}", quest.name))
+}
+
+#[tracing::instrument(
+name = "Complete quest",
+skip(quests, character),
+fields(character_id = % character.id)
+)]
+pub async fn post_complete_quest(
+    path: Path<String>,
+    quests: Data<QuestService>,
+    character: ActiveCharacter,
+) -> Result<HttpResponse, actix_web::Error> {
+    let outcome = quests.complete(&character, &path).await;
+    finish(outcome, "/quests", |quest| format!("You completed {}", quest.name))
+}
@@ -5,11 +5,11 @@ use actix_web::web::{Data, Form};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
 use secrecy::{ExposeSecret, Secret};
-use sqlx::PgPool;
 use uuid::Uuid;
+use crate::configuration::RegistrationSettings;
 use crate::domain::{AccountCredentials, AccountPassword, AccountEmail};
 use crate::helpers::generate_subscription_token;
-use crate::store::{get_account_by_email, store_user_account, store_user_activation_email_job, store_user_activation_token};
+use crate::store::{Store, StoreError};
 use crate::utils::{error_chain_fmt, see_other};
 
 #[derive(serde::Deserialize)]
@@ -23,7 +23,7 @@ impl TryFrom<FormData> for AccountCredentials {
     type Error = String;
     fn try_from(value: FormData) -> Result<Self, Self::Error> {
         if value.password_check.expose_secret() != value.password.expose_secret() {
-            return Err(format!("Password mismatch"));
+            return Err("Password mismatch".to_string());
         }
 
         let email = AccountEmail::parse(value.email)?;
@@ -60,48 +60,57 @@ impl ResponseError for RegistrationError {
 
 #[tracing::instrument(
 name = "Account registration",
-skip(data, pool),
+skip(data, store, settings),
 fields(
 account_email = % data.email
 )
 )]
 pub async fn post_register(
     data: Form<FormData>,
-    pool: Data<PgPool>,
+    store: Data<dyn Store>,
+    settings: Data<RegistrationSettings>,
 ) -> Result<HttpResponse, RegistrationError> {
     let new_account: AccountCredentials = data.0.try_into().map_err(RegistrationError::ValidationError)?;
-    if let Some(_u) = get_account_by_email(&pool, &new_account.email)
-        .await
-        .map_err(RegistrationError::UnexpectedError)?
-    {
-        // we already have a user that is registered
-        FlashMessage::error("This email address is already registered").send();
-        return Ok(see_other("/register"));
+    if settings.is_blocked(&new_account.email) {
+        return Err(RegistrationError::ValidationError(
+            "Disposable email addresses are not allowed".to_string()
+        ));
     }
 
-    let mut tx = pool.begin()
+    let mut tx = store.begin()
         .await
         .context("Failed to get pool transaction lock")?;
 
+    if let Some(_u) = tx.get_account_by_email(&new_account.email)
+        .await
+        .context("Failed to execute account fetch query")?
+    {
+        // we already have a user that is registered
+        return Ok(already_registered());
+    }
+
     // insert user into accounts
-    let account_id =
-        store_user_account(
-            &mut tx,
-            Uuid::new_v4(),
-            &new_account.email,
-            new_account.password.compute_hash().await?,
-        ).await.context("Failed to store new user account")?;
+    let account_id = match tx.store_user_account(
+        Uuid::new_v4(),
+        &new_account.email,
+        new_account.password.compute_hash().await?,
+    ).await {
+        Ok(account_id) => account_id,
+        // someone registered the same email between our check and the insert
+        Err(StoreError::Conflict(_)) => return Ok(already_registered()),
+        Err(e) => return Err(anyhow::Error::from(e).context("Failed to store new user account").into()),
+    };
 
     // store activation token
     let token = generate_subscription_token(32);
 
     // store job to send activation email
-    store_user_activation_token(&mut tx, account_id, &token)
+    tx.store_user_activation_token(account_id, &token)
         .await
         .context("Failed to store account activation token")?;
 
     // queue up activation email job
-    store_user_activation_email_job(&mut tx, &new_account.email, "") //TODO: content
+    tx.store_user_activation_email_job(&new_account.email, "") //TODO: content
         .await
         .context("Failed to save activation email job")?;
 
@@ -114,3 +123,106 @@ pub async fn post_register(
     ).send();
     Ok(see_other("/login"))
 }
+
+fn already_registered() -> HttpResponse {
+    FlashMessage::error("This email address is already registered").send();
+    see_other("/register")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use actix_web::{App, test, web};
+    use actix_web::cookie::Key;
+    use actix_web::http::header::LOCATION;
+    use actix_web::web::Data;
+    use actix_web_flash_messages::FlashMessagesFramework;
+    use actix_web_flash_messages::storage::CookieMessageStore;
+    use fake::Fake;
+    use fake::faker::internet::en::SafeEmail;
+    use secrecy::ExposeSecret;
+    use crate::configuration::RegistrationSettings;
+    use crate::domain::{AccountEmail, AccountPassword};
+    use crate::routes::post_register;
+    use crate::store::{InMemoryStore, Store};
+
+    fn registration_form(email: &str) -> Vec<(&'static str, String)> {
+        let password = AccountPassword::generate_password(12);
+        vec![
+            ("email", email.to_string()),
+            ("password", password.expose_secret().to_string()),
+            ("password_check", password.expose_secret().to_string()),
+        ]
+    }
+
+    fn settings() -> RegistrationSettings {
+        RegistrationSettings {
+            block_disposable_emails: true,
+            disposable_email_domains: vec!["mailinator.com".to_string()],
+        }
+    }
+
+    async fn register(store: Arc<InMemoryStore>, email: &str) -> actix_web::dev::ServiceResponse {
+        let message_store = CookieMessageStore::builder(Key::generate()).build();
+        let app = test::init_service(
+            App::new()
+                .wrap(FlashMessagesFramework::builder(message_store).build())
+                .app_data(Data::from(store as Arc<dyn Store>))
+                .app_data(Data::new(settings()))
+                .route("/register", web::post().to(post_register))
+        ).await;
+        let request = test::TestRequest::post()
+            .uri("/register")
+            .set_form(registration_form(email))
+            .to_request();
+        test::call_service(&app, request).await
+    }
+
+    #[actix_web::test]
+    async fn valid_registration_is_stored_and_redirects_to_login() {
+        let store = Arc::new(InMemoryStore::new());
+        let email: String = SafeEmail().fake();
+
+        let response = register(store.clone(), &email).await;
+
+        assert_eq!(response.status().as_u16(), 303);
+        assert_eq!(response.headers().get(LOCATION).unwrap(), "/login");
+        let mut tx = store.begin().await.unwrap();
+        let email = AccountEmail::parse(email).unwrap();
+        assert!(tx.get_account_by_email(&email).await.unwrap().is_some());
+    }
+
+    #[actix_web::test]
+    async fn duplicate_registration_redirects_back_to_register() {
+        let store = Arc::new(InMemoryStore::new());
+        let email: String = SafeEmail().fake();
+
+        register(store.clone(), &email).await;
+        let response = register(store, &email).await;
+
+        assert_eq!(response.status().as_u16(), 303);
+        assert_eq!(response.headers().get(LOCATION).unwrap(), "/register");
+    }
+
+    #[actix_web::test]
+    async fn emails_differing_only_by_case_are_the_same_account() {
+        let store = Arc::new(InMemoryStore::new());
+
+        register(store.clone(), "Bob.Smith@Example.com").await;
+        let response = register(store, " bob.smith@EXAMPLE.COM").await;
+
+        assert_eq!(response.headers().get(LOCATION).unwrap(), "/register");
+    }
+
+    #[actix_web::test]
+    async fn disposable_email_domains_are_rejected() {
+        let store = Arc::new(InMemoryStore::new());
+
+        let response = register(store.clone(), "someone@inbox.Mailinator.com").await;
+
+        assert_eq!(response.status().as_u16(), 400);
+        let mut tx = store.begin().await.unwrap();
+        let email = AccountEmail::parse("someone@inbox.mailinator.com".to_string()).unwrap();
+        assert!(tx.get_account_by_email(&email).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,32 @@
+use actix_web::{HttpRequest, HttpResponse};
+use actix_web::web::{Data, Payload};
+use crate::authentication::UserId;
+use crate::characters::ActiveCharacter;
+use crate::gateway::{run_connection, GatewayServices};
+use crate::utils::e500;
+
+/// Upgrades to a WebSocket, the session cookie already proved who is connecting
+#[tracing::instrument(
+name = "Open WebSocket",
+skip(req, body, services, character)
+)]
+pub async fn get_ws(
+    req: HttpRequest,
+    body: Payload,
+    services: Data<GatewayServices>,
+    user_id: UserId,
+    character: Option<ActiveCharacter>,
+) -> Result<HttpResponse, actix_web::Error> {
+    services.guilds.connected(user_id).await.map_err(e500)?;
+    let (response, session, stream) = actix_ws::handle(&req, body)?;
+
+    actix_web::rt::spawn(run_connection(
+        session,
+        stream,
+        user_id,
+        character.map(ActiveCharacter::into_inner),
+        services.get_ref().clone(),
+    ));
+
+    Ok(response)
+}
@@ -1,10 +1,12 @@
 use actix_web::dev::Server;
 use std::net::TcpListener;
+use std::sync::Arc;
 use actix_session::SessionMiddleware;
 use actix_session::storage::RedisSessionStore;
 use actix_web::{App, HttpServer, web};
 use actix_web::cookie::Key;
 use actix_web::web::Data;
+use anyhow::Context;
 use actix_web_flash_messages::FlashMessagesFramework;
 use actix_web_flash_messages::storage::CookieMessageStore;
 use actix_web_lab::middleware::from_fn;
@@ -13,14 +15,53 @@ use sqlx::PgPool;
 use tera::Tera;
 use tracing_actix_web::TracingLogger;
 use crate::authentication::reject_anonymous_users;
-use crate::configuration::Settings;
+use crate::chat::ChatService;
+use crate::combat::{get_combat_rules, CombatService, CombatTurnTimeoutSystem};
+use crate::configuration::{CharacterSettings, GatewaySettings, RegistrationSettings, Settings};
 use crate::email_client::EmailClient;
-use crate::routes::{get_account_home, get_home_page, get_login_form, post_login, get_register_form, post_register};
+use crate::events::GameEvents;
+use crate::game_loop::GameLoop;
+use crate::world::{get_world_map, PositionFlushSystem, WorldService};
+use crate::gateway::{ConnectionRegistry, GatewayServices};
+use crate::friends::FriendService;
+use crate::guilds::GuildService;
+use crate::mail::{MailExpirySystem, MailService};
+use crate::auctions::{AuctionExpirySystem, AuctionService};
+use crate::crafting::{get_recipe_book, CraftingQueueSystem, CraftingService};
+use crate::achievements::{get_achievement_book, AchievementService, AchievementSystem};
+use crate::matchmaking::{Matchmaker, MatchmakingService};
+use crate::parties::{PartyService, PartySystem};
+use crate::npcs::{get_npc_book, NpcService, NpcSystem};
+use crate::items::{get_item_catalog, InventoryService};
+use crate::loot::{get_loot_book, LootService};
+use crate::leaderboards::{LeaderboardCache, LeaderboardRefreshSystem, LeaderboardService};
+use crate::presence::PresenceStore;
+use crate::quests::{get_quest_book, QuestProgressSystem, QuestService};
+use crate::store::{PostgresStore, Store};
+use crate::routes::{get_account_home, get_home_page, get_login_form, post_login, get_register_form, post_register, get_profile_form, post_profile, get_player, get_characters, get_new_character_form, post_new_character, get_character_attributes_form, post_character_attributes, post_select_character, post_delete_character, post_restore_character, get_inventory, post_move_stack, post_split_stack, get_ledger_report, get_audit_log, get_chat_moderation, post_mute_player, post_unmute_player, get_combat_history, get_combat_log, get_quests, post_accept_quest, post_abandon_quest, post_complete_quest, get_leaderboards, get_leaderboard, get_leaderboard_season, get_leaderboard_admin, post_end_season, get_guild_list, get_own_guild, post_create_guild, post_apply_to_guild, post_accept_invitation, post_decline_invitation, post_guild_invite, post_accept_application, post_reject_application, post_kick_member, post_member_rank, post_rank_permissions, post_guild_motd, post_leave_guild, post_bank_deposit, post_bank_withdraw, get_friends, post_friend_request, post_accept_friend, post_decline_friend, post_cancel_friend_request, post_remove_friend, post_block_player, post_unblock_player, get_mailbox, get_mail, post_send_mail, post_claim_mail, post_return_mail, post_delete_mail, get_mail_admin, post_system_mail, get_auctions, post_create_listing, post_bid, post_buyout, post_cancel_listing, get_crafting, post_queue_craft, post_cancel_craft, post_character_title, get_matchmaking, post_join_queue, post_leave_queue, post_ready_check, get_ws};
 
 //region Application & impl
 pub struct Application {
     port: u16,
     server: Server,
+    registry: ConnectionRegistry,
+    chat: Arc<ChatService>,
+    world: Arc<WorldService>,
+    combat: Arc<CombatService>,
+    quests: Arc<QuestService>,
+    leaderboards: Arc<LeaderboardService>,
+    guilds: Arc<GuildService>,
+    friends: Arc<FriendService>,
+    mail: Arc<MailService>,
+    auctions: Arc<AuctionService>,
+    crafting: Arc<CraftingService>,
+    achievements: Arc<AchievementService>,
+    matchmaking: Arc<MatchmakingService>,
+    parties: Arc<PartyService>,
+    npcs: Arc<NpcService>,
+    events: GameEvents,
+    game_loop: Option<GameLoop>,
+    matchmaker: Option<Matchmaker>,
 }
 
 impl Application {
@@ -37,24 +78,239 @@ impl Application {
         tera.autoescape_on(vec![]);
 
         let email_client = config.email.client();
+        let pool = config.db.get_connection_pool();
+        let store = Arc::new(PostgresStore::new(pool.clone()));
+        let catalog = get_item_catalog().context("Failed to load item catalog")?;
+        tracing::info!("Loaded {} item definitions", catalog.len());
+        let inventory = Arc::new(InventoryService::new(Arc::new(catalog)));
+        let registry = ConnectionRegistry::new();
+        let events = GameEvents::new(config.game.event_queue_size);
+        let chat = Arc::new(ChatService::new(pool.clone(), registry.clone(), config.chat));
+        let world_map = get_world_map().context("Failed to load zones")?;
+        tracing::info!("Loaded {} zones", world_map.len());
+        let position_flush_interval = config.world.position_flush_interval();
+        let world = Arc::new(
+            WorldService::new(pool.clone(), registry.clone(), chat.clone(), Arc::new(world_map), events.clone(), config.world)
+                .context("Invalid world settings")?
+        );
+
+        let mail_sweep_interval = config.mail.sweep_interval();
+        let mail = Arc::new(MailService::new(pool.clone(), registry.clone(), inventory.clone(), events.clone(), config.mail));
+        let parties = Arc::new(PartyService::new(
+            pool.clone(), registry.clone(), chat.clone(), inventory.clone(), mail.clone(), config.parties,
+        ));
+
+        let combat_rules = Arc::new(get_combat_rules().context("Failed to load combat rules")?);
+        tracing::info!("Loaded {} creatures", combat_rules.creatures().count());
+        let combat = Arc::new(CombatService::new(
+            pool.clone(), registry.clone(), world.clone(), combat_rules.clone(), events.clone(), parties.clone(), config.combat,
+        ));
+
+        let loot_book = get_loot_book().context("Failed to load loot tables")?;
+        let problems = loot_book.check_references(inventory.catalog());
+        if !problems.is_empty() {
+            anyhow::bail!("Loot tables reference missing data: {}", problems.join("; "));
+        }
+        tracing::info!("Loaded {} loot tables", loot_book.len());
+        let loot = Arc::new(LootService::new(pool.clone(), inventory.clone(), Arc::new(loot_book)));
+
+        let npc_book = get_npc_book().context("Failed to load npcs")?;
+        let problems = npc_book.check_references(inventory.catalog(), world.map(), &combat_rules, loot.book());
+        if !problems.is_empty() {
+            anyhow::bail!("Npcs reference missing data: {}", problems.join("; "));
+        }
+        tracing::info!("Loaded {} npcs", npc_book.len());
+        let npcs = Arc::new(NpcService::new(
+            pool.clone(), registry.clone(), world.clone(), combat.clone(), parties.clone(), loot, Arc::new(npc_book), config.npcs,
+        ));
+
+        let quest_book = get_quest_book().context("Failed to load quests")?;
+        let problems = quest_book.check_references(inventory.catalog(), world.map(), &combat_rules);
+        if !problems.is_empty() {
+            anyhow::bail!("Quests reference missing data: {}", problems.join("; "));
+        }
+        tracing::info!("Loaded {} quests", quest_book.len());
+        let quests = Arc::new(QuestService::new(
+            pool.clone(), registry.clone(), world.clone(), inventory.clone(), combat_rules, Arc::new(quest_book), events.clone(),
+        ));
+
+        let redis = redis::Client::open(config.app.redis_uri.expose_secret().as_str())
+            .context("Invalid Redis URI")?;
+        let redis = redis::aio::ConnectionManager::new(redis)
+            .await
+            .context("Failed to connect to Redis")?;
+        let leaderboard_refresh_interval = config.leaderboards.refresh_interval();
+        let leaderboards = Arc::new(LeaderboardService::new(
+            pool.clone(), LeaderboardCache::new(redis.clone(), config.leaderboards.key_prefix.clone()), config.leaderboards,
+        ));
+        let guilds = Arc::new(GuildService::new(
+            pool.clone(), registry.clone(), chat.clone(), inventory.clone(), config.guilds,
+        ));
+        let presence = PresenceStore::new(redis, config.presence.key_prefix.clone(), config.presence.ttl());
+        let friends = Arc::new(FriendService::new(
+            pool.clone(), registry.clone(), chat.clone(), presence, config.friends, &config.presence,
+        ));
+        let auction_sweep_interval = config.auctions.sweep_interval();
+        let auctions = Arc::new(AuctionService::new(
+            pool.clone(), registry.clone(), inventory.clone(), mail.clone(), config.auctions,
+        ));
 
+        let recipe_book = get_recipe_book().context("Failed to load recipes")?;
+        let problems = recipe_book.check_references(inventory.catalog(), world.map());
+        if !problems.is_empty() {
+            anyhow::bail!("Recipes reference missing data: {}", problems.join("; "));
+        }
+        tracing::info!("Loaded {} recipes", recipe_book.len());
+        let crafting_sweep_interval = config.crafting.sweep_interval();
+        let crafting = Arc::new(CraftingService::new(
+            pool.clone(), registry.clone(), inventory.clone(), mail.clone(), world.clone(), events.clone(),
+            Arc::new(recipe_book), config.crafting,
+        ));
+
+        let achievement_book = get_achievement_book().context("Failed to load achievements")?;
+        let problems = achievement_book.check_references(inventory.catalog(), combat.rules(), quests.book(), crafting.book());
+        if !problems.is_empty() {
+            anyhow::bail!("Achievements reference missing data: {}", problems.join("; "));
+        }
+        tracing::info!("Loaded {} achievements", achievement_book.len());
+        let achievements = Arc::new(AchievementService::new(
+            pool.clone(), registry.clone(), mail.clone(), Arc::new(achievement_book), config.achievements,
+        ));
+
+        let matchmaker_interval = config.matchmaking.tick_interval();
+        let matchmaking = Arc::new(MatchmakingService::new(
+            pool.clone(), registry.clone(), combat.clone(), config.matchmaking,
+        ));
+        let matchmaker = Matchmaker::new(matchmaking.clone(), matchmaker_interval);
+
+        let mut game_loop = GameLoop::new(&config.game, chrono::Utc::now());
+        game_loop.add_system(PositionFlushSystem::new(world.clone(), position_flush_interval));
+        game_loop.add_system(CombatTurnTimeoutSystem::new(combat.clone()));
+        game_loop.add_system(QuestProgressSystem::new(quests.clone(), events.subscribe()));
+        game_loop.add_system(LeaderboardRefreshSystem::new(leaderboards.clone(), leaderboard_refresh_interval));
+        game_loop.add_system(MailExpirySystem::new(mail.clone(), mail_sweep_interval));
+        game_loop.add_system(AuctionExpirySystem::new(auctions.clone(), auction_sweep_interval));
+        game_loop.add_system(CraftingQueueSystem::new(crafting.clone(), crafting_sweep_interval));
+        game_loop.add_system(AchievementSystem::new(achievements.clone(), events.subscribe()));
+        game_loop.add_system(PartySystem::new(parties.clone()));
+        game_loop.add_system(NpcSystem::new(npcs.clone()));
+
+        let services = AppServices {
+            pool,
+            store,
+            template_engine: tera,
+            email_client,
+            registration_settings: config.registration,
+            character_settings: config.characters,
+            gateway_settings: config.gateway,
+            inventory,
+            registry: registry.clone(),
+            chat: chat.clone(),
+            world: world.clone(),
+            combat: combat.clone(),
+            quests: quests.clone(),
+            leaderboards: leaderboards.clone(),
+            guilds: guilds.clone(),
+            friends: friends.clone(),
+            mail: mail.clone(),
+            auctions: auctions.clone(),
+            crafting: crafting.clone(),
+            achievements: achievements.clone(),
+            matchmaking: matchmaking.clone(),
+            parties: parties.clone(),
+            npcs: npcs.clone(),
+        };
         let server = run(
             config.app.base_url,
             tcp_listener,
-            config.db.get_connection_pool(),
             config.app.redis_uri,
             config.app.cookie_secret,
-            tera,
-            email_client,
+            services,
         ).await?;
 
-        Ok(Self { port: local_port, server })
+        Ok(Self { port: local_port, server, registry, chat, world, combat, quests, leaderboards, guilds, friends, mail, auctions, crafting, achievements, matchmaking, parties, npcs, events, game_loop: Some(game_loop), matchmaker: Some(matchmaker) })
     }
 
     pub fn port(&self) -> u16 {
         self.port
     }
 
+    /// For systems outside of the HTTP server that push events to connected players
+    pub fn registry(&self) -> ConnectionRegistry {
+        self.registry.clone()
+    }
+
+    /// Zones and parties tell chat which channels their players hear
+    pub fn chat(&self) -> Arc<ChatService> {
+        self.chat.clone()
+    }
+
+    pub fn world(&self) -> Arc<WorldService> {
+        self.world.clone()
+    }
+
+    pub fn combat(&self) -> Arc<CombatService> {
+        self.combat.clone()
+    }
+
+    pub fn quests(&self) -> Arc<QuestService> {
+        self.quests.clone()
+    }
+
+    pub fn leaderboards(&self) -> Arc<LeaderboardService> {
+        self.leaderboards.clone()
+    }
+
+    pub fn guilds(&self) -> Arc<GuildService> {
+        self.guilds.clone()
+    }
+
+    pub fn friends(&self) -> Arc<FriendService> {
+        self.friends.clone()
+    }
+
+    pub fn mail(&self) -> Arc<MailService> {
+        self.mail.clone()
+    }
+
+    pub fn auctions(&self) -> Arc<AuctionService> {
+        self.auctions.clone()
+    }
+
+    pub fn crafting(&self) -> Arc<CraftingService> {
+        self.crafting.clone()
+    }
+
+    pub fn achievements(&self) -> Arc<AchievementService> {
+        self.achievements.clone()
+    }
+
+    pub fn matchmaking(&self) -> Arc<MatchmakingService> {
+        self.matchmaking.clone()
+    }
+
+    pub fn parties(&self) -> Arc<PartyService> {
+        self.parties.clone()
+    }
+
+    pub fn npcs(&self) -> Arc<NpcService> {
+        self.npcs.clone()
+    }
+
+    pub fn events(&self) -> GameEvents {
+        self.events.clone()
+    }
+
+    /// The game loop runs as its own task, tests keep it to step through ticks by hand
+    pub fn take_game_loop(&mut self) -> GameLoop {
+        self.game_loop.take().expect("The game loop was already taken")
+    }
+
+    /// Like the game loop, tests keep the matchmaker and tick matchmaking with a clock of their own
+    pub fn take_matchmaker(&mut self) -> Matchmaker {
+        self.matchmaker.take().expect("The matchmaker was already taken")
+    }
+
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
         self.server.await
     }
@@ -63,19 +319,101 @@ impl Application {
 
 pub struct ApplicationBaseUrl(pub String);
 
+/// Everything the handlers are given as app data, built once by `Application::build`
+pub struct AppServices {
+    pub pool: PgPool,
+    pub store: Arc<dyn Store>,
+    pub template_engine: Tera,
+    pub email_client: EmailClient,
+    pub registration_settings: RegistrationSettings,
+    pub character_settings: CharacterSettings,
+    pub gateway_settings: GatewaySettings,
+    pub inventory: Arc<InventoryService>,
+    pub registry: ConnectionRegistry,
+    pub chat: Arc<ChatService>,
+    pub world: Arc<WorldService>,
+    pub combat: Arc<CombatService>,
+    pub quests: Arc<QuestService>,
+    pub leaderboards: Arc<LeaderboardService>,
+    pub guilds: Arc<GuildService>,
+    pub friends: Arc<FriendService>,
+    pub mail: Arc<MailService>,
+    pub auctions: Arc<AuctionService>,
+    pub crafting: Arc<CraftingService>,
+    pub achievements: Arc<AchievementService>,
+    pub matchmaking: Arc<MatchmakingService>,
+    pub parties: Arc<PartyService>,
+    pub npcs: Arc<NpcService>,
+}
+
 pub async fn run(
     base_url: String,
     listener: TcpListener,
-    pool: PgPool,
     redis_uri: Secret<String>,
     cookie_secret: Secret<String>,
-    template_engine: Tera,
-    email_client: EmailClient,
+    services: AppServices,
 ) -> Result<Server, anyhow::Error> {
+    let AppServices {
+        pool,
+        store,
+        template_engine,
+        email_client,
+        registration_settings,
+        character_settings,
+        gateway_settings,
+        inventory,
+        registry,
+        chat,
+        world,
+        combat,
+        quests,
+        leaderboards,
+        guilds,
+        friends,
+        mail,
+        auctions,
+        crafting,
+        achievements,
+        matchmaking,
+        parties,
+        npcs,
+    } = services;
+    let gateway = Data::new(GatewayServices {
+        registry: registry.clone(),
+        chat: chat.clone(),
+        world: world.clone(),
+        combat: combat.clone(),
+        quests: quests.clone(),
+        guilds: guilds.clone(),
+        friends: friends.clone(),
+        matchmaking: matchmaking.clone(),
+        parties: parties.clone(),
+        npcs: npcs.clone(),
+        settings: gateway_settings,
+    });
     let base_url = Data::new(ApplicationBaseUrl(base_url));
-    let connection_pool = Data::new(pool);
+    let pool = Data::new(pool);
+    let store: Data<dyn Store> = Data::from(store);
     let template = Data::new(template_engine);
     let email_client = Data::new(email_client);
+    let registration_settings = Data::new(registration_settings);
+    let character_settings = Data::new(character_settings);
+    let inventory: Data<InventoryService> = Data::from(inventory);
+    let registry = Data::new(registry);
+    let chat: Data<ChatService> = Data::from(chat);
+    let world: Data<WorldService> = Data::from(world);
+    let combat: Data<CombatService> = Data::from(combat);
+    let quests: Data<QuestService> = Data::from(quests);
+    let leaderboards: Data<LeaderboardService> = Data::from(leaderboards);
+    let guilds: Data<GuildService> = Data::from(guilds);
+    let friends: Data<FriendService> = Data::from(friends);
+    let mail: Data<MailService> = Data::from(mail);
+    let auctions: Data<AuctionService> = Data::from(auctions);
+    let crafting: Data<CraftingService> = Data::from(crafting);
+    let achievements: Data<AchievementService> = Data::from(achievements);
+    let matchmaking: Data<MatchmakingService> = Data::from(matchmaking);
+    let parties: Data<PartyService> = Data::from(parties);
+    let npcs: Data<NpcService> = Data::from(npcs);
     let redis_store = RedisSessionStore::new(redis_uri.expose_secret())
         .await?;
     let secret_key = Key::from(cookie_secret.expose_secret().as_bytes());
@@ -104,16 +442,112 @@ pub async fn run(
             .route("/login", web::post().to(post_login))
             .route("/register", web::get().to(get_register_form))
             .route("/register", web::post().to(post_register))
+            .route("/players/{name}", web::get().to(get_player))
+            .route("/leaderboards", web::get().to(get_leaderboards))
+            .route("/leaderboards/seasons/{number}", web::get().to(get_leaderboard_season))
+            .route("/leaderboards/{board}", web::get().to(get_leaderboard))
             .service(
                 // Logged in routes
                 web::scope("")
                     .wrap(from_fn(reject_anonymous_users))
                     .route("/account", web::get().to(get_account_home))
+                    .route("/account/profile", web::get().to(get_profile_form))
+                    .route("/account/profile", web::post().to(post_profile))
+                    .route("/characters", web::get().to(get_characters))
+                    .route("/characters/new", web::get().to(get_new_character_form))
+                    .route("/characters/new", web::post().to(post_new_character))
+                    .route("/characters/new/attributes", web::get().to(get_character_attributes_form))
+                    .route("/characters/new/attributes", web::post().to(post_character_attributes))
+                    .route("/characters/{id}/select", web::post().to(post_select_character))
+                    .route("/characters/{id}/delete", web::post().to(post_delete_character))
+                    .route("/characters/{id}/restore", web::post().to(post_restore_character))
+                    .route("/characters/{id}/title", web::post().to(post_character_title))
+                    .route("/inventory", web::get().to(get_inventory))
+                    .route("/inventory/move", web::post().to(post_move_stack))
+                    .route("/inventory/split", web::post().to(post_split_stack))
+                    .route("/admin/ledger", web::get().to(get_ledger_report))
+                    .route("/admin/audit", web::get().to(get_audit_log))
+                    .route("/admin/chat", web::get().to(get_chat_moderation))
+                    .route("/admin/chat/mute", web::post().to(post_mute_player))
+                    .route("/admin/chat/unmute", web::post().to(post_unmute_player))
+                    .route("/admin/leaderboards", web::get().to(get_leaderboard_admin))
+                    .route("/admin/leaderboards/season", web::post().to(post_end_season))
+                    .route("/admin/mail", web::get().to(get_mail_admin))
+                    .route("/admin/mail", web::post().to(post_system_mail))
+                    .route("/combat", web::get().to(get_combat_history))
+                    .route("/combat/{id}", web::get().to(get_combat_log))
+                    .route("/quests", web::get().to(get_quests))
+                    .route("/quests/{id}/accept", web::post().to(post_accept_quest))
+                    .route("/quests/{id}/abandon", web::post().to(post_abandon_quest))
+                    .route("/quests/{id}/complete", web::post().to(post_complete_quest))
+                    .route("/guilds", web::get().to(get_guild_list))
+                    .route("/guilds", web::post().to(post_create_guild))
+                    .route("/guilds/{id}/apply", web::post().to(post_apply_to_guild))
+                    .route("/guilds/{id}/accept", web::post().to(post_accept_invitation))
+                    .route("/guilds/{id}/decline", web::post().to(post_decline_invitation))
+                    .route("/guild", web::get().to(get_own_guild))
+                    .route("/guild/invite", web::post().to(post_guild_invite))
+                    .route("/guild/applications/{user_id}/accept", web::post().to(post_accept_application))
+                    .route("/guild/applications/{user_id}/reject", web::post().to(post_reject_application))
+                    .route("/guild/members/{user_id}/kick", web::post().to(post_kick_member))
+                    .route("/guild/members/{user_id}/rank", web::post().to(post_member_rank))
+                    .route("/guild/ranks/{rank}", web::post().to(post_rank_permissions))
+                    .route("/guild/motd", web::post().to(post_guild_motd))
+                    .route("/guild/leave", web::post().to(post_leave_guild))
+                    .route("/guild/bank/deposit", web::post().to(post_bank_deposit))
+                    .route("/guild/bank/withdraw", web::post().to(post_bank_withdraw))
+                    .route("/friends", web::get().to(get_friends))
+                    .route("/friends", web::post().to(post_friend_request))
+                    .route("/friends/accept", web::post().to(post_accept_friend))
+                    .route("/friends/decline", web::post().to(post_decline_friend))
+                    .route("/friends/cancel", web::post().to(post_cancel_friend_request))
+                    .route("/friends/remove", web::post().to(post_remove_friend))
+                    .route("/friends/block", web::post().to(post_block_player))
+                    .route("/friends/unblock", web::post().to(post_unblock_player))
+                    .route("/mail", web::get().to(get_mailbox))
+                    .route("/mail", web::post().to(post_send_mail))
+                    .route("/mail/{id}", web::get().to(get_mail))
+                    .route("/mail/{id}/claim", web::post().to(post_claim_mail))
+                    .route("/mail/{id}/return", web::post().to(post_return_mail))
+                    .route("/mail/{id}/delete", web::post().to(post_delete_mail))
+                    .route("/auctions", web::get().to(get_auctions))
+                    .route("/auctions", web::post().to(post_create_listing))
+                    .route("/auctions/{id}/bid", web::post().to(post_bid))
+                    .route("/auctions/{id}/buy", web::post().to(post_buyout))
+                    .route("/auctions/{id}/cancel", web::post().to(post_cancel_listing))
+                    .route("/crafting", web::get().to(get_crafting))
+                    .route("/crafting", web::post().to(post_queue_craft))
+                    .route("/crafting/{id}/cancel", web::post().to(post_cancel_craft))
+                    .route("/matchmaking", web::get().to(get_matchmaking))
+                    .route("/matchmaking", web::post().to(post_join_queue))
+                    .route("/matchmaking/leave", web::post().to(post_leave_queue))
+                    .route("/matchmaking/{id}/ready", web::post().to(post_ready_check))
+                    .route("/ws", web::get().to(get_ws))
             )
             .app_data(base_url.clone())
-            .app_data(connection_pool.clone())
+            .app_data(pool.clone())
+            .app_data(store.clone())
             .app_data(template.clone())
             .app_data(email_client.clone())
+            .app_data(registration_settings.clone())
+            .app_data(character_settings.clone())
+            .app_data(inventory.clone())
+            .app_data(registry.clone())
+            .app_data(chat.clone())
+            .app_data(world.clone())
+            .app_data(combat.clone())
+            .app_data(quests.clone())
+            .app_data(leaderboards.clone())
+            .app_data(guilds.clone())
+            .app_data(friends.clone())
+            .app_data(mail.clone())
+            .app_data(auctions.clone())
+            .app_data(crafting.clone())
+            .app_data(achievements.clone())
+            .app_data(matchmaking.clone())
+            .app_data(parties.clone())
+            .app_data(npcs.clone())
+            .app_data(gateway.clone())
     })
         .listen(listener)?
         .run();
@@ -1,50 +1,115 @@
-use anyhow::Context;
-use sqlx::PgPool;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+use sqlx::postgres::PgExecutor;
 use uuid::Uuid;
-use crate::domain::AccountEmail;
+use crate::domain::{Account, AccountEmail, AccountRole, AccountStatus};
+
+struct AccountRow {
+    user_id: Uuid,
+    email: String,
+    status: String,
+    roles: Vec<String>,
+    created_at: DateTime<Utc>,
+    last_login_at: Option<DateTime<Utc>>,
+    display_name: Option<String>,
+}
+
+impl TryFrom<AccountRow> for Account {
+    type Error = anyhow::Error;
+
+    fn try_from(row: AccountRow) -> Result<Self, Self::Error> {
+        let roles = row.roles
+            .into_iter()
+            .map(AccountRole::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow!(e))?;
+
+        Ok(Account {
+            id: row.user_id,
+            email: AccountEmail::parse(row.email).map_err(|e| anyhow!(e))?,
+            status: AccountStatus::try_from(row.status).map_err(|e| anyhow!(e))?,
+            roles,
+            created_at: row.created_at,
+            last_login_at: row.last_login_at,
+            display_name: row.display_name,
+        })
+    }
+}
 
 #[tracing::instrument(
 name = "Get account by email",
-skip(pool)
+skip(executor)
 )]
 pub async fn get_account_by_email(
-    pool: &PgPool,
+    executor: impl PgExecutor<'_>,
     email: &AccountEmail,
-) -> Result<Option<Uuid>, anyhow::Error> {
-    let row = sqlx::query!(
+) -> Result<Option<Account>, anyhow::Error> {
+    sqlx::query_as!(
+        AccountRow,
         r#"
-        SELECT user_id, email, password_hash, created_at
-        FROM accounts
-        WHERE email = $1
+        SELECT a.user_id, a.email, a.status, a.roles, a.created_at, a.last_login_at,
+               p.display_name as "display_name?"
+        FROM accounts a
+        LEFT JOIN profiles p ON p.user_id = a.user_id
+        WHERE lower(a.email) = lower($1)
         "#,
         email.as_ref()
     )
-        .fetch_optional(pool)
+        .fetch_optional(executor)
         .await
         .context("Failed to execute account fetch query")?
-        .map(|r| r.user_id);
-    Ok(row)
+        .map(Account::try_from)
+        .transpose()
+        .context("Failed to parse stored account")
 }
 
 #[tracing::instrument(
 name = "Get account by user id",
-skip(pool)
+skip(executor)
 )]
 pub async fn get_account_by_user_id(
-    pool: &PgPool,
+    executor: impl PgExecutor<'_>,
     user_id: Uuid,
-) -> Result<Option<Uuid>, anyhow::Error> {
-    let row = sqlx::query!(
+) -> Result<Option<Account>, anyhow::Error> {
+    sqlx::query_as!(
+        AccountRow,
         r#"
-        SELECT user_id, email, password_hash, created_at
-        FROM accounts
-        WHERE user_id = $1
+        SELECT a.user_id, a.email, a.status, a.roles, a.created_at, a.last_login_at,
+               p.display_name as "display_name?"
+        FROM accounts a
+        LEFT JOIN profiles p ON p.user_id = a.user_id
+        WHERE a.user_id = $1
         "#,
         user_id
     )
-        .fetch_optional(pool)
+        .fetch_optional(executor)
         .await
         .context("Failed to execute account fetch query")?
-        .map(|r| r.user_id);
+        .map(Account::try_from)
+        .transpose()
+        .context("Failed to parse stored account")
+}
+
+#[tracing::instrument(
+name = "get stored credentials",
+skip(executor, email)
+)]
+pub async fn get_stored_credentials(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+) -> Result<Option<(Uuid, Secret<String>)>, anyhow::Error> {
+    let row = sqlx::query!(
+       r#"
+       SELECT user_id, password_hash
+       FROM accounts
+       WHERE lower(email) = lower($1)
+       "#,
+       email
+   ).fetch_optional(executor)
+        .await
+        .context("Failed to perform query to get stored credentials")?
+        .map(|r| (r.user_id, Secret::new(r.password_hash)));
+
     Ok(row)
-}
\ No newline at end of file
+}
@@ -0,0 +1,722 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+use uuid::Uuid;
+use crate::characters::Character;
+use crate::domain::{Account, AccountEmail, Profile, ProfileDetails};
+use crate::store::repository::{AccountRepository, CharacterRepository, EmailQueueRepository, ProfileRepository, Store, StoreError, StoreTransaction, TokenRepository};
+
+#[derive(Clone)]
+struct StoredAccount {
+    account: Account,
+    password_hash: Secret<String>,
+}
+
+#[derive(Clone)]
+struct StoredToken {
+    token: String,
+}
+
+#[derive(Clone)]
+struct StoredEmailJob {
+    id: Uuid,
+    email: String,
+}
+
+/// Rows of the in-memory "tables". Used both for the committed state and for the writes
+/// staged by a transaction that has not been committed yet.
+#[derive(Default)]
+struct MemoryState {
+    accounts: Vec<StoredAccount>,
+    logins: Vec<(Uuid, DateTime<Utc>)>,
+    profiles: Vec<Profile>,
+    characters: Vec<Character>,
+    tokens: Vec<StoredToken>,
+    email_queue: Vec<StoredEmailJob>,
+}
+
+impl MemoryState {
+    fn account_by_email(&self, email: &str) -> Option<&StoredAccount> {
+        let email = email.to_lowercase();
+        self.accounts.iter().find(|a| a.account.email.as_ref().to_lowercase() == email)
+    }
+
+    fn account_by_user_id(&self, user_id: Uuid) -> Option<&StoredAccount> {
+        self.accounts.iter().find(|a| a.account.id == user_id)
+    }
+
+    fn apply_login(&mut self, user_id: Uuid, at: DateTime<Utc>) {
+        if let Some(stored) = self.accounts.iter_mut().find(|a| a.account.id == user_id) {
+            stored.account.last_login_at = Some(at);
+        }
+    }
+
+    fn profile_by_user_id(&self, user_id: Uuid) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.user_id == user_id)
+    }
+
+    fn profile_by_display_name(&self, display_name: &str) -> Option<&Profile> {
+        let display_name = display_name.to_lowercase();
+        self.profiles.iter().find(|p| p.display_name.as_ref().to_lowercase() == display_name)
+    }
+
+    fn upsert_profile(&mut self, mut profile: Profile) {
+        match self.profiles.iter_mut().find(|p| p.user_id == profile.user_id) {
+            Some(existing) => {
+                profile.created_at = existing.created_at;
+                *existing = profile;
+            }
+            None => self.profiles.push(profile),
+        }
+    }
+
+    fn character_by_id(&self, character_id: Uuid) -> Option<&Character> {
+        self.characters.iter().find(|c| c.id == character_id)
+    }
+
+    fn upsert_character(&mut self, character: Character) {
+        match self.characters.iter_mut().find(|c| c.id == character.id) {
+            Some(existing) => *existing = character,
+            None => self.characters.push(character),
+        }
+    }
+
+    fn has_token(&self, token: &str) -> bool {
+        self.tokens.iter().any(|t| t.token == token)
+    }
+
+    fn has_queued_email(&self, email: &str) -> bool {
+        self.email_queue.iter().any(|j| j.email == email)
+    }
+
+    // Mirrors the constraints of the database schema
+    fn check_account(&self, account: &StoredAccount) -> Result<(), StoreError> {
+        let account = &account.account;
+        if self.account_by_user_id(account.id).is_some() {
+            return Err(StoreError::Conflict(format!("Account {} already exists", account.id)));
+        }
+        if self.account_by_email(account.email.as_ref()).is_some() {
+            return Err(StoreError::Conflict(format!("Account with email {} already exists", account.email)));
+        }
+        Ok(())
+    }
+
+    fn check_profile(&self, profile: &Profile) -> Result<(), StoreError> {
+        match self.profile_by_display_name(profile.display_name.as_ref()) {
+            Some(other) if other.user_id != profile.user_id => Err(StoreError::Conflict(
+                format!("Display name {} is already taken", profile.display_name)
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_token(&self, token: &StoredToken) -> Result<(), StoreError> {
+        if self.has_token(&token.token) {
+            return Err(StoreError::Conflict("Activation token already exists".to_string()));
+        }
+        Ok(())
+    }
+
+    fn check_email_job(&self, job: &StoredEmailJob) -> Result<(), StoreError> {
+        if self.has_queued_email(&job.email) {
+            return Err(StoreError::Conflict(format!("Email job for {} already queued", job.email)));
+        }
+        Ok(())
+    }
+}
+
+// Names are unique among live characters regardless of case, like the partial index in the schema
+fn check_character_name<'a>(
+    characters: impl Iterator<Item=&'a Character>,
+    character: &Character,
+) -> Result<(), StoreError> {
+    if character.is_deleted() {
+        return Ok(());
+    }
+    let name = character.name.as_ref().to_lowercase();
+    let taken = characters
+        .filter(|c| c.id != character.id && !c.is_deleted())
+        .any(|c| c.name.as_ref().to_lowercase() == name);
+    if taken {
+        return Err(StoreError::Conflict(format!("Character name {} is already taken", character.name)));
+    }
+    Ok(())
+}
+
+/// Store that keeps everything in memory, meant for unit testing handlers without a database.
+/// Writes are staged in the transaction and only become visible to others on commit, at which
+/// point the constraints are checked again against whatever was committed in the meantime.
+#[derive(Clone, Default)]
+pub struct InMemoryStore {
+    committed: Arc<Mutex<MemoryState>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn lock(state: &Mutex<MemoryState>) -> Result<MutexGuard<'_, MemoryState>, StoreError> {
+    state.lock()
+        .map_err(|_| StoreError::UnexpectedError(anyhow!("In-memory store lock was poisoned")))
+}
+
+#[async_trait]
+impl Store for InMemoryStore {
+    async fn begin(&self) -> Result<Box<dyn StoreTransaction>, StoreError> {
+        Ok(Box::new(InMemoryTransaction {
+            committed: self.committed.clone(),
+            staged: MemoryState::default(),
+        }))
+    }
+}
+
+pub struct InMemoryTransaction {
+    committed: Arc<Mutex<MemoryState>>,
+    staged: MemoryState,
+}
+
+impl InMemoryTransaction {
+    // Accounts as a query inside this transaction would see them, including the joined profile
+    fn view_account(&self, committed: &MemoryState, mut account: Account) -> Account {
+        if let Some((_, at)) = self.staged.logins.iter().rev().find(|(id, _)| *id == account.id) {
+            account.last_login_at = Some(*at);
+        }
+        account.display_name = self.staged.profile_by_user_id(account.id)
+            .or_else(|| committed.profile_by_user_id(account.id))
+            .map(|p| p.display_name.to_string());
+        account
+    }
+
+    // Characters as this transaction sees them, its own changes win over the committed rows
+    fn view_characters(&self, committed: &MemoryState) -> Vec<Character> {
+        let mut characters: Vec<Character> = committed.characters
+            .iter()
+            .filter(|c| self.staged.character_by_id(c.id).is_none())
+            .chain(self.staged.characters.iter())
+            .cloned()
+            .collect();
+        characters.sort_by_key(|c| c.created_at);
+        characters
+    }
+
+    fn has_account(&self, committed: &MemoryState, user_id: Uuid) -> bool {
+        self.staged.account_by_user_id(user_id).is_some() || committed.account_by_user_id(user_id).is_some()
+    }
+
+    // Stages the changed character after checking it against everything this transaction sees
+    fn stage_character(&mut self, character: Character) -> Result<(), StoreError> {
+        let characters = self.view_characters(&*lock(&self.committed)?);
+        check_character_name(characters.iter(), &character)?;
+        self.staged.upsert_character(character);
+        Ok(())
+    }
+
+    fn set_deleted_at(&mut self, character_id: Uuid, deleted_at: Option<DateTime<Utc>>) -> Result<(), StoreError> {
+        let character = self.view_characters(&*lock(&self.committed)?)
+            .into_iter()
+            .find(|c| c.id == character_id);
+        match character {
+            // deleting twice keeps the first deletion time
+            Some(character) if deleted_at.is_some() && character.is_deleted() => Ok(()),
+            Some(mut character) => {
+                character.deleted_at = deleted_at;
+                self.stage_character(character)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl StoreTransaction for InMemoryTransaction {
+    async fn commit(self: Box<Self>) -> Result<(), StoreError> {
+        let mut committed = lock(&self.committed)?;
+        let staged = self.staged;
+
+        for account in &staged.accounts {
+            committed.check_account(account)?;
+        }
+        for profile in &staged.profiles {
+            committed.check_profile(profile)?;
+        }
+        // characters changed by this transaction are checked as they are now, not as committed
+        for character in &staged.characters {
+            let others = committed.characters
+                .iter()
+                .filter(|c| staged.character_by_id(c.id).is_none())
+                .chain(staged.characters.iter());
+            check_character_name(others, character)?;
+        }
+        for token in &staged.tokens {
+            committed.check_token(token)?;
+        }
+        for job in &staged.email_queue {
+            committed.check_email_job(job)?;
+        }
+
+        committed.accounts.extend(staged.accounts);
+        for (user_id, at) in staged.logins {
+            committed.apply_login(user_id, at);
+        }
+        for profile in staged.profiles {
+            committed.upsert_profile(profile);
+        }
+        for character in staged.characters {
+            committed.upsert_character(character);
+        }
+        committed.tokens.extend(staged.tokens);
+        committed.email_queue.extend(staged.email_queue);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AccountRepository for InMemoryTransaction {
+    async fn get_account_by_email(
+        &mut self,
+        email: &AccountEmail,
+    ) -> Result<Option<Account>, StoreError> {
+        let committed = lock(&self.committed)?;
+        let account = self.staged.account_by_email(email.as_ref())
+            .or_else(|| committed.account_by_email(email.as_ref()));
+        Ok(account.map(|a| self.view_account(&committed, a.account.clone())))
+    }
+
+    async fn get_account_by_user_id(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Option<Account>, StoreError> {
+        let committed = lock(&self.committed)?;
+        let account = self.staged.account_by_user_id(user_id)
+            .or_else(|| committed.account_by_user_id(user_id));
+        Ok(account.map(|a| self.view_account(&committed, a.account.clone())))
+    }
+
+    async fn get_stored_credentials(
+        &mut self,
+        email: &str,
+    ) -> Result<Option<(Uuid, Secret<String>)>, StoreError> {
+        let committed = lock(&self.committed)?;
+        let account = self.staged.account_by_email(email)
+            .or_else(|| committed.account_by_email(email));
+        Ok(account.map(|a| (a.account.id, a.password_hash.clone())))
+    }
+
+    async fn store_user_account(
+        &mut self,
+        user_id: Uuid,
+        email: &AccountEmail,
+        hash: Secret<String>,
+    ) -> Result<Uuid, StoreError> {
+        let account = StoredAccount {
+            account: Account::new(user_id, email.clone()),
+            password_hash: hash,
+        };
+        lock(&self.committed)?.check_account(&account)?;
+        self.staged.check_account(&account)?;
+        self.staged.accounts.push(account);
+        Ok(user_id)
+    }
+
+    async fn record_login(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Option<DateTime<Utc>>, StoreError> {
+        let previous = self.get_account_by_user_id(user_id).await?.and_then(|a| a.last_login_at);
+        self.staged.logins.push((user_id, Utc::now()));
+        Ok(previous)
+    }
+}
+
+#[async_trait]
+impl ProfileRepository for InMemoryTransaction {
+    async fn get_profile_by_user_id(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Option<Profile>, StoreError> {
+        let committed = lock(&self.committed)?;
+        Ok(
+            self.staged.profile_by_user_id(user_id)
+                .or_else(|| committed.profile_by_user_id(user_id))
+                .cloned()
+        )
+    }
+
+    async fn get_profile_by_display_name(
+        &mut self,
+        display_name: &str,
+    ) -> Result<Option<Profile>, StoreError> {
+        let committed = lock(&self.committed)?;
+        let staged = self.staged.profile_by_display_name(display_name);
+        // a committed profile that was renamed in this transaction should no longer be found
+        let committed = committed.profile_by_display_name(display_name)
+            .filter(|p| self.staged.profile_by_user_id(p.user_id).is_none());
+        Ok(staged.or(committed).cloned())
+    }
+
+    async fn store_profile(
+        &mut self,
+        user_id: Uuid,
+        details: &ProfileDetails,
+    ) -> Result<(), StoreError> {
+        let now = Utc::now();
+        let profile = Profile {
+            user_id,
+            display_name: details.display_name.clone(),
+            avatar: details.avatar,
+            bio: details.bio.clone(),
+            locale: details.locale.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+
+        {
+            let committed = lock(&self.committed)?;
+            if self.staged.account_by_user_id(user_id).is_none()
+                && committed.account_by_user_id(user_id).is_none() {
+                return Err(StoreError::UnexpectedError(anyhow!("Account {} does not exist", user_id)));
+            }
+            committed.check_profile(&profile)?;
+        }
+        self.staged.check_profile(&profile)?;
+        self.staged.upsert_profile(profile);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CharacterRepository for InMemoryTransaction {
+    // Nothing to lock, names are checked again on commit but the character limit is not
+    async fn lock_account(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<(), StoreError> {
+        if !self.has_account(&*lock(&self.committed)?, user_id) {
+            return Err(StoreError::UnexpectedError(anyhow!("Account {} does not exist", user_id)));
+        }
+        Ok(())
+    }
+
+    async fn count_live_characters(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<i64, StoreError> {
+        let characters = self.view_characters(&*lock(&self.committed)?);
+        Ok(characters.iter().filter(|c| c.user_id == user_id && !c.is_deleted()).count() as i64)
+    }
+
+    async fn get_characters_by_user_id(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Vec<Character>, StoreError> {
+        let characters = self.view_characters(&*lock(&self.committed)?);
+        Ok(characters.into_iter().filter(|c| c.user_id == user_id).collect())
+    }
+
+    async fn get_character(
+        &mut self,
+        user_id: Uuid,
+        character_id: Uuid,
+    ) -> Result<Option<Character>, StoreError> {
+        let characters = self.view_characters(&*lock(&self.committed)?);
+        Ok(characters.into_iter().find(|c| c.id == character_id && c.user_id == user_id))
+    }
+
+    async fn store_character(
+        &mut self,
+        character: &Character,
+    ) -> Result<(), StoreError> {
+        {
+            let committed = lock(&self.committed)?;
+            if !self.has_account(&committed, character.user_id) {
+                return Err(StoreError::UnexpectedError(anyhow!("Account {} does not exist", character.user_id)));
+            }
+            if self.view_characters(&committed).iter().any(|c| c.id == character.id) {
+                return Err(StoreError::Conflict(format!("Character {} already exists", character.id)));
+            }
+        }
+        self.stage_character(character.clone())
+    }
+
+    async fn soft_delete_character(
+        &mut self,
+        character_id: Uuid,
+    ) -> Result<(), StoreError> {
+        self.set_deleted_at(character_id, Some(Utc::now()))
+    }
+
+    async fn undelete_character(
+        &mut self,
+        character_id: Uuid,
+    ) -> Result<(), StoreError> {
+        self.set_deleted_at(character_id, None)
+    }
+}
+
+#[async_trait]
+impl TokenRepository for InMemoryTransaction {
+    async fn store_user_activation_token(
+        &mut self,
+        user_id: Uuid,
+        token: &str,
+    ) -> Result<Uuid, StoreError> {
+        {
+            let committed = lock(&self.committed)?;
+            if self.staged.account_by_user_id(user_id).is_none()
+                && committed.account_by_user_id(user_id).is_none() {
+                return Err(StoreError::UnexpectedError(anyhow!("Account {} does not exist", user_id)));
+            }
+        }
+
+        let token = StoredToken {
+            token: token.to_string(),
+        };
+        lock(&self.committed)?.check_token(&token)?;
+        self.staged.check_token(&token)?;
+        self.staged.tokens.push(token);
+        Ok(user_id)
+    }
+}
+
+#[async_trait]
+impl EmailQueueRepository for InMemoryTransaction {
+    async fn store_user_activation_email_job(
+        &mut self,
+        recipient: &AccountEmail,
+        _content: &str,
+    ) -> Result<Uuid, StoreError> {
+        let job = StoredEmailJob {
+            id: Uuid::new_v4(),
+            email: recipient.as_ref().to_string(),
+        };
+        lock(&self.committed)?.check_email_job(&job)?;
+        self.staged.check_email_job(&job)?;
+        let id = job.id;
+        self.staged.email_queue.push(job);
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use claim::{assert_err, assert_none, assert_ok, assert_some};
+    use fake::Fake;
+    use fake::faker::internet::en::SafeEmail;
+    use secrecy::Secret;
+    use uuid::Uuid;
+    use crate::characters::{Character, CharacterClass, CharacterName};
+    use crate::domain::{AccountEmail, Avatar, DisplayName, Locale, ProfileBio, ProfileDetails};
+    use crate::store::{InMemoryStore, Store, StoreError};
+
+    fn email() -> AccountEmail {
+        AccountEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn hash() -> Secret<String> {
+        Secret::new("hash".to_string())
+    }
+
+    #[tokio::test]
+    async fn committed_writes_are_visible_to_new_transactions() {
+        let store = InMemoryStore::new();
+        let email = email();
+        let user_id = Uuid::new_v4();
+
+        let mut tx = store.begin().await.unwrap();
+        tx.store_user_account(user_id, &email, hash()).await.unwrap();
+        tx.store_user_activation_token(user_id, "token").await.unwrap();
+        tx.commit().await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        assert_eq!(Some(user_id), tx.get_account_by_email(&email).await.unwrap().map(|a| a.id));
+        assert_some!(tx.get_stored_credentials(email.as_ref()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn uncommitted_writes_are_discarded() {
+        let store = InMemoryStore::new();
+        let email = email();
+
+        let mut tx = store.begin().await.unwrap();
+        tx.store_user_account(Uuid::new_v4(), &email, hash()).await.unwrap();
+        assert_some!(tx.get_account_by_email(&email).await.unwrap());
+        drop(tx);
+
+        let mut tx = store.begin().await.unwrap();
+        assert_none!(tx.get_account_by_email(&email).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn uncommitted_writes_are_not_visible_to_other_transactions() {
+        let store = InMemoryStore::new();
+        let email = email();
+
+        let mut first = store.begin().await.unwrap();
+        let mut second = store.begin().await.unwrap();
+        first.store_user_account(Uuid::new_v4(), &email, hash()).await.unwrap();
+
+        assert_none!(second.get_account_by_email(&email).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn duplicate_email_is_a_conflict() {
+        let store = InMemoryStore::new();
+        let email = email();
+
+        let mut tx = store.begin().await.unwrap();
+        tx.store_user_account(Uuid::new_v4(), &email, hash()).await.unwrap();
+        let outcome = tx.store_user_account(Uuid::new_v4(), &email, hash()).await;
+
+        assert!(matches!(outcome, Err(StoreError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn concurrent_commits_of_the_same_email_only_succeed_once() {
+        let store = InMemoryStore::new();
+        let email = email();
+
+        let mut first = store.begin().await.unwrap();
+        let mut second = store.begin().await.unwrap();
+        first.store_user_account(Uuid::new_v4(), &email, hash()).await.unwrap();
+        second.store_user_account(Uuid::new_v4(), &email, hash()).await.unwrap();
+
+        assert_ok!(first.commit().await);
+        assert_err!(second.commit().await);
+    }
+
+    #[tokio::test]
+    async fn recorded_login_is_applied_on_commit() {
+        let store = InMemoryStore::new();
+        let user_id = Uuid::new_v4();
+
+        let mut tx = store.begin().await.unwrap();
+        tx.store_user_account(user_id, &email(), hash()).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        assert_none!(tx.record_login(user_id).await.unwrap());
+        tx.commit().await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        let account = tx.get_account_by_user_id(user_id).await.unwrap().unwrap();
+        let first = assert_some!(account.last_login_at);
+        assert_eq!(Some(first), tx.record_login(user_id).await.unwrap());
+    }
+
+    fn profile(name: &str) -> ProfileDetails {
+        ProfileDetails {
+            display_name: DisplayName::parse(name.to_string()).unwrap(),
+            avatar: Avatar::default(),
+            bio: ProfileBio::default(),
+            locale: Locale::default(),
+        }
+    }
+
+    async fn committed_account(store: &InMemoryStore) -> Uuid {
+        let user_id = Uuid::new_v4();
+        let mut tx = store.begin().await.unwrap();
+        tx.store_user_account(user_id, &email(), hash()).await.unwrap();
+        tx.commit().await.unwrap();
+        user_id
+    }
+
+    #[tokio::test]
+    async fn display_names_are_unique_regardless_of_case() {
+        let store = InMemoryStore::new();
+        let first = committed_account(&store).await;
+        let second = committed_account(&store).await;
+
+        let mut tx = store.begin().await.unwrap();
+        tx.store_profile(first, &profile("Bob")).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        let outcome = tx.store_profile(second, &profile("bOB")).await;
+        assert!(matches!(outcome, Err(StoreError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn profile_display_name_shows_up_on_the_account() {
+        let store = InMemoryStore::new();
+        let user_id = committed_account(&store).await;
+
+        let mut tx = store.begin().await.unwrap();
+        tx.store_profile(user_id, &profile("Bob")).await.unwrap();
+        tx.store_profile(user_id, &profile("Robert")).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        let account = tx.get_account_by_user_id(user_id).await.unwrap().unwrap();
+        assert_eq!(Some("Robert".to_string()), account.display_name);
+        assert_some!(tx.get_profile_by_display_name("robert").await.unwrap());
+        assert_none!(tx.get_profile_by_display_name("bob").await.unwrap());
+    }
+
+    fn character(user_id: Uuid, name: &str) -> Character {
+        let class = CharacterClass::Warrior;
+        Character {
+            id: Uuid::new_v4(),
+            user_id,
+            name: CharacterName::parse(name.to_string()).unwrap(),
+            class,
+            level: 1,
+            experience: 0,
+            attributes: class.base_attributes(),
+            created_at: Utc::now(),
+            deleted_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn character_names_are_unique_among_live_characters() {
+        let store = InMemoryStore::new();
+        let user_id = committed_account(&store).await;
+        let first = character(user_id, "Garrett");
+
+        let mut tx = store.begin().await.unwrap();
+        tx.store_character(&first).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        let outcome = tx.store_character(&character(user_id, "gARRETT")).await;
+        assert!(matches!(outcome, Err(StoreError::Conflict(_))));
+
+        tx.soft_delete_character(first.id).await.unwrap();
+        tx.store_character(&character(user_id, "gARRETT")).await.unwrap();
+        assert_eq!(1, tx.count_live_characters(user_id).await.unwrap());
+        tx.commit().await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        assert!(matches!(tx.undelete_character(first.id).await, Err(StoreError::Conflict(_))));
+        assert_eq!(2, tx.get_characters_by_user_id(user_id).await.unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn characters_are_only_found_for_their_owner() {
+        let store = InMemoryStore::new();
+        let owner = committed_account(&store).await;
+        let other = committed_account(&store).await;
+        let stored = character(owner, "Garrett");
+
+        let mut tx = store.begin().await.unwrap();
+        tx.store_character(&stored).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        assert_some!(tx.get_character(owner, stored.id).await.unwrap());
+        assert_none!(tx.get_character(other, stored.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn activation_token_requires_an_existing_account() {
+        let store = InMemoryStore::new();
+
+        let mut tx = store.begin().await.unwrap();
+        assert_err!(tx.store_user_activation_token(Uuid::new_v4(), "token").await);
+    }
+}
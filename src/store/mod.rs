@@ -1,5 +1,14 @@
 mod get;
+mod profile;
+#[allow(clippy::module_inception)]
 mod store;
+mod repository;
+mod postgres;
+mod memory;
 
-pub use get::{get_account_by_email, get_account_by_user_id};
-pub use store::{store_user_account, store_user_activation_token, store_user_activation_email_job};
\ No newline at end of file
+pub use get::{get_account_by_email, get_account_by_user_id, get_stored_credentials};
+pub use profile::{get_profile_by_display_name, get_profile_by_user_id, store_profile};
+pub use store::{store_account_login, store_user_account, store_user_activation_token, store_user_activation_email_job};
+pub use repository::{Store, StoreTransaction, StoreError, AccountRepository, ProfileRepository, CharacterRepository, TokenRepository, EmailQueueRepository};
+pub use postgres::{is_unique_violation, PostgresStore, PostgresTransaction};
+pub use memory::{InMemoryStore, InMemoryTransaction};
\ No newline at end of file
@@ -0,0 +1,216 @@
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::characters::{count_live_characters, get_character, get_characters_by_user_id, lock_account, soft_delete_character, store_character, undelete_character, Character};
+use crate::domain::{Account, AccountEmail, Profile, ProfileDetails};
+use crate::store::{get_profile_by_display_name, get_profile_by_user_id, store_profile, store_account_login, get_account_by_email, get_account_by_user_id, get_stored_credentials, store_user_account, store_user_activation_email_job, store_user_activation_token};
+use crate::store::repository::{AccountRepository, CharacterRepository, EmailQueueRepository, ProfileRepository, Store, StoreError, StoreTransaction, TokenRepository};
+
+const UNIQUE_VIOLATION: &str = "23505";
+
+/// Whether the statement failed on a unique index or constraint
+pub fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .and_then(|db| db.code())
+        .is_some_and(|code| code == UNIQUE_VIOLATION)
+}
+
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    pub fn new(pool: PgPool) -> Self {
+        PostgresStore { pool }
+    }
+}
+
+#[async_trait]
+impl Store for PostgresStore {
+    async fn begin(&self) -> Result<Box<dyn StoreTransaction>, StoreError> {
+        let tx = self.pool.begin()
+            .await
+            .context("Failed to get pool transaction lock")?;
+        Ok(Box::new(PostgresTransaction { tx }))
+    }
+}
+
+pub struct PostgresTransaction {
+    tx: Transaction<'static, Postgres>,
+}
+
+#[async_trait]
+impl StoreTransaction for PostgresTransaction {
+    async fn commit(self: Box<Self>) -> Result<(), StoreError> {
+        self.tx.commit()
+            .await
+            .map_err(|e| write_error(e, "Failed to commit transaction"))
+    }
+}
+
+#[async_trait]
+impl AccountRepository for PostgresTransaction {
+    async fn get_account_by_email(
+        &mut self,
+        email: &AccountEmail,
+    ) -> Result<Option<Account>, StoreError> {
+        Ok(get_account_by_email(&mut self.tx, email).await?)
+    }
+
+    async fn get_account_by_user_id(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Option<Account>, StoreError> {
+        Ok(get_account_by_user_id(&mut self.tx, user_id).await?)
+    }
+
+    async fn get_stored_credentials(
+        &mut self,
+        email: &str,
+    ) -> Result<Option<(Uuid, Secret<String>)>, StoreError> {
+        Ok(get_stored_credentials(&mut self.tx, email).await?)
+    }
+
+    async fn store_user_account(
+        &mut self,
+        user_id: Uuid,
+        email: &AccountEmail,
+        hash: Secret<String>,
+    ) -> Result<Uuid, StoreError> {
+        store_user_account(&mut self.tx, user_id, email, hash)
+            .await
+            .map_err(|e| write_error(e, "Failed to store new user account"))
+    }
+
+    async fn record_login(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Option<DateTime<Utc>>, StoreError> {
+        store_account_login(&mut self.tx, user_id)
+            .await
+            .map_err(|e| write_error(e, "Failed to record account login"))
+    }
+}
+
+#[async_trait]
+impl ProfileRepository for PostgresTransaction {
+    async fn get_profile_by_user_id(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Option<Profile>, StoreError> {
+        Ok(get_profile_by_user_id(&mut self.tx, user_id).await?)
+    }
+
+    async fn get_profile_by_display_name(
+        &mut self,
+        display_name: &str,
+    ) -> Result<Option<Profile>, StoreError> {
+        Ok(get_profile_by_display_name(&mut self.tx, display_name).await?)
+    }
+
+    async fn store_profile(
+        &mut self,
+        user_id: Uuid,
+        details: &ProfileDetails,
+    ) -> Result<(), StoreError> {
+        store_profile(&mut self.tx, user_id, details)
+            .await
+            .map_err(|e| write_error(e, "Failed to store profile"))
+    }
+}
+
+#[async_trait]
+impl CharacterRepository for PostgresTransaction {
+    async fn lock_account(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<(), StoreError> {
+        Ok(lock_account(&mut self.tx, user_id).await?)
+    }
+
+    async fn count_live_characters(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<i64, StoreError> {
+        Ok(count_live_characters(&mut self.tx, user_id).await?)
+    }
+
+    async fn get_characters_by_user_id(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Vec<Character>, StoreError> {
+        Ok(get_characters_by_user_id(&mut self.tx, user_id).await?)
+    }
+
+    async fn get_character(
+        &mut self,
+        user_id: Uuid,
+        character_id: Uuid,
+    ) -> Result<Option<Character>, StoreError> {
+        Ok(get_character(&mut self.tx, user_id, character_id).await?)
+    }
+
+    async fn store_character(
+        &mut self,
+        character: &Character,
+    ) -> Result<(), StoreError> {
+        store_character(&mut self.tx, character)
+            .await
+            .map_err(|e| write_error(e, "Failed to store character"))
+    }
+
+    async fn soft_delete_character(
+        &mut self,
+        character_id: Uuid,
+    ) -> Result<(), StoreError> {
+        Ok(soft_delete_character(&mut self.tx, character_id).await?)
+    }
+
+    async fn undelete_character(
+        &mut self,
+        character_id: Uuid,
+    ) -> Result<(), StoreError> {
+        undelete_character(&mut self.tx, character_id)
+            .await
+            .map_err(|e| write_error(e, "Failed to restore character"))
+    }
+}
+
+#[async_trait]
+impl TokenRepository for PostgresTransaction {
+    async fn store_user_activation_token(
+        &mut self,
+        user_id: Uuid,
+        token: &str,
+    ) -> Result<Uuid, StoreError> {
+        store_user_activation_token(&mut self.tx, user_id, token)
+            .await
+            .map_err(|e| write_error(e, "Failed to store account activation token"))
+    }
+}
+
+#[async_trait]
+impl EmailQueueRepository for PostgresTransaction {
+    async fn store_user_activation_email_job(
+        &mut self,
+        recipient: &AccountEmail,
+        content: &str,
+    ) -> Result<Uuid, StoreError> {
+        store_user_activation_email_job(&mut self.tx, recipient, content)
+            .await
+            .map_err(|e| write_error(e, "Failed to save activation email job"))
+    }
+}
+
+// Unique violations are surfaced as conflicts so the callers can handle them the same way
+// regardless of the backing store
+fn write_error(e: sqlx::Error, context: &'static str) -> StoreError {
+    if is_unique_violation(&e) {
+        StoreError::Conflict(format!("{}: {}", context, e))
+    } else {
+        StoreError::UnexpectedError(anyhow!(e).context(context))
+    }
+}
@@ -0,0 +1,111 @@
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::domain::{Avatar, DisplayName, Locale, Profile, ProfileBio, ProfileDetails};
+
+struct ProfileRow {
+    user_id: Uuid,
+    display_name: String,
+    avatar: String,
+    bio: String,
+    locale: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<ProfileRow> for Profile {
+    type Error = anyhow::Error;
+
+    fn try_from(row: ProfileRow) -> Result<Self, Self::Error> {
+        Ok(Profile {
+            user_id: row.user_id,
+            display_name: DisplayName::parse(row.display_name).map_err(|e| anyhow!(e))?,
+            avatar: Avatar::try_from(row.avatar).map_err(|e| anyhow!(e))?,
+            bio: ProfileBio::parse(row.bio).map_err(|e| anyhow!(e))?,
+            locale: Locale::parse(row.locale).map_err(|e| anyhow!(e))?,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+#[tracing::instrument(
+name = "Get profile by user id",
+skip(executor)
+)]
+pub async fn get_profile_by_user_id(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<Option<Profile>, anyhow::Error> {
+    sqlx::query_as!(
+        ProfileRow,
+        r#"
+        SELECT user_id, display_name, avatar, bio, locale, created_at, updated_at
+        FROM profiles
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to execute profile fetch query")?
+        .map(Profile::try_from)
+        .transpose()
+        .context("Failed to parse stored profile")
+}
+
+#[tracing::instrument(
+name = "Get profile by display name",
+skip(executor)
+)]
+pub async fn get_profile_by_display_name(
+    executor: impl PgExecutor<'_>,
+    display_name: &str,
+) -> Result<Option<Profile>, anyhow::Error> {
+    sqlx::query_as!(
+        ProfileRow,
+        r#"
+        SELECT user_id, display_name, avatar, bio, locale, created_at, updated_at
+        FROM profiles
+        WHERE lower(display_name) = lower($1)
+        "#,
+        display_name
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to execute profile fetch query")?
+        .map(Profile::try_from)
+        .transpose()
+        .context("Failed to parse stored profile")
+}
+
+#[tracing::instrument(
+name = "Store profile",
+skip(tx, details)
+)]
+pub async fn store_profile(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    details: &ProfileDetails,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO profiles (user_id, display_name, avatar, bio, locale, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, now(), now())
+        ON CONFLICT (user_id) DO UPDATE
+        SET display_name = EXCLUDED.display_name,
+            avatar       = EXCLUDED.avatar,
+            bio          = EXCLUDED.bio,
+            locale       = EXCLUDED.locale,
+            updated_at   = now()
+        "#,
+        user_id,
+        details.display_name.as_ref(),
+        details.avatar.as_str(),
+        details.bio.as_ref(),
+        details.locale.as_ref()
+    ).execute(tx).await?;
+    Ok(())
+}
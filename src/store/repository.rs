@@ -0,0 +1,150 @@
+use std::fmt::Formatter;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+use uuid::Uuid;
+use crate::characters::Character;
+use crate::domain::{Account, AccountEmail, Profile, ProfileDetails};
+use crate::utils::error_chain_fmt;
+
+#[derive(thiserror::Error)]
+pub enum StoreError {
+    #[error("{0}")]
+    Conflict(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for StoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Entry point handed to the handlers through `Data<dyn Store>`.
+/// Every read and write happens inside a transaction, dropping it without a commit discards the changes.
+#[async_trait]
+pub trait Store: Send + Sync {
+    async fn begin(&self) -> Result<Box<dyn StoreTransaction>, StoreError>;
+}
+
+#[async_trait]
+pub trait StoreTransaction: AccountRepository + ProfileRepository + CharacterRepository + TokenRepository + EmailQueueRepository {
+    async fn commit(self: Box<Self>) -> Result<(), StoreError>;
+}
+
+#[async_trait]
+pub trait AccountRepository: Send {
+    /// Emails are matched case-insensitively
+    async fn get_account_by_email(
+        &mut self,
+        email: &AccountEmail,
+    ) -> Result<Option<Account>, StoreError>;
+
+    async fn get_account_by_user_id(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Option<Account>, StoreError>;
+
+    async fn get_stored_credentials(
+        &mut self,
+        email: &str,
+    ) -> Result<Option<(Uuid, Secret<String>)>, StoreError>;
+
+    async fn store_user_account(
+        &mut self,
+        user_id: Uuid,
+        email: &AccountEmail,
+        hash: Secret<String>,
+    ) -> Result<Uuid, StoreError>;
+
+    /// Returns when the account logged in before this
+    async fn record_login(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Option<DateTime<Utc>>, StoreError>;
+}
+
+#[async_trait]
+pub trait ProfileRepository: Send {
+    async fn get_profile_by_user_id(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Option<Profile>, StoreError>;
+
+    /// Display names are matched case-insensitively
+    async fn get_profile_by_display_name(
+        &mut self,
+        display_name: &str,
+    ) -> Result<Option<Profile>, StoreError>;
+
+    /// Creates the profile on first save, a display name taken by someone else is a conflict
+    async fn store_profile(
+        &mut self,
+        user_id: Uuid,
+        details: &ProfileDetails,
+    ) -> Result<(), StoreError>;
+}
+
+#[async_trait]
+pub trait CharacterRepository: Send {
+    /// Holds changes to the account's characters until the transaction ends, so concurrent
+    /// creations are counted one after another
+    async fn lock_account(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<(), StoreError>;
+
+    async fn count_live_characters(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<i64, StoreError>;
+
+    /// Deleted characters are included so the list can offer to restore them
+    async fn get_characters_by_user_id(
+        &mut self,
+        user_id: Uuid,
+    ) -> Result<Vec<Character>, StoreError>;
+
+    /// Only finds characters owned by `user_id`, someone else's character id is as good as a missing one
+    async fn get_character(
+        &mut self,
+        user_id: Uuid,
+        character_id: Uuid,
+    ) -> Result<Option<Character>, StoreError>;
+
+    /// A name a live character already has, in any case, is a conflict
+    async fn store_character(
+        &mut self,
+        character: &Character,
+    ) -> Result<(), StoreError>;
+
+    async fn soft_delete_character(
+        &mut self,
+        character_id: Uuid,
+    ) -> Result<(), StoreError>;
+
+    /// Conflicts like `store_character` when a live character took the name in the meantime
+    async fn undelete_character(
+        &mut self,
+        character_id: Uuid,
+    ) -> Result<(), StoreError>;
+}
+
+#[async_trait]
+pub trait TokenRepository: Send {
+    async fn store_user_activation_token(
+        &mut self,
+        user_id: Uuid,
+        token: &str,
+    ) -> Result<Uuid, StoreError>;
+}
+
+#[async_trait]
+pub trait EmailQueueRepository: Send {
+    async fn store_user_activation_email_job(
+        &mut self,
+        recipient: &AccountEmail,
+        content: &str,
+    ) -> Result<Uuid, StoreError>;
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{Postgres, Transaction};
 use uuid::Uuid;
@@ -62,4 +63,30 @@ pub async fn store_user_activation_email_job(
         id, recipient.as_ref(), content
     ).execute(tx).await?;
     Ok(id)
-}
\ No newline at end of file
+}
+
+#[tracing::instrument(
+name = "Store account login",
+skip(tx)
+)]
+pub async fn store_account_login(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let previous = sqlx::query!(
+        r#"SELECT last_login_at FROM accounts WHERE user_id = $1 FOR UPDATE"#,
+        user_id
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .and_then(|r| r.last_login_at);
+    sqlx::query!(
+        r#"
+        UPDATE accounts
+        SET last_login_at = now()
+        WHERE user_id = $1
+        "#,
+        user_id
+    ).execute(tx).await?;
+    Ok(previous)
+}
@@ -15,6 +15,20 @@ pub fn e400<T>(e: T) -> actix_web::Error
     actix_web::error::ErrorBadRequest(e)
 }
 
+pub fn e403<T>(e: T) -> actix_web::Error
+    where
+        T: std::fmt::Debug + std::fmt::Display + 'static
+{
+    actix_web::error::ErrorForbidden(e)
+}
+
+pub fn e404<T>(e: T) -> actix_web::Error
+    where
+        T: std::fmt::Debug + std::fmt::Display + 'static
+{
+    actix_web::error::ErrorNotFound(e)
+}
+
 pub fn see_other(location: &str) -> HttpResponse {
     HttpResponse::SeeOther()
         .insert_header((LOCATION, location))
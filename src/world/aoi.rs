@@ -0,0 +1,59 @@
+use crate::world::Position;
+
+/// How one entity moving looks to an observer standing still
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisibilityChange {
+    Appeared,
+    Moved,
+    Left,
+    Unseen,
+}
+
+/// `None` for `old` means the entity just entered the world, `None` for `new` that it left.
+/// Distance is symmetric, so the mover sees the observer appear and leave the same way.
+pub fn visibility_change(
+    old: Option<&Position>,
+    new: Option<&Position>,
+    observer: &Position,
+    radius: i32,
+) -> VisibilityChange {
+    let was_visible = old.is_some_and(|p| p.is_within(observer, radius));
+    let is_visible = new.is_some_and(|p| p.is_within(observer, radius));
+    match (was_visible, is_visible) {
+        (false, true) => VisibilityChange::Appeared,
+        (true, true) => VisibilityChange::Moved,
+        (true, false) => VisibilityChange::Left,
+        (false, false) => VisibilityChange::Unseen,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world::{visibility_change, Position, VisibilityChange};
+
+    fn at(zone: &str, x: i32, y: i32) -> Position {
+        Position::new(zone, x, y)
+    }
+
+    #[test]
+    fn walking_into_and_out_of_range() {
+        let observer = at("greenvale", 0, 0);
+        assert_eq!(VisibilityChange::Appeared, visibility_change(Some(&at("greenvale", 4, 0)), Some(&at("greenvale", 3, 0)), &observer, 3));
+        assert_eq!(VisibilityChange::Moved, visibility_change(Some(&at("greenvale", 3, 0)), Some(&at("greenvale", 2, 1)), &observer, 3));
+        assert_eq!(VisibilityChange::Left, visibility_change(Some(&at("greenvale", 3, 0)), Some(&at("greenvale", 4, 0)), &observer, 3));
+        assert_eq!(VisibilityChange::Unseen, visibility_change(Some(&at("greenvale", 9, 0)), Some(&at("greenvale", 8, 0)), &observer, 3));
+    }
+
+    #[test]
+    fn entering_and_leaving_the_world() {
+        let observer = at("greenvale", 0, 0);
+        assert_eq!(VisibilityChange::Appeared, visibility_change(None, Some(&at("greenvale", 1, 1)), &observer, 3));
+        assert_eq!(VisibilityChange::Left, visibility_change(Some(&at("greenvale", 1, 1)), None, &observer, 3));
+    }
+
+    #[test]
+    fn changing_zones_leaves_everyone_behind() {
+        let observer = at("greenvale", 0, 0);
+        assert_eq!(VisibilityChange::Left, visibility_change(Some(&at("greenvale", 1, 0)), Some(&at("old_forest", 1, 0)), &observer, 3));
+    }
+}
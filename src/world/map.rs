@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::path::Path;
+use crate::game_data::{data_directory, load_data_files, GameDataError};
+use crate::world::{Position, Zone, ZoneDefinition};
+
+/// Every zone of the world, exits are checked to lead somewhere real
+#[derive(Debug)]
+pub struct WorldMap {
+    zones: HashMap<String, Zone>,
+}
+
+impl WorldMap {
+    pub fn new(definitions: Vec<ZoneDefinition>) -> Result<Self, GameDataError> {
+        let mut zones = HashMap::new();
+        for definition in definitions {
+            let zone = Zone::try_from(definition).map_err(GameDataError::Invalid)?;
+            if zones.contains_key(&zone.id) {
+                return Err(GameDataError::Invalid(format!("Zone {} is defined twice", zone.id)));
+            }
+            zones.insert(zone.id.clone(), zone);
+        }
+
+        for zone in zones.values() {
+            for exit in &zone.exits {
+                let target = zones.get(&exit.zone).ok_or_else(|| GameDataError::Invalid(format!(
+                    "Exit at {},{} in zone {} leads to unknown zone {}", exit.x, exit.y, zone.id, exit.zone
+                )))?;
+                if target.spawn(&exit.spawn).is_none() {
+                    return Err(GameDataError::Invalid(format!(
+                        "Exit at {},{} in zone {} leads to unknown spawn {} in zone {}",
+                        exit.x, exit.y, zone.id, exit.spawn, exit.zone
+                    )));
+                }
+            }
+        }
+        Ok(WorldMap { zones })
+    }
+
+    pub fn load(directory: &Path) -> Result<Self, GameDataError> {
+        let definitions = load_data_files::<ZoneDefinition>(directory)?
+            .into_iter()
+            .map(|(_, definition)| definition)
+            .collect();
+        Self::new(definitions)
+    }
+
+    pub fn get(&self, zone_id: &str) -> Option<&Zone> {
+        self.zones.get(zone_id)
+    }
+
+    pub fn contains(&self, zone_id: &str) -> bool {
+        self.zones.contains_key(zone_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.zones.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    /// Whether any zone has the crafting station
+    pub fn has_station(&self, station: &str) -> bool {
+        self.zones.values().any(|zone| zone.has_station(station))
+    }
+
+    pub fn spawn_position(&self, zone_id: &str, spawn_id: &str) -> Option<Position> {
+        self.get(zone_id)
+            .and_then(|zone| zone.spawn(spawn_id))
+            .map(|spawn| Position::new(zone_id, spawn.x, spawn.y))
+    }
+
+    /// Positions that no longer make sense after the data files changed
+    pub fn is_valid_position(&self, position: &Position) -> bool {
+        self.get(&position.zone_id).is_some_and(|zone| zone.is_walkable(position.x, position.y))
+    }
+}
+
+pub fn get_world_map() -> Result<WorldMap, GameDataError> {
+    WorldMap::load(&data_directory().join("zones"))
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use crate::world::{get_world_map, SpawnPoint, WorldMap, ZoneDefinition, ZoneExit};
+
+    fn zone(id: &str, exits: Vec<ZoneExit>) -> ZoneDefinition {
+        ZoneDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            tiles: vec!["...".to_string(), "...".to_string()],
+            spawns: vec![SpawnPoint { id: "start".to_string(), x: 0, y: 0 }],
+            exits,
+            stations: vec![],
+        }
+    }
+
+    fn exit(zone: &str, spawn: &str) -> ZoneExit {
+        ZoneExit { x: 2, y: 1, zone: zone.to_string(), spawn: spawn.to_string() }
+    }
+
+    #[test]
+    fn exits_must_lead_to_known_zones_and_spawns() {
+        assert_ok!(WorldMap::new(vec![zone("a", vec![exit("b", "start")]), zone("b", vec![])]));
+        assert_err!(WorldMap::new(vec![zone("a", vec![exit("c", "start")]), zone("b", vec![])]));
+        assert_err!(WorldMap::new(vec![zone("a", vec![exit("b", "gate")]), zone("b", vec![])]));
+    }
+
+    #[test]
+    fn zones_are_unique() {
+        assert_err!(WorldMap::new(vec![zone("a", vec![]), zone("a", vec![])]));
+    }
+
+    #[test]
+    fn shipped_zones_are_valid() {
+        let map = assert_ok!(get_world_map());
+        assert!(map.contains("greenvale"));
+        assert!(map.contains("old_forest"));
+    }
+}
@@ -0,0 +1,17 @@
+mod aoi;
+mod map;
+mod movement;
+mod position;
+mod service;
+mod store;
+mod system;
+mod zone;
+
+pub use aoi::{visibility_change, VisibilityChange};
+pub use map::{get_world_map, WorldMap};
+pub use movement::{plan_step, MovementError, Step};
+pub use position::Position;
+pub use service::{WorldError, WorldService};
+pub use store::get_character_position;
+pub use system::PositionFlushSystem;
+pub use zone::{SpawnPoint, Tile, Zone, ZoneDefinition, ZoneExit};
\ No newline at end of file
@@ -0,0 +1,117 @@
+use std::fmt::{Debug, Formatter};
+use crate::utils::error_chain_fmt;
+use crate::world::{Position, WorldMap};
+
+#[derive(thiserror::Error, PartialEq, Eq)]
+pub enum MovementError {
+    #[error("Characters move one tile at a time")]
+    InvalidStep,
+    #[error("Something is in the way")]
+    Blocked,
+    #[error("Zone {0} does not exist")]
+    UnknownZone(String),
+}
+
+impl Debug for MovementError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Moved(Position),
+    /// Stepped onto an exit and arrived at a spawn point in another zone
+    Transitioned(Position),
+}
+
+impl Step {
+    pub fn position(&self) -> &Position {
+        match self {
+            Step::Moved(position) | Step::Transitioned(position) => position,
+        }
+    }
+}
+
+/// Works out a single step in one of the eight directions. Diagonal steps can't squeeze between
+/// two blocked tiles or cut the corner of one.
+pub fn plan_step(map: &WorldMap, from: &Position, dx: i32, dy: i32) -> Result<Step, MovementError> {
+    if !(-1..=1).contains(&dx) || !(-1..=1).contains(&dy) || (dx == 0 && dy == 0) {
+        return Err(MovementError::InvalidStep);
+    }
+    let zone = map.get(&from.zone_id).ok_or_else(|| MovementError::UnknownZone(from.zone_id.clone()))?;
+
+    let (x, y) = (from.x + dx, from.y + dy);
+    if !zone.is_walkable(x, y) {
+        return Err(MovementError::Blocked);
+    }
+    if dx != 0 && dy != 0 && !(zone.is_walkable(from.x + dx, from.y) && zone.is_walkable(from.x, from.y + dy)) {
+        return Err(MovementError::Blocked);
+    }
+
+    match zone.exit_at(x, y) {
+        Some(exit) => map.spawn_position(&exit.zone, &exit.spawn)
+            .map(Step::Transitioned)
+            .ok_or_else(|| MovementError::UnknownZone(exit.zone.clone())),
+        None => Ok(Step::Moved(Position::new(from.zone_id.clone(), x, y))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::assert_ok_eq;
+    use crate::world::{plan_step, MovementError, Position, SpawnPoint, Step, WorldMap, ZoneDefinition, ZoneExit};
+
+    fn map() -> WorldMap {
+        WorldMap::new(vec![
+            ZoneDefinition {
+                id: "village".to_string(),
+                name: "Village".to_string(),
+                tiles: vec![
+                    "#####".to_string(),
+                    "#..#,".to_string(),
+                    "#...,".to_string(),
+                    "#####".to_string(),
+                ],
+                spawns: vec![SpawnPoint { id: "square".to_string(), x: 1, y: 1 }],
+                exits: vec![ZoneExit { x: 4, y: 2, zone: "forest".to_string(), spawn: "edge".to_string() }],
+                stations: vec![],
+            },
+            ZoneDefinition {
+                id: "forest".to_string(),
+                name: "Forest".to_string(),
+                tiles: vec![",,,".to_string()],
+                spawns: vec![SpawnPoint { id: "edge".to_string(), x: 0, y: 0 }],
+                exits: vec![],
+                stations: vec![],
+            },
+        ]).unwrap()
+    }
+
+    #[test]
+    fn single_steps_onto_walkable_tiles_are_allowed() {
+        let from = Position::new("village", 1, 1);
+        assert_ok_eq!(plan_step(&map(), &from, 1, 0), Step::Moved(Position::new("village", 2, 1)));
+        assert_ok_eq!(plan_step(&map(), &from, 1, 1), Step::Moved(Position::new("village", 2, 2)));
+    }
+
+    #[test]
+    fn steps_longer_than_one_tile_are_rejected() {
+        let from = Position::new("village", 1, 1);
+        assert_eq!(Err(MovementError::InvalidStep), plan_step(&map(), &from, 2, 0));
+        assert_eq!(Err(MovementError::InvalidStep), plan_step(&map(), &from, 0, 0));
+    }
+
+    #[test]
+    fn walls_block_movement_and_corners_cant_be_cut() {
+        assert_eq!(Err(MovementError::Blocked), plan_step(&map(), &Position::new("village", 1, 1), -1, 0));
+        // (3,1) is a wall, going from (3,2) to (4,1) would clip it
+        assert_eq!(Err(MovementError::Blocked), plan_step(&map(), &Position::new("village", 3, 2), 1, -1));
+    }
+
+    #[test]
+    fn stepping_onto_an_exit_changes_zone() {
+        let from = Position::new("village", 3, 2);
+        assert_ok_eq!(plan_step(&map(), &from, 1, 0), Step::Transitioned(Position::new("forest", 0, 0)));
+    }
+}
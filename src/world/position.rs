@@ -0,0 +1,43 @@
+/// Where a character stands, in tiles from the top left corner of the zone
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub zone_id: String,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Position {
+    pub fn new(zone_id: impl Into<String>, x: i32, y: i32) -> Self {
+        Position { zone_id: zone_id.into(), x, y }
+    }
+
+    /// Tiles between two positions counting diagonal steps as one, `None` across zones
+    pub fn distance(&self, other: &Position) -> Option<i32> {
+        (self.zone_id == other.zone_id)
+            .then(|| (self.x - other.x).abs().max((self.y - other.y).abs()))
+    }
+
+    pub fn is_within(&self, other: &Position, radius: i32) -> bool {
+        self.distance(other).is_some_and(|d| d <= radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world::Position;
+
+    #[test]
+    fn diagonal_steps_count_as_one() {
+        let a = Position::new("greenvale", 2, 2);
+        assert_eq!(Some(3), a.distance(&Position::new("greenvale", 5, 4)));
+        assert!(a.is_within(&Position::new("greenvale", 0, 4), 2));
+        assert!(!a.is_within(&Position::new("greenvale", 5, 2), 2));
+    }
+
+    #[test]
+    fn positions_in_different_zones_are_never_near() {
+        let a = Position::new("greenvale", 2, 2);
+        assert_eq!(None, a.distance(&Position::new("old_forest", 2, 2)));
+        assert!(!a.is_within(&Position::new("old_forest", 2, 2), 100));
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::authentication::UserId;
+use crate::characters::Character;
+use crate::chat::ChatService;
+use crate::configuration::WorldSettings;
+use crate::events::{GameEvent, GameEvents};
+use crate::game_data::GameDataError;
+use crate::gateway::{ClientError, ConnectionRegistry, EntityKind, EntityView, ErrorCode, RateLimiter, ServerMessage};
+use crate::utils::error_chain_fmt;
+use crate::world::{plan_step, visibility_change, MovementError, Position, Step, VisibilityChange, WorldMap};
+use crate::world::store::{get_character_position, store_character_positions};
+
+#[derive(thiserror::Error)]
+pub enum WorldError {
+    #[error("Select a character before entering the world")]
+    NoCharacter,
+    #[error("You are moving too fast")]
+    TooFast,
+    #[error(transparent)]
+    Movement(#[from] MovementError),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for WorldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ClientError for WorldError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            WorldError::NoCharacter => ErrorCode::Forbidden,
+            WorldError::TooFast => ErrorCode::RateLimited,
+            WorldError::Movement(_) => ErrorCode::InvalidMove,
+            WorldError::UnexpectedError(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+struct PlayerInWorld {
+    character_id: Uuid,
+    name: String,
+    position: Position,
+    limiter: RateLimiter,
+    /// Moved since the position was last saved
+    dirty: bool,
+}
+
+impl PlayerInWorld {
+    fn view(&self) -> EntityView {
+        EntityView {
+            id: self.character_id,
+            kind: EntityKind::Character,
+            name: self.name.clone(),
+            x: self.position.x,
+            y: self.position.y,
+        }
+    }
+}
+
+/// An npc as the world shows it, the npc service decides where it goes
+struct NpcInWorld {
+    name: String,
+    position: Position,
+}
+
+impl NpcInWorld {
+    fn view(&self, id: Uuid) -> EntityView {
+        EntityView {
+            id,
+            kind: EntityKind::Npc,
+            name: self.name.clone(),
+            x: self.position.x,
+            y: self.position.y,
+        }
+    }
+}
+
+/// Where every online character and every npc is. Character positions live in memory while playing and
+/// are saved by `PositionFlushSystem` and whenever a character leaves the world, npcs are never saved.
+pub struct WorldService {
+    pool: PgPool,
+    registry: ConnectionRegistry,
+    chat: Arc<ChatService>,
+    map: Arc<WorldMap>,
+    events: GameEvents,
+    settings: WorldSettings,
+    players: RwLock<HashMap<UserId, PlayerInWorld>>,
+    npcs: RwLock<HashMap<Uuid, NpcInWorld>>,
+}
+
+impl WorldService {
+    pub fn new(
+        pool: PgPool,
+        registry: ConnectionRegistry,
+        chat: Arc<ChatService>,
+        map: Arc<WorldMap>,
+        events: GameEvents,
+        settings: WorldSettings,
+    ) -> Result<Self, GameDataError> {
+        if !map.contains(&settings.starting_zone) {
+            return Err(GameDataError::Invalid(format!("Starting zone {} does not exist", settings.starting_zone)));
+        }
+        Ok(WorldService {
+            pool,
+            registry,
+            chat,
+            map,
+            events,
+            settings,
+            players: RwLock::new(HashMap::new()),
+            npcs: RwLock::new(HashMap::new()),
+        })
+    }
+
+    pub fn map(&self) -> &WorldMap {
+        &self.map
+    }
+
+    pub fn position(&self, user_id: UserId) -> Option<Position> {
+        self.players
+            .read()
+            .expect("World lock poisoned")
+            .get(&user_id)
+            .map(|p| p.position.clone())
+    }
+
+    /// Every character in the world with the user playing it
+    pub fn players_in_world(&self) -> Vec<(UserId, Uuid, Position)> {
+        self.players
+            .read()
+            .expect("World lock poisoned")
+            .iter()
+            .map(|(user_id, p)| (*user_id, p.character_id, p.position.clone()))
+            .collect()
+    }
+
+    /// Where a character is, online or not: the live position while in the world, else where it
+    /// was last saved, else where it will start
+    pub async fn locate(&self, character_id: Uuid) -> Result<Position, WorldError> {
+        let live = self.players
+            .read()
+            .expect("World lock poisoned")
+            .values()
+            .find(|p| p.character_id == character_id)
+            .map(|p| p.position.clone());
+        if let Some(position) = live {
+            return Ok(position);
+        }
+        match get_character_position(&self.pool, character_id).await? {
+            Some(position) if self.map.is_valid_position(&position) => Ok(position),
+            _ => Ok(self.starting_position()),
+        }
+    }
+
+    fn starting_position(&self) -> Position {
+        let zone = self.map.get(&self.settings.starting_zone).expect("Starting zone is checked on start up");
+        let spawn = zone.default_spawn();
+        Position::new(zone.id.clone(), spawn.x, spawn.y)
+    }
+
+    /// Puts the character where it was last saved, or at the start if the world changed under it.
+    /// Players already in the world with the same character, from another tab, are just caught up.
+    #[tracing::instrument(
+    name = "Enter world",
+    skip(self, character),
+    fields(character_id = % character.id)
+    )]
+    pub async fn enter(&self, user_id: UserId, character: &Character) -> Result<(), WorldError> {
+        let current = self.players
+            .read()
+            .expect("World lock poisoned")
+            .get(&user_id)
+            .map(|p| p.character_id);
+        match current {
+            Some(character_id) if character_id == character.id => {
+                let players = self.players.read().expect("World lock poisoned");
+                if let Some(player) = players.get(&user_id) {
+                    let entities = self.entities_in_view(&players, user_id, &player.position);
+                    self.registry.send_to_user(user_id, &self.zone_entered(&player.position, entities));
+                }
+                return Ok(());
+            }
+            Some(_) => self.leave(user_id).await?,
+            None => {}
+        }
+
+        let stored = get_character_position(&self.pool, character.id).await?;
+        let position = match &stored {
+            Some(position) if self.map.is_valid_position(position) => position.clone(),
+            _ => self.starting_position(),
+        };
+        let player = PlayerInWorld {
+            character_id: character.id,
+            name: character.name.to_string(),
+            position: position.clone(),
+            limiter: RateLimiter::new(self.settings.step_burst, self.settings.steps_per_second, Instant::now()),
+            dirty: stored.as_ref() != Some(&position),
+        };
+
+        {
+            let mut players = self.players.write().expect("World lock poisoned");
+            let entity = player.view();
+            players.insert(user_id, player);
+            let entities = self.announce(&players, user_id, &entity, None, Some(&position));
+            self.registry.send_to_user(user_id, &self.zone_entered(&position, entities));
+        }
+        self.chat.set_zone(user_id, Some(position.zone_id.clone()));
+        tracing::info!(zone_id = %position.zone_id, x = position.x, y = position.y, "Character entered the world");
+        Ok(())
+    }
+
+    /// Validates and applies one step, everyone in range hears about it
+    pub fn step(&self, user_id: UserId, dx: i32, dy: i32) -> Result<Position, WorldError> {
+        let mut players = self.players.write().expect("World lock poisoned");
+        let player = players.get_mut(&user_id).ok_or(WorldError::NoCharacter)?;
+        let step = plan_step(&self.map, &player.position, dx, dy)?;
+        if !player.limiter.try_acquire(Instant::now()) {
+            return Err(WorldError::TooFast);
+        }
+
+        let old = std::mem::replace(&mut player.position, step.position().clone());
+        player.dirty = true;
+        let new = player.position.clone();
+        let entity = player.view();
+        self.events.publish(GameEvent::PositionChanged {
+            user_id,
+            character_id: player.character_id,
+            position: new.clone(),
+        });
+
+        let entities = self.announce(&players, user_id, &entity, Some(&old), Some(&new));
+        match step {
+            Step::Moved(_) => {
+                self.registry.send_to_user(user_id, &ServerMessage::Moved { x: new.x, y: new.y });
+            }
+            Step::Transitioned(_) => {
+                drop(players);
+                self.registry.send_to_user(user_id, &self.zone_entered(&new, entities));
+                self.chat.set_zone(user_id, Some(new.zone_id.clone()));
+                tracing::info!(%user_id, from = %old.zone_id, to = %new.zone_id, "Character changed zone");
+            }
+        }
+        Ok(new)
+    }
+
+    /// Takes the character out of the world and saves where it stood
+    #[tracing::instrument(
+    name = "Leave world",
+    skip(self)
+    )]
+    pub async fn leave(&self, user_id: UserId) -> Result<(), WorldError> {
+        let player = {
+            let mut players = self.players.write().expect("World lock poisoned");
+            let player = match players.remove(&user_id) {
+                Some(player) => player,
+                None => return Ok(()),
+            };
+            self.announce(&players, user_id, &player.view(), Some(&player.position), None);
+            player
+        };
+        self.chat.set_zone(user_id, None);
+        store_character_positions(&self.pool, &[(player.character_id, player.position)]).await?;
+        Ok(())
+    }
+
+    /// Leaves the world once the player's last connection is gone
+    pub async fn disconnected(&self, user_id: UserId) {
+        if self.registry.is_online(user_id) {
+            return;
+        }
+        if let Err(e) = self.leave(user_id).await {
+            tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to save position on leaving");
+        }
+    }
+
+    /// Saves every position that changed since the last flush, returns how many were saved
+    pub async fn flush_positions(&self) -> Result<usize, anyhow::Error> {
+        let dirty: Vec<(Uuid, Position)> = self.players
+            .write()
+            .expect("World lock poisoned")
+            .values_mut()
+            .filter(|p| p.dirty)
+            .map(|p| {
+                p.dirty = false;
+                (p.character_id, p.position.clone())
+            })
+            .collect();
+        if dirty.is_empty() {
+            return Ok(0);
+        }
+
+        if let Err(e) = store_character_positions(&self.pool, &dirty).await {
+            // try again next time
+            let mut players = self.players.write().expect("World lock poisoned");
+            for player in players.values_mut() {
+                if dirty.iter().any(|(id, _)| *id == player.character_id) {
+                    player.dirty = true;
+                }
+            }
+            return Err(e);
+        }
+        Ok(dirty.len())
+    }
+
+    /// Shows a new npc to everyone around it
+    pub fn place_npc(&self, id: Uuid, name: &str, position: Position) {
+        let npc = NpcInWorld { name: name.to_string(), position };
+        let entity = npc.view(id);
+        let position = npc.position.clone();
+        self.npcs.write().expect("World lock poisoned").insert(id, npc);
+        self.announce_npc(&entity, None, Some(&position));
+    }
+
+    pub fn move_npc(&self, id: Uuid, position: Position) {
+        let (entity, old) = {
+            let mut npcs = self.npcs.write().expect("World lock poisoned");
+            let Some(npc) = npcs.get_mut(&id) else { return };
+            let old = std::mem::replace(&mut npc.position, position.clone());
+            (npc.view(id), old)
+        };
+        self.announce_npc(&entity, Some(&old), Some(&position));
+    }
+
+    pub fn remove_npc(&self, id: Uuid) {
+        let Some(npc) = self.npcs.write().expect("World lock poisoned").remove(&id) else { return };
+        self.announce_npc(&npc.view(id), Some(&npc.position), None);
+    }
+
+    /// Tells everyone whose view `mover` walked into or out of, and returns what came into the
+    /// mover's own view when it changed zone. Within a zone the mover is told right away.
+    fn announce(
+        &self,
+        players: &HashMap<UserId, PlayerInWorld>,
+        mover: UserId,
+        entity: &EntityView,
+        old: Option<&Position>,
+        new: Option<&Position>,
+    ) -> Vec<EntityView> {
+        let radius = self.settings.area_of_interest_radius;
+        let zone_changed = old.map(|p| &p.zone_id) != new.map(|p| &p.zone_id);
+        let mut appeared = Vec::new();
+
+        for (user_id, observer) in players.iter().filter(|(id, _)| **id != mover) {
+            match visibility_change(old, new, &observer.position, radius) {
+                VisibilityChange::Appeared => {
+                    self.registry.send_to_user(*user_id, &ServerMessage::EntityAppeared { entity: entity.clone() });
+                    if zone_changed {
+                        appeared.push(observer.view());
+                    } else {
+                        self.registry.send_to_user(mover, &ServerMessage::EntityAppeared { entity: observer.view() });
+                    }
+                }
+                VisibilityChange::Moved => {
+                    self.registry.send_to_user(*user_id, &ServerMessage::EntityMoved { id: entity.id, x: entity.x, y: entity.y });
+                }
+                VisibilityChange::Left => {
+                    self.registry.send_to_user(*user_id, &ServerMessage::EntityLeft { id: entity.id });
+                    if !zone_changed {
+                        self.registry.send_to_user(mover, &ServerMessage::EntityLeft { id: observer.character_id });
+                    }
+                }
+                VisibilityChange::Unseen => {}
+            }
+        }
+        // npcs don't care who they see, only the mover needs telling
+        for (id, npc) in self.npcs.read().expect("World lock poisoned").iter() {
+            match visibility_change(old, new, &npc.position, radius) {
+                VisibilityChange::Appeared if zone_changed => appeared.push(npc.view(*id)),
+                VisibilityChange::Appeared => {
+                    self.registry.send_to_user(mover, &ServerMessage::EntityAppeared { entity: npc.view(*id) });
+                }
+                VisibilityChange::Left if !zone_changed => {
+                    self.registry.send_to_user(mover, &ServerMessage::EntityLeft { id: *id });
+                }
+                _ => {}
+            }
+        }
+        appeared
+    }
+
+    /// Like `announce` for an npc, without holding the npc lock so players can be read
+    fn announce_npc(&self, entity: &EntityView, old: Option<&Position>, new: Option<&Position>) {
+        let players = self.players.read().expect("World lock poisoned");
+        for (user_id, observer) in players.iter() {
+            match visibility_change(old, new, &observer.position, self.settings.area_of_interest_radius) {
+                VisibilityChange::Appeared => {
+                    self.registry.send_to_user(*user_id, &ServerMessage::EntityAppeared { entity: entity.clone() });
+                }
+                VisibilityChange::Moved => {
+                    self.registry.send_to_user(*user_id, &ServerMessage::EntityMoved { id: entity.id, x: entity.x, y: entity.y });
+                }
+                VisibilityChange::Left => {
+                    self.registry.send_to_user(*user_id, &ServerMessage::EntityLeft { id: entity.id });
+                }
+                VisibilityChange::Unseen => {}
+            }
+        }
+    }
+
+    fn entities_in_view(&self, players: &HashMap<UserId, PlayerInWorld>, viewer: UserId, position: &Position) -> Vec<EntityView> {
+        let radius = self.settings.area_of_interest_radius;
+        let mut entities: Vec<EntityView> = players.iter()
+            .filter(|(id, p)| **id != viewer && p.position.is_within(position, radius))
+            .map(|(_, p)| p.view())
+            .collect();
+        entities.extend(
+            self.npcs
+                .read()
+                .expect("World lock poisoned")
+                .iter()
+                .filter(|(_, npc)| npc.position.is_within(position, radius))
+                .map(|(id, npc)| npc.view(*id))
+        );
+        entities
+    }
+
+    fn zone_entered(&self, position: &Position, entities: Vec<EntityView>) -> ServerMessage {
+        let zone = self.map.get(&position.zone_id).expect("Characters are only placed in known zones");
+        ServerMessage::ZoneEntered {
+            zone_id: zone.id.clone(),
+            zone_name: zone.name.clone(),
+            tiles: zone.rows().to_vec(),
+            x: position.x,
+            y: position.y,
+            entities,
+        }
+    }
+}
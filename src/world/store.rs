@@ -0,0 +1,61 @@
+use anyhow::Context;
+use sqlx::postgres::PgExecutor;
+use uuid::Uuid;
+use crate::world::Position;
+
+#[tracing::instrument(
+name = "Get character position",
+skip(executor)
+)]
+pub async fn get_character_position(
+    executor: impl PgExecutor<'_>,
+    character_id: Uuid,
+) -> Result<Option<Position>, anyhow::Error> {
+    let position = sqlx::query!(
+        r#"
+        SELECT zone_id, x, y
+        FROM character_positions
+        WHERE character_id = $1
+        "#,
+        character_id
+    )
+        .fetch_optional(executor)
+        .await
+        .context("Failed to fetch character position")?
+        .map(|r| Position::new(r.zone_id, r.x, r.y));
+    Ok(position)
+}
+
+#[tracing::instrument(
+name = "Store character positions",
+skip(executor, positions),
+fields(count = positions.len())
+)]
+pub async fn store_character_positions(
+    executor: impl PgExecutor<'_>,
+    positions: &[(Uuid, Position)],
+) -> Result<(), anyhow::Error> {
+    let character_ids: Vec<Uuid> = positions.iter().map(|(id, _)| *id).collect();
+    let zone_ids: Vec<String> = positions.iter().map(|(_, p)| p.zone_id.clone()).collect();
+    let xs: Vec<i32> = positions.iter().map(|(_, p)| p.x).collect();
+    let ys: Vec<i32> = positions.iter().map(|(_, p)| p.y).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO character_positions (character_id, zone_id, x, y, updated_at)
+        SELECT *, now() FROM UNNEST($1::uuid[], $2::text[], $3::int[], $4::int[])
+        ON CONFLICT (character_id) DO UPDATE
+        SET zone_id = EXCLUDED.zone_id,
+            x = EXCLUDED.x,
+            y = EXCLUDED.y,
+            updated_at = EXCLUDED.updated_at
+        "#,
+        &character_ids,
+        &zone_ids,
+        &xs,
+        &ys
+    )
+        .execute(executor)
+        .await
+        .context("Failed to store character positions")?;
+    Ok(())
+}
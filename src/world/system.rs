@@ -0,0 +1,38 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use crate::game_loop::{GameSystem, TickContext};
+use crate::world::WorldService;
+
+/// Saves moved characters every so often in game time, leaving the world saves right away
+pub struct PositionFlushSystem {
+    world: Arc<WorldService>,
+    interval: chrono::Duration,
+    last_flush: Option<DateTime<Utc>>,
+}
+
+impl PositionFlushSystem {
+    pub fn new(world: Arc<WorldService>, interval: chrono::Duration) -> Self {
+        PositionFlushSystem { world, interval, last_flush: None }
+    }
+}
+
+#[async_trait]
+impl GameSystem for PositionFlushSystem {
+    fn name(&self) -> &'static str {
+        "position_flush"
+    }
+
+    async fn run(&mut self, ctx: &TickContext) -> Result<(), anyhow::Error> {
+        let last_flush = *self.last_flush.get_or_insert(ctx.now);
+        if ctx.now - last_flush < self.interval {
+            return Ok(());
+        }
+        self.last_flush = Some(ctx.now);
+        let saved = self.world.flush_positions().await?;
+        if saved > 0 {
+            tracing::debug!(tick = ctx.tick, saved, "Saved character positions");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use crate::game_data::is_valid_id;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    Floor,
+    Grass,
+    Wall,
+    Water,
+    Tree,
+}
+
+impl Tile {
+    pub fn is_walkable(&self) -> bool {
+        matches!(self, Tile::Floor | Tile::Grass)
+    }
+}
+
+impl TryFrom<char> for Tile {
+    type Error = String;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            '.' => Ok(Tile::Floor),
+            ',' => Ok(Tile::Grass),
+            '#' => Ok(Tile::Wall),
+            '~' => Ok(Tile::Water),
+            'T' => Ok(Tile::Tree),
+            other => Err(format!("'{}' is not a known tile", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct SpawnPoint {
+    pub id: String,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Stepping onto the tile moves the character to `spawn` in `zone`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct ZoneExit {
+    pub x: i32,
+    pub y: i32,
+    pub zone: String,
+    pub spawn: String,
+}
+
+/// One zone as written in `data/zones`, each file holds one zone
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ZoneDefinition {
+    pub id: String,
+    pub name: String,
+    pub tiles: Vec<String>,
+    #[serde(default)]
+    pub spawns: Vec<SpawnPoint>,
+    #[serde(default)]
+    pub exits: Vec<ZoneExit>,
+    /// Crafting stations anyone in the zone can use, like `forge`
+    #[serde(default)]
+    pub stations: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+    pub width: i32,
+    pub height: i32,
+    tiles: Vec<Tile>,
+    rows: Vec<String>,
+    pub spawns: Vec<SpawnPoint>,
+    pub exits: Vec<ZoneExit>,
+    pub stations: Vec<String>,
+}
+
+impl Zone {
+    pub fn tile(&self, x: i32, y: i32) -> Option<Tile> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles.get((y * self.width + x) as usize).copied()
+    }
+
+    pub fn is_walkable(&self, x: i32, y: i32) -> bool {
+        self.tile(x, y).is_some_and(|t| t.is_walkable())
+    }
+
+    pub fn spawn(&self, id: &str) -> Option<&SpawnPoint> {
+        self.spawns.iter().find(|s| s.id == id)
+    }
+
+    /// Where characters arrive when nothing else says otherwise
+    pub fn default_spawn(&self) -> &SpawnPoint {
+        // validation makes sure there is at least one
+        &self.spawns[0]
+    }
+
+    pub fn has_station(&self, station: &str) -> bool {
+        self.stations.iter().any(|s| s == station)
+    }
+
+    pub fn exit_at(&self, x: i32, y: i32) -> Option<&ZoneExit> {
+        self.exits.iter().find(|e| e.x == x && e.y == y)
+    }
+
+    /// The grid as written in the data file, sent to clients so they can draw it
+    pub fn rows(&self) -> &[String] {
+        &self.rows
+    }
+}
+
+impl TryFrom<ZoneDefinition> for Zone {
+    type Error = String;
+
+    fn try_from(definition: ZoneDefinition) -> Result<Self, Self::Error> {
+        if !is_valid_id(&definition.id) {
+            return Err(format!("{} is not a valid zone id", definition.id));
+        }
+        if definition.name.trim().is_empty() {
+            return Err(format!("Zone {} needs a name", definition.id));
+        }
+
+        let height = definition.tiles.len();
+        let width = definition.tiles.first().map(|r| r.chars().count()).unwrap_or(0);
+        if width == 0 || height == 0 {
+            return Err(format!("Zone {} has no tiles", definition.id));
+        }
+        let mut tiles = Vec::with_capacity(width * height);
+        for (y, row) in definition.tiles.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(format!("Zone {} row {} is not {} tiles wide", definition.id, y, width));
+            }
+            for c in row.chars() {
+                tiles.push(Tile::try_from(c).map_err(|e| format!("Zone {} row {}: {}", definition.id, y, e))?);
+            }
+        }
+
+        let zone = Zone {
+            id: definition.id,
+            name: definition.name,
+            width: width as i32,
+            height: height as i32,
+            tiles,
+            rows: definition.tiles,
+            spawns: definition.spawns,
+            exits: definition.exits,
+            stations: definition.stations,
+        };
+
+        if zone.spawns.is_empty() {
+            return Err(format!("Zone {} needs at least one spawn point", zone.id));
+        }
+        for (i, spawn) in zone.spawns.iter().enumerate() {
+            if !is_valid_id(&spawn.id) {
+                return Err(format!("{} is not a valid spawn id in zone {}", spawn.id, zone.id));
+            }
+            if zone.spawns[..i].iter().any(|s| s.id == spawn.id) {
+                return Err(format!("Spawn {} is defined twice in zone {}", spawn.id, zone.id));
+            }
+            if !zone.is_walkable(spawn.x, spawn.y) {
+                return Err(format!("Spawn {} in zone {} is not on a walkable tile", spawn.id, zone.id));
+            }
+        }
+        for exit in &zone.exits {
+            if !zone.is_walkable(exit.x, exit.y) {
+                return Err(format!("Exit at {},{} in zone {} is not on a walkable tile", exit.x, exit.y, zone.id));
+            }
+        }
+        if let Some(station) = zone.stations.iter().find(|s| !is_valid_id(s)) {
+            return Err(format!("{} is not a valid station id in zone {}", station, zone.id));
+        }
+        Ok(zone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use crate::world::{SpawnPoint, Tile, Zone, ZoneDefinition, ZoneExit};
+
+    fn definition(tiles: &[&str]) -> ZoneDefinition {
+        ZoneDefinition {
+            id: "meadow".to_string(),
+            name: "Meadow".to_string(),
+            tiles: tiles.iter().map(|r| r.to_string()).collect(),
+            spawns: vec![SpawnPoint { id: "start".to_string(), x: 1, y: 1 }],
+            exits: vec![],
+            stations: vec![],
+        }
+    }
+
+    #[test]
+    fn tiles_are_looked_up_by_coordinates() {
+        let zone = Zone::try_from(definition(&["###", "#.~", "#,#"])).unwrap();
+        assert_eq!(3, zone.width);
+        assert_eq!(3, zone.height);
+        assert_eq!(Some(Tile::Water), zone.tile(2, 1));
+        assert!(zone.is_walkable(1, 2));
+        assert!(!zone.is_walkable(0, 0));
+        assert_eq!(None, zone.tile(3, 0));
+        assert_eq!(None, zone.tile(-1, 1));
+    }
+
+    #[test]
+    fn ragged_or_unknown_tiles_are_rejected() {
+        assert_err!(Zone::try_from(definition(&["###", "#.", "###"])));
+        assert_err!(Zone::try_from(definition(&["###", "#.?", "###"])));
+        assert_err!(Zone::try_from(definition(&[])));
+    }
+
+    #[test]
+    fn spawns_and_exits_must_be_walkable() {
+        assert_err!(Zone::try_from(definition(&["###", "###", "###"])));
+
+        let mut with_exit = definition(&["###", "#..", "###"]);
+        with_exit.exits.push(ZoneExit { x: 2, y: 1, zone: "forest".to_string(), spawn: "edge".to_string() });
+        assert_ok!(Zone::try_from(with_exit.clone()));
+        with_exit.exits[0].x = 0;
+        assert_err!(Zone::try_from(with_exit));
+    }
+
+    #[test]
+    fn zones_need_a_spawn_point() {
+        let mut no_spawns = definition(&["...", "...", "..."]);
+        no_spawns.spawns.clear();
+        assert_err!(Zone::try_from(no_spawns));
+    }
+
+    #[test]
+    fn stations_are_named_by_id() {
+        let mut with_forge = definition(&["...", "...", "..."]);
+        with_forge.stations.push("forge".to_string());
+        let zone = assert_ok!(Zone::try_from(with_forge.clone()));
+        assert!(zone.has_station("forge"));
+        assert!(!zone.has_station("loom"));
+        with_forge.stations.push("Big Anvil".to_string());
+        assert_err!(Zone::try_from(with_forge));
+    }
+}
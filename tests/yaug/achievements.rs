@@ -0,0 +1,147 @@
+use uuid::Uuid;
+use yaug::achievements::Statistic;
+use yaug::authentication::UserId;
+use yaug::events::GameEvent;
+use yaug::ledger::Currency;
+use yaug::mail::SystemMail;
+use crate::helpers::{assert_is_redirected_to, next_ws_json, spawn_test_app};
+
+fn killed(user_id: UserId, character_id: Uuid, creature_id: &str) -> GameEvent {
+    GameEvent::CreatureDefeated { user_id, character_id, creature_id: creature_id.to_string() }
+}
+
+#[tokio::test]
+async fn events_are_counted_and_earn_achievements_once() {
+    let app = spawn_test_app().await;
+    let (email, character_id) = app.new_character("Aldric").await;
+    let user_id = app.user_id(&email).await;
+    let mut ws = app.connect_ws().await.unwrap();
+    assert_eq!("welcome", next_ws_json(&mut ws).await["type"]);
+    assert_eq!("zone_entered", next_ws_json(&mut ws).await["type"]);
+
+    let earned = app.achievements.handle(&killed(user_id, character_id, "wolf")).await.unwrap();
+    assert_eq!(vec!["first_blood".to_string()], earned);
+    assert_eq!("Achievement earned: First Blood", next_ws_json(&mut ws).await["message"]);
+    assert!(app.achievements.handle(&killed(user_id, character_id, "boar")).await.unwrap().is_empty());
+
+    let statistics = app.achievements.statistics(character_id).await.unwrap();
+    assert_eq!(2, statistics.get(Statistic::CreaturesKilled, None));
+    assert_eq!(1, statistics.get(Statistic::CreaturesKilled, Some("wolf")));
+    assert_eq!(1, statistics.get(Statistic::CreaturesKilled, Some("boar")));
+    assert_eq!(0, statistics.get(Statistic::Deaths, None));
+}
+
+#[tokio::test]
+async fn rewards_are_sent_by_mail() {
+    let app = spawn_test_app().await;
+    let (email, character_id) = app.new_character("Aldric").await;
+    let user_id = app.user_id(&email).await;
+    app.set_statistic(character_id, "creatures_killed", "", 30).await;
+    app.set_statistic(character_id, "creatures_killed", "wolf", 24).await;
+
+    let earned = app.achievements.handle(&killed(user_id, character_id, "wolf")).await.unwrap();
+
+    // first blood was met long ago but never awarded, it counts the same statistic
+    assert_eq!(vec!["first_blood".to_string(), "wolfbane".to_string()], earned);
+    let mail = app.mail.mailbox(character_id).await.unwrap();
+    assert_eq!(1, mail.len());
+    assert_eq!(("Hall of Records", "Achievement: Wolfbane"), (mail[0].sender_name.as_str(), mail[0].subject.as_str()));
+    assert_eq!(Some((Currency::Gold, 50)), mail[0].money);
+
+    let response = app.post_mail(&format!("mail/{}/claim", mail[0].id), &()).await;
+    assert_is_redirected_to(&response, &format!("/mail/{}", mail[0].id));
+    assert_eq!(50, app.gold(character_id).await);
+}
+
+#[tokio::test]
+async fn every_criterion_has_to_be_met() {
+    let app = spawn_test_app().await;
+    let (email, character_id) = app.new_character("Aldric").await;
+    let user_id = app.user_id(&email).await;
+    app.set_statistic(character_id, "creatures_killed", "", 150).await;
+    app.set_statistic(character_id, "deaths", "", 8).await;
+    let died = GameEvent::CharacterDied { user_id, character_id };
+
+    assert_eq!(vec!["back_on_your_feet".to_string()], app.achievements.handle(&died).await.unwrap());
+    assert_eq!(vec!["veteran".to_string()], app.achievements.handle(&died).await.unwrap());
+}
+
+#[tokio::test]
+async fn the_game_loop_counts_published_events() {
+    let mut app = spawn_test_app().await;
+    let (email, character_id) = app.new_character("Aldric").await;
+    let user_id = app.user_id(&email).await;
+
+    app.events.publish(GameEvent::QuestCompleted { user_id, character_id, quest_id: "wolf_trouble".to_string() });
+    app.game_loop.tick().await;
+
+    let overview = app.achievements.overview(character_id).await.unwrap();
+    let earned: Vec<&str> = overview.achievements
+        .iter()
+        .filter(|a| a.earned_at.is_some())
+        .map(|a| a.id.as_str())
+        .collect();
+    assert_eq!(vec!["helping_hand", "shepherds_friend"], earned);
+}
+
+#[tokio::test]
+async fn gold_claimed_from_mail_is_counted() {
+    let mut app = spawn_test_app().await;
+    let (_, character_id) = app.new_character("Aldric").await;
+    let mail = app.mail.send_system(None, SystemMail {
+        recipient_id: character_id,
+        subject: "Prize".to_string(),
+        body: "You won the raffle.".to_string(),
+        money: Some((Currency::Gold, 120)),
+        items: vec![],
+    }).await.unwrap();
+
+    app.post_mail(&format!("mail/{}/claim", mail.id), &()).await;
+    app.game_loop.tick().await;
+
+    let overview = app.achievements.overview(character_id).await.unwrap();
+    let gold = overview.statistics.iter().find(|s| s.statistic == "gold_earned").unwrap();
+    assert_eq!(120, gold.value);
+}
+
+#[tokio::test]
+async fn earned_titles_can_be_shown() {
+    let app = spawn_test_app().await;
+    let (email, character_id) = app.new_character("Aldric").await;
+    let user_id = app.user_id(&email).await;
+
+    let response = app.post_character_title(character_id, "wolfbane").await;
+    assert_is_redirected_to(&response, "/characters");
+    assert!(app.get_characters_page_html().await.contains("You haven't earned that title yet"));
+
+    app.set_statistic(character_id, "creatures_killed", "wolf", 24).await;
+    app.achievements.handle(&killed(user_id, character_id, "wolf")).await.unwrap();
+    app.post_character_title(character_id, "first_blood").await;
+    assert!(app.get_characters_page_html().await.contains("You haven't earned that title yet"));
+
+    app.post_character_title(character_id, "wolfbane").await;
+    let html = app.get_characters_page_html().await;
+    assert!(html.contains("Aldric is now known as Aldric the Wolfbane"), "{}", html);
+    assert!(html.contains("<option value=\"wolfbane\" selected>the Wolfbane</option>"));
+
+    app.post_character_title(character_id, "").await;
+    assert!(app.get_characters_page_html().await.contains("Aldric no longer shows a title"));
+}
+
+#[tokio::test]
+async fn the_player_page_shows_statistics_achievements_and_progress() {
+    let app = spawn_test_app().await;
+    let (email, character_id) = app.new_character("Aldric").await;
+    let user_id = app.user_id(&email).await;
+    app.set_statistic(character_id, "creatures_killed", "wolf", 24).await;
+    app.achievements.handle(&killed(user_id, character_id, "wolf")).await.unwrap();
+    app.achievements.select_title(character_id, Some("wolfbane")).await.unwrap();
+    app.set_statistic(character_id, "items_crafted", "bread", 7).await;
+
+    let html = app.get_player_page("AldricPlayer").await.text().await.unwrap();
+
+    assert!(html.contains("Aldric the Wolfbane, level 1 Warrior"), "{}", html);
+    assert!(html.contains("Creatures killed: 1"));
+    assert!(html.contains("<b>Wolfbane</b>, Defeat 25 wolves."));
+    assert!(html.contains("Baker:\n        Items crafted (bread) 7/20"), "{}", html);
+}
@@ -0,0 +1,279 @@
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use yaug::auctions::{AuctionError, BidOutcome, ListingDraft};
+use yaug::ledger::{reconcile, Currency};
+use crate::helpers::{assert_is_redirected_to, next_ws_json, spawn_test_app, TestApp};
+
+const POTION: &str = "minor_healing_potion";
+
+/// Lists potions from the first backpack slot straight through the service
+async fn list_potions(app: &TestApp, seller_id: Uuid, quantity: i32, start_price: Option<i64>, buyout_price: Option<i64>) -> Uuid {
+    let seller = app.character(seller_id).await;
+    let draft = ListingDraft { slot: 0, quantity, start_price, buyout_price, hours: 12 };
+    app.auctions.list(&seller, draft).await.unwrap().id
+}
+
+/// Claims the newest mail of the character the client is playing
+async fn claim_newest_mail(app: &TestApp, character_id: Uuid) {
+    let mail_id = app.mail_ids(character_id).await[0];
+    app.post_mail(&format!("mail/{}/claim", mail_id), &()).await;
+}
+
+#[tokio::test]
+async fn fixed_price_listings_are_settled_by_mail() {
+    let app = spawn_test_app().await;
+    let (buyer, buyer_id) = app.new_character("Brienne").await;
+    let (seller, seller_id) = app.new_character("Aldric").await;
+    app.give_gold(seller_id, 10).await;
+    app.give_items(seller_id, POTION, 3).await;
+
+    let response = app.post_auctions("auctions", &serde_json::json!({
+        "slot": 0,
+        "quantity": 2,
+        "start_price": "",
+        "buyout_price": "100",
+        "hours": 24
+    })).await;
+    assert_is_redirected_to(&response, "/auctions");
+    let html = app.get_auctions_page_html("").await;
+    assert!(html.contains("Minor Healing Potion x2 is up for sale, the listing fee was 5 gold"));
+    assert_eq!(5, app.gold(seller_id).await);
+    assert_eq!(1, app.item_count(seller_id, POTION).await);
+
+    app.play_as(&buyer, buyer_id).await;
+    app.give_gold(buyer_id, 150).await;
+    assert!(app.get_auctions_page_html("?q=healing").await.contains("Minor Healing Potion x2"));
+    assert!(!app.get_auctions_page_html("?q=bread").await.contains("Minor Healing Potion x2"));
+    let listing_id = app.listing_id(seller_id).await;
+    let response = app.post_auctions(&format!("auctions/{}/buy", listing_id), &()).await;
+    assert_is_redirected_to(&response, "/auctions");
+    assert!(app.get_auctions_page_html("").await.contains("You bought Minor Healing Potion x2, it is on its way by mail"));
+    assert_eq!(50, app.gold(buyer_id).await);
+    assert!(app.get_mail_page_html("mail").await.contains("Auction won: Minor Healing Potion x2"));
+    claim_newest_mail(&app, buyer_id).await;
+    assert_eq!(2, app.item_count(buyer_id, POTION).await);
+
+    app.post_auctions(&format!("auctions/{}/buy", listing_id), &()).await;
+    assert!(app.get_auctions_page_html("").await.contains("That listing is no longer available"));
+
+    app.play_as(&seller, seller_id).await;
+    assert!(app.get_mail_page_html("mail").await.contains("Auction sold: Minor Healing Potion x2"));
+    claim_newest_mail(&app, seller_id).await;
+    assert_eq!(105, app.gold(seller_id).await);
+    assert!(reconcile(&app.db_pool).await.unwrap().is_consistent());
+}
+
+#[tokio::test]
+async fn only_one_of_two_buyers_gets_a_listing() {
+    let app = spawn_test_app().await;
+    let (_, first_id) = app.new_character("Brienne").await;
+    let (_, second_id) = app.new_character("Cedric").await;
+    let (_, seller_id) = app.new_character("Aldric").await;
+    app.give_gold(seller_id, 10).await;
+    app.give_items(seller_id, POTION, 1).await;
+    app.give_gold(first_id, 100).await;
+    app.give_gold(second_id, 100).await;
+    let listing_id = list_potions(&app, seller_id, 1, None, Some(100)).await;
+
+    let first = app.character(first_id).await;
+    let second = app.character(second_id).await;
+    let (a, b) = tokio::join!(app.auctions.buy(&first, listing_id, None), app.auctions.buy(&second, listing_id, None));
+
+    assert!(a.is_ok() != b.is_ok(), "exactly one purchase should go through: {:?} {:?}", a, b);
+    let lost = if a.is_ok() { b } else { a };
+    assert!(matches!(lost, Err(AuctionError::ListingNotFound)));
+    assert_eq!(100, app.gold(first_id).await + app.gold(second_id).await);
+    assert_eq!(1, app.mail_ids(first_id).await.len() + app.mail_ids(second_id).await.len());
+    assert!(reconcile(&app.db_pool).await.unwrap().is_consistent());
+}
+
+#[tokio::test]
+async fn listings_made_at_the_same_time_stay_under_the_limit() {
+    let app = spawn_test_app().await;
+    let (_, seller_id) = app.new_character("Aldric").await;
+    app.give_gold(seller_id, 10_000).await;
+    app.give_items(seller_id, POTION, 20).await;
+    for _ in 0..18 {
+        list_potions(&app, seller_id, 1, None, Some(100)).await;
+    }
+    app.give_items(seller_id, POTION, 18).await;
+
+    let seller = app.character(seller_id).await;
+    let draft = ListingDraft { slot: 0, quantity: 1, start_price: None, buyout_price: Some(100), hours: 12 };
+    let outcomes = futures_util::future::join_all((0..5).map(|_| app.auctions.list(&seller, draft.clone()))).await;
+
+    assert_eq!(2, outcomes.iter().filter(|o| o.is_ok()).count());
+    assert!(outcomes.iter().all(|o| !matches!(o, Err(AuctionError::UnexpectedError(_)))));
+    let listed = sqlx::query!(
+        r#"SELECT count(*) AS "count!" FROM auction_listings WHERE seller_id = $1 AND status = 'active'"#,
+        seller_id
+    )
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(20, listed);
+}
+
+#[tokio::test]
+async fn outbid_players_get_their_gold_back_and_a_notice() {
+    let app = spawn_test_app().await;
+    let (_, rival_id) = app.new_character("Brienne").await;
+    let (_, seller_id) = app.new_character("Selwyn").await;
+    let (_, bidder_id) = app.new_character("Aldric").await;
+    app.give_gold(seller_id, 10).await;
+    app.give_items(seller_id, POTION, 1).await;
+    app.give_gold(bidder_id, 100).await;
+    app.give_gold(rival_id, 100).await;
+    let listing_id = list_potions(&app, seller_id, 1, Some(40), None).await;
+
+    let response = app.post_auctions(&format!("auctions/{}/bid", listing_id), &serde_json::json!({ "amount": 40 })).await;
+    assert_is_redirected_to(&response, "/auctions");
+    assert!(app.get_auctions_page_html("").await.contains("You have the highest bid on Minor Healing Potion x1"));
+    assert_eq!(60, app.gold(bidder_id).await);
+    let mut ws = app.connect_ws().await.unwrap();
+    assert_eq!("welcome", next_ws_json(&mut ws).await["type"]);
+    assert_eq!("zone_entered", next_ws_json(&mut ws).await["type"]);
+
+    let rival = app.character(rival_id).await;
+    assert!(matches!(app.auctions.bid(&rival, listing_id, 41, None).await, Err(AuctionError::BidTooLow(42))));
+    assert!(matches!(app.auctions.bid(&rival, listing_id, 50, None).await, Ok(BidOutcome::Leading(_))));
+
+    let notice = next_ws_json(&mut ws).await;
+    assert_eq!("notice", notice["type"]);
+    assert_eq!("You were outbid on Minor Healing Potion x1, the bid is now 50 gold", notice["message"]);
+    assert_eq!(100, app.gold(bidder_id).await);
+    assert_eq!(50, app.gold(rival_id).await);
+
+    assert_eq!(0, app.auctions.expire(Utc::now() + Duration::hours(11)).await.unwrap());
+    assert_eq!(1, app.auctions.expire(Utc::now() + Duration::hours(13)).await.unwrap());
+    let won = app.mail.mailbox(rival_id).await.unwrap();
+    assert_eq!("Auction won: Minor Healing Potion x1", won[0].subject);
+    let sold = app.mail.mailbox(seller_id).await.unwrap();
+    assert_eq!("Auction sold: Minor Healing Potion x1", sold[0].subject);
+    assert_eq!(Some((Currency::Gold, 50)), sold[0].money);
+    assert!(reconcile(&app.db_pool).await.unwrap().is_consistent());
+}
+
+#[tokio::test]
+async fn bids_that_reach_the_buyout_price_buy_right_away() {
+    let app = spawn_test_app().await;
+    let (_, bidder_id) = app.new_character("Brienne").await;
+    let (_, buyer_id) = app.new_character("Cedric").await;
+    let (_, seller_id) = app.new_character("Aldric").await;
+    app.give_gold(seller_id, 10).await;
+    app.give_items(seller_id, POTION, 1).await;
+    app.give_gold(bidder_id, 100).await;
+    app.give_gold(buyer_id, 100).await;
+    let listing_id = list_potions(&app, seller_id, 1, Some(10), Some(30)).await;
+
+    let bidder = app.character(bidder_id).await;
+    app.auctions.bid(&bidder, listing_id, 20, None).await.unwrap();
+    let buyer = app.character(buyer_id).await;
+    let outcome = app.auctions.bid(&buyer, listing_id, 35, None).await.unwrap();
+
+    assert!(matches!(outcome, BidOutcome::Bought(_)));
+    assert_eq!(70, app.gold(buyer_id).await);
+    assert_eq!(100, app.gold(bidder_id).await);
+    assert_eq!(1, app.mail_ids(buyer_id).await.len());
+    assert_eq!(0, app.auctions.expire(Utc::now() + Duration::hours(13)).await.unwrap());
+}
+
+#[tokio::test]
+async fn unsold_and_cancelled_listings_go_back_to_the_seller() {
+    let app = spawn_test_app().await;
+    let (_, bidder_id) = app.new_character("Brienne").await;
+    let (_, seller_id) = app.new_character("Aldric").await;
+    app.give_gold(seller_id, 10).await;
+    app.give_gold(bidder_id, 100).await;
+    app.give_items(seller_id, POTION, 3).await;
+    let cancelled = list_potions(&app, seller_id, 1, None, Some(20)).await;
+    let unsold = list_potions(&app, seller_id, 1, Some(20), None).await;
+    let with_bid = list_potions(&app, seller_id, 1, Some(20), None).await;
+    let bidder = app.character(bidder_id).await;
+    app.auctions.bid(&bidder, with_bid, 20, None).await.unwrap();
+
+    app.post_auctions(&format!("auctions/{}/cancel", with_bid), &()).await;
+    assert!(app.get_auctions_page_html("").await.contains("Listings with bids can't be cancelled"));
+    let response = app.post_auctions(&format!("auctions/{}/cancel", cancelled), &()).await;
+    assert_is_redirected_to(&response, "/auctions");
+    assert!(app.get_auctions_page_html("").await.contains("Listing cancelled, Minor Healing Potion x1 is on its way back by mail"));
+    assert_eq!(0, app.item_count(seller_id, POTION).await);
+
+    assert_eq!(2, app.auctions.expire(Utc::now() + Duration::hours(13)).await.unwrap());
+    let html = app.get_mail_page_html("mail").await;
+    assert!(html.contains("Auction cancelled: Minor Healing Potion x1"));
+    assert!(html.contains("Auction expired: Minor Healing Potion x1"));
+    assert!(html.contains("Auction sold: Minor Healing Potion x1"));
+    for _ in 0..3 {
+        claim_newest_mail(&app, seller_id).await;
+        let newest = app.mail_ids(seller_id).await[0];
+        app.post_mail(&format!("mail/{}/delete", newest), &()).await;
+    }
+    assert_eq!(2, app.item_count(seller_id, POTION).await);
+    assert!(!app.get_auctions_page_html("").await.contains(&unsold.to_string()));
+}
+
+#[tokio::test]
+async fn invalid_listings_and_purchases_are_refused() {
+    let app = spawn_test_app().await;
+    let (buyer, buyer_id) = app.new_character("Brienne").await;
+    let (_, seller_id) = app.new_character("Aldric").await;
+    app.give_gold(seller_id, 10).await;
+    app.give_items(seller_id, POTION, 3).await;
+
+    for (start_price, buyout_price, hours, error) in [
+        ("", "", 12, "Set a starting bid, a buyout price or both"),
+        ("50", "20", 12, "The buyout price can't be below the starting bid"),
+        ("", "20", 5, "Listings run for 12, 24, 48 hours"),
+        ("", "-3", 12, "-3 is not a valid price"),
+    ] {
+        app.post_auctions("auctions", &serde_json::json!({
+            "slot": 0,
+            "quantity": 1,
+            "start_price": start_price,
+            "buyout_price": buyout_price,
+            "hours": hours
+        })).await;
+        let html = app.get_auctions_page_html("").await;
+        assert!(html.contains(error), "expected {} in {}", error, html);
+    }
+    assert_eq!(3, app.item_count(seller_id, POTION).await);
+    assert_eq!(10, app.gold(seller_id).await);
+
+    let fixed = list_potions(&app, seller_id, 1, None, Some(100)).await;
+    let auction = list_potions(&app, seller_id, 1, Some(10), None).await;
+    app.post_auctions(&format!("auctions/{}/buy", fixed), &()).await;
+    assert!(app.get_auctions_page_html("").await.contains("You can't buy or bid on your own listing"));
+
+    app.play_as(&buyer, buyer_id).await;
+    for (path, body, error) in [
+        (format!("auctions/{}/buy", fixed), serde_json::json!({}), "Not enough gold, 100 needed but only 0 available"),
+        (format!("auctions/{}/bid", fixed), serde_json::json!({ "amount": 100 }), "That listing can only be bought outright"),
+        (format!("auctions/{}/buy", auction), serde_json::json!({}), "That listing can't be bought outright"),
+        (format!("auctions/{}/cancel", auction), serde_json::json!({}), "That listing is no longer available"),
+    ] {
+        app.post_auctions(&path, &body).await;
+        let html = app.get_auctions_page_html("").await;
+        assert!(html.contains(error), "expected {} in {}", error, html);
+    }
+    assert!(app.mail_ids(buyer_id).await.is_empty());
+}
+
+#[tokio::test]
+async fn the_game_loop_closes_finished_listings() {
+    let mut app = spawn_test_app().await;
+    let (_, seller_id) = app.new_character("Aldric").await;
+    app.give_gold(seller_id, 10).await;
+    app.give_items(seller_id, POTION, 1).await;
+    let listing_id = list_potions(&app, seller_id, 1, Some(10), None).await;
+    sqlx::query!("UPDATE auction_listings SET expires_at = now() - interval '1 hour' WHERE id = $1", listing_id)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    app.game_loop.tick().await;
+
+    let mail = app.mail.mailbox(seller_id).await.unwrap();
+    assert_eq!("Auction expired: Minor Healing Potion x1", mail[0].subject);
+}
@@ -0,0 +1,206 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use yaug::characters::{add_experience, experience_for_level};
+use crate::helpers::{assert_is_redirected_to, spawn_test_app};
+
+/// Character names are letters only, a random lowercase suffix keeps them unique across tests
+fn unique_name() -> String {
+    let suffix: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .map(char::from)
+        .filter(|c| c.is_ascii_alphabetic())
+        .take(8)
+        .collect();
+    format!("Hero{}", suffix.to_lowercase())
+}
+
+#[tokio::test]
+async fn characters_page_requires_login() {
+    let app = spawn_test_app().await;
+
+    let response = app.post_new_character(&serde_json::json!({ "name": "Bob", "class": "mage" })).await;
+
+    assert_is_redirected_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn wizard_creates_and_activates_character() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    let name = unique_name();
+
+    let response = app.post_new_character(&serde_json::json!({ "name": name, "class": "mage" })).await;
+    assert_is_redirected_to(&response, "/characters/new/attributes");
+
+    let response = app.post_character_attributes(&serde_json::json!(
+        {
+            "strength": 0,
+            "dexterity": 0,
+            "intelligence": 10,
+            "vitality": 0
+        }
+    )).await;
+    assert_is_redirected_to(&response, "/characters");
+
+    let html = app.get_characters_page_html().await;
+    assert!(html.contains("is ready for adventure"));
+    assert!(html.contains("(playing"));
+
+    let stored = sqlx::query!("SELECT class, level, intelligence FROM characters")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!("mage", stored.class);
+    assert_eq!(1, stored.level);
+    assert_eq!(20, stored.intelligence);
+
+    assert!(app.get_account_home_html().await.contains(&format!("Playing as {}", name)));
+}
+
+#[tokio::test]
+async fn attributes_step_without_a_draft_goes_back_to_the_start() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+
+    let response = app.post_character_attributes(&serde_json::json!(
+        {
+            "strength": 10,
+            "dexterity": 0,
+            "intelligence": 0,
+            "vitality": 0
+        }
+    )).await;
+
+    assert_is_redirected_to(&response, "/characters/new");
+}
+
+#[tokio::test]
+async fn unspent_points_are_rejected() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.post_new_character(&serde_json::json!({ "name": unique_name(), "class": "rogue" })).await;
+
+    let response = app.post_character_attributes(&serde_json::json!(
+        {
+            "strength": 1,
+            "dexterity": 0,
+            "intelligence": 0,
+            "vitality": 0
+        }
+    )).await;
+
+    assert_is_redirected_to(&response, "/characters/new/attributes");
+}
+
+#[tokio::test]
+async fn character_names_are_unique_across_accounts() {
+    let app = spawn_test_app().await;
+    let name = unique_name();
+
+    app.register_and_login().await;
+    app.create_character(&name, "warrior").await;
+
+    app.register_and_login().await;
+    let response = app.create_character(&name.to_uppercase(), "cleric").await;
+    assert_is_redirected_to(&response, "/characters/new");
+}
+
+#[tokio::test]
+async fn accounts_are_limited_in_number_of_characters() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+
+    for _ in 0..5 {
+        let response = app.create_character(&unique_name(), "ranger").await;
+        assert_is_redirected_to(&response, "/characters");
+    }
+    app.create_character(&unique_name(), "ranger").await;
+
+    let html = app.get_characters_page_html().await;
+    assert!(html.contains("You can't have more than 5 characters"));
+    let count = sqlx::query!(r#"SELECT count(*) as "count!" FROM characters"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(5, count);
+}
+
+#[tokio::test]
+async fn deleted_character_can_be_restored_within_grace_period() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    let name = unique_name();
+    app.create_character(&name, "warrior").await;
+    let id = app.character_id(&name).await;
+
+    let response = app.post_character_action(id, "delete").await;
+    assert_is_redirected_to(&response, "/characters");
+    let html = app.get_characters_page_html().await;
+    assert!(html.contains("has been deleted"));
+    assert!(html.contains("Recently deleted"));
+    assert!(!app.get_account_home_html().await.contains("Playing as"));
+
+    let response = app.post_character_action(id, "restore").await;
+    assert_is_redirected_to(&response, "/characters");
+    assert!(app.get_characters_page_html().await.contains("has been restored"));
+}
+
+#[tokio::test]
+async fn deleted_character_cannot_be_restored_after_grace_period() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    let name = unique_name();
+    app.create_character(&name, "warrior").await;
+    let id = app.character_id(&name).await;
+    app.post_character_action(id, "delete").await;
+
+    sqlx::query!("UPDATE characters SET deleted_at = now() - interval '73 hours' WHERE id = $1", id)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    app.post_character_action(id, "restore").await;
+    assert!(app.get_characters_page_html().await.contains("can no longer be restored"));
+}
+
+#[tokio::test]
+async fn characters_of_other_accounts_cannot_be_touched() {
+    let app = spawn_test_app().await;
+    let name = unique_name();
+    app.register_and_login().await;
+    app.create_character(&name, "warrior").await;
+    let id = app.character_id(&name).await;
+
+    app.register_and_login().await;
+    app.post_character_action(id, "delete").await;
+    assert!(app.get_characters_page_html().await.contains("Character not found"));
+
+    let deleted_at = sqlx::query!("SELECT deleted_at FROM characters WHERE id = $1", id)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .deleted_at;
+    assert!(deleted_at.is_none());
+}
+
+#[tokio::test]
+async fn crossing_an_experience_threshold_raises_the_level() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    let name = unique_name();
+    app.create_character(&name, "warrior").await;
+    let character_id = app.character_id(&name).await;
+
+    let mut tx = app.db_pool.begin().await.unwrap();
+    assert_eq!(1, add_experience(&mut tx, character_id, experience_for_level(2) - 1).await.unwrap());
+    assert_eq!(2, add_experience(&mut tx, character_id, 1).await.unwrap());
+    assert_eq!(3, add_experience(&mut tx, character_id, experience_for_level(3)).await.unwrap());
+    tx.commit().await.unwrap();
+
+    let stored = sqlx::query!("SELECT level, experience FROM characters WHERE id = $1", character_id)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!((3, experience_for_level(2) + experience_for_level(3)), (stored.level, stored.experience));
+}
@@ -0,0 +1,271 @@
+use uuid::Uuid;
+use yaug::authentication::UserId;
+use crate::helpers::{next_ws_json, send_ws_json, spawn_test_app, TestApp, WsStream};
+
+fn unique_name(prefix: &str) -> String {
+    format!("{}{}", prefix, &Uuid::new_v4().simple().to_string()[..6])
+}
+
+async fn say(ws: &mut WsStream, channel: &str, body: &str) {
+    send_ws_json(ws, serde_json::json!({ "type": "chat_send", "channel": channel, "body": body })).await;
+}
+
+/// Round trips a ping, so anything pushed before it must already have arrived
+async fn assert_nothing_received(ws: &mut WsStream) {
+    send_ws_json(ws, serde_json::json!({ "type": "ping", "nonce": 1 })).await;
+    let next = next_ws_json(ws).await;
+    assert_eq!("pong", next["type"], "unexpected message {}", next);
+}
+
+async fn store_global_messages(app: &TestApp, sender: UserId, count: i64) {
+    sqlx::query!(
+        r#"
+        INSERT INTO chat_messages (id, channel, sender_id, sender_name, body, created_at)
+        SELECT gen_random_uuid(), 'global', $1, 'Historian', 'message ' || n, now() - (($2 - n) || ' seconds')::interval
+        FROM generate_series(1, $2) n
+        "#,
+        *sender,
+        count as i32
+    )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn global_messages_reach_every_online_player() {
+    let app = spawn_test_app().await;
+    let alice = unique_name("Alice");
+    let (_, mut alice_ws) = app.connect_player(&alice).await;
+    let (_, mut bob_ws) = app.connect_player(&unique_name("Bob")).await;
+
+    say(&mut alice_ws, "global", "  hello world ").await;
+
+    for ws in [&mut alice_ws, &mut bob_ws] {
+        let message = next_ws_json(ws).await;
+        assert_eq!("chat_message", message["type"]);
+        assert_eq!("global", message["channel"]);
+        assert_eq!(alice, message["sender"]);
+        assert_eq!("hello world", message["body"]);
+    }
+}
+
+#[tokio::test]
+async fn players_without_a_display_name_cannot_chat() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    let mut ws = app.connect_ws().await.unwrap();
+    next_ws_json(&mut ws).await;
+
+    say(&mut ws, "global", "hello?").await;
+
+    let error = next_ws_json(&mut ws).await;
+    assert_eq!("error", error["type"]);
+    assert_eq!("forbidden", error["code"]);
+}
+
+#[tokio::test]
+async fn whispers_only_reach_sender_and_recipient() {
+    let app = spawn_test_app().await;
+    let alice = unique_name("Alice");
+    let bob = unique_name("Bob");
+    let (_, mut alice_ws) = app.connect_player(&alice).await;
+    let (_, mut bob_ws) = app.connect_player(&bob).await;
+    let (_, mut carol_ws) = app.connect_player(&unique_name("Carol")).await;
+
+    send_ws_json(&mut alice_ws, serde_json::json!({ "type": "chat_whisper", "to": bob.to_lowercase(), "body": "psst" })).await;
+
+    for ws in [&mut bob_ws, &mut alice_ws] {
+        let message = next_ws_json(ws).await;
+        assert_eq!("whisper", message["channel"]);
+        assert_eq!(alice, message["sender"]);
+        assert_eq!(bob, message["recipient"]);
+        assert_eq!("psst", message["body"]);
+    }
+    assert_nothing_received(&mut carol_ws).await;
+}
+
+#[tokio::test]
+async fn whispers_to_unknown_or_offline_players_are_refused() {
+    let app = spawn_test_app().await;
+    let (_, mut alice_ws) = app.connect_player(&unique_name("Alice")).await;
+    let bob = unique_name("Bob");
+    let (_, bob_ws) = app.connect_player(&bob).await;
+    drop(bob_ws);
+
+    send_ws_json(&mut alice_ws, serde_json::json!({ "type": "chat_whisper", "to": "Nobody", "body": "hi" })).await;
+    assert_eq!("not_found", next_ws_json(&mut alice_ws).await["code"]);
+
+    // give the gateway a moment to notice bob is gone
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    send_ws_json(&mut alice_ws, serde_json::json!({ "type": "chat_whisper", "to": bob, "body": "hi" })).await;
+    assert_eq!("not_found", next_ws_json(&mut alice_ws).await["code"]);
+}
+
+#[tokio::test]
+async fn blocked_players_are_not_heard() {
+    let app = spawn_test_app().await;
+    let alice = unique_name("Alice");
+    let bob = unique_name("Bob");
+    let (_, mut alice_ws) = app.connect_player(&alice).await;
+    let (_, mut bob_ws) = app.connect_player(&bob).await;
+
+    send_ws_json(&mut bob_ws, serde_json::json!({ "type": "chat_block", "display_name": alice })).await;
+    assert_eq!("notice", next_ws_json(&mut bob_ws).await["type"]);
+    send_ws_json(&mut bob_ws, serde_json::json!({ "type": "chat_block_list" })).await;
+    assert_eq!(serde_json::json!([alice]), next_ws_json(&mut bob_ws).await["display_names"]);
+
+    say(&mut alice_ws, "global", "can you hear me").await;
+    assert_eq!("chat_message", next_ws_json(&mut alice_ws).await["type"]);
+    assert_nothing_received(&mut bob_ws).await;
+
+    send_ws_json(&mut alice_ws, serde_json::json!({ "type": "chat_whisper", "to": bob, "body": "hi" })).await;
+    assert_eq!("forbidden", next_ws_json(&mut alice_ws).await["code"]);
+
+    send_ws_json(&mut bob_ws, serde_json::json!({ "type": "chat_unblock", "display_name": alice })).await;
+    assert_eq!("notice", next_ws_json(&mut bob_ws).await["type"]);
+    say(&mut alice_ws, "global", "how about now").await;
+    assert_eq!("how about now", next_ws_json(&mut bob_ws).await["body"]);
+}
+
+#[tokio::test]
+async fn zone_and_party_messages_stay_within_their_members() {
+    let app = spawn_test_app().await;
+    let (alice_id, mut alice_ws) = app.connect_player(&unique_name("Alice")).await;
+    let (bob_id, mut bob_ws) = app.connect_player(&unique_name("Bob")).await;
+    let (carol_id, mut carol_ws) = app.connect_player(&unique_name("Carol")).await;
+
+    say(&mut alice_ws, "zone", "anyone here?").await;
+    assert_eq!("forbidden", next_ws_json(&mut alice_ws).await["code"]);
+
+    app.chat.set_zone(alice_id, Some("old_forest".to_string()));
+    app.chat.set_zone(bob_id, Some("old_forest".to_string()));
+    app.chat.set_zone(carol_id, Some("mines".to_string()));
+    let party = Uuid::new_v4();
+    app.chat.set_party(alice_id, Some(party));
+    app.chat.set_party(carol_id, Some(party));
+
+    say(&mut alice_ws, "zone", "wolves to the north").await;
+    assert_eq!("zone", next_ws_json(&mut alice_ws).await["channel"]);
+    assert_eq!("wolves to the north", next_ws_json(&mut bob_ws).await["body"]);
+    assert_nothing_received(&mut carol_ws).await;
+
+    say(&mut alice_ws, "party", "regroup").await;
+    assert_eq!("party", next_ws_json(&mut alice_ws).await["channel"]);
+    assert_eq!("regroup", next_ws_json(&mut carol_ws).await["body"]);
+    assert_nothing_received(&mut bob_ws).await;
+}
+
+#[tokio::test]
+async fn filtered_words_are_masked_and_audited() {
+    let app = spawn_test_app().await;
+    let (_, mut ws) = app.connect_player(&unique_name("Alice")).await;
+
+    say(&mut ws, "global", "oh sh1t, a dragon").await;
+
+    let message = next_ws_json(&mut ws).await;
+    assert_eq!("oh ****, a dragon", message["body"]);
+    let audited = sqlx::query!("SELECT action, details FROM audit_log")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!("chat.message_filtered", audited.action);
+    assert!(audited.details.contains("oh sh1t, a dragon"));
+}
+
+#[tokio::test]
+async fn reported_messages_are_recorded_in_the_audit_log() {
+    let app = spawn_test_app().await;
+    let (_, mut alice_ws) = app.connect_player(&unique_name("Alice")).await;
+    let (bob_id, mut bob_ws) = app.connect_player(&unique_name("Bob")).await;
+    say(&mut alice_ws, "global", "buy gold at scam.example").await;
+    let message_id = next_ws_json(&mut bob_ws).await["id"].as_str().unwrap().to_string();
+
+    send_ws_json(&mut bob_ws, serde_json::json!({ "type": "chat_report", "message_id": message_id, "reason": "spam" })).await;
+
+    assert_eq!("notice", next_ws_json(&mut bob_ws).await["type"]);
+    let audited = sqlx::query!("SELECT actor_id, action, subject_id, details FROM audit_log")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(Some(*bob_id), audited.actor_id);
+    assert_eq!("chat.message_reported", audited.action);
+    assert_eq!(Some(Uuid::parse_str(&message_id).unwrap()), audited.subject_id);
+    assert!(audited.details.contains("spam"));
+}
+
+#[tokio::test]
+async fn muted_players_cannot_chat_until_unmuted() {
+    let app = spawn_test_app().await;
+    let alice = unique_name("Alice");
+    let (_, mut alice_ws) = app.connect_player(&alice).await;
+    let moderator = app.register_and_login().await;
+    app.make_moderator(&moderator).await;
+
+    app.post_admin("chat/mute", &serde_json::json!({ "display_name": alice, "minutes": 30, "reason": "spamming" })).await;
+
+    assert_eq!("notice", next_ws_json(&mut alice_ws).await["type"]);
+    say(&mut alice_ws, "global", "hello?").await;
+    assert_eq!("muted", next_ws_json(&mut alice_ws).await["code"]);
+    let page = app.get_admin_page("chat").await.text().await.unwrap();
+    assert!(page.contains(&alice));
+    assert!(page.contains("spamming"));
+
+    app.post_admin("chat/unmute", &serde_json::json!({ "display_name": alice })).await;
+
+    say(&mut alice_ws, "global", "hello!").await;
+    assert_eq!("hello!", next_ws_json(&mut alice_ws).await["body"]);
+    let page = app.get_admin_page("audit").await.text().await.unwrap();
+    assert!(page.contains("chat.user_muted"));
+    assert!(page.contains("chat.user_unmuted"));
+}
+
+#[tokio::test]
+async fn chat_moderation_requires_a_moderator() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+
+    assert_eq!(403, app.get_admin_page("chat").await.status().as_u16());
+    assert_eq!(403, app.get_admin_page("audit").await.status().as_u16());
+}
+
+#[tokio::test]
+async fn history_is_paged_from_newest_to_oldest() {
+    let app = spawn_test_app().await;
+    let (alice_id, mut ws) = app.connect_player(&unique_name("Alice")).await;
+    store_global_messages(&app, alice_id, 60).await;
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "chat_history", "channel": "global" })).await;
+    let page = next_ws_json(&mut ws).await;
+    let messages = page["messages"].as_array().unwrap();
+    assert_eq!(50, messages.len());
+    assert_eq!(true, page["more"]);
+    assert_eq!("message 11", messages[0]["body"]);
+    assert_eq!("message 60", messages[49]["body"]);
+
+    let before = messages[0]["id"].clone();
+    send_ws_json(&mut ws, serde_json::json!({ "type": "chat_history", "channel": "global", "before": before })).await;
+    let page = next_ws_json(&mut ws).await;
+    let messages = page["messages"].as_array().unwrap();
+    assert_eq!(10, messages.len());
+    assert_eq!(false, page["more"]);
+    assert_eq!("message 1", messages[0]["body"]);
+}
+
+#[tokio::test]
+async fn chat_has_its_own_rate_limit() {
+    let app = spawn_test_app().await;
+    let (_, mut ws) = app.connect_player(&unique_name("Alice")).await;
+
+    for n in 0..6 {
+        say(&mut ws, "global", &format!("spam {}", n)).await;
+    }
+
+    let mut limited = 0;
+    for _ in 0..6 {
+        if next_ws_json(&mut ws).await["code"] == "rate_limited" {
+            limited += 1;
+        }
+    }
+    assert!(limited >= 1);
+}
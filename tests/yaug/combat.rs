@@ -0,0 +1,180 @@
+use std::time::Duration;
+use uuid::Uuid;
+use yaug::combat::{get_combat_record, CombatRecord, Encounter};
+use crate::helpers::{next_ws_json, send_ws_json, spawn_test_app, TestApp, WsStream};
+
+/// Skips anything else the server pushes in between, like other players moving about
+pub async fn next_of_type(ws: &mut WsStream, message_type: &str) -> serde_json::Value {
+    loop {
+        let message = next_ws_json(ws).await;
+        if message["type"] == message_type {
+            return message;
+        }
+    }
+}
+
+pub async fn start_fight(ws: &mut WsStream, creature: &str) -> (Uuid, usize) {
+    send_ws_json(ws, serde_json::json!({ "type": "combat_start", "creature": creature })).await;
+    let started = next_of_type(ws, "combat_started").await;
+    let encounter_id = started["encounter_id"].as_str().unwrap().parse().unwrap();
+    (encounter_id, started["you"].as_u64().unwrap() as usize)
+}
+
+/// Strikes the enemy every turn until the fight ends, returns the winning side
+pub async fn fight_to_the_end(ws: &mut WsStream, you: usize) -> Option<usize> {
+    let strike = serde_json::json!({ "type": "combat_act", "ability": "strike", "target": 1 - you });
+    loop {
+        let message = next_ws_json(ws).await;
+        // long fights run into the gateway's rate limit, the strike is tried again once it lets up
+        if message["type"] == "error" && message["code"] == "rate_limited" {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            send_ws_json(ws, strike.clone()).await;
+            continue;
+        }
+        if message["type"] != "combat_update" {
+            continue;
+        }
+        if message["turn"].is_null() {
+            let ended = message["events"].as_array().unwrap().last().unwrap().clone();
+            assert_eq!("ended", ended["type"]);
+            return ended["winner"].as_u64().map(|w| w as usize);
+        }
+        if message["turn"] == you {
+            send_ws_json(ws, strike.clone()).await;
+        }
+    }
+}
+
+pub async fn stored_fight(app: &TestApp, encounter_id: Uuid) -> CombatRecord {
+    for _ in 0..50 {
+        if let Some(record) = get_combat_record(&app.db_pool, encounter_id).await.unwrap() {
+            return record;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("Fight {} was not stored", encounter_id);
+}
+
+#[tokio::test]
+async fn fights_against_creatures_are_played_out_and_stored() {
+    let app = spawn_test_app().await;
+    let (user_id, mut ws, _) = app.enter_world("Tomas").await;
+
+    let (encounter_id, you) = start_fight(&mut ws, "wolf").await;
+    assert_eq!(Some(encounter_id), app.combat.encounter_id(user_id));
+    let winner = fight_to_the_end(&mut ws, you).await;
+
+    let record = stored_fight(&app, encounter_id).await;
+    assert_eq!(winner, record.winner);
+    assert_eq!(None, app.combat.encounter_id(user_id));
+    assert_eq!("Tomas", record.setup.combatants[you].name);
+    assert_eq!(Some("wolf"), record.setup.combatants[1 - you].creature_id.as_deref());
+}
+
+#[tokio::test]
+async fn stored_fights_replay_to_the_same_result() {
+    let app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+    let (encounter_id, you) = start_fight(&mut ws, "boar").await;
+    fight_to_the_end(&mut ws, you).await;
+    let record = stored_fight(&app, encounter_id).await;
+
+    let replayed = Encounter::replay(record.setup.clone(), record.seed, &record.actions).unwrap();
+
+    let outcome = replayed.outcome().unwrap();
+    assert_eq!(record.winner, outcome.winner);
+    assert_eq!(record.rounds, outcome.rounds);
+}
+
+#[tokio::test]
+async fn creatures_can_only_be_fought_where_they_roam() {
+    let app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "combat_start", "creature": "forest_spider" })).await;
+    assert_eq!("not_found", next_ws_json(&mut ws).await["code"]);
+    send_ws_json(&mut ws, serde_json::json!({ "type": "combat_start", "creature": "dragon" })).await;
+    assert_eq!("not_found", next_ws_json(&mut ws).await["code"]);
+}
+
+#[tokio::test]
+async fn players_fight_one_fight_at_a_time() {
+    let app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+    start_fight(&mut ws, "wolf").await;
+    next_of_type(&mut ws, "combat_update").await;
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "combat_start", "creature": "boar" })).await;
+
+    assert_eq!("invalid_action", next_of_type(&mut ws, "error").await["code"]);
+}
+
+#[tokio::test]
+async fn invalid_actions_are_refused() {
+    let app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "combat_act", "ability": "strike", "target": 1 })).await;
+    assert_eq!("not_found", next_ws_json(&mut ws).await["code"]);
+
+    let (_, you) = start_fight(&mut ws, "wolf").await;
+    send_ws_json(&mut ws, serde_json::json!({ "type": "combat_act", "ability": "fire_bolt", "target": 1 - you })).await;
+    assert_eq!("invalid_action", next_of_type(&mut ws, "error").await["code"]);
+    send_ws_json(&mut ws, serde_json::json!({ "type": "combat_act", "ability": "strike", "target": you })).await;
+    assert_eq!("invalid_action", next_of_type(&mut ws, "error").await["code"]);
+}
+
+#[tokio::test]
+async fn players_without_a_character_cannot_fight() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    let mut ws = app.connect_ws().await.unwrap();
+    next_ws_json(&mut ws).await;
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "combat_start", "creature": "wolf" })).await;
+
+    assert_eq!("forbidden", next_ws_json(&mut ws).await["code"]);
+}
+
+#[tokio::test]
+async fn idle_players_have_their_turn_played_for_them() {
+    let mut app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+    let (_, you) = start_fight(&mut ws, "wolf").await;
+    loop {
+        if next_of_type(&mut ws, "combat_update").await["turn"] == you {
+            break;
+        }
+    }
+
+    let ticks_per_timeout = 30 * 10;
+    for _ in 0..ticks_per_timeout {
+        app.game_loop.tick().await;
+    }
+
+    let update = next_of_type(&mut ws, "combat_update").await;
+    assert_eq!("ability_used", update["events"][0]["type"]);
+    assert_eq!(you, update["events"][0]["actor"]);
+}
+
+#[tokio::test]
+async fn combat_logs_are_shown_to_participants_and_moderators_only() {
+    let app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+    let (encounter_id, you) = start_fight(&mut ws, "wolf").await;
+    fight_to_the_end(&mut ws, you).await;
+    stored_fight(&app, encounter_id).await;
+
+    let history = app.get_combat_history_page().await.text().await.unwrap();
+    assert!(history.contains(&format!("/combat/{}", encounter_id)));
+    let log = app.get_combat_log_page(encounter_id).await.text().await.unwrap();
+    assert!(log.contains("Round 1"));
+    assert!(log.contains("Tomas uses Strike on Grey Wolf"));
+    assert!(!log.contains("different result"));
+
+    let outsider = app.register_and_login().await;
+    assert_eq!(403, app.get_combat_log_page(encounter_id).await.status().as_u16());
+    assert_eq!(404, app.get_combat_log_page(Uuid::new_v4()).await.status().as_u16());
+    app.make_moderator(&outsider).await;
+    assert_eq!(200, app.get_combat_log_page(encounter_id).await.status().as_u16());
+}
@@ -0,0 +1,193 @@
+use chrono::{Duration, Utc};
+use yaug::crafting::CraftingError;
+use yaug::events::GameEvent;
+use yaug::items::InventoryError;
+use crate::helpers::{assert_is_redirected_to, next_ws_json, spawn_test_app};
+
+#[tokio::test]
+async fn queued_crafts_take_inputs_and_hand_out_outputs_when_done() {
+    let app = spawn_test_app().await;
+    let (_, character_id) = app.new_character("Aldric").await;
+    app.give_items(character_id, "iron_ore", 12).await;
+    let mut events = app.events.subscribe();
+
+    let response = app.post_crafting("crafting", &serde_json::json!({ "recipe_id": "iron_sword", "count": 2 })).await;
+    assert_is_redirected_to(&response, "/crafting");
+    assert!(app.get_crafting_page_html().await.contains("Queued Iron Sword x2, done at"));
+    assert_eq!(2, app.item_count(character_id, "iron_ore").await);
+    let queue = app.crafting.queue(character_id).await.unwrap();
+    assert_eq!(2, queue.len());
+    assert_eq!(queue[0].finishes_at, queue[1].started_at);
+    assert_eq!(Duration::seconds(30), queue[1].finishes_at - queue[1].started_at);
+
+    assert_eq!(0, app.crafting.complete(Utc::now() + Duration::seconds(20)).await.unwrap());
+    assert_eq!(1, app.crafting.complete(Utc::now() + Duration::seconds(35)).await.unwrap());
+    assert_eq!(1, app.item_count(character_id, "iron_sword").await);
+    assert_eq!(1, app.crafting.complete(Utc::now() + Duration::seconds(65)).await.unwrap());
+    assert_eq!(2, app.item_count(character_id, "iron_sword").await);
+    assert!(app.crafting.queue(character_id).await.unwrap().is_empty());
+
+    let skills = app.crafting.skills(character_id).await.unwrap();
+    let blacksmithing = skills.iter().find(|s| s.skill == "blacksmithing").unwrap();
+    assert_eq!((1, 80, Some(100)), (blacksmithing.level, blacksmithing.experience, blacksmithing.next_level_at));
+    for _ in 0..2 {
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            GameEvent::ItemCrafted { character_id: id, recipe_id, .. } if id == character_id && recipe_id == "iron_sword"
+        ));
+    }
+}
+
+#[tokio::test]
+async fn cancelling_gives_the_inputs_back_and_moves_the_queue_up() {
+    let app = spawn_test_app().await;
+    let (_, character_id) = app.new_character("Aldric").await;
+    app.give_items(character_id, "flour", 6).await;
+    let queue = app.crafting.enqueue(&app.character(character_id).await, "bread", 3).await.unwrap();
+    assert_eq!(0, app.item_count(character_id, "flour").await);
+
+    let response = app.post_crafting(&format!("crafting/{}/cancel", queue[0].id), &()).await;
+    assert_is_redirected_to(&response, "/crafting");
+    assert!(app.get_crafting_page_html().await.contains("Cancelled Bread, the materials are back"));
+    assert_eq!(2, app.item_count(character_id, "flour").await);
+
+    let remaining = app.crafting.queue(character_id).await.unwrap();
+    assert_eq!(vec![queue[1].id, queue[2].id], remaining.iter().map(|j| j.id).collect::<Vec<_>>());
+    // the next craft started right away instead of waiting for the cancelled one
+    assert!(remaining[0].started_at < queue[0].finishes_at);
+    assert!(remaining[0].started_at <= Utc::now());
+    assert_eq!(remaining[0].finishes_at, remaining[1].started_at);
+
+    assert!(matches!(app.crafting.cancel(character_id, queue[0].id).await, Err(CraftingError::JobNotFound)));
+    assert_eq!(2, app.crafting.complete(Utc::now() + Duration::seconds(11)).await.unwrap());
+    assert_eq!(4, app.item_count(character_id, "bread").await);
+}
+
+#[tokio::test]
+async fn recipes_need_the_skill_level_and_the_station() {
+    let app = spawn_test_app().await;
+    let (_, character_id) = app.new_character("Aldric").await;
+    app.give_items(character_id, "iron_ore", 10).await;
+    app.give_items(character_id, "goblin_ear", 1).await;
+    app.give_items(character_id, "wolf_pelt", 4).await;
+
+    app.post_crafting("crafting", &serde_json::json!({ "recipe_id": "copper_ring" })).await;
+    assert!(app.get_crafting_page_html().await.contains("That needs Blacksmithing level 5"));
+    app.set_crafting_experience(character_id, "blacksmithing", 1000).await;
+    app.post_crafting("crafting", &serde_json::json!({ "recipe_id": "copper_ring" })).await;
+    assert!(app.get_crafting_page_html().await.contains("Queued Copper Ring, done at"));
+
+    sqlx::query!(
+        "INSERT INTO character_positions (character_id, zone_id, x, y) VALUES ($1, 'old_forest', 1, 1)",
+        character_id
+    )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.post_crafting("crafting", &serde_json::json!({ "recipe_id": "iron_sword" })).await;
+    assert!(app.get_crafting_page_html().await.contains("That needs a forge nearby"));
+    app.post_crafting("crafting", &serde_json::json!({ "recipe_id": "leather_vest" })).await;
+    assert!(app.get_crafting_page_html().await.contains("Queued Leather Vest, done at"));
+    assert_eq!(8, app.item_count(character_id, "iron_ore").await);
+}
+
+#[tokio::test]
+async fn crafts_need_materials_and_room_in_the_queue() {
+    let app = spawn_test_app().await;
+    let (_, character_id) = app.new_character("Aldric").await;
+    app.give_items(character_id, "flour", 30).await;
+
+    for (body, error) in [
+        (serde_json::json!({ "recipe_id": "iron_sword" }), "Not enough iron_ore, wanted 5 but there are only 0"),
+        (serde_json::json!({ "recipe_id": "moon_pie" }), "There is no recipe called moon_pie"),
+        (serde_json::json!({ "recipe_id": "bread", "count": 11 }), "You can queue between 1 and 10 crafts"),
+    ] {
+        app.post_crafting("crafting", &body).await;
+        let html = app.get_crafting_page_html().await;
+        assert!(html.contains(error), "expected {} in {}", error, html);
+    }
+
+    app.post_crafting("crafting", &serde_json::json!({ "recipe_id": "bread", "count": 8 })).await;
+    app.post_crafting("crafting", &serde_json::json!({ "recipe_id": "bread", "count": 3 })).await;
+    assert!(app.get_crafting_page_html().await.contains("You can have at most 10 crafts queued"));
+    assert_eq!(14, app.item_count(character_id, "flour").await);
+}
+
+#[tokio::test]
+async fn concurrent_crafts_cannot_spend_the_same_materials() {
+    let app = spawn_test_app().await;
+    let (_, character_id) = app.new_character("Aldric").await;
+    app.give_items(character_id, "iron_ore", 5).await;
+    let character = app.character(character_id).await;
+
+    let (first, second) = tokio::join!(
+        app.crafting.enqueue(&character, "iron_sword", 1),
+        app.crafting.enqueue(&character, "iron_sword", 1),
+    );
+
+    let failures: Vec<_> = [first, second].into_iter().filter_map(Result::err).collect();
+    assert_eq!(1, failures.len());
+    assert!(matches!(failures[0], CraftingError::Inventory(InventoryError::NotEnoughItems { .. })));
+    assert_eq!(1, app.crafting.queue(character_id).await.unwrap().len());
+}
+
+#[tokio::test]
+async fn outputs_that_do_not_fit_are_mailed() {
+    let app = spawn_test_app().await;
+    let (_, character_id) = app.new_character("Aldric").await;
+    app.give_items(character_id, "rusty_sword", 29).await;
+    app.give_items(character_id, "flour", 4).await;
+    app.crafting.enqueue(&app.character(character_id).await, "bread", 1).await.unwrap();
+
+    app.crafting.complete(Utc::now() + Duration::seconds(6)).await.unwrap();
+
+    assert_eq!(0, app.item_count(character_id, "bread").await);
+    let mail = app.mail.mailbox(character_id).await.unwrap();
+    assert_eq!(("Workshop", "Crafted: Bread"), (mail[0].sender_name.as_str(), mail[0].subject.as_str()));
+    assert_eq!("bread", mail[0].items[0].item_id);
+    assert_eq!(2, mail[0].items[0].quantity);
+}
+
+#[tokio::test]
+async fn skills_level_up_and_stop_learning_from_trivial_recipes() {
+    let app = spawn_test_app().await;
+    let (_, character_id) = app.new_character("Aldric").await;
+    app.give_items(character_id, "iron_ore", 10).await;
+    app.set_crafting_experience(character_id, "blacksmithing", 60).await;
+    let mut ws = app.connect_ws().await.unwrap();
+    assert_eq!("welcome", next_ws_json(&mut ws).await["type"]);
+    assert_eq!("zone_entered", next_ws_json(&mut ws).await["type"]);
+
+    app.crafting.enqueue(&app.character(character_id).await, "iron_sword", 1).await.unwrap();
+    app.crafting.complete(Utc::now() + Duration::seconds(31)).await.unwrap();
+
+    assert_eq!("You crafted Iron Sword x1", next_ws_json(&mut ws).await["message"]);
+    assert_eq!("Your Blacksmithing is now level 2", next_ws_json(&mut ws).await["message"]);
+
+    // level 11 is ten levels past the sword
+    app.set_crafting_experience(character_id, "blacksmithing", 5500).await;
+    app.crafting.enqueue(&app.character(character_id).await, "iron_sword", 1).await.unwrap();
+    app.crafting.complete(Utc::now() + Duration::seconds(31)).await.unwrap();
+    let skills = app.crafting.skills(character_id).await.unwrap();
+    let blacksmithing = skills.iter().find(|s| s.skill == "blacksmithing").unwrap();
+    assert_eq!((11, 5500), (blacksmithing.level, blacksmithing.experience));
+}
+
+#[tokio::test]
+async fn the_game_loop_hands_out_finished_crafts() {
+    let mut app = spawn_test_app().await;
+    let (_, character_id) = app.new_character("Aldric").await;
+    app.give_items(character_id, "wolf_pelt", 4).await;
+    let job = app.crafting.enqueue(&app.character(character_id).await, "leather_vest", 1).await.unwrap().remove(0);
+    sqlx::query!(
+        "UPDATE crafting_jobs SET started_at = now() - interval '2 hours', finishes_at = now() - interval '1 hour' WHERE id = $1",
+        job.id
+    )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    app.game_loop.tick().await;
+
+    assert_eq!(1, app.item_count(character_id, "leather_vest").await);
+}
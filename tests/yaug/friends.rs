@@ -0,0 +1,196 @@
+use crate::helpers::{assert_is_redirected_to, next_ws_json, send_ws_json, spawn_test_app, TestApp};
+
+/// Registers both players and makes them friends, the client ends up logged in as `second`
+async fn befriend(app: &TestApp, first: &str, second: &str) -> (String, String) {
+    let first_email = app.register_player(first).await;
+    let second_email = app.register_player(second).await;
+    app.post_friends("", first).await;
+    app.login_as(&first_email).await;
+    let response = app.post_friends("accept", second).await;
+    assert_is_redirected_to(&response, "/friends");
+    app.login_as(&second_email).await;
+    (first_email, second_email)
+}
+
+async fn are_friends(app: &TestApp, first: &str, second: &str) -> bool {
+    sqlx::query!(
+        r#"
+        SELECT count(*) as "count!"
+        FROM friendships f
+        JOIN profiles a ON a.user_id = f.user_id
+        JOIN profiles b ON b.user_id = f.friend_id
+        WHERE (a.display_name = $1 AND b.display_name = $2) OR (a.display_name = $2 AND b.display_name = $1)
+        "#,
+        first,
+        second
+    )
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count == 2
+}
+
+#[tokio::test]
+async fn accepted_requests_make_players_friends() {
+    let app = spawn_test_app().await;
+    let alice = app.register_player("Alice").await;
+    app.register_player("Bob").await;
+
+    let response = app.post_friends("", "alice").await;
+    assert_is_redirected_to(&response, "/friends");
+    let html = app.get_friends_page_html().await;
+    assert!(html.contains("Friend request sent to Alice"));
+    assert!(html.contains("Waiting for an answer"));
+
+    app.login_as(&alice).await;
+    let html = app.get_friends_page_html().await;
+    assert!(html.contains("Friend requests"));
+    app.post_friends("accept", "Bob").await;
+
+    let html = app.get_friends_page_html().await;
+    assert!(html.contains("You and Bob are now friends"));
+    assert!(html.contains("Bob <small>Offline"));
+    assert!(are_friends(&app, "Alice", "Bob").await);
+}
+
+#[tokio::test]
+async fn asking_back_accepts_the_pending_request() {
+    let app = spawn_test_app().await;
+    let alice = app.register_player("Alice").await;
+    app.register_player("Bob").await;
+    app.post_friends("", "Alice").await;
+
+    app.login_as(&alice).await;
+    app.post_friends("", "Bob").await;
+
+    assert!(app.get_friends_page_html().await.contains("You and Bob are now friends"));
+    assert!(are_friends(&app, "Alice", "Bob").await);
+}
+
+#[tokio::test]
+async fn declined_and_cancelled_requests_are_gone() {
+    let app = spawn_test_app().await;
+    let alice = app.register_player("Alice").await;
+    let bob = app.register_player("Bob").await;
+    app.post_friends("", "Alice").await;
+
+    app.login_as(&alice).await;
+    app.post_friends("decline", "Bob").await;
+    assert!(app.get_friends_page_html().await.contains("Declined the friend request from Bob"));
+    app.post_friends("accept", "Bob").await;
+    assert!(app.get_friends_page_html().await.contains("Bob hasn't sent you a friend request"));
+
+    app.login_as(&bob).await;
+    app.post_friends("", "Alice").await;
+    app.post_friends("cancel", "Alice").await;
+    assert!(app.get_friends_page_html().await.contains("Cancelled the friend request to Alice"));
+    app.login_as(&alice).await;
+    app.post_friends("accept", "Bob").await;
+    assert!(!are_friends(&app, "Alice", "Bob").await);
+}
+
+#[tokio::test]
+async fn invalid_friend_requests_are_refused() {
+    let app = spawn_test_app().await;
+    befriend(&app, "Alice", "Bob").await;
+
+    for (name, error) in [
+        ("Bob", "You can't add yourself as a friend"),
+        ("Nobody", "There is no player called Nobody"),
+        ("Alice", "Alice is already your friend"),
+    ] {
+        let response = app.post_friends("", name).await;
+        assert_is_redirected_to(&response, "/friends");
+        let html = app.get_friends_page_html().await;
+        assert!(html.contains(error), "expected {} in {}", error, html);
+    }
+
+    app.register_player("Carol").await;
+    app.post_friends("", "Bob").await;
+    app.post_friends("", "Bob").await;
+    assert!(app.get_friends_page_html().await.contains("You already sent Bob a friend request"));
+}
+
+#[tokio::test]
+async fn friends_can_be_removed() {
+    let app = spawn_test_app().await;
+    befriend(&app, "Alice", "Bob").await;
+
+    app.post_friends("remove", "Alice").await;
+
+    assert!(app.get_friends_page_html().await.contains("Alice is no longer your friend"));
+    assert!(!are_friends(&app, "Alice", "Bob").await);
+}
+
+#[tokio::test]
+async fn blocking_ends_friendships_and_refuses_requests_and_guild_invites() {
+    let app = spawn_test_app().await;
+    let (alice, bob) = befriend(&app, "Alice", "Bob").await;
+    let response = app.post_guild("guilds", &serde_json::json!({ "name": "Iron Wolves", "tag": "IW" })).await;
+    assert_is_redirected_to(&response, "/guild");
+
+    app.login_as(&alice).await;
+    app.post_friends("block", "Bob").await;
+    assert!(app.get_friends_page_html().await.contains("You blocked Bob"));
+    assert!(!are_friends(&app, "Alice", "Bob").await);
+    app.post_friends("", "Bob").await;
+    assert!(app.get_friends_page_html().await.contains("Unblock Bob before adding them as a friend"));
+
+    app.login_as(&bob).await;
+    app.post_friends("", "Alice").await;
+    assert!(app.get_friends_page_html().await.contains("Alice is not accepting friend requests from you"));
+    app.post_guild("guild/invite", &serde_json::json!({ "display_name": "Alice" })).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Alice is not accepting invitations from you"));
+
+    app.login_as(&alice).await;
+    app.post_friends("unblock", "Bob").await;
+    assert!(app.get_friends_page_html().await.contains("You unblocked Bob"));
+    app.login_as(&bob).await;
+    app.post_guild("guild/invite", &serde_json::json!({ "display_name": "Alice" })).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Alice was invited"));
+}
+
+#[tokio::test]
+async fn friends_hear_when_a_player_comes_online_and_leaves() {
+    let app = spawn_test_app().await;
+    let (alice, bob) = befriend(&app, "Alice", "Bob").await;
+    app.login_as(&alice).await;
+    let mut alice_ws = app.connect_ws().await.unwrap();
+    assert_eq!("welcome", next_ws_json(&mut alice_ws).await["type"]);
+
+    app.login_as(&bob).await;
+    let mut bob_ws = app.connect_ws().await.unwrap();
+    assert_eq!("welcome", next_ws_json(&mut bob_ws).await["type"]);
+
+    let status = next_ws_json(&mut alice_ws).await;
+    assert_eq!("friend_status", status["type"]);
+    assert_eq!("Bob", status["display_name"]);
+    assert_eq!("online", status["status"]);
+
+    send_ws_json(&mut bob_ws, serde_json::json!({ "type": "friend_list" })).await;
+    let list = next_ws_json(&mut bob_ws).await;
+    assert_eq!("friend_list", list["type"]);
+    assert_eq!(serde_json::json!([{ "display_name": "Alice", "status": "online" }]), list["friends"]);
+
+    bob_ws.close(None).await.unwrap();
+
+    let status = next_ws_json(&mut alice_ws).await;
+    assert_eq!("friend_status", status["type"]);
+    assert_eq!("Bob", status["display_name"]);
+    assert_eq!("offline", status["status"]);
+    app.login_as(&alice).await;
+    assert!(app.get_friends_page_html().await.contains("Bob <small>Offline"));
+}
+
+#[tokio::test]
+async fn blocking_over_the_gateway_also_ends_the_friendship() {
+    let app = spawn_test_app().await;
+    befriend(&app, "Alice", "Bob").await;
+    let mut ws = app.connect_ws().await.unwrap();
+    assert_eq!("welcome", next_ws_json(&mut ws).await["type"]);
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "chat_block", "display_name": "Alice" })).await;
+
+    assert_eq!("You blocked Alice", next_ws_json(&mut ws).await["message"]);
+    assert!(!are_friends(&app, "Alice", "Bob").await);
+}
@@ -0,0 +1,16 @@
+use crate::helpers::spawn_test_app;
+
+#[tokio::test]
+async fn test_apps_step_the_game_loop_by_hand() {
+    let mut app = spawn_test_app().await;
+    let start = app.game_loop.now();
+    assert_eq!(0, app.game_loop.current_tick());
+
+    for _ in 0..3 {
+        app.game_loop.tick().await;
+    }
+
+    assert_eq!(3, app.game_loop.current_tick());
+    let interval = chrono::Duration::from_std(app.game_loop.tick_interval()).unwrap();
+    assert_eq!(start + interval * 3, app.game_loop.now());
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+use futures_util::SinkExt;
+use tokio_tungstenite::tungstenite::Message;
+use yaug::gateway::ServerMessage;
+use crate::helpers::{next_ws_json, send_ws_json, spawn_test_app};
+
+#[tokio::test]
+async fn anonymous_clients_cannot_connect() {
+    let app = spawn_test_app().await;
+
+    assert!(app.connect_ws().await.is_err());
+}
+
+#[tokio::test]
+async fn connected_player_is_welcomed_and_answered() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    let mut ws = app.connect_ws().await.unwrap();
+
+    assert_eq!("welcome", next_ws_json(&mut ws).await["type"]);
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "ping", "nonce": 42 })).await;
+    let pong = next_ws_json(&mut ws).await;
+    assert_eq!("pong", pong["type"]);
+    assert_eq!(42, pong["nonce"]);
+}
+
+#[tokio::test]
+async fn malformed_messages_get_an_error_back() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    let mut ws = app.connect_ws().await.unwrap();
+    next_ws_json(&mut ws).await;
+
+    ws.send(Message::Text("{\"type\": \"teleport\"}".to_string())).await.unwrap();
+
+    let error = next_ws_json(&mut ws).await;
+    assert_eq!("error", error["type"]);
+    assert_eq!("invalid_message", error["code"]);
+}
+
+#[tokio::test]
+async fn flooding_clients_are_rate_limited() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    let mut ws = app.connect_ws().await.unwrap();
+    next_ws_json(&mut ws).await;
+
+    for nonce in 0..15 {
+        send_ws_json(&mut ws, serde_json::json!({ "type": "ping", "nonce": nonce })).await;
+    }
+
+    let mut limited = 0;
+    for _ in 0..15 {
+        if next_ws_json(&mut ws).await["code"] == "rate_limited" {
+            limited += 1;
+        }
+    }
+    assert!(limited >= 4, "only {} messages were rate limited", limited);
+}
+
+#[tokio::test]
+async fn server_can_push_to_a_connected_player() {
+    let app = spawn_test_app().await;
+    let email = app.register_and_login().await;
+    let user_id = app.user_id(&email).await;
+    let mut ws = app.connect_ws().await.unwrap();
+    next_ws_json(&mut ws).await;
+
+    assert!(app.registry.is_online(user_id));
+    assert_eq!(1, app.registry.send_to_user(user_id, &ServerMessage::Pong { nonce: Some(7) }));
+
+    let pushed = next_ws_json(&mut ws).await;
+    assert_eq!("pong", pushed["type"]);
+    assert_eq!(7, pushed["nonce"]);
+}
+
+#[tokio::test]
+async fn closed_connections_are_removed_from_the_registry() {
+    let app = spawn_test_app().await;
+    let email = app.register_and_login().await;
+    let user_id = app.user_id(&email).await;
+    let mut ws = app.connect_ws().await.unwrap();
+    next_ws_json(&mut ws).await;
+
+    ws.close(None).await.unwrap();
+
+    for _ in 0..50 {
+        if !app.registry.is_online(user_id) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("Connection is still registered after closing");
+}
@@ -0,0 +1,357 @@
+use crate::helpers::{assert_is_redirected_to, next_ws_json, send_ws_json, spawn_test_app, TestApp};
+
+/// Registers a player who founds `name`, the client stays logged in as them
+async fn found_guild(app: &TestApp, display_name: &str, name: &str, tag: &str) -> String {
+    let email = app.register_player(display_name).await;
+    let response = app.post_guild("guilds", &serde_json::json!({ "name": name, "tag": tag })).await;
+    assert_is_redirected_to(&response, "/guild");
+    email
+}
+
+/// Registers a player, has `inviter` invite them and accepts, the client ends up logged in as the new member
+async fn join_guild(app: &TestApp, inviter: &str, guild: &str, display_name: &str) -> String {
+    let email = app.register_player(display_name).await;
+    app.login_as(inviter).await;
+    app.post_guild("guild/invite", &serde_json::json!({ "display_name": display_name })).await;
+    app.login_as(&email).await;
+    let guild_id = app.guild_id(guild).await;
+    let response = app.post_guild(&format!("guilds/{}/accept", guild_id), &()).await;
+    assert_is_redirected_to(&response, "/guild");
+    email
+}
+
+async fn member_id(app: &TestApp, display_name: &str) -> uuid::Uuid {
+    sqlx::query!("SELECT user_id FROM profiles WHERE display_name = $1", display_name)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .user_id
+}
+
+async fn rank_of(app: &TestApp, display_name: &str) -> Option<i32> {
+    sqlx::query!(
+        "SELECT m.rank FROM guild_members m JOIN profiles p ON p.user_id = m.user_id WHERE p.display_name = $1",
+        display_name
+    )
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap()
+        .map(|r| r.rank)
+}
+
+#[tokio::test]
+async fn players_outside_a_guild_are_sent_to_the_guild_list() {
+    let app = spawn_test_app().await;
+    app.register_player("Loner").await;
+
+    let response = app.get_guild_page("guild").await;
+
+    assert_is_redirected_to(&response, "/guilds");
+}
+
+#[tokio::test]
+async fn founders_lead_their_new_guild() {
+    let app = spawn_test_app().await;
+    found_guild(&app, "Alice", "  Iron   Wolves ", "iw").await;
+
+    let html = app.get_guild_page_html("guild").await;
+    assert!(html.contains("Iron Wolves [IW]"));
+    assert!(html.contains("You are Guild Master"));
+    assert_eq!(Some(0), rank_of(&app, "Alice").await);
+
+    let list = app.get_guild_page_html("guilds").await;
+    assert!(list.contains("Iron Wolves [IW] <small>(1 members)</small>"));
+}
+
+#[tokio::test]
+async fn guild_names_and_tags_must_be_valid_and_unique() {
+    let app = spawn_test_app().await;
+    found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    app.register_player("Bob").await;
+
+    for (name, tag, error) in [
+        ("The Admin Club", "TAC", "Admin is a reserved name"),
+        ("Iron-Wolves", "IW2", "Guild name can only contain letters, digits and spaces"),
+        ("Wolves", "W", "Guild tag must be between 2 and 5 characters long"),
+        ("iron wolves", "IRW", "The name iron wolves is already taken"),
+        ("Steel Wolves", "iw", "The tag IW is already taken"),
+    ] {
+        let response = app.post_guild("guilds", &serde_json::json!({ "name": name, "tag": tag })).await;
+        assert_is_redirected_to(&response, "/guilds");
+        let html = app.get_guild_page_html("guilds").await;
+        assert!(html.contains(error), "expected {:?} for {} [{}]", error, name, tag);
+    }
+    assert_eq!(None, rank_of(&app, "Bob").await);
+}
+
+#[tokio::test]
+async fn invited_players_join_at_the_lowest_rank() {
+    let app = spawn_test_app().await;
+    let alice = found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    let bob = app.register_player("Bob").await;
+    app.login_as(&alice).await;
+
+    app.post_guild("guild/invite", &serde_json::json!({ "display_name": "bob" })).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Bob was invited"));
+
+    app.login_as(&bob).await;
+    assert!(app.get_guild_page_html("guilds").await.contains("Iron Wolves [IW], invited by Alice"));
+    let guild_id = app.guild_id("Iron Wolves").await;
+    let response = app.post_guild(&format!("guilds/{}/accept", guild_id), &()).await;
+    assert_is_redirected_to(&response, "/guild");
+    assert_eq!(Some(2), rank_of(&app, "Bob").await);
+
+    app.login_as(&alice).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Bob <small>(Member"));
+}
+
+#[tokio::test]
+async fn uninvited_players_cannot_accept() {
+    let app = spawn_test_app().await;
+    found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    app.register_player("Bob").await;
+    let guild_id = app.guild_id("Iron Wolves").await;
+
+    let response = app.post_guild(&format!("guilds/{}/accept", guild_id), &()).await;
+
+    assert_is_redirected_to(&response, "/guilds");
+    assert!(app.get_guild_page_html("guilds").await.contains("You have not been invited to that guild"));
+    assert_eq!(None, rank_of(&app, "Bob").await);
+}
+
+#[tokio::test]
+async fn players_in_a_guild_cannot_be_invited_elsewhere() {
+    let app = spawn_test_app().await;
+    found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    found_guild(&app, "Bob", "Steel Wolves", "SW").await;
+
+    app.post_guild("guild/invite", &serde_json::json!({ "display_name": "Alice" })).await;
+
+    assert!(app.get_guild_page_html("guild").await.contains("Alice is already in a guild"));
+}
+
+#[tokio::test]
+async fn applications_need_someone_who_may_invite() {
+    let app = spawn_test_app().await;
+    let alice = found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    let bob = join_guild(&app, &alice, "Iron Wolves", "Bob").await;
+    app.register_player("Carol").await;
+    let guild_id = app.guild_id("Iron Wolves").await;
+    app.post_guild(&format!("guilds/{}/apply", guild_id), &serde_json::json!({ "message": "Let me in" })).await;
+    let carol_id = member_id(&app, "Carol").await;
+
+    app.login_as(&bob).await;
+    assert!(!app.get_guild_page_html("guild").await.contains("Let me in"));
+    app.post_guild(&format!("guild/applications/{}/accept", carol_id), &()).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Your rank is not allowed to do that"));
+
+    app.login_as(&alice).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Carol: Let me in"));
+    app.post_guild(&format!("guild/applications/{}/accept", carol_id), &()).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Carol joined the guild"));
+    assert_eq!(Some(2), rank_of(&app, "Carol").await);
+}
+
+#[tokio::test]
+async fn kicking_only_works_down_the_ranks() {
+    let app = spawn_test_app().await;
+    let alice = found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    let bob = join_guild(&app, &alice, "Iron Wolves", "Bob").await;
+    let carol = join_guild(&app, &alice, "Iron Wolves", "Carol").await;
+    app.login_as(&alice).await;
+    app.post_guild(&format!("guild/members/{}/rank", member_id(&app, "Bob").await), &serde_json::json!({ "rank": 1 })).await;
+    assert_eq!(Some(1), rank_of(&app, "Bob").await);
+
+    app.login_as(&carol).await;
+    app.post_guild(&format!("guild/members/{}/kick", member_id(&app, "Bob").await), &()).await;
+    assert_eq!(Some(1), rank_of(&app, "Bob").await);
+
+    app.login_as(&bob).await;
+    app.post_guild(&format!("guild/members/{}/kick", member_id(&app, "Alice").await), &()).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Your rank is not allowed to do that"));
+    app.post_guild(&format!("guild/members/{}/kick", member_id(&app, "Carol").await), &()).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Carol was removed from the guild"));
+
+    assert_eq!(Some(0), rank_of(&app, "Alice").await);
+    assert_eq!(None, rank_of(&app, "Carol").await);
+    app.login_as(&carol).await;
+    assert_is_redirected_to(&app.get_guild_page("guild").await, "/guilds");
+}
+
+#[tokio::test]
+async fn guild_masters_hand_over_before_leaving() {
+    let app = spawn_test_app().await;
+    let alice = found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    join_guild(&app, &alice, "Iron Wolves", "Bob").await;
+    app.login_as(&alice).await;
+
+    app.post_guild("guild/leave", &()).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Make someone else guild master before leaving"));
+
+    app.post_guild(&format!("guild/members/{}/rank", member_id(&app, "Bob").await), &serde_json::json!({ "rank": 0 })).await;
+    assert_eq!(Some(0), rank_of(&app, "Bob").await);
+    assert_eq!(Some(1), rank_of(&app, "Alice").await);
+
+    let response = app.post_guild("guild/leave", &()).await;
+    assert_is_redirected_to(&response, "/guilds");
+    assert_eq!(None, rank_of(&app, "Alice").await);
+}
+
+#[tokio::test]
+async fn the_last_member_leaving_disbands_the_guild() {
+    let app = spawn_test_app().await;
+    found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+
+    app.post_guild("guild/leave", &()).await;
+
+    let html = app.get_guild_page_html("guilds").await;
+    assert!(html.contains("Nobody has founded a guild yet."));
+    let guilds = sqlx::query!(r#"SELECT count(*) as "count!" FROM guilds"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(0, guilds);
+}
+
+#[tokio::test]
+async fn only_granted_ranks_change_the_message_of_the_day() {
+    let app = spawn_test_app().await;
+    let alice = found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    let bob = join_guild(&app, &alice, "Iron Wolves", "Bob").await;
+
+    app.post_guild("guild/motd", &serde_json::json!({ "motd": "Bob was here" })).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Your rank is not allowed to do that"));
+
+    app.login_as(&alice).await;
+    app.post_guild("guild/ranks/2", &serde_json::json!({ "edit_motd": "on" })).await;
+    app.login_as(&bob).await;
+    app.post_guild("guild/motd", &serde_json::json!({ "motd": "Raid at <b>eight</b>" })).await;
+
+    let html = app.get_guild_page_html("guild").await;
+    assert!(html.contains("<b>Message of the day:</b> Raid at &lt;b&gt;eight&lt;&#x2F;b&gt;"));
+}
+
+#[tokio::test]
+async fn the_guild_master_rank_cannot_be_edited() {
+    let app = spawn_test_app().await;
+    found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+
+    app.post_guild("guild/ranks/0", &()).await;
+
+    assert!(app.get_guild_page_html("guild").await.contains("Your rank is not allowed to do that"));
+    assert_eq!(Some(0), rank_of(&app, "Alice").await);
+}
+
+#[tokio::test]
+async fn members_deposit_but_only_bankers_withdraw() {
+    let app = spawn_test_app().await;
+    let alice = found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    let bob = join_guild(&app, &alice, "Iron Wolves", "Bob").await;
+    app.create_character("Bobcat", "warrior").await;
+    let bobcat = app.character_id("Bobcat").await;
+    app.give_items(bobcat, "iron_ore", 20).await;
+
+    app.post_guild("guild/bank/deposit", &serde_json::json!({ "slot": 0, "quantity": 15 })).await;
+    let html = app.get_guild_page_html("guild").await;
+    assert!(html.contains("Deposited 15 Iron Ore"));
+    let bank = sqlx::query!("SELECT slot, item_id, quantity FROM guild_bank_items")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(1, bank.len());
+    assert_eq!(("iron_ore", 15), (bank[0].item_id.as_str(), bank[0].quantity));
+
+    app.post_guild("guild/bank/withdraw", &serde_json::json!({ "slot": 0, "quantity": 5 })).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Your rank is not allowed to do that"));
+
+    app.login_as(&alice).await;
+    app.post_guild("guild/ranks/2", &serde_json::json!({ "manage_bank": "on" })).await;
+    app.login_as(&bob).await;
+    app.post_guild("guild/bank/withdraw", &serde_json::json!({ "slot": 0, "quantity": 15 })).await;
+    assert!(app.get_guild_page_html("guild").await.contains("Withdrew 15 Iron Ore"));
+
+    let inventory = app.inventory_service().get_inventory(&app.db_pool, bobcat).await.unwrap();
+    assert_eq!(20, inventory.count("iron_ore"));
+    let banked = sqlx::query!(r#"SELECT count(*) as "count!" FROM guild_bank_items"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(0, banked);
+}
+
+#[tokio::test]
+async fn depositing_more_than_the_stack_changes_nothing() {
+    let app = spawn_test_app().await;
+    found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    app.create_character("Alicat", "warrior").await;
+    let alicat = app.character_id("Alicat").await;
+    app.give_items(alicat, "iron_ore", 5).await;
+
+    app.post_guild("guild/bank/deposit", &serde_json::json!({ "slot": 0, "quantity": 6 })).await;
+
+    let inventory = app.inventory_service().get_inventory(&app.db_pool, alicat).await.unwrap();
+    assert_eq!(5, inventory.count("iron_ore"));
+    let banked = sqlx::query!(r#"SELECT count(*) as "count!" FROM guild_bank_items"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(0, banked);
+}
+
+#[tokio::test]
+async fn guild_chat_only_reaches_members() {
+    let app = spawn_test_app().await;
+    let alice = found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    join_guild(&app, &alice, "Iron Wolves", "Bob").await;
+    let mut bob_ws = app.connect_ws().await.unwrap();
+    assert_eq!("welcome", next_ws_json(&mut bob_ws).await["type"]);
+    let (_, mut carol_ws) = app.connect_player("Carol").await;
+    app.login_as(&alice).await;
+    let mut alice_ws = app.connect_ws().await.unwrap();
+    assert_eq!("welcome", next_ws_json(&mut alice_ws).await["type"]);
+
+    send_ws_json(&mut alice_ws, serde_json::json!({ "type": "chat_send", "channel": "guild", "body": "wolves assemble" })).await;
+    for ws in [&mut alice_ws, &mut bob_ws] {
+        let message = next_ws_json(ws).await;
+        assert_eq!("chat_message", message["type"]);
+        assert_eq!("guild", message["channel"]);
+        assert_eq!("wolves assemble", message["body"]);
+    }
+
+    send_ws_json(&mut carol_ws, serde_json::json!({ "type": "chat_send", "channel": "guild", "body": "hello?" })).await;
+    let error = next_ws_json(&mut carol_ws).await;
+    assert_eq!("error", error["type"]);
+    assert_eq!("forbidden", error["code"]);
+}
+
+#[tokio::test]
+async fn guild_changes_are_audited_under_the_guild() {
+    let app = spawn_test_app().await;
+    let alice = found_guild(&app, "Alice", "Iron Wolves", "IW").await;
+    join_guild(&app, &alice, "Iron Wolves", "Bob").await;
+    app.login_as(&alice).await;
+    app.post_guild("guild/motd", &serde_json::json!({ "motd": "Welcome" })).await;
+
+    let guild_id = app.guild_id("Iron Wolves").await;
+    let actions: Vec<String> = sqlx::query!(
+        "SELECT action FROM audit_log WHERE subject_id = $1 ORDER BY created_at",
+        guild_id
+    )
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.action)
+        .collect();
+    assert_eq!(
+        vec!["guild.created", "guild.member_invited", "guild.member_joined", "guild.motd_changed"],
+        actions
+    );
+
+    let html = app.get_guild_page_html("guild").await;
+    assert!(html.contains("Alice invited Bob"));
+    assert!(html.contains("Alice changed the message of the day to &quot;Welcome&quot;"));
+}
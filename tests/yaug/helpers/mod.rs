@@ -1,6 +1,10 @@
+// Not every test module uses every helper
+#![allow(dead_code)]
+
 mod test_app;
 mod redirect;
 mod test_app_impl;
 
-pub use test_app::spawn_test_app;
-pub use redirect::assert_is_redirected_to;
\ No newline at end of file
+pub use test_app::{spawn_test_app, TestApp};
+pub use redirect::assert_is_redirected_to;
+pub use test_app_impl::{next_ws_json, send_ws_json, TEST_PASSWORD, WsStream};
\ No newline at end of file
@@ -1,8 +1,25 @@
+use std::sync::Arc;
+use reqwest::cookie::Jar;
 use sqlx::{PgConnection, PgPool, Connection, Executor};
 use once_cell::sync::Lazy;
 use uuid::Uuid;
+use yaug::chat::ChatService;
+use yaug::combat::CombatService;
 use yaug::configuration::{DatabaseSettings, get_configuration};
+use yaug::events::GameEvents;
+use yaug::game_loop::GameLoop;
+use yaug::gateway::ConnectionRegistry;
+use yaug::leaderboards::LeaderboardService;
+use yaug::mail::MailService;
+use yaug::auctions::AuctionService;
+use yaug::crafting::CraftingService;
+use yaug::achievements::AchievementService;
+use yaug::matchmaking::MatchmakingService;
+use yaug::npcs::NpcService;
+use yaug::parties::PartyService;
+use yaug::quests::QuestService;
 use yaug::startup::Application;
+use yaug::world::WorldService;
 use yaug::telemetry::{get_subscriber, init_subscriber};
 
 static TRACING: Lazy<()> = Lazy::new(|| {
@@ -27,6 +44,26 @@ pub struct TestApp {
     pub db_pool: PgPool,
     pub port: u16,
     pub api_client: reqwest::Client,
+    // shared with api_client, so the session cookie can be reused for WebSocket connections
+    pub cookie_jar: Arc<Jar>,
+    pub registry: ConnectionRegistry,
+    pub chat: Arc<ChatService>,
+    pub world: Arc<WorldService>,
+    pub combat: Arc<CombatService>,
+    pub quests: Arc<QuestService>,
+    pub leaderboards: Arc<LeaderboardService>,
+    pub mail: Arc<MailService>,
+    pub auctions: Arc<AuctionService>,
+    pub crafting: Arc<CraftingService>,
+    pub achievements: Arc<AchievementService>,
+    // the matchmaker isn't running either, tests call `tick` with the time they want
+    pub matchmaking: Arc<MatchmakingService>,
+    pub parties: Arc<PartyService>,
+    // ticked by the game loop, or by hand with any time for respawns
+    pub npcs: Arc<NpcService>,
+    pub events: GameEvents,
+    // not running on its own, tests call `tick` to step the simulation
+    pub game_loop: GameLoop,
 }
 
 impl Drop for TestApp {
@@ -41,31 +78,65 @@ pub async fn spawn_test_app() -> TestApp {
         let mut config = get_configuration().expect("Failed to load configuration");
         config.db.database = Uuid::new_v4().to_string(); // we need a clean db each time we test
         config.app.port = 0; // Let OS assign a random, free port
+        // Redis is shared between tests, every app gets its own boards
+        config.leaderboards.key_prefix = format!("leaderboard:{}", config.db.database);
+        config.presence.key_prefix = format!("presence:{}", config.db.database);
         config
     };
 
     setup_test_database_and_migrate(&settings.db).await;
 
-    let app = Application::build(settings.clone())
+    let mut app = Application::build(settings.clone())
         .await
         .expect("Failed to build application");
     let port = app.port();
+    let registry = app.registry();
+    let chat = app.chat();
+    let world = app.world();
+    let combat = app.combat();
+    let quests = app.quests();
+    let leaderboards = app.leaderboards();
+    let mail = app.mail();
+    let auctions = app.auctions();
+    let crafting = app.crafting();
+    let achievements = app.achievements();
+    let matchmaking = app.matchmaking();
+    let parties = app.parties();
+    let npcs = app.npcs();
+    let events = app.events();
+    let game_loop = app.take_game_loop();
+    drop(app.take_matchmaker());
     let address = format!("http://127.0.0.1:{}", port);
 
-    let _ = tokio::spawn(app.run_until_stopped());
+    drop(tokio::spawn(app.run_until_stopped()));
+    let cookie_jar = Arc::new(Jar::default());
     let api_client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::none()) // Prevent following those 302 redirects. we need to test em!
-        .cookie_store(true)
+        .cookie_provider(cookie_jar.clone())
         .build()
         .unwrap();
-    let test_app = TestApp {
+    TestApp {
         address,
         db_pool: settings.db.get_connection_pool(),
         port,
         api_client,
-    };
-
-    test_app
+        cookie_jar,
+        registry,
+        chat,
+        world,
+        combat,
+        quests,
+        leaderboards,
+        mail,
+        auctions,
+        crafting,
+        achievements,
+        matchmaking,
+        parties,
+        npcs,
+        events,
+        game_loop,
+    }
 }
 
 async fn setup_test_database_and_migrate(db_settings: &DatabaseSettings) -> PgPool {
@@ -1,10 +1,45 @@
+use fake::Fake;
+use fake::faker::internet::en::SafeEmail;
+use std::sync::Arc;
+use std::time::Duration;
+use futures_util::{SinkExt, StreamExt};
+use reqwest::cookie::CookieStore;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+use yaug::authentication::UserId;
+use yaug::characters::{experience_for_level, get_character, Character};
+use yaug::items::{get_item_catalog, InventoryService};
+use yaug::ledger::{get_balance, transfer_and_commit, Currency, LedgerAccount, SystemAccount, Transfer};
 use crate::helpers::test_app::TestApp;
 
+pub type WsStream = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+pub const TEST_PASSWORD: &str = "correct-Horse-battery!";
+
+/// Next text message from the gateway, gives up after five seconds
+pub async fn next_ws_json(ws: &mut WsStream) -> serde_json::Value {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("Timed out waiting for a message")
+            .expect("Connection closed")
+            .expect("Failed to read message");
+        if let Message::Text(text) = message {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+pub async fn send_ws_json(ws: &mut WsStream, value: serde_json::Value) {
+    ws.send(Message::Text(value.to_string())).await.unwrap();
+}
+
 impl TestApp {
     //region Account Home
     pub async fn get_account_home(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/account", &self.address))
+            .get(format!("{}/account", &self.address))
             .send()
             .await
             .expect("Failed to execute get account homepage html request")
@@ -15,10 +50,631 @@ impl TestApp {
     }
     //endregion
 
+    //region Profile
+    pub async fn get_profile_page_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/account/profile", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request to get profile page")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_profile<Body>(&self, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}/account/profile", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to post profile form")
+    }
+
+    pub async fn get_player_page(&self, name: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/players/{}", &self.address, name))
+            .send()
+            .await
+            .expect("Failed to execute request to get player page")
+    }
+    //endregion
+
+    //region Characters
+    pub async fn get_characters_page_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/characters", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request to get characters page")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_new_character<Body>(&self, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}/characters/new", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to post new character form")
+    }
+
+    pub async fn post_character_attributes<Body>(&self, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}/characters/new/attributes", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to post character attributes form")
+    }
+
+    pub async fn post_character_action(&self, character_id: Uuid, action: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/characters/{}/{}", &self.address, character_id, action))
+            .send()
+            .await
+            .expect("Failed to post character action")
+    }
+
+    /// Runs both wizard steps with an even spread of bonus points
+    pub async fn create_character(&self, name: &str, class: &str) -> reqwest::Response {
+        self.post_new_character(&serde_json::json!({ "name": name, "class": class })).await;
+        self.post_character_attributes(&serde_json::json!(
+            {
+                "strength": 3,
+                "dexterity": 3,
+                "intelligence": 2,
+                "vitality": 2
+            }
+        )).await
+    }
+
+    pub async fn character_id(&self, name: &str) -> Uuid {
+        sqlx::query!("SELECT id FROM characters WHERE lower(name) = lower($1) AND deleted_at IS NULL", name)
+            .fetch_one(&self.db_pool)
+            .await
+            .expect("Failed to fetch character id")
+            .id
+    }
+
+    /// Registers a player with one character and leaves the client playing it, returns the email
+    /// to log back in with
+    pub async fn new_character(&self, name: &str) -> (String, Uuid) {
+        let email = self.register_player(&format!("{}Player", name)).await;
+        self.create_character(name, "warrior").await;
+        let id = self.character_id(name).await;
+        self.post_character_action(id, "select").await;
+        (email, id)
+    }
+    //endregion
+
+    //region Inventory
+    pub async fn get_inventory_page(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/inventory", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request to get inventory page")
+    }
+
+    pub async fn post_inventory<Body>(&self, action: &str, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}/inventory/{}", &self.address, action))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to post inventory form")
+    }
+
+    /// Same service the application uses, for putting items into inventories directly
+    pub fn inventory_service(&self) -> InventoryService {
+        InventoryService::new(Arc::new(get_item_catalog().expect("Failed to load item catalog")))
+    }
+
+    pub async fn give_items(&self, character_id: Uuid, item_id: &str, quantity: i32) {
+        let mut tx = self.db_pool.begin().await.unwrap();
+        self.inventory_service()
+            .add_items(&mut tx, character_id, item_id, quantity)
+            .await
+            .expect("Failed to give items");
+        tx.commit().await.unwrap();
+    }
+
+    pub async fn item_count(&self, character_id: Uuid, item_id: &str) -> i32 {
+        self.inventory_service()
+            .get_inventory(&self.db_pool, character_id)
+            .await
+            .unwrap()
+            .count(item_id)
+    }
+    //endregion
+
+    //region Admin
+    pub async fn make_admin(&self, email: &str) {
+        sqlx::query!("UPDATE accounts SET roles = '{player,admin}' WHERE email = $1", email)
+            .execute(&self.db_pool)
+            .await
+            .expect("Failed to make account an admin");
+    }
+
+    pub async fn make_moderator(&self, email: &str) {
+        sqlx::query!("UPDATE accounts SET roles = '{player,moderator}' WHERE email = $1", email)
+            .execute(&self.db_pool)
+            .await
+            .expect("Failed to make account a moderator");
+    }
+
+    pub async fn get_admin_page(&self, page: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/{}", &self.address, page))
+            .send()
+            .await
+            .expect("Failed to execute request to get admin page")
+    }
+
+    pub async fn post_admin<Body>(&self, page: &str, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}/admin/{}", &self.address, page))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to post admin form")
+    }
+    //endregion
+
+    //region Combat
+    pub async fn get_combat_history_page(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/combat", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request to get combat history")
+    }
+
+    pub async fn get_combat_log_page(&self, encounter_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/combat/{}", &self.address, encounter_id))
+            .send()
+            .await
+            .expect("Failed to execute request to get combat log")
+    }
+    //endregion
+
+    //region Quests
+    pub async fn get_quests_page(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/quests", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request to get quests page")
+    }
+
+    /// `action` is one of accept, abandon or complete
+    pub async fn post_quest(&self, quest_id: &str, action: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/quests/{}/{}", &self.address, quest_id, action))
+            .send()
+            .await
+            .expect("Failed to post quest action")
+    }
+
+    /// Marks quests as done without playing them, for getting past prerequisites
+    pub async fn complete_quests(&self, character_id: Uuid, quest_ids: &[&str]) {
+        for quest_id in quest_ids {
+            sqlx::query!(
+                r#"
+                INSERT INTO character_quests (character_id, quest_id, status, progress, completions, completed_at)
+                VALUES ($1, $2, 'completed', '{}', 1, now())
+                "#,
+                character_id,
+                quest_id
+            )
+                .execute(&self.db_pool)
+                .await
+                .expect("Failed to complete quest");
+        }
+    }
+    //endregion
+
+    //region Leaderboards
+    pub async fn get_leaderboard_page(&self, path: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/leaderboards{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request to get leaderboard page")
+    }
+
+    /// Gives an existing character a level, `progress` experience into it and a PvP record without
+    /// playing for them
+    pub async fn set_standing(&self, character_id: Uuid, level: i32, progress: i64, rating: Option<i32>) {
+        let experience = experience_for_level(level) + progress;
+        sqlx::query!("UPDATE characters SET level = $2, experience = $3 WHERE id = $1", character_id, level, experience)
+            .execute(&self.db_pool)
+            .await
+            .expect("Failed to set character level");
+        if let Some(rating) = rating {
+            sqlx::query!(
+                "INSERT INTO character_ratings (character_id, rating, games) VALUES ($1, $2, 1)",
+                character_id,
+                rating
+            )
+                .execute(&self.db_pool)
+                .await
+                .expect("Failed to set character rating");
+        }
+    }
+    //endregion
+
+    //region Guilds
+    /// Registers and logs in a player with a profile, returns the email to log back in with
+    pub async fn register_player(&self, display_name: &str) -> String {
+        let email = self.register_and_login().await;
+        self.post_profile(&serde_json::json!({
+            "display_name": display_name,
+            "avatar": "knight",
+            "bio": "",
+            "locale": "en"
+        })).await;
+        email
+    }
+
+    pub async fn login_as(&self, email: &str) {
+        self.post_login(&serde_json::json!({ "email": email, "password": TEST_PASSWORD })).await;
+    }
+
+    /// `path` is `guilds` or `guild`
+    pub async fn get_guild_page(&self, path: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request to get guild page")
+    }
+
+    pub async fn get_guild_page_html(&self, path: &str) -> String {
+        self.get_guild_page(path).await.text().await.unwrap()
+    }
+
+    pub async fn post_guild<Body>(&self, path: &str, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}/{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to post guild form")
+    }
+
+    pub async fn guild_id(&self, name: &str) -> Uuid {
+        sqlx::query!("SELECT id FROM guilds WHERE lower(name) = lower($1)", name)
+            .fetch_one(&self.db_pool)
+            .await
+            .expect("Failed to fetch guild id")
+            .id
+    }
+    //endregion
+
+    //region Friends
+    pub async fn get_friends_page_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/friends", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request to get friends page")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// `action` is empty to send a request, otherwise `accept`, `decline`, `cancel`, `remove`, `block` or `unblock`
+    pub async fn post_friends(&self, action: &str, display_name: &str) -> reqwest::Response {
+        let url = match action {
+            "" => format!("{}/friends", &self.address),
+            action => format!("{}/friends/{}", &self.address, action),
+        };
+        self.api_client
+            .post(url)
+            .form(&serde_json::json!({ "display_name": display_name }))
+            .send()
+            .await
+            .expect("Failed to post friends form")
+    }
+    //endregion
+
+    //region Mail
+    /// Logs in as `email` and picks the character, mailboxes belong to characters
+    pub async fn play_as(&self, email: &str, character_id: Uuid) {
+        self.login_as(email).await;
+        self.post_character_action(character_id, "select").await;
+    }
+
+    pub async fn give_gold(&self, character_id: Uuid, amount: i64) {
+        let reward = Transfer::new(
+            LedgerAccount::System(SystemAccount::Rewards),
+            LedgerAccount::Character(character_id),
+            Currency::Gold,
+            amount,
+            "test money",
+        );
+        transfer_and_commit(&self.db_pool, &reward).await.expect("Failed to give gold");
+    }
+
+    pub async fn gold(&self, character_id: Uuid) -> i64 {
+        get_balance(&self.db_pool, character_id, Currency::Gold).await.unwrap()
+    }
+
+    /// `path` is `mail` or `mail/<id>`
+    pub async fn get_mail_page(&self, path: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request to get mail page")
+    }
+
+    pub async fn get_mail_page_html(&self, path: &str) -> String {
+        self.get_mail_page(path).await.text().await.unwrap()
+    }
+
+    pub async fn post_mail<Body>(&self, path: &str, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}/{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to post mail form")
+    }
+
+    /// Newest first
+    pub async fn mail_ids(&self, character_id: Uuid) -> Vec<Uuid> {
+        sqlx::query!("SELECT id FROM mail WHERE recipient_id = $1 ORDER BY created_at DESC", character_id)
+            .fetch_all(&self.db_pool)
+            .await
+            .expect("Failed to fetch mail")
+            .into_iter()
+            .map(|r| r.id)
+            .collect()
+    }
+    //endregion
+
+    //region Auctions
+    /// `query` is appended as is, like `?q=potion`
+    pub async fn get_auctions_page_html(&self, query: &str) -> String {
+        self.api_client
+            .get(format!("{}/auctions{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request to get auction house page")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// `path` is `auctions` or `auctions/<id>/<action>`
+    pub async fn post_auctions<Body>(&self, path: &str, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}/{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to post auction house form")
+    }
+
+    /// The newest listing of a seller
+    pub async fn listing_id(&self, seller_id: Uuid) -> Uuid {
+        sqlx::query!(
+            "SELECT id FROM auction_listings WHERE seller_id = $1 ORDER BY created_at DESC LIMIT 1",
+            seller_id
+        )
+            .fetch_one(&self.db_pool)
+            .await
+            .expect("Failed to fetch listing")
+            .id
+    }
+
+    /// For calling services directly, as the web handlers would with the active character
+    pub async fn character(&self, character_id: Uuid) -> Character {
+        let user_id = sqlx::query!("SELECT user_id FROM characters WHERE id = $1", character_id)
+            .fetch_one(&self.db_pool)
+            .await
+            .expect("Failed to fetch character owner")
+            .user_id;
+        get_character(&self.db_pool, user_id, character_id)
+            .await
+            .unwrap()
+            .expect("Character not found")
+    }
+    //endregion
+
+    //region Crafting
+    pub async fn get_crafting_page_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/crafting", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request to get crafting page")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// `path` is `crafting` or `crafting/<id>/cancel`
+    pub async fn post_crafting<Body>(&self, path: &str, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}/{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to post crafting form")
+    }
+
+    pub async fn set_crafting_experience(&self, character_id: Uuid, skill: &str, experience: i64) {
+        sqlx::query!(
+            r#"
+            INSERT INTO character_crafting_skills (character_id, skill, experience) VALUES ($1, $2, $3)
+            ON CONFLICT (character_id, skill) DO UPDATE SET experience = excluded.experience
+            "#,
+            character_id,
+            skill,
+            experience
+        )
+            .execute(&self.db_pool)
+            .await
+            .expect("Failed to set crafting experience");
+    }
+    //endregion
+
+    //region Achievements
+    /// `subject` is empty for the total
+    pub async fn set_statistic(&self, character_id: Uuid, statistic: &str, subject: &str, value: i64) {
+        sqlx::query!(
+            r#"
+            INSERT INTO character_statistics (character_id, statistic, subject, value) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (character_id, statistic, subject) DO UPDATE SET value = excluded.value
+            "#,
+            character_id,
+            statistic,
+            subject,
+            value
+        )
+            .execute(&self.db_pool)
+            .await
+            .expect("Failed to set statistic");
+    }
+
+    pub async fn post_character_title(&self, character_id: Uuid, achievement_id: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/characters/{}/title", &self.address, character_id))
+            .form(&serde_json::json!({ "achievement_id": achievement_id }))
+            .send()
+            .await
+            .expect("Failed to post character title")
+    }
+    //endregion
+
+    //region Matchmaking
+    pub async fn get_matchmaking_page_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/matchmaking", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request to get matchmaking page")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// `path` is `matchmaking`, `matchmaking/leave` or `matchmaking/<id>/ready`
+    pub async fn post_matchmaking<Body>(&self, path: &str, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize
+    {
+        self.api_client
+            .post(format!("{}/{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to post matchmaking form")
+    }
+    //endregion
+
+    //region Gateway
+    /// Opens `/ws` with the session cookie of whoever is logged in on `api_client`
+    pub async fn connect_ws(&self) -> Result<WsStream, tokio_tungstenite::tungstenite::Error> {
+        let mut request = format!("ws://127.0.0.1:{}/ws", self.port).into_client_request()?;
+        let url = reqwest::Url::parse(&self.address).unwrap();
+        if let Some(cookies) = self.cookie_jar.cookies(&url) {
+            request.headers_mut().insert("Cookie", cookies.to_str().unwrap().parse().unwrap());
+        }
+        let (stream, _) = tokio_tungstenite::connect_async(request).await?;
+        Ok(stream)
+    }
+
+    /// Registers and logs in a player with a profile, then connects them past the welcome message.
+    /// The client stays logged in as this player until the next login.
+    pub async fn connect_player(&self, display_name: &str) -> (UserId, WsStream) {
+        let email = self.register_player(display_name).await;
+        let mut ws = self.connect_ws().await.expect("Failed to connect to the gateway");
+        assert_eq!("welcome", next_ws_json(&mut ws).await["type"]);
+        (self.user_id(&email).await, ws)
+    }
+
+    /// Registers a player with a fresh character and connects them, returns the `zone_entered` message
+    pub async fn enter_world(&self, character_name: &str) -> (UserId, WsStream, serde_json::Value) {
+        self.enter_world_at(character_name, None).await
+    }
+
+    /// Same as `enter_world`, but with the character saved at `(zone, x, y)` beforehand
+    pub async fn enter_world_at(
+        &self,
+        character_name: &str,
+        position: Option<(&str, i32, i32)>,
+    ) -> (UserId, WsStream, serde_json::Value) {
+        let email = self.register_and_login().await;
+        self.create_character(character_name, "warrior").await;
+        if let Some((zone_id, x, y)) = position {
+            let character_id = self.character_id(character_name).await;
+            sqlx::query!(
+                "INSERT INTO character_positions (character_id, zone_id, x, y) VALUES ($1, $2, $3, $4)",
+                character_id,
+                zone_id,
+                x,
+                y
+            )
+                .execute(&self.db_pool)
+                .await
+                .expect("Failed to place character");
+        }
+        let mut ws = self.connect_ws().await.expect("Failed to connect to the gateway");
+        assert_eq!("welcome", next_ws_json(&mut ws).await["type"]);
+        let zone_entered = next_ws_json(&mut ws).await;
+        assert_eq!("zone_entered", zone_entered["type"]);
+        (self.user_id(&email).await, ws, zone_entered)
+    }
+
+    pub async fn user_id(&self, email: &str) -> UserId {
+        let id: Uuid = sqlx::query!("SELECT user_id FROM accounts WHERE email = $1", email)
+            .fetch_one(&self.db_pool)
+            .await
+            .expect("Failed to fetch user id")
+            .user_id;
+        UserId::from(id)
+    }
+    //endregion
+
     //region Login
     pub async fn get_login_page(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/login", &self.address))
+            .get(format!("{}/login", &self.address))
             .send()
             .await
             .expect("Failed to execute request to get login page")
@@ -34,7 +690,7 @@ impl TestApp {
     pub async fn post_registration(&self, body: String) -> reqwest::Response
     {
         self.api_client
-            .post(&format!("{}/register", &self.address))
+            .post(format!("{}/register", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body)
             .send()
@@ -44,7 +700,7 @@ impl TestApp {
 
     pub async fn get_registraion_page(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/register", &self.address))
+            .get(format!("{}/register", &self.address))
             .send()
             .await
             .expect("Failed to execute request to get login page")
@@ -55,12 +711,28 @@ impl TestApp {
     }
     //endregion
 
+    /// Registers a fresh account and logs the client in with it, returns the email used
+    pub async fn register_and_login(&self) -> String {
+        let email: String = SafeEmail().fake();
+        self.post_registration(format!(
+            "email={}&password={}&password_check={}",
+            email, TEST_PASSWORD, TEST_PASSWORD
+        )).await;
+        self.post_login(&serde_json::json!(
+            {
+                "email": email,
+                "password": TEST_PASSWORD
+            }
+        )).await;
+        email
+    }
+
     pub async fn post_login<Body>(&self, body: &Body) -> reqwest::Response
         where
             Body: serde::Serialize
     {
         self.api_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .form(body)
             .send()
             .await
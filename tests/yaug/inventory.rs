@@ -0,0 +1,159 @@
+use std::sync::Arc;
+use claim::assert_err;
+use yaug::items::InventoryOwner;
+use crate::helpers::{assert_is_redirected_to, spawn_test_app, TestApp};
+
+async fn app_with_character() -> (TestApp, uuid::Uuid) {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.create_character("Hoarder", "warrior").await;
+    let id = app.character_id("Hoarder").await;
+    (app, id)
+}
+
+async fn stored_count(app: &TestApp, item_id: &str) -> i64 {
+    sqlx::query!(
+        r#"SELECT coalesce(sum(quantity), 0) as "count!" FROM inventory_items WHERE item_id = $1"#,
+        item_id
+    )
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count
+}
+
+#[tokio::test]
+async fn inventory_needs_an_active_character() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+
+    let response = app.get_inventory_page().await;
+
+    assert_is_redirected_to(&response, "/characters");
+}
+
+#[tokio::test]
+async fn items_are_stacked_and_shown() {
+    let (app, id) = app_with_character().await;
+
+    app.give_items(id, "iron_ore", 60).await;
+    app.give_items(id, "rusty_sword", 1).await;
+
+    let rows = sqlx::query!("SELECT slot, item_id, quantity FROM inventory_items ORDER BY slot")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    let rows: Vec<(i32, &str, i32)> = rows.iter().map(|r| (r.slot, r.item_id.as_str(), r.quantity)).collect();
+    assert_eq!(vec![(0, "iron_ore", 50), (1, "iron_ore", 10), (2, "rusty_sword", 1)], rows);
+
+    let html = app.get_inventory_page().await.text().await.unwrap();
+    assert!(html.contains("Iron Ore x50"));
+    assert!(html.contains("Rusty Sword"));
+}
+
+#[tokio::test]
+async fn unknown_items_are_rejected() {
+    let (app, id) = app_with_character().await;
+    let mut tx = app.db_pool.begin().await.unwrap();
+
+    assert_err!(app.inventory_service().add_items(&mut tx, id, "excalibur", 1).await);
+}
+
+#[tokio::test]
+async fn moving_stacks_merges_them() {
+    let (app, id) = app_with_character().await;
+    app.give_items(id, "iron_ore", 60).await;
+
+    let response = app.post_inventory("move", &serde_json::json!({ "from": 1, "to": 0 })).await;
+    assert_is_redirected_to(&response, "/inventory");
+
+    let response = app.post_inventory("move", &serde_json::json!({ "from": 5, "to": 0 })).await;
+    assert_is_redirected_to(&response, "/inventory");
+    let html = app.get_inventory_page().await.text().await.unwrap();
+    assert!(html.contains("Slot 5 is empty"));
+    assert_eq!(60, stored_count(&app, "iron_ore").await);
+}
+
+#[tokio::test]
+async fn concurrent_removals_cannot_take_more_than_there_is() {
+    let (app, id) = app_with_character().await;
+    app.give_items(id, "iron_ore", 10).await;
+    let service = Arc::new(app.inventory_service());
+
+    let tasks: Vec<_> = (0..5)
+        .map(|_| {
+            let pool = app.db_pool.clone();
+            let service = service.clone();
+            tokio::spawn(async move {
+                let mut tx = pool.begin().await.unwrap();
+                service.remove_items(&mut tx, id, "iron_ore", 3).await?;
+                tx.commit().await.unwrap();
+                Ok::<_, yaug::items::InventoryError>(())
+            })
+        })
+        .collect();
+
+    let mut succeeded = 0;
+    for task in tasks {
+        if task.await.unwrap().is_ok() {
+            succeeded += 1;
+        }
+    }
+    assert_eq!(3, succeeded);
+    assert_eq!(1, stored_count(&app, "iron_ore").await);
+}
+
+#[tokio::test]
+async fn concurrent_additions_are_all_kept() {
+    let (app, id) = app_with_character().await;
+    let service = Arc::new(app.inventory_service());
+
+    let tasks: Vec<_> = (0..10)
+        .map(|_| {
+            let pool = app.db_pool.clone();
+            let service = service.clone();
+            tokio::spawn(async move {
+                let mut tx = pool.begin().await.unwrap();
+                service.add_items(&mut tx, id, "iron_ore", 7).await.unwrap();
+                tx.commit().await.unwrap();
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert_eq!(70, stored_count(&app, "iron_ore").await);
+}
+
+#[tokio::test]
+async fn opposite_transfers_between_characters_do_not_deadlock() {
+    let (app, hoarder) = app_with_character().await;
+    app.create_character("Trader", "mage").await;
+    let trader = app.character_id("Trader").await;
+    app.give_items(hoarder, "iron_ore", 20).await;
+    app.give_items(trader, "flour", 20).await;
+    let service = Arc::new(app.inventory_service());
+
+    let tasks: Vec<_> = (0..10)
+        .map(|i| {
+            let pool = app.db_pool.clone();
+            let service = service.clone();
+            let (from, to) = if i % 2 == 0 { (hoarder, trader) } else { (trader, hoarder) };
+            tokio::spawn(async move {
+                let mut tx = pool.begin().await.unwrap();
+                service
+                    .transfer_stack(&mut tx, InventoryOwner::Character(from), 0, 1, InventoryOwner::Character(to))
+                    .await
+                    .unwrap();
+                tx.commit().await.unwrap();
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert_eq!(20, stored_count(&app, "iron_ore").await);
+    assert_eq!(20, stored_count(&app, "flour").await);
+}
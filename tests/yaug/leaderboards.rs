@@ -0,0 +1,250 @@
+use uuid::Uuid;
+use yaug::characters::{add_experience, experience_for_level, MAX_LEVEL};
+use yaug::leaderboards::{Leaderboard, RankedEntry};
+use yaug::ledger::{transfer_and_commit, Currency, LedgerAccount, SystemAccount, Transfer};
+use crate::helpers::{assert_is_redirected_to, spawn_test_app, TestApp};
+
+/// Puts `count` characters named Hero1, Hero2, ... on the account behind `email`, Hero`n` at level `n`.
+/// Goes around the character limit, boards need more characters than one account may have.
+async fn insert_heroes(app: &TestApp, email: &str, count: i32) -> Vec<Uuid> {
+    let user_id = sqlx::query!("SELECT user_id FROM accounts WHERE email = $1", email)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .user_id;
+    let mut ids = Vec::new();
+    for n in 1..=count {
+        let id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO characters (id, user_id, name, class, level, experience, strength, dexterity, intelligence, vitality, created_at)
+            VALUES ($1, $2, $3, 'warrior', $4, $5, 8, 5, 3, 8, now())
+            "#,
+            id,
+            user_id,
+            format!("Hero{}", n),
+            n,
+            experience_for_level(n)
+        )
+            .execute(&app.db_pool)
+            .await
+            .expect("Failed to insert character");
+        ids.push(id);
+    }
+    ids
+}
+
+async fn give_gold(app: &TestApp, character_id: Uuid, amount: i64) {
+    let transfer = Transfer::new(
+        LedgerAccount::System(SystemAccount::Rewards),
+        LedgerAccount::Character(character_id),
+        Currency::Gold,
+        amount,
+        "test reward",
+    );
+    transfer_and_commit(&app.db_pool, &transfer).await.unwrap();
+}
+
+fn names(entries: &[RankedEntry]) -> Vec<(usize, &str)> {
+    entries.iter().map(|e| (e.rank, e.character_name.as_str())).collect()
+}
+
+#[tokio::test]
+async fn boards_rank_the_best_first_and_are_public() {
+    let mut app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.create_character("Tomas", "warrior").await;
+    app.create_character("Ilse", "mage").await;
+    app.create_character("Bram", "rogue").await;
+    app.set_standing(app.character_id("Tomas").await, 3, 100, None).await;
+    app.set_standing(app.character_id("Ilse").await, 3, 250, None).await;
+    app.set_standing(app.character_id("Bram").await, 5, 0, None).await;
+    app.game_loop.tick().await;
+
+    let page = app.leaderboards.top(Leaderboard::Level, 1).await.unwrap();
+    assert_eq!(3, page.total);
+    assert_eq!(vec![(1, "Bram"), (2, "Ilse"), (3, "Tomas")], names(&page.entries));
+
+    // no session cookie, the boards are for everyone
+    let html = reqwest::get(format!("{}/leaderboards/level", app.address)).await.unwrap().text().await.unwrap();
+    assert!(html.contains("Level 5 (2000 xp)"));
+    assert!(html.find("Bram").unwrap() < html.find("Ilse").unwrap());
+    assert!(html.find("Ilse").unwrap() < html.find("Tomas").unwrap());
+    let html = app.get_leaderboard_page("/level?page=2").await.text().await.unwrap();
+    assert!(html.contains("There are only 3 characters on this leaderboard"));
+}
+
+#[tokio::test]
+async fn boards_only_change_when_they_are_refreshed() {
+    let mut app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.create_character("Tomas", "warrior").await;
+    app.create_character("Ilse", "mage").await;
+    let tomas = app.character_id("Tomas").await;
+    app.set_standing(tomas, 2, 0, None).await;
+    app.game_loop.tick().await;
+
+    app.set_standing(app.character_id("Ilse").await, 4, 0, None).await;
+    let page = app.leaderboards.top(Leaderboard::Level, 1).await.unwrap();
+    assert_eq!(vec![(1, "Tomas"), (2, "Ilse")], names(&page.entries));
+
+    app.leaderboards.refresh_all().await.unwrap();
+    let page = app.leaderboards.top(Leaderboard::Level, 1).await.unwrap();
+    assert_eq!(vec![(1, "Ilse"), (2, "Tomas")], names(&page.entries));
+}
+
+#[tokio::test]
+async fn levelling_up_moves_a_character_up_the_board() {
+    let mut app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.create_character("Tomas", "warrior").await;
+    app.create_character("Ilse", "mage").await;
+    app.set_standing(app.character_id("Ilse").await, 2, 50, None).await;
+    app.game_loop.tick().await;
+    let page = app.leaderboards.top(Leaderboard::Level, 1).await.unwrap();
+    assert_eq!(vec![(1, "Ilse"), (2, "Tomas")], names(&page.entries));
+
+    let tomas = app.character_id("Tomas").await;
+    let mut tx = app.db_pool.begin().await.unwrap();
+    assert_eq!(3, add_experience(&mut tx, tomas, experience_for_level(3)).await.unwrap());
+    tx.commit().await.unwrap();
+    app.leaderboards.refresh_all().await.unwrap();
+
+    let page = app.leaderboards.top(Leaderboard::Level, 1).await.unwrap();
+    assert_eq!(vec![(1, "Tomas"), (2, "Ilse")], names(&page.entries));
+    let html = app.get_leaderboard_page("/level").await.text().await.unwrap();
+    assert!(html.contains("Level 3 (600 xp)"));
+
+    // past the last level more experience still counts
+    let ilse = app.character_id("Ilse").await;
+    let mut tx = app.db_pool.begin().await.unwrap();
+    add_experience(&mut tx, tomas, experience_for_level(MAX_LEVEL)).await.unwrap();
+    add_experience(&mut tx, ilse, experience_for_level(MAX_LEVEL) + 1000).await.unwrap();
+    tx.commit().await.unwrap();
+    app.leaderboards.refresh_all().await.unwrap();
+    let page = app.leaderboards.top(Leaderboard::Level, 1).await.unwrap();
+    assert_eq!(vec![(1, "Ilse"), (2, "Tomas")], names(&page.entries));
+}
+
+#[tokio::test]
+async fn neighbours_are_shown_around_a_character() {
+    let mut app = spawn_test_app().await;
+    let email = app.register_and_login().await;
+    let heroes = insert_heroes(&app, &email, 12).await;
+    app.game_loop.tick().await;
+
+    // Hero6 is 7th of 12, five above and the five below it
+    let around = app.leaderboards.around(Leaderboard::Level, heroes[5]).await.unwrap().unwrap();
+    assert_eq!((2..=12).collect::<Vec<usize>>(), around.iter().map(|e| e.rank).collect::<Vec<_>>());
+    assert_eq!("Hero6", around[5].character_name);
+    // at the top there is nobody above
+    let around = app.leaderboards.around(Leaderboard::Level, heroes[11]).await.unwrap().unwrap();
+    assert_eq!(vec![(1, "Hero12"), (2, "Hero11")], names(&around[..2]));
+    assert_eq!(6, around.len());
+
+    let html = app.get_leaderboard_page("/level?around=hero6").await.text().await.unwrap();
+    assert!(html.contains("Around Hero6"));
+    let html = app.get_leaderboard_page("/level?around=Nobody").await.text().await.unwrap();
+    assert!(html.contains("There is no character called Nobody"));
+    let html = app.get_leaderboard_page("/pvp?around=Hero6").await.text().await.unwrap();
+    assert!(html.contains("Hero6 is not on this leaderboard"));
+}
+
+#[tokio::test]
+async fn the_active_character_is_shown_without_asking() {
+    let mut app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.create_character("Tomas", "warrior").await;
+    app.game_loop.tick().await;
+
+    let html = app.get_leaderboard_page("/level").await.text().await.unwrap();
+
+    assert!(html.contains("Around Tomas"));
+}
+
+#[tokio::test]
+async fn wealth_and_pvp_boards_only_rank_characters_with_gold_or_matches() {
+    let mut app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.create_character("Tomas", "warrior").await;
+    app.create_character("Ilse", "mage").await;
+    app.create_character("Bram", "rogue").await;
+    let tomas = app.character_id("Tomas").await;
+    let ilse = app.character_id("Ilse").await;
+    give_gold(&app, tomas, 40).await;
+    give_gold(&app, ilse, 75).await;
+    app.set_standing(tomas, 1, 0, Some(1620)).await;
+    app.game_loop.tick().await;
+
+    let wealth = app.leaderboards.top(Leaderboard::Wealth, 1).await.unwrap();
+    let pvp = app.leaderboards.top(Leaderboard::PvpRating, 1).await.unwrap();
+
+    assert_eq!(vec![(1, "Ilse"), (2, "Tomas")], names(&wealth.entries));
+    assert_eq!(vec![(1, "Tomas")], names(&pvp.entries));
+    let html = app.get_leaderboard_page("/wealth").await.text().await.unwrap();
+    assert!(html.contains("75 gold"));
+    let html = app.get_leaderboard_page("/pvp").await.text().await.unwrap();
+    assert!(html.contains("1620"));
+}
+
+#[tokio::test]
+async fn ending_a_season_keeps_the_top_and_starts_pvp_over() {
+    let mut app = spawn_test_app().await;
+    let email = app.register_and_login().await;
+    app.make_admin(&email).await;
+    app.create_character("Tomas", "warrior").await;
+    let tomas = app.character_id("Tomas").await;
+    app.set_standing(tomas, 4, 10, Some(1710)).await;
+    give_gold(&app, tomas, 12).await;
+    app.game_loop.tick().await;
+
+    let response = app.post_admin("leaderboards/season", &serde_json::json!({})).await;
+    assert_is_redirected_to(&response, "/admin/leaderboards");
+    let html = app.get_admin_page("leaderboards").await.text().await.unwrap();
+    assert!(html.contains("Season 2 has started"));
+
+    let html = app.get_leaderboard_page("/seasons/1").await.text().await.unwrap();
+    assert!(html.contains("Level 4 (1210 xp)"));
+    assert!(html.contains("1710"));
+    assert!(html.contains("12 gold"));
+    let rating = sqlx::query!("SELECT rating, games FROM character_ratings WHERE character_id = $1", tomas)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!((1500, 0), (rating.rating, rating.games));
+    // PvP starts empty, levels are kept
+    assert!(app.leaderboards.top(Leaderboard::PvpRating, 1).await.unwrap().entries.is_empty());
+    assert_eq!(1, app.leaderboards.top(Leaderboard::Level, 1).await.unwrap().total);
+    let audit = app.get_admin_page("audit").await.text().await.unwrap();
+    assert!(audit.contains("leaderboards.season_ended"));
+    let html = app.get_leaderboard_page("").await.text().await.unwrap();
+    assert!(html.contains("/leaderboards/seasons/1"));
+    assert!(!html.contains("/leaderboards/seasons/2"));
+}
+
+#[tokio::test]
+async fn only_admins_can_end_seasons() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+
+    assert_eq!(403, app.get_admin_page("leaderboards").await.status().as_u16());
+    let response = app.post_admin("leaderboards/season", &serde_json::json!({})).await;
+
+    assert_eq!(403, response.status().as_u16());
+    let running = sqlx::query!("SELECT count(*) AS \"count!\" FROM leaderboard_seasons WHERE ended_at IS NULL")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(1, running.count);
+}
+
+#[tokio::test]
+async fn unknown_boards_and_seasons_are_not_found() {
+    let app = spawn_test_app().await;
+
+    assert_eq!(404, app.get_leaderboard_page("/fishing").await.status().as_u16());
+    assert_eq!(404, app.get_leaderboard_page("/seasons/7").await.status().as_u16());
+    // the running season has nothing to show until it ends
+    assert_eq!(404, app.get_leaderboard_page("/seasons/1").await.status().as_u16());
+    assert_eq!(200, app.get_leaderboard_page("").await.status().as_u16());
+}
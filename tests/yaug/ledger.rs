@@ -0,0 +1,161 @@
+use claim::{assert_err, assert_matches};
+use uuid::Uuid;
+use yaug::ledger::{get_balance, reconcile, transfer_and_commit, Currency, LedgerAccount, LedgerError, SystemAccount, Transfer, TransferOutcome};
+use crate::helpers::{spawn_test_app, TestApp};
+
+async fn app_with_character(name: &str) -> (TestApp, Uuid) {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.create_character(name, "rogue").await;
+    let id = app.character_id(name).await;
+    (app, id)
+}
+
+fn reward(character_id: Uuid, amount: i64) -> Transfer {
+    Transfer::new(
+        LedgerAccount::System(SystemAccount::Rewards),
+        LedgerAccount::Character(character_id),
+        Currency::Gold,
+        amount,
+        "test reward",
+    )
+}
+
+async fn gold(app: &TestApp, character_id: Uuid) -> i64 {
+    get_balance(&app.db_pool, character_id, Currency::Gold).await.unwrap()
+}
+
+#[tokio::test]
+async fn rewards_credit_the_character_and_keep_the_ledger_balanced() {
+    let (app, id) = app_with_character("Midas").await;
+
+    transfer_and_commit(&app.db_pool, &reward(id, 150)).await.unwrap();
+
+    assert_eq!(150, gold(&app, id).await);
+    let report = reconcile(&app.db_pool).await.unwrap();
+    assert!(report.is_consistent());
+    assert_eq!(Some(&0), report.currency_totals.get(&Currency::Gold));
+}
+
+#[tokio::test]
+async fn characters_cannot_spend_more_than_they_have() {
+    let (app, id) = app_with_character("Pauper").await;
+    transfer_and_commit(&app.db_pool, &reward(id, 10)).await.unwrap();
+
+    let spend = Transfer::new(
+        LedgerAccount::Character(id),
+        LedgerAccount::System(SystemAccount::Vendors),
+        Currency::Gold,
+        11,
+        "overpriced sword",
+    );
+    let outcome = transfer_and_commit(&app.db_pool, &spend).await;
+
+    assert_matches!(outcome, Err(LedgerError::InsufficientFunds { available: 10, .. }));
+    assert_eq!(10, gold(&app, id).await);
+}
+
+#[tokio::test]
+async fn retried_transfers_are_applied_once() {
+    let (app, id) = app_with_character("Retry").await;
+    let transfer = reward(id, 25).idempotency_key("quest-1-reward");
+
+    let first = transfer_and_commit(&app.db_pool, &transfer).await.unwrap();
+    let second = transfer_and_commit(&app.db_pool, &transfer).await.unwrap();
+
+    assert_matches!(first, TransferOutcome::Applied(_));
+    assert_eq!(TransferOutcome::AlreadyApplied(first.transfer_id()), second);
+    assert_eq!(25, gold(&app, id).await);
+
+    let different = reward(id, 30).idempotency_key("quest-1-reward");
+    assert_matches!(
+        transfer_and_commit(&app.db_pool, &different).await,
+        Err(LedgerError::IdempotencyConflict(_))
+    );
+}
+
+#[tokio::test]
+async fn idempotency_keys_belong_to_the_debiting_account() {
+    let (app, id) = app_with_character("Payer").await;
+    app.create_character("Payee", "rogue").await;
+    let other = app.character_id("Payee").await;
+    transfer_and_commit(&app.db_pool, &reward(id, 50).idempotency_key("payday")).await.unwrap();
+
+    // the same key from the same account to someone else is not a retry
+    assert_matches!(
+        transfer_and_commit(&app.db_pool, &reward(other, 50).idempotency_key("payday")).await,
+        Err(LedgerError::IdempotencyConflict(_))
+    );
+    assert_eq!(0, gold(&app, other).await);
+
+    // another account picking the same key is unrelated
+    let tip = Transfer::new(LedgerAccount::Character(id), LedgerAccount::Character(other), Currency::Gold, 20, "tip")
+        .idempotency_key("payday");
+    assert_matches!(transfer_and_commit(&app.db_pool, &tip).await, Ok(TransferOutcome::Applied(_)));
+    assert_eq!(30, gold(&app, id).await);
+    assert_eq!(20, gold(&app, other).await);
+}
+
+#[tokio::test]
+async fn concurrent_spending_cannot_overdraw() {
+    let (app, id) = app_with_character("Spender").await;
+    transfer_and_commit(&app.db_pool, &reward(id, 100)).await.unwrap();
+
+    let tasks: Vec<_> = (0..10)
+        .map(|_| {
+            let pool = app.db_pool.clone();
+            tokio::spawn(async move {
+                let spend = Transfer::new(
+                    LedgerAccount::Character(id),
+                    LedgerAccount::System(SystemAccount::Fees),
+                    Currency::Gold,
+                    30,
+                    "repair",
+                );
+                transfer_and_commit(&pool, &spend).await
+            })
+        })
+        .collect();
+
+    let mut succeeded = 0;
+    for task in tasks {
+        if task.await.unwrap().is_ok() {
+            succeeded += 1;
+        }
+    }
+    assert_eq!(3, succeeded);
+    assert_eq!(10, gold(&app, id).await);
+    assert!(reconcile(&app.db_pool).await.unwrap().is_consistent());
+}
+
+#[tokio::test]
+async fn ledger_entries_cannot_be_changed() {
+    let (app, id) = app_with_character("Forger").await;
+    transfer_and_commit(&app.db_pool, &reward(id, 5)).await.unwrap();
+
+    assert_err!(sqlx::query!("UPDATE ledger_entries SET amount = 5000").execute(&app.db_pool).await);
+    assert_err!(sqlx::query!("DELETE FROM ledger_transfers").execute(&app.db_pool).await);
+}
+
+#[tokio::test]
+async fn tampered_balance_shows_up_in_the_admin_report() {
+    let app = spawn_test_app().await;
+    let email = app.register_and_login().await;
+    app.create_character("Tamper", "rogue").await;
+    let id = app.character_id("Tamper").await;
+    transfer_and_commit(&app.db_pool, &reward(id, 5)).await.unwrap();
+
+    assert_eq!(403, app.get_admin_page("ledger").await.status().as_u16());
+
+    app.make_admin(&email).await;
+    let html = app.get_admin_page("ledger").await.text().await.unwrap();
+    assert!(html.contains("The ledger is consistent"));
+
+    sqlx::query!("UPDATE ledger_accounts SET balance = 5000 WHERE character_id = $1", id)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let html = app.get_admin_page("ledger").await.text().await.unwrap();
+    assert!(html.contains("The ledger is NOT consistent"));
+    assert!(html.contains("mismatch"));
+}
@@ -1,5 +1,7 @@
+use fake::Fake;
+use fake::faker::internet::en::SafeEmail;
 use uuid::Uuid;
-use crate::helpers::{assert_is_redirected_to, spawn_test_app};
+use crate::helpers::{assert_is_redirected_to, spawn_test_app, TEST_PASSWORD};
 
 #[tokio::test]
 async fn protected_page_redirects_to_login_with_message() {
@@ -71,5 +73,36 @@ async fn malformed_credentials_are_rejected() {
 
 #[tokio::test]
 async fn valid_credentials_redirect_to_account_home() {
-    todo!()
+    let app = spawn_test_app().await;
+    let email: String = SafeEmail().fake();
+    let password = TEST_PASSWORD;
+    app.post_registration(format!(
+        "email={}&password={}&password_check={}",
+        email, password, password
+    )).await;
+
+    let response = app.post_login(&serde_json::json!(
+        {
+            "email": email,
+            "password": password
+        }
+    )).await;
+    assert_is_redirected_to(&response, "/account");
+
+    let html = app.get_account_home_html().await;
+    assert!(html.contains(&email));
+    assert!(html.contains("Status: pending"));
+}
+
+#[tokio::test]
+async fn account_home_shows_the_login_before_this_one() {
+    let app = spawn_test_app().await;
+    let email = app.register_and_login().await;
+    assert!(app.get_account_home_html().await.contains("Last login: never"));
+
+    app.login_as(&email).await;
+
+    let html = app.get_account_home_html().await;
+    assert!(!html.contains("Last login: never"));
+    assert!(html.contains("Last login: 2"));
 }
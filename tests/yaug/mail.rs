@@ -0,0 +1,242 @@
+use chrono::{Duration, Utc};
+use tokio::sync::broadcast::Receiver;
+use uuid::Uuid;
+use yaug::events::GameEvent;
+use yaug::ledger::reconcile;
+use crate::helpers::{assert_is_redirected_to, spawn_test_app, TestApp};
+
+const POTION: &str = "minor_healing_potion";
+
+async fn send(app: &TestApp, to: &str, extra: &[(&str, &str)]) -> reqwest::Response {
+    let mut form = vec![("to", to), ("subject", "Supplies"), ("body", "For the road")];
+    form.extend_from_slice(extra);
+    app.post_mail("mail", &form).await
+}
+
+/// The gold earned events published so far as character and amount
+fn gold_earned(events: &mut Receiver<GameEvent>) -> Vec<(Uuid, i64)> {
+    std::iter::from_fn(|| events.try_recv().ok())
+        .filter_map(|e| match e {
+            GameEvent::GoldEarned { character_id, amount, .. } => Some((character_id, amount)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn attachments_are_held_in_escrow_until_claimed() {
+    let app = spawn_test_app().await;
+    let (bob, bob_id) = app.new_character("Brienne").await;
+    let (_, alice_id) = app.new_character("Aldric").await;
+    app.give_gold(alice_id, 100).await;
+    app.give_items(alice_id, POTION, 3).await;
+
+    let response = send(&app, "brienne", &[("amount", "40"), ("currency", "gold"), ("slot_0", "2")]).await;
+    assert_is_redirected_to(&response, "/mail");
+    assert!(app.get_mail_page_html("mail").await.contains("Your mail is on its way"));
+    assert_eq!(60, app.gold(alice_id).await);
+    assert_eq!(1, app.item_count(alice_id, POTION).await);
+    assert_eq!(0, app.gold(bob_id).await);
+
+    app.play_as(&bob, bob_id).await;
+    let html = app.get_mail_page_html("mail").await;
+    assert!(html.contains("(1 unread)"));
+    assert!(html.contains("40 gold, Minor Healing Potion x2"));
+
+    let mail_id = app.mail_ids(bob_id).await[0];
+    let mut events = app.events.subscribe();
+    let response = app.post_mail(&format!("mail/{}/claim", mail_id), &()).await;
+    assert_is_redirected_to(&response, &format!("/mail/{}", mail_id));
+    assert!(app.get_mail_page_html(&format!("mail/{}", mail_id)).await.contains("You took the attachments"));
+    assert_eq!(40, app.gold(bob_id).await);
+    // gold from another player only changed hands
+    assert!(gold_earned(&mut events).is_empty());
+    assert_eq!(2, app.item_count(bob_id, POTION).await);
+
+    app.post_mail(&format!("mail/{}/claim", mail_id), &()).await;
+    assert!(app.get_mail_page_html(&format!("mail/{}", mail_id)).await.contains("There is nothing attached to take"));
+    assert!(reconcile(&app.db_pool).await.unwrap().is_consistent());
+}
+
+#[tokio::test]
+async fn a_mail_form_sent_twice_only_goes_out_once() {
+    let app = spawn_test_app().await;
+    let (_, bob_id) = app.new_character("Brienne").await;
+    let (_, alice_id) = app.new_character("Aldric").await;
+    app.give_gold(alice_id, 100).await;
+    let html = app.get_mail_page_html("mail").await;
+    let key = html.split("name=\"request_key\" value=\"").nth(1).unwrap().split('"').next().unwrap().to_string();
+
+    let form = [("amount", "40"), ("currency", "gold"), ("request_key", key.as_str())];
+    send(&app, "Brienne", &form).await;
+    let response = send(&app, "Brienne", &form).await;
+    assert_is_redirected_to(&response, "/mail");
+    assert!(app.get_mail_page_html("mail").await.contains("That request went through already"));
+    assert_eq!(60, app.gold(alice_id).await);
+    assert_eq!(1, app.mail_ids(bob_id).await.len());
+
+    // a fresh page makes a fresh key
+    let next = app.get_mail_page_html("mail").await;
+    assert!(!next.contains(&key));
+}
+
+#[tokio::test]
+async fn cash_on_delivery_is_paid_to_the_sender_by_mail() {
+    let app = spawn_test_app().await;
+    let (bob, bob_id) = app.new_character("Brienne").await;
+    let (alice, alice_id) = app.new_character("Aldric").await;
+    app.give_items(alice_id, POTION, 1).await;
+    app.give_gold(bob_id, 10).await;
+    send(&app, "Brienne", &[("slot_0", "1"), ("cod_amount", "25")]).await;
+
+    app.play_as(&bob, bob_id).await;
+    let mail_id = app.mail_ids(bob_id).await[0];
+    assert!(app.get_mail_page_html(&format!("mail/{}", mail_id)).await.contains("Pay 25 gold and take"));
+    app.post_mail(&format!("mail/{}/claim", mail_id), &()).await;
+    assert!(app.get_mail_page_html(&format!("mail/{}", mail_id)).await.contains("Not enough gold, 25 needed but only 10 available"));
+    assert_eq!(0, app.item_count(bob_id, POTION).await);
+
+    app.give_gold(bob_id, 15).await;
+    app.post_mail(&format!("mail/{}/claim", mail_id), &()).await;
+    assert_eq!(0, app.gold(bob_id).await);
+    assert_eq!(1, app.item_count(bob_id, POTION).await);
+
+    app.play_as(&alice, alice_id).await;
+    assert!(app.get_mail_page_html("mail").await.contains("Payment for: Supplies"));
+    let payment_id = app.mail_ids(alice_id).await[0];
+    let mut events = app.events.subscribe();
+    app.post_mail(&format!("mail/{}/claim", payment_id), &()).await;
+    assert_eq!(25, app.gold(alice_id).await);
+    assert_eq!(vec![(alice_id, 25)], gold_earned(&mut events));
+}
+
+#[tokio::test]
+async fn invalid_mail_is_refused_without_taking_anything() {
+    let app = spawn_test_app().await;
+    app.new_character("Brienne").await;
+    let (_, alice_id) = app.new_character("Aldric").await;
+    app.give_gold(alice_id, 10).await;
+    app.give_items(alice_id, POTION, 1).await;
+
+    for (to, extra, error) in [
+        ("Nobody", vec![], "There is no character called Nobody"),
+        ("Aldric", vec![], "You can't send mail to yourself"),
+        ("Brienne", vec![("subject", " ")], "Subjects must be between 1 and 64 characters long"),
+        ("Brienne", vec![("cod_amount", "5")], "Cash on delivery needs items attached"),
+        ("Brienne", vec![("amount", "50"), ("currency", "gold"), ("slot_0", "1")], "Not enough gold, 50 needed but only 10 available"),
+        ("Brienne", vec![("slot_0", "2")], "Not enough minor_healing_potion, wanted 2 but there are only 1"),
+    ] {
+        let mut form = vec![("to", to), ("subject", "Supplies"), ("body", "")];
+        form.extend(extra);
+        let response = app.post_mail("mail", &form).await;
+        assert_is_redirected_to(&response, "/mail");
+        let html = app.get_mail_page_html("mail").await;
+        assert!(html.contains(error), "expected {} in {}", error, html);
+    }
+    assert_eq!(10, app.gold(alice_id).await);
+    assert_eq!(1, app.item_count(alice_id, POTION).await);
+}
+
+#[tokio::test]
+async fn expired_mail_goes_back_to_the_sender_once() {
+    let app = spawn_test_app().await;
+    let (_, bob_id) = app.new_character("Brienne").await;
+    let (_, alice_id) = app.new_character("Aldric").await;
+    app.give_gold(alice_id, 100).await;
+    app.give_items(alice_id, POTION, 1).await;
+    send(&app, "Brienne", &[("amount", "30"), ("currency", "gold"), ("slot_0", "1")]).await;
+
+    assert_eq!(0, app.mail.expire(Utc::now() + Duration::days(29)).await.unwrap());
+    assert_eq!(1, app.mail.expire(Utc::now() + Duration::days(31)).await.unwrap());
+
+    assert!(app.mail_ids(bob_id).await.is_empty());
+    let html = app.get_mail_page_html("mail").await;
+    assert!(html.contains("Returned: Supplies"));
+    assert!(html.contains("30 gold, Minor Healing Potion x1"));
+
+    // nobody claimed it the second time either, so it's gone for good
+    assert_eq!(1, app.mail.expire(Utc::now() + Duration::days(62)).await.unwrap());
+    assert!(app.mail_ids(alice_id).await.is_empty());
+    assert_eq!(70, app.gold(alice_id).await);
+    assert!(reconcile(&app.db_pool).await.unwrap().is_consistent());
+}
+
+#[tokio::test]
+async fn recipients_can_send_mail_back_and_delete_it_once_empty() {
+    let app = spawn_test_app().await;
+    let (bob, bob_id) = app.new_character("Brienne").await;
+    let (alice, alice_id) = app.new_character("Aldric").await;
+    app.give_items(alice_id, POTION, 1).await;
+    send(&app, "Brienne", &[("slot_0", "1"), ("cod_amount", "500")]).await;
+    send(&app, "Brienne", &[]).await;
+
+    app.play_as(&bob, bob_id).await;
+    let ids = app.mail_ids(bob_id).await;
+    let (plain, parcel) = (ids[0], ids[1]);
+    app.post_mail(&format!("mail/{}/delete", parcel), &()).await;
+    assert!(app.get_mail_page_html("mail").await.contains("Take the attachments before deleting the mail"));
+    app.post_mail(&format!("mail/{}/return", plain), &()).await;
+    assert!(app.get_mail_page_html("mail").await.contains("Only unclaimed mail from other players can be sent back"));
+
+    app.post_mail(&format!("mail/{}/return", parcel), &()).await;
+    app.post_mail(&format!("mail/{}/delete", plain), &()).await;
+    assert!(app.get_mail_page_html("mail").await.contains("Mail deleted"));
+    assert!(app.mail_ids(bob_id).await.is_empty());
+
+    app.play_as(&alice, alice_id).await;
+    let returned = app.mail_ids(alice_id).await[0];
+    let html = app.get_mail_page_html(&format!("mail/{}", returned)).await;
+    assert!(html.contains("Returned: Supplies"));
+    assert!(!html.contains("Pay 500 gold"));
+    app.post_mail(&format!("mail/{}/claim", returned), &()).await;
+    assert_eq!(1, app.item_count(alice_id, POTION).await);
+}
+
+#[tokio::test]
+async fn blocked_players_cannot_send_mail() {
+    let app = spawn_test_app().await;
+    let (bob, bob_id) = app.new_character("Brienne").await;
+    let (alice, alice_id) = app.new_character("Aldric").await;
+    app.play_as(&bob, bob_id).await;
+    app.post_friends("block", "AldricPlayer").await;
+
+    app.play_as(&alice, alice_id).await;
+    send(&app, "Brienne", &[]).await;
+
+    assert!(app.get_mail_page_html("mail").await.contains("Brienne is not accepting mail from you"));
+    app.play_as(&bob, bob_id).await;
+    assert!(app.mail_ids(bob_id).await.is_empty());
+}
+
+#[tokio::test]
+async fn admins_send_system_mail_as_compensation() {
+    let app = spawn_test_app().await;
+    let (bob, bob_id) = app.new_character("Brienne").await;
+    let admin = app.register_and_login().await;
+    app.make_admin(&admin).await;
+
+    let response = app.post_admin("mail", &serde_json::json!({
+        "character": "brienne",
+        "subject": "Sorry about the outage",
+        "body": "",
+        "currency": "gold",
+        "amount": 50,
+        "item_id": "bread",
+        "quantity": 2
+    })).await;
+    assert_is_redirected_to(&response, "/admin/mail");
+    let html = app.get_admin_page("mail").await.text().await.unwrap();
+    assert!(html.contains("Mail sent to brienne"));
+    let audit = app.get_admin_page("audit").await.text().await.unwrap();
+    assert!(audit.contains("mail.system_sent"));
+
+    app.play_as(&bob, bob_id).await;
+    let html = app.get_mail_page_html("mail").await;
+    assert!(html.contains("Postmaster"));
+    assert!(html.contains("Sorry about the outage"));
+    let mail_id = app.mail_ids(bob_id).await[0];
+    app.post_mail(&format!("mail/{}/claim", mail_id), &()).await;
+    assert_eq!(50, app.gold(bob_id).await);
+    assert_eq!(2, app.inventory_service().get_inventory(&app.db_pool, bob_id).await.unwrap().count("bread"));
+    assert!(reconcile(&app.db_pool).await.unwrap().is_consistent());
+}
@@ -1,3 +1,23 @@
+mod achievements;
+mod auctions;
+mod characters;
+mod chat;
+mod combat;
+mod crafting;
 mod login;
+mod friends;
+mod gateway;
+mod game_loop;
+mod guilds;
 mod helpers;
-mod register;
\ No newline at end of file
+mod inventory;
+mod leaderboards;
+mod ledger;
+mod mail;
+mod matchmaking;
+mod npcs;
+mod parties;
+mod profile;
+mod quests;
+mod register;
+mod world;
\ No newline at end of file
@@ -0,0 +1,215 @@
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use yaug::authentication::UserId;
+use yaug::matchmaking::{get_ratings, QueueStatus};
+use crate::combat::{fight_to_the_end, next_of_type, stored_fight};
+use crate::helpers::{assert_is_redirected_to, send_ws_json, spawn_test_app, TestApp, WsStream};
+
+/// Enters the world with a fresh character, rated `rating` when given, and queues it for `queue`
+async fn queue_up(app: &TestApp, name: &str, queue: &str, rating: Option<i32>) -> (UserId, WsStream, Uuid) {
+    let (user_id, mut ws, _) = app.enter_world(name).await;
+    let character_id = app.character_id(name).await;
+    if rating.is_some() {
+        app.set_standing(character_id, 1, 0, rating).await;
+    }
+    send_ws_json(&mut ws, serde_json::json!({ "type": "queue_join", "queue": queue })).await;
+    assert_eq!(queue, next_of_type(&mut ws, "queue_joined").await["queue"]);
+    (user_id, ws, character_id)
+}
+
+async fn answer(ws: &mut WsStream, ready_check_id: &serde_json::Value, accept: bool) {
+    send_ws_json(ws, serde_json::json!({ "type": "ready_check", "ready_check_id": ready_check_id, "accept": accept })).await;
+}
+
+#[tokio::test]
+async fn a_duel_is_matched_fought_and_rated() {
+    let app = spawn_test_app().await;
+    let now = Utc::now();
+    let (_, mut tomas, tomas_id) = queue_up(&app, "Tomas", "duel", None).await;
+    let (_, mut maria, maria_id) = queue_up(&app, "Maria", "duel", None).await;
+
+    app.matchmaking.tick(now).await.unwrap();
+    let found = next_of_type(&mut tomas, "match_found").await;
+    assert_eq!(found, next_of_type(&mut maria, "match_found").await);
+    answer(&mut tomas, &found["ready_check_id"], true).await;
+    answer(&mut maria, &found["ready_check_id"], true).await;
+
+    let started = next_of_type(&mut tomas, "combat_started").await;
+    let encounter_id: Uuid = started["encounter_id"].as_str().unwrap().parse().unwrap();
+    let tomas_side = started["you"].as_u64().unwrap() as usize;
+    let maria_side = next_of_type(&mut maria, "combat_started").await["you"].as_u64().unwrap() as usize;
+    tokio::join!(fight_to_the_end(&mut tomas, tomas_side), fight_to_the_end(&mut maria, maria_side));
+    let record = stored_fight(&app, encounter_id).await;
+
+    app.matchmaking.tick(now).await.unwrap();
+    let tomas_change = next_of_type(&mut tomas, "rating_changed").await["change"].as_i64().unwrap();
+    let maria_change = next_of_type(&mut maria, "rating_changed").await["change"].as_i64().unwrap();
+    match record.winner {
+        Some(side) if side == tomas_side => assert!(tomas_change > 0 && maria_change < 0),
+        Some(_) => assert!(maria_change > 0 && tomas_change < 0),
+        None => assert_eq!((0, 0), (tomas_change, maria_change)),
+    }
+    assert_eq!(0, tomas_change + maria_change, "Equal ratings trade the same points");
+    let ratings = get_ratings(&app.db_pool, &[tomas_id, maria_id]).await.unwrap();
+    assert_eq!(1, ratings[&tomas_id].games);
+    assert_eq!(1, ratings[&maria_id].games);
+
+    // settling again changes nothing
+    app.matchmaking.tick(now).await.unwrap();
+    assert_eq!(ratings, get_ratings(&app.db_pool, &[tomas_id, maria_id]).await.unwrap());
+}
+
+#[tokio::test]
+async fn a_match_whose_fight_was_never_stored_is_voided_without_rating_anyone() {
+    let app = spawn_test_app().await;
+    let now = Utc::now();
+    let (_, tomas_id) = app.new_character("Tomas").await;
+    let (_, maria_id) = app.new_character("Maria").await;
+    // as left behind by a restart mid fight: the match is stored, the fight is in nobody's memory
+    let match_id = Uuid::new_v4();
+    sqlx::query!(
+        "INSERT INTO matchmaking_matches (id, queue, started_at) VALUES ($1, 'duel', $2)",
+        match_id,
+        now - Duration::minutes(5)
+    )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    sqlx::query!(
+        r#"
+        INSERT INTO matchmaking_match_players (match_id, character_id, side, rating_before)
+        VALUES ($1, $2, 0, 1500), ($1, $3, 1, 1500)
+        "#,
+        match_id,
+        tomas_id,
+        maria_id
+    )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    app.matchmaking.tick(now).await.unwrap();
+
+    let stored = sqlx::query!("SELECT finished_at, voided FROM matchmaking_matches WHERE id = $1", match_id)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(stored.finished_at.is_some());
+    assert!(stored.voided);
+    let ratings = get_ratings(&app.db_pool, &[tomas_id, maria_id]).await.unwrap();
+    assert_eq!(0, ratings[&tomas_id].games);
+    assert_eq!(0, ratings[&maria_id].games);
+}
+
+#[tokio::test]
+async fn rating_tolerance_widens_the_longer_players_wait() {
+    let app = spawn_test_app().await;
+    let (tomas_user, mut tomas, _) = queue_up(&app, "Tomas", "duel", Some(1500)).await;
+    let (maria_user, _maria, _) = queue_up(&app, "Maria", "duel", Some(1800)).await;
+    let Some(QueueStatus::Queued { since, .. }) = app.matchmaking.status(tomas_user, Utc::now()) else {
+        panic!("Tomas is not queued");
+    };
+
+    // 100 to begin with and 10 more every second
+    let sooner = since + Duration::seconds(19);
+    assert!(matches!(app.matchmaking.status(tomas_user, sooner), Some(QueueStatus::Queued { tolerance: Some(290), .. })));
+    app.matchmaking.tick(sooner).await.unwrap();
+    assert!(matches!(app.matchmaking.status(maria_user, sooner), Some(QueueStatus::Queued { .. })));
+
+    let later = since + Duration::seconds(20);
+    assert!(matches!(app.matchmaking.status(tomas_user, later), Some(QueueStatus::Queued { tolerance: Some(300), .. })));
+    app.matchmaking.tick(later).await.unwrap();
+    next_of_type(&mut tomas, "match_found").await;
+    assert!(matches!(app.matchmaking.status(maria_user, later), Some(QueueStatus::ReadyCheck { .. })));
+}
+
+#[tokio::test]
+async fn players_who_miss_the_ready_check_are_dropped_and_the_others_requeued() {
+    let app = spawn_test_app().await;
+    let now = Utc::now();
+    let (tomas_user, mut tomas, _) = queue_up(&app, "Tomas", "duel", None).await;
+    let (maria_user, mut maria, _) = queue_up(&app, "Maria", "duel", None).await;
+    app.matchmaking.tick(now).await.unwrap();
+    let found = next_of_type(&mut tomas, "match_found").await;
+    answer(&mut tomas, &found["ready_check_id"], true).await;
+    // the answer went through once the status says so
+    while !matches!(app.matchmaking.status(tomas_user, now), Some(QueueStatus::ReadyCheck { accepted: true, .. })) {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    app.matchmaking.tick(now + Duration::seconds(19)).await.unwrap();
+    assert!(app.matchmaking.status(maria_user, now).is_some());
+    app.matchmaking.tick(now + Duration::seconds(20)).await.unwrap();
+
+    assert_eq!(true, next_of_type(&mut tomas, "ready_check_failed").await["requeued"]);
+    assert_eq!(false, next_of_type(&mut maria, "ready_check_failed").await["requeued"]);
+    assert!(matches!(app.matchmaking.status(tomas_user, now), Some(QueueStatus::Queued { .. })));
+    assert_eq!(None, app.matchmaking.status(maria_user, now));
+    assert_eq!(None, app.combat.encounter_id(tomas_user));
+}
+
+#[tokio::test]
+async fn declining_a_match_leaves_the_queue() {
+    let app = spawn_test_app().await;
+    let now = Utc::now();
+    let (tomas_user, mut tomas, _) = queue_up(&app, "Tomas", "duel", None).await;
+    let (maria_user, mut maria, _) = queue_up(&app, "Maria", "duel", None).await;
+    app.matchmaking.tick(now).await.unwrap();
+    let found = next_of_type(&mut maria, "match_found").await;
+    answer(&mut maria, &found["ready_check_id"], false).await;
+
+    assert_eq!(false, next_of_type(&mut maria, "ready_check_failed").await["requeued"]);
+    assert_eq!(true, next_of_type(&mut tomas, "ready_check_failed").await["requeued"]);
+    assert_eq!(None, app.matchmaking.status(maria_user, now));
+    assert!(app.matchmaking.status(tomas_user, now).is_some());
+
+    // a late answer finds nothing to answer
+    answer(&mut tomas, &found["ready_check_id"], true).await;
+    assert_eq!("not_found", next_of_type(&mut tomas, "error").await["code"]);
+}
+
+#[tokio::test]
+async fn dungeon_groups_fight_creatures_together() {
+    let app = spawn_test_app().await;
+    let now = Utc::now();
+    let mut players = Vec::new();
+    for name in ["Tomas", "Maria", "Ilona"] {
+        players.push(queue_up(&app, name, "dungeon", None).await);
+    }
+    app.matchmaking.tick(now).await.unwrap();
+    for (_, ws, _) in players.iter_mut() {
+        let found = next_of_type(ws, "match_found").await;
+        answer(ws, &found["ready_check_id"], true).await;
+    }
+
+    let started = next_of_type(&mut players[0].1, "combat_started").await;
+    let combatants = started["combatants"].as_array().unwrap();
+    assert_eq!(6, combatants.len());
+    let sides: Vec<u64> = combatants.iter().map(|c| c["side"].as_u64().unwrap()).collect();
+    assert_eq!(vec![0, 0, 0, 1, 1, 1], sides);
+    let encounter_id: Uuid = started["encounter_id"].as_str().unwrap().parse().unwrap();
+    for (user_id, _, _) in &players {
+        assert_eq!(Some(encounter_id), app.combat.encounter_id(*user_id));
+    }
+}
+
+#[tokio::test]
+async fn players_can_queue_once_from_the_matchmaking_page() {
+    let app = spawn_test_app().await;
+    let (user_id, _ws, _) = app.enter_world("Tomas").await;
+
+    let html = app.get_matchmaking_page_html().await;
+    assert!(html.contains("Rating 1500 after 0 rated matches"));
+    let response = app.post_matchmaking("matchmaking", &serde_json::json!({ "queue": "team" })).await;
+    assert_is_redirected_to(&response, "/matchmaking");
+    assert!(app.get_matchmaking_page_html().await.contains("You joined the Team battle queue"));
+    assert!(app.matchmaking.status(user_id, Utc::now()).is_some());
+
+    app.post_matchmaking("matchmaking", &serde_json::json!({ "queue": "duel" })).await;
+    assert!(app.get_matchmaking_page_html().await.contains("You are already in a queue"));
+
+    let response = app.post_matchmaking("matchmaking/leave", &serde_json::json!({})).await;
+    assert_is_redirected_to(&response, "/matchmaking");
+    assert!(app.get_matchmaking_page_html().await.contains("You left the Team battle queue"));
+    assert_eq!(None, app.matchmaking.status(user_id, Utc::now()));
+}
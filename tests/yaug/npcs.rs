@@ -0,0 +1,136 @@
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::combat::{fight_to_the_end, next_of_type, stored_fight};
+use crate::helpers::{send_ws_json, spawn_test_app, TestApp, WsStream};
+
+/// Skips everything else until `name` comes into view
+async fn wait_for_entity(ws: &mut WsStream, name: &str) -> serde_json::Value {
+    loop {
+        let appeared = next_of_type(ws, "entity_appeared").await;
+        if appeared["entity"]["name"] == name {
+            return appeared["entity"].clone();
+        }
+    }
+}
+
+/// Ticks npcs by hand until one of them picks a fight, a hurt spider runs off and heals up first
+async fn tick_until_attacked(app: &TestApp, ws: &mut WsStream, now: DateTime<Utc>, tick: &mut u64) -> Uuid {
+    for _ in 0..50 {
+        app.npcs.tick(now, *tick).await.unwrap();
+        *tick += 2;
+        let waiting = tokio::time::timeout(Duration::from_millis(50), next_of_type(ws, "combat_started"));
+        if let Ok(started) = waiting.await {
+            return started["encounter_id"].as_str().unwrap().parse().unwrap();
+        }
+    }
+    panic!("Nothing attacked");
+}
+
+fn only_npc(app: &TestApp, template_id: &str) -> Uuid {
+    let npcs = app.npcs.npcs_of(template_id);
+    assert_eq!(1, npcs.len());
+    npcs[0].0
+}
+
+#[tokio::test]
+async fn npcs_spawn_on_the_first_tick_and_are_seen_like_players() {
+    let mut app = spawn_test_app().await;
+    let (_, mut ws, zone) = app.enter_world("Tomas").await;
+    assert_eq!(0, zone["entities"].as_array().unwrap().len());
+
+    app.game_loop.tick().await;
+
+    let elder = wait_for_entity(&mut ws, "Elder Maren").await;
+    assert_eq!("npc", elder["kind"]);
+    assert_eq!(6, elder["x"]);
+    let (_, _, zone) = app.enter_world("Anna").await;
+    let entities = zone["entities"].as_array().unwrap();
+    assert!(entities.iter().any(|e| e["name"] == "Elder Maren" && e["kind"] == "npc"));
+    assert_eq!(2, app.npcs.npcs_of("sheep").len());
+}
+
+#[tokio::test]
+async fn npcs_nearby_answer_in_turn() {
+    let mut app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+    app.game_loop.tick().await;
+
+    let talk = serde_json::json!({ "type": "talk", "npc": "elder_maren" });
+    send_ws_json(&mut ws, talk.clone()).await;
+    let said = next_of_type(&mut ws, "npc_said").await;
+    assert_eq!("Elder Maren", said["name"]);
+    assert_eq!("Welcome to Greenvale, traveller.", said["line"]);
+    assert_eq!(only_npc(&app, "elder_maren").to_string(), said["npc_id"]);
+    send_ws_json(&mut ws, talk).await;
+    assert_eq!("The wolves have grown bold this year, mind the east road.", next_of_type(&mut ws, "npc_said").await["line"]);
+
+    // the guard walks the north wall, out of earshot
+    send_ws_json(&mut ws, serde_json::json!({ "type": "talk", "npc": "village_guard" })).await;
+    assert_eq!("not_found", next_of_type(&mut ws, "error").await["code"]);
+}
+
+#[tokio::test]
+async fn only_fighting_npcs_right_next_to_the_player_can_be_attacked() {
+    let mut app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+    app.game_loop.tick().await;
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "npc_attack", "npc_id": only_npc(&app, "elder_maren") })).await;
+    let error = next_of_type(&mut ws, "error").await;
+    assert_eq!("invalid_action", error["code"]);
+    assert_eq!("Elder Maren won't fight", error["message"]);
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "npc_attack", "npc_id": only_npc(&app, "forest_spider") })).await;
+    assert_eq!("Forest Spider is too far away", next_of_type(&mut ws, "error").await["message"]);
+    send_ws_json(&mut ws, serde_json::json!({ "type": "npc_attack", "npc_id": Uuid::new_v4() })).await;
+    assert_eq!("not_found", next_of_type(&mut ws, "error").await["code"]);
+}
+
+#[tokio::test]
+async fn hostile_npcs_attack_players_who_come_close() {
+    let mut app = spawn_test_app().await;
+    let (user_id, mut ws, _) = app.enter_world_at("Tomas", Some(("old_forest", 10, 2))).await;
+
+    for _ in 0..10 {
+        app.game_loop.tick().await;
+        let waiting = tokio::time::timeout(Duration::from_millis(50), next_of_type(&mut ws, "combat_started"));
+        if let Ok(started) = waiting.await {
+            assert_eq!("Forest Spider", started["combatants"][1]["name"]);
+            assert!(app.combat.encounter_id(user_id).is_some());
+            return;
+        }
+    }
+    panic!("The spider never attacked");
+}
+
+#[tokio::test]
+async fn defeated_npcs_respawn_once_their_timer_is_up() {
+    let app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world_at("Tomas", Some(("old_forest", 11, 2))).await;
+    let now = Utc::now();
+
+    // the spider spawns right next to Tomas and attacks at once, again after every fight it wins
+    let mut tick = 1;
+    let mut won = false;
+    for _ in 0..5 {
+        let encounter_id = tick_until_attacked(&app, &mut ws, now, &mut tick).await;
+        won = fight_to_the_end(&mut ws, 0).await == Some(0);
+        stored_fight(&app, encounter_id).await;
+        if won {
+            break;
+        }
+    }
+    assert!(won, "Tomas never beat the spider");
+    let spider = only_npc(&app, "forest_spider");
+
+    app.npcs.tick(now, tick).await.unwrap();
+    assert_eq!(spider.to_string(), next_of_type(&mut ws, "entity_left").await["id"]);
+    assert!(app.npcs.npcs_of("forest_spider").is_empty());
+
+    app.npcs.tick(now + chrono::Duration::seconds(89), tick + 2).await.unwrap();
+    assert!(app.npcs.npcs_of("forest_spider").is_empty());
+    app.npcs.tick(now + chrono::Duration::seconds(90), tick + 4).await.unwrap();
+    assert_eq!("Forest Spider", wait_for_entity(&mut ws, "Forest Spider").await["name"]);
+    assert_ne!(spider, only_npc(&app, "forest_spider"));
+}
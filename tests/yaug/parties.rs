@@ -0,0 +1,256 @@
+use std::time::Duration;
+use chrono::Utc;
+use uuid::Uuid;
+use yaug::authentication::UserId;
+use yaug::items::ItemStack;
+use yaug::parties::Looter;
+use yaug::world::Position;
+use crate::combat::{fight_to_the_end, next_of_type, start_fight, stored_fight};
+use crate::helpers::{next_ws_json, send_ws_json, spawn_test_app, TestApp, WsStream};
+
+/// Registers a player whose display name and character are both `name` and enters the world,
+/// returns the player's email along with the connection
+async fn join_world(app: &TestApp, name: &str) -> (UserId, WsStream, String) {
+    let email = app.register_player(name).await;
+    app.create_character(name, "warrior").await;
+    let mut ws = app.connect_ws().await.expect("Failed to connect to the gateway");
+    assert_eq!("welcome", next_ws_json(&mut ws).await["type"]);
+    assert_eq!("zone_entered", next_ws_json(&mut ws).await["type"]);
+    (app.user_id(&email).await, ws, email)
+}
+
+/// The first player invites everyone else, who all accept
+async fn form_party(app: &TestApp, names: &[&str]) -> Vec<(UserId, WsStream, String)> {
+    let mut players = Vec::new();
+    for name in names {
+        players.push(join_world(app, name).await);
+    }
+    for (i, name) in names.iter().enumerate().skip(1) {
+        send_ws_json(&mut players[0].1, serde_json::json!({ "type": "party_invite", "display_name": name })).await;
+        let ws = &mut players[i].1;
+        let invite = next_of_type(ws, "party_invite").await;
+        assert_eq!(names[0], invite["from"]);
+        send_ws_json(ws, serde_json::json!({ "type": "party_accept", "party_id": invite["party_id"] })).await;
+        let update = next_of_type(ws, "party_update").await;
+        assert_eq!(i + 1, update["party"]["members"].as_array().unwrap().len());
+    }
+    players
+}
+
+/// Waits for the party update that shows `name` online or not
+async fn wait_for_member(ws: &mut WsStream, name: &str, online: bool) -> serde_json::Value {
+    loop {
+        let update = next_of_type(ws, "party_update").await;
+        let members = update["party"]["members"].as_array().unwrap();
+        if members.iter().any(|m| m["display_name"] == name && m["online"] == online) {
+            return update;
+        }
+    }
+}
+
+fn looter(user_id: UserId, character_id: Uuid, name: &str) -> Looter {
+    Looter { user_id, character_id, name: name.to_string() }
+}
+
+fn bread(quantity: i32) -> ItemStack {
+    ItemStack { item_id: "bread".to_string(), quantity }
+}
+
+#[tokio::test]
+async fn invited_players_join_the_party_and_share_its_chat() {
+    let app = spawn_test_app().await;
+    let mut players = form_party(&app, &["Tomas", "Maria"]).await;
+
+    let party = app.parties.party(players[1].0).unwrap();
+    assert_eq!("Tomas", party.leader);
+    let names: Vec<_> = party.members.iter().map(|m| m.display_name.as_str()).collect();
+    assert_eq!(vec!["Tomas", "Maria"], names);
+    assert_eq!(Some(party.id), app.parties.party(players[0].0).map(|p| p.id));
+
+    send_ws_json(&mut players[0].1, serde_json::json!({ "type": "chat_send", "channel": "party", "body": "regroup" })).await;
+    let line = next_of_type(&mut players[1].1, "chat_message").await;
+    assert_eq!("party", line["channel"]);
+    assert_eq!("regroup", line["body"]);
+}
+
+#[tokio::test]
+async fn players_who_block_the_inviter_refuse_invitations() {
+    let app = spawn_test_app().await;
+    let (tomas, mut tomas_ws, _) = join_world(&app, "Tomas").await;
+    let (_, mut maria_ws, _) = join_world(&app, "Maria").await;
+    send_ws_json(&mut maria_ws, serde_json::json!({ "type": "chat_block", "display_name": "Tomas" })).await;
+    assert_eq!("You blocked Tomas", next_of_type(&mut maria_ws, "notice").await["message"]);
+
+    send_ws_json(&mut tomas_ws, serde_json::json!({ "type": "party_invite", "display_name": "Maria" })).await;
+    let error = next_of_type(&mut tomas_ws, "error").await;
+    assert_eq!("forbidden", error["code"]);
+    assert_eq!("Maria is not accepting invitations from you", error["message"]);
+
+    send_ws_json(&mut tomas_ws, serde_json::json!({ "type": "party_invite", "display_name": "Nobody" })).await;
+    assert_eq!("not_found", next_of_type(&mut tomas_ws, "error").await["code"]);
+    assert!(app.parties.party(tomas).is_none());
+}
+
+#[tokio::test]
+async fn leaders_kick_and_promote_and_parties_disband_when_one_member_is_left() {
+    let app = spawn_test_app().await;
+    let mut players = form_party(&app, &["Tomas", "Maria", "Ilona"]).await;
+
+    send_ws_json(&mut players[1].1, serde_json::json!({ "type": "party_kick", "display_name": "Ilona" })).await;
+    assert_eq!("forbidden", next_of_type(&mut players[1].1, "error").await["code"]);
+
+    send_ws_json(&mut players[0].1, serde_json::json!({ "type": "party_promote", "display_name": "maria" })).await;
+    assert_eq!("Maria", next_of_type(&mut players[2].1, "party_update").await["party"]["leader"]);
+    send_ws_json(&mut players[1].1, serde_json::json!({ "type": "party_kick", "display_name": "Ilona" })).await;
+    next_of_type(&mut players[2].1, "party_left").await;
+    assert!(app.parties.party(players[2].0).is_none());
+    assert_eq!(2, app.parties.party(players[0].0).unwrap().members.len());
+
+    send_ws_json(&mut players[0].1, serde_json::json!({ "type": "party_leave" })).await;
+    next_of_type(&mut players[1].1, "party_left").await;
+    assert!(app.parties.party(players[0].0).is_none());
+    assert!(app.parties.party(players[1].0).is_none());
+}
+
+#[tokio::test]
+async fn members_keep_their_place_through_short_disconnects() {
+    let app = spawn_test_app().await;
+    let mut players = form_party(&app, &["Tomas", "Maria"]).await;
+    let (maria, mut maria_ws, maria_email) = players.pop().unwrap();
+    let tomas_ws = &mut players[0].1;
+
+    maria_ws.close(None).await.unwrap();
+    wait_for_member(tomas_ws, "Maria", false).await;
+    app.parties.tick(Utc::now()).await.unwrap();
+    assert_eq!(2, app.parties.party(maria).unwrap().members.len());
+
+    app.login_as(&maria_email).await;
+    let mut maria_ws = app.connect_ws().await.unwrap();
+    assert_eq!("welcome", next_ws_json(&mut maria_ws).await["type"]);
+    let update = next_of_type(&mut maria_ws, "party_update").await;
+    assert_eq!(2, update["party"]["members"].as_array().unwrap().len());
+    wait_for_member(tomas_ws, "Maria", true).await;
+
+    maria_ws.close(None).await.unwrap();
+    wait_for_member(tomas_ws, "Maria", false).await;
+    app.parties.tick(Utc::now() + chrono::Duration::seconds(121)).await.unwrap();
+    next_of_type(tomas_ws, "party_left").await;
+    assert!(app.parties.party(maria).is_none());
+}
+
+#[tokio::test]
+async fn party_members_share_kills_towards_their_quests() {
+    let mut app = spawn_test_app().await;
+    let mut players = form_party(&app, &["Tomas", "Maria"]).await;
+    // the client is still logged in as Maria
+    let maria_character = app.character_id("Maria").await;
+    app.complete_quests(maria_character, &["welcome_to_greenvale"]).await;
+    app.post_quest("wolf_trouble", "accept").await;
+
+    let tomas_ws = &mut players[0].1;
+    let mut won = false;
+    for _ in 0..5 {
+        let (encounter_id, you) = start_fight(tomas_ws, "wolf").await;
+        won = fight_to_the_end(tomas_ws, you).await == Some(you);
+        stored_fight(&app, encounter_id).await;
+        if won {
+            break;
+        }
+    }
+    assert!(won, "Tomas never beat the wolf");
+
+    let maria_ws = &mut players[1].1;
+    for _ in 0..50 {
+        app.game_loop.tick().await;
+        let waiting = tokio::time::timeout(Duration::from_millis(50), next_of_type(maria_ws, "quest_progress"));
+        if let Ok(progress) = waiting.await {
+            assert_eq!("wolf_trouble", progress["quest_id"]);
+            assert_eq!(1, progress["progress"]);
+            return;
+        }
+    }
+    panic!("Maria's quest didn't move");
+}
+
+#[tokio::test]
+async fn party_members_near_the_winner_share_the_loot() {
+    let app = spawn_test_app().await;
+    let players = form_party(&app, &["Tomas", "Maria"]).await;
+    let (stranger, _ws, _) = join_world(&app, "Ana").await;
+    let tomas = looter(players[0].0, app.character_id("Tomas").await, "Tomas");
+    let maria = looter(players[1].0, app.character_id("Maria").await, "Maria");
+
+    // everyone starts out on the same spot, only the party shares
+    let in_world = app.world.players_in_world();
+    assert!(in_world.iter().any(|(user_id, _, _)| *user_id == stranger));
+    assert_eq!(vec![tomas.clone(), maria.clone()], app.parties.looters_near(tomas.clone(), &in_world));
+
+    let far_away: Vec<_> = in_world
+        .into_iter()
+        .map(|(user_id, character_id, position)| match user_id == maria.user_id {
+            true => (user_id, character_id, Position::new(position.zone_id, position.x + 50, position.y)),
+            false => (user_id, character_id, position),
+        })
+        .collect();
+    assert_eq!(vec![tomas.clone()], app.parties.looters_near(tomas, &far_away));
+}
+
+#[tokio::test]
+async fn loot_is_shared_the_way_the_party_leader_chose() {
+    let app = spawn_test_app().await;
+    let mut players = form_party(&app, &["Tomas", "Maria"]).await;
+    let tomas_character = app.character_id("Tomas").await;
+    let maria_character = app.character_id("Maria").await;
+    let looters = vec![looter(players[0].0, tomas_character, "Tomas"), looter(players[1].0, maria_character, "Maria")];
+
+    // round robin to begin with
+    app.parties.distribute_loot(&looters, vec![bread(1), bread(2), bread(3), bread(4)], Utc::now()).await.unwrap();
+    assert_eq!(2, app.mail_ids(tomas_character).await.len());
+    assert_eq!(2, app.mail_ids(maria_character).await.len());
+    assert_eq!("Tomas", next_of_type(&mut players[1].1, "loot_awarded").await["to"]);
+
+    // free for all hands everything out all the same
+    send_ws_json(&mut players[0].1, serde_json::json!({ "type": "party_loot_mode", "mode": "free_for_all" })).await;
+    assert_eq!("free_for_all", next_of_type(&mut players[1].1, "party_update").await["party"]["loot_mode"]);
+    app.parties.distribute_loot(&looters, vec![bread(1), bread(1)], Utc::now()).await.unwrap();
+    assert_eq!(6, app.mail_ids(tomas_character).await.len() + app.mail_ids(maria_character).await.len());
+
+    send_ws_json(&mut players[0].1, serde_json::json!({ "type": "party_loot_mode", "mode": "need_greed" })).await;
+    assert_eq!("need_greed", next_of_type(&mut players[1].1, "party_update").await["party"]["loot_mode"]);
+    let mails = app.mail_ids(tomas_character).await.len();
+    app.parties.distribute_loot(&looters, vec![bread(5)], Utc::now()).await.unwrap();
+    let started = next_of_type(&mut players[0].1, "loot_roll_started").await;
+    assert_eq!(started, next_of_type(&mut players[1].1, "loot_roll_started").await);
+
+    send_ws_json(&mut players[1].1, serde_json::json!({ "type": "loot_roll", "roll_id": started["roll_id"], "choice": "greed" })).await;
+    send_ws_json(&mut players[0].1, serde_json::json!({ "type": "loot_roll", "roll_id": started["roll_id"], "choice": "need" })).await;
+    let ended = next_of_type(&mut players[1].1, "loot_roll_ended").await;
+    assert_eq!("Tomas", ended["winner"]);
+    loop {
+        let awarded = next_of_type(&mut players[1].1, "loot_awarded").await;
+        if awarded["quantity"] == 5 {
+            assert_eq!("Tomas", awarded["to"]);
+            break;
+        }
+    }
+    assert_eq!(mails + 1, app.mail_ids(tomas_character).await.len());
+
+    // too late to roll again
+    send_ws_json(&mut players[1].1, serde_json::json!({ "type": "loot_roll", "roll_id": started["roll_id"], "choice": "need" })).await;
+    assert_eq!("not_found", next_of_type(&mut players[1].1, "error").await["code"]);
+}
+
+#[tokio::test]
+async fn party_leaders_queue_the_whole_party() {
+    let app = spawn_test_app().await;
+    let mut players = form_party(&app, &["Tomas", "Maria", "Ilona"]).await;
+
+    send_ws_json(&mut players[1].1, serde_json::json!({ "type": "queue_join", "queue": "dungeon" })).await;
+    assert_eq!("forbidden", next_of_type(&mut players[1].1, "error").await["code"]);
+
+    send_ws_json(&mut players[0].1, serde_json::json!({ "type": "queue_join", "queue": "dungeon" })).await;
+    for (user_id, ws, _) in players.iter_mut() {
+        assert_eq!("dungeon", next_of_type(ws, "queue_joined").await["queue"]);
+        assert!(app.matchmaking.status(*user_id, Utc::now()).is_some());
+    }
+}
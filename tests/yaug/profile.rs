@@ -0,0 +1,78 @@
+use uuid::Uuid;
+use crate::helpers::{assert_is_redirected_to, spawn_test_app};
+
+fn unique_name() -> String {
+    format!("Hero{}", &Uuid::new_v4().simple().to_string()[..8])
+}
+
+fn profile_form(display_name: &str) -> serde_json::Value {
+    serde_json::json!(
+        {
+            "display_name": display_name,
+            "avatar": "mage",
+            "bio": "I like <b>long</b> walks in the dungeon",
+            "locale": "en"
+        }
+    )
+}
+
+#[tokio::test]
+async fn profile_page_requires_login() {
+    let app = spawn_test_app().await;
+
+    let response = app.post_profile(&profile_form(&unique_name())).await;
+
+    assert_is_redirected_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn saved_profile_is_shown_on_public_page_without_email() {
+    let app = spawn_test_app().await;
+    let email = app.register_and_login().await;
+    let name = unique_name();
+
+    let response = app.post_profile(&profile_form(&name)).await;
+    assert_is_redirected_to(&response, "/account/profile");
+    assert!(app.get_profile_page_html().await.contains("Your profile has been saved"));
+
+    // lookups ignore case
+    let response = app.get_player_page(&name.to_uppercase()).await;
+    assert_eq!(200, response.status().as_u16());
+    let html = response.text().await.unwrap();
+    assert!(html.contains(&name));
+    assert!(html.contains("&lt;b&gt;long&lt;&#x2F;b&gt;"));
+    assert!(!html.contains(&email));
+}
+
+#[tokio::test]
+async fn display_name_taken_in_different_case_is_rejected() {
+    let app = spawn_test_app().await;
+    let name = unique_name();
+
+    app.register_and_login().await;
+    app.post_profile(&profile_form(&name)).await;
+
+    app.register_and_login().await;
+    let response = app.post_profile(&profile_form(&name.to_lowercase())).await;
+    assert_is_redirected_to(&response, "/account/profile");
+    assert!(app.get_profile_page_html().await.contains("is already taken"));
+}
+
+#[tokio::test]
+async fn invalid_display_name_is_rejected_with_message() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+
+    let response = app.post_profile(&profile_form("Admin")).await;
+    assert_is_redirected_to(&response, "/account/profile");
+    assert!(app.get_profile_page_html().await.contains("reserved name"));
+}
+
+#[tokio::test]
+async fn unknown_player_is_not_found() {
+    let app = spawn_test_app().await;
+
+    let response = app.get_player_page(&unique_name()).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
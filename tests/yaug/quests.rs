@@ -0,0 +1,234 @@
+use std::time::Duration;
+use uuid::Uuid;
+use yaug::authentication::UserId;
+use yaug::events::GameEvent;
+use yaug::ledger::{get_balance, Currency};
+use crate::combat::{fight_to_the_end, next_of_type, start_fight, stored_fight};
+use crate::helpers::{assert_is_redirected_to, next_ws_json, send_ws_json, spawn_test_app, TestApp, WsStream};
+
+/// Steps the game loop until the quest system pushes progress to the player
+async fn next_progress(app: &mut TestApp, ws: &mut WsStream) -> serde_json::Value {
+    for _ in 0..50 {
+        app.game_loop.tick().await;
+        let waiting = tokio::time::timeout(Duration::from_millis(50), next_of_type(ws, "quest_progress"));
+        if let Ok(progress) = waiting.await {
+            return progress;
+        }
+    }
+    panic!("No quest progress was pushed");
+}
+
+async fn quest_row(app: &TestApp, character_id: Uuid, quest_id: &str) -> Option<(String, Vec<i32>)> {
+    sqlx::query!(
+        "SELECT status, progress FROM character_quests WHERE character_id = $1 AND quest_id = $2",
+        character_id,
+        quest_id
+    )
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap()
+        .map(|r| (r.status, r.progress))
+}
+
+async fn experience(app: &TestApp, character_id: Uuid) -> i64 {
+    sqlx::query!("SELECT experience FROM characters WHERE id = $1", character_id)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .experience
+}
+
+async fn gold(app: &TestApp, character_id: Uuid) -> i64 {
+    get_balance(&app.db_pool, character_id, Currency::Gold).await.unwrap()
+}
+
+/// Posts a quest action and returns the quest page it redirects to
+async fn quest_action(app: &TestApp, quest_id: &str, action: &str) -> String {
+    let response = app.post_quest(quest_id, action).await;
+    assert_is_redirected_to(&response, "/quests");
+    app.get_quests_page().await.text().await.unwrap()
+}
+
+fn defeated(user_id: UserId, character_id: Uuid, creature_id: &str) -> GameEvent {
+    GameEvent::CreatureDefeated { user_id, character_id, creature_id: creature_id.to_string() }
+}
+
+#[tokio::test]
+async fn quests_need_their_prerequisites_done() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.create_character("Tomas", "warrior").await;
+
+    let html = app.get_quests_page().await.text().await.unwrap();
+    assert!(html.contains("Welcome to Greenvale"));
+    assert!(!html.contains("Wolf Trouble"));
+
+    let html = quest_action(&app, "wolf_trouble", "accept").await;
+    assert!(html.contains("Wolf Trouble needs other quests done first"));
+    let html = quest_action(&app, "dragon_slayer", "accept").await;
+    assert!(html.contains("There is no quest called dragon_slayer"));
+}
+
+#[tokio::test]
+async fn talking_to_an_npc_finishes_the_quest_and_handing_it_in_pays_the_rewards() {
+    let mut app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+    let character_id = app.character_id("Tomas").await;
+    quest_action(&app, "welcome_to_greenvale", "accept").await;
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "talk", "npc": "elder_maren" })).await;
+    let progress = next_progress(&mut app, &mut ws).await;
+
+    assert_eq!("welcome_to_greenvale", progress["quest_id"]);
+    assert_eq!(0, progress["objective"]);
+    assert_eq!(1, progress["progress"]);
+    assert_eq!(Some(("active".to_string(), vec![1])), quest_row(&app, character_id, "welcome_to_greenvale").await);
+    let html = app.get_quests_page().await.text().await.unwrap();
+    assert!(html.contains("Talk to Elder Maren in Greenvale 1/1"));
+    assert!(html.contains("Hand in"));
+
+    let html = quest_action(&app, "welcome_to_greenvale", "complete").await;
+    assert!(html.contains("You completed Welcome to Greenvale"));
+    assert_eq!(10, gold(&app, character_id).await);
+    assert_eq!(50, experience(&app, character_id).await);
+    // the next quests in the chain open up
+    assert!(html.contains("Wolf Trouble"));
+
+    let html = quest_action(&app, "welcome_to_greenvale", "accept").await;
+    assert!(html.contains("Welcome to Greenvale was already done"));
+    let html = quest_action(&app, "welcome_to_greenvale", "complete").await;
+    assert!(html.contains("Welcome to Greenvale is not in your journal"));
+    assert_eq!(10, gold(&app, character_id).await);
+}
+
+#[tokio::test]
+async fn npcs_can_only_be_talked_to_where_they_are() {
+    let app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world_at("Tomas", Some(("old_forest", 1, 2))).await;
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "talk", "npc": "elder_maren" })).await;
+    assert_eq!("not_found", next_ws_json(&mut ws).await["code"]);
+    send_ws_json(&mut ws, serde_json::json!({ "type": "talk", "npc": "nobody" })).await;
+    assert_eq!("not_found", next_ws_json(&mut ws).await["code"]);
+}
+
+#[tokio::test]
+async fn defeated_creatures_count_towards_kill_objectives() {
+    let mut app = spawn_test_app().await;
+    let (user_id, mut ws, _) = app.enter_world("Tomas").await;
+    let character_id = app.character_id("Tomas").await;
+    app.complete_quests(character_id, &["welcome_to_greenvale"]).await;
+    quest_action(&app, "wolf_trouble", "accept").await;
+
+    app.events.publish(defeated(user_id, character_id, "wolf"));
+    app.events.publish(defeated(user_id, character_id, "boar"));
+    app.events.publish(defeated(user_id, character_id, "wolf"));
+    // someone else's wolves don't count
+    app.events.publish(defeated(UserId::from(Uuid::new_v4()), Uuid::new_v4(), "wolf"));
+
+    assert_eq!(1, next_progress(&mut app, &mut ws).await["progress"]);
+    assert_eq!(2, next_progress(&mut app, &mut ws).await["progress"]);
+    assert_eq!(Some(("active".to_string(), vec![2])), quest_row(&app, character_id, "wolf_trouble").await);
+    let html = quest_action(&app, "wolf_trouble", "complete").await;
+    assert!(html.contains("Wolf Trouble is not finished yet"));
+    assert!(html.contains("Defeat Grey Wolf 2/3"));
+}
+
+#[tokio::test]
+async fn winning_a_fight_counts_the_defeated_creatures() {
+    let mut app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+    let character_id = app.character_id("Tomas").await;
+    app.complete_quests(character_id, &["welcome_to_greenvale"]).await;
+    quest_action(&app, "wolf_trouble", "accept").await;
+
+    let (encounter_id, you) = start_fight(&mut ws, "wolf").await;
+    let winner = fight_to_the_end(&mut ws, you).await;
+    stored_fight(&app, encounter_id).await;
+
+    if winner == Some(you) {
+        assert_eq!(1, next_progress(&mut app, &mut ws).await["progress"]);
+    } else {
+        app.game_loop.tick().await;
+        assert_eq!(Some(("active".to_string(), vec![0])), quest_row(&app, character_id, "wolf_trouble").await);
+    }
+}
+
+#[tokio::test]
+async fn walking_to_a_location_finishes_reach_objectives() {
+    let mut app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world_at("Tomas", Some(("old_forest", 7, 5))).await;
+    let character_id = app.character_id("Tomas").await;
+    app.complete_quests(character_id, &["welcome_to_greenvale", "wolf_trouble"]).await;
+    quest_action(&app, "into_the_old_forest", "accept").await;
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "move", "dx": 1, "dy": 0 })).await;
+    let progress = next_progress(&mut app, &mut ws).await;
+
+    assert_eq!("into_the_old_forest", progress["quest_id"]);
+    assert_eq!(0, progress["objective"]);
+    assert_eq!(Some(("active".to_string(), vec![1, 0])), quest_row(&app, character_id, "into_the_old_forest").await);
+}
+
+#[tokio::test]
+async fn collected_items_are_taken_and_repeatable_quests_come_back() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.create_character("Tomas", "warrior").await;
+    let character_id = app.character_id("Tomas").await;
+    app.complete_quests(character_id, &["welcome_to_greenvale"]).await;
+    app.give_items(character_id, "wolf_pelt", 3).await;
+
+    let html = quest_action(&app, "pelts_for_the_tanner", "accept").await;
+    assert!(html.contains("Collect Wolf Pelt 2/2"));
+    let html = quest_action(&app, "pelts_for_the_tanner", "complete").await;
+
+    assert!(html.contains("You completed Pelts for the Tanner"));
+    assert_eq!(1, app.inventory_service().get_inventory(&app.db_pool, character_id).await.unwrap().count("wolf_pelt"));
+    assert_eq!(8, gold(&app, character_id).await);
+
+    let html = quest_action(&app, "pelts_for_the_tanner", "accept").await;
+    assert!(html.contains("Collect Wolf Pelt 1/2"));
+    let html = quest_action(&app, "pelts_for_the_tanner", "complete").await;
+    assert!(html.contains("Pelts for the Tanner is not finished yet"));
+    assert_eq!(8, gold(&app, character_id).await);
+}
+
+#[tokio::test]
+async fn rewards_that_do_not_fit_leave_everything_as_it_was() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.create_character("Tomas", "warrior").await;
+    let character_id = app.character_id("Tomas").await;
+    app.complete_quests(character_id, &["welcome_to_greenvale"]).await;
+    quest_action(&app, "wolf_trouble", "accept").await;
+    sqlx::query!("UPDATE character_quests SET progress = '{3}' WHERE quest_id = 'wolf_trouble'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.give_items(character_id, "rusty_sword", 30).await;
+
+    let html = quest_action(&app, "wolf_trouble", "complete").await;
+
+    assert!(html.contains("There is not enough room in the inventory"));
+    assert_eq!(0, gold(&app, character_id).await);
+    assert_eq!(0, experience(&app, character_id).await);
+    assert_eq!(Some(("active".to_string(), vec![3])), quest_row(&app, character_id, "wolf_trouble").await);
+}
+
+#[tokio::test]
+async fn abandoned_quests_can_be_accepted_again() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    app.create_character("Tomas", "warrior").await;
+    let character_id = app.character_id("Tomas").await;
+    quest_action(&app, "welcome_to_greenvale", "accept").await;
+
+    let html = quest_action(&app, "welcome_to_greenvale", "abandon").await;
+
+    assert!(html.contains("You abandoned Welcome to Greenvale"));
+    assert!(html.contains("Nothing in your journal"));
+    assert_eq!(None, quest_row(&app, character_id, "welcome_to_greenvale").await);
+    let html = quest_action(&app, "welcome_to_greenvale", "accept").await;
+    assert!(html.contains("Welcome to Greenvale was added to your journal"));
+}
\ No newline at end of file
@@ -1,10 +1,8 @@
-use anyhow::Context;
-use claim::assert_ok;
 use fake::Fake;
 use fake::faker::internet::en::SafeEmail;
-use secrecy::{ExposeSecret, Secret};
+use secrecy::ExposeSecret;
 use yaug::domain::AccountPassword;
-use crate::helpers::spawn_test_app;
+use crate::helpers::{assert_is_redirected_to, spawn_test_app};
 
 #[tokio::test]
 async fn register_returns_a_303_on_valid_data() {
@@ -129,7 +127,7 @@ async fn register_persists_valid_account() {
 
     assert_eq!(email, saved.email);
 
-    let token = sqlx::query!(
+    let _token = sqlx::query!(
         r#"
         SELECT token
         FROM activation_token
@@ -158,7 +156,19 @@ async fn register_activated_user_can_login() {
 
 #[tokio::test]
 async fn register_duplicate_email_are_not_accepted() {
-    todo!()
+    let app = spawn_test_app().await;
+    let password = "correct-Horse-battery!";
+    let register = |email: &str| format!(
+        "email={}&password={}&password_check={}",
+        email, password, password
+    );
+
+    app.post_registration(register("Bob.Smith@Example.com")).await;
+    let response = app.post_registration(register("bob.smith@example.COM")).await;
+
+    assert_is_redirected_to(&response, "/register");
+    let html = app.get_registration_page_html().await;
+    assert!(html.contains("This email address is already registered"));
 }
 
 #[tokio::test]
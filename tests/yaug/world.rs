@@ -0,0 +1,202 @@
+use std::time::Duration;
+use yaug::world::Position;
+use crate::helpers::{next_ws_json, send_ws_json, spawn_test_app, TestApp, WsStream};
+
+async fn step(ws: &mut WsStream, dx: i32, dy: i32) {
+    send_ws_json(ws, serde_json::json!({ "type": "move", "dx": dx, "dy": dy })).await;
+}
+
+async fn stored_position(app: &TestApp, character_name: &str) -> Option<(String, i32, i32)> {
+    let character_id = app.character_id(character_name).await;
+    sqlx::query!("SELECT zone_id, x, y FROM character_positions WHERE character_id = $1", character_id)
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap()
+        .map(|r| (r.zone_id, r.x, r.y))
+}
+
+#[tokio::test]
+async fn new_characters_start_at_the_starting_zone() {
+    let app = spawn_test_app().await;
+
+    let (_, _ws, zone) = app.enter_world("Tomas").await;
+
+    assert_eq!("greenvale", zone["zone_id"]);
+    assert_eq!("Greenvale", zone["zone_name"]);
+    assert_eq!(8, zone["x"]);
+    assert_eq!(6, zone["y"]);
+    assert_eq!(10, zone["tiles"].as_array().unwrap().len());
+}
+
+#[tokio::test]
+async fn valid_steps_are_applied_and_saved_on_leaving() {
+    let app = spawn_test_app().await;
+    let (user_id, mut ws, _) = app.enter_world("Tomas").await;
+
+    step(&mut ws, 1, -1).await;
+
+    let moved = next_ws_json(&mut ws).await;
+    assert_eq!("moved", moved["type"]);
+    assert_eq!(9, moved["x"]);
+    assert_eq!(5, moved["y"]);
+    assert_eq!(Some(Position::new("greenvale", 9, 5)), app.world.position(user_id));
+
+    drop(ws);
+    for _ in 0..50 {
+        if stored_position(&app, "Tomas").await == Some(("greenvale".to_string(), 9, 5)) {
+            assert_eq!(None, app.world.position(user_id));
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("Position was not saved after leaving");
+}
+
+#[tokio::test]
+async fn characters_come_back_where_they_left() {
+    let app = spawn_test_app().await;
+
+    let (_, _ws, zone) = app.enter_world_at("Tomas", Some(("old_forest", 4, 5))).await;
+
+    assert_eq!("old_forest", zone["zone_id"]);
+    assert_eq!(4, zone["x"]);
+    assert_eq!(5, zone["y"]);
+}
+
+#[tokio::test]
+async fn positions_that_no_longer_exist_are_reset_to_the_start() {
+    let app = spawn_test_app().await;
+
+    let (_, _ws, zone) = app.enter_world_at("Tomas", Some(("greenvale", 0, 0))).await;
+
+    assert_eq!("greenvale", zone["zone_id"]);
+    assert_eq!(8, zone["x"]);
+    assert_eq!(6, zone["y"]);
+}
+
+#[tokio::test]
+async fn walls_and_long_steps_are_rejected() {
+    let app = spawn_test_app().await;
+    let (user_id, mut ws, _) = app.enter_world_at("Tomas", Some(("greenvale", 1, 1))).await;
+
+    step(&mut ws, 0, -1).await;
+    assert_eq!("invalid_move", next_ws_json(&mut ws).await["code"]);
+    step(&mut ws, 2, 0).await;
+    assert_eq!("invalid_move", next_ws_json(&mut ws).await["code"]);
+
+    assert_eq!(Some(Position::new("greenvale", 1, 1)), app.world.position(user_id));
+}
+
+#[tokio::test]
+async fn moving_faster_than_allowed_is_refused() {
+    let app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+
+    for _ in 0..3 {
+        step(&mut ws, 1, 0).await;
+        step(&mut ws, -1, 0).await;
+    }
+
+    let mut refused = 0;
+    for _ in 0..6 {
+        if next_ws_json(&mut ws).await["code"] == "rate_limited" {
+            refused += 1;
+        }
+    }
+    assert!(refused >= 2, "only {} steps were refused", refused);
+}
+
+#[tokio::test]
+async fn stepping_onto_an_exit_changes_zone() {
+    let app = spawn_test_app().await;
+    let (user_id, mut ws, _) = app.enter_world_at("Tomas", Some(("greenvale", 14, 4))).await;
+
+    step(&mut ws, 1, 0).await;
+
+    let zone = next_ws_json(&mut ws).await;
+    assert_eq!("zone_entered", zone["type"]);
+    assert_eq!("old_forest", zone["zone_id"]);
+    assert_eq!(1, zone["x"]);
+    assert_eq!(2, zone["y"]);
+    assert_eq!(Some(Position::new("old_forest", 1, 2)), app.world.position(user_id));
+}
+
+#[tokio::test]
+async fn players_only_hear_about_others_in_range() {
+    let app = spawn_test_app().await;
+    let (_, mut near_ws, _) = app.enter_world_at("Anna", Some(("greenvale", 1, 6))).await;
+    let (_, mut far_ws, zone) = app.enter_world_at("Bruno", Some(("greenvale", 10, 6))).await;
+    assert_eq!(0, zone["entities"].as_array().unwrap().len());
+
+    // one step closer puts them exactly at the edge of each other's view
+    step(&mut far_ws, -1, 0).await;
+
+    let appeared = next_ws_json(&mut near_ws).await;
+    assert_eq!("entity_appeared", appeared["type"]);
+    assert_eq!("Bruno", appeared["entity"]["name"]);
+    assert_eq!(9, appeared["entity"]["x"]);
+    let seen = next_ws_json(&mut far_ws).await;
+    assert_eq!("entity_appeared", seen["type"]);
+    assert_eq!("Anna", seen["entity"]["name"]);
+    assert_eq!("moved", next_ws_json(&mut far_ws).await["type"]);
+
+    step(&mut far_ws, 1, 0).await;
+
+    let left = next_ws_json(&mut near_ws).await;
+    assert_eq!("entity_left", left["type"]);
+    assert_eq!(appeared["entity"]["id"], left["id"]);
+}
+
+#[tokio::test]
+async fn players_entering_nearby_see_each_other() {
+    let app = spawn_test_app().await;
+    let (_, mut first_ws, _) = app.enter_world("Anna").await;
+
+    let (_, _second_ws, zone) = app.enter_world("Bruno").await;
+
+    let entities = zone["entities"].as_array().unwrap();
+    assert_eq!(1, entities.len());
+    assert_eq!("Anna", entities[0]["name"]);
+    let appeared = next_ws_json(&mut first_ws).await;
+    assert_eq!("entity_appeared", appeared["type"]);
+    assert_eq!("Bruno", appeared["entity"]["name"]);
+}
+
+#[tokio::test]
+async fn zone_chat_follows_the_character() {
+    let app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+    app.post_profile(&serde_json::json!({ "display_name": "Tomas", "avatar": "knight", "bio": "", "locale": "en" })).await;
+
+    send_ws_json(&mut ws, serde_json::json!({ "type": "chat_send", "channel": "zone", "body": "hello valley" })).await;
+
+    assert_eq!("hello valley", next_ws_json(&mut ws).await["body"]);
+}
+
+#[tokio::test]
+async fn the_game_loop_saves_positions_while_playing() {
+    let mut app = spawn_test_app().await;
+    let (_, mut ws, _) = app.enter_world("Tomas").await;
+    step(&mut ws, 1, 0).await;
+    assert_eq!("moved", next_ws_json(&mut ws).await["type"]);
+    assert_eq!(None, stored_position(&app, "Tomas").await);
+
+    let ticks_per_flush = 5 * 10;
+    for _ in 0..=ticks_per_flush {
+        app.game_loop.tick().await;
+    }
+
+    assert_eq!(Some(("greenvale".to_string(), 9, 6)), stored_position(&app, "Tomas").await);
+}
+
+#[tokio::test]
+async fn connections_without_a_character_cannot_move() {
+    let app = spawn_test_app().await;
+    app.register_and_login().await;
+    let mut ws = app.connect_ws().await.unwrap();
+    next_ws_json(&mut ws).await;
+
+    step(&mut ws, 1, 0).await;
+
+    assert_eq!("forbidden", next_ws_json(&mut ws).await["code"]);
+}